@@ -0,0 +1,200 @@
+//! Export / import of Setup Center's own settings.
+//!
+//! The desktop app's preferences live in `state.json` next to per-machine
+//! data (workspace list, install bookkeeping, PIDs), plus a couple of UI
+//! preferences (language, theme) in the webview's localStorage.
+//! `export_app_settings` writes the portable part into one versioned JSON
+//! file; `import_app_settings` applies such a file on another machine
+//! through the regular setters, so in-memory switches (bridge trace,
+//! download limit) follow immediately.
+//!
+//! Left out on purpose: workspaces and paths (machine specific), the GitHub
+//! token (a secret), and the orphan-kill PID exclusions.  UI preferences are
+//! passed in by the frontend on export and handed back on import for it to
+//! apply.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::errors::CmdResult;
+use crate::{
+    atomic_write, log_to_file, mirrors, net, now_ms, ollama, read_state_file, retention,
+    set_auto_start_backend, set_auto_update, shutdown, skills, spend, trace, AppStateFile,
+};
+
+pub(crate) const FORMAT: &str = "openakita-app-settings";
+pub(crate) const FORMAT_VERSION: u32 = 1;
+
+/// The exported preferences; every field is optional so a file from an
+/// older app (or a hand-trimmed one) only touches what it names.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_start_backend: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_update: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offline_mode: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bridge_trace: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirrors: Option<mirrors::MirrorConfig>,
+    /// 0 = unlimited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_rate_limit_kbps: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub require_signed_skills: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ollama_auto_start: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention: Option<retention::RetentionConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spend: Option<spend::SpendConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shutdown: Option<shutdown::ShutdownConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppSettingsFile {
+    pub format: String,
+    pub version: u32,
+    #[serde(default)]
+    pub exported_at_ms: u64,
+    #[serde(default)]
+    pub app_version: String,
+    pub settings: AppSettings,
+    /// Frontend preferences (language, theme, ...), opaque to the backend.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ui: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppSettingsImportResult {
+    /// Settings written, by their camelCase name.
+    pub applied: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ui: Option<serde_json::Value>,
+}
+
+pub(crate) fn settings_from_state(state: &AppStateFile) -> AppSettings {
+    AppSettings {
+        auto_start_backend: state.auto_start_backend,
+        auto_update: state.auto_update,
+        offline_mode: state.offline_mode,
+        bridge_trace: state.bridge_trace,
+        mirrors: state.mirrors.clone(),
+        download_rate_limit_kbps: Some(state.download_rate_limit_kbps.unwrap_or(0)),
+        require_signed_skills: state.require_signed_skills,
+        ollama_auto_start: state.ollama_auto_start,
+        retention: state.retention.clone(),
+        spend: state.spend.clone(),
+        shutdown: state.shutdown.clone(),
+    }
+}
+
+/// Parse and check an exported file.
+pub(crate) fn parse_settings_file(content: &str) -> Result<AppSettingsFile, String> {
+    let file: AppSettingsFile = serde_json::from_str(content.trim_start_matches('\u{feff}'))
+        .map_err(|e| format!("INVALID_ARGUMENT|不是有效的设置文件: {e}"))?;
+    if file.format != FORMAT {
+        return Err(format!(
+            "INVALID_ARGUMENT|不是 OpenAkita 设置文件 (format={})",
+            file.format
+        ));
+    }
+    if file.version > FORMAT_VERSION {
+        return Err(format!(
+            "INVALID_ARGUMENT|设置文件来自更新版本的桌面端 (v{})，请先升级",
+            file.version
+        ));
+    }
+    Ok(file)
+}
+
+/// Write the current settings (plus the frontend's `ui` preferences) to
+/// `path`.  Returns the path written.
+#[tauri::command]
+pub fn export_app_settings(path: String, ui: Option<serde_json::Value>) -> CmdResult<String> {
+    let path = PathBuf::from(path.trim());
+    if !path.is_absolute() {
+        return Err(format!("INVALID_ARGUMENT|需要绝对路径: {}", path.display()).into());
+    }
+    let file = AppSettingsFile {
+        format: FORMAT.to_string(),
+        version: FORMAT_VERSION,
+        exported_at_ms: now_ms(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        settings: settings_from_state(&read_state_file()),
+        ui,
+    };
+    let json = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+    atomic_write(&path, json).map_err(|e| format!("写入设置文件失败: {e}"))?;
+    log_to_file(&format!("[app_settings] exported to {}", path.display()));
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Apply a file written by `export_app_settings`.
+#[tauri::command]
+pub fn import_app_settings(path: String) -> CmdResult<AppSettingsImportResult> {
+    let content = std::fs::read_to_string(path.trim())
+        .map_err(|e| format!("NOT_FOUND|读取设置文件失败: {e}"))?;
+    let file = parse_settings_file(&content)?;
+    let s = file.settings;
+    let mut applied = Vec::new();
+    // 镜像先写：它会校验 URL，不合法时其它设置都还没动
+    if let Some(m) = s.mirrors {
+        mirrors::set_mirrors(m)?;
+        applied.push("mirrors");
+    }
+    if let Some(v) = s.auto_start_backend {
+        set_auto_start_backend(v)?;
+        applied.push("autoStartBackend");
+    }
+    if let Some(v) = s.auto_update {
+        set_auto_update(v)?;
+        applied.push("autoUpdate");
+    }
+    if let Some(v) = s.offline_mode {
+        net::set_offline_mode(v)?;
+        applied.push("offlineMode");
+    }
+    if let Some(v) = s.bridge_trace {
+        trace::set_bridge_trace(v)?;
+        applied.push("bridgeTrace");
+    }
+    if let Some(v) = s.download_rate_limit_kbps {
+        net::set_download_rate_limit(v)?;
+        applied.push("downloadRateLimitKbps");
+    }
+    if let Some(v) = s.require_signed_skills {
+        skills::set_require_signed_skills(v)?;
+        applied.push("requireSignedSkills");
+    }
+    if let Some(v) = s.ollama_auto_start {
+        ollama::set_ollama_auto_start(v)?;
+        applied.push("ollamaAutoStart");
+    }
+    if let Some(v) = s.retention {
+        retention::set_retention_config(v)?;
+        applied.push("retention");
+    }
+    if let Some(v) = s.spend {
+        spend::set_spend_config(v)?;
+        applied.push("spend");
+    }
+    if let Some(v) = s.shutdown {
+        shutdown::set_shutdown_config(v)?;
+        applied.push("shutdown");
+    }
+    log_to_file(&format!(
+        "[app_settings] imported {applied:?} (exported by {} at {})",
+        file.app_version, file.exported_at_ms
+    ));
+    Ok(AppSettingsImportResult {
+        applied: applied.into_iter().map(String::from).collect(),
+        ui: file.ui,
+    })
+}
@@ -0,0 +1,361 @@
+//! Safe zip / tar.gz extraction shared by skill installs, skill rollback and
+//! workspace backup import.
+//!
+//! Every entry path must consist of plain components (no absolute paths,
+//! drive prefixes or `..`), and no entry may be written through a symlink
+//! that an earlier entry created, so a crafted archive can't place files
+//! outside `dest` ("zip slip").  Symlink entries are recreated only when
+//! their target stays inside `dest`, otherwise extraction fails; on Windows,
+//! where creating symlinks usually needs Developer Mode, they are skipped
+//! with a log line.  Unix permission bits are kept, so executables in an
+//! archive (e.g. a bundled interpreter) stay executable.  Long paths on
+//! Windows get the `\\?\` prefix.  Callers can cap the unpacked size, skip
+//! entries and receive progress callbacks.
+
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use crate::log_to_file;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct ExtractProgress {
+    pub(crate) files: u64,
+    pub(crate) bytes: u64,
+    /// Sum of the uncompressed sizes when the format records it (zip).
+    pub(crate) total_bytes: Option<u64>,
+}
+
+#[derive(Default)]
+pub(crate) struct ExtractOptions<'a> {
+    /// Refuse archives that unpack to more than this many bytes.
+    pub(crate) max_unpacked_bytes: Option<u64>,
+    /// Entries (relative path) for which this returns true are not written.
+    pub(crate) skip: Option<&'a dyn Fn(&Path) -> bool>,
+    pub(crate) on_progress: Option<&'a mut dyn FnMut(ExtractProgress)>,
+}
+
+enum EntryKind {
+    Dir,
+    File,
+    Symlink(PathBuf),
+    Hardlink(PathBuf),
+    Other,
+}
+
+/// Validate an archive entry name and turn it into a relative path.
+/// `None` for names that are absolute, contain `..` or a drive prefix.
+pub(crate) fn sanitize_entry_path(name: &Path) -> Option<PathBuf> {
+    let mut out = PathBuf::new();
+    for comp in name.components() {
+        match comp {
+            Component::Normal(part) => out.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    (!out.as_os_str().is_empty()).then_some(out)
+}
+
+/// Where a symlink at `link_rel` pointing to `target` ends up, relative to
+/// the extraction root; `None` if it leaves the root or is absolute.
+/// `is_symlink(rel)` reports links already extracted: a `..` after passing
+/// through one can't be resolved lexically, so it is refused.
+pub(crate) fn resolve_link_target(
+    link_rel: &Path,
+    target: &Path,
+    is_symlink: impl Fn(&Path) -> bool,
+) -> Option<PathBuf> {
+    let mut out: Vec<std::ffi::OsString> = link_rel
+        .parent()
+        .map(|p| p.iter().map(|c| c.to_os_string()).collect())
+        .unwrap_or_default();
+    let mut through_link = false;
+    for comp in target.components() {
+        match comp {
+            Component::Normal(part) => {
+                out.push(part.to_os_string());
+                through_link |= is_symlink(&out.iter().collect::<PathBuf>());
+            }
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if through_link {
+                    return None;
+                }
+                out.pop()?;
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(out.iter().collect())
+}
+
+#[cfg(windows)]
+fn long_path(p: PathBuf) -> PathBuf {
+    let s = p.to_string_lossy();
+    if s.len() < 240 || s.starts_with(r"\\?\") || !p.is_absolute() {
+        return p;
+    }
+    let s = s.replace('/', "\\");
+    match s.strip_prefix(r"\\") {
+        Some(unc) => PathBuf::from(format!(r"\\?\UNC\{unc}")),
+        None => PathBuf::from(format!(r"\\?\{s}")),
+    }
+}
+
+#[cfg(not(windows))]
+fn long_path(p: PathBuf) -> PathBuf {
+    p
+}
+
+/// Refuse to write through a symlink an earlier entry created.
+fn check_no_symlink_parents(dest: &Path, rel: &Path) -> Result<(), String> {
+    let mut cur = dest.to_path_buf();
+    if let Some(parent) = rel.parent() {
+        for comp in parent.components() {
+            cur.push(comp);
+            if fs::symlink_metadata(&cur).is_ok_and(|m| m.file_type().is_symlink()) {
+                return Err(format!(
+                    "archive entry {} is inside a symlink",
+                    rel.display()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+struct Extractor<'a, 'b> {
+    dest: PathBuf,
+    opts: &'b mut ExtractOptions<'a>,
+    progress: ExtractProgress,
+}
+
+impl Extractor<'_, '_> {
+    fn entry(
+        &mut self,
+        name: &Path,
+        kind: EntryKind,
+        mode: Option<u32>,
+        size: u64,
+        reader: &mut dyn Read,
+    ) -> Result<(), String> {
+        let rel = sanitize_entry_path(name)
+            .ok_or_else(|| format!("archive contains an unsafe path: {}", name.display()))?;
+        if self.opts.skip.is_some_and(|skip| skip(&rel)) {
+            return Ok(());
+        }
+        check_no_symlink_parents(&self.dest, &rel)?;
+        let target = long_path(self.dest.join(&rel));
+        match kind {
+            EntryKind::Dir => {
+                fs::create_dir_all(&target).map_err(|e| format!("create dir: {e}"))?;
+                return Ok(());
+            }
+            EntryKind::Other => return Ok(()),
+            _ => {}
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("create dir: {e}"))?;
+        }
+        match kind {
+            EntryKind::File => {
+                self.progress.bytes += size;
+                if let Some(max) = self.opts.max_unpacked_bytes {
+                    if self.progress.bytes > max {
+                        return Err(format!(
+                            "archive unpacks to more than {} MB",
+                            max / 1024 / 1024
+                        ));
+                    }
+                }
+                let mut out = fs::File::create(&target).map_err(|e| format!("write file: {e}"))?;
+                // 不信任头部记录的大小，多读一个字节检查
+                let copied = std::io::copy(&mut reader.take(size + 1), &mut out)
+                    .map_err(|e| format!("extract file: {e}"))?;
+                if copied > size {
+                    return Err(format!(
+                        "archive entry {} is larger than declared",
+                        rel.display()
+                    ));
+                }
+                set_mode(&target, mode);
+            }
+            EntryKind::Symlink(link) => {
+                let dest = &self.dest;
+                let is_link = |p: &Path| {
+                    fs::symlink_metadata(dest.join(p)).is_ok_and(|m| m.file_type().is_symlink())
+                };
+                if resolve_link_target(&rel, &link, is_link).is_none() {
+                    return Err(format!(
+                        "archive symlink {} points outside the extraction dir: {}",
+                        rel.display(),
+                        link.display()
+                    ));
+                }
+                create_symlink(&link, &target, &rel);
+            }
+            EntryKind::Hardlink(link) => {
+                let src = sanitize_entry_path(&link).ok_or_else(|| {
+                    format!("archive hardlink {} has an unsafe target", rel.display())
+                })?;
+                check_no_symlink_parents(&self.dest, &src)?;
+                fs::hard_link(long_path(self.dest.join(&src)), &target)
+                    .or_else(|_| fs::copy(self.dest.join(&src), &target).map(|_| ()))
+                    .map_err(|e| format!("link {}: {e}", rel.display()))?;
+            }
+            EntryKind::Dir | EntryKind::Other => {}
+        }
+        self.progress.files += 1;
+        if let Some(cb) = self.opts.on_progress.as_mut() {
+            cb(self.progress);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: Option<u32>) {
+    use std::os::unix::fs::PermissionsExt as _;
+    if let Some(mode) = mode {
+        // 去掉 setuid / setgid / sticky，只保留权限位
+        let _ = fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777));
+    }
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: Option<u32>) {}
+
+#[cfg(unix)]
+fn create_symlink(link: &Path, at: &Path, _rel: &Path) {
+    let _ = fs::remove_file(at);
+    if let Err(e) = std::os::unix::fs::symlink(link, at) {
+        log_to_file(&format!("[archive] symlink {} failed: {e}", at.display()));
+    }
+}
+
+#[cfg(not(unix))]
+fn create_symlink(link: &Path, at: &Path, rel: &Path) {
+    let resolved = at.parent().map(|p| p.join(link)).filter(|p| p.is_dir());
+    let result = if resolved.is_some() {
+        std::os::windows::fs::symlink_dir(link, at)
+    } else {
+        std::os::windows::fs::symlink_file(link, at)
+    };
+    if let Err(e) = result {
+        log_to_file(&format!(
+            "[archive] skipped symlink {} -> {}: {e}",
+            rel.display(),
+            link.display()
+        ));
+    }
+}
+
+/// Unpack a zip archive into `dest`.
+pub(crate) fn extract_zip(
+    archive_path: &Path,
+    dest: &Path,
+    opts: &mut ExtractOptions,
+) -> Result<ExtractProgress, String> {
+    let file = fs::File::open(archive_path).map_err(|e| format!("open zip: {e}"))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("read zip: {e}"))?;
+    fs::create_dir_all(dest).map_err(|e| format!("create dir: {e}"))?;
+    let mut total = 0u64;
+    for i in 0..archive.len() {
+        if let Ok(entry) = archive.by_index_raw(i) {
+            total = total.saturating_add(entry.size());
+        }
+    }
+    let mut ex = Extractor {
+        dest: dest.to_path_buf(),
+        opts,
+        progress: ExtractProgress {
+            total_bytes: Some(total),
+            ..Default::default()
+        },
+    };
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| format!("zip entry: {e}"))?;
+        let name = PathBuf::from(entry.name());
+        let mode = entry.unix_mode();
+        let is_link = mode.is_some_and(|m| m & 0o170000 == 0o120000);
+        let kind = if entry.is_dir() {
+            EntryKind::Dir
+        } else if is_link {
+            let mut link = String::new();
+            entry
+                .read_to_string(&mut link)
+                .map_err(|e| format!("zip symlink: {e}"))?;
+            EntryKind::Symlink(PathBuf::from(link))
+        } else {
+            EntryKind::File
+        };
+        let size = entry.size();
+        ex.entry(&name, kind, mode, size, &mut entry)?;
+    }
+    Ok(ex.progress)
+}
+
+/// Unpack a `.tar.gz` archive into `dest`.
+pub(crate) fn extract_tar_gz(
+    archive_path: &Path,
+    dest: &Path,
+    opts: &mut ExtractOptions,
+) -> Result<ExtractProgress, String> {
+    let file = fs::File::open(archive_path).map_err(|e| format!("open tar.gz: {e}"))?;
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    fs::create_dir_all(dest).map_err(|e| format!("create dir: {e}"))?;
+    let mut ex = Extractor {
+        dest: dest.to_path_buf(),
+        opts,
+        progress: ExtractProgress::default(),
+    };
+    for entry in archive.entries().map_err(|e| format!("read tar.gz: {e}"))? {
+        let mut entry = entry.map_err(|e| format!("tar entry: {e}"))?;
+        let name = entry
+            .path()
+            .map_err(|e| format!("tar entry path: {e}"))?
+            .into_owned();
+        let header = entry.header();
+        let mode = header.mode().ok();
+        let size = header.size().unwrap_or(0);
+        let link = || {
+            entry
+                .link_name()
+                .ok()
+                .flatten()
+                .map(|l| l.into_owned())
+                .ok_or_else(|| format!("tar link {} has no target", name.display()))
+        };
+        let kind = match header.entry_type() {
+            tar::EntryType::Directory => EntryKind::Dir,
+            tar::EntryType::Regular | tar::EntryType::Continuous => EntryKind::File,
+            tar::EntryType::Symlink => EntryKind::Symlink(link()?),
+            tar::EntryType::Link => EntryKind::Hardlink(link()?),
+            // pax / GNU 扩展头由 tar crate 处理；设备文件、FIFO 等不解压
+            _ => EntryKind::Other,
+        };
+        ex.entry(&name, kind, mode, size, &mut entry)?;
+    }
+    Ok(ex.progress)
+}
+
+/// Pick the extractor from the file's magic bytes (zip or gzip).
+pub(crate) fn extract_archive(
+    archive_path: &Path,
+    dest: &Path,
+    opts: &mut ExtractOptions,
+) -> Result<ExtractProgress, String> {
+    let mut magic = [0u8; 4];
+    let n = fs::File::open(archive_path)
+        .and_then(|mut f| f.read(&mut magic))
+        .map_err(|e| format!("open archive: {e}"))?;
+    match &magic[..n.min(4)] {
+        [0x1f, 0x8b, ..] => extract_tar_gz(archive_path, dest, opts),
+        [b'P', b'K', ..] => extract_zip(archive_path, dest, opts),
+        _ => Err(format!(
+            "unsupported archive format (zip / tar.gz): {}",
+            archive_path.display()
+        )),
+    }
+}
@@ -0,0 +1,442 @@
+//! The backend's HTTP API, discovered from its OpenAPI spec.
+//!
+//! Frontend code used to build `http://127.0.0.1:<port>/api/...` URLs
+//! itself, guessing the port and hardcoding routes that move between
+//! backend versions.  `get_backend_api_info(workspace_id)` fetches
+//! `/openapi.json` from the running backend and reduces it to an index of
+//! operations (id, method, path, parameters); the index is cached in memory
+//! and under `~/.openakita/cache/openapi/`, keyed by the backend's version
+//! and git hash from `/api/health`, so it is fetched once per release.
+//!
+//! `call_backend(workspace_id, operation_id, params)` then calls one of
+//! those operations through the local control channel (`backend_ipc`):
+//! path parameters are substituted and encoded, query parameters appended,
+//! `body` sent as JSON, an optional bearer `token` attached (loopback
+//! requests are exempt from the backend's web password, remote ones are
+//! not).  For an observer workspace (`observer.rs`) both go to the remote
+//! backend it watches, with its stored token, and only `GET` operations
+//! are allowed.  HTTP failures are mapped to error codes:
+//!
+//! | status | code |
+//! |---|---|
+//! | 400, 422 | `INVALID_ARGUMENT` |
+//! | 401, 403 | `BACKEND_UNAUTHORIZED` |
+//! | 404 | `NOT_FOUND` |
+//! | 409 | `ALREADY_EXISTS` |
+//! | 408, 504, client timeout | `BACKEND_TIMEOUT` |
+//! | 503, connection refused | `BACKEND_NOT_RUNNING` |
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::errors::CmdResult;
+use crate::{
+    atomic_write, backend_ipc, is_pid_file_valid, log_to_file, net, now_epoch_secs, observer,
+    openakita_root_dir, read_pid_file, read_workspace_api_port,
+};
+
+const SPEC_TIMEOUT: Duration = Duration::from_secs(30);
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_CALL_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_CALL_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiParam {
+    pub name: String,
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiOperation {
+    pub operation_id: String,
+    /// Upper case, e.g. `GET`.
+    pub method: String,
+    /// Template with `{name}` placeholders, e.g. `/api/sessions/{session_id}`.
+    pub path: String,
+    #[serde(default)]
+    pub summary: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub path_params: Vec<String>,
+    #[serde(default)]
+    pub query_params: Vec<ApiParam>,
+    #[serde(default)]
+    pub has_body: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiInfo {
+    pub version: String,
+    pub git_hash: String,
+    pub port: u16,
+    pub fetched_at: u64,
+    pub operations: Vec<ApiOperation>,
+}
+
+/// Arguments of [`call_backend`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CallParams {
+    pub path: Map<String, Value>,
+    pub query: Map<String, Value>,
+    pub body: Option<Value>,
+    pub timeout_ms: Option<u64>,
+    /// Bearer token for backends that require the web password.
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallResult {
+    pub status: u16,
+    /// Response JSON, or the text when it is not JSON.
+    pub data: Value,
+}
+
+/// version key -> index.
+static SPECS: Lazy<Mutex<HashMap<String, ApiInfo>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+/// workspace -> (backend pid, version key) of the last lookup, so calls to
+/// the same process skip the health probe.
+static WORKSPACES: Lazy<Mutex<HashMap<String, (u32, String)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn param_names(params: &[Value], location: &str) -> Vec<ApiParam> {
+    params
+        .iter()
+        .filter(|p| p.get("in").and_then(Value::as_str) == Some(location))
+        .filter_map(|p| {
+            Some(ApiParam {
+                name: p.get("name")?.as_str()?.to_string(),
+                required: p.get("required").and_then(Value::as_bool).unwrap_or(false),
+            })
+        })
+        .collect()
+}
+
+/// Operations of an OpenAPI 3 document; ones without `operationId` are
+/// skipped.  Sorted by id.
+pub(crate) fn parse_operations(spec: &Value) -> Vec<ApiOperation> {
+    let mut out = Vec::new();
+    let Some(paths) = spec.get("paths").and_then(Value::as_object) else {
+        return out;
+    };
+    for (path, item) in paths {
+        let shared = item
+            .get("parameters")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        for method in METHODS {
+            let Some(op) = item.get(method) else { continue };
+            let Some(id) = op.get("operationId").and_then(Value::as_str) else {
+                continue;
+            };
+            let mut params = shared.clone();
+            if let Some(own) = op.get("parameters").and_then(Value::as_array) {
+                params.extend(own.iter().cloned());
+            }
+            out.push(ApiOperation {
+                operation_id: id.to_string(),
+                method: method.to_uppercase(),
+                path: path.clone(),
+                summary: op
+                    .get("summary")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+                tags: op
+                    .get("tags")
+                    .and_then(Value::as_array)
+                    .map(|t| {
+                        t.iter()
+                            .filter_map(Value::as_str)
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                path_params: param_names(&params, "path")
+                    .into_iter()
+                    .map(|p| p.name)
+                    .collect(),
+                query_params: param_names(&params, "query"),
+                has_body: op.get("requestBody").is_some(),
+            });
+        }
+    }
+    out.sort_by(|a, b| a.operation_id.cmp(&b.operation_id));
+    out
+}
+
+/// Percent-encode everything but RFC 3986 unreserved characters.
+fn encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+fn scalar(v: &Value) -> Option<String> {
+    match v {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// Request path (with query string) for `op` and `params`.
+pub(crate) fn build_path(op: &ApiOperation, params: &CallParams) -> Result<String, String> {
+    let mut path = op.path.clone();
+    for name in &op.path_params {
+        let value =
+            params.path.get(name).and_then(scalar).ok_or_else(|| {
+                format!("INVALID_ARGUMENT|{} 缺少路径参数 {name}", op.operation_id)
+            })?;
+        path = path.replace(&format!("{{{name}}}"), &encode(&value));
+    }
+    for p in op.query_params.iter().filter(|p| p.required) {
+        if params.query.get(&p.name).and_then(scalar).is_none() {
+            return Err(format!(
+                "INVALID_ARGUMENT|{} 缺少查询参数 {}",
+                op.operation_id, p.name
+            ));
+        }
+    }
+    let mut pairs = Vec::new();
+    for (k, v) in &params.query {
+        let values = match v {
+            Value::Array(items) => items.iter().filter_map(scalar).collect(),
+            other => scalar(other).into_iter().collect::<Vec<_>>(),
+        };
+        pairs.extend(
+            values
+                .into_iter()
+                .map(|v| format!("{}={}", encode(k), encode(&v))),
+        );
+    }
+    if !pairs.is_empty() {
+        path.push('?');
+        path.push_str(&pairs.join("&"));
+    }
+    Ok(path)
+}
+
+/// Error code for a failed HTTP status.
+pub(crate) fn status_code(status: u16) -> &'static str {
+    match status {
+        400 | 422 => "INVALID_ARGUMENT",
+        401 | 403 => "BACKEND_UNAUTHORIZED",
+        404 => "NOT_FOUND",
+        409 => "ALREADY_EXISTS",
+        408 | 504 => "BACKEND_TIMEOUT",
+        503 => "BACKEND_NOT_RUNNING",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Human part of a FastAPI error body (`detail` may be a string or a list
+/// of validation errors).
+pub(crate) fn error_message(status: u16, body: &str) -> String {
+    let value: Value = serde_json::from_str(body).unwrap_or(Value::Null);
+    let detail = value.get("detail").or_else(|| value.get("error"));
+    let msg = match detail {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|i| i.get("msg").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("; "),
+        _ => body.chars().take(500).collect(),
+    };
+    format!("HTTP {status}: {msg}")
+}
+
+fn transport_error(e: String) -> String {
+    let lower = e.to_lowercase();
+    if lower.contains("timed out") || lower.contains("timeout") {
+        format!("BACKEND_TIMEOUT|{e}")
+    } else {
+        format!("BACKEND_NOT_RUNNING|{e}")
+    }
+}
+
+fn cache_path(key: &str) -> PathBuf {
+    openakita_root_dir()
+        .join("cache")
+        .join("openapi")
+        .join(format!("{}.json", net::cache_key_hash(key)))
+}
+
+fn running_port(workspace_id: &str) -> Result<(u32, u16), String> {
+    // 观察者没有本地进程，pid 记 0（每次都重新探测版本）
+    if let Some(target) = observer::target_of(workspace_id) {
+        return Ok((0, target.port));
+    }
+    let pid = read_pid_file(workspace_id)
+        .filter(is_pid_file_valid)
+        .map(|d| d.pid)
+        .ok_or_else(|| format!("BACKEND_NOT_RUNNING|工作区 {workspace_id} 的后端未运行"))?;
+    Ok((pid, read_workspace_api_port(workspace_id).unwrap_or(18900)))
+}
+
+async fn get_json(workspace_id: &str, path: &str, timeout: Duration) -> Result<Value, String> {
+    let resp = backend_ipc::workspace_request(workspace_id, "GET", path, &[], None, timeout)
+        .await
+        .map_err(transport_error)?;
+    if !resp.is_success() {
+        return Err(format!(
+            "{}|{}",
+            status_code(resp.status),
+            error_message(resp.status, &resp.text())
+        ));
+    }
+    resp.json()
+}
+
+/// Operation index of `workspace_id`'s running backend.
+async fn load(workspace_id: &str, force_refresh: bool) -> Result<ApiInfo, String> {
+    let (pid, port) = running_port(workspace_id)?;
+    let known = WORKSPACES.lock().unwrap().get(workspace_id).cloned();
+    if let Some((_, key)) = known.filter(|(p, _)| *p == pid && pid != 0 && !force_refresh) {
+        if let Some(info) = SPECS.lock().unwrap().get(&key) {
+            return Ok(ApiInfo {
+                port,
+                ..info.clone()
+            });
+        }
+    }
+    let health = get_json(workspace_id, "/api/health", HEALTH_TIMEOUT).await?;
+    let field = |k: &str| {
+        health
+            .get(k)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    let (version, git_hash) = (field("version"), field("git_hash"));
+    let key = format!("{version}|{git_hash}");
+    // 版本号与 git hash 都拿不到时无法判断 spec 是否变化，不落盘
+    let persistent = !version.is_empty() || !git_hash.is_empty();
+    let cached = if force_refresh {
+        None
+    } else {
+        SPECS.lock().unwrap().get(&key).cloned().or_else(|| {
+            let data = std::fs::read(cache_path(&key)).ok()?;
+            serde_json::from_slice::<ApiInfo>(&data)
+                .ok()
+                .filter(|i| persistent && i.version == version && i.git_hash == git_hash)
+        })
+    };
+    let info = match cached {
+        Some(info) => ApiInfo { port, ..info },
+        None => {
+            let spec = get_json(workspace_id, "/openapi.json", SPEC_TIMEOUT).await?;
+            let fetched = ApiInfo {
+                version: version.clone(),
+                git_hash: git_hash.clone(),
+                port,
+                fetched_at: now_epoch_secs(),
+                operations: parse_operations(&spec),
+            };
+            log_to_file(&format!(
+                "[backend-api] {workspace_id}: {} operations (openakita {version} {git_hash})",
+                fetched.operations.len()
+            ));
+            if persistent {
+                if let Ok(data) = serde_json::to_vec(&fetched) {
+                    let _ = atomic_write(cache_path(&key), data);
+                }
+            }
+            fetched
+        }
+    };
+    SPECS.lock().unwrap().insert(key.clone(), info.clone());
+    WORKSPACES
+        .lock()
+        .unwrap()
+        .insert(workspace_id.to_string(), (pid, key));
+    Ok(info)
+}
+
+/// Operations the workspace's running backend offers.
+#[tauri::command]
+pub async fn get_backend_api_info(
+    workspace_id: String,
+    force_refresh: Option<bool>,
+) -> CmdResult<ApiInfo> {
+    load(&workspace_id, force_refresh.unwrap_or(false))
+        .await
+        .map_err(Into::into)
+}
+
+/// Call operation `operation_id` of the workspace's running backend.
+#[tauri::command]
+pub async fn call_backend(
+    workspace_id: String,
+    operation_id: String,
+    params: Option<CallParams>,
+) -> CmdResult<CallResult> {
+    let params = params.unwrap_or_default();
+    let info = load(&workspace_id, false).await?;
+    let op = info
+        .operations
+        .iter()
+        .find(|o| o.operation_id == operation_id)
+        .ok_or_else(|| {
+            format!(
+                "NOT_FOUND|openakita {} 没有接口 {operation_id}",
+                info.version
+            )
+        })?;
+    let path = build_path(op, &params)?;
+    let mut headers = Vec::new();
+    let body = params.body.as_ref().map(Value::to_string);
+    if body.is_some() {
+        headers.push(("Content-Type".to_string(), "application/json".to_string()));
+    }
+    if let Some(token) = params.token.as_deref().filter(|t| !t.is_empty()) {
+        headers.push(("Authorization".to_string(), format!("Bearer {token}")));
+    }
+    let timeout = params
+        .timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_CALL_TIMEOUT)
+        .clamp(Duration::from_secs(1), MAX_CALL_TIMEOUT);
+    let resp = backend_ipc::workspace_request(
+        &workspace_id,
+        &op.method,
+        &path,
+        &headers,
+        body.as_deref().map(str::as_bytes),
+        timeout,
+    )
+    .await
+    .map_err(transport_error)?;
+    let text = resp.text();
+    if !resp.is_success() {
+        return Err(format!(
+            "{}|{operation_id}: {}",
+            status_code(resp.status),
+            error_message(resp.status, &text)
+        )
+        .into());
+    }
+    Ok(CallResult {
+        status: resp.status,
+        data: serde_json::from_str(&text).unwrap_or(Value::String(text)),
+    })
+}
@@ -0,0 +1,584 @@
+//! Local control channel to the Python backend.
+//!
+//! Health probes, `/api/shutdown` and non-streaming proxy calls used to go
+//! through `http://127.0.0.1:<port>`, which breaks in surprising ways: a
+//! system proxy that ignores `NO_PROXY`, another process squatting the port
+//! after a crash, or endpoint security software intercepting loopback
+//! traffic.  On Unix the backend is now spawned with `OPENAKITA_API_UDS`
+//! pointing at `~/.openakita/run/api-<port>.sock` and additionally serves
+//! the same app on that socket; the socket's directory is `0700`, so only
+//! the current user can reach it.
+//!
+//! Callers go through [`backend_request_blocking`] / [`backend_request`],
+//! which speak plain HTTP/1.1 over the socket when it accepts a connection
+//! and fall back to TCP otherwise (Windows, older backends, socket path too long, or
+//! `OPENAKITA_DISABLE_UDS=1`).  uvicorn has no named-pipe listener, so on
+//! Windows the channel is always TCP.
+//!
+//! Streaming calls (`backend_fetch` SSE) and the WebView itself keep using
+//! TCP.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::{log_to_file, net, observer, read_workspace_api_port, run_dir, trace};
+
+/// Env var carrying the socket path into the backend process.
+pub(crate) const BACKEND_UDS_ENV: &str = "OPENAKITA_API_UDS";
+
+/// `sockaddr_un.sun_path` is 104 bytes on macOS / 108 on Linux; stay below
+/// both with room for the trailing NUL.
+const MAX_SOCKET_PATH_LEN: usize = 100;
+
+pub(crate) struct BackendResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl BackendResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn json(&self) -> Result<serde_json::Value, String> {
+        serde_json::from_slice(&self.body).map_err(|e| format!("parse backend JSON failed: {e}"))
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+fn uds_disabled() -> bool {
+    matches!(
+        std::env::var("OPENAKITA_DISABLE_UDS").ok().as_deref(),
+        Some("1") | Some("true") | Some("yes")
+    )
+}
+
+/// Socket path for the backend listening on `port`, or `None` when the
+/// platform / path length does not allow a Unix socket.
+pub(crate) fn backend_socket_path(port: u16) -> Option<PathBuf> {
+    if !cfg!(unix) || uds_disabled() {
+        return None;
+    }
+    let path = run_dir().join(format!("api-{port}.sock"));
+    if path.as_os_str().len() > MAX_SOCKET_PATH_LEN {
+        return None;
+    }
+    Some(path)
+}
+
+/// Prepare the socket directory and remove a stale socket before spawning
+/// the backend.  Returns the path to inject as [`BACKEND_UDS_ENV`].
+pub(crate) fn prepare_backend_socket(port: u16) -> Option<PathBuf> {
+    let path = backend_socket_path(port)?;
+    let dir = path.parent()?;
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        log_to_file(&format!("[ipc] create {} failed: {e}", dir.display()));
+        return None;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700));
+    }
+    let _ = std::fs::remove_file(&path);
+    Some(path)
+}
+
+pub(crate) fn remove_backend_socket(port: u16) {
+    if let Some(path) = backend_socket_path(port) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// RFC 9110 `tchar` punctuation; with ASCII letters and digits this is
+/// what a method or header name may consist of.
+const TCHAR_SYMBOLS: &[u8] = b"!#$%&'*+-.^_`|~";
+
+fn is_http_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || TCHAR_SYMBOLS.contains(&b))
+}
+
+/// Reject anything that would let a caller smuggle extra header lines or a
+/// second request into the raw HTTP/1.1 we write on the socket: the method
+/// and header names must be tokens; the path and header values may not
+/// contain CR, LF, NUL (or, for the path, spaces).
+pub(crate) fn validate_request(
+    method: &str,
+    path: &str,
+    headers: &[(String, String)],
+) -> Result<(), String> {
+    if !is_http_token(method) {
+        return Err(format!("INVALID_ARGUMENT|invalid HTTP method {method:?}"));
+    }
+    if !path.starts_with('/') || path.bytes().any(|b| b.is_ascii_control() || b == b' ') {
+        return Err(format!("INVALID_ARGUMENT|invalid request path {path:?}"));
+    }
+    for (k, v) in headers {
+        if !is_http_token(k) {
+            return Err(format!("INVALID_ARGUMENT|invalid header name {k:?}"));
+        }
+        if v.bytes().any(|b| matches!(b, b'\r' | b'\n' | b'\0')) {
+            return Err(format!(
+                "INVALID_ARGUMENT|header {k} has a control character in its value"
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg_attr(not(unix), allow(dead_code))]
+fn build_http_request(
+    method: &str,
+    path: &str,
+    headers: &[(String, String)],
+    body: Option<&[u8]>,
+) -> Vec<u8> {
+    let mut head = format!(
+        "{method} {path} HTTP/1.1\r\nHost: localhost\r\nUser-Agent: {}\r\nConnection: close\r\n",
+        net::HTTP_USER_AGENT
+    );
+    for (k, v) in headers {
+        if k.eq_ignore_ascii_case("host")
+            || k.eq_ignore_ascii_case("connection")
+            || k.eq_ignore_ascii_case("content-length")
+        {
+            continue;
+        }
+        head.push_str(&format!("{k}: {v}\r\n"));
+    }
+    let body = body.unwrap_or_default();
+    if !body.is_empty() || matches!(method, "POST" | "PUT" | "PATCH") {
+        head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    head.push_str("\r\n");
+    let mut out = head.into_bytes();
+    out.extend_from_slice(body);
+    out
+}
+
+#[cfg_attr(not(unix), allow(dead_code))]
+fn decode_chunked(mut data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    loop {
+        let line_end = data
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or("truncated chunk header")?;
+        let size_line = std::str::from_utf8(&data[..line_end]).map_err(|_| "bad chunk header")?;
+        let size_hex = size_line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size_hex, 16)
+            .map_err(|_| format!("bad chunk size: {size_hex:?}"))?;
+        data = &data[line_end + 2..];
+        if size == 0 {
+            return Ok(out);
+        }
+        if data.len() < size {
+            return Err("truncated chunk body".into());
+        }
+        out.extend_from_slice(&data[..size]);
+        data = data.get(size + 2..).unwrap_or_default();
+    }
+}
+
+/// Parse a complete `Connection: close` HTTP/1.1 response.
+#[cfg_attr(not(unix), allow(dead_code))]
+pub(crate) fn parse_http_response(raw: &[u8]) -> Result<BackendResponse, String> {
+    let head_end = raw
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or("incomplete HTTP response header")?;
+    let head = String::from_utf8_lossy(&raw[..head_end]);
+    let mut lines = head.split("\r\n");
+    let status_line = lines.next().unwrap_or_default();
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse::<u16>().ok())
+        .ok_or_else(|| format!("bad HTTP status line: {status_line:?}"))?;
+
+    let mut chunked = false;
+    let mut content_length: Option<usize> = None;
+    for line in lines {
+        let Some((k, v)) = line.split_once(':') else {
+            continue;
+        };
+        let (k, v) = (k.trim(), v.trim());
+        if k.eq_ignore_ascii_case("transfer-encoding") && v.eq_ignore_ascii_case("chunked") {
+            chunked = true;
+        } else if k.eq_ignore_ascii_case("content-length") {
+            content_length = v.parse().ok();
+        }
+    }
+
+    let rest = &raw[head_end + 4..];
+    let body = if chunked {
+        decode_chunked(rest)?
+    } else if let Some(len) = content_length {
+        rest.get(..len)
+            .ok_or("truncated HTTP response body")?
+            .to_vec()
+    } else {
+        rest.to_vec()
+    };
+    Ok(BackendResponse { status, body })
+}
+
+/// Failure talking to the backend socket.  Only [`UdsError::Connect`] is
+/// safe to retry over TCP: once any byte of the request has been written
+/// the backend may already be acting on it, and replaying a POST / PUT /
+/// DELETE would run it twice.
+#[cfg(unix)]
+enum UdsError {
+    Connect(String),
+    Exchange(String),
+}
+
+#[cfg(unix)]
+fn uds_request_blocking(
+    socket: &std::path::Path,
+    request: &[u8],
+    timeout: Duration,
+) -> Result<BackendResponse, UdsError> {
+    use std::io::{Read, Write};
+    let mut stream = std::os::unix::net::UnixStream::connect(socket)
+        .map_err(|e| UdsError::Connect(format!("connect {} failed: {e}", socket.display())))?;
+    let _ = stream.set_read_timeout(Some(timeout));
+    let _ = stream.set_write_timeout(Some(timeout));
+    stream
+        .write_all(request)
+        .map_err(|e| UdsError::Exchange(format!("uds write failed: {e}")))?;
+    let mut raw = Vec::new();
+    stream
+        .read_to_end(&mut raw)
+        .map_err(|e| UdsError::Exchange(format!("uds read failed: {e}")))?;
+    parse_http_response(&raw).map_err(UdsError::Exchange)
+}
+
+#[cfg(unix)]
+async fn uds_request(
+    socket: &std::path::Path,
+    request: &[u8],
+    timeout: Duration,
+) -> Result<BackendResponse, UdsError> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    let deadline = tokio::time::Instant::now() + timeout;
+    let timed_out = || format!("uds request timed out after {}s", timeout.as_secs());
+    let connect = tokio::net::UnixStream::connect(socket);
+    let mut stream = match tokio::time::timeout_at(deadline, connect).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => {
+            return Err(UdsError::Connect(format!(
+                "connect {} failed: {e}",
+                socket.display()
+            )))
+        }
+        Err(_) => return Err(UdsError::Connect(timed_out())),
+    };
+    let io = async {
+        stream
+            .write_all(request)
+            .await
+            .map_err(|e| format!("uds write failed: {e}"))?;
+        let mut raw = Vec::new();
+        stream
+            .read_to_end(&mut raw)
+            .await
+            .map_err(|e| format!("uds read failed: {e}"))?;
+        parse_http_response(&raw)
+    };
+    match tokio::time::timeout_at(deadline, io).await {
+        Ok(result) => result.map_err(UdsError::Exchange),
+        Err(_) => Err(UdsError::Exchange(timed_out())),
+    }
+}
+
+fn tcp_request_blocking(
+    port: u16,
+    method: &str,
+    path: &str,
+    headers: &[(String, String)],
+    body: Option<&[u8]>,
+    timeout: Duration,
+) -> Result<BackendResponse, String> {
+    let base = format!("http://127.0.0.1:{port}");
+    http_request_blocking(&base, true, method, path, headers, body, timeout)
+}
+
+/// Blocking request to `base` (`scheme://host:port`); `local` skips the
+/// system proxy.
+fn http_request_blocking(
+    base: &str,
+    local: bool,
+    method: &str,
+    path: &str,
+    headers: &[(String, String)],
+    body: Option<&[u8]>,
+    timeout: Duration,
+) -> Result<BackendResponse, String> {
+    let mut builder = reqwest::blocking::Client::builder().timeout(timeout);
+    if local {
+        builder = builder.no_proxy();
+    }
+    let client = builder.build().map_err(|e| format!("http client: {e}"))?;
+    let url = format!("{base}{path}");
+    let method = reqwest::Method::from_bytes(method.as_bytes())
+        .map_err(|e| format!("bad HTTP method {method}: {e}"))?;
+    let mut req = client.request(method, &url);
+    for (k, v) in headers {
+        req = req.header(k, v);
+    }
+    if let Some(b) = body {
+        req = req.body(b.to_vec());
+    }
+    let resp = req.send().map_err(|e| format!("HTTP {url} failed: {e}"))?;
+    let status = resp.status().as_u16();
+    let body = resp
+        .bytes()
+        .map_err(|e| format!("read response body failed: {e}"))?
+        .to_vec();
+    Ok(BackendResponse { status, body })
+}
+
+async fn tcp_request(
+    port: u16,
+    method: &str,
+    path: &str,
+    headers: &[(String, String)],
+    body: Option<&[u8]>,
+    timeout: Duration,
+) -> Result<BackendResponse, String> {
+    let base = format!("http://127.0.0.1:{port}");
+    http_request(
+        net::local_http_client(),
+        &base,
+        method,
+        path,
+        headers,
+        body,
+        timeout,
+    )
+    .await
+}
+
+async fn http_request(
+    client: &reqwest::Client,
+    base: &str,
+    method: &str,
+    path: &str,
+    headers: &[(String, String)],
+    body: Option<&[u8]>,
+    timeout: Duration,
+) -> Result<BackendResponse, String> {
+    let url = format!("{base}{path}");
+    let method = reqwest::Method::from_bytes(method.as_bytes())
+        .map_err(|e| format!("bad HTTP method {method}: {e}"))?;
+    let mut req = client.request(method, &url).timeout(timeout);
+    for (k, v) in headers {
+        req = req.header(k, v);
+    }
+    if let Some(b) = body {
+        req = req.body(b.to_vec());
+    }
+    let resp = req
+        .send()
+        .await
+        .map_err(|e| format!("HTTP {url} failed: {e}"))?;
+    let status = resp.status().as_u16();
+    let body = resp
+        .bytes()
+        .await
+        .map_err(|e| format!("read response body failed: {e}"))?
+        .to_vec();
+    Ok(BackendResponse { status, body })
+}
+
+fn trace_backend(
+    port: u16,
+    method: &str,
+    path: &str,
+    body: Option<&[u8]>,
+    started: Instant,
+    result: &Result<BackendResponse, String>,
+) {
+    if !trace::enabled() {
+        return;
+    }
+    let request = body.map(String::from_utf8_lossy);
+    let (status, text) = match result {
+        Ok(r) => (Some(r.status), Ok(r.text())),
+        Err(e) => (None, Err(e.as_str())),
+    };
+    trace::record(
+        "backend",
+        &format!("{method} :{port}{path}"),
+        started,
+        status,
+        request.as_deref(),
+        text.as_deref().map_err(|e| *e),
+    );
+}
+
+/// Blocking request to the backend on `port`: UDS first, TCP fallback when
+/// the socket cannot be connected to.  Errors after the request was sent
+/// are returned as-is rather than replayed over TCP.
+/// `path` must start with `/` and may include a query string.
+pub(crate) fn backend_request_blocking(
+    port: u16,
+    method: &str,
+    path: &str,
+    headers: &[(String, String)],
+    body: Option<&[u8]>,
+    timeout: Duration,
+) -> Result<BackendResponse, String> {
+    let started = Instant::now();
+    let result = backend_request_blocking_inner(port, method, path, headers, body, timeout);
+    trace_backend(port, method, path, body, started, &result);
+    result
+}
+
+fn backend_request_blocking_inner(
+    port: u16,
+    method: &str,
+    path: &str,
+    headers: &[(String, String)],
+    body: Option<&[u8]>,
+    timeout: Duration,
+) -> Result<BackendResponse, String> {
+    validate_request(method, path, headers)?;
+    #[cfg(unix)]
+    if let Some(socket) = backend_socket_path(port).filter(|p| p.exists()) {
+        let request = build_http_request(method, path, headers, body);
+        match uds_request_blocking(&socket, &request, timeout) {
+            Ok(resp) => return Ok(resp),
+            Err(UdsError::Connect(e)) => log_to_file(&format!(
+                "[ipc] {method} {path} via uds failed, using tcp: {e}"
+            )),
+            Err(UdsError::Exchange(e)) => return Err(e),
+        }
+    }
+    tcp_request_blocking(port, method, path, headers, body, timeout)
+}
+
+/// Async variant of [`backend_request_blocking`].
+pub(crate) async fn backend_request(
+    port: u16,
+    method: &str,
+    path: &str,
+    headers: &[(String, String)],
+    body: Option<&[u8]>,
+    timeout: Duration,
+) -> Result<BackendResponse, String> {
+    let started = Instant::now();
+    let result = backend_request_inner(port, method, path, headers, body, timeout).await;
+    trace_backend(port, method, path, body, started, &result);
+    result
+}
+
+async fn backend_request_inner(
+    port: u16,
+    method: &str,
+    path: &str,
+    headers: &[(String, String)],
+    body: Option<&[u8]>,
+    timeout: Duration,
+) -> Result<BackendResponse, String> {
+    validate_request(method, path, headers)?;
+    #[cfg(unix)]
+    if let Some(socket) = backend_socket_path(port).filter(|p| p.exists()) {
+        let request = build_http_request(method, path, headers, body);
+        match uds_request(&socket, &request, timeout).await {
+            Ok(resp) => return Ok(resp),
+            Err(UdsError::Connect(e)) => log_to_file(&format!(
+                "[ipc] {method} {path} via uds failed, using tcp: {e}"
+            )),
+            Err(UdsError::Exchange(e)) => return Err(e),
+        }
+    }
+    tcp_request(port, method, path, headers, body, timeout).await
+}
+
+/// Request to the workspace's backend: the local one on its `API_PORT`, or
+/// for an observer workspace the remote backend it watches (read-only,
+/// with its stored token).
+pub(crate) fn workspace_request_blocking(
+    workspace_id: &str,
+    method: &str,
+    path: &str,
+    headers: &[(String, String)],
+    body: Option<&[u8]>,
+    timeout: Duration,
+) -> Result<BackendResponse, String> {
+    let Some(target) = observer::target_of(workspace_id) else {
+        let port = read_workspace_api_port(workspace_id).unwrap_or(18900);
+        return backend_request_blocking(port, method, path, headers, body, timeout);
+    };
+    let headers = target.request_headers(workspace_id, method, headers)?;
+    let started = Instant::now();
+    let result = http_request_blocking(
+        &target.base_url(),
+        false,
+        method,
+        path,
+        &headers,
+        body,
+        timeout,
+    );
+    trace_backend(target.port, method, path, body, started, &result);
+    result
+}
+
+/// Async variant of [`workspace_request_blocking`].
+pub(crate) async fn workspace_request(
+    workspace_id: &str,
+    method: &str,
+    path: &str,
+    headers: &[(String, String)],
+    body: Option<&[u8]>,
+    timeout: Duration,
+) -> Result<BackendResponse, String> {
+    let Some(target) = observer::target_of(workspace_id) else {
+        let port = read_workspace_api_port(workspace_id).unwrap_or(18900);
+        return backend_request(port, method, path, headers, body, timeout).await;
+    };
+    let headers = target.request_headers(workspace_id, method, headers)?;
+    let started = Instant::now();
+    let base = target.base_url();
+    let result = http_request(
+        net::http_client(),
+        &base,
+        method,
+        path,
+        &headers,
+        body,
+        timeout,
+    )
+    .await;
+    trace_backend(target.port, method, path, body, started, &result);
+    result
+}
+
+/// Split `http://127.0.0.1:<port>/path?q` into `(port, "/path?q")`.
+pub(crate) fn split_local_url(url: &str) -> Option<(u16, String)> {
+    let rest = url
+        .strip_prefix("http://127.0.0.1")
+        .or_else(|| url.strip_prefix("http://localhost"))?;
+    let (port, path) = match rest.strip_prefix(':') {
+        Some(r) => {
+            let idx = r.find(['/', '?']).unwrap_or(r.len());
+            (r[..idx].parse::<u16>().ok()?, &r[idx..])
+        }
+        None if rest.is_empty() || rest.starts_with('/') || rest.starts_with('?') => (80, rest),
+        None => return None,
+    };
+    let path = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/{path}")
+    };
+    Some((port, path))
+}
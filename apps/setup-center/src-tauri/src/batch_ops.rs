@@ -0,0 +1,257 @@
+//! Fleet-style operations over several workspaces.
+//!
+//! `batch_operation(op, workspace_ids, options)` runs one operation on every
+//! listed workspace (all workspaces when the list is empty) with at most
+//! `concurrency` of them in flight, and emits a `batch-operation-progress`
+//! event as each one finishes, so an overview page can fill in row by row:
+//!
+//! * `health` — running / pid / HTTP health of each backend;
+//! * `stop`   — stop each backend, as the stop button does (stops still take
+//!   the backend lifecycle lock one at a time; the batch saves the round
+//!   trips, not the shutdown waits);
+//! * `backup` — a workspace backup zip into `options.outputDir`, through the
+//!   running backend when there is one, natively otherwise.
+//!
+//! One workspace failing does not stop the others; the final result lists
+//! every workspace in request order.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+use crate::errors::CmdResult;
+use crate::{
+    emit_global, export_workspace_backup, export_workspace_backup_native, invalidate_service_polls,
+    is_backend_http_healthy, observer, read_state_file, read_workspace_api_port,
+    service_status_uncached, service_stop_impl, spawn_blocking_result,
+};
+
+const DEFAULT_CONCURRENCY: usize = 4;
+const MAX_CONCURRENCY: usize = 16;
+
+static BATCH_SEQ: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchOp {
+    Health,
+    Stop,
+    Backup,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BatchOptions {
+    /// Workspaces in flight at once (1–16, default 4).
+    pub concurrency: Option<usize>,
+    /// Required for `backup`.
+    pub output_dir: Option<String>,
+    pub include_userdata: bool,
+    pub include_media: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchItemResult {
+    pub workspace_id: String,
+    pub ok: bool,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchProgress {
+    pub batch_id: u64,
+    pub op: BatchOp,
+    pub done: usize,
+    pub total: usize,
+    pub item: BatchItemResult,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchResult {
+    pub batch_id: u64,
+    pub op: BatchOp,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub elapsed_ms: u64,
+    pub results: Vec<BatchItemResult>,
+}
+
+/// Run `f` over `items` on at most `limit` threads.  `on_done` is called as
+/// each item finishes (in completion order); the returned results are in
+/// `items` order.
+pub(crate) fn run_bounded<T, R, F, D>(items: Vec<T>, limit: usize, f: F, on_done: D) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+    D: Fn(usize, &R) + Sync,
+{
+    let total = items.len();
+    let queue: Mutex<VecDeque<(usize, T)>> = Mutex::new(items.into_iter().enumerate().collect());
+    let results: Mutex<Vec<Option<R>>> = Mutex::new((0..total).map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..limit.clamp(1, total.max(1)) {
+            scope.spawn(|| loop {
+                let Some((index, item)) = queue.lock().unwrap().pop_front() else {
+                    break;
+                };
+                let result = f(&item);
+                on_done(index, &result);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+fn health(workspace_id: &str) -> Result<serde_json::Value, String> {
+    let status = service_status_uncached(workspace_id)?;
+    // 观察者的 running 本身就来自远端 /api/health
+    let (port, healthy) = match observer::target_of(workspace_id) {
+        Some(target) => (Some(target.port), status.running),
+        None => {
+            let port = read_workspace_api_port(workspace_id);
+            (port, status.running && is_backend_http_healthy(port))
+        }
+    };
+    Ok(serde_json::json!({
+        "running": status.running,
+        "pid": status.pid,
+        "port": port,
+        "healthy": healthy,
+    }))
+}
+
+fn stop(workspace_id: &str) -> Result<serde_json::Value, String> {
+    let result = service_stop_impl(workspace_id.to_string()).map_err(String::from);
+    invalidate_service_polls(workspace_id);
+    serde_json::to_value(result?).map_err(|e| e.to_string())
+}
+
+fn backup(workspace_id: &str, options: &BatchOptions) -> Result<serde_json::Value, String> {
+    let output_dir = options.output_dir.as_deref().unwrap_or_default();
+    // 后端在跑时走它的导出接口（与手动备份一致），否则直接打包目录
+    let running = service_status_uncached(workspace_id).is_ok_and(|s| s.running);
+    match read_workspace_api_port(workspace_id).filter(|_| running) {
+        Some(port) => export_workspace_backup(
+            workspace_id.to_string(),
+            output_dir.to_string(),
+            options.include_userdata,
+            options.include_media,
+            port,
+        )
+        .map_err(String::from),
+        None => export_workspace_backup_native(
+            workspace_id,
+            output_dir,
+            options.include_userdata,
+            options.include_media,
+        ),
+    }
+}
+
+fn run_one(op: BatchOp, workspace_id: &str, options: &BatchOptions) -> BatchItemResult {
+    let started = Instant::now();
+    let known = read_state_file()
+        .workspaces
+        .iter()
+        .any(|w| w.id == workspace_id);
+    let result = if !known {
+        Err(format!("NOT_FOUND|工作区不存在: {workspace_id}"))
+    } else {
+        match op {
+            BatchOp::Health => health(workspace_id),
+            BatchOp::Stop => stop(workspace_id),
+            BatchOp::Backup => backup(workspace_id, options),
+        }
+    };
+    let (ok, result, error) = match result {
+        Ok(v) => (true, Some(v), None),
+        Err(e) => (false, None, Some(e)),
+    };
+    BatchItemResult {
+        workspace_id: workspace_id.to_string(),
+        ok,
+        result,
+        error,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+/// Run `op` on `workspace_ids` (every workspace when empty), streaming
+/// `batch-operation-progress` events.
+#[tauri::command]
+pub async fn batch_operation(
+    op: BatchOp,
+    workspace_ids: Vec<String>,
+    options: Option<BatchOptions>,
+) -> CmdResult<BatchResult> {
+    let options = options.unwrap_or_default();
+    if op == BatchOp::Backup && options.output_dir.as_deref().unwrap_or("").is_empty() {
+        return Err("INVALID_ARGUMENT|backup 需要 outputDir".into());
+    }
+    let concurrency = options.concurrency.unwrap_or(DEFAULT_CONCURRENCY);
+    if !(1..=MAX_CONCURRENCY).contains(&concurrency) {
+        return Err(format!("INVALID_ARGUMENT|concurrency 需在 1-{MAX_CONCURRENCY} 之间").into());
+    }
+    let mut ids = if workspace_ids.is_empty() {
+        read_state_file()
+            .workspaces
+            .into_iter()
+            .map(|w| w.id)
+            .collect()
+    } else {
+        workspace_ids
+    };
+    let mut seen = std::collections::HashSet::new();
+    ids.retain(|id| seen.insert(id.clone()));
+
+    let batch_id = BATCH_SEQ.fetch_add(1, Ordering::SeqCst) + 1;
+    spawn_blocking_result(move || {
+        let started = Instant::now();
+        let total = ids.len();
+        let done = AtomicU64::new(0);
+        let results = run_bounded(
+            ids,
+            concurrency,
+            |id| run_one(op, id, &options),
+            |_, item: &BatchItemResult| {
+                let done = done.fetch_add(1, Ordering::SeqCst) as usize + 1;
+                emit_global(
+                    "batch-operation-progress",
+                    BatchProgress {
+                        batch_id,
+                        op,
+                        done,
+                        total,
+                        item: item.clone(),
+                    },
+                );
+            },
+        );
+        let succeeded = results.iter().filter(|r| r.ok).count();
+        Ok(BatchResult {
+            batch_id,
+            op,
+            succeeded,
+            failed: results.len() - succeeded,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+            results,
+        })
+    })
+    .await
+    .map_err(Into::into)
+}
@@ -0,0 +1,178 @@
+//! Short-lived cache for read-only bridge commands.
+//!
+//! The provider list and the workspace's skill list were fetched through
+//! the bridge on every page visit, although both change only when
+//! something is installed.  [`cached`] keeps their results in memory and
+//! under `~/.openakita/cache/bridge/`, keyed by venv and arguments (the
+//! `--workspace-dir` is one of them):
+//!
+//! * each command has its own TTL ([`ttl`]); commands without one are not
+//!   cached;
+//! * an entry also records a fingerprint — the venv's openakita version
+//!   and, for workspace commands, the skills folder and `data/skills.json`
+//!   — so edits made outside Setup Center invalidate it as well;
+//! * installing, removing or configuring a skill drops that workspace's
+//!   entries and pip installs drop the venv's ([`invalidate_workspace`],
+//!   [`invalidate_venv`]);
+//! * `force_refresh` skips the lookup and stores the new result.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::{atomic_write, compat, net, now_epoch_secs, openakita_root_dir, skill_watch};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Entry {
+    pub key: String,
+    pub venv: String,
+    pub args: Vec<String>,
+    pub fingerprint: String,
+    pub stored_at: u64,
+    pub ttl_secs: u64,
+    pub body: String,
+}
+
+static MEMORY: Lazy<Mutex<HashMap<String, Entry>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// How long a result of `cmd` stays fresh; `None` = never cached.
+pub(crate) fn ttl(cmd: &str) -> Option<Duration> {
+    match cmd {
+        "list-providers" => Some(Duration::from_secs(30 * 60)),
+        "list-skills" | "get-skill-config" => Some(Duration::from_secs(2 * 60)),
+        _ => None,
+    }
+}
+
+pub(crate) fn key(venv: &str, args: &[&str]) -> String {
+    format!("{venv}\u{1f}{}", args.join("\u{1f}"))
+}
+
+/// Value of `--workspace-dir` in `args`.
+pub(crate) fn workspace_arg<'a>(args: &[&'a str]) -> Option<&'a str> {
+    let i = args.iter().position(|a| *a == "--workspace-dir")?;
+    args.get(i + 1).copied()
+}
+
+pub(crate) fn is_fresh(entry: &Entry, fingerprint: &str, now: u64) -> bool {
+    entry.fingerprint == fingerprint && now.saturating_sub(entry.stored_at) < entry.ttl_secs
+}
+
+fn mtime_ms(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// What the result depends on besides time.
+fn fingerprint(venv: &str, args: &[&str]) -> String {
+    let version = compat::venv_openakita_version(venv).unwrap_or_default();
+    let Some(wd) = workspace_arg(args) else {
+        return version;
+    };
+    let wd = Path::new(wd);
+    let skills = skill_watch::scan_skills(&wd.join("skills"));
+    let stamp = format!(
+        "{skills:?}|{}",
+        mtime_ms(&wd.join("data").join("skills.json"))
+    );
+    format!("{version}|{}", net::cache_key_hash(&stamp))
+}
+
+fn cache_dir() -> PathBuf {
+    openakita_root_dir().join("cache").join("bridge")
+}
+
+fn entry_path(key: &str) -> PathBuf {
+    cache_dir().join(format!("{}.json", net::cache_key_hash(key)))
+}
+
+fn read_disk(key: &str) -> Option<Entry> {
+    let entry: Entry = serde_json::from_slice(&fs::read(entry_path(key)).ok()?).ok()?;
+    (entry.key == key).then_some(entry)
+}
+
+fn lookup(key: &str) -> Option<Entry> {
+    if let Some(e) = MEMORY.lock().unwrap().get(key) {
+        return Some(e.clone());
+    }
+    let e = read_disk(key)?;
+    MEMORY.lock().unwrap().insert(key.to_string(), e.clone());
+    Some(e)
+}
+
+fn store(entry: Entry) {
+    if let Ok(data) = serde_json::to_vec(&entry) {
+        let _ = atomic_write(entry_path(&entry.key), data);
+    }
+    MEMORY.lock().unwrap().insert(entry.key.clone(), entry);
+}
+
+/// Result of bridge `args` in `venv`, from the cache when fresh, otherwise
+/// from `run` (only successful results are stored).
+pub(crate) fn cached(
+    venv: &str,
+    args: &[&str],
+    force_refresh: bool,
+    run: impl FnOnce() -> Result<String, String>,
+) -> Result<String, String> {
+    let Some(ttl) = args.first().and_then(|c| ttl(c)) else {
+        return run();
+    };
+    let key = key(venv, args);
+    let print = fingerprint(venv, args);
+    if !force_refresh {
+        if let Some(e) = lookup(&key).filter(|e| is_fresh(e, &print, now_epoch_secs())) {
+            return Ok(e.body);
+        }
+    }
+    let body = run()?;
+    store(Entry {
+        key,
+        venv: venv.to_string(),
+        args: args.iter().map(|a| a.to_string()).collect(),
+        // 以执行后的状态为准（命令本身可能触发了文件变化）
+        fingerprint: fingerprint(venv, args),
+        stored_at: now_epoch_secs(),
+        ttl_secs: ttl.as_secs(),
+        body: body.clone(),
+    });
+    Ok(body)
+}
+
+fn remove_where(pred: impl Fn(&Entry) -> bool) {
+    MEMORY.lock().unwrap().retain(|_, e| !pred(e));
+    let Ok(rd) = fs::read_dir(cache_dir()) else {
+        return;
+    };
+    for path in rd.flatten().map(|e| e.path()) {
+        let hit = fs::read(&path)
+            .ok()
+            .and_then(|d| serde_json::from_slice::<Entry>(&d).ok())
+            .is_none_or(|e| pred(&e));
+        if hit {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Drop everything cached for the workspace at `workspace_dir` (skills
+/// installed, removed or reconfigured).
+pub(crate) fn invalidate_workspace(workspace_dir: &str) {
+    remove_where(|e| {
+        let args: Vec<&str> = e.args.iter().map(String::as_str).collect();
+        workspace_arg(&args) == Some(workspace_dir)
+    });
+}
+
+/// Drop everything cached for `venv` (packages changed).
+pub(crate) fn invalidate_venv(venv: &str) {
+    remove_where(|e| e.venv == venv);
+}
@@ -0,0 +1,201 @@
+//! Execution lanes for bridge calls.
+//!
+//! The install queue only covers commands that change the venv; two bridge
+//! calls that both write the workspace (installing a skill while its
+//! config is being saved, a `register-skill` racing an uninstall) could
+//! still interleave and leave `data/skills.json` or a skill folder half
+//! written.  Every bridge call now enters a lane first — the workspace it
+//! names with `--workspace-dir`, or its venv otherwise:
+//!
+//! * writers ([`is_write`]) run alone, in arrival order;
+//! * readers run in parallel with each other, but not past a writer that
+//!   arrived before them (so a stream of list calls cannot starve a save).
+//!
+//! Lane changes are emitted as `bridge_lane` events
+//! (`{ lane, running, queued, writing }`) and `get_bridge_lanes()` returns a
+//! snapshot, so the UI can show "waiting for another operation".
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use crate::{bridge_cache, emit_global, install_queue, log_to_file};
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeLane {
+    /// Workspace directory or venv the lane serialises.
+    lane: String,
+    running: usize,
+    queued: usize,
+    /// A writer is running.
+    writing: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Ticket {
+    pub id: u64,
+    pub write: bool,
+    pub running: bool,
+}
+
+#[derive(Default)]
+struct Lanes {
+    by_key: HashMap<String, VecDeque<Ticket>>,
+    next_ticket: u64,
+}
+
+static LANES: Lazy<(Mutex<Lanes>, Condvar)> =
+    Lazy::new(|| (Mutex::new(Lanes::default()), Condvar::new()));
+
+/// Bridge commands that write the workspace or the venv.
+pub(crate) fn is_write(cmd: &str) -> bool {
+    matches!(
+        cmd,
+        "install-skill"
+            | "uninstall-skill"
+            | "set-skill-config"
+            | "register-skill"
+            | "ensure-channel-deps"
+            | "memory-db"
+    )
+}
+
+/// Lane of bridge `args` run from `venv_dir`.
+pub(crate) fn lane_key(venv_dir: &str, args: &[&str]) -> String {
+    install_queue::venv_key(bridge_cache::workspace_arg(args).unwrap_or(venv_dir))
+}
+
+/// Whether ticket `id` may start: a writer needs to be first in line, a
+/// reader only needs no writer ahead of it.
+pub(crate) fn may_run(queue: &VecDeque<Ticket>, id: u64) -> bool {
+    let Some(pos) = queue.iter().position(|t| t.id == id) else {
+        return false;
+    };
+    if queue[pos].write {
+        pos == 0
+    } else {
+        queue.iter().take(pos).all(|t| !t.write)
+    }
+}
+
+fn snapshot(key: &str, queue: &VecDeque<Ticket>) -> BridgeLane {
+    BridgeLane {
+        lane: key.to_string(),
+        running: queue.iter().filter(|t| t.running).count(),
+        queued: queue.iter().filter(|t| !t.running).count(),
+        writing: queue.iter().any(|t| t.running && t.write),
+    }
+}
+
+fn emit(key: &str, lanes: &Lanes) {
+    let lane = match lanes.by_key.get(key) {
+        Some(q) => snapshot(key, q),
+        None => snapshot(key, &VecDeque::new()),
+    };
+    emit_global("bridge_lane", lane);
+}
+
+fn remove(lanes: &mut Lanes, key: &str, id: u64) {
+    if let Some(q) = lanes.by_key.get_mut(key) {
+        q.retain(|t| t.id != id);
+        if q.is_empty() {
+            lanes.by_key.remove(key);
+        }
+    }
+}
+
+/// Holds a place in the lane until dropped.
+pub(crate) struct LaneGuard {
+    key: String,
+    id: u64,
+}
+
+impl Drop for LaneGuard {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*LANES;
+        let Ok(mut lanes) = lock.lock() else { return };
+        remove(&mut lanes, &self.key, self.id);
+        emit(&self.key, &lanes);
+        cvar.notify_all();
+    }
+}
+
+/// Block until bridge `args` may run in its lane.  Returns
+/// `BRIDGE_CANCELLED|...` if `cancel` is set while still waiting.
+pub(crate) fn enter(
+    venv_dir: &str,
+    args: &[&str],
+    cancel: Option<&AtomicBool>,
+) -> Result<LaneGuard, String> {
+    let cmd = args.first().copied().unwrap_or_default();
+    let key = lane_key(venv_dir, args);
+    let write = is_write(cmd);
+    let (lock, cvar) = &*LANES;
+    let mut lanes = lock
+        .lock()
+        .map_err(|e| format!("bridge lane lock failed: {e}"))?;
+    let id = lanes.next_ticket;
+    lanes.next_ticket += 1;
+    lanes
+        .by_key
+        .entry(key.clone())
+        .or_default()
+        .push_back(Ticket {
+            id,
+            write,
+            running: false,
+        });
+    let mut waited = false;
+    loop {
+        let ready = lanes.by_key.get(&key).is_some_and(|q| may_run(q, id));
+        if ready {
+            break;
+        }
+        if !waited {
+            log_to_file(&format!("[bridge-lane] {cmd} waiting for {key}"));
+            emit(&key, &lanes);
+            waited = true;
+        }
+        if cancel.is_some_and(|c| c.load(Ordering::SeqCst)) {
+            remove(&mut lanes, &key, id);
+            emit(&key, &lanes);
+            cvar.notify_all();
+            return Err(format!(
+                "BRIDGE_CANCELLED|bridge {cmd} cancelled while queued"
+            ));
+        }
+        lanes = cvar
+            .wait_timeout(lanes, Duration::from_millis(250))
+            .map_err(|e| format!("bridge lane lock failed: {e}"))?
+            .0;
+    }
+    if let Some(t) = lanes
+        .by_key
+        .get_mut(&key)
+        .and_then(|q| q.iter_mut().find(|t| t.id == id))
+    {
+        t.running = true;
+    }
+    emit(&key, &lanes);
+    Ok(LaneGuard { key, id })
+}
+
+/// Lanes with running or queued bridge calls.
+#[tauri::command]
+pub fn get_bridge_lanes() -> Vec<BridgeLane> {
+    let (lock, _) = &*LANES;
+    let Ok(lanes) = lock.lock() else {
+        return Vec::new();
+    };
+    let mut out: Vec<BridgeLane> = lanes
+        .by_key
+        .iter()
+        .map(|(key, q)| snapshot(key, q))
+        .collect();
+    out.sort_by(|a, b| a.lane.cmp(&b.lane));
+    out
+}
@@ -0,0 +1,213 @@
+//! Response envelope of `openakita.setup_center.bridge`.
+//!
+//! Bridge commands used to answer with whatever they printed; a library
+//! that `print`ed a progress line or a deprecation notice turned the
+//! result into invalid JSON and the frontend failed somewhere far from the
+//! cause.  With `OPENAKITA_BRIDGE_ENVELOPE=1` (or `"envelope": true` for
+//! the worker) the bridge prints exactly one line:
+//!
+//! ```json
+//! {"bridgeEnvelope": 1, "ok": true, "data": ..., "error": null, "warnings": []}
+//! ```
+//!
+//! Stray stdout lines end up in `warnings`.  [`decode`] unwraps it, checks
+//! `data` against the serde model of the command where there is one, and
+//! hands callers the same JSON string they got before.  Anything that does
+//! not fit becomes `BRIDGE_PROTOCOL` with the offending output attached.
+//! Bridges older than the envelope are still read: the last line that
+//! parses as JSON wins.
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{log_to_file, redact};
+
+/// Env var that switches a one-shot bridge to envelope output.
+pub(crate) const ENVELOPE_ENV: &str = "OPENAKITA_BRIDGE_ENVELOPE";
+/// Output kept in a protocol error.
+const EXCERPT_CHARS: usize = 2000;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Envelope {
+    pub bridge_envelope: u32,
+    pub ok: bool,
+    #[serde(default)]
+    pub data: Value,
+    #[serde(default)]
+    pub error: Option<EnvelopeError>,
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct EnvelopeError {
+    pub message: String,
+    pub exit_code: Option<i32>,
+    pub stderr: String,
+}
+
+// ── 各命令的输出模型：只校验前端依赖的字段 ──
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Provider {
+    name: String,
+    slug: String,
+    api_type: String,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct ListedModel {
+    id: String,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct SkillList {
+    count: usize,
+    skills: Vec<SkillEntry>,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct SkillEntry {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct HealthResult {
+    name: String,
+    status: String,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct MarketplaceEntry {
+    name: String,
+    url: String,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct SkillConfig {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct StatusReply {
+    status: String,
+}
+
+fn check<T: DeserializeOwned>(data: &Value) -> Result<(), String> {
+    T::deserialize(data).map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// Check `data` against the model of bridge command `cmd`.  Commands
+/// without a model pass.
+pub(crate) fn validate(cmd: &str, data: &Value) -> Result<(), String> {
+    match cmd {
+        "list-providers" => check::<Vec<Provider>>(data),
+        "list-models" => check::<Vec<ListedModel>>(data),
+        "list-skills" => check::<SkillList>(data),
+        "health-check-endpoint" | "health-check-im" => check::<Vec<HealthResult>>(data),
+        "list-marketplace" => check::<Vec<MarketplaceEntry>>(data),
+        "get-skill-config" => check::<SkillConfig>(data),
+        "install-skill"
+        | "uninstall-skill"
+        | "set-skill-config"
+        | "register-skill"
+        | "set-registry-token"
+        | "download-registry-artifact" => check::<StatusReply>(data),
+        _ => Ok(()),
+    }
+}
+
+/// The envelope line in `stdout` and the other non-empty lines.
+pub(crate) fn find_envelope(stdout: &str) -> (Option<Envelope>, Vec<String>) {
+    let mut envelope = None;
+    let mut noise = Vec::new();
+    for line in stdout.lines().rev() {
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
+        }
+        if envelope.is_none() && text.contains("\"bridgeEnvelope\"") {
+            match serde_json::from_str::<Envelope>(text) {
+                Ok(env) if env.bridge_envelope >= 1 => {
+                    envelope = Some(env);
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        noise.insert(0, text.to_string());
+    }
+    (envelope, noise)
+}
+
+/// Output of a bridge without the envelope: all of it if it is JSON,
+/// otherwise the last line that is.
+pub(crate) fn legacy_data(stdout: &str) -> Option<Value> {
+    if let Ok(v) = serde_json::from_str(stdout.trim()) {
+        return Some(v);
+    }
+    stdout
+        .lines()
+        .rev()
+        .find_map(|l| serde_json::from_str(l.trim()).ok())
+}
+
+fn excerpt(output: &str) -> String {
+    let mut s: String = output.chars().take(EXCERPT_CHARS).collect();
+    if s.len() < output.len() {
+        s.push_str(" …");
+    }
+    redact::redact(&s)
+}
+
+pub(crate) fn protocol_error(cmd: &str, reason: &str, output: &str) -> String {
+    format!(
+        "BRIDGE_PROTOCOL|bridge protocol error ({cmd}): {reason}\noutput:\n{}",
+        excerpt(output)
+    )
+}
+
+/// Unwrap the bridge's stdout for command `args[0]` into the JSON of its
+/// result, or the error it reported.
+pub(crate) fn decode(args: &[&str], stdout: &str) -> Result<String, String> {
+    let cmd = args.first().copied().unwrap_or_default();
+    let (envelope, noise) = find_envelope(stdout);
+    let data = match envelope {
+        Some(env) => {
+            for w in env.warnings.iter().chain(&noise) {
+                log_to_file(&format!("[bridge] {cmd} warning: {}", redact::redact(w)));
+            }
+            if !env.ok {
+                let err = env.error.unwrap_or_default();
+                // 与单次进程非零退出相同的格式，ErrorPayload 会取 stderr 最后一行
+                return Err(redact::redact(&format!(
+                    "python failed: exit status: {}\nstdout:\n\nstderr:\n{}{}",
+                    err.exit_code.unwrap_or(1),
+                    err.stderr,
+                    if err.stderr.trim_end().ends_with(err.message.trim()) {
+                        String::new()
+                    } else {
+                        format!("{}\n", err.message)
+                    }
+                )));
+            }
+            env.data
+        }
+        None => {
+            legacy_data(stdout).ok_or_else(|| protocol_error(cmd, "no JSON in output", stdout))?
+        }
+    };
+    validate(cmd, &data).map_err(|e| protocol_error(cmd, &e, stdout))?;
+    Ok(data.to_string())
+}
@@ -0,0 +1,344 @@
+//! Long-lived bridge worker, one per venv.
+//!
+//! Every `run_python_module_json` call into `openakita.setup_center.bridge`
+//! used to spawn a fresh interpreter and re-import openakita, 1–3 s before
+//! the command even started — paid again for every skill list, model list
+//! and health check.  Instead the first call starts
+//! `python -m openakita.setup_center.bridge --server` for that venv and
+//! later calls reuse it:
+//!
+//! * one JSON request per stdin line, `{"id", "args", "secrets"?}`, and one
+//!   reply per stdout line carrying the same `id` — requests run
+//!   concurrently in the worker and replies are matched by id; the reply's
+//!   stdout is the command's [`bridge_protocol`](crate::bridge_protocol)
+//!   envelope;
+//! * the worker announces itself with `{"ready": true, "protocol": 1}`; a
+//!   bridge without `--server` (older openakita) never does and that venv
+//!   keeps using one-shot processes;
+//! * when the worker dies, requests in flight are retried as one-shot
+//!   processes and the next call starts a new worker;
+//! * `reset()` stops all workers after packages changed (pip install /
+//!   uninstall) so the next call imports the new code.
+//!
+//! Calls with extra environment (mirror settings) stay one-shot, as the
+//! worker's environment is shared by all requests.  `OPENAKITA_BRIDGE_WORKER=0`
+//! turns the worker off.
+
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{apply_no_window, log_to_file, redact, resolve_python, strip_harmful_python_env};
+
+const BRIDGE: &str = "openakita.setup_center.bridge";
+const PROTOCOL: u64 = 1;
+const READY_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often a waiting call checks its cancel flag.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const STDERR_TAIL_LINES: usize = 40;
+/// Env the one-shot path sets for secrets; the worker takes them per request.
+const SECRETS_ENV: &str = "OPENAKITA_BRIDGE_SECRETS_STDIN";
+
+type Pending = Arc<Mutex<HashMap<u64, mpsc::Sender<Reply>>>>;
+
+struct Worker {
+    pid: u32,
+    child: Mutex<Child>,
+    stdin: Mutex<ChildStdin>,
+    pending: Pending,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct Reply {
+    pub id: Option<u64>,
+    pub ok: bool,
+    pub exit_code: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Default for Reply {
+    fn default() -> Self {
+        Reply {
+            id: None,
+            ok: false,
+            exit_code: 1,
+            stdout: String::new(),
+            stderr: String::new(),
+        }
+    }
+}
+
+/// One stdout line of the worker.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Frame {
+    Ready {
+        protocol: u64,
+    },
+    Reply(Reply),
+    /// Stray output (a library printing to the real stdout).
+    Other,
+}
+
+static WORKERS: Lazy<Mutex<HashMap<String, Arc<Worker>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+/// Venvs whose bridge has no working `--server` mode; one-shot until `reset()`.
+static UNSUPPORTED: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+pub(crate) fn encode_request(id: u64, args: &[&str], secrets: Option<&Value>) -> String {
+    let mut req = json!({ "id": id, "args": args, "envelope": true });
+    if let Some(s) = secrets {
+        req["secrets"] = s.clone();
+    }
+    req.to_string()
+}
+
+pub(crate) fn parse_frame(line: &str) -> Frame {
+    let Ok(v) = serde_json::from_str::<Value>(line) else {
+        return Frame::Other;
+    };
+    if v.get("ready").and_then(Value::as_bool) == Some(true) {
+        let protocol = v.get("protocol").and_then(Value::as_u64).unwrap_or(0);
+        return Frame::Ready { protocol };
+    }
+    match serde_json::from_value::<Reply>(v) {
+        Ok(r) if r.id.is_some() => Frame::Reply(r),
+        _ => Frame::Other,
+    }
+}
+
+/// A reply in the same shape as the one-shot runner's result.
+pub(crate) fn reply_result(reply: Reply) -> Result<String, String> {
+    if reply.ok {
+        return Ok(reply.stdout.trim().to_string());
+    }
+    Err(redact::redact(&format!(
+        "python failed: exit status: {}\nstdout:\n{}\nstderr:\n{}",
+        reply.exit_code, reply.stdout, reply.stderr
+    )))
+}
+
+/// Whether a call with `extra_env` can go to the shared worker.
+pub(crate) fn eligible(extra_env: &[(&str, &str)]) -> bool {
+    extra_env.iter().all(|(k, _)| *k == SECRETS_ENV)
+}
+
+fn enabled() -> bool {
+    std::env::var("OPENAKITA_BRIDGE_WORKER").map_or(true, |v| v.trim() != "0")
+}
+
+fn spawn(venv_dir: &str) -> Result<Arc<Worker>, String> {
+    let (py, pythonpath) = resolve_python(venv_dir)?;
+    let mut c = Command::new(&py);
+    apply_no_window(&mut c);
+    strip_harmful_python_env(&mut c);
+    c.env("PYTHONUTF8", "1");
+    c.env("PYTHONIOENCODING", "utf-8");
+    c.env("PYTHONUNBUFFERED", "1");
+    if let Some(ref pp) = pythonpath {
+        c.env("PYTHONPATH", pp);
+    }
+    c.args(["-m", BRIDGE, "--server"]);
+    c.stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = c
+        .spawn()
+        .map_err(|e| format!("failed to run python: {e}"))?;
+    let pid = child.id();
+    let stdin = child.stdin.take().ok_or("python stdin pipe missing")?;
+    let stdout = child.stdout.take().ok_or("python stdout pipe missing")?;
+    let stderr = child.stderr.take().ok_or("python stderr pipe missing")?;
+
+    let tail: Arc<Mutex<VecDeque<String>>> = Arc::default();
+    thread::spawn({
+        let tail = tail.clone();
+        move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                let mut t = tail.lock().unwrap();
+                if t.len() == STDERR_TAIL_LINES {
+                    t.pop_front();
+                }
+                t.push_back(line);
+            }
+        }
+    });
+
+    let pending: Pending = Arc::default();
+    let (ready_tx, ready_rx) = mpsc::channel::<u64>();
+    thread::spawn({
+        let (pending, venv) = (pending.clone(), venv_dir.to_string());
+        move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                match parse_frame(&line) {
+                    Frame::Ready { protocol } => {
+                        let _ = ready_tx.send(protocol);
+                    }
+                    Frame::Reply(r) => {
+                        let tx = r.id.and_then(|id| pending.lock().unwrap().remove(&id));
+                        if let Some(tx) = tx {
+                            let _ = tx.send(r);
+                        }
+                    }
+                    Frame::Other => {}
+                }
+            }
+            // EOF：进程已退出。丢弃等待中的请求（调用方改走单次进程）
+            pending.lock().unwrap().clear();
+            let mut workers = WORKERS.lock().unwrap();
+            let exited = match workers.get(&venv) {
+                Some(w) if w.pid == pid => workers.remove(&venv),
+                _ => None,
+            };
+            drop(workers);
+            if let Some(w) = exited {
+                let _ = w.child.lock().unwrap().wait();
+            }
+            let tail: Vec<String> = tail.lock().unwrap().iter().cloned().collect();
+            log_to_file(&format!(
+                "[bridge-worker] pid={pid} exited ({venv})\n{}",
+                redact::redact(&tail.join("\n"))
+            ));
+        }
+    });
+
+    match ready_rx.recv_timeout(READY_TIMEOUT) {
+        Ok(PROTOCOL) => {}
+        other => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("bridge --server not available: {other:?}"));
+        }
+    }
+    log_to_file(&format!("[bridge-worker] pid={pid} ready ({venv_dir})"));
+    Ok(Arc::new(Worker {
+        pid,
+        child: Mutex::new(child),
+        stdin: Mutex::new(stdin),
+        pending,
+    }))
+}
+
+fn worker_for(venv_dir: &str) -> Option<Arc<Worker>> {
+    if UNSUPPORTED.lock().unwrap().contains(venv_dir) {
+        return None;
+    }
+    let mut workers = WORKERS.lock().unwrap();
+    if let Some(w) = workers.get(venv_dir) {
+        return Some(w.clone());
+    }
+    match spawn(venv_dir) {
+        Ok(w) => {
+            workers.insert(venv_dir.to_string(), w.clone());
+            Some(w)
+        }
+        Err(e) => {
+            log_to_file(&format!(
+                "[bridge-worker] {venv_dir}: {e}; using one-shot processes"
+            ));
+            UNSUPPORTED.lock().unwrap().insert(venv_dir.to_string());
+            None
+        }
+    }
+}
+
+fn kill(venv_dir: &str, w: &Worker) {
+    let mut workers = WORKERS.lock().unwrap();
+    if workers.get(venv_dir).is_some_and(|x| x.pid == w.pid) {
+        workers.remove(venv_dir);
+    }
+    drop(workers);
+    let mut child = w.child.lock().unwrap();
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// One request for [`call`].
+pub(crate) struct Call<'a> {
+    pub args: &'a [&'a str],
+    pub extra_env: &'a [(&'a str, &'a str)],
+    /// JSON object handed to `_stdin_secrets()`.
+    pub secrets: Option<&'a str>,
+    pub timeout: Duration,
+    pub cancel: Option<&'a AtomicBool>,
+}
+
+/// Run one bridge command through `venv_dir`'s worker.  `None` means the
+/// worker could not take it and the caller should spawn a one-shot process.
+///
+/// A cancelled request is abandoned (its reply is dropped; Python threads
+/// cannot be interrupted).  A timed-out one restarts the worker, since a
+/// request that hangs usually means the interpreter is wedged.
+pub(crate) fn call(venv_dir: &str, call: Call<'_>) -> Option<Result<String, String>> {
+    if !enabled() || !eligible(call.extra_env) {
+        return None;
+    }
+    let secrets = match call.secrets.map(serde_json::from_str::<Value>) {
+        None => None,
+        Some(Ok(v)) => Some(v),
+        Some(Err(_)) => return None,
+    };
+    let worker = worker_for(venv_dir)?;
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let (tx, rx) = mpsc::channel();
+    worker.pending.lock().unwrap().insert(id, tx);
+    let line = encode_request(id, call.args, secrets.as_ref());
+    let written = {
+        let mut stdin = worker.stdin.lock().unwrap();
+        writeln!(stdin, "{line}").and_then(|_| stdin.flush())
+    };
+    if written.is_err() {
+        worker.pending.lock().unwrap().remove(&id);
+        kill(venv_dir, &worker);
+        return None;
+    }
+    let label = format!(
+        "python {BRIDGE} {}",
+        call.args.first().copied().unwrap_or_default()
+    );
+    let deadline = Instant::now() + call.timeout;
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            worker.pending.lock().unwrap().remove(&id);
+            log_to_file(&format!(
+                "[bridge-worker] pid={} request {id} timed out, restarting",
+                worker.pid
+            ));
+            kill(venv_dir, &worker);
+            return Some(Err(format!(
+                "BRIDGE_TIMEOUT|{label} exceeded {}s",
+                call.timeout.as_secs()
+            )));
+        }
+        if call.cancel.is_some_and(|c| c.load(Ordering::SeqCst)) {
+            worker.pending.lock().unwrap().remove(&id);
+            return Some(Err(format!("BRIDGE_CANCELLED|{label} cancelled")));
+        }
+        match rx.recv_timeout((deadline - now).min(POLL_INTERVAL)) {
+            Ok(reply) => return Some(reply_result(reply)),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            // 进程中途退出：本次改走单次进程，下次调用会重新拉起
+            Err(mpsc::RecvTimeoutError::Disconnected) => return None,
+        }
+    }
+}
+
+/// Stop every worker and retry venvs without `--server`; the next call
+/// starts fresh with whatever is installed now.
+pub(crate) fn reset() {
+    UNSUPPORTED.lock().unwrap().clear();
+    let workers: Vec<(String, Arc<Worker>)> = WORKERS.lock().unwrap().drain().collect();
+    for (venv, w) in workers {
+        kill(&venv, &w);
+    }
+}
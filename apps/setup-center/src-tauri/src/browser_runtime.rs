@@ -0,0 +1,478 @@
+//! Managed Chromium for the agent's browser skills.
+//!
+//! Browser automation needs a Chromium the Playwright driver can launch.
+//! The packaged backend may ship one, but venv installs and slim builds
+//! don't, and users had to run `playwright install chromium` inside the
+//! venv by hand.  `install_browser_runtime(kind)` does it for them:
+//!
+//! * `playwright` — runs `python -m playwright install --no-shell chromium`
+//!   with `PLAYWRIGHT_BROWSERS_PATH` pointing at
+//!   `modules/browser/browsers`, the directory the backend already picks
+//!   up (see `native_backend_command` and the Python `_managed_browsers_dir`).
+//!   The revision matches the installed Playwright driver.
+//! * `portable` — downloads the current stable Chrome for Testing build
+//!   into `modules/browser/portable` without needing Python at all; the
+//!   backend is pointed at it through `OPENAKITA_BROWSER_EXECUTABLE`.
+//!
+//! Both check free disk space first and report progress as
+//! `browser-runtime` events (`{ kind, stage, percent, message }`, stage =
+//! checking / downloading / extracting / done / error).
+//! `get_browser_runtime_status()` reports what is installed where.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::errors::CmdResult;
+use crate::{
+    apply_no_window, archive, atomic_write, available_space_mb, dir_size_bytes, emit_if_ui_live,
+    log_to_file, modules_dir, net, now_ms, openakita_root_dir, resolve_python,
+    run_streaming_command, spawn_blocking_result, strip_harmful_python_env,
+};
+
+const EVENT: &str = "browser-runtime";
+const CFT_VERSIONS_URL: &str =
+    "https://googlechromelabs.github.io/chrome-for-testing/last-known-good-versions-with-downloads.json";
+/// Chromium unpacks to ~400 MB; the portable zip sits next to it until
+/// extraction finishes.
+const PLAYWRIGHT_REQUIRED_MB: f64 = 600.0;
+const PORTABLE_REQUIRED_MB: f64 = 900.0;
+const PLAYWRIGHT_INSTALL_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+static INSTALLING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowserRuntimeInfo {
+    /// Playwright revision (`chromium-1148` → "1148") or Chrome version.
+    pub version: String,
+    pub executable: Option<String>,
+    pub size_mb: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowserRuntimeStatus {
+    pub installed: bool,
+    pub playwright: Option<BrowserRuntimeInfo>,
+    pub portable: Option<BrowserRuntimeInfo>,
+    pub playwright_dir: String,
+    pub portable_dir: String,
+    pub installing: bool,
+    pub free_mb: f64,
+}
+
+/// `portable/manifest.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PortableManifest {
+    version: String,
+    /// Relative to the portable directory.
+    executable: String,
+    #[serde(default)]
+    installed_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgressEvent<'a> {
+    kind: &'a str,
+    stage: &'a str,
+    percent: Option<u8>,
+    message: String,
+}
+
+pub(crate) fn playwright_browsers_dir() -> PathBuf {
+    modules_dir().join("browser").join("browsers")
+}
+
+fn portable_dir() -> PathBuf {
+    modules_dir().join("browser").join("portable")
+}
+
+/// Chrome for Testing platform name of this build.
+fn cft_platform() -> Option<&'static str> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("windows", "x86_64") => Some("win64"),
+        ("windows", "x86") => Some("win32"),
+        ("macos", "aarch64") => Some("mac-arm64"),
+        ("macos", "x86_64") => Some("mac-x64"),
+        ("linux", "x86_64") => Some("linux64"),
+        _ => None,
+    }
+}
+
+/// Executable inside the unpacked `chrome-{platform}.zip`.
+pub(crate) fn cft_executable(platform: &str) -> String {
+    match platform {
+        "win64" | "win32" => format!("chrome-{platform}/chrome.exe"),
+        "mac-arm64" | "mac-x64" => format!(
+            "chrome-{platform}/Google Chrome for Testing.app/Contents/MacOS/Google Chrome for Testing"
+        ),
+        _ => format!("chrome-{platform}/chrome"),
+    }
+}
+
+/// (version, url) of the stable Chrome download for `platform` from the
+/// `last-known-good-versions-with-downloads.json` document.
+pub(crate) fn pick_cft_download(
+    doc: &serde_json::Value,
+    platform: &str,
+) -> Option<(String, String)> {
+    let stable = doc.get("channels")?.get("Stable")?;
+    let version = stable.get("version")?.as_str()?.to_string();
+    let url = stable
+        .get("downloads")?
+        .get("chrome")?
+        .as_array()?
+        .iter()
+        .find(|d| d.get("platform").and_then(|p| p.as_str()) == Some(platform))?
+        .get("url")?
+        .as_str()?
+        .to_string();
+    Some((version, url))
+}
+
+/// Percentage from a Playwright download progress line
+/// (`|■■■■■■■■        |  50% of 169.8 MiB`).
+pub(crate) fn parse_playwright_percent(line: &str) -> Option<u8> {
+    let idx = line.find("% of ")?;
+    let digits: String = line[..idx]
+        .chars()
+        .rev()
+        .take_while(char::is_ascii_digit)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    digits.parse::<u8>().ok().filter(|p| *p <= 100)
+}
+
+/// Chromium executable inside a Playwright `chromium-*` directory.
+fn playwright_executable(dir: &Path) -> Option<PathBuf> {
+    let candidates: &[&str] = if cfg!(windows) {
+        &["chrome-win64/chrome.exe", "chrome-win/chrome.exe"]
+    } else if cfg!(target_os = "macos") {
+        &[
+            "chrome-mac-arm64/Chromium.app/Contents/MacOS/Chromium",
+            "chrome-mac/Chromium.app/Contents/MacOS/Chromium",
+            "chrome-mac-arm64/Google Chrome for Testing.app/Contents/MacOS/Google Chrome for Testing",
+            "chrome-mac-x64/Google Chrome for Testing.app/Contents/MacOS/Google Chrome for Testing",
+        ]
+    } else {
+        &["chrome-linux64/chrome", "chrome-linux/chrome"]
+    };
+    candidates.iter().map(|c| dir.join(c)).find(|p| p.is_file())
+}
+
+fn playwright_info() -> Option<BrowserRuntimeInfo> {
+    let root = playwright_browsers_dir();
+    let mut dirs: Vec<PathBuf> = fs::read_dir(&root)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_dir()
+                && p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with("chromium-"))
+        })
+        .collect();
+    // 多个修订并存时取最新的
+    dirs.sort();
+    let dir = dirs.pop()?;
+    let name = dir.file_name()?.to_string_lossy().to_string();
+    Some(BrowserRuntimeInfo {
+        version: name.trim_start_matches("chromium-").to_string(),
+        executable: playwright_executable(&dir).map(|p| p.to_string_lossy().to_string()),
+        size_mb: dir_size_bytes(&root) as f64 / 1024.0 / 1024.0,
+    })
+}
+
+fn read_portable_manifest() -> Option<PortableManifest> {
+    let content = fs::read_to_string(portable_dir().join("manifest.json")).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// The portable Chrome to hand to the backend, if one is installed.
+pub(crate) fn portable_executable() -> Option<PathBuf> {
+    let manifest = read_portable_manifest()?;
+    let exe = portable_dir().join(manifest.executable);
+    exe.is_file().then_some(exe)
+}
+
+fn portable_info() -> Option<BrowserRuntimeInfo> {
+    let manifest = read_portable_manifest()?;
+    Some(BrowserRuntimeInfo {
+        version: manifest.version,
+        executable: portable_executable().map(|p| p.to_string_lossy().to_string()),
+        size_mb: dir_size_bytes(&portable_dir()) as f64 / 1024.0 / 1024.0,
+    })
+}
+
+/// Free space where the runtime goes; walks up to the first existing
+/// ancestor since `modules/` may not exist yet.
+fn free_mb() -> f64 {
+    let dir = modules_dir();
+    dir.ancestors()
+        .find(|p| p.exists())
+        .map(available_space_mb)
+        .unwrap_or(0.0)
+}
+
+fn current_status() -> BrowserRuntimeStatus {
+    let playwright = playwright_info();
+    let portable = portable_info();
+    BrowserRuntimeStatus {
+        installed: playwright.as_ref().is_some_and(|i| i.executable.is_some())
+            || portable.as_ref().is_some_and(|i| i.executable.is_some()),
+        playwright,
+        portable,
+        playwright_dir: playwright_browsers_dir().to_string_lossy().to_string(),
+        portable_dir: portable_dir().to_string_lossy().to_string(),
+        installing: INSTALLING.load(Ordering::SeqCst),
+        free_mb: free_mb(),
+    }
+}
+
+#[tauri::command]
+pub async fn get_browser_runtime_status() -> CmdResult<BrowserRuntimeStatus> {
+    spawn_blocking_result(|| Ok(current_status()))
+        .await
+        .map_err(Into::into)
+}
+
+fn emit(app: &tauri::AppHandle, kind: &str, stage: &str, percent: Option<u8>, message: String) {
+    emit_if_ui_live(
+        app,
+        EVENT,
+        ProgressEvent {
+            kind,
+            stage,
+            percent,
+            message,
+        },
+    );
+}
+
+fn ensure_disk_space(required_mb: f64) -> Result<(), String> {
+    let free = free_mb();
+    if free < required_mb {
+        return Err(format!(
+            "INSUFFICIENT_DISK_SPACE|磁盘空间不足：浏览器运行时需要约 {required_mb:.0} MB，可用 {free:.0} MB"
+        ));
+    }
+    Ok(())
+}
+
+fn install_playwright(app: &tauri::AppHandle, venv_dir: &str) -> Result<(), String> {
+    let (py, pythonpath) = resolve_python(venv_dir)?;
+    let browsers_dir = playwright_browsers_dir();
+    fs::create_dir_all(&browsers_dir).map_err(|e| format!("create browsers dir: {e}"))?;
+    let mut cmd = Command::new(&py);
+    apply_no_window(&mut cmd);
+    strip_harmful_python_env(&mut cmd);
+    cmd.env("PYTHONUTF8", "1");
+    cmd.env("PYTHONIOENCODING", "utf-8");
+    if let Some(ref pp) = pythonpath {
+        cmd.env("PYTHONPATH", pp);
+    }
+    cmd.env("PLAYWRIGHT_BROWSERS_PATH", &browsers_dir);
+    cmd.args(["-m", "playwright", "install", "--no-shell", "chromium"]);
+    let mut log = String::new();
+    let emit_line = |text: &str| {
+        // Playwright 每 10% 打一行进度条，其它行原样转给前端
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            emit(
+                app,
+                "playwright",
+                "downloading",
+                parse_playwright_percent(line),
+                line.trim().to_string(),
+            );
+        }
+    };
+    let status = run_streaming_command(
+        cmd,
+        "playwright install chromium",
+        Some(&mut log),
+        Some(&emit_line),
+        PLAYWRIGHT_INSTALL_TIMEOUT,
+    )?;
+    if !status.success() {
+        let tail: String = log
+            .lines()
+            .rev()
+            .take(20)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect::<Vec<_>>()
+            .join("\n");
+        if log.contains("No module named playwright") {
+            return Err(format!(
+                "NOT_FOUND|当前 Python 环境未安装 playwright，可改用便携版 Chromium（portable）\n{tail}"
+            ));
+        }
+        return Err(format!("playwright install 失败（{status}）\n{tail}"));
+    }
+    if playwright_info().and_then(|i| i.executable).is_none() {
+        return Err(format!(
+            "playwright install 已完成，但在 {} 下没有找到 Chromium",
+            browsers_dir.display()
+        ));
+    }
+    Ok(())
+}
+
+async fn install_portable(app: &tauri::AppHandle) -> Result<(), String> {
+    let platform = cft_platform().ok_or_else(|| {
+        "INVALID_ARGUMENT|当前平台没有便携版 Chromium，请改用 playwright".to_string()
+    })?;
+    let doc = net::http_client()
+        .get(CFT_VERSIONS_URL)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("获取 Chromium 版本列表失败: {e}"))?
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| format!("解析 Chromium 版本列表失败: {e}"))?;
+    let (version, url) = pick_cft_download(&doc, platform)
+        .ok_or_else(|| format!("版本列表里没有 {platform} 的 Chromium"))?;
+    log_to_file(&format!("[browser_runtime] portable {version} from {url}"));
+
+    let parent = modules_dir().join("browser");
+    fs::create_dir_all(&parent).map_err(|e| format!("create {}: {e}", parent.display()))?;
+    let zip_path = parent.join("portable-download.zip");
+    let staging = parent.join("portable.staging");
+    let result = async {
+        net::download_to_file(&url, &zip_path, |done, total| {
+            emit(
+                app,
+                "portable",
+                "downloading",
+                net::download_percent(done, total),
+                format!("{:.1} MB", done as f64 / 1024.0 / 1024.0),
+            )
+        })
+        .await?;
+        emit(app, "portable", "extracting", None, version.clone());
+        let (zip, stage) = (zip_path.clone(), staging.clone());
+        let app2 = app.clone();
+        spawn_blocking_result(move || {
+            let _ = fs::remove_dir_all(&stage);
+            let mut last_percent = None;
+            let mut on_progress = |p: archive::ExtractProgress| {
+                let percent = net::download_percent(p.bytes, p.total_bytes);
+                if percent != last_percent {
+                    last_percent = percent;
+                    emit(&app2, "portable", "extracting", percent, String::new());
+                }
+            };
+            archive::extract_zip(
+                &zip,
+                &stage,
+                &mut archive::ExtractOptions {
+                    on_progress: Some(&mut on_progress),
+                    ..Default::default()
+                },
+            )?;
+            Ok(())
+        })
+        .await?;
+        let executable = cft_executable(platform);
+        if !staging.join(&executable).is_file() {
+            return Err(format!("下载的 Chromium 包里没有 {executable}"));
+        }
+        let manifest = PortableManifest {
+            version: version.clone(),
+            executable,
+            installed_at_ms: now_ms(),
+        };
+        let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+        atomic_write(staging.join("manifest.json"), json)
+            .map_err(|e| format!("write manifest: {e}"))?;
+        // 新版本完整解压后再替换旧目录，失败时旧的还能用
+        let target = portable_dir();
+        if target.exists() {
+            fs::remove_dir_all(&target)
+                .map_err(|e| format!("remove old {}: {e}", target.display()))?;
+        }
+        fs::rename(&staging, &target).map_err(|e| format!("install {}: {e}", target.display()))
+    }
+    .await;
+    let _ = fs::remove_file(&zip_path);
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&staging);
+    }
+    result
+}
+
+struct InstallingGuard;
+
+impl Drop for InstallingGuard {
+    fn drop(&mut self) {
+        INSTALLING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Install the browser runtime: `kind` = `playwright` (default) or
+/// `portable`.  `venv_dir` is the Python used for `playwright`, defaulting
+/// to `~/.openakita/venv` / the bundled interpreter.
+#[tauri::command]
+pub async fn install_browser_runtime(
+    app: tauri::AppHandle,
+    kind: Option<String>,
+    venv_dir: Option<String>,
+) -> CmdResult<BrowserRuntimeStatus> {
+    let kind = kind.unwrap_or_else(|| "playwright".to_string());
+    let required = match kind.as_str() {
+        "playwright" => PLAYWRIGHT_REQUIRED_MB,
+        "portable" => PORTABLE_REQUIRED_MB,
+        other => {
+            return Err(format!(
+                "INVALID_ARGUMENT|未知的浏览器运行时类型: {other}（playwright / portable）"
+            )
+            .into())
+        }
+    };
+    net::ensure_online("下载浏览器运行时")?;
+    if INSTALLING.swap(true, Ordering::SeqCst) {
+        return Err("ALREADY_EXISTS|浏览器运行时正在安装中".into());
+    }
+    let _guard = InstallingGuard;
+    emit(&app, &kind, "checking", None, String::new());
+    log_to_file(&format!("[browser_runtime] install {kind} started"));
+    let result = async {
+        ensure_disk_space(required)?;
+        if kind == "portable" {
+            install_portable(&app).await
+        } else {
+            let venv = venv_dir.unwrap_or_else(|| {
+                openakita_root_dir()
+                    .join("venv")
+                    .to_string_lossy()
+                    .to_string()
+            });
+            let app2 = app.clone();
+            spawn_blocking_result(move || install_playwright(&app2, &venv)).await
+        }
+    }
+    .await;
+    match &result {
+        Ok(()) => {
+            log_to_file(&format!("[browser_runtime] install {kind} done"));
+            emit(&app, &kind, "done", Some(100), String::new());
+        }
+        Err(e) => {
+            log_to_file(&format!("[browser_runtime] install {kind} failed: {e}"));
+            emit(&app, &kind, "error", None, e.clone());
+        }
+    }
+    result?;
+    Ok(current_status())
+}
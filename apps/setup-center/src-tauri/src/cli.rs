@@ -0,0 +1,357 @@
+//! Headless subcommands of the desktop binary.
+//!
+//! The same executable that runs the GUI also answers
+//!
+//! ```text
+//! openakita-setup-center start   [--workspace ID] [--venv DIR] [--no-wait]
+//! openakita-setup-center stop    [--workspace ID]
+//! openakita-setup-center status  [--workspace ID] [--json]
+//! openakita-setup-center doctor  [--json]
+//! openakita-setup-center install [--version X.Y.Z] [--index-url URL] [--venv DIR]
+//! openakita-setup-center supervise [--workspace ID] [--venv DIR]
+//! ```
+//!
+//! for scripts, SSH sessions and headless boxes.  The subcommands call the
+//! same internals as the Tauri commands (start / stop / status, the startup
+//! self-check plus the Python and clock diagnostics, pip install) and never build a
+//! window.  Only an exact subcommand in `argv[1]` switches to CLI mode, so
+//! the GUI's own flags (`--background`, deep links, ...) are unaffected.
+//!
+//! The workspace defaults to the current one from the state file.  Exit
+//! codes: 0 ok, 1 failed / unhealthy, 2 usage error.
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    clock, diagnose_python_env, invalidate_service_polls, is_backend_http_healthy, log_to_file,
+    openakita_root_dir, openakita_service_start_headless, pip_install_blocking, read_state_file,
+    read_workspace_api_port, service_status_uncached, service_stop_impl,
+    set_backend_manually_stopped, startup_check, supervisor, BACKEND_LIFECYCLE_LOCK,
+};
+
+const USAGE: &str = "\
+Usage: openakita-setup-center <command> [options]
+
+Commands:
+  start    [--workspace ID] [--venv DIR] [--no-wait]   start the backend
+  stop     [--workspace ID]                            stop the backend
+  status   [--workspace ID] [--json]                   show backend status
+  doctor   [--json]                                    run the self-checks
+  install  [--version X.Y.Z] [--index-url URL] [--venv DIR]
+                                                       pip install openakita
+  supervise [--workspace ID] [--venv DIR]              keep the backend running
+                                                       (companion watchdog)
+  help                                                 show this text
+
+Without a command the desktop app starts as usual.";
+
+/// How long `start` waits for the HTTP API before giving up.
+const START_WAIT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum CliCommand {
+    Start {
+        workspace: Option<String>,
+        venv: Option<String>,
+        wait: bool,
+    },
+    Stop {
+        workspace: Option<String>,
+    },
+    Status {
+        workspace: Option<String>,
+        json: bool,
+    },
+    Doctor {
+        json: bool,
+    },
+    Install {
+        version: Option<String>,
+        index_url: Option<String>,
+        venv: Option<String>,
+    },
+    Supervise {
+        workspace: Option<String>,
+        venv: Option<String>,
+    },
+    Help,
+}
+
+/// Parse `argv[1..]`.  `None` = not a CLI invocation (start the GUI);
+/// `Some(Err)` = a subcommand with bad options.
+pub(crate) fn parse_args(args: &[String]) -> Option<Result<CliCommand, String>> {
+    let (cmd, rest) = args.split_first()?;
+    if !matches!(
+        cmd.as_str(),
+        "start" | "stop" | "status" | "doctor" | "install" | "supervise" | "help" | "--help" | "-h"
+    ) {
+        return None;
+    }
+    Some(parse_command(cmd, rest))
+}
+
+fn parse_command(cmd: &str, rest: &[String]) -> Result<CliCommand, String> {
+    let mut workspace = None;
+    let mut venv = None;
+    let mut version = None;
+    let mut index_url = None;
+    let mut json = false;
+    let mut wait = true;
+    let mut it = rest.iter();
+    while let Some(arg) = it.next() {
+        // 同时支持 `--opt value` 和 `--opt=value`
+        let (flag, inline) = match arg.split_once('=') {
+            Some((f, v)) if f.starts_with("--") => (f, Some(v.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| it.next().cloned())
+                .filter(|v| !v.is_empty())
+                .ok_or_else(|| format!("{flag} needs a value"))
+        };
+        let allowed = match (cmd, flag) {
+            ("start" | "stop" | "status" | "supervise", "--workspace" | "-w") => {
+                workspace = Some(value()?);
+                true
+            }
+            ("start" | "install" | "supervise", "--venv") => {
+                venv = Some(value()?);
+                true
+            }
+            ("install", "--version") => {
+                version = Some(value()?);
+                true
+            }
+            ("install", "--index-url") => {
+                index_url = Some(value()?);
+                true
+            }
+            ("status" | "doctor", "--json") => {
+                json = true;
+                true
+            }
+            ("start", "--no-wait") => {
+                wait = false;
+                true
+            }
+            _ => false,
+        };
+        if !allowed {
+            return Err(format!("unknown option for `{cmd}`: {arg}"));
+        }
+    }
+    if let Some(v) = &version {
+        if !v
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'))
+        {
+            return Err(format!("invalid version: {v}"));
+        }
+    }
+    Ok(match cmd {
+        "start" => CliCommand::Start {
+            workspace,
+            venv,
+            wait,
+        },
+        "stop" => CliCommand::Stop { workspace },
+        "status" => CliCommand::Status { workspace, json },
+        "doctor" => CliCommand::Doctor { json },
+        "install" => CliCommand::Install {
+            version,
+            index_url,
+            venv,
+        },
+        "supervise" => CliCommand::Supervise { workspace, venv },
+        _ => CliCommand::Help,
+    })
+}
+
+/// Release builds on Windows are GUI-subsystem executables with no console;
+/// borrow the parent's so output reaches the terminal that ran us.
+#[cfg(windows)]
+fn attach_parent_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // 从资源管理器双击启动时没有父控制台，失败即可忽略
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+/// Run a CLI invocation.  `None` when `args` isn't one and the GUI should
+/// start; otherwise the process exit code.
+pub(crate) fn run(args: &[String]) -> Option<i32> {
+    let parsed = parse_args(args.get(1..).unwrap_or_default())?;
+    #[cfg(windows)]
+    attach_parent_console();
+    let code = match parsed {
+        Ok(cmd) => {
+            log_to_file(&format!("[cli] {cmd:?}"));
+            match execute(cmd) {
+                Ok(code) => code,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    1
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            2
+        }
+    };
+    Some(code)
+}
+
+fn resolve_workspace(workspace: Option<String>) -> Result<String, String> {
+    workspace
+        .or_else(|| read_state_file().current_workspace_id)
+        .ok_or_else(|| "no current workspace; pass --workspace ID".to_string())
+}
+
+fn default_venv(venv: Option<String>) -> String {
+    venv.unwrap_or_else(|| {
+        openakita_root_dir()
+            .join("venv")
+            .to_string_lossy()
+            .to_string()
+    })
+}
+
+fn print_json(value: &impl serde::Serialize) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{text}");
+    Ok(())
+}
+
+fn execute(cmd: CliCommand) -> Result<i32, String> {
+    match cmd {
+        CliCommand::Help => {
+            println!("{USAGE}");
+            Ok(0)
+        }
+        CliCommand::Start {
+            workspace,
+            venv,
+            wait,
+        } => {
+            let ws = resolve_workspace(workspace)?;
+            {
+                let _lifecycle_guard = BACKEND_LIFECYCLE_LOCK.lock().unwrap();
+                set_backend_manually_stopped(&ws, false)?;
+            }
+            let status = openakita_service_start_headless(default_venv(venv), ws.clone())?;
+            println!("started workspace {ws} (pid {:?})", status.pid);
+            if !wait {
+                return Ok(0);
+            }
+            let port = read_workspace_api_port(&ws);
+            let deadline = Instant::now() + START_WAIT;
+            while Instant::now() < deadline {
+                if is_backend_http_healthy(port) {
+                    println!("backend is ready on port {}", port.unwrap_or(18900));
+                    return Ok(0);
+                }
+                if !service_status_uncached(&ws)?.running {
+                    return Err("backend exited during startup; see the service log".into());
+                }
+                std::thread::sleep(Duration::from_secs(1));
+            }
+            Err(format!(
+                "backend not ready after {}s (still starting?)",
+                START_WAIT.as_secs()
+            ))
+        }
+        CliCommand::Stop { workspace } => {
+            let ws = resolve_workspace(workspace)?;
+            let status = service_stop_impl(ws.clone()).map_err(|e| e.message)?;
+            invalidate_service_polls(&ws);
+            if status.running {
+                return Err(format!(
+                    "backend of {ws} is still running (pid {:?})",
+                    status.pid
+                ));
+            }
+            println!("stopped workspace {ws}");
+            Ok(0)
+        }
+        CliCommand::Status { workspace, json } => {
+            let ws = resolve_workspace(workspace)?;
+            let status = service_status_uncached(&ws)?;
+            if json {
+                print_json(&status)?;
+            } else if status.running {
+                println!(
+                    "{ws}: running (pid {}, phase {})",
+                    status.pid.map(|p| p.to_string()).unwrap_or_default(),
+                    if status.heartbeat_phase.is_empty() {
+                        "-"
+                    } else {
+                        &status.heartbeat_phase
+                    }
+                );
+            } else {
+                println!("{ws}: stopped");
+            }
+            Ok(if status.running { 0 } else { 1 })
+        }
+        CliCommand::Doctor { json } => {
+            let issues = startup_check::run_startup_checks();
+            let python = diagnose_python_env(String::new());
+            let time = clock::diagnose(read_state_file().current_workspace_id.as_deref());
+            let healthy = python.summary == "healthy"
+                && !issues.iter().any(|i| i.severity == "error")
+                && !time.issues.iter().any(|i| i.severity == "error");
+            if json {
+                print_json(&serde_json::json!({
+                    "healthy": healthy,
+                    "startupIssues": issues,
+                    "python": python,
+                    "time": time,
+                }))?;
+            } else {
+                for i in &issues {
+                    println!("[{}] {}: {}", i.severity, i.code, i.message);
+                    if !i.hint.is_empty() {
+                        println!("    hint: {}", i.hint);
+                    }
+                }
+                for c in &python.contracts {
+                    println!("[{}] {}: {}", c.status, c.id, c.title);
+                    if let Some(hint) = c.fix_hint.as_deref().filter(|_| c.status != "pass") {
+                        println!("    hint: {hint}");
+                    }
+                }
+                for i in &time.issues {
+                    println!("[{}] {}: {}", i.severity, i.code, i.message);
+                }
+                println!("{}", if healthy { "healthy" } else { "problems found" });
+            }
+            Ok(if healthy { 0 } else { 1 })
+        }
+        CliCommand::Install {
+            version,
+            index_url,
+            venv,
+        } => {
+            let spec = version
+                .map(|v| format!("openakita=={v}"))
+                .unwrap_or_else(|| "openakita".to_string());
+            let venv = default_venv(venv);
+            println!("installing {spec} into {venv} ...");
+            let log = pip_install_blocking(&venv, &spec, index_url, "cli")?;
+            println!("{log}");
+            Ok(0)
+        }
+        CliCommand::Supervise { workspace, venv } => {
+            let ws = resolve_workspace(workspace)?;
+            supervisor::run(ws, default_venv(venv))?;
+            Ok(0)
+        }
+    }
+}
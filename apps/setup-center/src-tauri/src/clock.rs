@@ -0,0 +1,315 @@
+//! Clock and timezone sanity check.
+//!
+//! Scheduled tasks firing at odd hours are usually one of two things: the
+//! machine's clock is off (dead CMOS battery, VM restored from a snapshot,
+//! NTP blocked), or the backend's `SCHEDULER_TIMEZONE` (default
+//! `Asia/Shanghai`) isn't the zone the user lives in.  [`diagnose`] measures
+//! the clock against an SNTP server and compares the OS timezone with the
+//! scheduler's; `get_time_diagnostics()` serves the scheduler page and the
+//! CLI `doctor` prints it.
+//!
+//! Windows reports zone *names* ("China Standard Time"), so the comparison
+//! goes through a small table of common zones; an unmapped zone is reported
+//! but never flagged as a mismatch.
+
+use serde::Serialize;
+use std::net::UdpSocket;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::errors::CmdResult;
+use crate::{env_overlay, log_to_file, net, read_state_file, spawn_blocking_result};
+
+/// Tried in order; the first answers.  Aliyun first for users in mainland
+/// China, where pool.ntp.org is often slow.
+const NTP_SERVERS: &[&str] = &["ntp.aliyun.com", "pool.ntp.org", "time.windows.com"];
+const DEFAULT_SCHEDULER_TZ: &str = "Asia/Shanghai";
+/// Seconds between the NTP (1900) and Unix (1970) epochs.
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+/// Skew worth a warning / an error, in milliseconds.
+const SKEW_WARN_MS: i64 = 60_000;
+const SKEW_ERROR_MS: i64 = 5 * 60_000;
+
+/// Windows zone name → IANA zone, for the zones users actually report.
+const WINDOWS_ZONES: &[(&str, &str)] = &[
+    ("China Standard Time", "Asia/Shanghai"),
+    ("Taipei Standard Time", "Asia/Taipei"),
+    ("Tokyo Standard Time", "Asia/Tokyo"),
+    ("Korea Standard Time", "Asia/Seoul"),
+    ("Singapore Standard Time", "Asia/Singapore"),
+    ("India Standard Time", "Asia/Kolkata"),
+    ("UTC", "UTC"),
+    ("GMT Standard Time", "Europe/London"),
+    ("W. Europe Standard Time", "Europe/Berlin"),
+    ("Romance Standard Time", "Europe/Paris"),
+    ("Russian Standard Time", "Europe/Moscow"),
+    ("Eastern Standard Time", "America/New_York"),
+    ("Central Standard Time", "America/Chicago"),
+    ("Mountain Standard Time", "America/Denver"),
+    ("Pacific Standard Time", "America/Los_Angeles"),
+    ("AUS Eastern Standard Time", "Australia/Sydney"),
+];
+
+/// Names that mean the same zone as far as the scheduler is concerned.
+const ZONE_ALIASES: &[(&str, &str)] = &[
+    ("Asia/Chongqing", "Asia/Shanghai"),
+    ("Asia/Harbin", "Asia/Shanghai"),
+    ("PRC", "Asia/Shanghai"),
+    ("Asia/Calcutta", "Asia/Kolkata"),
+    ("Etc/UTC", "UTC"),
+    ("Etc/GMT", "UTC"),
+    ("GMT", "UTC"),
+    ("Zulu", "UTC"),
+];
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeIssue {
+    /// `CLOCK_SKEW` | `NTP_UNREACHABLE` | `TIMEZONE_MISMATCH`
+    pub code: String,
+    /// `error` | `warning` | `info`
+    pub severity: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeDiagnostics {
+    pub system_time_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ntp_server: Option<String>,
+    /// Server time minus local time; positive = local clock is behind.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ntp_offset_ms: Option<i64>,
+    /// OS timezone as reported (IANA name, or Windows zone name).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os_timezone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os_utc_offset_minutes: Option<i32>,
+    pub scheduler_timezone: String,
+    /// `env` (workspace .env) | `default`
+    pub scheduler_timezone_source: String,
+    pub issues: Vec<TimeIssue>,
+}
+
+/// 48-byte SNTP v4 client request.
+pub(crate) fn sntp_request() -> [u8; 48] {
+    let mut req = [0u8; 48];
+    req[0] = 0x23; // LI=0, VN=4, Mode=3 (client)
+    req
+}
+
+/// Clock offset in ms from a server reply, given the local send (`t1`) and
+/// receive (`t4`) times in Unix ms: ((t2 - t1) + (t3 - t4)) / 2.
+pub(crate) fn sntp_offset_ms(reply: &[u8], t1_ms: u64, t4_ms: u64) -> Option<i64> {
+    if reply.len() < 48 || reply[0] & 0x07 != 4 || reply[1] == 0 {
+        return None; // 不是 server 回复，或 stratum 0（kiss-o'-death）
+    }
+    let ts_ms = |at: usize| -> Option<i64> {
+        let secs = u32::from_be_bytes(reply[at..at + 4].try_into().ok()?) as u64;
+        let frac = u32::from_be_bytes(reply[at + 4..at + 8].try_into().ok()?) as u64;
+        let unix = secs.checked_sub(NTP_UNIX_OFFSET)?;
+        Some((unix * 1000 + ((frac * 1000) >> 32)) as i64)
+    };
+    let (t2, t3) = (ts_ms(32)?, ts_ms(40)?);
+    Some(((t2 - t1_ms as i64) + (t3 - t4_ms as i64)) / 2)
+}
+
+fn unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn query_ntp(server: &str) -> Result<i64, String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| e.to_string())?;
+    socket
+        .set_read_timeout(Some(Duration::from_secs(3)))
+        .map_err(|e| e.to_string())?;
+    socket
+        .connect((server, 123))
+        .map_err(|e| format!("{server}: {e}"))?;
+    let t1 = unix_ms();
+    socket
+        .send(&sntp_request())
+        .map_err(|e| format!("{server}: {e}"))?;
+    let mut buf = [0u8; 64];
+    let n = socket
+        .recv(&mut buf)
+        .map_err(|e| format!("{server}: {e}"))?;
+    let t4 = unix_ms();
+    sntp_offset_ms(&buf[..n], t1, t4).ok_or_else(|| format!("{server}: invalid reply"))
+}
+
+fn canonical_zone(name: &str) -> &str {
+    let name = WINDOWS_ZONES
+        .iter()
+        .find(|(win, _)| win.eq_ignore_ascii_case(name))
+        .map_or(name, |(_, iana)| iana);
+    ZONE_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, canon)| canon)
+}
+
+/// Whether the OS zone and the scheduler zone differ.  `None` = can't tell
+/// (an unmapped Windows zone name).
+pub(crate) fn zones_differ(os_zone: &str, scheduler_zone: &str) -> Option<bool> {
+    let (os, sched) = (canonical_zone(os_zone), canonical_zone(scheduler_zone));
+    if !os.contains('/') && os != "UTC" {
+        return None;
+    }
+    Some(!os.eq_ignore_ascii_case(sched))
+}
+
+/// The OS timezone name.
+fn os_timezone() -> Option<String> {
+    if let Some(tz) = std::env::var("TZ")
+        .ok()
+        .map(|t| t.trim_start_matches(':').to_string())
+        .filter(|t| t.contains('/') || t == "UTC")
+    {
+        return Some(tz);
+    }
+    #[cfg(windows)]
+    {
+        let mut cmd = std::process::Command::new("tzutil");
+        cmd.arg("/g");
+        crate::apply_no_window(&mut cmd);
+        let out = cmd.output().ok()?;
+        let name = String::from_utf8_lossy(&out.stdout).trim().to_string();
+        (!name.is_empty()).then_some(name)
+    }
+    #[cfg(not(windows))]
+    {
+        // /etc/localtime -> /usr/share/zoneinfo/Asia/Shanghai（macOS 为 /var/db/timezone/zoneinfo/...）
+        if let Ok(target) = std::fs::read_link("/etc/localtime") {
+            let s = target.to_string_lossy().to_string();
+            if let Some((_, zone)) = s.split_once("zoneinfo/") {
+                return Some(zone.to_string());
+            }
+        }
+        std::fs::read_to_string("/etc/timezone")
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    }
+}
+
+#[cfg(unix)]
+fn os_utc_offset_minutes() -> Option<i32> {
+    let now = unsafe { libc::time(std::ptr::null_mut()) };
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&now, &mut tm) }.is_null() {
+        return None;
+    }
+    Some((tm.tm_gmtoff / 60) as i32)
+}
+
+#[cfg(not(unix))]
+fn os_utc_offset_minutes() -> Option<i32> {
+    None
+}
+
+/// `SCHEDULER_TIMEZONE` of the workspace, and where it came from.
+fn scheduler_timezone(workspace_id: Option<&str>) -> (String, &'static str) {
+    workspace_id
+        .map(env_overlay::workspace_env)
+        .unwrap_or_default()
+        .into_iter()
+        .find(|(k, v)| k == "SCHEDULER_TIMEZONE" && !v.is_empty())
+        .map_or((DEFAULT_SCHEDULER_TZ.to_string(), "default"), |(_, v)| {
+            (v, "env")
+        })
+}
+
+/// Turn the measurements into issues.
+pub(crate) fn time_issues(
+    ntp_offset_ms: Result<i64, String>,
+    os_zone: Option<&str>,
+    scheduler_zone: &str,
+) -> Vec<TimeIssue> {
+    let issue = |code: &str, severity: &str, message: String| TimeIssue {
+        code: code.to_string(),
+        severity: severity.to_string(),
+        message,
+    };
+    let mut issues = Vec::new();
+    match ntp_offset_ms {
+        Ok(off) if off.abs() >= SKEW_WARN_MS => issues.push(issue(
+            "CLOCK_SKEW",
+            if off.abs() >= SKEW_ERROR_MS { "error" } else { "warning" },
+            format!(
+                "系统时钟{}了 {} 秒，定时任务会提前或推迟触发；请开启系统的自动同步时间 / system clock is {}s {}",
+                if off > 0 { "慢" } else { "快" },
+                off.abs() / 1000,
+                off.abs() / 1000,
+                if off > 0 { "behind" } else { "ahead" },
+            ),
+        )),
+        Ok(_) => {}
+        Err(e) => issues.push(issue(
+            "NTP_UNREACHABLE",
+            "info",
+            format!("无法连接时间服务器，未能校验系统时钟: {e}"),
+        )),
+    }
+    if let Some(os) = os_zone {
+        if zones_differ(os, scheduler_zone) == Some(true) {
+            issues.push(issue(
+                "TIMEZONE_MISMATCH",
+                "warning",
+                format!(
+                    "调度器时区 SCHEDULER_TIMEZONE={scheduler_zone} 与系统时区 {os} 不同，\
+                     定时任务按 {scheduler_zone} 的时间触发 / scheduler and OS timezones differ"
+                ),
+            ));
+        }
+    }
+    issues
+}
+
+/// Run the checks (blocking: up to a few seconds of NTP round trips).
+pub(crate) fn diagnose(workspace_id: Option<&str>) -> TimeDiagnostics {
+    let (scheduler_tz, source) = scheduler_timezone(workspace_id);
+    let os_tz = os_timezone();
+    let (server, offset) = if net::offline_mode_enabled() {
+        (None, Err("offline mode".to_string()))
+    } else {
+        let mut last = Err("no NTP server configured".to_string());
+        let mut used = None;
+        for server in NTP_SERVERS {
+            last = query_ntp(server);
+            if last.is_ok() {
+                used = Some(server.to_string());
+                break;
+            }
+        }
+        (used, last)
+    };
+    let issues = time_issues(offset.clone(), os_tz.as_deref(), &scheduler_tz);
+    log_to_file(&format!(
+        "[clock] ntp={server:?} offset={offset:?} os_tz={os_tz:?} scheduler_tz={scheduler_tz}"
+    ));
+    TimeDiagnostics {
+        system_time_ms: unix_ms(),
+        ntp_server: server,
+        ntp_offset_ms: offset.ok(),
+        os_timezone: os_tz,
+        os_utc_offset_minutes: os_utc_offset_minutes(),
+        scheduler_timezone: scheduler_tz,
+        scheduler_timezone_source: source.to_string(),
+        issues,
+    }
+}
+
+/// Clock skew and timezone report for `workspaceId` (default: current).
+#[tauri::command]
+pub async fn get_time_diagnostics(workspace_id: Option<String>) -> CmdResult<TimeDiagnostics> {
+    spawn_blocking_result(move || {
+        let ws = workspace_id.or_else(|| read_state_file().current_workspace_id);
+        Ok(diagnose(ws.as_deref()))
+    })
+    .await
+    .map_err(Into::into)
+}
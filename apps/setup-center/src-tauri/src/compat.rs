@@ -0,0 +1,214 @@
+//! Version gate between Setup Center and the openakita package it drives.
+//!
+//! The bridge (`openakita.setup_center.bridge`) and the service API change
+//! together with the desktop; a venv holding a much older or newer
+//! openakita used to fail later with a bridge traceback that said nothing
+//! about versions.  Before a bridge call or a start from that venv, the
+//! installed version (read from its `openakita-<ver>.dist-info`, no Python
+//! spawn) is compared against [`MIN_BACKEND_VERSION`] and this desktop's
+//! own `major.minor`:
+//!
+//! * older than the minimum → `BACKEND_INCOMPATIBLE`, action "upgrade";
+//! * a newer `major.minor` than the desktop → action "downgrade".
+//!
+//! Either way the fix is `openakita==<desktop version>`, which
+//! `fix_backend_compat` installs in one step.  The bundled backend and the
+//! dual-runtime venv ship with the desktop and are not gated; unknown or
+//! dev versions pass.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::errors::{CmdResult, ErrorCode, ErrorPayload};
+use crate::{
+    install_queue, log_to_file, pip_install_blocking, spawn_blocking_result, venv_python_path,
+};
+
+/// Oldest openakita whose bridge commands and service API this crate uses.
+pub(crate) const MIN_BACKEND_VERSION: &str = "1.27.0";
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Compatibility {
+    pub compatible: bool,
+    /// `None` when no openakita is installed in the venv.
+    pub installed: Option<String>,
+    pub min_version: String,
+    pub desktop_version: String,
+    /// "upgrade" | "downgrade" when incompatible.
+    pub action: Option<String>,
+    /// pip spec that fixes it, e.g. `openakita==1.27.32`.
+    pub fix_spec: Option<String>,
+}
+
+static CACHE: Lazy<Mutex<HashMap<String, (Instant, Compatibility)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// `1.2.3`, `v1.2`, `1.2.3rc1`, `1.2.3.dev4+gabc` → `(1, 2, 3)`.
+pub(crate) fn parse_version(v: &str) -> Option<(u64, u64, u64)> {
+    let v = v.trim().trim_start_matches('v');
+    let mut parts = v.split(['.', '-', '+']).map(|p| {
+        let digits: String = p.chars().take_while(char::is_ascii_digit).collect();
+        digits.parse::<u64>().ok()
+    });
+    let major = parts.next()??;
+    let minor = parts.next().flatten().unwrap_or(0);
+    let patch = parts.next().flatten().unwrap_or(0);
+    Some((major, minor, patch))
+}
+
+/// Compare `installed` with the minimum and the desktop version.
+pub(crate) fn evaluate(installed: Option<&str>, min: &str, desktop: &str) -> Compatibility {
+    let parsed = installed.and_then(parse_version);
+    let action = match (parsed, parse_version(min), parse_version(desktop)) {
+        // 开发版（0.0.0-dev）或无法解析的版本号不拦
+        (Some((0, 0, 0)), _, _) | (None, _, _) => None,
+        (Some(v), Some(min), _) if v < min => Some("upgrade"),
+        (Some(v), _, Some(d)) if (v.0, v.1) > (d.0, d.1) => Some("downgrade"),
+        _ => None,
+    };
+    Compatibility {
+        compatible: action.is_none(),
+        installed: installed.map(str::to_string),
+        min_version: min.to_string(),
+        desktop_version: desktop.to_string(),
+        action: action.map(str::to_string),
+        fix_spec: action.map(|_| format!("openakita=={desktop}")),
+    }
+}
+
+/// `site-packages` directories of a venv.
+fn site_packages(venv_dir: &Path) -> Vec<PathBuf> {
+    let win = venv_dir.join("Lib").join("site-packages");
+    let mut out: Vec<PathBuf> = std::fs::read_dir(venv_dir.join("lib"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path().join("site-packages"))
+        .filter(|p| p.is_dir())
+        .collect();
+    if win.is_dir() {
+        out.push(win);
+    }
+    out
+}
+
+/// openakita version installed in `venv_dir`, from its dist-info name.
+pub(crate) fn venv_openakita_version(venv_dir: &str) -> Option<String> {
+    site_packages(Path::new(venv_dir))
+        .into_iter()
+        .find_map(|sp| {
+            std::fs::read_dir(sp).ok()?.flatten().find_map(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                let version = name
+                    .strip_prefix("openakita-")?
+                    .strip_suffix(".dist-info")?;
+                Some(version.to_string())
+            })
+        })
+}
+
+fn check_uncached(venv_dir: &str) -> Compatibility {
+    let installed = venv_openakita_version(venv_dir);
+    evaluate(
+        installed.as_deref(),
+        MIN_BACKEND_VERSION,
+        env!("CARGO_PKG_VERSION"),
+    )
+}
+
+pub(crate) fn check(venv_dir: &str) -> Compatibility {
+    let mut cache = CACHE.lock().unwrap();
+    if let Some((at, c)) = cache.get(venv_dir) {
+        if at.elapsed() < CACHE_TTL {
+            return c.clone();
+        }
+    }
+    let c = check_uncached(venv_dir);
+    cache.insert(venv_dir.to_string(), (Instant::now(), c.clone()));
+    c
+}
+
+/// Forget cached results after openakita was installed or removed.
+pub(crate) fn invalidate() {
+    CACHE.lock().unwrap().clear();
+}
+
+fn message(c: &Compatibility) -> String {
+    let installed = c.installed.as_deref().unwrap_or("?");
+    match c.action.as_deref() {
+        Some("downgrade") => format!(
+            "openakita {installed} 比桌面端 {} 新，请降级到 {}",
+            c.desktop_version,
+            c.fix_spec.as_deref().unwrap_or_default()
+        ),
+        _ => format!(
+            "openakita {installed} 过旧（至少需要 {}），请升级到 {}",
+            c.min_version,
+            c.fix_spec.as_deref().unwrap_or_default()
+        ),
+    }
+}
+
+/// Gate for code about to run openakita from `venv_dir`'s own Python.
+/// Venvs without an interpreter fall back to the bundled runtime and pass.
+pub(crate) fn ensure_compatible(venv_dir: &str) -> Result<(), String> {
+    if !venv_python_path(venv_dir).exists() {
+        return Ok(());
+    }
+    let c = check(venv_dir);
+    if c.compatible {
+        return Ok(());
+    }
+    Err(format!("BACKEND_INCOMPATIBLE|{}", message(&c)))
+}
+
+/// Attach the compatibility report to a `BACKEND_INCOMPATIBLE` error so
+/// the frontend can offer the fix without another round trip.
+pub(crate) fn with_details(mut err: ErrorPayload, venv_dir: &str) -> ErrorPayload {
+    if err.code != ErrorCode::BackendIncompatible {
+        return err;
+    }
+    let report = serde_json::to_value(check(venv_dir)).unwrap_or_default();
+    let mut details = err.details.take().unwrap_or_else(|| serde_json::json!({}));
+    if let Some(obj) = details.as_object_mut() {
+        obj.insert("compatibility".into(), report);
+    }
+    err.with_details(details)
+}
+
+#[tauri::command]
+pub fn check_backend_compat(venv_dir: String) -> Compatibility {
+    invalidate();
+    check(&venv_dir)
+}
+
+/// Install the version this desktop expects into `venv_dir`.
+#[tauri::command]
+pub async fn fix_backend_compat(
+    app: tauri::AppHandle,
+    venv_dir: String,
+    index_url: Option<String>,
+) -> CmdResult<Compatibility> {
+    spawn_blocking_result(move || {
+        let c = check_uncached(&venv_dir);
+        let Some(spec) = c.fix_spec.clone() else {
+            return Ok(c);
+        };
+        let _slot = install_queue::acquire(&app, &venv_dir, "pip", "compat-fix", None)?;
+        log_to_file(&format!(
+            "[compat] {} → {spec} in {venv_dir}",
+            c.installed.as_deref().unwrap_or("?")
+        ));
+        pip_install_blocking(&venv_dir, &spec, index_url, "compat-fix")?;
+        invalidate();
+        Ok(check(&venv_dir))
+    })
+    .await
+    .map_err(Into::into)
+}
@@ -0,0 +1,347 @@
+//! Restart a backend that crashed under Setup Center (opt-in).
+//!
+//! The heartbeat thread only notices a dead backend after three failed
+//! health checks and then retries forever; a backend that crashes on every
+//! start spins there quietly.  With `crashWatch.enabled` in `state.json`
+//! this thread watches the child Setup Center spawned (`MANAGED_CHILD`)
+//! with `try_wait` once a second.  When it exits on its own — not through
+//! a stop, quit or rolling restart, which take the handle first — the
+//! backend is started again after a backoff of `backoffSecs` doubling per
+//! attempt, at most `maxRestarts` times per `windowSecs`:
+//!
+//! * `service_restarted` (`{ workspaceId, attempt, maxRestarts, exitCode,
+//!   backoffMs, pid }`) after each restart;
+//! * `service_crash_loop` (`{ workspaceId, restarts, windowSecs, exitCode,
+//!   lastError }`) when the limit is hit.  Nothing restarts the workspace
+//!   after that (the heartbeat holds off too) until the user starts it.
+//!
+//! Exits found by a status poll first are handed over through [`exited`].
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::ExitStatus;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::errors::CmdResult;
+use crate::{
+    backend_was_manually_stopped, emit_global, launch_env, log_to_file, now_ms, openakita_root_dir,
+    openakita_service_start_impl, read_state_file, remove_heartbeat_file, service_pid_file,
+    supervisor, telemetry, timeline, write_state_file, AUTO_START_IN_PROGRESS,
+    AUTO_START_STARTED_AT_MS, BACKEND_LIFECYCLE_LOCK, MANAGED_CHILD, SHUTDOWN, STATE_FILE_LOCK,
+};
+
+const POLL: Duration = Duration::from_secs(1);
+const MAX_BACKOFF_MS: u64 = 5 * 60 * 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CrashWatchConfig {
+    pub enabled: bool,
+    /// Restarts allowed inside `window_secs` before giving up.
+    pub max_restarts: u32,
+    /// Wait before the first restart; doubles with each further one.
+    pub backoff_secs: u64,
+    pub window_secs: u64,
+}
+
+impl Default for CrashWatchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_restarts: 3,
+            backoff_secs: 5,
+            window_secs: 10 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashWatchState {
+    pub workspace_id: String,
+    /// Restarts inside the current window (epoch ms).
+    pub restarts: Vec<u64>,
+    /// A restart is waiting out its backoff or running.
+    pub restarting: bool,
+    pub crash_loop: bool,
+    pub last_exit_code: Option<i32>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Next {
+    Restart { attempt: u32, delay_ms: u64 },
+    CrashLoop { restarts: u32 },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RestartedEvent {
+    workspace_id: String,
+    attempt: u32,
+    max_restarts: u32,
+    exit_code: Option<i32>,
+    backoff_ms: u64,
+    pid: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CrashLoopEvent {
+    workspace_id: String,
+    restarts: u32,
+    window_secs: u64,
+    exit_code: Option<i32>,
+    last_error: Option<String>,
+}
+
+static STATES: Lazy<Mutex<HashMap<String, CrashWatchState>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+/// Exits noticed elsewhere, waiting for the watch thread.
+static PENDING: Mutex<Vec<(String, Option<i32>)>> = Mutex::new(Vec::new());
+
+pub(crate) fn config() -> CrashWatchConfig {
+    read_state_file().crash_watch.unwrap_or_default()
+}
+
+/// What to do after a crash, given the restarts already made (epoch ms).
+pub(crate) fn next(restarts: &[u64], now_ms: u64, cfg: &CrashWatchConfig) -> Next {
+    let window = Duration::from_secs(cfg.window_secs);
+    let (recent, allowed) =
+        supervisor::breaker(restarts, now_ms, window, cfg.max_restarts as usize);
+    if !allowed {
+        return Next::CrashLoop {
+            restarts: recent.len() as u32,
+        };
+    }
+    let attempt = recent.len() as u32 + 1;
+    let delay_ms = (cfg.backoff_secs * 1000)
+        .saturating_mul(1 << (attempt - 1).min(16))
+        .min(MAX_BACKOFF_MS);
+    Next::Restart { attempt, delay_ms }
+}
+
+fn update(workspace_id: &str, f: impl FnOnce(&mut CrashWatchState)) -> CrashWatchState {
+    let mut states = STATES.lock().unwrap();
+    let state = states
+        .entry(workspace_id.to_string())
+        .or_insert_with(|| CrashWatchState {
+            workspace_id: workspace_id.to_string(),
+            ..Default::default()
+        });
+    f(state);
+    state.clone()
+}
+
+/// The watchdog owns restarts of this workspace right now (waiting out a
+/// backoff, or given up after a crash loop); the heartbeat stays out.
+pub(crate) fn holds(workspace_id: &str) -> bool {
+    config().enabled
+        && STATES
+            .lock()
+            .unwrap()
+            .get(workspace_id)
+            .is_some_and(|s| s.restarting || s.crash_loop)
+}
+
+/// The user started the backend: forget the crash history.
+pub(crate) fn reset(workspace_id: &str) {
+    STATES.lock().unwrap().remove(workspace_id);
+}
+
+/// A status poll found the managed backend exited.
+pub(crate) fn exited(workspace_id: &str, status: Option<ExitStatus>) {
+    if config().enabled {
+        PENDING
+            .lock()
+            .unwrap()
+            .push((workspace_id.to_string(), status.and_then(|s| s.code())));
+    }
+}
+
+/// `try_wait` on the managed child; takes the handle when it has exited.
+fn reap() -> Option<(String, Option<i32>)> {
+    let mut guard = MANAGED_CHILD.lock().unwrap();
+    let mp = guard.as_mut()?;
+    let status = match mp.child.try_wait() {
+        Ok(None) => return None,
+        Ok(status) => status,
+        Err(_) => None,
+    };
+    let mp = guard.take()?;
+    drop(guard);
+    telemetry::report_backend_exit(&mp.workspace_id, status);
+    let _ = std::fs::remove_file(service_pid_file(&mp.workspace_id));
+    remove_heartbeat_file(&mp.workspace_id);
+    Some((mp.workspace_id, status.and_then(|s| s.code())))
+}
+
+fn wanted_down(workspace_id: &str) -> bool {
+    SHUTDOWN.load(Ordering::SeqCst) || backend_was_manually_stopped(workspace_id)
+}
+
+fn start(workspace_id: &str) -> Result<Option<u32>, String> {
+    let _lifecycle_guard = BACKEND_LIFECYCLE_LOCK.lock().unwrap();
+    if wanted_down(workspace_id) {
+        return Err("stopped meanwhile".into());
+    }
+    let venv_dir = launch_env::last_venv_dir(workspace_id).unwrap_or_else(|| {
+        openakita_root_dir()
+            .join("venv")
+            .to_string_lossy()
+            .to_string()
+    });
+    AUTO_START_IN_PROGRESS.store(true, Ordering::SeqCst);
+    AUTO_START_STARTED_AT_MS.store(now_ms(), Ordering::SeqCst);
+    let result = openakita_service_start_impl(venv_dir, workspace_id.to_string());
+    AUTO_START_IN_PROGRESS.store(false, Ordering::SeqCst);
+    AUTO_START_STARTED_AT_MS.store(0, Ordering::SeqCst);
+    result.map(|status| status.pid)
+}
+
+/// Restart `workspace_id` after an unexpected exit until it stays up, the
+/// breaker trips, or someone stops it.
+fn handle_exit(workspace_id: &str, exit_code: Option<i32>) {
+    if wanted_down(workspace_id) {
+        return;
+    }
+    log_to_file(&format!(
+        "[crash-watch] {workspace_id}: backend exited unexpectedly (code {exit_code:?})"
+    ));
+    timeline::record_health(
+        workspace_id,
+        "crashed",
+        Some(format!("exit code {exit_code:?}")),
+    );
+    update(workspace_id, |s| s.last_exit_code = exit_code);
+    loop {
+        let cfg = config();
+        let state = update(workspace_id, |_| {});
+        let (attempt, delay_ms) = match next(&state.restarts, now_ms(), &cfg) {
+            Next::Restart { attempt, delay_ms } => (attempt, delay_ms),
+            Next::CrashLoop { restarts } => {
+                let state = update(workspace_id, |s| {
+                    s.crash_loop = true;
+                    s.restarting = false;
+                });
+                log_to_file(&format!(
+                    "[crash-watch] {workspace_id}: {restarts} restarts in {}s, giving up",
+                    cfg.window_secs
+                ));
+                emit_global(
+                    "service_crash_loop",
+                    CrashLoopEvent {
+                        workspace_id: workspace_id.to_string(),
+                        restarts,
+                        window_secs: cfg.window_secs,
+                        exit_code,
+                        last_error: state.last_error,
+                    },
+                );
+                return;
+            }
+        };
+        update(workspace_id, |s| s.restarting = true);
+        let mut waited = 0;
+        while waited < delay_ms {
+            std::thread::sleep(POLL);
+            waited += POLL.as_millis() as u64;
+            if wanted_down(workspace_id) {
+                update(workspace_id, |s| s.restarting = false);
+                return;
+            }
+        }
+        let result = start(workspace_id);
+        let now = now_ms();
+        let window_start = now.saturating_sub(cfg.window_secs * 1000);
+        update(workspace_id, |s| {
+            s.restarts.retain(|t| *t >= window_start);
+            s.restarts.push(now);
+            s.restarting = false;
+            s.last_error = result.as_ref().err().cloned();
+        });
+        match result {
+            Ok(pid) => {
+                log_to_file(&format!(
+                    "[crash-watch] {workspace_id}: restarted (attempt {attempt}, pid {pid:?})"
+                ));
+                emit_global(
+                    "service_restarted",
+                    RestartedEvent {
+                        workspace_id: workspace_id.to_string(),
+                        attempt,
+                        max_restarts: cfg.max_restarts,
+                        exit_code,
+                        backoff_ms: delay_ms,
+                        pid,
+                    },
+                );
+                return;
+            }
+            Err(e) if wanted_down(workspace_id) => {
+                log_to_file(&format!(
+                    "[crash-watch] {workspace_id}: restart dropped: {e}"
+                ));
+                return;
+            }
+            // 起不来也算一次，继续按退避重试直到熔断
+            Err(e) => log_to_file(&format!(
+                "[crash-watch] {workspace_id}: restart attempt {attempt} failed: {e}"
+            )),
+        }
+    }
+}
+
+/// Start the watch thread (once, from `setup`).
+pub(crate) fn spawn() {
+    std::thread::spawn(|| loop {
+        std::thread::sleep(POLL);
+        if SHUTDOWN.load(Ordering::SeqCst) {
+            return;
+        }
+        if !config().enabled {
+            PENDING.lock().unwrap().clear();
+            continue;
+        }
+        let mut exits: Vec<_> = PENDING.lock().unwrap().drain(..).collect();
+        exits.extend(reap());
+        for (workspace_id, exit_code) in exits {
+            handle_exit(&workspace_id, exit_code);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_crash_watch_config() -> CrashWatchConfig {
+    config()
+}
+
+#[tauri::command]
+pub fn set_crash_watch_config(config: CrashWatchConfig) -> CmdResult<()> {
+    if config.max_restarts == 0 || config.window_secs == 0 {
+        return Err("INVALID_ARGUMENT|maxRestarts 和 windowSecs 必须大于 0".into());
+    }
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    state.crash_watch = Some(config);
+    write_state_file(&state).map_err(Into::into)
+}
+
+/// Crash / restart history of the workspace's backend.
+#[tauri::command]
+pub fn get_crash_watch_state(workspace_id: String) -> CrashWatchState {
+    STATES
+        .lock()
+        .unwrap()
+        .get(&workspace_id)
+        .cloned()
+        .unwrap_or(CrashWatchState {
+            workspace_id,
+            ..Default::default()
+        })
+}
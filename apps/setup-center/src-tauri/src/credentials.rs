@@ -0,0 +1,518 @@
+//! API key health of a workspace's LLM endpoints.
+//!
+//! A key that ran out of credit or was revoked shows up in the backend as
+//! failed inference calls that fall through to the next endpoint, so the
+//! user just sees flaky answers.  `check_endpoint_credentials(workspace_id)`
+//! looks at every endpoint in `data/llm_endpoints.json`, resolves its key
+//! from the workspace env (`api_key_env`) and asks the provider:
+//!
+//! | provider     | request                        | reports                   |
+//! |--------------|--------------------------------|---------------------------|
+//! | OpenRouter   | `GET /api/v1/key`              | usage / limit, expiry     |
+//! | DeepSeek     | `GET /user/balance`            | balance                   |
+//! | Kimi         | `GET /v1/users/me/balance`     | balance                   |
+//! | SiliconFlow  | `GET /v1/user/info`            | balance                   |
+//! | anyone else  | `GET <base>/models`            | key accepted or rejected  |
+//!
+//! (Anthropic and OpenAI only expose quota to admin keys, so theirs are
+//! checked for validity only.)  Results are cached for `cacheSecs` per
+//! endpoint.  Entering a bad state — over `alertAtPercent` of the key's
+//! limit, balance under `lowBalance`, expiring within `expiryWarnDays`,
+//! exhausted or rejected — raises one system notification and a
+//! `credential-alert` event; it is raised again only after the key has
+//! been fine in between.  Keys never leave this module except as a
+//! `sk-…abcd` hint.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::errors::CmdResult;
+use crate::log_query::parse_iso_ms;
+use crate::{
+    emit_if_ui_live, env_overlay, log_to_file, net, notify, now_ms, path_repair, read_state_file,
+    workspace_dir, write_state_file, STATE_FILE_LOCK,
+};
+
+const EVENT: &str = "credential-alert";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+const DAY_MS: u64 = 86_400_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CredentialCheckConfig {
+    /// Alert when a key has used this share of its limit.
+    pub alert_at_percent: u8,
+    /// Alert when a prepaid balance drops below this (provider currency).
+    pub low_balance: f64,
+    pub expiry_warn_days: u64,
+    /// How long a result is reused before the provider is asked again.
+    pub cache_secs: u64,
+}
+
+impl Default for CredentialCheckConfig {
+    fn default() -> Self {
+        Self {
+            alert_at_percent: 90,
+            low_balance: 5.0,
+            expiry_warn_days: 7,
+            cache_secs: 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CredentialStatus {
+    Ok,
+    /// Over the usage threshold or under the low-balance mark.
+    Low,
+    Expiring,
+    Exhausted,
+    /// The provider rejected the key (401 / 403).
+    Invalid,
+    /// `api_key_env` is not set in the workspace env.
+    Missing,
+    /// Could not tell (network error, local endpoint, unexpected answer).
+    Unknown,
+}
+
+impl CredentialStatus {
+    fn alerts(self) -> bool {
+        matches!(
+            self,
+            Self::Low | Self::Expiring | Self::Exhausted | Self::Invalid
+        )
+    }
+}
+
+/// What a provider told about a key.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct KeyUsage {
+    pub balance: Option<f64>,
+    pub currency: Option<String>,
+    pub used: Option<f64>,
+    pub limit: Option<f64>,
+    pub expires_at_ms: Option<u64>,
+    /// `false` when the provider says the key cannot be used any more.
+    pub available: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialHealth {
+    pub endpoint: String,
+    pub provider: String,
+    pub api_key_env: Option<String>,
+    pub key_hint: Option<String>,
+    pub status: CredentialStatus,
+    pub balance: Option<f64>,
+    pub currency: Option<String>,
+    pub used: Option<f64>,
+    pub limit: Option<f64>,
+    pub used_percent: Option<f64>,
+    pub expires_at_ms: Option<u64>,
+    pub message: Option<String>,
+    pub checked_at_ms: u64,
+    /// Served from the cache rather than asked just now.
+    pub cached: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AlertEvent {
+    workspace_id: String,
+    endpoint: String,
+    status: CredentialStatus,
+    message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Probe {
+    OpenRouter,
+    DeepSeek,
+    Moonshot,
+    SiliconFlow,
+    /// Only whether the key is accepted.
+    Models {
+        anthropic: bool,
+    },
+}
+
+/// workspace + endpoint → last result
+static CACHE: Lazy<Mutex<HashMap<String, CredentialHealth>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+/// workspace + endpoint → status last alerted
+static ALERTED: Lazy<Mutex<HashMap<String, CredentialStatus>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub(crate) fn config() -> CredentialCheckConfig {
+    read_state_file().credential_check.unwrap_or_default()
+}
+
+fn host_of(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, r)| r);
+    rest.split(['/', ':']).next().unwrap_or("")
+}
+
+/// How to check a key for an endpoint at `base_url`, and the URL to ask.
+pub(crate) fn probe_for(base_url: &str, api_type: &str) -> (Probe, String) {
+    let base = base_url.trim_end_matches('/');
+    let host = host_of(base).to_ascii_lowercase();
+    let origin = base
+        .find("://")
+        .and_then(|i| base[i + 3..].find('/').map(|j| &base[..i + 3 + j]))
+        .unwrap_or(base);
+    match host.as_str() {
+        "openrouter.ai" => (Probe::OpenRouter, format!("{origin}/api/v1/key")),
+        "api.deepseek.com" => (Probe::DeepSeek, format!("{origin}/user/balance")),
+        "api.moonshot.cn" | "api.moonshot.ai" => {
+            (Probe::Moonshot, format!("{origin}/v1/users/me/balance"))
+        }
+        "api.siliconflow.cn" | "api.siliconflow.com" => {
+            (Probe::SiliconFlow, format!("{origin}/v1/user/info"))
+        }
+        _ if api_type == "anthropic" => {
+            let v1 = if base.ends_with("/v1") {
+                base.to_string()
+            } else {
+                format!("{base}/v1")
+            };
+            (Probe::Models { anthropic: true }, format!("{v1}/models"))
+        }
+        _ => (Probe::Models { anthropic: false }, format!("{base}/models")),
+    }
+}
+
+/// Numbers come as JSON numbers or strings depending on the provider.
+fn num(v: Option<&Value>) -> Option<f64> {
+    match v? {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Key facts from a provider's answer to its `probe` request.
+pub(crate) fn parse_usage(probe: Probe, body: &Value) -> KeyUsage {
+    match probe {
+        Probe::OpenRouter => {
+            let d = &body["data"];
+            KeyUsage {
+                used: num(d.get("usage")),
+                limit: num(d.get("limit")),
+                currency: Some("USD".into()),
+                expires_at_ms: d["expires_at"].as_str().and_then(parse_iso_ms),
+                available: num(d.get("limit_remaining")).map(|r| r > 0.0),
+                ..Default::default()
+            }
+        }
+        Probe::DeepSeek => {
+            let info = body["balance_infos"]
+                .as_array()
+                .and_then(|a| a.first())
+                .cloned()
+                .unwrap_or(Value::Null);
+            KeyUsage {
+                balance: num(info.get("total_balance")),
+                currency: info["currency"].as_str().map(str::to_string),
+                available: body["is_available"].as_bool(),
+                ..Default::default()
+            }
+        }
+        Probe::Moonshot => KeyUsage {
+            balance: num(body["data"].get("available_balance")),
+            currency: Some("CNY".into()),
+            ..Default::default()
+        },
+        Probe::SiliconFlow => KeyUsage {
+            balance: num(body["data"].get("totalBalance")),
+            currency: Some("CNY".into()),
+            ..Default::default()
+        },
+        Probe::Models { .. } => KeyUsage::default(),
+    }
+}
+
+/// Status of a key the provider accepted, with the reason when not ok.
+pub(crate) fn assess(
+    u: &KeyUsage,
+    cfg: &CredentialCheckConfig,
+    now_ms: u64,
+) -> (CredentialStatus, Option<String>) {
+    let currency = u.currency.as_deref().unwrap_or("");
+    if let Some(exp) = u.expires_at_ms {
+        if exp <= now_ms {
+            return (CredentialStatus::Exhausted, Some("Key 已过期".into()));
+        }
+    }
+    if u.available == Some(false) {
+        return (CredentialStatus::Exhausted, Some("额度已用完".into()));
+    }
+    if let Some(b) = u.balance {
+        if b <= 0.0 {
+            return (
+                CredentialStatus::Exhausted,
+                Some(format!("余额已用完（{b:.2} {currency}）")),
+            );
+        }
+    }
+    if let (Some(used), Some(limit)) = (u.used, u.limit.filter(|l| *l > 0.0)) {
+        let percent = used / limit * 100.0;
+        if percent >= 100.0 {
+            return (
+                CredentialStatus::Exhausted,
+                Some(format!("额度已用完（{used:.2} / {limit:.2} {currency}）")),
+            );
+        }
+        if percent >= f64::from(cfg.alert_at_percent) {
+            return (
+                CredentialStatus::Low,
+                Some(format!(
+                    "已用额度的 {percent:.0}%（{used:.2} / {limit:.2} {currency}）"
+                )),
+            );
+        }
+    }
+    if let Some(b) = u.balance.filter(|b| *b < cfg.low_balance) {
+        return (
+            CredentialStatus::Low,
+            Some(format!("余额仅剩 {b:.2} {currency}")),
+        );
+    }
+    if let Some(exp) = u.expires_at_ms {
+        let days = (exp - now_ms).div_ceil(DAY_MS);
+        if days <= cfg.expiry_warn_days {
+            return (
+                CredentialStatus::Expiring,
+                Some(format!("Key 将在 {days} 天内过期")),
+            );
+        }
+    }
+    (CredentialStatus::Ok, None)
+}
+
+fn key_hint(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    let tail: String = chars[chars.len().saturating_sub(4)..].iter().collect();
+    let head: String = chars.iter().take(3.min(chars.len() / 4)).collect();
+    format!("{head}…{tail}")
+}
+
+/// `(name, provider, api_type, base_url, api_key_env)` of each endpoint.
+fn endpoints(workspace_id: &str) -> Vec<(String, String, String, String, Option<String>)> {
+    let path = path_repair::endpoints_config_path(&workspace_dir(workspace_id));
+    let config: Value = fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or(Value::Null);
+    let s = |ep: &Value, k: &str| ep.get(k).and_then(Value::as_str).map(str::to_string);
+    ["endpoints", "compiler_endpoints"]
+        .iter()
+        .filter_map(|k| config.get(*k).and_then(Value::as_array))
+        .flatten()
+        .filter_map(|ep| {
+            Some((
+                s(ep, "name")?,
+                s(ep, "provider").unwrap_or_default(),
+                s(ep, "api_type").unwrap_or_default(),
+                s(ep, "base_url")?,
+                s(ep, "api_key_env").filter(|e| !e.is_empty()),
+            ))
+        })
+        .collect()
+}
+
+async fn ask(probe: Probe, url: &str, key: &str) -> Result<(u16, Value), String> {
+    let mut req = net::http_client().get(url).timeout(REQUEST_TIMEOUT);
+    req = match probe {
+        Probe::Models { anthropic: true } => req
+            .header("x-api-key", key)
+            .header("anthropic-version", "2023-06-01"),
+        _ => req.bearer_auth(key),
+    };
+    let resp = req
+        .send()
+        .await
+        .map_err(|e| format!("请求失败: {}", e.without_url()))?;
+    let status = resp.status().as_u16();
+    let body = resp.json::<Value>().await.unwrap_or(Value::Null);
+    Ok((status, body))
+}
+
+async fn check_one(
+    name: String,
+    provider: String,
+    api_type: String,
+    base_url: String,
+    key_env: Option<String>,
+    key: Option<String>,
+    cfg: &CredentialCheckConfig,
+) -> CredentialHealth {
+    let mut health = CredentialHealth {
+        endpoint: name,
+        provider,
+        api_key_env: key_env,
+        key_hint: key.as_deref().map(key_hint),
+        status: CredentialStatus::Unknown,
+        balance: None,
+        currency: None,
+        used: None,
+        limit: None,
+        used_percent: None,
+        expires_at_ms: None,
+        message: None,
+        checked_at_ms: now_ms(),
+        cached: false,
+    };
+    if net::is_local_url(&base_url) {
+        health.message = Some("本地端点，不检查".into());
+        return health;
+    }
+    let Some(key) = key else {
+        health.status = CredentialStatus::Missing;
+        health.message = Some(match &health.api_key_env {
+            Some(env) => format!("工作区环境变量里没有 {env}"),
+            None => "端点未配置 api_key_env".into(),
+        });
+        return health;
+    };
+    let (probe, url) = probe_for(&base_url, &api_type);
+    let (code, body) = match ask(probe, &url, &key).await {
+        Ok(r) => r,
+        Err(e) => {
+            health.message = Some(e);
+            return health;
+        }
+    };
+    match code {
+        200..=299 => {
+            let usage = parse_usage(probe, &body);
+            let (status, message) = assess(&usage, cfg, health.checked_at_ms);
+            health.status = status;
+            health.message = message;
+            health.used_percent = match (usage.used, usage.limit) {
+                (Some(u), Some(l)) if l > 0.0 => Some(u / l * 100.0),
+                _ => None,
+            };
+            health.balance = usage.balance;
+            health.currency = usage.currency;
+            health.used = usage.used;
+            health.limit = usage.limit;
+            health.expires_at_ms = usage.expires_at_ms;
+        }
+        401 | 403 => {
+            health.status = CredentialStatus::Invalid;
+            health.message = Some(format!("Key 被拒绝（HTTP {code}），可能已失效或被吊销"));
+        }
+        402 => {
+            health.status = CredentialStatus::Exhausted;
+            health.message = Some("余额不足（HTTP 402）".into());
+        }
+        // 有的兼容网关没有 /models，说明不了 key 的好坏
+        _ => health.message = Some(format!("无法判断（HTTP {code}）")),
+    }
+    health
+}
+
+fn alert(app: &tauri::AppHandle, workspace_id: &str, h: &CredentialHealth) {
+    let key = format!("{workspace_id}\n{}", h.endpoint);
+    {
+        let mut alerted = ALERTED.lock().unwrap();
+        if !h.status.alerts() {
+            // 恢复正常后下次出问题再提醒
+            if h.status == CredentialStatus::Ok {
+                alerted.remove(&key);
+            }
+            return;
+        }
+        if alerted.get(&key) == Some(&h.status) {
+            return;
+        }
+        alerted.insert(key, h.status);
+    }
+    let message = h.message.clone().unwrap_or_default();
+    log_to_file(&format!(
+        "[credentials] {workspace_id}/{}: {:?} {message}",
+        h.endpoint, h.status
+    ));
+    notify::send(
+        app,
+        "OpenAkita",
+        &format!("端点 {} 的 API Key：{message}", h.endpoint),
+    );
+    emit_if_ui_live(
+        app,
+        EVENT,
+        AlertEvent {
+            workspace_id: workspace_id.to_string(),
+            endpoint: h.endpoint.clone(),
+            status: h.status,
+            message,
+        },
+    );
+}
+
+/// Key health of every endpoint of the workspace.  Results younger than
+/// `cacheSecs` are reused unless `refresh` is set.
+#[tauri::command]
+pub async fn check_endpoint_credentials(
+    app: tauri::AppHandle,
+    workspace_id: String,
+    refresh: Option<bool>,
+) -> CmdResult<Vec<CredentialHealth>> {
+    if !workspace_dir(&workspace_id).is_dir() {
+        return Err(format!("NOT_FOUND|工作区不存在: {workspace_id}").into());
+    }
+    let cfg = config();
+    let refresh = refresh.unwrap_or(false);
+    let mut out = Vec::new();
+    for (name, provider, api_type, base_url, key_env) in endpoints(&workspace_id) {
+        let cache_key = format!("{workspace_id}\n{name}");
+        let cached = CACHE.lock().unwrap().get(&cache_key).cloned();
+        if let Some(mut h) = cached.filter(|h| {
+            !refresh && now_ms().saturating_sub(h.checked_at_ms) < cfg.cache_secs * 1000
+        }) {
+            h.cached = true;
+            out.push(h);
+            continue;
+        }
+        let key = key_env
+            .as_deref()
+            .and_then(|env| env_overlay::workspace_var(&workspace_id, env))
+            .filter(|k| !k.trim().is_empty());
+        if key.is_some() && !net::is_local_url(&base_url) {
+            net::ensure_online("API Key 检查")?;
+        }
+        let health = check_one(name, provider, api_type, base_url, key_env, key, &cfg).await;
+        alert(&app, &workspace_id, &health);
+        CACHE.lock().unwrap().insert(cache_key, health.clone());
+        out.push(health);
+    }
+    Ok(out)
+}
+
+#[tauri::command]
+pub fn get_credential_check_config() -> CredentialCheckConfig {
+    config()
+}
+
+#[tauri::command]
+pub fn set_credential_check_config(config: CredentialCheckConfig) -> CmdResult<()> {
+    if config.alert_at_percent == 0 || config.alert_at_percent > 100 {
+        return Err("INVALID_ARGUMENT|alertAtPercent 需在 1-100 之间".into());
+    }
+    if config.low_balance < 0.0 {
+        return Err("INVALID_ARGUMENT|lowBalance 不能为负数".into());
+    }
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    state.credential_check = Some(config);
+    write_state_file(&state).map_err(Into::into)
+}
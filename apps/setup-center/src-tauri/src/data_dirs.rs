@@ -0,0 +1,602 @@
+//! Per-workspace locations for `data/`, `logs/` and the cache.
+//!
+//! A workspace's `data/` grows to many GB (memory database, generated
+//! media, traces) and is often better kept on a big secondary disk than on
+//! the SSD holding the home directory.  `WorkspaceMeta.dirOverrides`
+//! records where each of these lives instead of its default:
+//!
+//! | kind    | default                                                   |
+//! |---------|-----------------------------------------------------------|
+//! | `data`  | `workspaces/<id>/data`                                    |
+//! | `logs`  | `workspaces/<id>/logs`                                    |
+//! | `cache` | `cache/` (this workspace's `sessions/<id>`, `skills/<id>`) |
+//!
+//! The backend and most of the desktop address `data/` and `logs/` relative
+//! to the workspace, so a moved directory is reached through a link left in
+//! its place (a symlink; a directory junction on Windows, which needs no
+//! admin rights).  Start, scaffold, the log readers and backups keep working
+//! unchanged; [`ensure_links`] (run by the scaffold on every start) puts a
+//! missing link back — migrating the data root copies no links — and fails
+//! with a clear message while the target disk is not mounted.  Workspace
+//! file reads / writes resolve through [`split_linked`] so the link does
+//! not trip the path-escape check.  The cache is only used by Setup Center,
+//! which asks [`cache_root`].
+//!
+//! Moving is guided: `preflight_workspace_dir_move` reports size, free
+//! space and whether the move can go ahead; `move_workspace_dir` (backend
+//! stopped) moves the content — a rename on the same volume, otherwise a
+//! copy that is verified before the source is deleted, with
+//! `workspace-dir-move-progress` events — then fixes the link and records
+//! the location.  No target moves the
+//! directory back to its default.  Deleting a workspace leaves a moved
+//! directory where it is.
+
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::errors::CmdResult;
+use crate::{
+    available_space_mb, comparable_path, dir_size_bytes, emit_global, force_remove_dir,
+    is_pid_file_valid, is_safe_openakita_data_root, log_to_file, observer, openakita_root_dir,
+    read_pid_file, read_state_file, spawn_blocking_result, workspace_dir, write_state_file,
+    BACKEND_LIFECYCLE_LOCK, STATE_FILE_LOCK,
+};
+
+/// Per-workspace caches under the cache root.
+const CACHE_SUBDIRS: [&str; 2] = ["sessions", "skills"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DirKind {
+    Data,
+    Logs,
+    Cache,
+}
+
+impl DirKind {
+    /// Kinds living inside the workspace, reached through a link once moved.
+    const LINKED: [DirKind; 2] = [DirKind::Data, DirKind::Logs];
+
+    fn name(self) -> &'static str {
+        match self {
+            DirKind::Data => "data",
+            DirKind::Logs => "logs",
+            DirKind::Cache => "cache",
+        }
+    }
+}
+
+/// Absolute paths; `None` keeps the default location.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DirOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logs: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<String>,
+}
+
+impl DirOverrides {
+    fn get(&self, kind: DirKind) -> Option<&str> {
+        match kind {
+            DirKind::Data => self.data.as_deref(),
+            DirKind::Logs => self.logs.as_deref(),
+            DirKind::Cache => self.cache.as_deref(),
+        }
+        .filter(|p| !p.is_empty())
+    }
+
+    fn set(&mut self, kind: DirKind, path: Option<String>) {
+        match kind {
+            DirKind::Data => self.data = path,
+            DirKind::Logs => self.logs = path,
+            DirKind::Cache => self.cache = path,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceDir {
+    pub kind: DirKind,
+    pub path: String,
+    pub moved: bool,
+    /// The directory (or its disk) is reachable.
+    pub available: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirMovePreflight {
+    pub workspace_id: String,
+    pub kind: DirKind,
+    pub source_path: String,
+    pub target_path: String,
+    pub size_bytes: u64,
+    pub target_free_mb: f64,
+    pub can_move: bool,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirMoveResult {
+    pub workspace_id: String,
+    pub kind: DirKind,
+    pub path: String,
+    pub moved_bytes: u64,
+    /// Same volume: the directory was renamed, nothing was copied.
+    pub renamed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MoveProgress {
+    workspace_id: String,
+    kind: DirKind,
+    copied_bytes: u64,
+    total_bytes: u64,
+}
+
+pub(crate) fn overrides_of(workspace_id: &str) -> DirOverrides {
+    read_state_file()
+        .workspaces
+        .into_iter()
+        .find(|w| w.id == workspace_id)
+        .and_then(|w| w.dir_overrides)
+        .unwrap_or_default()
+}
+
+fn default_dir(workspace_id: &str, kind: DirKind) -> PathBuf {
+    match kind {
+        DirKind::Cache => openakita_root_dir().join("cache"),
+        _ => workspace_dir(workspace_id).join(kind.name()),
+    }
+}
+
+fn dir_of(workspace_id: &str, kind: DirKind) -> PathBuf {
+    overrides_of(workspace_id)
+        .get(kind)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| default_dir(workspace_id, kind))
+}
+
+/// Root holding the workspace's `sessions/<id>` and `skills/<id>` caches.
+pub(crate) fn cache_root(workspace_id: &str) -> PathBuf {
+    dir_of(workspace_id, DirKind::Cache)
+}
+
+/// `relative` inside a moved `data/` or `logs/`: the real directory and
+/// the rest of the path.
+pub(crate) fn link_target_rel(
+    relative: &str,
+    overrides: &DirOverrides,
+) -> Option<(PathBuf, String)> {
+    let mut parts = Path::new(relative).components();
+    let first = parts.next()?.as_os_str().to_str()?;
+    let kind = DirKind::LINKED.into_iter().find(|k| k.name() == first)?;
+    let dir = overrides.get(kind)?;
+    Some((
+        PathBuf::from(dir),
+        parts.as_path().to_string_lossy().to_string(),
+    ))
+}
+
+pub(crate) fn split_linked(workspace_id: &str, relative: &str) -> Option<(PathBuf, String)> {
+    link_target_rel(relative, &overrides_of(workspace_id))
+}
+
+fn is_link(path: &Path) -> bool {
+    // 目录联接（junction）在 Windows 上同样报告为 symlink
+    fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink())
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    let canon = |p: &Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+    comparable_path(&canon(a)) == comparable_path(&canon(b))
+}
+
+#[cfg(unix)]
+pub(crate) fn create_link(target: &Path, link: &Path) -> Result<(), String> {
+    std::os::unix::fs::symlink(target, link)
+        .map_err(|e| format!("link {} -> {}: {e}", link.display(), target.display()))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn create_link(target: &Path, link: &Path) -> Result<(), String> {
+    use crate::apply_no_window;
+    use std::process::Command;
+
+    // 目录联接（junction）不需要管理员权限
+    let mut cmd = Command::new("cmd");
+    cmd.args(["/C", "mklink", "/J"]).arg(link).arg(target);
+    apply_no_window(&mut cmd);
+    let out = cmd.output().map_err(|e| format!("mklink: {e}"))?;
+    if out.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "mklink /J {} {}: {}",
+            link.display(),
+            target.display(),
+            String::from_utf8_lossy(&out.stderr).trim()
+        ))
+    }
+}
+
+fn remove_link(link: &Path) -> Result<(), String> {
+    // Unix 的 symlink 是文件，Windows 的 junction 按目录删除（都不会动到目标）
+    fs::remove_file(link)
+        .or_else(|_| fs::remove_dir(link))
+        .map_err(|e| format!("remove link {}: {e}", link.display()))
+}
+
+/// Put back the links of moved directories of the workspace at `ws_dir`
+/// (no-op for directories that were not moved).  Fails while a target is
+/// unreachable, or when a plain directory took the link's place.
+pub(crate) fn ensure_links(ws_dir: &Path) -> Result<(), String> {
+    let Some(overrides) = read_state_file()
+        .workspaces
+        .into_iter()
+        .find(|w| workspace_dir(&w.id) == ws_dir)
+        .and_then(|w| w.dir_overrides)
+    else {
+        return Ok(());
+    };
+    for kind in DirKind::LINKED {
+        let Some(target) = overrides.get(kind).map(PathBuf::from) else {
+            continue;
+        };
+        if !target.is_dir() {
+            return Err(format!(
+                "NOT_FOUND|工作区的 {} 目录已移到 {}，但该位置当前不可用（磁盘未连接？）",
+                kind.name(),
+                target.display()
+            ));
+        }
+        let link = ws_dir.join(kind.name());
+        if is_link(&link) {
+            if same_dir(&link, &target) {
+                continue;
+            }
+            remove_link(&link)?;
+        } else if link.exists() {
+            // 链接丢失期间被建成了普通目录：空的直接换回链接，否则交给用户处理
+            if fs::remove_dir(&link).is_err() {
+                return Err(format!(
+                    "ALREADY_EXISTS|{} 应指向 {}，但现在是一个非空的普通目录；请先把其中内容移到目标位置",
+                    link.display(),
+                    target.display()
+                ));
+            }
+        }
+        create_link(&target, &link)?;
+        log_to_file(&format!(
+            "[data_dirs] linked {} -> {}",
+            link.display(),
+            target.display()
+        ));
+    }
+    Ok(())
+}
+
+fn is_empty_dir(path: &Path) -> bool {
+    fs::read_dir(path).is_ok_and(|mut d| d.next().is_none())
+}
+
+/// (source, target) directory pairs a move of `kind` touches.
+fn move_pairs(
+    workspace_id: &str,
+    kind: DirKind,
+    source: &Path,
+    target: &Path,
+) -> Vec<(PathBuf, PathBuf)> {
+    match kind {
+        DirKind::Cache => CACHE_SUBDIRS
+            .iter()
+            .map(|sub| {
+                (
+                    source.join(sub).join(workspace_id),
+                    target.join(sub).join(workspace_id),
+                )
+            })
+            .collect(),
+        _ => vec![(source.to_path_buf(), target.to_path_buf())],
+    }
+}
+
+fn preflight(
+    workspace_id: &str,
+    kind: DirKind,
+    target_path: Option<&str>,
+) -> Result<DirMovePreflight, String> {
+    if !read_state_file()
+        .workspaces
+        .iter()
+        .any(|w| w.id == workspace_id)
+    {
+        return Err(format!("NOT_FOUND|工作区不存在: {workspace_id}"));
+    }
+    let default = default_dir(workspace_id, kind);
+    let source = dir_of(workspace_id, kind);
+    let target = match target_path.map(str::trim).filter(|p| !p.is_empty()) {
+        Some(p) => {
+            let p = PathBuf::from(p);
+            if !p.is_absolute() {
+                return Err("INVALID_ARGUMENT|请使用绝对路径".into());
+            }
+            p
+        }
+        None => default.clone(),
+    };
+    let pairs = move_pairs(workspace_id, kind, &source, &target);
+    let size_bytes: u64 = pairs.iter().map(|(s, _)| dir_size_bytes(s)).sum();
+    let free_path = target
+        .ancestors()
+        .find(|p| p.exists())
+        .unwrap_or(&target)
+        .to_path_buf();
+    let target_free_mb = available_space_mb(&free_path);
+    let size_mb = size_bytes as f64 / 1024.0 / 1024.0;
+
+    let ws_dir = workspace_dir(workspace_id);
+    let inside = |a: &Path, b: &Path| comparable_path(a).starts_with(&comparable_path(b));
+    // 不解析链接：移回默认位置时，工作区里的链接正指向当前位置
+    let reason = if comparable_path(&source) == comparable_path(&target) {
+        Some("目标与当前位置相同".to_string())
+    } else if target != default && !is_safe_openakita_data_root(&target) {
+        Some("不能放在磁盘根目录、用户主目录或系统常用目录，请使用专用目录".into())
+    } else if target != default && kind != DirKind::Cache && inside(&target, &ws_dir) {
+        Some("目标位于工作区内部".into())
+    } else if inside(&target, &source) || inside(&source, &target) {
+        Some("目标与当前位置互相包含".into())
+    } else if pairs
+        .iter()
+        .any(|(_, t)| t.exists() && !is_link(t) && !is_empty_dir(t))
+    {
+        Some("目标目录已存在且不为空".into())
+    } else if target_free_mb <= size_mb * 1.1 + 100.0 {
+        Some(format!(
+            "目标磁盘空间不足（需要 {:.0} MB，可用 {:.0} MB）",
+            size_mb * 1.1,
+            target_free_mb
+        ))
+    } else {
+        None
+    };
+    Ok(DirMovePreflight {
+        workspace_id: workspace_id.to_string(),
+        kind,
+        source_path: source.to_string_lossy().to_string(),
+        target_path: target.to_string_lossy().to_string(),
+        size_bytes,
+        target_free_mb,
+        can_move: reason.is_none(),
+        reason: reason.unwrap_or_else(|| "可以移动".into()),
+    })
+}
+
+fn copy_tree(
+    src: &Path,
+    dst: &Path,
+    copied: &mut u64,
+    on_file: &dyn Fn(u64),
+) -> Result<(), String> {
+    fs::create_dir_all(dst).map_err(|e| format!("create dir {}: {e}", dst.display()))?;
+    let entries = fs::read_dir(src).map_err(|e| format!("read dir {}: {e}", src.display()))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("read dir {}: {e}", src.display()))?;
+        let (from, to) = (entry.path(), dst.join(entry.file_name()));
+        let ft = entry
+            .file_type()
+            .map_err(|e| format!("stat {}: {e}", from.display()))?;
+        if ft.is_symlink() {
+            log_to_file(&format!("[data_dirs] skipped link {}", from.display()));
+        } else if ft.is_dir() {
+            copy_tree(&from, &to, copied, on_file)?;
+        } else {
+            *copied += fs::copy(&from, &to)
+                .map_err(|e| format!("copy {} -> {}: {e}", from.display(), to.display()))?;
+            on_file(*copied);
+        }
+    }
+    Ok(())
+}
+
+/// Move the content of `src` to `dst` (absent or empty).  Returns (bytes,
+/// renamed).  A failed copy leaves `src` untouched and removes `dst`.
+pub(crate) fn move_content(
+    src: &Path,
+    dst: &Path,
+    on_progress: &dyn Fn(u64, u64),
+) -> Result<(u64, bool), String> {
+    if dst.exists() {
+        fs::remove_dir(dst).map_err(|e| format!("目标目录不为空 {}: {e}", dst.display()))?;
+    }
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("create dir {}: {e}", parent.display()))?;
+    }
+    if !src.exists() {
+        fs::create_dir_all(dst).map_err(|e| format!("create dir {}: {e}", dst.display()))?;
+        return Ok((0, false));
+    }
+    let total = dir_size_bytes(src);
+    if fs::rename(src, dst).is_ok() {
+        on_progress(total, total);
+        return Ok((total, true));
+    }
+    // 跨卷：复制、核对大小，再删源目录
+    let mut copied = 0;
+    let result = copy_tree(src, dst, &mut copied, &|done| on_progress(done, total));
+    let result = result.and_then(|_| {
+        let written = dir_size_bytes(dst);
+        if written == total {
+            Ok(())
+        } else {
+            Err(format!("复制后大小不一致：{written} / {total} 字节"))
+        }
+    });
+    if let Err(e) = result {
+        let _ = force_remove_dir(dst);
+        return Err(e);
+    }
+    if let Err(e) = force_remove_dir(src) {
+        log_to_file(&format!(
+            "[data_dirs] copied but could not remove {}: {e}",
+            src.display()
+        ));
+    }
+    Ok((total, false))
+}
+
+fn record(workspace_id: &str, kind: DirKind, path: Option<String>) -> Result<(), String> {
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    let meta = state
+        .workspaces
+        .iter_mut()
+        .find(|w| w.id == workspace_id)
+        .ok_or_else(|| format!("NOT_FOUND|工作区不存在: {workspace_id}"))?;
+    let mut overrides = meta.dir_overrides.take().unwrap_or_default();
+    overrides.set(kind, path);
+    meta.dir_overrides = (!overrides.is_empty()).then_some(overrides);
+    write_state_file(&state)
+}
+
+fn move_dir(
+    workspace_id: &str,
+    kind: DirKind,
+    target_path: Option<&str>,
+) -> Result<DirMoveResult, String> {
+    let _lifecycle_guard = BACKEND_LIFECYCLE_LOCK.lock().unwrap();
+    if read_pid_file(workspace_id)
+        .as_ref()
+        .is_some_and(is_pid_file_valid)
+    {
+        return Err("BACKEND_RUNNING|请先停止该工作区的后端再移动目录".into());
+    }
+    let plan = preflight(workspace_id, kind, target_path)?;
+    if !plan.can_move {
+        return Err(format!("INVALID_ARGUMENT|{}", plan.reason));
+    }
+    let default = default_dir(workspace_id, kind);
+    let (source, target) = (
+        PathBuf::from(&plan.source_path),
+        PathBuf::from(&plan.target_path),
+    );
+    log_to_file(&format!(
+        "[data_dirs] moving {} of {workspace_id}: {} -> {} ({} bytes)",
+        kind.name(),
+        source.display(),
+        target.display(),
+        plan.size_bytes
+    ));
+    let last_pct = Cell::new(u64::MAX);
+    let emit = |copied: u64, total: u64| {
+        let pct = (copied * 100).checked_div(total).unwrap_or(100);
+        if last_pct.replace(pct) == pct {
+            return;
+        }
+        emit_global(
+            "workspace-dir-move-progress",
+            MoveProgress {
+                workspace_id: workspace_id.to_string(),
+                kind,
+                copied_bytes: copied,
+                total_bytes: total,
+            },
+        );
+    };
+
+    // 工作区里的链接先拿掉，目录本身才能搬进去 / 搬出来
+    let linked = kind != DirKind::Cache && is_link(&default);
+    if linked {
+        remove_link(&default)?;
+    }
+    let mut moved_bytes = 0;
+    let mut renamed = true;
+    for (from, to) in move_pairs(workspace_id, kind, &source, &target) {
+        match move_content(&from, &to, &emit) {
+            Ok((bytes, r)) => {
+                moved_bytes += bytes;
+                renamed &= r;
+            }
+            Err(e) => {
+                if linked {
+                    let _ = create_link(&source, &default);
+                }
+                return Err(e);
+            }
+        }
+    }
+    let moved = target != default;
+    record(
+        workspace_id,
+        kind,
+        moved.then(|| target.to_string_lossy().to_string()),
+    )?;
+    if moved && kind != DirKind::Cache {
+        create_link(&target, &default)?;
+    }
+    Ok(DirMoveResult {
+        workspace_id: workspace_id.to_string(),
+        kind,
+        path: plan.target_path,
+        moved_bytes,
+        renamed,
+    })
+}
+
+/// Where the workspace's `data/`, `logs/` and cache currently live.
+#[tauri::command]
+pub fn get_workspace_dirs(workspace_id: String) -> Vec<WorkspaceDir> {
+    let overrides = overrides_of(&workspace_id);
+    [DirKind::Data, DirKind::Logs, DirKind::Cache]
+        .into_iter()
+        .map(|kind| {
+            let path = dir_of(&workspace_id, kind);
+            WorkspaceDir {
+                kind,
+                available: overrides.get(kind).is_none() || path.is_dir(),
+                path: path.to_string_lossy().to_string(),
+                moved: overrides.get(kind).is_some(),
+            }
+        })
+        .collect()
+}
+
+/// Check a move of `kind` to `target_path` (default location when absent).
+#[tauri::command]
+pub async fn preflight_workspace_dir_move(
+    workspace_id: String,
+    kind: DirKind,
+    target_path: Option<String>,
+) -> CmdResult<DirMovePreflight> {
+    spawn_blocking_result(move || preflight(&workspace_id, kind, target_path.as_deref()))
+        .await
+        .map_err(Into::into)
+}
+
+/// Move `kind` to `target_path` (back to the default when absent).  The
+/// backend must be stopped.
+#[tauri::command]
+pub async fn move_workspace_dir(
+    workspace_id: String,
+    kind: DirKind,
+    target_path: Option<String>,
+) -> CmdResult<DirMoveResult> {
+    observer::ensure_writable(&workspace_id)?;
+    spawn_blocking_result(move || move_dir(&workspace_id, kind, target_path.as_deref()))
+        .await
+        .map_err(Into::into)
+}
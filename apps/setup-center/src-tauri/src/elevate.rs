@@ -0,0 +1,424 @@
+//! Elevation helper for privileged operations.
+//!
+//! A few repairs cannot be done as the desktop user: taking back a runtime
+//! directory that an installer / antivirus left owned by an administrator, or
+//! ending a backend process that was started from another session.  The
+//! frontend asks for one of a fixed set of [`ElevatedOperation`]s via
+//! `run_elevated`; nothing else can be run elevated.
+//!
+//! Flow:
+//!
+//! 1. the operation is validated (paths must be inside the OpenAkita data
+//!    root, PIDs must not belong to an unrelated process);
+//! 2. a native consent dialog shows exactly what will run and why;
+//! 3. the OS prompt is raised:
+//!    * Windows — the app relaunches itself with `--elevated-op <request>`
+//!      through `Start-Process -Verb RunAs` (UAC); [`run_helper`] re-validates
+//!      and performs the operation in the elevated copy, then exits;
+//!    * macOS — `osascript … with administrator privileges`;
+//!    * Linux — `pkexec` (polkit).
+//!
+//! Declining either prompt rejects with `ELEVATION_DECLINED`.  New privileged
+//! flows (e.g. a system-service installer) add a variant here rather than
+//! calling the platform tools directly.
+
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::errors::{CmdResult, ErrorCode, ErrorPayload};
+use crate::{
+    apply_no_window, is_safe_openakita_data_root, log_to_file, openakita_process_identity,
+    openakita_root_dir, spawn_blocking_result,
+};
+
+/// Command-line flag that switches the binary into one-shot helper mode.
+pub(crate) const HELPER_FLAG: &str = "--elevated-op";
+
+/// Windows `ERROR_CANCELLED`: the user dismissed the UAC prompt.
+#[cfg(windows)]
+const EXIT_CANCELLED: i32 = 1223;
+/// pkexec: 126 = authentication dialog dismissed, 127 = not authorized.
+#[cfg(all(unix, not(target_os = "macos")))]
+const PKEXEC_DISMISSED: i32 = 126;
+#[cfg(all(unix, not(target_os = "macos")))]
+const PKEXEC_NOT_AUTHORIZED: i32 = 127;
+
+/// The complete list of things the app may do with elevated rights.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ElevatedOperation {
+    /// Give ownership and full control of `path` (recursively) back to the
+    /// current user.  `path` must be inside the OpenAkita data root; a path
+    /// that does not exist yet is repaired at its nearest existing ancestor.
+    RepairPermissions { path: String },
+    /// Force-end an OpenAkita backend process owned by another user/session.
+    KillProcess { pid: u32 },
+}
+
+/// What the elevated helper receives.  `root` / `user` travel with the
+/// request because the UAC-elevated copy may run as a different account
+/// (over-the-shoulder elevation) with a different home and environment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HelperRequest {
+    op: ElevatedOperation,
+    root: String,
+    user: String,
+}
+
+/// A validated operation, ready to be shown in the consent dialog and run.
+#[derive(Debug, Clone)]
+pub(crate) enum Plan {
+    Repair { path: PathBuf, user: String },
+    Kill { pid: u32 },
+}
+
+fn current_user() -> String {
+    #[cfg(windows)]
+    {
+        let user = std::env::var("USERNAME").unwrap_or_default();
+        match std::env::var("USERDOMAIN") {
+            Ok(domain) if !domain.is_empty() => format!("{domain}\\{user}"),
+            _ => user,
+        }
+    }
+    #[cfg(not(windows))]
+    {
+        // chown 需要数字 uid:gid，避免用户名里有空格/特殊字符
+        let id = |flag: &str| {
+            Command::new("id")
+                .arg(flag)
+                .output()
+                .ok()
+                .filter(|o| o.status.success())
+                .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+                .unwrap_or_default()
+        };
+        format!("{}:{}", id("-u"), id("-g"))
+    }
+}
+
+/// Nearest existing ancestor of `path` (itself included).
+fn nearest_existing(path: &Path) -> Option<PathBuf> {
+    path.ancestors().find(|p| p.exists()).map(Path::to_path_buf)
+}
+
+pub(crate) fn plan(op: &ElevatedOperation, root: &Path, user: &str) -> Result<Plan, String> {
+    match op {
+        ElevatedOperation::RepairPermissions { path } => {
+            let requested = PathBuf::from(path);
+            if !requested.is_absolute() {
+                return Err(format!("INVALID_ARGUMENT|需要绝对路径: {path}"));
+            }
+            if !is_safe_openakita_data_root(root) {
+                return Err(format!(
+                    "INVALID_ARGUMENT|数据目录 {} 不允许提权修复",
+                    root.display()
+                ));
+            }
+            if user.trim().is_empty() || user.contains('"') || user == ":" {
+                return Err("ELEVATION_FAILED|无法确定当前用户".into());
+            }
+            let root = root
+                .canonicalize()
+                .map_err(|e| format!("NOT_FOUND|数据目录 {} 不可用: {e}", root.display()))?;
+            let target = nearest_existing(&requested)
+                .and_then(|p| p.canonicalize().ok())
+                .ok_or_else(|| format!("NOT_FOUND|{path} 及其上级目录都不存在"))?;
+            if !target.starts_with(&root) {
+                return Err(format!(
+                    "PATH_ESCAPE|{} 不在数据目录 {} 内",
+                    target.display(),
+                    root.display()
+                ));
+            }
+            Ok(Plan::Repair {
+                path: target,
+                user: user.to_string(),
+            })
+        }
+        ElevatedOperation::KillProcess { pid } => {
+            if *pid == 0 || *pid == std::process::id() {
+                return Err(format!("INVALID_ARGUMENT|无效的 PID: {pid}"));
+            }
+            // 读不到别的会话的命令行时身份未知（None），交给用户在确认框里判断；
+            // 明确不是 OpenAkita 后端的进程一律拒绝
+            if openakita_process_identity(*pid) == Some(false) {
+                return Err(format!(
+                    "INVALID_ARGUMENT|PID {pid} 不是正在运行的 OpenAkita 后端进程"
+                ));
+            }
+            Ok(Plan::Kill { pid: *pid })
+        }
+    }
+}
+
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// The shell command run by pkexec / osascript (macOS & Linux).
+pub(crate) fn unix_command(plan: &Plan) -> String {
+    match plan {
+        Plan::Repair { path, user } => {
+            let path = sh_quote(&path.to_string_lossy());
+            format!(
+                "chown -R {} {path} && chmod -R u+rwX {path}",
+                sh_quote(user)
+            )
+        }
+        Plan::Kill { pid } => format!("kill -KILL {pid}"),
+    }
+}
+
+/// The commands the elevated Windows helper runs, as (program, args).
+pub(crate) fn windows_commands(plan: &Plan) -> Vec<(&'static str, Vec<String>)> {
+    match plan {
+        Plan::Repair { path, user } => {
+            let path = path.to_string_lossy().to_string();
+            vec![
+                (
+                    "icacls",
+                    vec![
+                        path.clone(),
+                        "/setowner".into(),
+                        user.clone(),
+                        "/T".into(),
+                        "/C".into(),
+                        "/Q".into(),
+                    ],
+                ),
+                (
+                    "icacls",
+                    vec![
+                        path,
+                        "/grant".into(),
+                        format!("{user}:(OI)(CI)F"),
+                        "/T".into(),
+                        "/C".into(),
+                        "/Q".into(),
+                    ],
+                ),
+            ]
+        }
+        Plan::Kill { pid } => vec![(
+            "taskkill",
+            vec!["/PID".into(), pid.to_string(), "/T".into(), "/F".into()],
+        )],
+    }
+}
+
+fn command_preview(plan: &Plan) -> String {
+    if cfg!(windows) {
+        windows_commands(plan)
+            .into_iter()
+            .map(|(prog, args)| {
+                let args: Vec<String> = args
+                    .into_iter()
+                    .map(|a| {
+                        if a.contains(' ') {
+                            format!("\"{a}\"")
+                        } else {
+                            a
+                        }
+                    })
+                    .collect();
+                format!("{prog} {}", args.join(" "))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        unix_command(plan)
+    }
+}
+
+fn consent_text(plan: &Plan) -> String {
+    let what = match plan {
+        Plan::Repair { path, user } => format!(
+            "将以管理员权限把以下目录（含所有子目录和文件）的所有者和完全控制权交还给当前用户（{user}）：\n{}",
+            path.display()
+        ),
+        Plan::Kill { pid } => format!(
+            "将以管理员权限强制结束 OpenAkita 后端进程（PID {pid}）。该进程属于其他用户或会话，结束后其中未保存的任务会丢失。"
+        ),
+    };
+    let prompt = if cfg!(windows) {
+        "继续后 Windows 会弹出用户账户控制（UAC）确认。"
+    } else if cfg!(target_os = "macos") {
+        "继续后 macOS 会要求输入管理员密码。"
+    } else {
+        "继续后系统（polkit）会要求输入管理员密码。"
+    };
+    format!(
+        "{what}\n\n将执行的命令：\n{}\n\n{prompt}",
+        command_preview(plan)
+    )
+}
+
+fn declined() -> String {
+    "ELEVATION_DECLINED|已取消管理员授权".into()
+}
+
+fn failed(status: std::process::ExitStatus, stderr: &[u8]) -> String {
+    let stderr = String::from_utf8_lossy(stderr);
+    let last = stderr
+        .lines()
+        .rev()
+        .find(|l| !l.trim().is_empty())
+        .unwrap_or("")
+        .trim();
+    format!("ELEVATION_FAILED|提权操作失败（{status}）: {last}")
+}
+
+#[cfg(windows)]
+fn execute(_plan: &Plan, request: &HelperRequest) -> Result<(), String> {
+    // 提权副本自己重新校验并执行 request（见 run_helper）
+    let exe = std::env::current_exe().map_err(|e| format!("ELEVATION_FAILED|{e}"))?;
+    let json = serde_json::to_vec(request).map_err(|e| e.to_string())?;
+    let encoded = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json);
+    let ps_quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+    let script = format!(
+        "try {{ $p = Start-Process -FilePath {} -ArgumentList {},{} -Verb RunAs -Wait -PassThru -WindowStyle Hidden -ErrorAction Stop }} catch {{ exit {EXIT_CANCELLED} }}; exit $p.ExitCode",
+        ps_quote(&exe.to_string_lossy()),
+        ps_quote(HELPER_FLAG),
+        ps_quote(&encoded),
+    );
+    let mut cmd = Command::new("powershell");
+    cmd.args(["-NoProfile", "-NonInteractive", "-Command", &script]);
+    apply_no_window(&mut cmd);
+    let out = cmd
+        .output()
+        .map_err(|e| format!("ELEVATION_FAILED|无法启动 PowerShell: {e}"))?;
+    match out.status.code() {
+        Some(0) => Ok(()),
+        Some(EXIT_CANCELLED) => Err(declined()),
+        _ => Err(failed(out.status, &out.stderr)),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn execute(plan: &Plan, _request: &HelperRequest) -> Result<(), String> {
+    let as_quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    let script = format!(
+        "do shell script {} with prompt {} with administrator privileges",
+        as_quote(&unix_command(plan)),
+        as_quote("OpenAkita 需要管理员权限来完成刚才确认的操作。"),
+    );
+    let out = Command::new("osascript")
+        .args(["-e", &script])
+        .output()
+        .map_err(|e| format!("ELEVATION_FAILED|无法启动 osascript: {e}"))?;
+    if out.status.success() {
+        return Ok(());
+    }
+    // -128 = userCanceledErr
+    if String::from_utf8_lossy(&out.stderr).contains("-128") {
+        return Err(declined());
+    }
+    Err(failed(out.status, &out.stderr))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn execute(plan: &Plan, _request: &HelperRequest) -> Result<(), String> {
+    let out = Command::new("pkexec")
+        .args(["/bin/sh", "-c", &unix_command(plan)])
+        .output()
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                "ELEVATION_FAILED|未找到 pkexec（polkit），请用管理员账户手动执行确认框中的命令"
+                    .to_string()
+            } else {
+                format!("ELEVATION_FAILED|无法启动 pkexec: {e}")
+            }
+        })?;
+    match out.status.code() {
+        Some(0) => Ok(()),
+        Some(PKEXEC_DISMISSED) | Some(PKEXEC_NOT_AUTHORIZED) => Err(declined()),
+        _ => Err(failed(out.status, &out.stderr)),
+    }
+}
+
+/// Run one allowlisted operation with elevated rights after the user
+/// confirms it in a native dialog (and then in the OS prompt).
+#[tauri::command]
+pub async fn run_elevated(app: AppHandle, operation: ElevatedOperation) -> CmdResult<()> {
+    let request = HelperRequest {
+        op: operation,
+        root: openakita_root_dir().to_string_lossy().to_string(),
+        user: current_user(),
+    };
+    let req = request.clone();
+    let plan =
+        spawn_blocking_result(move || plan(&req.op, Path::new(&req.root), &req.user)).await?;
+    let confirmed = app
+        .dialog()
+        .message(consent_text(&plan))
+        .title("需要管理员权限")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "继续".to_string(),
+            "取消".to_string(),
+        ))
+        .blocking_show();
+    if !confirmed {
+        log_to_file(&format!("[elevate] declined {:?}", request.op));
+        return Err(ErrorPayload::new(
+            ErrorCode::ElevationDeclined,
+            "已取消管理员授权",
+        ));
+    }
+    log_to_file(&format!("[elevate] running {:?}", request.op));
+    let result = spawn_blocking_result(move || execute(&plan, &request)).await;
+    log_to_file(&format!("[elevate] finished: {result:?}"));
+    result.map_err(Into::into)
+}
+
+/// Entry point of the elevated Windows helper (`<exe> --elevated-op <req>`).
+/// Returns the process exit code.  The request is validated again here: the
+/// helper runs with administrator rights and trusts nothing on its command
+/// line.
+pub(crate) fn run_helper(encoded: &str) -> i32 {
+    let request: HelperRequest = match base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|e| e.to_string())
+        .and_then(|b| serde_json::from_slice(&b).map_err(|e| e.to_string()))
+    {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("invalid elevated request: {e}");
+            return 2;
+        }
+    };
+    let plan = match plan(&request.op, Path::new(&request.root), &request.user) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("{e}");
+            return 2;
+        }
+    };
+    if !cfg!(windows) {
+        eprintln!("{HELPER_FLAG} is only used on Windows");
+        return 2;
+    }
+    for (prog, args) in windows_commands(&plan) {
+        let mut cmd = Command::new(prog);
+        cmd.args(&args);
+        apply_no_window(&mut cmd);
+        match cmd.status() {
+            Ok(s) if s.success() => {}
+            Ok(s) => {
+                eprintln!("{prog} failed: {s}");
+                return s.code().unwrap_or(1);
+            }
+            Err(e) => {
+                eprintln!("{prog}: {e}");
+                return 1;
+            }
+        }
+    }
+    0
+}
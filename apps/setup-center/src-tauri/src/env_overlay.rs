@@ -0,0 +1,263 @@
+//! Layered workspace env files.
+//!
+//! A workspace used to have exactly one `.env`, which mixed settings worth
+//! sharing with API keys and machine-specific paths.  It can now be split:
+//!
+//! * `.env` — the base, safe to commit;
+//! * `.env.<profile>` — the workspace's env profile ("dev", "prod"),
+//!   chosen with `set_env_profile`;
+//! * `.env.local` — this machine's overrides and secrets, never shared.
+//!
+//! Later files win, key by key.  The backend gets the profile name in
+//! `OPENAKITA_ENV_PROFILE` and its `.env` loader applies the same layers;
+//! the desktop's own readers (API port, redaction, launch record) go
+//! through [`workspace_env`].  `get_effective_env(workspace_id, profile)`
+//! shows the merge: every variable, the file it came from and the files it
+//! shadows.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::errors::CmdResult;
+use crate::{
+    observer, read_env_kv, read_state_file, redact, trace, workspace_dir, write_state_file,
+    STATE_FILE_LOCK,
+};
+
+pub(crate) const BASE_FILE: &str = ".env";
+pub(crate) const LOCAL_FILE: &str = ".env.local";
+/// `.env.<suffix>` files that are not profiles.
+const NOT_PROFILES: &[&str] = &[
+    "local", "bak", "backup", "old", "tmp", "example", "sample", "template",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveVar {
+    pub name: String,
+    pub value: String,
+    /// File the value comes from.
+    pub source: String,
+    /// Earlier files that also set it, in merge order.
+    pub shadowed: Vec<String>,
+    pub redacted: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvLayer {
+    pub file: String,
+    pub exists: bool,
+    pub vars: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveEnv {
+    pub workspace_id: String,
+    pub profile: Option<String>,
+    /// Profiles with a `.env.<profile>` file in the workspace.
+    pub available_profiles: Vec<String>,
+    pub layers: Vec<EnvLayer>,
+    pub vars: Vec<EffectiveVar>,
+}
+
+pub(crate) fn valid_profile(name: &str) -> bool {
+    (1..=32).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        && !NOT_PROFILES.contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// Env files of `profile` in merge order.
+pub(crate) fn layer_files(profile: Option<&str>) -> Vec<String> {
+    let mut files = vec![BASE_FILE.to_string()];
+    if let Some(p) = profile.filter(|p| valid_profile(p)) {
+        files.push(format!(".env.{p}"));
+    }
+    files.push(LOCAL_FILE.to_string());
+    files
+}
+
+/// Merge `(file, variables)` layers; within a file the last assignment
+/// wins, as with dotenv.  Values are not redacted.
+pub(crate) fn merge(layers: &[(String, Vec<(String, String)>)]) -> Vec<EffectiveVar> {
+    let mut merged: BTreeMap<String, EffectiveVar> = BTreeMap::new();
+    for (file, vars) in layers {
+        for (name, value) in vars {
+            match merged.get_mut(name) {
+                Some(var) => {
+                    if var.source != *file {
+                        let previous = std::mem::replace(&mut var.source, file.clone());
+                        var.shadowed.push(previous);
+                    }
+                    var.value = value.clone();
+                }
+                None => {
+                    merged.insert(
+                        name.clone(),
+                        EffectiveVar {
+                            name: name.clone(),
+                            value: value.clone(),
+                            source: file.clone(),
+                            shadowed: Vec::new(),
+                            redacted: false,
+                        },
+                    );
+                }
+            }
+        }
+    }
+    merged.into_values().collect()
+}
+
+/// The workspace's env profile, if one is set.
+pub(crate) fn profile_of(workspace_id: &str) -> Option<String> {
+    read_state_file()
+        .workspaces
+        .into_iter()
+        .find(|w| w.id == workspace_id)
+        .and_then(|w| w.env_profile)
+}
+
+fn read_layers(ws_dir: &Path, profile: Option<&str>) -> Vec<(String, Vec<(String, String)>)> {
+    layer_files(profile)
+        .into_iter()
+        .map(|file| {
+            let vars = read_env_kv(&ws_dir.join(&file));
+            (file, vars)
+        })
+        .collect()
+}
+
+/// Merged variables of the env files in `ws_dir`.
+pub(crate) fn merged_env(ws_dir: &Path, profile: Option<&str>) -> Vec<(String, String)> {
+    merge(&read_layers(ws_dir, profile))
+        .into_iter()
+        .map(|v| (v.name, v.value))
+        .collect()
+}
+
+/// Merged variables of the workspace with its configured profile.
+pub(crate) fn workspace_env(workspace_id: &str) -> Vec<(String, String)> {
+    merged_env(
+        &workspace_dir(workspace_id),
+        profile_of(workspace_id).as_deref(),
+    )
+}
+
+/// One merged variable of the workspace.
+pub(crate) fn workspace_var(workspace_id: &str, key: &str) -> Option<String> {
+    workspace_env(workspace_id)
+        .into_iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v)
+}
+
+/// `.env` and every `.env.*` file in `ws_dir`, whatever the profile —
+/// secrets in any of them should be redacted.
+pub(crate) fn env_files(ws_dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(ws_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            name == BASE_FILE || name.starts_with(".env.")
+        })
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .collect();
+    files.sort();
+    files
+}
+
+pub(crate) fn available_profiles(ws_dir: &Path) -> Vec<String> {
+    env_files(ws_dir)
+        .iter()
+        .filter_map(|p| {
+            let name = p.file_name()?.to_string_lossy().to_string();
+            let profile = name.strip_prefix(".env.")?;
+            valid_profile(profile).then(|| profile.to_string())
+        })
+        .collect()
+}
+
+/// What the backend would see from the workspace's env files with
+/// `profile` (the configured one when omitted).  Secret values are masked.
+#[tauri::command]
+pub fn get_effective_env(workspace_id: String, profile: Option<String>) -> CmdResult<EffectiveEnv> {
+    let ws_dir = workspace_dir(&workspace_id);
+    if workspace_id.is_empty() || !ws_dir.is_dir() {
+        return Err(format!("NOT_FOUND|工作区不存在: {workspace_id}").into());
+    }
+    let profile = match profile.filter(|p| !p.is_empty()) {
+        Some(p) if !valid_profile(&p) => {
+            return Err(format!("INVALID_ARGUMENT|无效的环境配置名: {p}").into());
+        }
+        Some(p) => Some(p),
+        None => profile_of(&workspace_id),
+    };
+    let layers = read_layers(&ws_dir, profile.as_deref());
+    let known = redact::workspace_secret_values(&workspace_id);
+    let vars = merge(&layers)
+        .into_iter()
+        .map(|mut v| {
+            let masked = if trace::is_secret_name(&v.name) && !v.value.is_empty() {
+                "***".to_string()
+            } else {
+                redact::redact_text(&v.value, &known)
+            };
+            v.redacted = masked != v.value;
+            v.value = masked;
+            v
+        })
+        .collect();
+    Ok(EffectiveEnv {
+        workspace_id,
+        available_profiles: available_profiles(&ws_dir),
+        layers: layers
+            .iter()
+            .map(|(file, vars)| EnvLayer {
+                file: file.clone(),
+                exists: ws_dir.join(file).is_file(),
+                vars: vars.len(),
+            })
+            .collect(),
+        profile,
+        vars,
+    })
+}
+
+/// Choose the workspace's env profile (`None` for base + local only).
+/// Takes effect at the next backend start.
+#[tauri::command]
+pub fn set_env_profile(workspace_id: String, profile: Option<String>) -> CmdResult<()> {
+    observer::ensure_writable(&workspace_id)?;
+    let profile = profile.filter(|p| !p.is_empty());
+    if let Some(p) = &profile {
+        if !valid_profile(p) {
+            return Err(format!("INVALID_ARGUMENT|无效的环境配置名: {p}").into());
+        }
+        if !workspace_dir(&workspace_id)
+            .join(format!(".env.{p}"))
+            .is_file()
+        {
+            return Err(format!("NOT_FOUND|工作区 {workspace_id} 没有 .env.{p}").into());
+        }
+    }
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    let ws = state
+        .workspaces
+        .iter_mut()
+        .find(|w| w.id == workspace_id)
+        .ok_or_else(|| format!("NOT_FOUND|工作区不存在: {workspace_id}"))?;
+    ws.env_profile = profile;
+    write_state_file(&state).map_err(Into::into)
+}
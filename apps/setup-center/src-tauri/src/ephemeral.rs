@@ -0,0 +1,351 @@
+//! Session-scoped temporary workspaces.
+//!
+//! Trying a new model or skill in the primary workspace means editing its
+//! `.env` / endpoints and remembering to undo it.  `create_ephemeral_workspace`
+//! instead builds a throwaway workspace under the system temp dir:
+//!
+//! * with `fromTemplate`, the template workspace's env files, endpoints,
+//!   identity and skills are copied (no memory, sessions or logs);
+//! * `.env.local` pins a random free `API_PORT`, so its backend runs beside
+//!   the primary one, and turns the IM channels off — the copied bot tokens
+//!   would otherwise fight the primary backend for the same bots;
+//! * with `autoStart`, a backend is started right away.
+//!
+//! Ephemeral workspaces are listed like any other (`ephemeral: true`) and go
+//! away with `discard_ephemeral_workspace`, on app exit, or — if the app
+//! crashed — at the next start: their backend is stopped, the state entry
+//! and the directory are removed.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::errors::CmdResult;
+use crate::{
+    copy_dir_recursive, ensure_workspace_scaffold, env_overlay, graceful_stop_pid, is_pid_running,
+    kill_pid, log_to_file, openakita_service_start, read_pid_file, read_state_file,
+    read_workspace_api_port, remove_heartbeat_file, service_pid_file, spawn_blocking_result,
+    update_env_content, write_state_file, AppStateFile, EnvEntry, ServiceStatus, WorkspaceMeta,
+    MANAGED_CHILD, STATE_FILE_LOCK,
+};
+
+/// Copied from the template, relative to the workspace.
+const TEMPLATE_FILES: &[&str] = &["data/llm_endpoints.json"];
+const TEMPLATE_DIRS: &[&str] = &["identity", "skills"];
+/// Written to the ephemeral `.env.local` on top of the random `API_PORT`.
+const ISOLATION: &[(&str, &str)] = &[
+    ("TELEGRAM_ENABLED", "false"),
+    ("FEISHU_ENABLED", "false"),
+    ("WEWORK_ENABLED", "false"),
+    ("DINGTALK_ENABLED", "false"),
+    ("ONEBOT_ENABLED", "false"),
+    ("QQBOT_ENABLED", "false"),
+    ("WECHAT_ENABLED", "false"),
+];
+
+/// Ephemeral workspace id → its directory.  `workspace_dir` looks here first.
+static EPHEMERAL: Lazy<Mutex<HashMap<String, PathBuf>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EphemeralWorkspace {
+    pub workspace_id: String,
+    pub name: String,
+    pub path: String,
+    pub template: Option<String>,
+    pub api_port: u16,
+    /// Template items that were copied, relative to the workspace.
+    pub copied: Vec<String>,
+    /// Backend status when `autoStart` was requested and the start worked.
+    pub status: Option<ServiceStatus>,
+    /// Why the auto-start failed; the workspace is kept for inspection.
+    pub start_error: Option<String>,
+}
+
+fn temp_root() -> PathBuf {
+    std::env::temp_dir().join("openakita-ephemeral")
+}
+
+pub(crate) fn dir_of(workspace_id: &str) -> Option<PathBuf> {
+    EPHEMERAL.lock().ok()?.get(workspace_id).cloned()
+}
+
+fn register(workspace_id: &str, dir: PathBuf) {
+    if let Ok(mut map) = EPHEMERAL.lock() {
+        map.insert(workspace_id.to_string(), dir);
+    }
+}
+
+fn random_suffix() -> String {
+    let mut buf = [0u8; 4];
+    let _ = getrandom::fill(&mut buf);
+    buf.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn free_port() -> Result<u16, String> {
+    std::net::TcpListener::bind(("127.0.0.1", 0))
+        .and_then(|l| l.local_addr())
+        .map(|a| a.port())
+        .map_err(|e| format!("无法分配空闲端口: {e}"))
+}
+
+/// Copy the template's configuration into `to`.  Returns what was copied.
+pub(crate) fn copy_template(from: &Path, to: &Path) -> Result<Vec<String>, String> {
+    let mut copied = Vec::new();
+    for src in env_overlay::env_files(from) {
+        let name = src
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        fs::copy(&src, to.join(&name)).map_err(|e| format!("copy {name}: {e}"))?;
+        copied.push(name);
+    }
+    for rel in TEMPLATE_FILES {
+        let src = from.join(rel);
+        if src.is_file() {
+            let dst = to.join(rel);
+            if let Some(parent) = dst.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("create {rel}: {e}"))?;
+            }
+            fs::copy(&src, &dst).map_err(|e| format!("copy {rel}: {e}"))?;
+            copied.push(rel.to_string());
+        }
+    }
+    for rel in TEMPLATE_DIRS {
+        let src = from.join(rel);
+        if src.is_dir() {
+            copy_dir_recursive(&src, &to.join(rel))?;
+            copied.push(format!("{rel}/"));
+        }
+    }
+    Ok(copied)
+}
+
+/// `.env.local` entries of an ephemeral workspace listening on `port`.
+pub(crate) fn isolation_entries(port: u16) -> Vec<EnvEntry> {
+    std::iter::once(("API_PORT", port.to_string()))
+        .chain(ISOLATION.iter().map(|(k, v)| (*k, v.to_string())))
+        .map(|(key, value)| EnvEntry {
+            key: key.to_string(),
+            value,
+        })
+        .collect()
+}
+
+/// Drop the workspace from the state file, moving the current workspace
+/// off it if needed.
+fn forget(state: &mut AppStateFile, workspace_id: &str) {
+    state.workspaces.retain(|w| w.id != workspace_id);
+    if state.current_workspace_id.as_deref() == Some(workspace_id) {
+        state.current_workspace_id = state
+            .workspaces
+            .iter()
+            .find(|w| w.id == "default")
+            .or_else(|| state.workspaces.iter().find(|w| !w.ephemeral))
+            .map(|w| w.id.clone());
+    }
+}
+
+fn stop_backend(workspace_id: &str) {
+    let managed = {
+        let mut guard = MANAGED_CHILD.lock().unwrap();
+        match guard.as_ref() {
+            Some(mp) if mp.workspace_id == workspace_id => guard.take(),
+            _ => None,
+        }
+    };
+    let port = read_workspace_api_port(workspace_id);
+    if let Some(mut mp) = managed {
+        let _ = graceful_stop_pid(mp.pid, port);
+        if is_pid_running(mp.pid) {
+            let _ = mp.child.kill();
+        }
+        let _ = mp.child.wait();
+    }
+    if let Some(data) = read_pid_file(workspace_id) {
+        if is_pid_running(data.pid) {
+            let _ = graceful_stop_pid(data.pid, port);
+        }
+        if is_pid_running(data.pid) {
+            let _ = kill_pid(data.pid);
+        }
+    }
+    let _ = fs::remove_file(service_pid_file(workspace_id));
+    remove_heartbeat_file(workspace_id);
+}
+
+/// Stop the workspace's backend and delete the workspace.
+fn remove(workspace_id: &str, dir: &Path) {
+    stop_backend(workspace_id);
+    if let Ok(_lock) = STATE_FILE_LOCK.lock() {
+        let mut state = read_state_file();
+        forget(&mut state, workspace_id);
+        let _ = write_state_file(&state);
+    }
+    // Windows 上刚退出的进程可能还占着日志 / 数据库文件，稍等重试
+    let mut result = fs::remove_dir_all(dir);
+    for _ in 0..5 {
+        if result.is_ok() || !dir.exists() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(300));
+        result = fs::remove_dir_all(dir);
+    }
+    if let (Err(e), true) = (&result, dir.exists()) {
+        log_to_file(&format!(
+            "[ephemeral] {workspace_id}: remove {} failed: {e}",
+            dir.display()
+        ));
+    }
+    if let Ok(mut map) = EPHEMERAL.lock() {
+        map.remove(workspace_id);
+    }
+    log_to_file(&format!("[ephemeral] {workspace_id}: discarded"));
+}
+
+/// App exit: remove every ephemeral workspace of this session.
+pub(crate) fn cleanup_all() {
+    let all: Vec<(String, PathBuf)> = EPHEMERAL
+        .lock()
+        .map(|m| m.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+        .unwrap_or_default();
+    for (id, dir) in all {
+        remove(&id, &dir);
+    }
+}
+
+/// App start: remove ephemeral workspaces a crashed session left behind.
+pub(crate) fn sweep_leftovers() {
+    let leftovers: Vec<String> = read_state_file()
+        .workspaces
+        .into_iter()
+        .filter(|w| w.ephemeral)
+        .map(|w| w.id)
+        .collect();
+    for id in leftovers {
+        let dir = temp_root().join(&id);
+        register(&id, dir.clone());
+        remove(&id, &dir);
+    }
+}
+
+/// Create a throwaway workspace, optionally from an existing workspace's
+/// configuration and with its backend started (`autoStart` needs `venvDir`).
+#[tauri::command]
+pub async fn create_ephemeral_workspace(
+    app: tauri::AppHandle,
+    from_template: Option<String>,
+    auto_start: bool,
+    venv_dir: Option<String>,
+) -> CmdResult<EphemeralWorkspace> {
+    let from_template = from_template.filter(|t| !t.is_empty());
+    let template = match &from_template {
+        Some(t) => Some(
+            read_state_file()
+                .workspaces
+                .into_iter()
+                .find(|w| w.id == *t)
+                .ok_or_else(|| format!("NOT_FOUND|模板工作区不存在: {t}"))?,
+        ),
+        None => None,
+    };
+    let venv_dir = match (auto_start, venv_dir.filter(|v| !v.is_empty())) {
+        (true, None) => return Err("INVALID_ARGUMENT|autoStart 需要 venvDir".into()),
+        (_, venv) => venv,
+    };
+
+    let id = format!("tmp-{}", random_suffix());
+    let dir = temp_root().join(&id);
+    fs::create_dir_all(&dir).map_err(|e| format!("create {}: {e}", dir.display()))?;
+    register(&id, dir.clone());
+    let prepared = (|| -> Result<(Vec<String>, u16), String> {
+        let copied = match &template {
+            Some(t) => copy_template(&crate::workspace_dir(&t.id), &dir)?,
+            None => Vec::new(),
+        };
+        ensure_workspace_scaffold(&dir)?;
+        let port = free_port()?;
+        let local = dir.join(env_overlay::LOCAL_FILE);
+        let existing = fs::read_to_string(&local).unwrap_or_default();
+        fs::write(
+            &local,
+            update_env_content(&existing, &isolation_entries(port)),
+        )
+        .map_err(|e| format!("write .env.local: {e}"))?;
+        Ok((copied, port))
+    })();
+    let (copied, api_port) = match prepared {
+        Ok(v) => v,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&dir);
+            if let Ok(mut map) = EPHEMERAL.lock() {
+                map.remove(&id);
+            }
+            return Err(e.into());
+        }
+    };
+
+    let name = match &template {
+        Some(t) => format!("临时 · {}", t.name),
+        None => "临时工作区".to_string(),
+    };
+    {
+        let _lock = STATE_FILE_LOCK
+            .lock()
+            .map_err(|e| format!("state lock failed: {e}"))?;
+        let mut state = read_state_file();
+        state.workspaces.push(WorkspaceMeta {
+            id: id.clone(),
+            name: name.clone(),
+            env_profile: template.as_ref().and_then(|t| t.env_profile.clone()),
+            resource_limits: template.as_ref().and_then(|t| t.resource_limits.clone()),
+            ephemeral: true,
+            observer: None,
+            dir_overrides: None,
+            shutdown_timeout_secs: None,
+        });
+        write_state_file(&state)?;
+    }
+    log_to_file(&format!(
+        "[ephemeral] {id}: created at {} from {:?}, API_PORT={api_port}",
+        dir.display(),
+        from_template
+    ));
+
+    let (status, start_error) = match venv_dir.filter(|_| auto_start) {
+        Some(venv) => match openakita_service_start(app, venv, id.clone()).await {
+            Ok(status) => (Some(status), None),
+            Err(e) => (None, Some(String::from(e))),
+        },
+        None => (None, None),
+    };
+    Ok(EphemeralWorkspace {
+        workspace_id: id,
+        name,
+        path: dir.to_string_lossy().to_string(),
+        template: from_template,
+        api_port,
+        copied,
+        status,
+        start_error,
+    })
+}
+
+/// Stop an ephemeral workspace's backend and delete it now.
+#[tauri::command]
+pub async fn discard_ephemeral_workspace(workspace_id: String) -> CmdResult<()> {
+    let dir = dir_of(&workspace_id)
+        .ok_or_else(|| format!("INVALID_ARGUMENT|{workspace_id} 不是本次会话创建的临时工作区"))?;
+    spawn_blocking_result(move || {
+        remove(&workspace_id, &dir);
+        Ok(())
+    })
+    .await
+    .map_err(Into::into)
+}
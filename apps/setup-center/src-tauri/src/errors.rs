@@ -0,0 +1,290 @@
+//! Machine-readable command errors.
+//!
+//! Commands used to reject with free-form strings (mixed Chinese / English),
+//! which the frontend could neither branch on nor localize.  Every
+//! `#[tauri::command]` now returns [`CmdResult`]; the rejection value is an
+//! [`ErrorPayload`]:
+//!
+//! ```json
+//! { "code": "BRIDGE_TIMEOUT", "message": "python openakita... exceeded 60s",
+//!   "details": null, "retryable": true }
+//! ```
+//!
+//! Internal helpers keep returning `Result<_, String>`.  The existing
+//! `CODE|message` prefix convention is the bridge between the two: `?` (via
+//! `From<String>`) turns a prefixed string into the matching [`ErrorCode`],
+//! and an `ErrorPayload` converts back into `CODE|message` where a helper
+//! still wants a string.  Strings without a known prefix become
+//! [`ErrorCode::Unknown`] with the text as `message`.
+
+use serde::Serialize;
+use std::fmt;
+
+/// Stable error codes.  Serialized as `SCREAMING_SNAKE_CASE`; this list is
+/// the contract with the frontend (`platform/index.ts`'s `CommandErrorCode`),
+/// so codes are only ever added, never renamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// Free-form error without a more specific code.
+    Unknown,
+    /// The command's background task panicked; `message` names the crash
+    /// report under `~/.openakita/run/`.
+    Panic,
+    /// A command argument failed validation.
+    InvalidArgument,
+    /// The workspace / skill / file the command refers to does not exist.
+    NotFound,
+    AlreadyExists,
+    /// A relative path resolved outside its workspace.
+    PathEscape,
+    /// `workspace_read_file` refused a text read over the size limit.
+    FileTooLarge,
+    /// `workspace_read_file` refused a binary file; use the bytes API.
+    BinaryFile,
+    /// `open_external_url` refused a non-http(s)/mailto URL.
+    UrlRejected,
+    /// The Python bridge (`openakita.setup_center.bridge`) exited non-zero.
+    BridgeFailed,
+    BridgeTimeout,
+    BridgeCancelled,
+    /// The bridge's output was not the JSON the command promises.
+    BridgeProtocol,
+    RequestCancelled,
+    /// Offline mode is on and the command needs the network.
+    OfflineMode,
+    GithubRateLimited,
+    RuntimeInstallTimeout,
+    RuntimePermissionDenied,
+    RuntimeWheelHashMismatch,
+    SkillManifestInvalid,
+    SkillConfigInvalid,
+    SkillUnverified,
+    SkillChecksumMismatch,
+    SkillNoHistory,
+    OauthNotConfigured,
+    OauthUnsupported,
+    OauthDenied,
+    OauthStateMismatch,
+    OauthTimeout,
+    WebhookRelayUnsupported,
+    WebhookTunnelMissing,
+    WebhookTunnelFailed,
+    /// The user cancelled the consent dialog or the OS elevation prompt.
+    ElevationDeclined,
+    ElevationFailed,
+    /// `undo_action` refused: the target changed after the undo record was made.
+    UndoConflict,
+    /// Not enough free space on the target disk for a download / install.
+    InsufficientDiskSpace,
+    /// A downloaded model file did not match its SHA-256.
+    ModelChecksumMismatch,
+    /// The Ollama daemon is not reachable and could not be started.
+    OllamaNotRunning,
+    /// The operation needs the workspace's backend to be stopped first.
+    BackendRunning,
+    /// The operation is applied through the workspace's backend, which is not up.
+    BackendNotRunning,
+    /// The venv's openakita is too old / too new for this desktop; on
+    /// service start `details.compatibility` carries the fix (`compat.rs`).
+    BackendIncompatible,
+    /// The backend rejected the call for missing / wrong credentials.
+    BackendUnauthorized,
+    /// A backend call did not answer in time.
+    BackendTimeout,
+    /// The workspace only observes a backend running elsewhere and cannot
+    /// be started, stopped or reconfigured from here.
+    ObserverReadOnly,
+}
+
+impl ErrorCode {
+    const ALL: &'static [ErrorCode] = &[
+        ErrorCode::Unknown,
+        ErrorCode::Panic,
+        ErrorCode::InvalidArgument,
+        ErrorCode::NotFound,
+        ErrorCode::AlreadyExists,
+        ErrorCode::PathEscape,
+        ErrorCode::FileTooLarge,
+        ErrorCode::BinaryFile,
+        ErrorCode::UrlRejected,
+        ErrorCode::BridgeFailed,
+        ErrorCode::BridgeTimeout,
+        ErrorCode::BridgeCancelled,
+        ErrorCode::BridgeProtocol,
+        ErrorCode::RequestCancelled,
+        ErrorCode::OfflineMode,
+        ErrorCode::GithubRateLimited,
+        ErrorCode::RuntimeInstallTimeout,
+        ErrorCode::RuntimePermissionDenied,
+        ErrorCode::RuntimeWheelHashMismatch,
+        ErrorCode::SkillManifestInvalid,
+        ErrorCode::SkillConfigInvalid,
+        ErrorCode::SkillUnverified,
+        ErrorCode::SkillChecksumMismatch,
+        ErrorCode::SkillNoHistory,
+        ErrorCode::OauthNotConfigured,
+        ErrorCode::OauthUnsupported,
+        ErrorCode::OauthDenied,
+        ErrorCode::OauthStateMismatch,
+        ErrorCode::OauthTimeout,
+        ErrorCode::WebhookRelayUnsupported,
+        ErrorCode::WebhookTunnelMissing,
+        ErrorCode::WebhookTunnelFailed,
+        ErrorCode::ElevationDeclined,
+        ErrorCode::ElevationFailed,
+        ErrorCode::UndoConflict,
+        ErrorCode::InsufficientDiskSpace,
+        ErrorCode::ModelChecksumMismatch,
+        ErrorCode::OllamaNotRunning,
+        ErrorCode::BackendRunning,
+        ErrorCode::BackendNotRunning,
+        ErrorCode::BackendIncompatible,
+        ErrorCode::BackendUnauthorized,
+        ErrorCode::BackendTimeout,
+        ErrorCode::ObserverReadOnly,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::Unknown => "UNKNOWN",
+            ErrorCode::Panic => "PANIC",
+            ErrorCode::InvalidArgument => "INVALID_ARGUMENT",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::AlreadyExists => "ALREADY_EXISTS",
+            ErrorCode::PathEscape => "PATH_ESCAPE",
+            ErrorCode::FileTooLarge => "FILE_TOO_LARGE",
+            ErrorCode::BinaryFile => "BINARY_FILE",
+            ErrorCode::UrlRejected => "URL_REJECTED",
+            ErrorCode::BridgeFailed => "BRIDGE_FAILED",
+            ErrorCode::BridgeTimeout => "BRIDGE_TIMEOUT",
+            ErrorCode::BridgeCancelled => "BRIDGE_CANCELLED",
+            ErrorCode::BridgeProtocol => "BRIDGE_PROTOCOL",
+            ErrorCode::RequestCancelled => "REQUEST_CANCELLED",
+            ErrorCode::OfflineMode => "OFFLINE_MODE",
+            ErrorCode::GithubRateLimited => "GITHUB_RATE_LIMITED",
+            ErrorCode::RuntimeInstallTimeout => "RUNTIME_INSTALL_TIMEOUT",
+            ErrorCode::RuntimePermissionDenied => "RUNTIME_PERMISSION_DENIED",
+            ErrorCode::RuntimeWheelHashMismatch => "RUNTIME_WHEEL_HASH_MISMATCH",
+            ErrorCode::SkillManifestInvalid => "SKILL_MANIFEST_INVALID",
+            ErrorCode::SkillConfigInvalid => "SKILL_CONFIG_INVALID",
+            ErrorCode::SkillUnverified => "SKILL_UNVERIFIED",
+            ErrorCode::SkillChecksumMismatch => "SKILL_CHECKSUM_MISMATCH",
+            ErrorCode::SkillNoHistory => "SKILL_NO_HISTORY",
+            ErrorCode::OauthNotConfigured => "OAUTH_NOT_CONFIGURED",
+            ErrorCode::OauthUnsupported => "OAUTH_UNSUPPORTED",
+            ErrorCode::OauthDenied => "OAUTH_DENIED",
+            ErrorCode::OauthStateMismatch => "OAUTH_STATE_MISMATCH",
+            ErrorCode::OauthTimeout => "OAUTH_TIMEOUT",
+            ErrorCode::WebhookRelayUnsupported => "WEBHOOK_RELAY_UNSUPPORTED",
+            ErrorCode::WebhookTunnelMissing => "WEBHOOK_TUNNEL_MISSING",
+            ErrorCode::WebhookTunnelFailed => "WEBHOOK_TUNNEL_FAILED",
+            ErrorCode::ElevationDeclined => "ELEVATION_DECLINED",
+            ErrorCode::ElevationFailed => "ELEVATION_FAILED",
+            ErrorCode::UndoConflict => "UNDO_CONFLICT",
+            ErrorCode::InsufficientDiskSpace => "INSUFFICIENT_DISK_SPACE",
+            ErrorCode::ModelChecksumMismatch => "MODEL_CHECKSUM_MISMATCH",
+            ErrorCode::OllamaNotRunning => "OLLAMA_NOT_RUNNING",
+            ErrorCode::BackendRunning => "BACKEND_RUNNING",
+            ErrorCode::BackendNotRunning => "BACKEND_NOT_RUNNING",
+            ErrorCode::BackendIncompatible => "BACKEND_INCOMPATIBLE",
+            ErrorCode::BackendUnauthorized => "BACKEND_UNAUTHORIZED",
+            ErrorCode::BackendTimeout => "BACKEND_TIMEOUT",
+            ErrorCode::ObserverReadOnly => "OBSERVER_READ_ONLY",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<ErrorCode> {
+        Self::ALL.iter().copied().find(|c| c.as_str() == s)
+    }
+
+    /// Whether retrying the same call unchanged can reasonably succeed.
+    pub fn retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorCode::BridgeTimeout
+                | ErrorCode::OfflineMode
+                | ErrorCode::GithubRateLimited
+                | ErrorCode::RuntimeInstallTimeout
+                | ErrorCode::OauthTimeout
+                | ErrorCode::WebhookTunnelFailed
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorPayload {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+    pub retryable: bool,
+}
+
+pub(crate) type CmdResult<T> = Result<T, ErrorPayload>;
+
+impl ErrorPayload {
+    pub(crate) fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        ErrorPayload {
+            code,
+            message: message.into(),
+            details: None,
+            retryable: code.retryable(),
+        }
+    }
+
+    pub(crate) fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+/// `CODE|message` → that code; otherwise `Unknown` with the whole text.
+impl From<String> for ErrorPayload {
+    fn from(s: String) -> Self {
+        if let Some((prefix, rest)) = s.split_once('|') {
+            if let Some(code) = ErrorCode::parse(prefix) {
+                return ErrorPayload::new(code, rest);
+            }
+        }
+        // run_python_module_json 的非零退出：message 取状态行 + 最后一行 stderr
+        // （通常是异常），完整输出放 details
+        if let Some(rest) = s.strip_prefix("python failed: ") {
+            let (status, output) = rest.split_once('\n').unwrap_or((rest, ""));
+            let (stdout, stderr) = output
+                .strip_prefix("stdout:\n")
+                .and_then(|o| o.split_once("\nstderr:\n"))
+                .unwrap_or(("", output));
+            let message = match stderr.lines().rev().find(|l| !l.trim().is_empty()) {
+                Some(last) => format!("python failed: {status}: {}", last.trim()),
+                None => format!("python failed: {status}"),
+            };
+            return ErrorPayload::new(ErrorCode::BridgeFailed, message)
+                .with_details(serde_json::json!({ "stdout": stdout, "stderr": stderr }));
+        }
+        ErrorPayload::new(ErrorCode::Unknown, s)
+    }
+}
+
+impl From<&str> for ErrorPayload {
+    fn from(s: &str) -> Self {
+        ErrorPayload::from(s.to_string())
+    }
+}
+
+/// Back to the `CODE|message` form for helpers that return `String` errors.
+impl From<ErrorPayload> for String {
+    fn from(e: ErrorPayload) -> Self {
+        e.to_string()
+    }
+}
+
+impl fmt::Display for ErrorPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.code {
+            ErrorCode::Unknown => f.write_str(&self.message),
+            code => write!(f, "{}|{}", code.as_str(), self.message),
+        }
+    }
+}
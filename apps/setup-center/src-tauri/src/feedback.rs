@@ -0,0 +1,292 @@
+//! Structured feedback reports.
+//!
+//! Bug reports such as "the chat stops mid-answer" arrived as a sentence
+//! plus whatever the user thought to paste, and the missing pieces — which
+//! endpoint, what the log said, which conversation — were collected by hand
+//! afterwards.  `submit_feedback` assembles them in one step:
+//!
+//! * the user's type / title / description and an optional conversation id;
+//! * app version, OS, whether the backend was running;
+//! * sanitized config: `.env` keys with secret values masked and the LLM
+//!   endpoints without credentials;
+//! * the last [`LOG_EXCERPT_LINES`] lines of the serve log;
+//! * with `includeBundle`, the conversation's transcript and the
+//!   diagnostic bundle next to the report.
+//!
+//! Everything passes through [`redact`] and is saved under
+//! `~/.openakita/feedback/<id>/`.  When a collection endpoint is configured
+//! (`set_feedback_collect_url`), `report.json` is also POSTed there; a
+//! failed POST leaves the local copy and is reported back, not raised.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::errors::CmdResult;
+use crate::{
+    atomic_write, chrono_like_timestamp, export_diagnostic_bundle, is_pid_file_valid, log_to_file,
+    net, now_ms, openakita_root_dir, read_env_kv, read_pid_file, read_state_file, redact, sessions,
+    spawn_blocking_result, trace, workspace_dir, write_state_file, STATE_FILE_LOCK,
+};
+
+pub(crate) const SCHEMA: &str = "openakita-feedback/1";
+const LOG_EXCERPT_LINES: usize = 200;
+/// Only the end of the serve log is read.
+const LOG_TAIL_BYTES: u64 = 256 * 1024;
+const POST_TIMEOUT: Duration = Duration::from_secs(20);
+const REPORT_TYPES: &[&str] = &["bug", "feature", "question", "other"];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedbackInput {
+    /// "bug" | "feature" | "question" | "other"
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    pub description: String,
+    /// Conversation the report is about.
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub include_bundle: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedbackResult {
+    pub id: String,
+    pub report_path: String,
+    pub bundle_path: Option<String>,
+    /// POSTed to the collection endpoint.
+    pub submitted: bool,
+    pub submit_error: Option<String>,
+}
+
+/// `.env` as a map, secret values masked.
+pub(crate) fn sanitized_env(pairs: Vec<(String, String)>) -> Map<String, Value> {
+    pairs
+        .into_iter()
+        .map(|(k, v)| {
+            let v = if trace::is_secret_name(&k) && !v.is_empty() {
+                "***".to_string()
+            } else {
+                v
+            };
+            (k, Value::String(v))
+        })
+        .collect()
+}
+
+/// LLM endpoints reduced to what helps a diagnosis; keys, headers and
+/// anything else credential-like are dropped.
+pub(crate) fn sanitized_endpoints(config: &Value) -> Value {
+    const KEEP: &[&str] = &[
+        "name",
+        "provider",
+        "api_type",
+        "base_url",
+        "model",
+        "priority",
+        "context_window",
+        "max_tokens",
+        "timeout",
+        "capabilities",
+        "api_key_env",
+    ];
+    let pick = |ep: &Value| -> Value {
+        KEEP.iter()
+            .filter_map(|k| Some((k.to_string(), ep.get(*k)?.clone())))
+            .collect::<Map<_, _>>()
+            .into()
+    };
+    let mut out = Map::new();
+    for key in ["endpoints", "compiler_endpoints"] {
+        if let Some(list) = config.get(key).and_then(Value::as_array) {
+            out.insert(key.into(), list.iter().map(pick).collect());
+        }
+    }
+    out.into()
+}
+
+/// The last `lines` lines of `text`.
+pub(crate) fn last_lines(text: &str, lines: usize) -> String {
+    let all: Vec<&str> = text.lines().collect();
+    all[all.len().saturating_sub(lines)..].join("\n")
+}
+
+pub(crate) fn read_tail(path: &Path, max_bytes: u64) -> String {
+    let Ok(mut f) = fs::File::open(path) else {
+        return String::new();
+    };
+    let len = f.metadata().map(|m| m.len()).unwrap_or(0);
+    let _ = f.seek(SeekFrom::Start(len.saturating_sub(max_bytes)));
+    let mut buf = Vec::new();
+    let _ = f.read_to_end(&mut buf);
+    String::from_utf8_lossy(&buf).into_owned()
+}
+
+fn report_id() -> String {
+    let mut r = [0u8; 3];
+    let _ = getrandom::fill(&mut r);
+    format!(
+        "fb-{}-{:02x}{:02x}{:02x}",
+        chrono_like_timestamp(),
+        r[0],
+        r[1],
+        r[2]
+    )
+}
+
+pub(crate) fn feedback_dir() -> PathBuf {
+    openakita_root_dir().join("feedback")
+}
+
+fn build_report(id: &str, workspace_id: &str, input: &FeedbackInput) -> Value {
+    let ws_dir = workspace_dir(workspace_id);
+    let endpoints: Value = fs::read_to_string(ws_dir.join("data").join("llm_endpoints.json"))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or(Value::Null);
+    let log = read_tail(
+        &ws_dir.join("logs").join("openakita-serve.log"),
+        LOG_TAIL_BYTES,
+    );
+    json!({
+        "schema": SCHEMA,
+        "id": id,
+        "type": input.kind,
+        "title": input.title.as_deref().unwrap_or("").trim(),
+        "description": input.description.trim(),
+        "createdAtMs": now_ms(),
+        "app": {
+            "version": env!("CARGO_PKG_VERSION"),
+            "os": std::env::consts::OS,
+            "arch": std::env::consts::ARCH,
+        },
+        "workspace": {
+            "id": workspace_id,
+            "backendRunning": read_pid_file(workspace_id).as_ref().is_some_and(is_pid_file_valid),
+        },
+        "session": input.session_id.as_deref().map(|id| json!({ "id": id })),
+        "config": {
+            "env": sanitized_env(read_env_kv(&ws_dir.join(".env"))),
+            "llmEndpoints": sanitized_endpoints(&endpoints),
+        },
+        "logExcerpt": last_lines(&log, LOG_EXCERPT_LINES),
+    })
+}
+
+async fn post_report(url: &str, body: String) -> Result<(), String> {
+    net::ensure_online("提交反馈")?;
+    let resp = net::http_client()
+        .post(url)
+        .header("Content-Type", "application/json")
+        .timeout(POST_TIMEOUT)
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| format!("POST {url} failed: {e}"))?;
+    if !resp.status().is_success() {
+        return Err(format!("POST {url}: HTTP {}", resp.status()));
+    }
+    Ok(())
+}
+
+/// Collection endpoint for [`submit_feedback`]; `None` keeps reports local.
+#[tauri::command]
+pub fn get_feedback_collect_url() -> Option<String> {
+    read_state_file().feedback_collect_url
+}
+
+#[tauri::command]
+pub fn set_feedback_collect_url(url: Option<String>) -> CmdResult<()> {
+    let url = url.map(|u| u.trim().to_string()).filter(|u| !u.is_empty());
+    if let Some(u) = &url {
+        let parsed = reqwest::Url::parse(u).map_err(|e| format!("INVALID_ARGUMENT|{u}: {e}"))?;
+        if !matches!(parsed.scheme(), "https" | "http") {
+            return Err(format!("INVALID_ARGUMENT|只支持 http(s) 地址: {u}").into());
+        }
+    }
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    state.feedback_collect_url = url;
+    write_state_file(&state).map_err(Into::into)
+}
+
+/// Assemble a feedback report for `workspace_id`, save it locally and, if
+/// a collection endpoint is configured, POST it there.
+#[tauri::command]
+pub async fn submit_feedback(
+    workspace_id: String,
+    report: FeedbackInput,
+) -> CmdResult<FeedbackResult> {
+    if !REPORT_TYPES.contains(&report.kind.as_str()) {
+        return Err(format!("INVALID_ARGUMENT|未知的反馈类型: {}", report.kind).into());
+    }
+    if report.description.trim().is_empty() {
+        return Err("INVALID_ARGUMENT|请填写问题描述".into());
+    }
+    if workspace_id.is_empty() || !workspace_dir(&workspace_id).is_dir() {
+        return Err(format!("NOT_FOUND|工作区不存在: {workspace_id}").into());
+    }
+    let id = report_id();
+    let dir = feedback_dir().join(&id);
+    let (report_path, body, bundle_path) = spawn_blocking_result({
+        let (id, dir, ws) = (id.clone(), dir.clone(), workspace_id.clone());
+        move || {
+            let value = build_report(&id, &ws, &report);
+            let body = redact::redact_for_workspace(
+                &serde_json::to_string_pretty(&value).unwrap_or_default(),
+                &ws,
+            );
+            let report_path = dir.join("report.json");
+            atomic_write(&report_path, &body)?;
+            if !report.include_bundle {
+                return Ok((report_path, body, None));
+            }
+            // 附上所指会话的原文（已脱敏）和诊断包
+            let ids: Vec<String> = report.session_id.iter().cloned().collect();
+            for (_, transcript) in sessions::transcripts_for_feedback(&ws, &ids) {
+                atomic_write(dir.join("session.json"), transcript)?;
+            }
+            let dest = dir.join("bundle.zip").to_string_lossy().to_string();
+            let bundle = match export_diagnostic_bundle(ws, None, Some(dest), Some(false)) {
+                Ok(p) => Some(p),
+                Err(e) => {
+                    log_to_file(&format!("[feedback] {id} bundle failed: {}", e.message));
+                    None
+                }
+            };
+            Ok((report_path, body, bundle))
+        }
+    })
+    .await?;
+
+    let (submitted, submit_error) = match get_feedback_collect_url() {
+        Some(url) => match post_report(&url, body).await {
+            Ok(()) => (true, None),
+            Err(e) => {
+                log_to_file(&format!("[feedback] {id} submit failed: {e}"));
+                (false, Some(e))
+            }
+        },
+        None => (false, None),
+    };
+    log_to_file(&format!(
+        "[feedback] {id} saved (bundle={}, submitted={submitted})",
+        bundle_path.is_some()
+    ));
+    Ok(FeedbackResult {
+        id,
+        report_path: report_path.to_string_lossy().to_string(),
+        bundle_path,
+        submitted,
+        submit_error,
+    })
+}
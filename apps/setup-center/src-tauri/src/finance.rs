@@ -22,15 +22,17 @@
 //!   "export backup" flow; returns the chosen absolute path or `None`
 //!   when the user cancels.
 //!
-//! All four commands are intentionally thin: error strings flow back
-//! to JS as `Result<_, String>` so the React layer can surface a
-//! single toast instead of a TypeScript discriminated union.
+//! All four commands are intentionally thin: errors flow back to JS as a
+//! plain [`CmdResult`] (code `UNKNOWN` + message) so the React layer can
+//! surface a single toast instead of a TypeScript discriminated union.
 
 use serde_json::json;
 use tauri::AppHandle;
 use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
 use tauri_plugin_notification::NotificationExt;
 
+use crate::errors::CmdResult;
+
 /// Constant string returned by [`show_finance_consent_dialog`] when the
 /// user clicks the primary "允许一次" button.
 pub const CONSENT_ALLOW_ONCE: &str = "allow_once";
@@ -52,7 +54,7 @@ pub async fn show_finance_consent_dialog(
     app: AppHandle,
     title: String,
     body: String,
-) -> Result<String, String> {
+) -> CmdResult<String> {
     let confirmed = app
         .dialog()
         .message(body)
@@ -97,13 +99,13 @@ pub async fn finance_show_notification(
     app: AppHandle,
     title: String,
     body: String,
-) -> Result<(), String> {
+) -> CmdResult<()> {
     app.notification()
         .builder()
         .title(title)
         .body(body)
         .show()
-        .map_err(|err| format!("notification failed: {err}"))
+        .map_err(|err| format!("notification failed: {err}").into())
 }
 
 /// Show a native save-file dialog seeded with `default_name`.
@@ -119,7 +121,7 @@ pub async fn finance_show_notification(
 pub async fn finance_pick_save_path(
     app: AppHandle,
     default_name: String,
-) -> Result<Option<String>, String> {
+) -> CmdResult<Option<String>> {
     let picked = app
         .dialog()
         .file()
@@ -0,0 +1,479 @@
+//! Guided IM channel setup.
+//!
+//! `openakita_health_check_im` only tells whether an already configured
+//! channel works.  This module helps get there:
+//!
+//! * [`im_validate_token`] checks credentials before they are saved —
+//!   Telegram `getMe` and the DingTalk app access token are asked directly,
+//!   Feishu / Lark and QQ go through the existing bridge validators.
+//! * [`im_begin_login`] drives the QR flows (WeChat, WeCom, Feishu Device
+//!   Flow, QQ) in the background: the QR content arrives as an `im-login`
+//!   event (`stage: "qr"`), followed by `scanned`, then `success` /
+//!   `expired` / `error` / `cancelled`.
+//!
+//! In both cases, once credentials are confirmed and a workspace is given,
+//! the channel's keys and its `*_ENABLED` switch are written into that
+//! workspace's `.env`; events only carry the key names, never the secrets.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::errors::CmdResult;
+use crate::{
+    emit_if_ui_live, log_to_file, net, now_ms, run_bridge_with_secrets, run_python_module_json,
+    spawn_blocking_result, workspace_update_env, EnvEntry,
+};
+
+const BRIDGE: &str = "openakita.setup_center.bridge";
+const LOGIN_EVENT: &str = "im-login";
+/// Upper bound for one QR session (Feishu device codes live 10 minutes).
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(600);
+
+static LOGINS: Lazy<Mutex<HashMap<String, Arc<AtomicBool>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImTokenCheck {
+    pub channel: String,
+    pub valid: bool,
+    /// Bot username / app name when the platform reports one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// `.env` keys written (only when a workspace was given and valid).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub saved_keys: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ImLoginEvent {
+    login_id: String,
+    channel: String,
+    /// `qr` | `scanned` | `success` | `expired` | `error` | `cancelled`
+    stage: String,
+    /// What the QR code encodes (render it as-is).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    qr_content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    saved_keys: Vec<String>,
+}
+
+/// One normalized poll result of a QR flow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum LoginPoll {
+    Pending,
+    Scanned,
+    /// `.env` entries to write.
+    Done(Vec<(String, String)>),
+    Expired,
+    Failed(String),
+}
+
+/// `.env` entries for confirmed credentials: `(key, value)` pairs plus the
+/// channel's enable switch.
+pub(crate) fn env_entries(channel: &str, creds: &[&str]) -> Option<Vec<(String, String)>> {
+    let (enabled, keys): (&str, &[&str]) = match channel {
+        "telegram" => ("TELEGRAM_ENABLED", &["TELEGRAM_BOT_TOKEN"]),
+        "dingtalk" => (
+            "DINGTALK_ENABLED",
+            &["DINGTALK_CLIENT_ID", "DINGTALK_CLIENT_SECRET"],
+        ),
+        "feishu" => ("FEISHU_ENABLED", &["FEISHU_APP_ID", "FEISHU_APP_SECRET"]),
+        "qqbot" => ("QQBOT_ENABLED", &["QQBOT_APP_ID", "QQBOT_APP_SECRET"]),
+        "wecom" => (
+            "WEWORK_WS_ENABLED",
+            &["WEWORK_WS_BOT_ID", "WEWORK_WS_SECRET"],
+        ),
+        "wechat" => ("WECHAT_ENABLED", &["WECHAT_TOKEN"]),
+        _ => return None,
+    };
+    if creds.len() != keys.len() || creds.iter().any(|v| v.trim().is_empty()) {
+        return None;
+    }
+    let mut out: Vec<(String, String)> = keys
+        .iter()
+        .zip(creds)
+        .map(|(k, v)| (k.to_string(), v.trim().to_string()))
+        .collect();
+    out.push((enabled.to_string(), "true".to_string()));
+    Some(out)
+}
+
+/// Map one bridge poll response of `channel` to a [`LoginPoll`].
+pub(crate) fn classify_login_poll(channel: &str, data: &Value) -> LoginPoll {
+    let s = |k: &str| data.get(k).and_then(Value::as_str).unwrap_or("");
+    let status = s("status");
+    let failed = |fallback: &str| {
+        let msg = [s("error"), s("message"), fallback]
+            .into_iter()
+            .find(|m| !m.is_empty())
+            .unwrap_or_default();
+        LoginPoll::Failed(msg.to_string())
+    };
+    let done = |creds: &[&str]| match env_entries(channel, creds) {
+        Some(entries) => LoginPoll::Done(entries),
+        None => LoginPoll::Failed(format!("{channel}: 返回的凭证不完整")),
+    };
+    match channel {
+        // wait → scaned → confirmed(token)
+        "wechat" => match status {
+            "wait" | "" => LoginPoll::Pending,
+            "scaned" => LoginPoll::Scanned,
+            "confirmed" => done(&[s("token")]),
+            "expired" => LoginPoll::Expired,
+            _ => failed(status),
+        },
+        "wecom" => match status {
+            "pending" | "" => LoginPoll::Pending,
+            "success" => done(&[s("bot_id"), s("secret")]),
+            "expired" => LoginPoll::Expired,
+            _ => failed(status),
+        },
+        // Device Flow：成功时没有 status，只有 app_id / app_secret
+        "feishu" => {
+            if !s("app_id").is_empty() {
+                return done(&[s("app_id"), s("app_secret")]);
+            }
+            match status {
+                "authorization_pending" | "slow_down" | "" => LoginPoll::Pending,
+                "expired_token" => LoginPoll::Expired,
+                _ => failed(status),
+            }
+        }
+        // poll_and_create：已有机器人时 app_secret 为空，需要用户去 QQ 开放平台重置
+        "qqbot" => match status {
+            "waiting" | "" => LoginPoll::Pending,
+            "ok" if s("app_secret").is_empty() => {
+                failed("机器人已存在，开放平台不再返回 AppSecret，请在 QQ 开放平台重置后手动填写")
+            }
+            "ok" => done(&[s("app_id"), s("app_secret")]),
+            _ => failed(status),
+        },
+        _ => failed("unsupported channel"),
+    }
+}
+
+fn save_env(workspace_id: &str, entries: Vec<(String, String)>) -> Result<Vec<String>, String> {
+    let keys = entries.iter().map(|(k, _)| k.clone()).collect();
+    let entries = entries
+        .into_iter()
+        .map(|(key, value)| EnvEntry { key, value })
+        .collect();
+    workspace_update_env(workspace_id.to_string(), entries).map_err(|e| e.message)?;
+    log_to_file(&format!(
+        "[im_setup] wrote {keys:?} into {workspace_id}/.env"
+    ));
+    Ok(keys)
+}
+
+async fn telegram_get_me(token: &str) -> Result<Option<String>, String> {
+    let url = format!("https://api.telegram.org/bot{token}/getMe");
+    let resp = net::http_client()
+        .get(&url)
+        .timeout(Duration::from_secs(15))
+        .send()
+        .await
+        // reqwest 的错误信息里带完整 URL，也就带着 token
+        .map_err(|e| format!("无法连接 Telegram: {}", e.without_url()))?;
+    let body: Value = resp.json().await.map_err(|e| e.without_url().to_string())?;
+    if body.get("ok").and_then(Value::as_bool) == Some(true) {
+        Ok(body["result"]["username"].as_str().map(|u| format!("@{u}")))
+    } else {
+        Err(body["description"]
+            .as_str()
+            .unwrap_or("token rejected")
+            .to_string())
+    }
+}
+
+async fn dingtalk_access_token(app_key: &str, app_secret: &str) -> Result<(), String> {
+    let resp = net::http_client()
+        .post("https://api.dingtalk.com/v1.0/oauth2/accessToken")
+        .timeout(Duration::from_secs(15))
+        .json(&serde_json::json!({ "appKey": app_key, "appSecret": app_secret }))
+        .send()
+        .await
+        .map_err(|e| format!("无法连接钉钉开放平台: {e}"))?;
+    let body: Value = resp.json().await.map_err(|e| e.to_string())?;
+    if body.get("accessToken").and_then(Value::as_str).is_some() {
+        Ok(())
+    } else {
+        Err(body["message"]
+            .as_str()
+            .unwrap_or("credentials rejected")
+            .to_string())
+    }
+}
+
+/// Bridge validators print `{valid, error?}`.
+fn bridge_validate(
+    venv_dir: &str,
+    args: &[&str],
+    secret: &str,
+) -> Result<Result<Option<String>, String>, String> {
+    let raw =
+        run_bridge_with_secrets(venv_dir, args, &serde_json::json!({ "app_secret": secret }))?;
+    let v: Value = serde_json::from_str(&raw).map_err(|e| format!("bridge output: {e}"))?;
+    Ok(if v["valid"].as_bool() == Some(true) {
+        Ok(None)
+    } else {
+        Err(v["error"].as_str().unwrap_or("invalid").to_string())
+    })
+}
+
+/// Check a channel's credentials.  `token` is the bot token (Telegram) or
+/// the app secret (`appId` required for DingTalk / Feishu / QQ).  With
+/// `workspaceId`, valid credentials are written into that workspace's `.env`.
+#[tauri::command]
+pub async fn im_validate_token(
+    venv_dir: String,
+    channel: String,
+    token: String,
+    app_id: Option<String>,
+    domain: Option<String>,
+    workspace_id: Option<String>,
+) -> CmdResult<ImTokenCheck> {
+    let token = token.trim().to_string();
+    let app_id = app_id.unwrap_or_default().trim().to_string();
+    if token.is_empty() {
+        return Err("INVALID_ARGUMENT|token 不能为空".into());
+    }
+    if channel != "telegram" && app_id.is_empty() {
+        return Err(format!("INVALID_ARGUMENT|{channel} 需要同时提供 appId").into());
+    }
+    net::ensure_online("校验 IM 凭证")?;
+    let outcome = match channel.as_str() {
+        "telegram" => telegram_get_me(&token).await,
+        "dingtalk" => dingtalk_access_token(&app_id, &token).await.map(|_| None),
+        "feishu" | "qqbot" => {
+            let (venv, ch, id, secret) = (venv_dir, channel.clone(), app_id.clone(), token.clone());
+            spawn_blocking_result(move || {
+                let d = domain.unwrap_or_else(|| "feishu".to_string());
+                let args: Vec<&str> = if ch == "feishu" {
+                    vec!["feishu-validate", "--app-id", &id, "--domain", &d]
+                } else {
+                    vec!["qqbot-validate", "--app-id", &id]
+                };
+                bridge_validate(&venv, &args, &secret)
+            })
+            .await?
+        }
+        other => {
+            return Err(format!("INVALID_ARGUMENT|不支持校验的渠道: {other}").into());
+        }
+    };
+    let mut check = ImTokenCheck {
+        channel: channel.clone(),
+        valid: outcome.is_ok(),
+        identity: None,
+        error: None,
+        saved_keys: Vec::new(),
+    };
+    match outcome {
+        Ok(identity) => check.identity = identity,
+        Err(e) => check.error = Some(e),
+    }
+    if let (true, Some(ws)) = (check.valid, workspace_id) {
+        let creds: Vec<&str> = if channel == "telegram" {
+            vec![token.as_str()]
+        } else {
+            vec![app_id.as_str(), token.as_str()]
+        };
+        if let Some(entries) = env_entries(&channel, &creds) {
+            check.saved_keys = spawn_blocking_result(move || save_env(&ws, entries)).await?;
+        }
+    }
+    Ok(check)
+}
+
+/// `(start args, QR content field, session field, poll command + flag)`.
+fn login_flow(
+    channel: &str,
+) -> Option<(&'static str, &'static str, &'static str, [&'static str; 2])> {
+    Some(match channel {
+        "wechat" => (
+            "wechat-onboard-start",
+            "qrcode_url",
+            "qrcode",
+            ["wechat-onboard-poll", "--qrcode"],
+        ),
+        "wecom" => (
+            "wecom-onboard-start",
+            "auth_url",
+            "scode",
+            ["wecom-onboard-poll", "--scode"],
+        ),
+        "feishu" => (
+            "feishu-onboard-start",
+            "verification_uri",
+            "device_code",
+            ["feishu-onboard-poll", "--device-code"],
+        ),
+        "qqbot" => (
+            "qqbot-onboard-start",
+            "qr_url",
+            "session_id",
+            ["qqbot-onboard-poll-and-create", "--session-id"],
+        ),
+        _ => return None,
+    })
+}
+
+fn bridge_json(venv_dir: &str, args: &[&str]) -> Result<Value, String> {
+    let raw = run_python_module_json(venv_dir, BRIDGE, args, &[])?;
+    serde_json::from_str(&raw).map_err(|e| format!("bridge output: {e}"))
+}
+
+/// Start a QR login for `channel` (`wechat` | `wecom` | `feishu` | `qqbot`)
+/// and return its login id; progress arrives as `im-login` events.
+#[tauri::command]
+pub async fn im_begin_login(
+    app: tauri::AppHandle,
+    venv_dir: String,
+    workspace_id: String,
+    channel: String,
+    domain: Option<String>,
+) -> CmdResult<String> {
+    let Some((start_cmd, qr_field, session_field, [poll_cmd, poll_flag])) = login_flow(&channel)
+    else {
+        return Err(format!("INVALID_ARGUMENT|该渠道没有扫码登录: {channel}").into());
+    };
+    net::ensure_online("IM 扫码登录")?;
+    let domain = domain.unwrap_or_else(|| "feishu".to_string());
+    let start_venv = venv_dir.clone();
+    let start_domain = domain.clone();
+    let started = spawn_blocking_result(move || {
+        let mut args: Vec<&str> = vec![start_cmd];
+        if start_cmd == "feishu-onboard-start" {
+            args.extend(["--domain", start_domain.as_str()]);
+        }
+        bridge_json(&start_venv, &args)
+    })
+    .await?;
+    let field = |k: &str| {
+        started
+            .get(k)
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string()
+    };
+    let (qr, session) = (field(qr_field), field(session_field));
+    if qr.is_empty() || session.is_empty() {
+        let msg = field("error");
+        return Err(format!(
+            "二维码获取失败: {}",
+            if msg.is_empty() {
+                started.to_string()
+            } else {
+                msg
+            }
+        )
+        .into());
+    }
+
+    let login_id = format!("{channel}-{}", now_ms());
+    let cancel = Arc::new(AtomicBool::new(false));
+    LOGINS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(login_id.clone(), cancel.clone());
+    let event = {
+        let (login_id, channel) = (login_id.clone(), channel.clone());
+        move |stage: &str, message: Option<String>, saved_keys: Vec<String>| ImLoginEvent {
+            login_id: login_id.clone(),
+            channel: channel.clone(),
+            stage: stage.to_string(),
+            qr_content: None,
+            message,
+            saved_keys,
+        }
+    };
+    emit_if_ui_live(
+        &app,
+        LOGIN_EVENT,
+        ImLoginEvent {
+            qr_content: Some(qr),
+            ..event("qr", None, Vec::new())
+        },
+    );
+    log_to_file(&format!("[im_setup] login {login_id} started"));
+
+    let id = login_id.clone();
+    let _ = std::thread::Builder::new()
+        .name(format!("im-login-{channel}"))
+        .spawn(move || {
+            // Device Flow 要求至少按 interval 轮询，其余渠道 2 秒一次
+            let interval = started
+                .get("interval")
+                .and_then(Value::as_u64)
+                .map_or(Duration::from_secs(2), Duration::from_secs);
+            let deadline = Instant::now() + LOGIN_TIMEOUT;
+            let mut scanned = false;
+            let (stage, message, saved) = loop {
+                std::thread::sleep(interval);
+                if cancel.load(Ordering::SeqCst) {
+                    break ("cancelled", None, Vec::new());
+                }
+                if Instant::now() > deadline {
+                    break ("expired", None, Vec::new());
+                }
+                let mut args = vec![poll_cmd, poll_flag, session.as_str()];
+                if channel == "feishu" {
+                    args.extend(["--domain", domain.as_str()]);
+                }
+                let poll = match bridge_json(&venv_dir, &args) {
+                    Ok(data) => classify_login_poll(&channel, &data),
+                    // 单次网络抖动不终止流程，直到超时
+                    Err(e) => {
+                        log_to_file(&format!("[im_setup] {id} poll failed: {e}"));
+                        LoginPoll::Pending
+                    }
+                };
+                match poll {
+                    LoginPoll::Pending => {}
+                    LoginPoll::Scanned => {
+                        if !scanned {
+                            scanned = true;
+                            emit_if_ui_live(&app, LOGIN_EVENT, event("scanned", None, Vec::new()));
+                        }
+                    }
+                    LoginPoll::Done(entries) => match save_env(&workspace_id, entries) {
+                        Ok(keys) => break ("success", None, keys),
+                        Err(e) => break ("error", Some(e), Vec::new()),
+                    },
+                    LoginPoll::Expired => break ("expired", None, Vec::new()),
+                    LoginPoll::Failed(msg) => break ("error", Some(msg), Vec::new()),
+                }
+            };
+            LOGINS.lock().unwrap_or_else(|e| e.into_inner()).remove(&id);
+            log_to_file(&format!("[im_setup] login {id} finished: {stage}"));
+            emit_if_ui_live(&app, LOGIN_EVENT, event(stage, message, saved));
+        });
+    Ok(login_id)
+}
+
+/// Abandon a QR login started by `im_begin_login`.
+#[tauri::command]
+pub fn im_cancel_login(login_id: String) -> bool {
+    match LOGINS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&login_id)
+    {
+        Some(flag) => {
+            flag.store(true, Ordering::SeqCst);
+            true
+        }
+        None => false,
+    }
+}
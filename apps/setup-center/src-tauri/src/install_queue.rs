@@ -0,0 +1,198 @@
+//! Per-venv install queue.
+//!
+//! pip installs, IM channel dependency installs and skill install /
+//! uninstall all mutate the same virtualenv; two of them running at once
+//! (double clicks, installing two skills back to back) leave half-written
+//! `site-packages` behind.  Every such command now takes a slot from
+//! [`acquire`] before touching the venv: slots for one venv are granted
+//! strictly in arrival order, different venvs proceed in parallel.
+//!
+//! Queue changes are emitted as `install_queue` events
+//! (`{ venvDir, id, kind, status: queued|running|done|cancelled, position }`,
+//! position 0 = running) and `get_install_queue()` returns a snapshot.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use crate::{emit_if_ui_live, log_to_file};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallQueueEntry {
+    venv_dir: String,
+    id: String,
+    /// `pip` / `pip-uninstall` / `channel-deps` / `skill` / `skill-uninstall`
+    kind: String,
+    status: &'static str,
+    position: usize,
+}
+
+#[derive(Default)]
+struct Queues {
+    /// venv key -> waiting + running entries, head is running.
+    by_venv: HashMap<String, VecDeque<(u64, String, String)>>,
+    next_ticket: u64,
+}
+
+static QUEUES: Lazy<(Mutex<Queues>, Condvar)> =
+    Lazy::new(|| (Mutex::new(Queues::default()), Condvar::new()));
+
+pub(crate) fn venv_key(venv_dir: &str) -> String {
+    let p = std::fs::canonicalize(venv_dir)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| venv_dir.trim_end_matches(['/', '\\']).to_string());
+    if cfg!(windows) {
+        p.to_lowercase()
+    } else {
+        p
+    }
+}
+
+fn entries_for(venv_dir: &str, q: &VecDeque<(u64, String, String)>) -> Vec<InstallQueueEntry> {
+    q.iter()
+        .enumerate()
+        .map(|(pos, (_, id, kind))| InstallQueueEntry {
+            venv_dir: venv_dir.to_string(),
+            id: id.clone(),
+            kind: kind.clone(),
+            status: if pos == 0 { "running" } else { "queued" },
+            position: pos,
+        })
+        .collect()
+}
+
+fn emit_positions(app: &tauri::AppHandle, key: &str, venv_dir: &str, queues: &Queues) {
+    if let Some(q) = queues.by_venv.get(key) {
+        for entry in entries_for(venv_dir, q) {
+            emit_if_ui_live(app, "install_queue", entry);
+        }
+    }
+}
+
+/// Holds the venv until dropped; the next queued install then starts.
+pub(crate) struct InstallSlot {
+    app: tauri::AppHandle,
+    key: String,
+    venv_dir: String,
+    ticket: u64,
+    id: String,
+    kind: String,
+}
+
+impl Drop for InstallSlot {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*QUEUES;
+        let Ok(mut queues) = lock.lock() else { return };
+        if let Some(q) = queues.by_venv.get_mut(&self.key) {
+            q.retain(|(t, _, _)| *t != self.ticket);
+            if q.is_empty() {
+                queues.by_venv.remove(&self.key);
+            }
+        }
+        emit_if_ui_live(
+            &self.app,
+            "install_queue",
+            InstallQueueEntry {
+                venv_dir: self.venv_dir.clone(),
+                id: self.id.clone(),
+                kind: self.kind.clone(),
+                status: "done",
+                position: 0,
+            },
+        );
+        emit_positions(&self.app, &self.key, &self.venv_dir, &queues);
+        cvar.notify_all();
+    }
+}
+
+/// Block until this install is at the head of `venv_dir`'s queue.
+/// Returns `BRIDGE_CANCELLED|...` if `cancel` is set while still waiting.
+pub(crate) fn acquire(
+    app: &tauri::AppHandle,
+    venv_dir: &str,
+    kind: &str,
+    id: &str,
+    cancel: Option<&AtomicBool>,
+) -> Result<InstallSlot, String> {
+    let key = venv_key(venv_dir);
+    let (lock, cvar) = &*QUEUES;
+    let mut queues = lock
+        .lock()
+        .map_err(|e| format!("install queue lock failed: {e}"))?;
+    let ticket = queues.next_ticket;
+    queues.next_ticket += 1;
+    queues.by_venv.entry(key.clone()).or_default().push_back((
+        ticket,
+        id.to_string(),
+        kind.to_string(),
+    ));
+    emit_positions(app, &key, venv_dir, &queues);
+    let mut waited = false;
+    loop {
+        let head = queues
+            .by_venv
+            .get(&key)
+            .and_then(|q| q.front())
+            .map(|(t, _, _)| *t);
+        if head == Some(ticket) {
+            break;
+        }
+        if !waited {
+            log_to_file(&format!(
+                "[install-queue] {kind} {id} waiting for {venv_dir}"
+            ));
+            waited = true;
+        }
+        if cancel.is_some_and(|c| c.load(Ordering::SeqCst)) {
+            if let Some(q) = queues.by_venv.get_mut(&key) {
+                q.retain(|(t, _, _)| *t != ticket);
+            }
+            emit_if_ui_live(
+                app,
+                "install_queue",
+                InstallQueueEntry {
+                    venv_dir: venv_dir.to_string(),
+                    id: id.to_string(),
+                    kind: kind.to_string(),
+                    status: "cancelled",
+                    position: 0,
+                },
+            );
+            emit_positions(app, &key, venv_dir, &queues);
+            cvar.notify_all();
+            return Err(format!(
+                "BRIDGE_CANCELLED|{kind} {id} cancelled while queued"
+            ));
+        }
+        queues = cvar
+            .wait_timeout(queues, Duration::from_millis(250))
+            .map_err(|e| format!("install queue lock failed: {e}"))?
+            .0;
+    }
+    Ok(InstallSlot {
+        app: app.clone(),
+        key,
+        venv_dir: venv_dir.to_string(),
+        ticket,
+        id: id.to_string(),
+        kind: kind.to_string(),
+    })
+}
+
+/// Current queue across all venvs (running entries have position 0).
+#[tauri::command]
+pub fn get_install_queue() -> Vec<InstallQueueEntry> {
+    let (lock, _) = &*QUEUES;
+    let Ok(queues) = lock.lock() else {
+        return Vec::new();
+    };
+    queues
+        .by_venv
+        .iter()
+        .flat_map(|(key, q)| entries_for(key, q))
+        .collect()
+}
@@ -0,0 +1,560 @@
+//! Snapshot of how a backend was launched.
+//!
+//! "Works from the terminal, not from the app" is almost always an
+//! environment difference: a `PYTHONPATH` the desktop strips, a proxy
+//! variable the app never saw, a `.env` entry overriding what we injected.
+//! Right before `service_start` spawns the backend it records the command
+//! line, working directory and the environment the process will end up
+//! with, and `get_service_launch_env` hands that back to the UI.
+//!
+//! The environment is resolved the way the backend sees it: the desktop's
+//! own environment, minus the variables `apply_dual_runtime_env` removes,
+//! plus what `service_start` sets, plus the workspace `.env` (loaded by the
+//! Python side with `override=True`, so it wins).  Each entry says where it
+//! came from.  Values are redacted before they touch the disk: secret-named
+//! variables are masked outright, everything else goes through
+//! [`redact::redact_text`] with the workspace's configured secrets.
+//!
+//! The record lives in `run/openakita-{ws}.launch.json` next to the PID
+//! file and is kept after the backend exits, so the last launch can still
+//! be inspected after a crash.
+//!
+//! Each record also carries a [`Fingerprint`]: hashes of the resolved
+//! environment, the openakita and Python versions and the entries of
+//! `data/llm_endpoints.json`.  A start that survives its first seconds is
+//! copied to `openakita-{ws}.launch-ok.json`; when a later start fails,
+//! `explain_start_failure` diffs the two ("OPENAI_BASE_URL modified,
+//! openakita upgraded 1.25.13→1.26.0") instead of leaving "it worked
+//! yesterday" to guesswork.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::errors::CmdResult;
+use crate::{
+    atomic_write, compat, env_overlay, is_pid_running, log_to_file, redact, run_dir, sha256_hex,
+    trace, workspace_dir,
+};
+
+const MASK: &str = "***";
+/// Variables that differ on every start or shell and say nothing about why
+/// a start failed.
+const VOLATILE_ENV: &[&str] = &[
+    "OPENAKITA_SPAWN_STARTED_AT_MS",
+    "OPENAKITA_DESKTOP_SESSION_TOKEN",
+    "_",
+    "OLDPWD",
+    "PWD",
+    "SHLVL",
+    "SSH_AUTH_SOCK",
+    "TERM_SESSION_ID",
+    "WINDOWID",
+    "XDG_SESSION_ID",
+    "SECURITYSESSIONID",
+];
+
+/// What a start depended on, hashed so secrets are never stored.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Fingerprint {
+    /// name -> hash of the value the backend saw.
+    pub env: BTreeMap<String, String>,
+    pub openakita: Option<String>,
+    pub python: Option<String>,
+    /// `llm_endpoints.json` entry (`endpoints/<name>`, or a top-level key)
+    /// -> hash.
+    pub endpoints: BTreeMap<String, String>,
+    /// Hash of program + arguments.
+    pub command: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchChange {
+    /// "env" | "openakita" | "python" | "endpoint" | "command"
+    pub kind: String,
+    pub name: String,
+    /// "added" | "removed" | "modified" | "upgraded" | "downgraded"
+    pub change: String,
+    /// Redacted values (env) or versions, when known.
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartFailureExplanation {
+    pub workspace_id: String,
+    /// unix epoch seconds of the last successful start.
+    pub baseline_at: Option<u64>,
+    pub attempt_at: u64,
+    pub changes: Vec<LaunchChange>,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchEnvVar {
+    pub name: String,
+    pub value: String,
+    /// "inherited" | "desktop" | "dotenv"
+    pub source: String,
+    #[serde(default)]
+    pub redacted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchEnv {
+    pub workspace_id: String,
+    #[serde(default)]
+    pub pid: u32,
+    /// unix epoch seconds
+    #[serde(default)]
+    pub started_at: u64,
+    pub program: String,
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// venv the backend was started from (reused by `openakita_service_restart`).
+    #[serde(default)]
+    pub venv_dir: Option<String>,
+    pub env: Vec<LaunchEnvVar>,
+    /// Inherited variables the desktop removed before spawning.
+    #[serde(default)]
+    pub removed: Vec<String>,
+    #[serde(default)]
+    pub fingerprint: Fingerprint,
+    /// Filled in by `get_service_launch_env`, not stored.
+    #[serde(default, skip_deserializing)]
+    pub running: bool,
+}
+
+fn launch_env_file(workspace_id: &str) -> PathBuf {
+    run_dir().join(format!("openakita-{workspace_id}.launch.json"))
+}
+
+fn last_ok_file(workspace_id: &str) -> PathBuf {
+    run_dir().join(format!("openakita-{workspace_id}.launch-ok.json"))
+}
+
+fn short_hash(data: &str) -> String {
+    sha256_hex(data.as_bytes())[..16].to_string()
+}
+
+/// Environment variable names are case-insensitive on Windows.
+fn key_eq(a: &str, b: &str) -> bool {
+    if cfg!(windows) {
+        a.eq_ignore_ascii_case(b)
+    } else {
+        a == b
+    }
+}
+
+fn set_var(env: &mut Vec<LaunchEnvVar>, name: &str, value: String, source: &str) {
+    env.retain(|v| !key_eq(&v.name, name));
+    env.push(LaunchEnvVar {
+        name: name.to_string(),
+        value,
+        source: source.to_string(),
+        redacted: false,
+    });
+}
+
+/// Layer `inherited` ← `overrides` (None = removed) ← `dotenv` and redact
+/// the result.  Returns (sorted variables, removed names).
+pub(crate) fn resolve_env(
+    inherited: Vec<(String, String)>,
+    overrides: Vec<(String, Option<String>)>,
+    dotenv: Vec<(String, String)>,
+    known_secrets: &[String],
+) -> (Vec<LaunchEnvVar>, Vec<String>) {
+    let (mut env, removed) = layer_env(inherited, overrides, dotenv);
+    redact_env(&mut env, known_secrets);
+    (env, removed)
+}
+
+/// [`resolve_env`] without the redaction.
+fn layer_env(
+    inherited: Vec<(String, String)>,
+    overrides: Vec<(String, Option<String>)>,
+    dotenv: Vec<(String, String)>,
+) -> (Vec<LaunchEnvVar>, Vec<String>) {
+    let mut env = Vec::new();
+    for (k, v) in inherited {
+        set_var(&mut env, &k, v, "inherited");
+    }
+    let mut removed = Vec::new();
+    for (k, v) in overrides {
+        match v {
+            Some(v) => set_var(&mut env, &k, v, "desktop"),
+            None => {
+                let before = env.len();
+                env.retain(|e| !key_eq(&e.name, &k));
+                if env.len() != before {
+                    removed.push(k);
+                }
+            }
+        }
+    }
+    for (k, v) in dotenv {
+        set_var(&mut env, &k, v, "dotenv");
+    }
+    env.sort_by(|a, b| {
+        a.name
+            .to_ascii_lowercase()
+            .cmp(&b.name.to_ascii_lowercase())
+    });
+    removed.sort();
+    removed.dedup();
+    (env, removed)
+}
+
+fn redact_env(env: &mut [LaunchEnvVar], known_secrets: &[String]) {
+    for var in env {
+        let masked = if trace::is_secret_name(&var.name) {
+            if var.value.is_empty() {
+                var.value.clone()
+            } else {
+                MASK.to_string()
+            }
+        } else {
+            redact::redact_text(&var.value, known_secrets)
+        };
+        var.redacted = masked != var.value;
+        var.value = masked;
+    }
+}
+
+/// `version` from the `pyvenv.cfg` of `venv_dir`.
+fn python_version(venv_dir: &Path) -> Option<String> {
+    let cfg = fs::read_to_string(venv_dir.join("pyvenv.cfg")).ok()?;
+    cfg.lines().find_map(|line| {
+        let (k, v) = line.split_once('=')?;
+        matches!(k.trim(), "version" | "version_info").then(|| v.trim().to_string())
+    })
+}
+
+/// Entries of `llm_endpoints.json`: objects in a top-level array are keyed
+/// `<array>/<name>`, anything else by its top-level key.
+pub(crate) fn endpoint_hashes(config: &Value) -> BTreeMap<String, String> {
+    let mut out = BTreeMap::new();
+    let Some(obj) = config.as_object() else {
+        return out;
+    };
+    for (key, value) in obj {
+        match value.as_array() {
+            Some(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    let name = item
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                        .unwrap_or_else(|| format!("#{i}"));
+                    out.insert(format!("{key}/{name}"), short_hash(&item.to_string()));
+                }
+            }
+            None => {
+                out.insert(key.clone(), short_hash(&value.to_string()));
+            }
+        }
+    }
+    out
+}
+
+fn fingerprint(cmd: &Command, env: &[LaunchEnvVar], venv_dir: &Path, ws_dir: &Path) -> Fingerprint {
+    let command = std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|a| a.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("\0");
+    let endpoints = fs::read_to_string(ws_dir.join("data").join("llm_endpoints.json"))
+        .ok()
+        .and_then(|s| serde_json::from_str::<Value>(s.trim_start_matches('\u{feff}')).ok())
+        .map(|v| endpoint_hashes(&v))
+        .unwrap_or_default();
+    Fingerprint {
+        env: env
+            .iter()
+            .filter(|v| !VOLATILE_ENV.iter().any(|n| key_eq(n, &v.name)))
+            .map(|v| (v.name.clone(), short_hash(&v.value)))
+            .collect(),
+        openakita: compat::venv_openakita_version(&venv_dir.to_string_lossy()),
+        python: python_version(venv_dir),
+        endpoints,
+        command: short_hash(&command),
+    }
+}
+
+/// Capture what `cmd` is about to run with.  Call right before `spawn()`.
+pub(crate) fn capture(
+    cmd: &Command,
+    workspace_id: &str,
+    ws_dir: &Path,
+    venv_dir: &Path,
+) -> LaunchEnv {
+    let known = redact::workspace_secret_values(workspace_id);
+    let overrides: Vec<(String, Option<String>)> = cmd
+        .get_envs()
+        .map(|(k, v)| {
+            (
+                k.to_string_lossy().to_string(),
+                v.map(|v| v.to_string_lossy().to_string()),
+            )
+        })
+        .collect();
+    let dotenv = env_overlay::merged_env(ws_dir, env_overlay::profile_of(workspace_id).as_deref());
+    let inherited: Vec<(String, String)> = std::env::vars_os()
+        .map(|(k, v)| {
+            (
+                k.to_string_lossy().to_string(),
+                v.to_string_lossy().to_string(),
+            )
+        })
+        .collect();
+    // 指纹基于原始值（只存哈希）
+    let (raw, _) = layer_env(inherited.clone(), overrides.clone(), dotenv.clone());
+    let fingerprint = fingerprint(cmd, &raw, venv_dir, ws_dir);
+    let (env, removed) = resolve_env(inherited, overrides, dotenv, &known);
+    LaunchEnv {
+        workspace_id: workspace_id.to_string(),
+        pid: 0,
+        started_at: 0,
+        program: redact::redact_text(&cmd.get_program().to_string_lossy(), &known),
+        args: cmd
+            .get_args()
+            .map(|a| redact::redact_text(&a.to_string_lossy(), &known))
+            .collect(),
+        cwd: cmd
+            .get_current_dir()
+            .map(|d| d.to_string_lossy().to_string()),
+        venv_dir: Some(venv_dir.to_string_lossy().to_string()),
+        env,
+        removed,
+        fingerprint,
+        running: false,
+    }
+}
+
+/// Persist a captured launch once the process is up.  Best effort: a
+/// failure here must not fail the start.
+pub(crate) fn record(mut launch: LaunchEnv, pid: u32, started_at: u64) {
+    launch.pid = pid;
+    launch.started_at = started_at;
+    let path = launch_env_file(&launch.workspace_id);
+    let result = serde_json::to_string_pretty(&launch)
+        .map_err(|e| e.to_string())
+        .and_then(|json| atomic_write(&path, json).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        log_to_file(&format!(
+            "[launch_env] write {} failed: {e}",
+            path.display()
+        ));
+    }
+}
+
+/// Keep the workspace's launch record as the baseline for later failures.
+/// Call once the backend `pid` has survived its start.
+pub(crate) fn mark_success(workspace_id: &str, pid: u32) {
+    let Some(launch) = read_launch(&launch_env_file(workspace_id)) else {
+        return;
+    };
+    if launch.pid != pid {
+        return;
+    }
+    let path = last_ok_file(workspace_id);
+    let result = serde_json::to_string_pretty(&launch)
+        .map_err(|e| e.to_string())
+        .and_then(|json| atomic_write(&path, json).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        log_to_file(&format!(
+            "[launch_env] write {} failed: {e}",
+            path.display()
+        ));
+    }
+}
+
+fn read_launch(path: &Path) -> Option<LaunchEnv> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// venv of the workspace's last recorded start.
+pub(crate) fn last_venv_dir(workspace_id: &str) -> Option<String> {
+    read_launch(&launch_env_file(workspace_id))?
+        .venv_dir
+        .filter(|v| !v.is_empty())
+}
+
+fn change(
+    kind: &str,
+    name: &str,
+    change: &str,
+    before: Option<String>,
+    after: Option<String>,
+) -> LaunchChange {
+    LaunchChange {
+        kind: kind.to_string(),
+        name: name.to_string(),
+        change: change.to_string(),
+        before,
+        after,
+    }
+}
+
+fn diff_map(
+    kind: &str,
+    before: &BTreeMap<String, String>,
+    after: &BTreeMap<String, String>,
+    out: &mut Vec<LaunchChange>,
+) {
+    let names: std::collections::BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    for name in names {
+        let what = match (before.get(name), after.get(name)) {
+            (None, Some(_)) => "added",
+            (Some(_), None) => "removed",
+            (Some(a), Some(b)) if a != b => "modified",
+            _ => continue,
+        };
+        out.push(change(kind, name, what, None, None));
+    }
+}
+
+fn diff_version(
+    kind: &str,
+    before: &Option<String>,
+    after: &Option<String>,
+    out: &mut Vec<LaunchChange>,
+) {
+    let what = match (before, after) {
+        (None, Some(_)) => "added",
+        (Some(_), None) => "removed",
+        (Some(a), Some(b)) if a != b => {
+            match (compat::parse_version(a), compat::parse_version(b)) {
+                (Some(x), Some(y)) if y > x => "upgraded",
+                (Some(x), Some(y)) if y < x => "downgraded",
+                _ => "modified",
+            }
+        }
+        _ => return,
+    };
+    out.push(change(kind, kind, what, before.clone(), after.clone()));
+}
+
+/// What differs between the fingerprint of a good start and a later one.
+pub(crate) fn diff(before: &Fingerprint, after: &Fingerprint) -> Vec<LaunchChange> {
+    let mut out = Vec::new();
+    diff_map("env", &before.env, &after.env, &mut out);
+    diff_version("openakita", &before.openakita, &after.openakita, &mut out);
+    diff_version("python", &before.python, &after.python, &mut out);
+    diff_map("endpoint", &before.endpoints, &after.endpoints, &mut out);
+    if before.command != after.command {
+        out.push(change("command", "command line", "modified", None, None));
+    }
+    out
+}
+
+/// "OPENAI_BASE_URL modified, openakita upgraded 1.25.13→1.26.0".
+pub(crate) fn summarize(changes: &[LaunchChange]) -> String {
+    const SHOWN: usize = 8;
+    let mut parts: Vec<String> = changes
+        .iter()
+        .take(SHOWN)
+        .map(|c| {
+            let name = match c.kind.as_str() {
+                "endpoint" => format!("endpoint {}", c.name),
+                _ => c.name.clone(),
+            };
+            match (c.kind.as_str(), &c.before, &c.after) {
+                ("openakita" | "python", Some(a), Some(b)) => {
+                    format!("{name} {} {a}→{b}", c.change)
+                }
+                _ => format!("{name} {}", c.change),
+            }
+        })
+        .collect();
+    if changes.len() > SHOWN {
+        parts.push(format!("{} more", changes.len() - SHOWN));
+    }
+    parts.join(", ")
+}
+
+fn explain(workspace_id: &str) -> CmdResult<StartFailureExplanation> {
+    if workspace_id.is_empty() || !workspace_dir(workspace_id).is_dir() {
+        return Err(format!("NOT_FOUND|工作区不存在: {workspace_id}").into());
+    }
+    let attempt = read_launch(&launch_env_file(workspace_id)).ok_or_else(|| {
+        format!("NOT_FOUND|工作区 {workspace_id} 还没有由桌面端启动过后端 / no recorded launch")
+    })?;
+    let mut out = StartFailureExplanation {
+        workspace_id: workspace_id.to_string(),
+        baseline_at: None,
+        attempt_at: attempt.started_at,
+        changes: Vec::new(),
+        summary: String::new(),
+    };
+    let Some(baseline) = read_launch(&last_ok_file(workspace_id)) else {
+        out.summary = "没有成功启动的记录，无从对比 / no successful start recorded".into();
+        return Ok(out);
+    };
+    out.baseline_at = Some(baseline.started_at);
+    if baseline.pid == attempt.pid && baseline.started_at == attempt.started_at {
+        out.summary = "上次启动是成功的 / the last start succeeded".into();
+        return Ok(out);
+    }
+    out.changes = diff(&baseline.fingerprint, &attempt.fingerprint);
+    // 环境变量附上脱敏后的前后值，方便一眼看出改了什么
+    let value = |launch: &LaunchEnv, name: &str| {
+        launch
+            .env
+            .iter()
+            .find(|v| v.name == name)
+            .map(|v| v.value.clone())
+    };
+    for c in out.changes.iter_mut().filter(|c| c.kind == "env") {
+        c.before = value(&baseline, &c.name);
+        c.after = value(&attempt, &c.name);
+    }
+    out.summary = if out.changes.is_empty() {
+        "与上次成功启动相比没有变化 / nothing changed since the last successful start".into()
+    } else {
+        summarize(&out.changes)
+    };
+    Ok(out)
+}
+
+/// Summary of what changed since the last successful start, for a start
+/// that just failed.  None when there is nothing to compare or no change.
+pub(crate) fn changes_since_last_ok(workspace_id: &str) -> Option<String> {
+    explain(workspace_id)
+        .ok()
+        .filter(|e| !e.changes.is_empty())
+        .map(|e| e.summary)
+}
+
+/// Why the workspace's last start may have failed: what changed in its
+/// environment, openakita / Python versions, endpoints config or command
+/// line since the last start that came up.
+#[tauri::command]
+pub fn explain_start_failure(workspace_id: String) -> CmdResult<StartFailureExplanation> {
+    explain(&workspace_id)
+}
+
+/// The environment, command line and start time of the workspace's last
+/// desktop-started backend.
+#[tauri::command]
+pub fn get_service_launch_env(workspace_id: String) -> CmdResult<LaunchEnv> {
+    if workspace_id.is_empty() || !workspace_dir(&workspace_id).is_dir() {
+        return Err(format!("NOT_FOUND|工作区不存在: {workspace_id}").into());
+    }
+    let content = fs::read_to_string(launch_env_file(&workspace_id)).map_err(|_| {
+        format!("NOT_FOUND|工作区 {workspace_id} 还没有由桌面端启动过后端 / no recorded launch")
+    })?;
+    let mut launch: LaunchEnv =
+        serde_json::from_str(&content).map_err(|e| format!("启动记录已损坏: {e}"))?;
+    launch.running = launch.pid != 0 && is_pid_running(launch.pid);
+    Ok(launch)
+}
@@ -0,0 +1,440 @@
+//! Import from installs that predate Setup Center workspaces.
+//!
+//! Users who ran openakita from a git checkout (or an old config directory)
+//! keep their `.env`, identity files, LLM endpoints and data next to the
+//! code, and a fresh desktop install started them from scratch.
+//! `detect_legacy_installs` looks in the usual places for such a directory;
+//! `migrate_legacy_install` copies it into a workspace — a new one unless
+//! `workspace_id` names an existing one:
+//!
+//! * `.env` is merged key by key;
+//! * `identity/` (SOUL / AGENT / USER / MEMORY, personas, prompts) and
+//!   `data/` (endpoints, databases, memory) are copied file by file, minus
+//!   caches, logs and lock files.
+//!
+//! Anything the workspace already has with different content is a
+//! conflict: kept by default, or with `overwrite` replaced after the old
+//! copy is moved to `backups/legacy-import-<ts>/`.  Files Setup Center
+//! generated and nobody edited are not conflicts.  The source is only
+//! read, never changed.  The legacy backend should be stopped first so its
+//! databases are copied in a consistent state.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::errors::CmdResult;
+use crate::{
+    atomic_write, chrono_like_timestamp, create_workspace, ensure_workspace_scaffold,
+    is_pid_file_valid, log_to_file, read_env_kv, read_pid_file, read_state_file, read_text_lossy,
+    scaffold_integrity, sha256_hex, spawn_blocking_result, update_env_content, workspace_dir,
+    workspaces_dir, EnvEntry,
+};
+
+/// Checkout locations tried under the home directory.
+const CHECKOUT_DIRS: &[&str] = &[
+    "openakita",
+    "OpenAkita",
+    "code/openakita",
+    "dev/openakita",
+    "git/openakita",
+    "projects/openakita",
+    "Projects/openakita",
+    "repos/openakita",
+    "src/openakita",
+    "workspace/openakita",
+    "Desktop/openakita",
+    "Documents/openakita",
+    "Documents/GitHub/openakita",
+    "source/repos/openakita",
+];
+/// Trees imported besides `.env`.
+const IMPORT_TREES: &[&str] = &["identity", "data"];
+/// Skipped anywhere below [`IMPORT_TREES`].
+const SKIP_NAMES: &[&str] = &[
+    "cache",
+    "tmp",
+    "temp",
+    "logs",
+    "__pycache__",
+    ".DS_Store",
+    "scaffold-manifest.json",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyInstall {
+    pub path: String,
+    /// "git-checkout" | "config-dir"
+    pub kind: String,
+    pub env_keys: usize,
+    pub has_endpoints: bool,
+    /// Identity files present, relative to `identity/`.
+    pub identity_files: Vec<String>,
+    pub data_bytes: u64,
+    pub last_modified_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationConflict {
+    /// `.env` key or workspace-relative path.
+    pub item: String,
+    /// "env" | "file"
+    pub kind: String,
+    /// "kept-existing" | "overwritten"
+    pub resolution: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationReport {
+    pub workspace_id: String,
+    pub created_workspace: bool,
+    pub env_imported: Vec<String>,
+    pub files_copied: Vec<String>,
+    /// Already identical in the workspace.
+    pub files_unchanged: usize,
+    pub conflicts: Vec<MigrationConflict>,
+    /// Where replaced workspace files went (`overwrite` only).
+    pub backup_dir: Option<String>,
+}
+
+/// What to do with one file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FileAction {
+    Copy,
+    Unchanged,
+    /// Target differs and is the user's; `overwrite` decides.
+    Conflict,
+}
+
+/// `target` is `None` when the workspace lacks the file; `pristine` when it
+/// is an unedited Setup Center default.
+pub(crate) fn file_action(source_hash: &str, target: Option<&str>, pristine: bool) -> FileAction {
+    match target {
+        None => FileAction::Copy,
+        Some(t) if t == source_hash => FileAction::Unchanged,
+        Some(_) if pristine => FileAction::Copy,
+        Some(_) => FileAction::Conflict,
+    }
+}
+
+/// Key-by-key `.env` merge: `(entries to write, conflicting keys)`.  Keys
+/// missing or empty in the workspace are imported; differing values are
+/// conflicts and only written with `overwrite`.
+pub(crate) fn merge_env(
+    source: &[(String, String)],
+    target: &[(String, String)],
+    overwrite: bool,
+) -> (Vec<EnvEntry>, Vec<String>) {
+    let target: BTreeMap<&str, &str> = target
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    let mut writes = Vec::new();
+    let mut conflicts = Vec::new();
+    for (k, v) in source {
+        if v.trim().is_empty() {
+            continue;
+        }
+        match target.get(k.as_str()) {
+            Some(t) if t == v => continue,
+            Some(t) if !t.trim().is_empty() => {
+                conflicts.push(k.clone());
+                if !overwrite {
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        writes.push(EnvEntry {
+            key: k.clone(),
+            value: v.clone(),
+        });
+    }
+    (writes, conflicts)
+}
+
+/// "git-checkout" / "config-dir" when `dir` holds an install's files.
+pub(crate) fn install_kind(dir: &Path) -> Option<&'static str> {
+    let has_config = dir.join(".env").is_file()
+        || dir.join("data").join("llm_endpoints.json").is_file()
+        || dir.join("identity").join("SOUL.md").is_file();
+    if !has_config {
+        return None;
+    }
+    if dir.join("src").join("openakita").is_dir() {
+        Some("git-checkout")
+    } else {
+        Some("config-dir")
+    }
+}
+
+fn candidate_dirs() -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    if let Some(home) = dirs_next::home_dir() {
+        out.extend(CHECKOUT_DIRS.iter().map(|d| home.join(d)));
+        out.push(home.join(".openakita-legacy"));
+    }
+    if let Some(config) = dirs_next::config_dir() {
+        out.push(config.join("openakita"));
+        out.push(config.join("OpenAkita"));
+    }
+    out
+}
+
+/// Files below `root/tree` worth importing, as `tree/...` relative paths.
+fn import_files(root: &Path, tree: &str) -> Vec<String> {
+    fn walk(root: &Path, rel: &Path, out: &mut Vec<String>) {
+        let Ok(rd) = fs::read_dir(root.join(rel)) else {
+            return;
+        };
+        for e in rd.flatten() {
+            let name = e.file_name().to_string_lossy().to_string();
+            let skip = SKIP_NAMES.contains(&name.as_str())
+                || name.ends_with(".pid")
+                || name.ends_with(".lock")
+                || name.ends_with(".example");
+            let Ok(ft) = e.file_type() else { continue };
+            if skip || ft.is_symlink() {
+                continue;
+            }
+            let child = rel.join(&name);
+            if ft.is_dir() {
+                walk(root, &child, out);
+            } else if ft.is_file() {
+                out.push(child.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+    let mut out = Vec::new();
+    walk(root, Path::new(tree), &mut out);
+    out.sort();
+    out
+}
+
+fn describe(dir: &Path, kind: &str) -> LegacyInstall {
+    let files: Vec<String> = IMPORT_TREES
+        .iter()
+        .flat_map(|t| import_files(dir, t))
+        .collect();
+    let mut data_bytes = 0;
+    let mut last_modified_ms = 0;
+    for rel in files.iter().chain([".env".to_string()].iter()) {
+        if let Ok(meta) = fs::metadata(dir.join(rel)) {
+            if rel.starts_with("data/") {
+                data_bytes += meta.len();
+            }
+            let modified = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_millis() as u64);
+            last_modified_ms = last_modified_ms.max(modified);
+        }
+    }
+    LegacyInstall {
+        path: dir.to_string_lossy().to_string(),
+        kind: kind.to_string(),
+        env_keys: read_env_kv(&dir.join(".env")).len(),
+        has_endpoints: dir.join("data").join("llm_endpoints.json").is_file(),
+        identity_files: files
+            .iter()
+            .filter_map(|f| f.strip_prefix("identity/"))
+            .map(str::to_string)
+            .collect(),
+        data_bytes,
+        last_modified_ms,
+    }
+}
+
+fn file_hash(path: &Path) -> Option<String> {
+    fs::read(path).ok().map(|d| sha256_hex(&d))
+}
+
+fn migrate(
+    source: &Path,
+    workspace_id: &str,
+    created_workspace: bool,
+    overwrite: bool,
+) -> Result<MigrationReport, String> {
+    let dir = workspace_dir(workspace_id);
+    ensure_workspace_scaffold(&dir)?;
+    let pristine: Vec<String> = scaffold_integrity(&dir)
+        .into_iter()
+        .filter(|f| f.status == "pristine")
+        .map(|f| f.path)
+        .collect();
+    let backup_dir = dir
+        .join("backups")
+        .join(format!("legacy-import-{}", chrono_like_timestamp()));
+    let mut conflicts = Vec::new();
+    let mut files_copied = Vec::new();
+    let mut files_unchanged = 0;
+    let mut backed_up = false;
+
+    // ── .env：逐键合并 ──
+    let env_path = dir.join(".env");
+    let (writes, env_conflicts) = merge_env(
+        &read_env_kv(&source.join(".env")),
+        &read_env_kv(&env_path),
+        overwrite,
+    );
+    if !writes.is_empty() {
+        let existing = read_text_lossy(&env_path);
+        if overwrite && !env_conflicts.is_empty() {
+            atomic_write(backup_dir.join(".env"), &existing)?;
+            backed_up = true;
+        }
+        atomic_write(&env_path, update_env_content(&existing, &writes))
+            .map_err(|e| format!("write .env failed: {e}"))?;
+    }
+    let resolution = if overwrite {
+        "overwritten"
+    } else {
+        "kept-existing"
+    };
+    conflicts.extend(env_conflicts.into_iter().map(|k| MigrationConflict {
+        item: k,
+        kind: "env".into(),
+        resolution: resolution.into(),
+    }));
+
+    // ── identity/ 与 data/：逐文件复制 ──
+    for rel in IMPORT_TREES.iter().flat_map(|t| import_files(source, t)) {
+        let from = source.join(&rel);
+        let to = dir.join(&rel);
+        let Some(src_hash) = file_hash(&from) else {
+            continue;
+        };
+        let target_hash = file_hash(&to);
+        match file_action(&src_hash, target_hash.as_deref(), pristine.contains(&rel)) {
+            FileAction::Unchanged => {
+                files_unchanged += 1;
+                continue;
+            }
+            FileAction::Conflict => {
+                conflicts.push(MigrationConflict {
+                    item: rel.clone(),
+                    kind: "file".into(),
+                    resolution: resolution.into(),
+                });
+                if !overwrite {
+                    continue;
+                }
+                let backup = backup_dir.join(&rel);
+                if let Some(parent) = backup.parent() {
+                    fs::create_dir_all(parent).map_err(|e| format!("create backup dir: {e}"))?;
+                }
+                fs::copy(&to, &backup).map_err(|e| format!("back up {rel}: {e}"))?;
+                backed_up = true;
+            }
+            FileAction::Copy => {}
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("create dir for {rel}: {e}"))?;
+        }
+        fs::copy(&from, &to).map_err(|e| format!("copy {rel}: {e}"))?;
+        files_copied.push(rel);
+    }
+
+    let mut env_imported: Vec<String> = writes.into_iter().map(|e| e.key).collect();
+    env_imported.sort();
+    log_to_file(&format!(
+        "[legacy] imported {} into {workspace_id}: env={} files={} unchanged={files_unchanged} conflicts={}",
+        source.display(),
+        env_imported.len(),
+        files_copied.len(),
+        conflicts.len()
+    ));
+    Ok(MigrationReport {
+        workspace_id: workspace_id.to_string(),
+        created_workspace,
+        env_imported,
+        files_copied,
+        files_unchanged,
+        conflicts,
+        backup_dir: backed_up.then(|| backup_dir.to_string_lossy().to_string()),
+    })
+}
+
+/// Legacy installs found in the usual checkout and config locations.
+#[tauri::command]
+pub async fn detect_legacy_installs() -> CmdResult<Vec<LegacyInstall>> {
+    spawn_blocking_result(|| {
+        let workspaces = workspaces_dir();
+        let mut seen = Vec::new();
+        let mut found = Vec::new();
+        for dir in candidate_dirs() {
+            let Ok(canon) = dir.canonicalize() else {
+                continue;
+            };
+            // 大小写不敏感的文件系统上 openakita / OpenAkita 是同一个目录
+            if seen.contains(&canon) || canon.starts_with(&workspaces) {
+                continue;
+            }
+            seen.push(canon);
+            if let Some(kind) = install_kind(&dir) {
+                found.push(describe(&dir, kind));
+            }
+        }
+        Ok(found)
+    })
+    .await
+    .map_err(Into::into)
+}
+
+/// Import the install at `source` into `workspace_id`, creating the
+/// workspace (named `workspace_name`) when it does not exist yet.
+#[tauri::command]
+pub async fn migrate_legacy_install(
+    source: String,
+    workspace_id: String,
+    workspace_name: Option<String>,
+    overwrite: Option<bool>,
+) -> CmdResult<MigrationReport> {
+    spawn_blocking_result(move || {
+        let src = PathBuf::from(source.trim());
+        if install_kind(&src).is_none() {
+            return Err(format!(
+                "INVALID_ARGUMENT|{} 里没有找到 .env、data/llm_endpoints.json 或 identity/",
+                src.display()
+            ));
+        }
+        if src
+            .canonicalize()
+            .is_ok_and(|c| c.starts_with(workspaces_dir()))
+        {
+            return Err("INVALID_ARGUMENT|源目录已经是一个工作区".into());
+        }
+        let exists = !workspace_id.is_empty()
+            && read_state_file()
+                .workspaces
+                .iter()
+                .any(|w| w.id == workspace_id);
+        let workspace_id = if exists {
+            if read_pid_file(&workspace_id)
+                .as_ref()
+                .is_some_and(is_pid_file_valid)
+            {
+                return Err("BACKEND_RUNNING|请先停止该工作区的后端再导入".into());
+            }
+            workspace_id
+        } else {
+            let name = workspace_name
+                .filter(|n| !n.trim().is_empty())
+                .unwrap_or_else(|| {
+                    let base = src.file_name().map(|n| n.to_string_lossy().to_string());
+                    format!("Imported {}", base.unwrap_or_default())
+                });
+            create_workspace(workspace_id, name, false)
+                .map_err(String::from)?
+                .id
+        };
+        migrate(&src, &workspace_id, !exists, overwrite.unwrap_or(false))
+    })
+    .await
+    .map_err(Into::into)
+}
@@ -0,0 +1,309 @@
+//! Discovery of local OpenAI-compatible LLM servers.
+//!
+//! A local endpoint is only as big as the context the server actually
+//! loaded the model with.  LM Studio defaults to 4096 tokens while the LLM
+//! page happily accepts 200K, and the first long chat then fails with
+//! llama.cpp's `n_keep >= n_ctx` 400.  `discover_local_llm_servers` looks
+//! at the usual localhost ports and asks each server what it runs:
+//!
+//! * LM Studio — `/api/v0/models` (loaded state, loaded and maximum
+//!   context length per model);
+//! * Ollama — `/api/tags` plus `/api/ps` for the running models' context;
+//! * llama.cpp `llama-server` — `/props` (`n_ctx`);
+//! * anything else answering `/v1/models` (vLLM reports `max_model_len`).
+//!
+//! Given a workspace, each server also lists the configured endpoints whose
+//! `context_window` exceeds what the server serves for that model.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::time::Duration;
+
+use crate::{net, ollama, workspace_dir};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(300);
+const REQUEST_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Ports probed besides Ollama's (which follows `OLLAMA_HOST`).
+const PORTS: &[u16] = &[
+    1234, // LM Studio
+    8080, // llama.cpp llama-server
+    8000, // vLLM
+    1337, // Jan
+    5001, // KoboldCpp
+    4891, // GPT4All
+    5000, // text-generation-webui
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalLlmModel {
+    pub id: String,
+    pub loaded: bool,
+    /// Context the model is served with, when the server reports it.
+    pub context_length: Option<u64>,
+    /// The model's own maximum.
+    pub max_context_length: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextConflict {
+    pub endpoint: String,
+    pub model: String,
+    pub configured: u64,
+    pub served: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalLlmServer {
+    /// "lmstudio" | "ollama" | "llamacpp" | "vllm" | "openai-compatible"
+    pub kind: String,
+    pub port: u16,
+    /// OpenAI-compatible base URL for `llm_endpoints.json`.
+    pub base_url: String,
+    /// `provider` slug to use in `llm_endpoints.json`.
+    pub provider: String,
+    pub version: Option<String>,
+    pub models: Vec<LocalLlmModel>,
+    pub conflicts: Vec<ContextConflict>,
+}
+
+fn u64_field(v: &Value, key: &str) -> Option<u64> {
+    v.get(key).and_then(Value::as_u64).filter(|n| *n > 0)
+}
+
+/// LM Studio `/api/v0/models`; embedding models are left out.
+pub(crate) fn parse_lmstudio_models(body: &Value) -> Vec<LocalLlmModel> {
+    body.get("data")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|m| m.get("type").and_then(Value::as_str) != Some("embeddings"))
+        .filter_map(|m| {
+            let loaded = m.get("state").and_then(Value::as_str) == Some("loaded");
+            Some(LocalLlmModel {
+                id: m.get("id")?.as_str()?.to_string(),
+                loaded,
+                context_length: u64_field(m, "loaded_context_length").filter(|_| loaded),
+                max_context_length: u64_field(m, "max_context_length"),
+            })
+        })
+        .collect()
+}
+
+/// Ollama `/api/tags` merged with `/api/ps` (loaded models and, on recent
+/// versions, their `context_length`).
+pub(crate) fn parse_ollama_models(tags: &Value, ps: &Value) -> Vec<LocalLlmModel> {
+    let running: HashMap<&str, Option<u64>> = ps
+        .get("models")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|m| Some((m.get("name")?.as_str()?, u64_field(m, "context_length"))))
+        .collect();
+    ollama::parse_tags(tags)
+        .into_iter()
+        .map(|m| {
+            let ctx = running.get(m.name.as_str());
+            LocalLlmModel {
+                loaded: ctx.is_some(),
+                context_length: ctx.copied().flatten(),
+                max_context_length: None,
+                id: m.name,
+            }
+        })
+        .collect()
+}
+
+/// Generic `/v1/models`.  `n_ctx` (llama.cpp `/props`) applies to every
+/// model the server lists; vLLM reports `max_model_len` per model.
+pub(crate) fn parse_openai_models(body: &Value, n_ctx: Option<u64>) -> Vec<LocalLlmModel> {
+    body.get("data")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|m| {
+            let served = n_ctx.or_else(|| u64_field(m, "max_model_len"));
+            Some(LocalLlmModel {
+                id: m.get("id")?.as_str()?.to_string(),
+                loaded: true,
+                context_length: served,
+                max_context_length: u64_field(m, "max_model_len"),
+            })
+        })
+        .collect()
+}
+
+/// `n_ctx` from llama.cpp's `/props` (nested in older builds).
+pub(crate) fn llamacpp_n_ctx(props: &Value) -> Option<u64> {
+    u64_field(props, "n_ctx").or_else(|| {
+        props
+            .get("default_generation_settings")
+            .and_then(|s| u64_field(s, "n_ctx"))
+    })
+}
+
+fn endpoint_port(base_url: &str) -> Option<u16> {
+    let url = reqwest::Url::parse(base_url).ok()?;
+    let host = url.host_str()?;
+    matches!(
+        host,
+        "localhost" | "127.0.0.1" | "0.0.0.0" | "[::1]" | "::1"
+    )
+    .then(|| url.port_or_known_default())
+    .flatten()
+}
+
+/// Endpoints of `config` that point at `port` and claim more context than
+/// the server serves for their model.  `context_window` 0 is fine: the
+/// backend then uses its small local default.
+pub(crate) fn context_conflicts(
+    config: &Value,
+    port: u16,
+    models: &[LocalLlmModel],
+) -> Vec<ContextConflict> {
+    ["endpoints", "compiler_endpoints"]
+        .iter()
+        .filter_map(|k| config.get(*k).and_then(Value::as_array))
+        .flatten()
+        .filter_map(|ep| {
+            let base = ep.get("base_url")?.as_str()?;
+            if endpoint_port(base)? != port {
+                return None;
+            }
+            let model = ep.get("model")?.as_str()?;
+            let configured = u64_field(ep, "context_window")?;
+            let served = models
+                .iter()
+                .find(|m| m.id == model)
+                .and_then(|m| m.context_length)?;
+            (configured > served).then(|| ContextConflict {
+                endpoint: ep
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or(model)
+                    .to_string(),
+                model: model.to_string(),
+                configured,
+                served,
+            })
+        })
+        .collect()
+}
+
+async fn get_json(port: u16, path: &str) -> Option<Value> {
+    net::local_http_client()
+        .get(format!("http://127.0.0.1:{port}{path}"))
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .await
+        .ok()
+}
+
+fn server(kind: &str, provider: &str, port: u16, models: Vec<LocalLlmModel>) -> LocalLlmServer {
+    LocalLlmServer {
+        kind: kind.to_string(),
+        port,
+        base_url: format!("http://127.0.0.1:{port}/v1"),
+        provider: provider.to_string(),
+        version: None,
+        models,
+        conflicts: Vec::new(),
+    }
+}
+
+async fn probe(port: u16, ollama_port: u16) -> Option<LocalLlmServer> {
+    let addr = std::net::SocketAddr::from(([127, 0, 0, 1], port));
+    tokio::time::timeout(CONNECT_TIMEOUT, tokio::net::TcpStream::connect(addr))
+        .await
+        .ok()?
+        .ok()?;
+    if port == ollama_port {
+        if let Some(tags) = get_json(port, "/api/tags").await {
+            let ps = get_json(port, "/api/ps").await.unwrap_or(Value::Null);
+            let mut s = server("ollama", "ollama", port, parse_ollama_models(&tags, &ps));
+            s.version = get_json(port, "/api/version")
+                .await
+                .and_then(|v| v.get("version")?.as_str().map(str::to_string));
+            return Some(s);
+        }
+    }
+    if let Some(body) = get_json(port, "/api/v0/models").await {
+        if body.get("data").is_some() {
+            return Some(server(
+                "lmstudio",
+                "lmstudio",
+                port,
+                parse_lmstudio_models(&body),
+            ));
+        }
+    }
+    let models = get_json(port, "/v1/models").await?;
+    if let Some(n_ctx) = get_json(port, "/props")
+        .await
+        .as_ref()
+        .and_then(llamacpp_n_ctx)
+    {
+        return Some(server(
+            "llamacpp",
+            "custom",
+            port,
+            parse_openai_models(&models, Some(n_ctx)),
+        ));
+    }
+    let vllm = models
+        .get("data")
+        .and_then(Value::as_array)
+        .and_then(|d| d.first())
+        .and_then(|m| m.get("owned_by"))
+        .and_then(Value::as_str)
+        == Some("vllm");
+    let kind = if vllm { "vllm" } else { "openai-compatible" };
+    Some(server(
+        kind,
+        "custom",
+        port,
+        parse_openai_models(&models, None),
+    ))
+}
+
+/// Probe the usual localhost ports for LLM servers.  With `workspace_id`,
+/// each server's `conflicts` lists that workspace's endpoints configured
+/// with a larger context than the server serves.
+#[tauri::command]
+pub async fn discover_local_llm_servers(workspace_id: Option<String>) -> Vec<LocalLlmServer> {
+    let ollama_port = ollama::host_port().1;
+    let mut ports = vec![ollama_port];
+    ports.extend(PORTS.iter().copied().filter(|p| *p != ollama_port));
+    let handles: Vec<_> = ports
+        .into_iter()
+        .map(|port| tauri::async_runtime::spawn(probe(port, ollama_port)))
+        .collect();
+    let mut servers = Vec::new();
+    for h in handles {
+        if let Ok(Some(s)) = h.await {
+            servers.push(s);
+        }
+    }
+    let config = workspace_id
+        .filter(|id| !id.is_empty())
+        .and_then(|id| {
+            fs::read_to_string(workspace_dir(&id).join("data").join("llm_endpoints.json")).ok()
+        })
+        .and_then(|s| serde_json::from_str::<Value>(&s).ok());
+    if let Some(config) = config {
+        for s in &mut servers {
+            s.conflicts = context_conflicts(&config, s.port, &s.models);
+        }
+    }
+    servers
+}
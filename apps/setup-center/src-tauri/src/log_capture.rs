@@ -0,0 +1,450 @@
+//! Backend stdout / stderr captured through pipes.
+//!
+//! The backend used to inherit two handles on `logs/openakita-serve.log`.
+//! Under `pythonw` (no console) writes through those handles are block
+//! buffered, so stdout and stderr landed in the file out of order and
+//! seconds late, and the file could not be rotated while the backend held
+//! it open.  The backend's output now goes to pipes read here:
+//!
+//! * one reader thread per stream, one writer thread per backend that owns
+//!   the log file — every line is prefixed with the time it arrived and
+//!   its level, written and flushed immediately, and the file rotates to
+//!   `openakita-serve.log.1` at [`MAX_LOG_BYTES`];
+//! * lines are batched into `service_log` events (`{ workspaceId, lines }`,
+//!   redacted, every [`EVENT_INTERVAL`]) for a live log view;
+//! * error / warning counters per workspace, reset at each start, are
+//!   returned by `get_service_log_stats(workspace_id)`.
+//!
+//! Levels come from the line itself (`- ERROR -`, `[WARNING]`, `ERROR:`);
+//! a `Traceback` and its indented frames count as one error.
+//!
+//! Pipes are only used when the backend cannot outlive its reader: a
+//! desktop-owned backend in the Windows kill-on-close job
+//! ([`resource_limits::joins_app_job`]).  Everywhere else — CLI `start`,
+//! Linux / macOS where a backend survives a desktop crash — the backend
+//! gets the file handles as before, so `openakita-serve.log` keeps growing
+//! (the silent-backend watch reads its mtime).  For a desktop-owned backend
+//! the file is then followed ([`follow`]) for the same events and counters.
+//! Set `OPENAKITA_LOG_PIPE=0` to always hand the backend the file.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{
+    emit_global, is_pid_running, log_to_file, now_ms, redact, resource_limits, time_from_epoch,
+};
+
+pub(crate) const MAX_LOG_BYTES: u64 = 10 * 1024 * 1024;
+const EVENT_INTERVAL: Duration = Duration::from_millis(250);
+const MAX_EVENT_LINES: usize = 500;
+/// Longest line kept in counters' `last_error`.
+const LAST_ERROR_CHARS: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    pub(crate) fn tag(self) -> &'static str {
+        match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warning => "WARN",
+            Level::Error => "ERROR",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogLine {
+    pub ts_ms: u64,
+    pub level: Level,
+    /// "stdout" | "stderr" (a followed log file reports "stdout")
+    pub stream: &'static str,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LogBatch {
+    workspace_id: String,
+    lines: Vec<LogLine>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogStats {
+    pub workspace_id: String,
+    /// Capture active (backend output still open).
+    pub capturing: bool,
+    pub since_ms: u64,
+    pub lines: u64,
+    pub errors: u64,
+    pub warnings: u64,
+    pub last_error: Option<String>,
+    pub last_error_at_ms: Option<u64>,
+}
+
+static STATS: Lazy<Mutex<HashMap<String, LogStats>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Pipe capture is on unless `OPENAKITA_LOG_PIPE=0`.
+fn enabled() -> bool {
+    !matches!(
+        std::env::var("OPENAKITA_LOG_PIPE").ok().as_deref(),
+        Some("0") | Some("false") | Some("no")
+    )
+}
+
+/// Whether the backend's output goes through pipes: only when capture is
+/// enabled and the backend ends with the desktop that reads them.
+pub(crate) fn pipes_output(enabled: bool, owned_by_app: bool) -> bool {
+    enabled && resource_limits::joins_app_job(owned_by_app)
+}
+
+/// Pipe mode for a backend about to be spawned.
+pub(crate) fn use_pipes(owned_by_app: bool) -> bool {
+    pipes_output(enabled(), owned_by_app)
+}
+
+/// Level of the first level word in the line's head, if any.
+fn level_word(line: &str) -> Option<Level> {
+    let head: String = line.chars().take(120).collect();
+    head.split(|c: char| !c.is_ascii_alphabetic())
+        .find_map(|w| match w {
+            "DEBUG" => Some(Level::Debug),
+            "INFO" => Some(Level::Info),
+            "WARNING" | "WARN" => Some(Level::Warning),
+            "ERROR" | "CRITICAL" | "FATAL" => Some(Level::Error),
+            _ => None,
+        })
+}
+
+/// Per-stream level detection; remembers whether it is inside a traceback.
+#[derive(Debug, Default)]
+pub(crate) struct LevelParser {
+    in_traceback: bool,
+}
+
+impl LevelParser {
+    /// Level of `line` and whether it continues the previous entry (not
+    /// counted again).
+    pub(crate) fn classify(&mut self, line: &str) -> (Level, bool) {
+        if line.starts_with("Traceback (most recent call last)") {
+            let continues = self.in_traceback;
+            self.in_traceback = true;
+            return (Level::Error, continues);
+        }
+        if self.in_traceback {
+            // 缩进的栈帧属于同一条错误；第一行不缩进的是异常本身，之后结束
+            if !line.starts_with([' ', '\t']) {
+                self.in_traceback = false;
+            }
+            return (Level::Error, true);
+        }
+        (level_word(line).unwrap_or(Level::Info), false)
+    }
+}
+
+fn timestamp(ms: u64) -> String {
+    let (y, mo, d, h, mi, s) = time_from_epoch(ms / 1000);
+    format!(
+        "{y:04}-{mo:02}-{d:02}T{h:02}:{mi:02}:{s:02}.{:03}Z",
+        ms % 1000
+    )
+}
+
+/// The line as written to the log file.
+pub(crate) fn format_line(line: &LogLine) -> String {
+    format!(
+        "{} {:<5} {}\n",
+        timestamp(line.ts_ms),
+        line.level.tag(),
+        line.text
+    )
+}
+
+fn open_append(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Log file with size-based rotation to `<name>.1`.
+struct RotatingFile {
+    path: PathBuf,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf) -> Self {
+        let file = open_append(&path).ok();
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        Self { path, file, size }
+    }
+
+    fn write(&mut self, text: &str) {
+        if self.size + text.len() as u64 > MAX_LOG_BYTES {
+            self.file = None;
+            let rotated = self.path.with_extension("log.1");
+            let _ = fs::remove_file(&rotated);
+            let _ = fs::rename(&self.path, &rotated);
+            self.file = open_append(&self.path).ok();
+            self.size = 0;
+        }
+        if let Some(f) = self.file.as_mut() {
+            if f.write_all(text.as_bytes()).and_then(|_| f.flush()).is_ok() {
+                self.size += text.len() as u64;
+            }
+        }
+    }
+}
+
+fn update_stats(workspace_id: &str, line: &LogLine, continues: bool) {
+    let Ok(mut stats) = STATS.lock() else { return };
+    let Some(s) = stats.get_mut(workspace_id) else {
+        return;
+    };
+    s.lines += 1;
+    if continues {
+        // traceback 末尾不缩进的那行（异常类型与消息）比 "Traceback ..." 更有用
+        if line.level == Level::Error && !line.text.starts_with([' ', '\t']) {
+            s.last_error = Some(line.text.chars().take(LAST_ERROR_CHARS).collect());
+        }
+        return;
+    }
+    match line.level {
+        Level::Error => {
+            s.errors += 1;
+            s.last_error = Some(line.text.chars().take(LAST_ERROR_CHARS).collect());
+            s.last_error_at_ms = Some(line.ts_ms);
+        }
+        Level::Warning => s.warnings += 1,
+        _ => {}
+    }
+}
+
+fn flush_events(workspace_id: &str, batch: &mut Vec<LogLine>) {
+    if batch.is_empty() {
+        return;
+    }
+    let known = redact::workspace_secret_values(workspace_id);
+    let lines = batch
+        .drain(..)
+        .map(|mut l| {
+            l.text = redact::redact_text(&l.text, &known);
+            l
+        })
+        .collect();
+    emit_global(
+        "service_log",
+        LogBatch {
+            workspace_id: workspace_id.to_string(),
+            lines,
+        },
+    );
+}
+
+fn spawn_reader(
+    stream: &'static str,
+    pipe: impl Read + Send + 'static,
+    tx: mpsc::Sender<(&'static str, String)>,
+) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let text = String::from_utf8_lossy(&buf);
+                    let text = text.trim_end_matches(['\r', '\n']).to_string();
+                    if tx.send((stream, text)).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Reset the workspace's counters; returns the new `since_ms`.
+fn reset_stats(workspace_id: &str) -> u64 {
+    let since_ms = now_ms();
+    if let Ok(mut stats) = STATS.lock() {
+        stats.insert(
+            workspace_id.to_string(),
+            LogStats {
+                workspace_id: workspace_id.to_string(),
+                capturing: true,
+                since_ms,
+                ..Default::default()
+            },
+        );
+    }
+    since_ms
+}
+
+/// Hook up a freshly spawned backend: capture its pipes if it has them,
+/// otherwise follow `log_path` when the desktop owns the backend.
+pub(crate) fn attach(child: &mut Child, workspace_id: &str, log_path: &Path, owned_by_app: bool) {
+    match (child.stdout.take(), child.stderr.take()) {
+        (Some(out), Some(err)) => spawn(workspace_id, log_path.to_path_buf(), out, err),
+        _ if owned_by_app => follow(workspace_id, log_path.to_path_buf(), child.id()),
+        _ => {}
+    }
+}
+
+/// Start capturing the backend's `stdout` / `stderr` into `log_path`.
+/// Runs until both pipes close (the backend exited).
+pub(crate) fn spawn(
+    workspace_id: &str,
+    log_path: PathBuf,
+    stdout: impl Read + Send + 'static,
+    stderr: impl Read + Send + 'static,
+) {
+    let workspace_id = workspace_id.to_string();
+    reset_stats(&workspace_id);
+    let (tx, rx) = mpsc::channel();
+    spawn_reader("stdout", stdout, tx.clone());
+    spawn_reader("stderr", stderr, tx);
+    thread::spawn(move || {
+        let mut file = RotatingFile::open(log_path);
+        let mut parsers: [LevelParser; 2] = Default::default();
+        let mut batch = Vec::new();
+        let mut last_flush = Instant::now();
+        loop {
+            let msg = rx.recv_timeout(EVENT_INTERVAL);
+            let closed = matches!(msg, Err(mpsc::RecvTimeoutError::Disconnected));
+            if let Ok((stream, text)) = msg {
+                let parser = &mut parsers[usize::from(stream == "stderr")];
+                let (level, continues) = parser.classify(&text);
+                let line = LogLine {
+                    ts_ms: now_ms(),
+                    level,
+                    stream,
+                    text,
+                };
+                file.write(&format_line(&line));
+                update_stats(&workspace_id, &line, continues);
+                batch.push(line);
+            }
+            if closed || batch.len() >= MAX_EVENT_LINES || last_flush.elapsed() >= EVENT_INTERVAL {
+                flush_events(&workspace_id, &mut batch);
+                last_flush = Instant::now();
+            }
+            if closed {
+                break;
+            }
+        }
+        if let Ok(mut stats) = STATS.lock() {
+            if let Some(s) = stats.get_mut(&workspace_id) {
+                s.capturing = false;
+            }
+        }
+        log_to_file(&format!(
+            "[log-capture] {workspace_id}: backend output closed"
+        ));
+    });
+}
+
+/// Bytes appended to `path` since `offset` (from 0 again after a rotation
+/// or truncation); `offset` is moved past them.
+pub(crate) fn read_appended(path: &Path, offset: &mut u64) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let Ok(mut file) = File::open(path) else {
+        return buf;
+    };
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    if len < *offset {
+        *offset = 0;
+    }
+    if file.seek(SeekFrom::Start(*offset)).is_ok() {
+        if let Ok(n) = file.take(len - *offset).read_to_end(&mut buf) {
+            *offset += n as u64;
+        }
+    }
+    buf
+}
+
+/// Follow `log_path` while the backend writes to it directly: the same
+/// events and counters as [`spawn`], nothing written.  Stops once `pid`
+/// exits or the workspace is started again.
+pub(crate) fn follow(workspace_id: &str, log_path: PathBuf, pid: u32) {
+    let workspace_id = workspace_id.to_string();
+    let since_ms = reset_stats(&workspace_id);
+    let mut offset = fs::metadata(&log_path).map(|m| m.len()).unwrap_or(0);
+    thread::spawn(move || {
+        let mut parser = LevelParser::default();
+        let mut pending = Vec::new();
+        let mut batch = Vec::new();
+        loop {
+            thread::sleep(EVENT_INTERVAL);
+            let current = STATS
+                .lock()
+                .ok()
+                .and_then(|s| s.get(&workspace_id).map(|s| s.since_ms))
+                == Some(since_ms);
+            // 先判断存活再读：进程退出前最后写的几行也能读到
+            let done = !current || !is_pid_running(pid);
+            pending.extend(read_appended(&log_path, &mut offset));
+            while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+                let raw: Vec<u8> = pending.drain(..=end).collect();
+                let text = String::from_utf8_lossy(&raw)
+                    .trim_end_matches(['\r', '\n'])
+                    .to_string();
+                let (level, continues) = parser.classify(&text);
+                let line = LogLine {
+                    ts_ms: now_ms(),
+                    level,
+                    stream: "stdout",
+                    text,
+                };
+                update_stats(&workspace_id, &line, continues);
+                batch.push(line);
+                if batch.len() >= MAX_EVENT_LINES {
+                    flush_events(&workspace_id, &mut batch);
+                }
+            }
+            flush_events(&workspace_id, &mut batch);
+            if done {
+                if current {
+                    if let Ok(mut stats) = STATS.lock() {
+                        if let Some(s) = stats.get_mut(&workspace_id) {
+                            s.capturing = false;
+                        }
+                    }
+                }
+                break;
+            }
+        }
+    });
+}
+
+/// Line / error / warning counts of the workspace's backend output since
+/// its last start.
+#[tauri::command]
+pub fn get_service_log_stats(workspace_id: String) -> LogStats {
+    STATS
+        .lock()
+        .ok()
+        .and_then(|s| s.get(&workspace_id).cloned())
+        .unwrap_or(LogStats {
+            workspace_id,
+            ..Default::default()
+        })
+}
@@ -0,0 +1,564 @@
+//! Structured view of the backend's service log.
+//!
+//! `openakita_service_log_query(workspace_id, query)` reads the same tail as
+//! `openakita_service_log` (redacted, observers included) and returns it as
+//! entries instead of text.  Each line of `openakita-serve.log` is
+//!
+//! * our capture prefix (`2026-01-02T03:04:05.678Z INFO  `, see
+//!   `log_capture.rs`), when the output went through the pipes, then
+//! * either a JSON object — newer backends log JSON lines
+//!   (`{"ts": …, "level": …, "module": …, "message": …, …}`) — or plain
+//!   `logging` text (`2026-01-02 03:04:05,678 - openakita.mcp - INFO - …`).
+//!
+//! JSON keys other than time / level / module / message end up in `fields`,
+//! which `filters` can match (`module=mcp`, `tool=browser`).  A traceback
+//! and its frames stay one entry.
+//!
+//! `export_service_log(workspace_id, options)` writes such a selection —
+//! plus a time range, over the whole log including the rotated
+//! `openakita-serve.log.1` — to a file the user picked, as text, JSON lines
+//! or a zip of both, redacted unless `redact: false`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::errors::CmdResult;
+use crate::log_capture::{Level, LevelParser};
+use crate::{
+    atomic_write, now_ms, observer, redact, service_log_uncached, spawn_blocking_result,
+    workspace_dir,
+};
+
+const DEFAULT_TAIL_BYTES: u64 = 200_000;
+const DEFAULT_LIMIT: usize = 1000;
+const MAX_LIMIT: usize = 10_000;
+/// Observers only offer their log's tail.
+const OBSERVER_EXPORT_BYTES: u64 = 400_000;
+
+const TS_KEYS: &[&str] = &["ts", "timestamp", "time", "asctime", "@timestamp"];
+const LEVEL_KEYS: &[&str] = &["level", "levelname", "severity", "lvl"];
+const MODULE_KEYS: &[&str] = &["module", "logger", "name"];
+const MESSAGE_KEYS: &[&str] = &["message", "msg", "event"];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    /// Time as the backend wrote it (or our capture time).
+    pub ts: Option<String>,
+    /// `ts` in epoch ms when it carries a time zone (capture time otherwise).
+    pub ts_ms: Option<u64>,
+    pub level: Level,
+    pub module: Option<String>,
+    pub message: String,
+    /// Remaining keys of a JSON line; empty for plain text.
+    pub fields: Map<String, Value>,
+    /// Line came from a JSON log.
+    pub structured: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LogQuery {
+    /// How much of the log to read (default 200 KB, at most 400 KB).
+    pub tail_bytes: Option<u64>,
+    /// Lowest level returned.
+    pub min_level: Option<Level>,
+    /// Case-insensitive text anywhere in the message, module or fields.
+    pub search: Option<String>,
+    /// `key=value` terms, all of which must match (case-insensitive):
+    /// `level`, `module` (whole dotted segments: `mcp` matches
+    /// `openakita.mcp` and `mcp.client`), or any key of `fields`.
+    pub filters: Vec<String>,
+    /// Most recent entries returned (default 1000).
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogQueryResult {
+    pub path: String,
+    pub entries: Vec<LogEntry>,
+    /// Entries that matched before `limit` was applied.
+    pub matched: usize,
+    /// Some JSON lines were seen.
+    pub structured: bool,
+    /// Older log content was not read.
+    pub truncated: bool,
+}
+
+/// Epoch ms of `YYYY-MM-DD[T ]HH:MM:SS[.fff|,fff](Z|±HH:MM)`; `None` for
+/// anything else, including times without a zone.
+pub(crate) fn parse_iso_ms(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let num = |r: std::ops::Range<usize>| s.get(r)?.parse::<i64>().ok();
+    let (y, mo, d) = (num(0..4)?, num(5..7)?, num(8..10)?);
+    let (h, mi, sec) = (num(11..13)?, num(14..16)?, num(17..19)?);
+    if !matches!(s.as_bytes().get(10), Some(b'T' | b' ')) {
+        return None;
+    }
+    let mut rest = &s[19..];
+    let mut ms = 0i64;
+    if let Some(frac) = rest.strip_prefix(['.', ',']) {
+        let digits = frac.chars().take_while(char::is_ascii_digit).count();
+        let padded = format!("{:0<3}", &frac[..digits.min(3)]);
+        ms = padded.parse().ok()?;
+        rest = &frac[digits..];
+    }
+    let offset_min = match rest {
+        "Z" | "z" => 0,
+        _ if rest.len() == 6 && matches!(rest.as_bytes()[0], b'+' | b'-') => {
+            let sign = if rest.starts_with('-') { -1 } else { 1 };
+            let oh: i64 = rest[1..3].parse().ok()?;
+            let om: i64 = rest[4..6].parse().ok()?;
+            sign * (oh * 60 + om)
+        }
+        _ => return None,
+    };
+    // days_from_civil (Howard Hinnant)
+    let yy = if mo <= 2 { y - 1 } else { y };
+    let era = yy.div_euclid(400);
+    let yoe = yy - era * 400;
+    let doy = (153 * ((mo + 9) % 12) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    let secs = days * 86_400 + h * 3600 + mi * 60 + sec - offset_min * 60;
+    u64::try_from(secs * 1000 + ms).ok()
+}
+
+fn level_of(s: &str) -> Option<Level> {
+    match s.trim().to_ascii_uppercase().as_str() {
+        "DEBUG" | "TRACE" => Some(Level::Debug),
+        "INFO" | "NOTICE" => Some(Level::Info),
+        "WARNING" | "WARN" => Some(Level::Warning),
+        "ERROR" | "CRITICAL" | "FATAL" | "EXCEPTION" => Some(Level::Error),
+        _ => None,
+    }
+}
+
+/// Our capture prefix: `<ISO time> <LEVEL padded to 5> `.
+fn strip_capture_prefix(line: &str) -> Option<(&str, u64, Level, &str)> {
+    let ts = line.get(..24)?;
+    let ms = parse_iso_ms(ts)?;
+    let rest = line.get(25..)?;
+    let tag = rest.get(..5)?.trim_end();
+    let level = level_of(tag)?;
+    Some((ts, ms, level, rest.get(6..).unwrap_or("")))
+}
+
+fn take_str(obj: &mut Map<String, Value>, keys: &[&str]) -> Option<Value> {
+    keys.iter().find_map(|k| obj.remove(*k))
+}
+
+fn value_text(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// `level` is used when the object has none.
+fn parse_json(text: &str, level: Level) -> Option<LogEntry> {
+    let Value::Object(mut obj) = serde_json::from_str(text.trim()).ok()? else {
+        return None;
+    };
+    let ts = take_str(&mut obj, TS_KEYS);
+    let ts_ms = match &ts {
+        Some(Value::Number(n)) => n.as_f64().map(|f| {
+            // 秒或毫秒都有人用
+            if f < 1e12 {
+                (f * 1000.0) as u64
+            } else {
+                f as u64
+            }
+        }),
+        Some(Value::String(s)) => parse_iso_ms(s),
+        _ => None,
+    };
+    let level = take_str(&mut obj, LEVEL_KEYS)
+        .and_then(|v| level_of(&value_text(&v)))
+        .unwrap_or(level);
+    let module = take_str(&mut obj, MODULE_KEYS).map(|v| value_text(&v));
+    let message = take_str(&mut obj, MESSAGE_KEYS)
+        .map(|v| value_text(&v))
+        .unwrap_or_default();
+    Some(LogEntry {
+        ts: ts.as_ref().map(value_text),
+        ts_ms,
+        level,
+        module,
+        message,
+        fields: obj,
+        structured: true,
+    })
+}
+
+/// `2026-01-02 03:04:05,678 - module - LEVEL - message` (the backend's
+/// default format); other text is kept whole as the message.
+fn parse_text(text: &str, level: Level) -> LogEntry {
+    let parts: Vec<&str> = text.splitn(4, " - ").collect();
+    if let [ts, module, lvl, message] = parts[..] {
+        if let Some(lvl) = level_of(lvl).filter(|_| ts.len() >= 19) {
+            return LogEntry {
+                ts: Some(ts.to_string()),
+                ts_ms: parse_iso_ms(ts),
+                level: lvl,
+                module: Some(module.trim().to_string()),
+                message: message.to_string(),
+                fields: Map::new(),
+                structured: false,
+            };
+        }
+    }
+    LogEntry {
+        ts: None,
+        ts_ms: None,
+        level,
+        module: None,
+        message: text.to_string(),
+        fields: Map::new(),
+        structured: false,
+    }
+}
+
+/// Entries of a chunk of service log, oldest first.
+pub(crate) fn parse_entries(content: &str) -> Vec<LogEntry> {
+    let mut parser = LevelParser::default();
+    let mut entries: Vec<LogEntry> = Vec::new();
+    for line in content.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (captured_ts, captured_ms, captured_level, text) = match strip_capture_prefix(line) {
+            Some((ts, ms, level, text)) => (Some(ts), Some(ms), Some(level), text),
+            None => (None, None, None, line),
+        };
+        let (level, continues) = parser.classify(text);
+        if continues {
+            if let Some(last) = entries.last_mut() {
+                last.message.push('\n');
+                last.message.push_str(text);
+                last.level = last.level.max(level);
+                continue;
+            }
+        }
+        let level = captured_level.unwrap_or(level);
+        let mut entry = parse_json(text, level).unwrap_or_else(|| parse_text(text, level));
+        if entry.ts.is_none() {
+            entry.ts = captured_ts.map(str::to_string);
+        }
+        entry.ts_ms = entry.ts_ms.or(captured_ms);
+        entries.push(entry);
+    }
+    entries
+}
+
+fn parse_filters(filters: &[String]) -> Result<Vec<(String, String)>, String> {
+    filters
+        .iter()
+        .filter(|f| !f.trim().is_empty())
+        .map(|f| match f.split_once('=') {
+            Some((k, v)) if !k.trim().is_empty() => {
+                Ok((k.trim().to_ascii_lowercase(), v.trim().to_lowercase()))
+            }
+            _ => Err(format!("INVALID_ARGUMENT|过滤条件应为 key=value: {f}")),
+        })
+        .collect()
+}
+
+fn module_matches(module: &str, want: &str) -> bool {
+    let module = module.to_lowercase();
+    module == want
+        || module.starts_with(&format!("{want}."))
+        || module.ends_with(&format!(".{want}"))
+        || module.contains(&format!(".{want}."))
+}
+
+fn field_matches(entry: &LogEntry, key: &str, want: &str) -> bool {
+    match key {
+        "level" => level_of(want) == Some(entry.level),
+        "module" => entry
+            .module
+            .as_deref()
+            .is_some_and(|m| module_matches(m, want)),
+        _ => entry
+            .fields
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .is_some_and(|(_, v)| value_text(v).to_lowercase() == want),
+    }
+}
+
+impl LogEntry {
+    pub(crate) fn matches(&self, query: &LogQuery) -> Result<bool, String> {
+        if query.min_level.is_some_and(|min| self.level < min) {
+            return Ok(false);
+        }
+        for (key, want) in parse_filters(&query.filters)? {
+            if !field_matches(self, &key, &want) {
+                return Ok(false);
+            }
+        }
+        if let Some(search) = query.search.as_deref().map(str::trim) {
+            let search = search.to_lowercase();
+            let hay = format!(
+                "{} {} {}",
+                self.message,
+                self.module.as_deref().unwrap_or(""),
+                serde_json::to_string(&self.fields).unwrap_or_default()
+            );
+            if !search.is_empty() && !hay.to_lowercase().contains(&search) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+fn query(workspace_id: &str, query: &LogQuery) -> Result<LogQueryResult, String> {
+    parse_filters(&query.filters)?;
+    let tail = query.tail_bytes.unwrap_or(DEFAULT_TAIL_BYTES);
+    let chunk = service_log_uncached(workspace_id, Some(tail))?;
+    let content = if chunk.truncated {
+        // 截断处多半是半行，丢掉
+        chunk.content.split_once('\n').map_or("", |(_, rest)| rest)
+    } else {
+        chunk.content.as_str()
+    };
+    let entries = parse_entries(content);
+    let structured = entries.iter().any(|e| e.structured);
+    let mut matching = Vec::new();
+    for entry in entries {
+        if entry.matches(query)? {
+            matching.push(entry);
+        }
+    }
+    let matched = matching.len();
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let entries = matching.split_off(matched.saturating_sub(limit));
+    Ok(LogQueryResult {
+        path: chunk.path,
+        entries,
+        matched,
+        structured,
+        truncated: chunk.truncated,
+    })
+}
+
+/// Parsed, filtered entries from the tail of the workspace's service log.
+#[tauri::command]
+pub async fn openakita_service_log_query(
+    workspace_id: String,
+    query: Option<LogQuery>,
+) -> CmdResult<LogQueryResult> {
+    let q = query.unwrap_or_default();
+    spawn_blocking_result(move || self::query(&workspace_id, &q))
+        .await
+        .map_err(Into::into)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Txt,
+    Jsonl,
+    Zip,
+}
+
+/// Inclusive epoch-ms bounds; either may be open.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LogRange {
+    pub from_ms: Option<u64>,
+    pub to_ms: Option<u64>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportLogOptions {
+    /// `minLevel` / `search` / `filters` as in the query (`tailBytes` and
+    /// `limit` are ignored: the whole log is read).
+    #[serde(default)]
+    pub filter: LogQuery,
+    #[serde(default)]
+    pub range: LogRange,
+    pub dest_path: String,
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default = "default_true")]
+    pub redact: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportLogResult {
+    pub path: String,
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+/// Entries inside `range`.  An entry without a time of its own (plain
+/// text without a zone) takes the time of the entry before it; leading
+/// ones without any are dropped once a bound is set.
+pub(crate) fn in_range(entries: Vec<LogEntry>, range: LogRange) -> Vec<LogEntry> {
+    if range.from_ms.is_none() && range.to_ms.is_none() {
+        return entries;
+    }
+    let mut last = None;
+    entries
+        .into_iter()
+        .filter(|e| {
+            last = e.ts_ms.or(last);
+            last.is_some_and(|t| {
+                range.from_ms.is_none_or(|from| t >= from) && range.to_ms.is_none_or(|to| t <= to)
+            })
+        })
+        .collect()
+}
+
+/// One entry as a log line: `<ts> <LEVEL> [module] message {fields}`.
+pub(crate) fn entry_text(entry: &LogEntry) -> String {
+    let mut line = String::new();
+    if let Some(ts) = &entry.ts {
+        line.push_str(ts);
+        line.push(' ');
+    }
+    line.push_str(&format!("{:<5}", entry.level.tag()));
+    if let Some(module) = &entry.module {
+        line.push_str(&format!(" [{module}]"));
+    }
+    line.push(' ');
+    line.push_str(&entry.message);
+    if !entry.fields.is_empty() {
+        line.push(' ');
+        line.push_str(&serde_json::to_string(&entry.fields).unwrap_or_default());
+    }
+    line.push('\n');
+    line
+}
+
+fn entries_jsonl(entries: &[LogEntry]) -> String {
+    entries
+        .iter()
+        .filter_map(|e| serde_json::to_string(e).ok())
+        .map(|l| l + "\n")
+        .collect()
+}
+
+/// The whole service log, rotated part first.
+pub(crate) fn full_log(workspace_id: &str, redact: bool) -> Result<String, String> {
+    let mut content = String::new();
+    if observer::target_of(workspace_id).is_some() {
+        let chunk = service_log_uncached(workspace_id, Some(OBSERVER_EXPORT_BYTES))?;
+        content = match chunk.content.split_once('\n') {
+            Some((_, rest)) if chunk.truncated => rest.to_string(),
+            _ => chunk.content,
+        };
+    } else {
+        let logs = workspace_dir(workspace_id).join("logs");
+        for name in ["openakita-serve.log.1", "openakita-serve.log"] {
+            match fs::read(logs.join(name)) {
+                Ok(bytes) => content.push_str(&String::from_utf8_lossy(&bytes)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(format!("read {name} failed: {e}")),
+            }
+        }
+    }
+    Ok(if redact {
+        redact::redact_for_workspace(&content, workspace_id)
+    } else {
+        content
+    })
+}
+
+fn write_zip(
+    dest: &Path,
+    workspace_id: &str,
+    entries: &[LogEntry],
+    meta: Value,
+) -> Result<(), String> {
+    let stem = format!("{workspace_id}-service-log");
+    let tmp = dest.with_extension("zip.tmp");
+    let file = fs::File::create(&tmp).map_err(|e| format!("create zip: {e}"))?;
+    let mut zw = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let text: String = entries.iter().map(entry_text).collect();
+    let meta = serde_json::to_string_pretty(&meta).unwrap_or_default();
+    for (name, body) in [
+        (format!("{stem}.txt"), text),
+        (format!("{stem}.jsonl"), entries_jsonl(entries)),
+        ("export.json".to_string(), meta),
+    ] {
+        zw.start_file(name, options)
+            .and_then(|_| zw.write_all(body.as_bytes()).map_err(Into::into))
+            .map_err(|e| format!("write zip: {e}"))?;
+    }
+    zw.finish().map_err(|e| format!("finish zip: {e}"))?;
+    fs::rename(&tmp, dest).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        format!("save {}: {e}", dest.display())
+    })
+}
+
+fn export(workspace_id: &str, options: &ExportLogOptions) -> Result<ExportLogResult, String> {
+    parse_filters(&options.filter.filters)?;
+    let dest = PathBuf::from(options.dest_path.trim());
+    if options.dest_path.trim().is_empty() || dest.is_dir() {
+        return Err("INVALID_ARGUMENT|需要导出文件路径".into());
+    }
+    if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| format!("create {}: {e}", parent.display()))?;
+    }
+    let content = full_log(workspace_id, options.redact)?;
+    let mut entries = Vec::new();
+    for entry in in_range(parse_entries(&content), options.range) {
+        if entry.matches(&options.filter)? {
+            entries.push(entry);
+        }
+    }
+    match options.format {
+        ExportFormat::Txt => {
+            let text: String = entries.iter().map(entry_text).collect();
+            atomic_write(&dest, text)?;
+        }
+        ExportFormat::Jsonl => atomic_write(&dest, entries_jsonl(&entries))?,
+        ExportFormat::Zip => {
+            let meta = serde_json::json!({
+                "workspaceId": workspace_id,
+                "exportedAtMs": now_ms(),
+                "fromMs": options.range.from_ms,
+                "toMs": options.range.to_ms,
+                "minLevel": options.filter.min_level,
+                "search": options.filter.search,
+                "filters": options.filter.filters,
+                "redacted": options.redact,
+                "entries": entries.len(),
+            });
+            write_zip(&dest, workspace_id, &entries, meta)?;
+        }
+    }
+    Ok(ExportLogResult {
+        path: dest.to_string_lossy().to_string(),
+        entries: entries.len(),
+        bytes: fs::metadata(&dest).map(|m| m.len()).unwrap_or(0),
+    })
+}
+
+/// Write the filtered service log of a workspace to `options.destPath`.
+#[tauri::command]
+pub async fn export_service_log(
+    workspace_id: String,
+    options: ExportLogOptions,
+) -> CmdResult<ExportLogResult> {
+    spawn_blocking_result(move || export(&workspace_id, &options))
+        .await
+        .map_err(Into::into)
+}
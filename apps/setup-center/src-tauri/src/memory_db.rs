@@ -0,0 +1,276 @@
+//! Maintenance of a workspace's memory database.
+//!
+//! The backend keeps its long-term memory in SQLite
+//! (`data/memory/openakita.db`).  It only grows: deleted memories leave
+//! free pages behind and consolidation gets slower until its tasks time
+//! out.  These commands look after the file from the desktop:
+//!
+//! * `memory_db_stats` — size, WAL size, page / free-page counts and row
+//!   counts per table;
+//! * `memory_db_integrity_check` — `PRAGMA integrity_check`;
+//! * `memory_db_backup` — SQLite online backup into
+//!   `data/memory/backups/` (or a given file);
+//! * `memory_db_vacuum` — `VACUUM INTO` a temp file, `quick_check` it,
+//!   then swap it in.  Refused with `BACKEND_RUNNING` while the
+//!   workspace's backend is up.
+//!
+//! The desktop has no SQLite of its own, so the work runs in the bridge
+//! (`memory-db`) with the workspace's Python.  Stats, checks and backups
+//! open the database read-only.  Backup and vacuum progress is emitted as
+//! `memory-db` events.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::errors::CmdResult;
+use crate::{
+    chrono_like_timestamp, emit_if_ui_live, is_pid_file_valid, log_to_file, observer,
+    read_pid_file, run_python_module_json, run_python_module_json_streaming, spawn_blocking_result,
+    workspace_dir,
+};
+
+const BRIDGE: &str = "openakita.setup_center.bridge";
+const EVENT: &str = "memory-db";
+/// A vacuum rewrites the whole file; on a slow disk that takes a while.
+const LONG_OP_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct TableRows {
+    pub name: String,
+    /// None for virtual tables whose module isn't available.
+    pub rows: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct MemoryDbStats {
+    pub path: String,
+    pub size_bytes: u64,
+    pub wal_bytes: u64,
+    pub page_size: u64,
+    pub page_count: u64,
+    pub free_pages: u64,
+    /// Free pages / all pages.
+    pub fragmentation: f64,
+    pub reclaimable_bytes: u64,
+    pub tables: Vec<TableRows>,
+    #[serde(default)]
+    pub backend_running: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct IntegrityReport {
+    pub path: String,
+    pub ok: bool,
+    pub problems: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct BackupResult {
+    pub path: String,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct VacuumResult {
+    pub path: String,
+    pub before_bytes: u64,
+    pub after_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgressEvent<'a> {
+    workspace_id: &'a str,
+    action: &'a str,
+    /// "backup" | "vacuum" | "done" | "error"
+    stage: &'a str,
+    percent: Option<u8>,
+    message: String,
+}
+
+pub(crate) fn memory_db_path(ws_dir: &Path) -> PathBuf {
+    ws_dir.join("data").join("memory").join("openakita.db")
+}
+
+fn backend_running(workspace_id: &str) -> bool {
+    read_pid_file(workspace_id)
+        .as_ref()
+        .is_some_and(is_pid_file_valid)
+}
+
+fn resolve_db(workspace_id: &str) -> Result<PathBuf, String> {
+    let ws_dir = workspace_dir(workspace_id);
+    if workspace_id.is_empty() || !ws_dir.is_dir() {
+        return Err(format!("NOT_FOUND|工作区不存在: {workspace_id}"));
+    }
+    let db = memory_db_path(&ws_dir);
+    if !db.is_file() {
+        return Err(format!("NOT_FOUND|工作区 {workspace_id} 还没有记忆数据库"));
+    }
+    Ok(db)
+}
+
+/// Last stdout line of a bridge run, parsed.
+pub(crate) fn parse_bridge_output<T: serde::de::DeserializeOwned>(out: &str) -> Result<T, String> {
+    let last = out.lines().last().unwrap_or_default();
+    serde_json::from_str(last).map_err(|e| format!("unexpected memory-db output ({e}): {out}"))
+}
+
+fn run_quick(venv_dir: &str, action: &str, db: &Path) -> Result<String, String> {
+    let db = db.to_string_lossy();
+    run_python_module_json(
+        venv_dir,
+        BRIDGE,
+        &["memory-db", "--action", action, "--db", &db],
+        &[],
+    )
+}
+
+/// Run a long `memory-db` action, forwarding its progress lines.
+fn run_streaming(
+    app: &tauri::AppHandle,
+    venv_dir: &str,
+    workspace_id: &str,
+    action: &str,
+    args: &[&str],
+) -> Result<String, String> {
+    let emit = |stage: &str, percent: Option<u8>, message: String| {
+        emit_if_ui_live(
+            app,
+            EVENT,
+            ProgressEvent {
+                workspace_id,
+                action,
+                stage,
+                percent,
+                message,
+            },
+        );
+    };
+    let mut full = vec!["memory-db", "--action", action, "--stream"];
+    full.extend_from_slice(args);
+    let out = run_python_module_json_streaming(
+        venv_dir,
+        BRIDGE,
+        &full,
+        &[],
+        LONG_OP_TIMEOUT,
+        None,
+        |event, payload| {
+            if event != EVENT {
+                return;
+            }
+            let stage = payload
+                .get("stage")
+                .and_then(|v| v.as_str())
+                .unwrap_or(action);
+            let percent = payload
+                .get("percent")
+                .and_then(|v| v.as_u64())
+                .map(|p| p.min(100) as u8);
+            emit(stage, percent, String::new());
+        },
+    );
+    match &out {
+        Ok(_) => emit("done", Some(100), String::new()),
+        Err(e) => {
+            log_to_file(&format!(
+                "[memory_db] {action} ws={workspace_id} failed: {e}"
+            ));
+            emit("error", None, e.clone());
+        }
+    }
+    out
+}
+
+#[tauri::command]
+pub async fn memory_db_stats(venv_dir: String, workspace_id: String) -> CmdResult<MemoryDbStats> {
+    spawn_blocking_result(move || {
+        let db = resolve_db(&workspace_id)?;
+        let mut stats: MemoryDbStats = parse_bridge_output(&run_quick(&venv_dir, "stats", &db)?)?;
+        stats.backend_running = backend_running(&workspace_id);
+        Ok(stats)
+    })
+    .await
+    .map_err(Into::into)
+}
+
+#[tauri::command]
+pub async fn memory_db_integrity_check(
+    venv_dir: String,
+    workspace_id: String,
+) -> CmdResult<IntegrityReport> {
+    spawn_blocking_result(move || {
+        let db = resolve_db(&workspace_id)?;
+        parse_bridge_output(&run_quick(&venv_dir, "integrity-check", &db)?)
+    })
+    .await
+    .map_err(Into::into)
+}
+
+/// Copy the memory database to `dest`, by default
+/// `data/memory/backups/openakita-<timestamp>.db`.
+#[tauri::command]
+pub async fn memory_db_backup(
+    app: tauri::AppHandle,
+    venv_dir: String,
+    workspace_id: String,
+    dest: Option<String>,
+) -> CmdResult<BackupResult> {
+    spawn_blocking_result(move || {
+        let db = resolve_db(&workspace_id)?;
+        let dest = match dest.map(|d| d.trim().to_string()).filter(|d| !d.is_empty()) {
+            Some(d) => PathBuf::from(d),
+            None => db
+                .with_file_name("backups")
+                .join(format!("openakita-{}.db", chrono_like_timestamp())),
+        };
+        if dest == db || !dest.is_absolute() {
+            return Err(format!("INVALID_ARGUMENT|备份目标无效: {}", dest.display()));
+        }
+        let (db_arg, dest_arg) = (db.to_string_lossy(), dest.to_string_lossy());
+        let out = run_streaming(
+            &app,
+            &venv_dir,
+            &workspace_id,
+            "backup",
+            &["--db", &db_arg, "--dest", &dest_arg],
+        )?;
+        parse_bridge_output(&out)
+    })
+    .await
+    .map_err(Into::into)
+}
+
+/// Rebuild the memory database without its free pages.  The backend must
+/// be stopped.
+#[tauri::command]
+pub async fn memory_db_vacuum(
+    app: tauri::AppHandle,
+    venv_dir: String,
+    workspace_id: String,
+) -> CmdResult<VacuumResult> {
+    observer::ensure_writable(&workspace_id)?;
+    spawn_blocking_result(move || {
+        let db = resolve_db(&workspace_id)?;
+        if backend_running(&workspace_id) {
+            return Err("BACKEND_RUNNING|请先停止该工作区的后端再整理记忆库".into());
+        }
+        let db_arg = db.to_string_lossy();
+        let out = run_streaming(&app, &venv_dir, &workspace_id, "vacuum", &["--db", &db_arg])?;
+        let result: VacuumResult = parse_bridge_output(&out)?;
+        log_to_file(&format!(
+            "[memory_db] vacuum ws={workspace_id}: {} -> {} bytes",
+            result.before_bytes, result.after_bytes
+        ));
+        Ok(result)
+    })
+    .await
+    .map_err(Into::into)
+}
@@ -7,18 +7,17 @@ use serde_json::Value;
 use std::fs;
 use std::path::Path;
 
+use crate::{atomic_write, normalize_workspace_id, unique_workspace_id, validate_workspace_id};
+
 /// 当前配置文件版本。每次添加迁移时递增此值。
-pub const CURRENT_CONFIG_VERSION: u32 = 1;
+pub const CURRENT_CONFIG_VERSION: u32 = 2;
 
 type MigrationFn = fn(state: &mut Value, root: &Path) -> Result<(), String>;
 
 /// 返回所有已注册的迁移。
 /// 元组格式: (目标版本号, 迁移函数)
 fn get_migrations() -> Vec<(u32, MigrationFn)> {
-    vec![
-        // 示例（下一个版本需要迁移时取消注释并实现）：
-        // (2, migrate_v1_to_v2),
-    ]
+    vec![(2, migrate_v1_to_v2)]
 }
 
 /// 运行所有必要的迁移，从 current_version 升级到 CURRENT_CONFIG_VERSION。
@@ -47,8 +46,7 @@ pub fn run_migrations(state_path: &Path, root: &Path) -> Result<(), String> {
             state["configVersion"] = serde_json::json!(CURRENT_CONFIG_VERSION);
             let data = serde_json::to_string_pretty(&state)
                 .map_err(|e| format!("serialize state.json failed: {e}"))?;
-            fs::write(state_path, data)
-                .map_err(|e| format!("write state.json failed: {e}"))?;
+            atomic_write(state_path, data).map_err(|e| format!("write state.json failed: {e}"))?;
         }
         return Ok(());
     }
@@ -80,8 +78,7 @@ pub fn run_migrations(state_path: &Path, root: &Path) -> Result<(), String> {
     // 写回
     let data = serde_json::to_string_pretty(&state)
         .map_err(|e| format!("serialize state.json failed: {e}"))?;
-    fs::write(state_path, data)
-        .map_err(|e| format!("write state.json failed: {e}"))?;
+    atomic_write(state_path, data).map_err(|e| format!("write state.json failed: {e}"))?;
 
     Ok(())
 }
@@ -90,14 +87,77 @@ pub fn run_migrations(state_path: &Path, root: &Path) -> Result<(), String> {
 // 迁移函数区域 — 每个版本的迁移函数放在下面
 // ═══════════════════════════════════════════════════════════════════════
 
-// 示例迁移函数（留作参考，下一次需要迁移时照此模式添加）：
-//
-// fn migrate_v1_to_v2(state: &mut Value, root: &Path) -> Result<(), String> {
-//     // 例如：重命名字段、添加新字段、迁移工作区配置等
-//     if let Some(obj) = state.as_object_mut() {
-//         // 添加新字段的默认值
-//         obj.entry("newField").or_insert(serde_json::json!("default"));
-//     }
-//     Ok(())
-// }
+/// 改名一个工作区在磁盘上的痕迹：工作区目录（必须成功）、PID 文件和
+/// 技能版本缓存（尽力而为）。
+fn rename_workspace_paths(root: &Path, old: &str, new: &str) -> Result<(), String> {
+    let old_dir = root.join("workspaces").join(old);
+    let new_dir = root.join("workspaces").join(new);
+    if old_dir.exists() {
+        if new_dir.exists() {
+            return Err(format!("{} already exists", new_dir.display()));
+        }
+        fs::rename(&old_dir, &new_dir)
+            .map_err(|e| format!("rename {} failed: {e}", old_dir.display()))?;
+    }
+    let run = root.join("run");
+    let old_pid = run.join(format!("openakita-{old}.pid"));
+    if old_pid.exists() {
+        let _ = fs::rename(&old_pid, run.join(format!("openakita-{new}.pid")));
+    }
+    let cache = root.join("cache").join("skills");
+    if cache.join(old).exists() && !cache.join(new).exists() {
+        let _ = fs::rename(cache.join(old), cache.join(new));
+    }
+    Ok(())
+}
+
+/// v1 → v2：工作区 id 收紧为安全 slug（见 `validate_workspace_id`）。
+/// 含空格、中文、大写或 Windows 保留名的旧 id 换成规整后的新 id，并同步
+/// 改名目录、PID 文件和当前工作区；改名失败的保留旧 id（只影响新建校验）。
+fn migrate_v1_to_v2(state: &mut Value, root: &Path) -> Result<(), String> {
+    let mut renamed: Vec<(String, String)> = Vec::new();
+    if let Some(list) = state.get_mut("workspaces").and_then(|v| v.as_array_mut()) {
+        let mut taken: Vec<String> = list
+            .iter()
+            .filter_map(|w| w.get("id")?.as_str())
+            .filter(|id| validate_workspace_id(id).is_ok())
+            .map(String::from)
+            .collect();
+        for ws in list.iter_mut() {
+            let Some(old) = ws.get("id").and_then(|v| v.as_str()).map(String::from) else {
+                continue;
+            };
+            if validate_workspace_id(&old).is_ok() {
+                continue;
+            }
+            let mut base = normalize_workspace_id(&old);
+            if base.is_empty() {
+                let name = ws.get("name").and_then(|v| v.as_str()).unwrap_or("");
+                base = normalize_workspace_id(name);
+            }
+            let new = unique_workspace_id(&base, &taken);
+            match rename_workspace_paths(root, &old, &new) {
+                Ok(()) => {
+                    eprintln!("Workspace id migrated: {old:?} -> {new}");
+                    ws["id"] = serde_json::json!(new);
+                    taken.push(new.clone());
+                    renamed.push((old, new));
+                }
+                Err(e) => {
+                    eprintln!("Workspace id {old:?} kept: {e}");
+                    taken.push(old);
+                }
+            }
+        }
+    }
+    let current = state
+        .get("currentWorkspaceId")
+        .and_then(|v| v.as_str())
+        .map(String::from);
+    let moved = current.and_then(|cur| renamed.into_iter().find(|(old, _)| *old == cur));
+    if let Some((_, new)) = moved {
+        state["currentWorkspaceId"] = serde_json::json!(new);
+    }
+    Ok(())
+}
 
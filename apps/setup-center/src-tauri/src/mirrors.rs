@@ -0,0 +1,132 @@
+//! Mirror configuration shared by every download path.
+//!
+//! PyPI index, python-build-standalone source, GitHub proxy prefix and the
+//! skill marketplace registry used to be hardcoded (or passed ad hoc by the
+//! front-end) in several places.  They now live in one `mirrors` object in
+//! `state.json`, edited through `get_mirrors()` / `set_mirrors(cfg)`, and
+//! are consumed by:
+//!
+//! * `pip_install`, `fetch_pypi_versions` and the runtime bootstrap
+//!   (`PIP_INDEX_URL` / `UV_INDEX_URL`) — [`MirrorConfig::pypi_index`];
+//! * uv's managed-Python download (`UV_PYTHON_INSTALL_MIRROR`) —
+//!   [`MirrorConfig::python_build_mirror`];
+//! * skill installs and the marketplace bridge calls, via
+//!   [`bridge_env`] (`OPENAKITA_GITHUB_PROXY` /
+//!   `OPENAKITA_SKILL_REGISTRY_URL`, plus the private registries of
+//!   `skill_registry` as `OPENAKITA_SKILL_REGISTRIES`);
+//! * GitHub release downloads made by the app itself, via [`github_url`];
+//! * model downloads from Hugging Face — [`MirrorConfig::hf_endpoint`]
+//!   (e.g. `https://hf-mirror.com`).
+//!
+//! An explicit argument from the caller (e.g. `index_url`) and the
+//! `OPENAKITA_PIP_INDEX_URL` / `PIP_INDEX_URL` env vars still take
+//! precedence; unset fields fall back to the previous built-in defaults.
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::CmdResult;
+use crate::{read_state_file, skill_registry, write_state_file, STATE_FILE_LOCK};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorConfig {
+    /// PyPI simple index, e.g. `https://pypi.tuna.tsinghua.edu.cn/simple/`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pypi_index: Option<String>,
+    /// python-build-standalone release mirror for uv managed Python.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub python_build_mirror: Option<String>,
+    /// Prefix prepended to `https://github.com/...` downloads,
+    /// e.g. `https://ghproxy.net/`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_proxy: Option<String>,
+    /// Skill marketplace registry (JSON list endpoint).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub marketplace_registry: Option<String>,
+    /// Hugging Face endpoint replacing `https://huggingface.co`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hf_endpoint: Option<String>,
+}
+
+fn normalize_url(field: &str, value: Option<String>) -> Result<Option<String>, String> {
+    let Some(v) = value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+    else {
+        return Ok(None);
+    };
+    let parsed = reqwest::Url::parse(&v).map_err(|e| format!("{field} 不是有效的 URL: {e}"))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(format!("{field} 仅支持 http/https 地址: {v}"));
+    }
+    Ok(Some(v))
+}
+
+/// Validate and normalise a config coming from the UI: blank fields are
+/// dropped, URLs must be http(s), the GitHub proxy always ends with `/`.
+pub(crate) fn normalize(cfg: MirrorConfig) -> Result<MirrorConfig, String> {
+    let github_proxy = normalize_url("githubProxy", cfg.github_proxy)?.map(|mut p| {
+        if !p.ends_with('/') {
+            p.push('/');
+        }
+        p
+    });
+    Ok(MirrorConfig {
+        pypi_index: normalize_url("pypiIndex", cfg.pypi_index)?,
+        python_build_mirror: normalize_url("pythonBuildMirror", cfg.python_build_mirror)?,
+        github_proxy,
+        marketplace_registry: normalize_url("marketplaceRegistry", cfg.marketplace_registry)?,
+        hf_endpoint: normalize_url("hfEndpoint", cfg.hf_endpoint)?
+            .map(|e| e.trim_end_matches('/').to_string()),
+    })
+}
+
+/// Current mirror settings (all `None` when never configured).
+pub(crate) fn current() -> MirrorConfig {
+    read_state_file().mirrors.unwrap_or_default()
+}
+
+/// Env vars passed to bridge subprocesses that download skills / catalogs.
+pub(crate) fn bridge_env(cfg: &MirrorConfig) -> Vec<(&'static str, String)> {
+    let mut env = Vec::new();
+    if let Some(ref p) = cfg.github_proxy {
+        env.push(("OPENAKITA_GITHUB_PROXY", p.clone()));
+    }
+    if let Some(ref r) = cfg.marketplace_registry {
+        env.push(("OPENAKITA_SKILL_REGISTRY_URL", r.clone()));
+    }
+    if let Some(v) = skill_registry::bridge_env_value(&skill_registry::configured()) {
+        env.push((skill_registry::REGISTRIES_ENV, v));
+    }
+    env
+}
+
+/// Route a `https://github.com/...` download through the configured proxy
+/// prefix; other URLs are returned unchanged.
+pub(crate) fn github_url(url: &str, cfg: &MirrorConfig) -> String {
+    match &cfg.github_proxy {
+        Some(proxy) if url.starts_with("https://github.com/") => format!("{proxy}{url}"),
+        _ => url.to_string(),
+    }
+}
+
+#[tauri::command]
+pub fn get_mirrors() -> MirrorConfig {
+    current()
+}
+
+#[tauri::command]
+pub fn set_mirrors(cfg: MirrorConfig) -> CmdResult<MirrorConfig> {
+    let cfg = normalize(cfg)?;
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    state.mirrors = if cfg == MirrorConfig::default() {
+        None
+    } else {
+        Some(cfg.clone())
+    };
+    write_state_file(&state)?;
+    Ok(cfg)
+}
@@ -0,0 +1,440 @@
+//! Local model files for LM Studio / Ollama-style inference backends.
+//!
+//! Users pointing an endpoint at a local server still have to fetch GGUF
+//! weights somewhere.  `download_model(repo, file)` pulls one file from a
+//! Hugging Face repository into the models area (`~/.openakita/models` by
+//! default, movable with `set_models_dir`), laid out as
+//! `<owner>--<name>/<file>`:
+//!
+//! * the transfer goes through [`net::download_resumable`] into
+//!   `<file>.part`, so an interrupted or cancelled download continues where
+//!   it stopped on the next call;
+//! * the expected SHA-256 comes from the caller or from the repository's
+//!   tree API (LFS object id) and is checked before `.part` is renamed;
+//! * the endpoint follows the `hfEndpoint` mirror (e.g. hf-mirror.com).
+//!
+//! Each finished file gets a `<file>.openakita.json` sidecar (repo,
+//! revision, checksum).  `list_local_models()` lists model files in the
+//! area — including ones copied there by hand and unfinished `.part`
+//! downloads.  Progress is emitted as `model-download` events; the
+//! `downloadId` doubles as the `cancel_http_request` id.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::errors::CmdResult;
+use crate::{
+    archive, atomic_write, available_space_mb, emit_if_ui_live, log_to_file, mirrors, net, now_ms,
+    openakita_root_dir, read_state_file, skills, spawn_blocking_result, write_state_file,
+    STATE_FILE_LOCK,
+};
+
+const EVENT: &str = "model-download";
+const DEFAULT_HF_ENDPOINT: &str = "https://huggingface.co";
+const PART_SUFFIX: &str = ".part";
+const SIDECAR_SUFFIX: &str = ".openakita.json";
+const MODEL_EXTENSIONS: &[&str] = &["gguf", "safetensors", "bin", "onnx"];
+/// Headroom kept free on the models disk beyond the file itself.
+const FREE_SPACE_MARGIN_MB: f64 = 512.0;
+
+static ACTIVE: Lazy<Mutex<HashSet<PathBuf>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalModel {
+    pub name: String,
+    pub repo: Option<String>,
+    pub path: String,
+    pub size_bytes: u64,
+    pub sha256: Option<String>,
+    /// The checksum was verified after download.
+    pub verified: bool,
+    /// An unfinished `.part` download; `download_model` resumes it.
+    pub partial: bool,
+    pub downloaded_at_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelSidecar {
+    repo: String,
+    file: String,
+    revision: String,
+    #[serde(default)]
+    sha256: Option<String>,
+    #[serde(default)]
+    verified: bool,
+    #[serde(default)]
+    downloaded_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgressEvent<'a> {
+    download_id: &'a str,
+    repo: &'a str,
+    file: &'a str,
+    stage: &'a str,
+    percent: Option<u8>,
+    bytes_done: u64,
+    total_bytes: Option<u64>,
+    message: String,
+}
+
+pub(crate) fn models_root() -> PathBuf {
+    read_state_file()
+        .models_dir
+        .filter(|d| !d.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| openakita_root_dir().join("models"))
+}
+
+/// `owner/name` with the characters Hugging Face allows.
+pub(crate) fn valid_repo(repo: &str) -> bool {
+    let ok = |part: &str| {
+        !part.is_empty()
+            && part != "."
+            && part != ".."
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    matches!(repo.split_once('/'), Some((owner, name)) if ok(owner) && ok(name))
+}
+
+pub(crate) fn model_url(endpoint: &str, repo: &str, revision: &str, file: &str) -> String {
+    format!(
+        "{}/{repo}/resolve/{revision}/{file}",
+        endpoint.trim_end_matches('/')
+    )
+}
+
+/// (sha256, size) of `file` from a `/api/models/{repo}/tree/{rev}[/dir]`
+/// listing.  Only LFS files carry a SHA-256.
+pub(crate) fn tree_lfs_entry(tree: &serde_json::Value, file: &str) -> Option<(String, u64)> {
+    let entry = tree
+        .as_array()?
+        .iter()
+        .find(|e| e.get("path").and_then(|p| p.as_str()) == Some(file))?;
+    let lfs = entry.get("lfs")?;
+    let oid = lfs.get("oid")?.as_str()?.to_ascii_lowercase();
+    let size = lfs
+        .get("size")
+        .or_else(|| entry.get("size"))
+        .and_then(|s| s.as_u64())?;
+    Some((oid, size))
+}
+
+fn sidecar_path(model: &Path) -> PathBuf {
+    let mut name = model.file_name().unwrap_or_default().to_os_string();
+    name.push(SIDECAR_SUFFIX);
+    model.with_file_name(name)
+}
+
+fn part_path(model: &Path) -> PathBuf {
+    let mut name = model.file_name().unwrap_or_default().to_os_string();
+    name.push(PART_SUFFIX);
+    model.with_file_name(name)
+}
+
+fn is_model_file(name: &str) -> bool {
+    let base = name.strip_suffix(PART_SUFFIX).unwrap_or(name);
+    Path::new(base)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| MODEL_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+fn read_sidecar(model: &Path) -> Option<ModelSidecar> {
+    let content = fs::read_to_string(sidecar_path(model)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn local_model(path: &Path, size_bytes: u64, partial: bool) -> LocalModel {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let sidecar = if partial { None } else { read_sidecar(path) };
+    LocalModel {
+        name: name.strip_suffix(PART_SUFFIX).unwrap_or(&name).to_string(),
+        repo: sidecar.as_ref().map(|s| s.repo.clone()),
+        path: path.to_string_lossy().to_string(),
+        size_bytes,
+        sha256: sidecar.as_ref().and_then(|s| s.sha256.clone()),
+        verified: sidecar.as_ref().is_some_and(|s| s.verified),
+        partial,
+        downloaded_at_ms: sidecar.map(|s| s.downloaded_at_ms),
+    }
+}
+
+fn collect_models(dir: &Path, depth: usize, out: &mut Vec<LocalModel>) {
+    let Ok(rd) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in rd.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth > 0 {
+                collect_models(&path, depth - 1, out);
+            }
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !is_model_file(&name) {
+            continue;
+        }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        out.push(local_model(&path, size, name.ends_with(PART_SUFFIX)));
+    }
+}
+
+#[tauri::command]
+pub async fn list_local_models() -> CmdResult<Vec<LocalModel>> {
+    spawn_blocking_result(|| {
+        let mut out = Vec::new();
+        // <owner>--<name>/<子目录>/<文件> 最多三层
+        collect_models(&models_root(), 3, &mut out);
+        out.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(out)
+    })
+    .await
+    .map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn get_models_dir() -> String {
+    models_root().to_string_lossy().to_string()
+}
+
+/// Move the models area (`None` / empty = back to the default).  Existing
+/// files are not moved.
+#[tauri::command]
+pub fn set_models_dir(path: Option<String>) -> CmdResult<String> {
+    let path = path.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    if let Some(ref p) = path {
+        let dir = Path::new(p);
+        if !dir.is_absolute() {
+            return Err(format!("INVALID_ARGUMENT|需要绝对路径: {p}").into());
+        }
+        fs::create_dir_all(dir).map_err(|e| format!("创建模型目录失败: {e}"))?;
+    }
+    {
+        let _lock = STATE_FILE_LOCK
+            .lock()
+            .map_err(|e| format!("state lock failed: {e}"))?;
+        let mut state = read_state_file();
+        state.models_dir = path;
+        write_state_file(&state)?;
+    }
+    let root = get_models_dir();
+    log_to_file(&format!("[models] models dir -> {root}"));
+    Ok(root)
+}
+
+async fn fetch_lfs_entry(
+    endpoint: &str,
+    repo: &str,
+    revision: &str,
+    file: &str,
+) -> Option<(String, u64)> {
+    let dir = file.rsplit_once('/').map(|(d, _)| d);
+    let url = match dir {
+        Some(d) => format!("{endpoint}/api/models/{repo}/tree/{revision}/{d}"),
+        None => format!("{endpoint}/api/models/{repo}/tree/{revision}"),
+    };
+    let resp = net::http_client()
+        .get(&url)
+        .timeout(std::time::Duration::from_secs(20))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status());
+    match resp {
+        Ok(r) => r
+            .json::<serde_json::Value>()
+            .await
+            .ok()
+            .and_then(|tree| tree_lfs_entry(&tree, file)),
+        Err(e) => {
+            log_to_file(&format!("[models] tree lookup {url} failed: {e}"));
+            None
+        }
+    }
+}
+
+struct ActiveGuard(PathBuf);
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        if let Ok(mut set) = ACTIVE.lock() {
+            set.remove(&self.0);
+        }
+    }
+}
+
+/// Download `file` of Hugging Face repo `repo` (at `revision`, default
+/// `main`).  `sha256` overrides the checksum from the repository listing.
+#[tauri::command]
+pub async fn download_model(
+    app: tauri::AppHandle,
+    repo: String,
+    file: String,
+    revision: Option<String>,
+    sha256: Option<String>,
+    download_id: Option<String>,
+) -> CmdResult<LocalModel> {
+    let repo = repo.trim().to_string();
+    let file = file.trim().trim_start_matches('/').to_string();
+    let revision = revision
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+        .unwrap_or_else(|| "main".to_string());
+    if !valid_repo(&repo) {
+        return Err(format!("INVALID_ARGUMENT|仓库名应为 owner/name: {repo}").into());
+    }
+    let rel = archive::sanitize_entry_path(Path::new(&file))
+        .filter(|_| !file.contains('\\'))
+        .ok_or_else(|| format!("INVALID_ARGUMENT|文件路径无效: {file}"))?;
+    if revision.contains(['/', '\\', '?', '#']) || revision.contains("..") {
+        return Err(format!("INVALID_ARGUMENT|revision 无效: {revision}").into());
+    }
+    net::ensure_online("下载模型")?;
+    let download_id = download_id.unwrap_or_else(|| format!("model-{}", now_ms()));
+    let dest = models_root().join(repo.replace('/', "--")).join(&rel);
+    let part = part_path(&dest);
+    if !ACTIVE
+        .lock()
+        .map_err(|e| format!("model lock failed: {e}"))?
+        .insert(dest.clone())
+    {
+        return Err(format!("ALREADY_EXISTS|{file} 正在下载中").into());
+    }
+    let _guard = ActiveGuard(dest.clone());
+
+    let emit = |stage: &str, percent: Option<u8>, done: u64, total: Option<u64>, msg: String| {
+        emit_if_ui_live(
+            &app,
+            EVENT,
+            ProgressEvent {
+                download_id: &download_id,
+                repo: &repo,
+                file: &file,
+                stage,
+                percent,
+                bytes_done: done,
+                total_bytes: total,
+                message: msg,
+            },
+        );
+    };
+    emit("checking", None, 0, None, String::new());
+
+    let endpoint = mirrors::current()
+        .hf_endpoint
+        .unwrap_or_else(|| DEFAULT_HF_ENDPOINT.to_string());
+    let remote = fetch_lfs_entry(&endpoint, &repo, &revision, &file).await;
+    let expected = sha256
+        .map(|s| s.trim().trim_start_matches("sha256:").to_ascii_lowercase())
+        .filter(|s| !s.is_empty())
+        .or_else(|| remote.as_ref().map(|(sha, _)| sha.clone()));
+    // 已下载且校验过同一个 sha256 的文件直接复用
+    if let Some(sha) = expected.as_deref() {
+        let done = read_sidecar(&dest).filter(|s| s.verified && s.sha256.as_deref() == Some(sha));
+        if done.is_some() && dest.is_file() {
+            let size = fs::metadata(&dest).map(|m| m.len()).unwrap_or(0);
+            emit("done", Some(100), size, Some(size), String::new());
+            return Ok(local_model(&dest, size, false));
+        }
+    }
+    let url = model_url(&endpoint, &repo, &revision, &file);
+    log_to_file(&format!(
+        "[models] download {url} -> {} (sha256={expected:?})",
+        dest.display()
+    ));
+
+    let result = async {
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("创建模型目录失败: {e}"))?;
+        }
+        if let Some((_, size)) = remote {
+            let have = fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+            let need_mb = size.saturating_sub(have) as f64 / 1024.0 / 1024.0;
+            let free = available_space_mb(dest.parent().unwrap_or(&dest));
+            if free < need_mb + FREE_SPACE_MARGIN_MB {
+                return Err(format!(
+                    "INSUFFICIENT_DISK_SPACE|磁盘空间不足：还需下载 {need_mb:.0} MB，可用 {free:.0} MB"
+                ));
+            }
+        }
+        net::run_cancellable(
+            Some(&download_id),
+            net::download_resumable(&url, &part, |done, total| {
+                emit(
+                    "downloading",
+                    net::download_percent(done, total),
+                    done,
+                    total,
+                    String::new(),
+                )
+            }),
+        )
+        .await?;
+
+        let verified = match expected.clone() {
+            Some(expected) => {
+                emit("verifying", None, 0, None, String::new());
+                let part2 = part.clone();
+                let actual = spawn_blocking_result(move || skills::sha256_file(&part2)).await?;
+                if actual != expected {
+                    // 校验失败的部分文件不能再续传，删掉重来
+                    let _ = fs::remove_file(&part);
+                    return Err(format!(
+                        "MODEL_CHECKSUM_MISMATCH|模型文件校验失败: expected {expected}, got {actual}"
+                    ));
+                }
+                true
+            }
+            None => false,
+        };
+        fs::rename(&part, &dest).map_err(|e| format!("保存模型文件失败: {e}"))?;
+        let sidecar = ModelSidecar {
+            repo: repo.clone(),
+            file: file.clone(),
+            revision: revision.clone(),
+            sha256: expected.clone(),
+            verified,
+            downloaded_at_ms: now_ms(),
+        };
+        let json = serde_json::to_string_pretty(&sidecar).map_err(|e| e.to_string())?;
+        atomic_write(sidecar_path(&dest), json).map_err(|e| format!("write sidecar: {e}"))?;
+        let size = fs::metadata(&dest).map(|m| m.len()).unwrap_or(0);
+        Ok(local_model(&dest, size, false))
+    }
+    .await;
+
+    match &result {
+        Ok(m) => {
+            log_to_file(&format!(
+                "[models] downloaded {} ({} bytes)",
+                m.path, m.size_bytes
+            ));
+            emit(
+                "done",
+                Some(100),
+                m.size_bytes,
+                Some(m.size_bytes),
+                String::new(),
+            );
+        }
+        Err(e) if e.starts_with(net::REQUEST_CANCELLED_ERROR_PREFIX) => {
+            log_to_file(&format!("[models] download {file} cancelled, partial kept"));
+            emit("cancelled", None, 0, None, String::new());
+        }
+        Err(e) => {
+            log_to_file(&format!("[models] download {file} failed: {e}"));
+            emit("error", None, 0, None, e.clone());
+        }
+    }
+    result.map_err(Into::into)
+}
@@ -0,0 +1,480 @@
+//! Native tools the backend shells out to (FFmpeg, Tesseract OCR).
+//!
+//! Voice skills call `ffmpeg`, document skills call `tesseract`; when they
+//! aren't on `PATH` the skill fails halfway with "ffmpeg not found".
+//! `list_native_deps()` reports for each tool whether a managed copy or a
+//! system one is found (and its version); `install_native_dep(name)`
+//! downloads a portable build into `~/.openakita/runtime/tools/<name>/`.
+//! `native_backend_command` puts the managed `bin` directories in front of
+//! the backend's `PATH` (plus `TESSDATA_PREFIX` for Tesseract), so a fresh
+//! install is picked up on the next backend start.
+//!
+//! Built-in sources exist only where an upstream publishes a portable
+//! archive (FFmpeg on Windows / macOS / Linux x86_64).  Tesseract has no
+//! official portable build: `install_native_dep` takes an explicit `url`
+//! (any zip / tar.gz holding the executable), otherwise the error carries
+//! the package-manager command for the platform.
+//!
+//! Progress is emitted as `native-dep` events
+//! (`{ name, stage, percent, message }`, stage = downloading / extracting /
+//! done / error).
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+use crate::errors::CmdResult;
+use crate::{
+    apply_no_window, archive, atomic_write, available_space_mb, emit_if_ui_live, log_to_file,
+    mirrors, net, now_ms, prepend_path, runtime_root_dir, spawn_blocking_result,
+};
+
+const EVENT: &str = "native-dep";
+/// Upper bound for download + unpacked build; FFmpeg static builds are the
+/// largest at ~200 MB unpacked.
+const REQUIRED_MB: f64 = 500.0;
+/// How deep below the archive root the executable may sit
+/// (`ffmpeg-master-latest-win64-gpl/bin/ffmpeg.exe`).
+const MAX_SEARCH_DEPTH: usize = 4;
+
+static INSTALLING: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+struct NativeDepSpec {
+    name: &'static str,
+    executable: &'static str,
+    version_arg: &'static str,
+    /// (windows, macos, linux) package-manager commands.
+    hints: (&'static str, &'static str, &'static str),
+}
+
+const DEPS: &[NativeDepSpec] = &[
+    NativeDepSpec {
+        name: "ffmpeg",
+        executable: "ffmpeg",
+        version_arg: "-version",
+        hints: (
+            "winget install Gyan.FFmpeg",
+            "brew install ffmpeg",
+            "sudo apt install ffmpeg",
+        ),
+    },
+    NativeDepSpec {
+        name: "tesseract",
+        executable: "tesseract",
+        version_arg: "--version",
+        hints: (
+            "winget install UB-Mannheim.TesseractOCR",
+            "brew install tesseract",
+            "sudo apt install tesseract-ocr",
+        ),
+    },
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NativeDepStatus {
+    pub name: String,
+    pub installed: bool,
+    /// "managed" | "system"
+    pub source: Option<String>,
+    pub path: Option<String>,
+    pub version: Option<String>,
+    /// A built-in portable download exists for this platform.
+    pub installable: bool,
+    /// Package-manager command for a system-wide install.
+    pub hint: String,
+}
+
+/// `runtime/tools/<name>/manifest.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolManifest {
+    name: String,
+    /// Directory holding the executable, relative to the tool directory.
+    bin_dir: String,
+    /// `tessdata` directory, relative to the tool directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tessdata: Option<String>,
+    #[serde(default)]
+    version: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    installed_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgressEvent<'a> {
+    name: &'a str,
+    stage: &'a str,
+    percent: Option<u8>,
+    message: String,
+}
+
+fn tools_dir() -> PathBuf {
+    runtime_root_dir().join("tools")
+}
+
+fn spec(name: &str) -> Option<&'static NativeDepSpec> {
+    DEPS.iter().find(|d| d.name == name)
+}
+
+fn exe_name(base: &str) -> String {
+    if cfg!(windows) {
+        format!("{base}.exe")
+    } else {
+        base.to_string()
+    }
+}
+
+fn platform_hint(spec: &NativeDepSpec) -> &'static str {
+    if cfg!(windows) {
+        spec.hints.0
+    } else if cfg!(target_os = "macos") {
+        spec.hints.1
+    } else {
+        spec.hints.2
+    }
+}
+
+/// Portable archive for `name` on `os` / `arch` (`std::env::consts` names).
+pub(crate) fn builtin_source(name: &str, os: &str, arch: &str) -> Option<&'static str> {
+    match (name, os, arch) {
+        ("ffmpeg", "windows", "x86_64") => Some(
+            "https://github.com/BtbN/FFmpeg-Builds/releases/download/latest/ffmpeg-master-latest-win64-gpl.zip",
+        ),
+        ("ffmpeg", "macos", "aarch64") => {
+            Some("https://ffmpeg.martin-riedl.de/redirect/latest/macos/arm64/release/ffmpeg.zip")
+        }
+        ("ffmpeg", "macos", "x86_64") => {
+            Some("https://ffmpeg.martin-riedl.de/redirect/latest/macos/amd64/release/ffmpeg.zip")
+        }
+        ("ffmpeg", "linux", "x86_64") => {
+            Some("https://ffmpeg.martin-riedl.de/redirect/latest/linux/amd64/release/ffmpeg.zip")
+        }
+        _ => None,
+    }
+}
+
+/// Breadth-first search for a file called `file_name` at most `max_depth`
+/// directories below `root`.  Shallowest match wins.
+pub(crate) fn find_file(root: &Path, file_name: &str, max_depth: usize) -> Option<PathBuf> {
+    let mut level = vec![root.to_path_buf()];
+    for _ in 0..=max_depth {
+        let mut next = Vec::new();
+        for dir in level {
+            let Ok(rd) = fs::read_dir(&dir) else {
+                continue;
+            };
+            let mut entries: Vec<PathBuf> = rd.flatten().map(|e| e.path()).collect();
+            entries.sort();
+            for p in entries {
+                if p.is_dir() {
+                    next.push(p);
+                } else if p.file_name().is_some_and(|n| n == file_name) {
+                    return Some(p);
+                }
+            }
+        }
+        level = next;
+    }
+    None
+}
+
+fn find_dir(root: &Path, dir_name: &str, max_depth: usize) -> Option<PathBuf> {
+    let mut level = vec![root.to_path_buf()];
+    for _ in 0..=max_depth {
+        let mut next = Vec::new();
+        for dir in level {
+            let Ok(rd) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for p in rd.flatten().map(|e| e.path()).filter(|p| p.is_dir()) {
+                if p.file_name().is_some_and(|n| n == dir_name) {
+                    return Some(p);
+                }
+                next.push(p);
+            }
+        }
+        level = next;
+    }
+    None
+}
+
+fn read_manifest(name: &str) -> Option<ToolManifest> {
+    let content = fs::read_to_string(tools_dir().join(name).join("manifest.json")).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Managed copy of `spec`'s executable, if installed.
+fn managed_executable(spec: &NativeDepSpec) -> Option<PathBuf> {
+    let manifest = read_manifest(spec.name)?;
+    let exe = tools_dir()
+        .join(spec.name)
+        .join(manifest.bin_dir)
+        .join(exe_name(spec.executable));
+    exe.is_file().then_some(exe)
+}
+
+fn find_on_path(executable: &str) -> Option<PathBuf> {
+    let name = exe_name(executable);
+    std::env::var_os("PATH").and_then(|p| {
+        std::env::split_paths(&p)
+            .map(|d| d.join(&name))
+            .find(|p| p.is_file())
+    })
+}
+
+/// First line of `<exe> <version_arg>`.
+fn probe_version(exe: &Path, version_arg: &str) -> Option<String> {
+    let mut cmd = Command::new(exe);
+    cmd.arg(version_arg);
+    apply_no_window(&mut cmd);
+    let out = cmd.output().ok()?;
+    // tesseract 4.x 把版本号打到 stderr
+    let text = if out.stdout.is_empty() {
+        out.stderr
+    } else {
+        out.stdout
+    };
+    String::from_utf8_lossy(&text)
+        .lines()
+        .next()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+}
+
+fn dep_status(spec: &NativeDepSpec) -> NativeDepStatus {
+    let (source, path) = match managed_executable(spec) {
+        Some(p) => (Some("managed"), Some(p)),
+        None => match find_on_path(spec.executable) {
+            Some(p) => (Some("system"), Some(p)),
+            None => (None, None),
+        },
+    };
+    NativeDepStatus {
+        name: spec.name.to_string(),
+        installed: path.is_some(),
+        source: source.map(String::from),
+        version: path
+            .as_deref()
+            .and_then(|p| probe_version(p, spec.version_arg)),
+        path: path.map(|p| p.to_string_lossy().to_string()),
+        installable: builtin_source(spec.name, std::env::consts::OS, std::env::consts::ARCH)
+            .is_some(),
+        hint: platform_hint(spec).to_string(),
+    }
+}
+
+#[tauri::command]
+pub async fn list_native_deps() -> CmdResult<Vec<NativeDepStatus>> {
+    spawn_blocking_result(|| Ok(DEPS.iter().map(dep_status).collect()))
+        .await
+        .map_err(Into::into)
+}
+
+/// Put the managed tools on a backend command's `PATH`.
+pub(crate) fn apply_to_command(cmd: &mut Command) {
+    for spec in DEPS {
+        let Some(manifest) = read_manifest(spec.name) else {
+            continue;
+        };
+        let dir = tools_dir().join(spec.name);
+        let bin = dir.join(&manifest.bin_dir);
+        if bin.join(exe_name(spec.executable)).is_file() {
+            prepend_path(cmd, &bin);
+        }
+        if let Some(tessdata) = manifest.tessdata.map(|t| dir.join(t)) {
+            if tessdata.is_dir() {
+                cmd.env("TESSDATA_PREFIX", tessdata);
+            }
+        }
+    }
+}
+
+fn emit(app: &tauri::AppHandle, name: &str, stage: &str, percent: Option<u8>, message: String) {
+    emit_if_ui_live(
+        app,
+        EVENT,
+        ProgressEvent {
+            name,
+            stage,
+            percent,
+            message,
+        },
+    );
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    if let Ok(meta) = fs::metadata(path) {
+        let mut perms = meta.permissions();
+        perms.set_mode(perms.mode() | 0o755);
+        let _ = fs::set_permissions(path, perms);
+    }
+}
+
+/// Unpack `archive_path` into `staging`, locate the executable and write the
+/// manifest.  Returns the executable inside `staging`.
+fn unpack_tool(
+    app: &tauri::AppHandle,
+    spec: &NativeDepSpec,
+    url: &str,
+    archive_path: &Path,
+    staging: &Path,
+) -> Result<PathBuf, String> {
+    let _ = fs::remove_dir_all(staging);
+    let mut last_percent = None;
+    let mut on_progress = |p: archive::ExtractProgress| {
+        let percent = net::download_percent(p.bytes, p.total_bytes);
+        if percent != last_percent {
+            last_percent = percent;
+            emit(app, spec.name, "extracting", percent, String::new());
+        }
+    };
+    archive::extract_archive(
+        archive_path,
+        staging,
+        &mut archive::ExtractOptions {
+            on_progress: Some(&mut on_progress),
+            ..Default::default()
+        },
+    )?;
+    let exe = find_file(staging, &exe_name(spec.executable), MAX_SEARCH_DEPTH)
+        .ok_or_else(|| format!("下载的压缩包里没有 {}", exe_name(spec.executable)))?;
+    #[cfg(unix)]
+    make_executable(&exe);
+    let rel = |p: &Path| {
+        p.strip_prefix(staging)
+            .map(|r| r.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default()
+    };
+    let bin_dir = exe.parent().map(rel).unwrap_or_default();
+    let tessdata = (spec.name == "tesseract")
+        .then(|| find_dir(staging, "tessdata", MAX_SEARCH_DEPTH))
+        .flatten()
+        .map(|d| rel(&d));
+    let manifest = ToolManifest {
+        name: spec.name.to_string(),
+        bin_dir,
+        tessdata,
+        version: probe_version(&exe, spec.version_arg).unwrap_or_default(),
+        url: url.to_string(),
+        installed_at_ms: now_ms(),
+    };
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    atomic_write(staging.join("manifest.json"), json)
+        .map_err(|e| format!("write manifest: {e}"))?;
+    Ok(exe)
+}
+
+async fn install(
+    app: &tauri::AppHandle,
+    spec: &'static NativeDepSpec,
+    url: String,
+) -> Result<(), String> {
+    let root = tools_dir();
+    fs::create_dir_all(&root).map_err(|e| format!("create {}: {e}", root.display()))?;
+    let free = available_space_mb(&root);
+    if free < REQUIRED_MB {
+        return Err(format!(
+            "INSUFFICIENT_DISK_SPACE|磁盘空间不足：安装 {} 需要约 {REQUIRED_MB:.0} MB，可用 {free:.0} MB",
+            spec.name
+        ));
+    }
+    let archive_path = root.join(format!(".{}-download", spec.name));
+    let staging = root.join(format!(".{}-staging", spec.name));
+    let result = async {
+        net::download_to_file(&url, &archive_path, |done, total| {
+            emit(
+                app,
+                spec.name,
+                "downloading",
+                net::download_percent(done, total),
+                format!("{:.1} MB", done as f64 / 1024.0 / 1024.0),
+            )
+        })
+        .await?;
+        emit(app, spec.name, "extracting", None, String::new());
+        let (app2, url2, archive2, staging2) = (
+            app.clone(),
+            url.clone(),
+            archive_path.clone(),
+            staging.clone(),
+        );
+        spawn_blocking_result(move || unpack_tool(&app2, spec, &url2, &archive2, &staging2))
+            .await?;
+        // 新版本解压、校验完再替换旧目录
+        let target = root.join(spec.name);
+        if target.exists() {
+            fs::remove_dir_all(&target)
+                .map_err(|e| format!("remove old {}: {e}", target.display()))?;
+        }
+        fs::rename(&staging, &target).map_err(|e| format!("install {}: {e}", target.display()))
+    }
+    .await;
+    let _ = fs::remove_file(&archive_path);
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&staging);
+    }
+    result
+}
+
+/// Download a portable build of `name` into `runtime/tools/<name>`.  `url`
+/// overrides the built-in source (required where there is none).  Takes
+/// effect on the next backend start.
+#[tauri::command]
+pub async fn install_native_dep(
+    app: tauri::AppHandle,
+    name: String,
+    url: Option<String>,
+) -> CmdResult<NativeDepStatus> {
+    let spec = spec(name.trim())
+        .ok_or_else(|| format!("INVALID_ARGUMENT|未知的依赖: {name}（ffmpeg / tesseract）"))?;
+    let url = match url.map(|u| u.trim().to_string()).filter(|u| !u.is_empty()) {
+        Some(u) if u.starts_with("https://") || u.starts_with("http://") => u,
+        Some(u) => return Err(format!("INVALID_ARGUMENT|仅支持 http/https 地址: {u}").into()),
+        None => builtin_source(spec.name, std::env::consts::OS, std::env::consts::ARCH)
+            .map(|u| mirrors::github_url(u, &mirrors::current()))
+            .ok_or_else(|| {
+                format!(
+                    "NOT_FOUND|{} 在当前平台没有内置的便携版，请提供下载地址，或手动安装：{}",
+                    spec.name,
+                    platform_hint(spec)
+                )
+            })?,
+    };
+    net::ensure_online(&format!("下载 {}", spec.name))?;
+    if !INSTALLING
+        .lock()
+        .map_err(|e| format!("install lock failed: {e}"))?
+        .insert(spec.name.to_string())
+    {
+        return Err(format!("ALREADY_EXISTS|{} 正在安装中", spec.name).into());
+    }
+    log_to_file(&format!("[native_deps] install {} from {url}", spec.name));
+    let result = install(&app, spec, url).await;
+    if let Ok(mut set) = INSTALLING.lock() {
+        set.remove(spec.name);
+    }
+    match &result {
+        Ok(()) => {
+            log_to_file(&format!("[native_deps] install {} done", spec.name));
+            emit(&app, spec.name, "done", Some(100), String::new());
+        }
+        Err(e) => {
+            log_to_file(&format!("[native_deps] install {} failed: {e}", spec.name));
+            emit(&app, spec.name, "error", None, e.clone());
+        }
+    }
+    result?;
+    spawn_blocking_result(move || Ok(dep_status(spec)))
+        .await
+        .map_err(Into::into)
+}
@@ -0,0 +1,923 @@
+//! Outbound HTTP helpers shared by Setup Center commands.
+//!
+//! GitHub API access: anonymous calls to `api.github.com` are limited to
+//! 60 requests/hour per IP, which shared-IP environments (campus NAT,
+//! corporate proxies, CI runners) exhaust quickly.  Requests made through
+//! [`github_get_json`] therefore:
+//!
+//! * attach an optional token (`OPENAKITA_GITHUB_TOKEN` / `GITHUB_TOKEN`
+//!   env, then the `githubToken` field in `state.json`);
+//! * read `X-RateLimit-Remaining` / `X-RateLimit-Reset` / `Retry-After`
+//!   on 403/429 responses, back off and retry when the wait is short;
+//! * give up early with a `GITHUB_RATE_LIMITED|...` error when the reset
+//!   is far away, so callers can fall back to the mirror manifest instead
+//!   of blocking the UI for tens of minutes.
+//!
+//! Response cache: metadata lookups (PyPI versions, marketplace catalog,
+//! release info) go through an on-disk cache under `~/.openakita/cache/http/`
+//! that honours `ETag` / `Last-Modified` / `Cache-Control: max-age`.  Fresh
+//! entries are served without touching the network; stale entries are
+//! revalidated, and served as-is when the network is unreachable so the
+//! pages still render offline.  `no-store` / `private` responses are never
+//! written.  Callers pass `refresh: true` to bypass the cache.
+//!
+//! Offline mode: a persisted `offlineMode` flag (or `OPENAKITA_OFFLINE=1`)
+//! makes every network-touching command fail fast with an
+//! `OFFLINE_MODE|...` error — or serve cached data where there is some —
+//! instead of hanging on connect timeouts on air-gapped / metered links.
+//!
+//! Clients: external requests share one async [`reqwest::Client`]
+//! ([`http_client`]) and localhost backend calls share a proxy-free one
+//! ([`local_http_client`]), so connection pools are reused and no blocking
+//! pool thread is parked on a socket.  Per-request timeouts are set on the
+//! request builder.  Commands that accept a `request_id` can be aborted
+//! from JS through `cancel_http_request`.
+//!
+//! Bandwidth: large downloads made by the app itself (`download_file`, and
+//! anything else streaming through [`throttle_download`]) share one global
+//! token bucket whose rate is set with `set_download_rate_limit(kbps)` and
+//! persisted as `downloadRateLimitKbps`.  0 / unset means unlimited.
+//! pip / uv subprocesses manage their own sockets and are not throttled.
+//! Runtime downloads (browser, native tools, models) go through
+//! [`download_to_file`] / [`download_resumable`], which add a stall timeout,
+//! percentage callbacks and `Range` resume on top.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+use crate::errors::CmdResult;
+use crate::{
+    atomic_write, log_to_file, now_epoch_secs, openakita_root_dir, read_state_file, trace,
+    write_state_file, STATE_FILE_LOCK,
+};
+
+pub(crate) const HTTP_USER_AGENT: &str = "openakita-desktop/1.0";
+
+// ── Shared clients + cancellation ──
+
+static HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .user_agent(HTTP_USER_AGENT)
+        .connect_timeout(Duration::from_secs(10))
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new())
+});
+
+static LOCAL_HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .no_proxy()
+        .connect_timeout(Duration::from_secs(10))
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new())
+});
+
+static INSECURE_HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .user_agent(HTTP_USER_AGENT)
+        .connect_timeout(Duration::from_secs(10))
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new())
+});
+
+/// Shared client for external (internet) requests; honours system proxy.
+pub(crate) fn http_client() -> &'static reqwest::Client {
+    &HTTP_CLIENT
+}
+
+/// Like [`http_client`] but skips TLS certificate verification.  Only for
+/// explicit opt-in (`allowInsecure`) against self-signed intranet mirrors.
+pub(crate) fn insecure_http_client() -> &'static reqwest::Client {
+    &INSECURE_HTTP_CLIENT
+}
+
+/// Shared client for 127.0.0.1 backend calls; never goes through a proxy.
+pub(crate) fn local_http_client() -> &'static reqwest::Client {
+    &LOCAL_HTTP_CLIENT
+}
+
+pub(crate) fn is_local_url(url: &str) -> bool {
+    url.starts_with("http://127.0.0.1") || url.starts_with("http://localhost")
+}
+
+pub(crate) const REQUEST_CANCELLED_ERROR_PREFIX: &str = "REQUEST_CANCELLED|";
+
+static HTTP_CANCEL_HANDLES: Lazy<Mutex<HashMap<String, Arc<Notify>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn cancel_handle(request_id: &str) -> Arc<Notify> {
+    match HTTP_CANCEL_HANDLES.lock() {
+        Ok(mut map) => map
+            .entry(request_id.to_string())
+            .or_insert_with(|| Arc::new(Notify::new()))
+            .clone(),
+        Err(_) => Arc::new(Notify::new()),
+    }
+}
+
+/// Run `fut` and abort it when `cancel_http_request(request_id)` fires.
+/// Without a `request_id` the future simply runs to completion.
+pub(crate) async fn run_cancellable<T, F>(request_id: Option<&str>, fut: F) -> Result<T, String>
+where
+    F: Future<Output = Result<T, String>>,
+{
+    let Some(id) = request_id else {
+        return fut.await;
+    };
+    let notify = cancel_handle(id);
+    let result = tokio::select! {
+        r = fut => r,
+        _ = notify.notified() => Err(format!("{REQUEST_CANCELLED_ERROR_PREFIX}request {id} cancelled")),
+    };
+    if let Ok(mut map) = HTTP_CANCEL_HANDLES.lock() {
+        map.remove(id);
+    }
+    result
+}
+
+/// Cancel an in-flight request started with the same `request_id`.
+/// A cancel that arrives before the request registers is kept as a permit,
+/// so the request aborts as soon as it starts.
+#[tauri::command]
+pub fn cancel_http_request(request_id: String) {
+    cancel_handle(&request_id).notify_one();
+}
+
+// ── Offline mode ──
+
+pub(crate) const OFFLINE_MODE_ERROR_PREFIX: &str = "OFFLINE_MODE|";
+
+pub(crate) fn offline_mode_enabled() -> bool {
+    if let Ok(v) = std::env::var("OPENAKITA_OFFLINE") {
+        let v = v.trim().to_ascii_lowercase();
+        if !v.is_empty() {
+            return v == "1" || v == "true" || v == "yes";
+        }
+    }
+    read_state_file().offline_mode.unwrap_or(false)
+}
+
+/// Fail fast with an `OFFLINE_MODE|...` error when offline mode is on.
+/// `what` describes the skipped operation, e.g. "检查 PyPI 版本".
+pub(crate) fn ensure_online(what: &str) -> Result<(), String> {
+    if offline_mode_enabled() {
+        return Err(format!(
+            "{OFFLINE_MODE_ERROR_PREFIX}离线模式已开启，已跳过{what}（offline mode enabled）"
+        ));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_offline_mode() -> bool {
+    offline_mode_enabled()
+}
+
+#[tauri::command]
+pub fn set_offline_mode(enabled: bool) -> CmdResult<()> {
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    state.offline_mode = Some(enabled);
+    write_state_file(&state)?;
+    log_to_file(&format!("[net] offline mode set to {enabled}"));
+    Ok(())
+}
+
+// ── Download bandwidth limit ──
+
+/// Current limit in bytes/second; 0 = unlimited.
+static DOWNLOAD_RATE_BPS: Lazy<AtomicU64> = Lazy::new(|| {
+    AtomicU64::new(kbps_to_bytes_per_sec(
+        read_state_file().download_rate_limit_kbps.unwrap_or(0),
+    ))
+});
+
+static DOWNLOAD_BUCKET: Lazy<tokio::sync::Mutex<TokenBucket>> =
+    Lazy::new(|| tokio::sync::Mutex::new(TokenBucket::new(Instant::now())));
+
+fn kbps_to_bytes_per_sec(kbps: u64) -> u64 {
+    kbps.saturating_mul(1000) / 8
+}
+
+/// Token bucket with a one-second burst.  Tokens may go negative: the
+/// caller sleeps for the returned debt, so chunk size doesn't matter.
+pub(crate) struct TokenBucket {
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(now: Instant) -> Self {
+        Self {
+            tokens: 0.0,
+            last: now,
+        }
+    }
+
+    /// Take `bytes` at `rate` bytes/s and return how long to wait.
+    pub(crate) fn take(&mut self, bytes: usize, rate: u64, now: Instant) -> Duration {
+        let rate = rate as f64;
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * rate).min(rate) - bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / rate)
+        }
+    }
+}
+
+/// Await until `bytes` more bytes may be consumed under the global limit.
+/// Call once per received chunk.  The bucket lock is held while sleeping so
+/// concurrent downloads share the budget instead of each getting the full
+/// rate.
+pub(crate) async fn throttle_download(bytes: usize) {
+    let rate = DOWNLOAD_RATE_BPS.load(Ordering::Relaxed);
+    if rate == 0 || bytes == 0 {
+        return;
+    }
+    let mut bucket = DOWNLOAD_BUCKET.lock().await;
+    let wait = bucket.take(bytes, rate, Instant::now());
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// No bytes for this long aborts a download.
+const DOWNLOAD_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Percent done, `None` while the size is unknown.
+pub(crate) fn download_percent(done: u64, total: Option<u64>) -> Option<u8> {
+    total
+        .filter(|t| *t > 0)
+        .map(|t| (done.saturating_mul(100) / t).min(100) as u8)
+}
+
+/// Total size from a `Content-Range: bytes 100-199/1000` header.
+pub(crate) fn content_range_total(value: &str) -> Option<u64> {
+    value
+        .trim()
+        .strip_prefix("bytes ")?
+        .rsplit_once('/')?
+        .1
+        .parse()
+        .ok()
+}
+
+/// Stream `url` into `dest` under the global rate limit.  `on_progress`
+/// gets (bytes done, total if known) each time the percentage changes, or
+/// every MiB when the server sends no length.
+/// Returns the number of bytes written.
+pub(crate) async fn download_to_file(
+    url: &str,
+    dest: &std::path::Path,
+    on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<u64, String> {
+    download(url, dest, false, on_progress).await
+}
+
+/// [`download_to_file`] that continues a partial `dest` with a `Range`
+/// request; servers without range support restart from zero.  Returns the
+/// final size of `dest`.
+pub(crate) async fn download_resumable(
+    url: &str,
+    dest: &std::path::Path,
+    on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<u64, String> {
+    download(url, dest, true, on_progress).await
+}
+
+async fn download(
+    url: &str,
+    dest: &std::path::Path,
+    resume: bool,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<u64, String> {
+    use std::io::Write as _;
+
+    let existing = if resume {
+        fs::metadata(dest).map(|m| m.len()).unwrap_or(0)
+    } else {
+        0
+    };
+    let mut req = http_client().get(url);
+    if existing > 0 {
+        req = req.header(reqwest::header::RANGE, format!("bytes={existing}-"));
+    }
+    let resp = req
+        .send()
+        .await
+        .map_err(|e| format!("download {url} failed: {e}"))?;
+    // 416：本地部分文件已经完整（或比远端还大），交给调用方校验
+    if existing > 0 && resp.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+        return Ok(existing);
+    }
+    let mut resp = resp
+        .error_for_status()
+        .map_err(|e| format!("download {url} failed: {e}"))?;
+    let appending = existing > 0 && resp.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+    let (mut done, total) = if appending {
+        let total = resp
+            .headers()
+            .get(reqwest::header::CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(content_range_total);
+        (existing, total)
+    } else {
+        (0, resp.content_length())
+    };
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("create {}: {e}", parent.display()))?;
+    }
+    let mut file = if appending {
+        fs::OpenOptions::new().append(true).open(dest)
+    } else {
+        fs::File::create(dest)
+    }
+    .map_err(|e| format!("write {}: {e}", dest.display()))?;
+    let mut last_mark = None;
+    while let Some(chunk) = tokio::time::timeout(DOWNLOAD_STALL_TIMEOUT, resp.chunk())
+        .await
+        .map_err(|_| format!("download {url} stalled"))?
+        .map_err(|e| format!("download {url} failed: {e}"))?
+    {
+        throttle_download(chunk.len()).await;
+        file.write_all(&chunk)
+            .map_err(|e| format!("write {}: {e}", dest.display()))?;
+        done += chunk.len() as u64;
+        let mark = download_percent(done, total)
+            .map(u64::from)
+            .unwrap_or(done >> 20);
+        if last_mark != Some(mark) {
+            last_mark = Some(mark);
+            on_progress(done, total);
+        }
+    }
+    file.flush()
+        .map_err(|e| format!("write {}: {e}", dest.display()))?;
+    Ok(done)
+}
+
+#[tauri::command]
+pub fn get_download_rate_limit() -> u64 {
+    read_state_file().download_rate_limit_kbps.unwrap_or(0)
+}
+
+/// Set the global download limit in kilobits per second (0 = unlimited).
+/// Takes effect immediately for downloads already in progress.
+#[tauri::command]
+pub fn set_download_rate_limit(kbps: u64) -> CmdResult<()> {
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    state.download_rate_limit_kbps = (kbps > 0).then_some(kbps);
+    write_state_file(&state)?;
+    DOWNLOAD_RATE_BPS.store(kbps_to_bytes_per_sec(kbps), Ordering::Relaxed);
+    log_to_file(&format!("[net] download rate limit set to {kbps} kbps"));
+    Ok(())
+}
+
+// ── GitHub API ──
+
+const GITHUB_API_MAX_ATTEMPTS: u32 = 3;
+/// 单次退避的最长等待；超过该值直接放弃，交给镜像兜底。
+const GITHUB_API_MAX_WAIT_SECS: u64 = 30;
+const DEFAULT_RELEASE_REPO: &str = "openakita/openakita";
+/// 与 tauri.conf.json updater endpoints 同源的发布清单镜像。
+const RELEASE_MIRROR_MANIFEST_URL: &str = "https://dl-openakita.fzstack.com/api/release.json";
+
+/// Returns the GitHub token and where it came from (`"env"` | `"settings"`).
+pub(crate) fn github_token_with_source() -> Option<(String, &'static str)> {
+    for key in ["OPENAKITA_GITHUB_TOKEN", "GITHUB_TOKEN"] {
+        if let Ok(v) = std::env::var(key) {
+            if !v.trim().is_empty() {
+                return Some((v.trim().to_string(), "env"));
+            }
+        }
+    }
+    read_state_file()
+        .github_token
+        .filter(|t| !t.trim().is_empty())
+        .map(|t| (t.trim().to_string(), "settings"))
+}
+
+/// Decide how long to wait before retrying a GitHub API response.
+///
+/// Returns `None` when the response is not a rate-limit response.  For
+/// rate-limited responses returns the number of seconds to wait, preferring
+/// `Retry-After` (secondary limits) over `X-RateLimit-Reset` (primary limit).
+pub(crate) fn github_rate_limit_wait_secs(
+    status: u16,
+    remaining: Option<&str>,
+    reset: Option<&str>,
+    retry_after: Option<&str>,
+    now_secs: u64,
+    attempt: u32,
+) -> Option<u64> {
+    if status != 403 && status != 429 {
+        return None;
+    }
+    if let Some(secs) = retry_after.and_then(|v| v.trim().parse::<u64>().ok()) {
+        return Some(secs);
+    }
+    let exhausted = remaining.map(|v| v.trim() == "0").unwrap_or(false);
+    if exhausted {
+        if let Some(reset_at) = reset.and_then(|v| v.trim().parse::<u64>().ok()) {
+            return Some(reset_at.saturating_sub(now_secs).max(1));
+        }
+    }
+    if status == 429 || exhausted {
+        // 无明确等待时间：指数退避 2s / 4s / 8s
+        return Some(2u64 << attempt.min(4));
+    }
+    // 普通 403（权限不足 / token 无效）不是限流，不重试
+    None
+}
+
+/// GET a GitHub API URL as JSON with token + rate-limit aware retries.
+pub(crate) async fn github_get_json(url: &str) -> Result<serde_json::Value, String> {
+    let started = Instant::now();
+    let result = github_get_json_inner(url).await;
+    if trace::enabled() {
+        let body = result.as_ref().map(|v| v.to_string());
+        trace::record(
+            "http",
+            &format!("GET {url}"),
+            started,
+            None,
+            None,
+            body.as_deref().map_err(|e| e.as_str()),
+        );
+    }
+    result
+}
+
+async fn github_get_json_inner(url: &str) -> Result<serde_json::Value, String> {
+    ensure_online("GitHub API 请求")?;
+    let client = http_client();
+    let token = github_token_with_source().map(|(t, _)| t);
+
+    let mut last_err = String::new();
+    for attempt in 0..GITHUB_API_MAX_ATTEMPTS {
+        let mut req = client
+            .get(url)
+            .timeout(Duration::from_secs(15))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28");
+        if let Some(ref t) = token {
+            req = req.bearer_auth(t);
+        }
+        let resp = match req.send().await {
+            Ok(r) => r,
+            Err(e) => {
+                last_err = format!("GitHub API request failed ({url}): {e}");
+                tokio::time::sleep(Duration::from_secs(1 + attempt as u64)).await;
+                continue;
+            }
+        };
+        let status = resp.status().as_u16();
+        if resp.status().is_success() {
+            return resp
+                .json::<serde_json::Value>()
+                .await
+                .map_err(|e| format!("parse GitHub API JSON failed: {e}"));
+        }
+        let header = |name: &str| {
+            resp.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string())
+        };
+        let wait = github_rate_limit_wait_secs(
+            status,
+            header("x-ratelimit-remaining").as_deref(),
+            header("x-ratelimit-reset").as_deref(),
+            header("retry-after").as_deref(),
+            now_epoch_secs(),
+            attempt,
+        );
+        match wait {
+            Some(secs) if secs > GITHUB_API_MAX_WAIT_SECS => {
+                log_to_file(&format!(
+                    "[github] rate limited (status={status}, authenticated={}), reset in {secs}s; giving up",
+                    token.is_some()
+                ));
+                return Err(format!(
+                    "GITHUB_RATE_LIMITED|GitHub API 限流，约 {} 分钟后恢复。可在设置中配置 GitHub Token 提升额度",
+                    secs.div_ceil(60)
+                ));
+            }
+            Some(secs) => {
+                log_to_file(&format!(
+                    "[github] rate limited (status={status}), retry {}/{} in {secs}s",
+                    attempt + 1,
+                    GITHUB_API_MAX_ATTEMPTS
+                ));
+                last_err = format!(
+                    "GITHUB_RATE_LIMITED|GitHub API 限流（HTTP {status}），重试 {GITHUB_API_MAX_ATTEMPTS} 次后仍失败"
+                );
+                tokio::time::sleep(Duration::from_secs(secs)).await;
+            }
+            None => {
+                let body = resp.text().await.unwrap_or_default();
+                let snippet: String = body.chars().take(300).collect();
+                return Err(format!(
+                    "GitHub API returned HTTP {status} ({url}): {snippet}"
+                ));
+            }
+        }
+    }
+    Err(last_err)
+}
+
+async fn fetch_release_mirror_manifest() -> Result<serde_json::Value, String> {
+    ensure_online("发布清单请求")?;
+    http_client()
+        .get(RELEASE_MIRROR_MANIFEST_URL)
+        .timeout(Duration::from_secs(15))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("fetch release mirror manifest failed: {e}"))?
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| format!("parse release mirror manifest failed: {e}"))
+}
+
+/// Latest release metadata: GitHub API first, mirror manifest as fallback.
+/// Returns `{ source: "github" | "mirror", release, githubError? }`.
+pub(crate) async fn fetch_latest_release_json(
+    repo: &str,
+    refresh: bool,
+) -> Result<serde_json::Value, String> {
+    let cache_key = format!("release:{repo}");
+    if !refresh {
+        if let Some(body) = cache_get_fresh(&cache_key) {
+            if let Ok(v) = serde_json::from_str::<serde_json::Value>(&body) {
+                return Ok(v);
+            }
+        }
+    }
+    let url = format!("https://api.github.com/repos/{repo}/releases/latest");
+    let result = match github_get_json(&url).await {
+        Ok(release) => Ok(serde_json::json!({ "source": "github", "release": release })),
+        Err(gh_err) => {
+            log_to_file(&format!(
+                "[github] latest release lookup failed, falling back to mirror: {gh_err}"
+            ));
+            fetch_release_mirror_manifest()
+                .await
+                .map(|release| {
+                    serde_json::json!({
+                        "source": "mirror",
+                        "release": release,
+                        "githubError": gh_err,
+                    })
+                })
+                .map_err(|mirror_err| format!("{gh_err}\n镜像兜底也失败: {mirror_err}"))
+        }
+    };
+    match result {
+        Ok(v) => {
+            cache_put(&cache_key, &v.to_string(), RELEASE_CACHE_TTL_SECS);
+            Ok(v)
+        }
+        Err(e) => cache_get_stale(&cache_key)
+            .and_then(|body| serde_json::from_str::<serde_json::Value>(&body).ok())
+            .ok_or(e),
+    }
+}
+
+// ── HTTP response cache ──
+
+const RELEASE_CACHE_TTL_SECS: u64 = 10 * 60;
+/// 服务端未给出 max-age 时的默认新鲜期
+pub(crate) const DEFAULT_HTTP_CACHE_TTL_SECS: u64 = 30 * 60;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HttpCacheEntry {
+    key: String,
+    #[serde(default)]
+    etag: Option<String>,
+    #[serde(default)]
+    last_modified: Option<String>,
+    stored_at: u64,
+    max_age_secs: u64,
+    body: String,
+}
+
+pub(crate) fn http_cache_dir() -> PathBuf {
+    openakita_root_dir().join("cache").join("http")
+}
+
+/// FNV-1a 64-bit，仅用于把 URL / cache key 映射成稳定的文件名。
+pub(crate) fn cache_key_hash(key: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in key.as_bytes() {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{hash:016x}")
+}
+
+/// Parse `Cache-Control`. Returns `Some(0)` for `no-cache` / `no-store`,
+/// the `max-age` value when present, otherwise `None`.
+pub(crate) fn parse_cache_max_age(cache_control: Option<&str>) -> Option<u64> {
+    let cc = cache_control?;
+    let mut max_age = None;
+    for directive in cc.split(',') {
+        let d = directive.trim().to_ascii_lowercase();
+        if d == "no-cache" || d == "no-store" {
+            return Some(0);
+        }
+        if let Some(v) = d.strip_prefix("max-age=") {
+            max_age = v.trim_matches('"').parse::<u64>().ok();
+        }
+    }
+    max_age
+}
+
+/// `no-store` / `private` responses must not be written to the shared
+/// on-disk cache at all, validators or not.
+pub(crate) fn cache_control_allows_store(cache_control: Option<&str>) -> bool {
+    cache_control.is_none_or(|cc| {
+        cc.split(',').all(|directive| {
+            let d = directive.trim().to_ascii_lowercase();
+            d != "no-store" && d != "private" && !d.starts_with("private=")
+        })
+    })
+}
+
+fn cache_entry_path(key: &str) -> PathBuf {
+    http_cache_dir().join(format!("{}.json", cache_key_hash(key)))
+}
+
+fn read_cache_entry(key: &str) -> Option<HttpCacheEntry> {
+    let content = fs::read_to_string(cache_entry_path(key)).ok()?;
+    let entry = serde_json::from_str::<HttpCacheEntry>(&content).ok()?;
+    // hash 碰撞保护
+    (entry.key == key).then_some(entry)
+}
+
+fn write_cache_entry(entry: &HttpCacheEntry) {
+    let dir = http_cache_dir();
+    if fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+    if let Ok(data) = serde_json::to_vec(entry) {
+        let path = cache_entry_path(&entry.key);
+        let _ = atomic_write(&path, data);
+    }
+}
+
+fn remove_cache_entry(key: &str) {
+    let _ = fs::remove_file(cache_entry_path(key));
+}
+
+fn cache_entry_is_fresh(entry: &HttpCacheEntry, now: u64) -> bool {
+    now.saturating_sub(entry.stored_at) < entry.max_age_secs
+}
+
+pub(crate) fn cache_get_fresh(key: &str) -> Option<String> {
+    read_cache_entry(key)
+        .filter(|e| cache_entry_is_fresh(e, now_epoch_secs()))
+        .map(|e| e.body)
+}
+
+pub(crate) fn cache_get_stale(key: &str) -> Option<String> {
+    read_cache_entry(key).map(|e| e.body)
+}
+
+pub(crate) fn cache_put(key: &str, body: &str, max_age_secs: u64) {
+    write_cache_entry(&HttpCacheEntry {
+        key: key.to_string(),
+        etag: None,
+        last_modified: None,
+        stored_at: now_epoch_secs(),
+        max_age_secs,
+        body: body.to_string(),
+    });
+}
+
+/// GET `url` as text through the on-disk cache (see module docs).
+pub(crate) async fn cached_get_text(
+    client: &reqwest::Client,
+    url: &str,
+    timeout: Duration,
+    refresh: bool,
+) -> Result<String, String> {
+    let started = Instant::now();
+    let result = cached_get_text_inner(client, url, timeout, refresh).await;
+    trace::record(
+        "http",
+        &format!("GET {url}"),
+        started,
+        None,
+        None,
+        result.as_deref().map_err(String::as_str),
+    );
+    result
+}
+
+async fn cached_get_text_inner(
+    client: &reqwest::Client,
+    url: &str,
+    timeout: Duration,
+    refresh: bool,
+) -> Result<String, String> {
+    let cached = read_cache_entry(url);
+    if let Some(ref entry) = cached {
+        if !refresh && cache_entry_is_fresh(entry, now_epoch_secs()) {
+            return Ok(entry.body.clone());
+        }
+    }
+    if let Err(e) = ensure_online(&format!("请求 {url}")) {
+        return cached.map(|entry| entry.body).ok_or(e);
+    }
+
+    let mut req = client.get(url).timeout(timeout);
+    if let Some(ref entry) = cached {
+        if let Some(ref etag) = entry.etag {
+            req = req.header("If-None-Match", etag);
+        }
+        if let Some(ref lm) = entry.last_modified {
+            req = req.header("If-Modified-Since", lm);
+        }
+    }
+    let resp = match req.send().await {
+        Ok(r) => r,
+        Err(e) => {
+            if let Some(entry) = cached {
+                log_to_file(&format!(
+                    "[http-cache] network error, serving stale entry for {url}: {e}"
+                ));
+                return Ok(entry.body);
+            }
+            return Err(format!("HTTP GET failed ({url}): {e}"));
+        }
+    };
+
+    let header = |name: &str| {
+        resp.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string())
+    };
+    let cache_control = header("cache-control");
+    let storable = cache_control_allows_store(cache_control.as_deref());
+    let max_age =
+        parse_cache_max_age(cache_control.as_deref()).unwrap_or(DEFAULT_HTTP_CACHE_TTL_SECS);
+    let etag = header("etag");
+    let last_modified = header("last-modified");
+
+    if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+        if let Some(mut entry) = cached {
+            if storable {
+                entry.stored_at = now_epoch_secs();
+                entry.max_age_secs = max_age;
+                write_cache_entry(&entry);
+            } else {
+                remove_cache_entry(url);
+            }
+            return Ok(entry.body);
+        }
+    }
+    let status = resp.status();
+    if !status.is_success() {
+        if status.is_server_error() {
+            if let Some(entry) = cached {
+                log_to_file(&format!(
+                    "[http-cache] HTTP {status}, serving stale entry for {url}"
+                ));
+                return Ok(entry.body);
+            }
+        }
+        return Err(format!("HTTP GET failed ({url}): {status}"));
+    }
+    let body = resp
+        .text()
+        .await
+        .map_err(|e| format!("read response body failed: {e}"))?;
+    if !storable {
+        if cached.is_some() {
+            remove_cache_entry(url);
+        }
+    } else if max_age > 0 || etag.is_some() || last_modified.is_some() {
+        write_cache_entry(&HttpCacheEntry {
+            key: url.to_string(),
+            etag,
+            last_modified,
+            stored_at: now_epoch_secs(),
+            max_age_secs: max_age,
+            body: body.clone(),
+        });
+    }
+    Ok(body)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClearHttpCacheResult {
+    removed_files: u64,
+    freed_bytes: u64,
+}
+
+/// 清空 `~/.openakita/cache/http/`。
+#[tauri::command]
+pub fn clear_http_cache() -> CmdResult<ClearHttpCacheResult> {
+    let dir = http_cache_dir();
+    let mut removed_files = 0u64;
+    let mut freed_bytes = 0u64;
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(ClearHttpCacheResult {
+            removed_files,
+            freed_bytes,
+        });
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        if fs::remove_file(&path).is_ok() {
+            removed_files += 1;
+            freed_bytes += size;
+        }
+    }
+    log_to_file(&format!(
+        "[http-cache] cleared {removed_files} entries ({freed_bytes} bytes)"
+    ));
+    Ok(ClearHttpCacheResult {
+        removed_files,
+        freed_bytes,
+    })
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GithubTokenStatus {
+    configured: bool,
+    /// "env" | "settings"；未配置时为 None
+    source: Option<String>,
+}
+
+#[tauri::command]
+pub fn get_github_token_status() -> GithubTokenStatus {
+    match github_token_with_source() {
+        Some((_, source)) => GithubTokenStatus {
+            configured: true,
+            source: Some(source.to_string()),
+        },
+        None => GithubTokenStatus {
+            configured: false,
+            source: None,
+        },
+    }
+}
+
+/// 保存（或清除，传 None / 空串）GitHub Token。只写 state.json，不回显给前端。
+///
+/// 与私有技能源的 token（[`crate::skill_registry`]，存在系统 keyring）不同，
+/// 这里有意保持明文：keyring 只能经 Python bridge 访问，而这个 token 在还没有
+/// venv 的首次安装阶段就要用（Python 构建包、发布信息都走 GitHub API）。
+/// 它只是访问公开数据时的限流凭据，建议用不带任何权限的 fine-grained token；
+/// 导出设置（`app_settings`）时不会带出。
+#[tauri::command]
+pub fn set_github_token(token: Option<String>) -> CmdResult<()> {
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    state.github_token = token
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty());
+    write_state_file(&state).map_err(Into::into)
+}
+
+#[tauri::command]
+pub async fn fetch_latest_release(
+    repo: Option<String>,
+    refresh: Option<bool>,
+) -> CmdResult<serde_json::Value> {
+    let repo = repo.unwrap_or_else(|| DEFAULT_RELEASE_REPO.to_string());
+    if repo.split('/').count() != 2 || repo.contains("..") {
+        return Err(format!("invalid repo: {repo}").into());
+    }
+    fetch_latest_release_json(&repo, refresh.unwrap_or(false))
+        .await
+        .map_err(Into::into)
+}
@@ -0,0 +1,213 @@
+//! Native system notifications ("backend stopped" and friends).
+//!
+//! One entry point, [`send`], picks the mechanism for the platform:
+//!
+//! * Windows — a toast through PowerShell / WinRT, under the installer's
+//!   AUMID so it's attributed to OpenAkita Desktop.
+//! * macOS — `osascript -e 'display notification ...'`.
+//! * Linux — `org.freedesktop.Notifications` over the session D-Bus (through
+//!   `tauri-plugin-notification`), falling back to `notify-send` when there
+//!   is no session bus or the D-Bus call fails.
+//!
+//! Linux desktops vary (no notification daemon on a bare WM, no session bus
+//! under some sandboxes), so the mechanism is detected once and exposed via
+//! `get_notification_capability()`; with neither available notifications
+//! are logged and dropped.  Sending is always fire-and-forget and never
+//! blocks the caller.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::log_to_file;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)] // 每个平台只构造其中几种
+pub enum NotificationBackend {
+    WindowsToast,
+    Osascript,
+    Dbus,
+    NotifySend,
+    None,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationCapability {
+    pub backend: NotificationBackend,
+    /// Used when `backend` fails at send time (Linux: D-Bus → notify-send).
+    pub fallback: Option<NotificationBackend>,
+}
+
+/// Pick the Linux mechanism from what's present.
+pub(crate) fn linux_capability(session_bus: bool, notify_send: bool) -> NotificationCapability {
+    let notify_send = notify_send.then_some(NotificationBackend::NotifySend);
+    if session_bus {
+        NotificationCapability {
+            backend: NotificationBackend::Dbus,
+            fallback: notify_send,
+        }
+    } else {
+        NotificationCapability {
+            backend: notify_send.unwrap_or(NotificationBackend::None),
+            fallback: None,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn has_session_bus() -> bool {
+    if std::env::var_os("DBUS_SESSION_BUS_ADDRESS").is_some_and(|v| !v.is_empty()) {
+        return true;
+    }
+    // systemd 用户会话的默认总线地址
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(|d| std::path::Path::new(&d).join("bus").exists())
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "linux")]
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|p| std::env::split_paths(&p).any(|d| d.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+static CAPABILITY: Lazy<NotificationCapability> = Lazy::new(|| {
+    #[cfg(windows)]
+    let cap = NotificationCapability {
+        backend: NotificationBackend::WindowsToast,
+        fallback: None,
+    };
+    #[cfg(target_os = "macos")]
+    let cap = NotificationCapability {
+        backend: NotificationBackend::Osascript,
+        fallback: None,
+    };
+    #[cfg(target_os = "linux")]
+    let cap = linux_capability(has_session_bus(), on_path("notify-send"));
+    #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+    let cap = NotificationCapability {
+        backend: NotificationBackend::None,
+        fallback: None,
+    };
+    log_to_file(&format!("[notify] capability: {cap:?}"));
+    cap
+});
+
+#[cfg(windows)]
+fn send_windows_toast(title: &str, body: &str) -> Result<(), String> {
+    // AUMID 必须与 NSIS 安装器在开始菜单快捷方式上设置的一致（即 tauri.conf.json 的
+    // identifier），否则 Windows 无法关联到已注册的应用，导致通知内容为空。
+    // 同时在注册表注册 AUMID 以确保通知正常显示。
+    let ps_quote = |s: &str| s.replace('\'', "''");
+    let script = format!(
+        "try {{ \
+            $aumid = 'com.openakita.setupcenter'; \
+            $rp = \"HKCU:\\SOFTWARE\\Classes\\AppUserModelId\\$aumid\"; \
+            if (!(Test-Path $rp)) {{ New-Item $rp -Force | Out-Null; Set-ItemProperty $rp -Name DisplayName -Value 'OpenAkita Desktop' }}; \
+            [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] | Out-Null; \
+            $xml = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+            $t = $xml.GetElementsByTagName('text'); \
+            $t[0].AppendChild($xml.CreateTextNode('{}')) | Out-Null; \
+            $t[1].AppendChild($xml.CreateTextNode('{}')) | Out-Null; \
+            $n = [Windows.UI.Notifications.ToastNotification]::new($xml); \
+            [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier($aumid).Show($n) \
+        }} catch {{}}",
+        ps_quote(title),
+        ps_quote(body)
+    );
+    let mut cmd = std::process::Command::new("powershell");
+    cmd.args(["-NoProfile", "-NonInteractive", "-Command", &script]);
+    crate::apply_no_window(&mut cmd);
+    cmd.spawn().map(|_| ()).map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn send_osascript(title: &str, body: &str) -> Result<(), String> {
+    let quote = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    std::process::Command::new("osascript")
+        .args([
+            "-e",
+            &format!(
+                "display notification \"{}\" with title \"{}\"",
+                quote(body),
+                quote(title)
+            ),
+        ])
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn send_dbus(app: &AppHandle, title: &str, body: &str) -> Result<(), String> {
+    use tauri_plugin_notification::NotificationExt;
+    app.notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show()
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn send_notify_send(title: &str, body: &str) -> Result<(), String> {
+    std::process::Command::new("notify-send")
+        .args(["--app-name=OpenAkita", title, body])
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+fn send_with(
+    backend: NotificationBackend,
+    app: &AppHandle,
+    title: &str,
+    body: &str,
+) -> Result<(), String> {
+    match backend {
+        #[cfg(windows)]
+        NotificationBackend::WindowsToast => send_windows_toast(title, body),
+        #[cfg(target_os = "macos")]
+        NotificationBackend::Osascript => send_osascript(title, body),
+        #[cfg(target_os = "linux")]
+        NotificationBackend::Dbus => send_dbus(app, title, body),
+        #[cfg(target_os = "linux")]
+        NotificationBackend::NotifySend => send_notify_send(title, body),
+        _ => Err(format!("{backend:?} not available on this platform")),
+    }
+}
+
+/// Show a system notification in the background.  Failures are logged.
+pub(crate) fn send(app: &AppHandle, title: &str, body: &str) {
+    let cap = CAPABILITY.clone();
+    if cap.backend == NotificationBackend::None {
+        log_to_file(&format!(
+            "[notify] no notification backend, dropped: {title}"
+        ));
+        return;
+    }
+    let (app, title, body) = (app.clone(), title.to_string(), body.to_string());
+    // D-Bus 调用是同步的，不在调用方（常是主线程上的命令）里等
+    std::thread::spawn(move || {
+        let Err(e) = send_with(cap.backend, &app, &title, &body) else {
+            return;
+        };
+        let retried = cap
+            .fallback
+            .map(|fb| send_with(fb, &app, &title, &body).map(|_| fb));
+        log_to_file(&format!(
+            "[notify] {:?} failed: {e}; fallback: {retried:?}",
+            cap.backend
+        ));
+    });
+}
+
+/// Which notification mechanism this machine supports.
+#[tauri::command]
+pub fn get_notification_capability() -> NotificationCapability {
+    CAPABILITY.clone()
+}
@@ -0,0 +1,375 @@
+//! Browser sign-in for LLM providers (OAuth 2.0 authorization code + PKCE).
+//!
+//! `begin_provider_oauth(provider, workspace_id)` binds a short-lived
+//! listener on `127.0.0.1:<random port>`, opens the provider's consent page
+//! in the default browser with that address as the redirect URI, waits for
+//! the redirect (5 minutes at most), checks `state`, exchanges the code for
+//! a key and writes it to the workspace `.env` — the same place the
+//! provider forms store pasted keys — under the provider's usual env var.
+//!
+//! Built-in providers:
+//!
+//! * `openrouter` — OpenRouter's PKCE flow returns a regular API key and
+//!   needs no client registration.
+//! * `github-models` — standard GitHub OAuth app flow; the access token is
+//!   what GitHub Models accepts.  Requires a client id/secret in
+//!   `OPENAKITA_OAUTH_GITHUB_CLIENT_ID` / `..._CLIENT_SECRET`.
+//!
+//! Progress is reported through `provider-oauth` events
+//! (`waiting` / `exchanging` / `done` / `error`).
+
+use base64::Engine as _;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::errors::CmdResult;
+use crate::{
+    atomic_write, emit_if_ui_live, ensure_workspace_scaffold, log_to_file, net, open_external_url,
+    read_text_lossy, update_env_content, workspace_dir, EnvEntry,
+};
+
+const OAUTH_CALLBACK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const OAUTH_CALLBACK_PATH: &str = "/callback";
+
+enum KeyExchange {
+    /// `POST /api/v1/auth/keys {code, code_verifier}` → `{ key }`.
+    OpenRouter,
+    /// RFC 6749 token endpoint → `{ access_token }`.
+    OAuth2Token,
+}
+
+impl KeyExchange {
+    /// OpenRouter's key flow does not echo `state`; RFC 6749 providers must,
+    /// and a callback without it cannot be tied to our request.
+    fn requires_state(&self) -> bool {
+        matches!(self, KeyExchange::OAuth2Token)
+    }
+}
+
+struct OAuthProviderSpec {
+    id: &'static str,
+    authorize_url: &'static str,
+    token_url: &'static str,
+    scope: Option<&'static str>,
+    /// Env var prefix for client credentials; `None` when not needed.
+    client_env: Option<&'static str>,
+    env_key: &'static str,
+    exchange: KeyExchange,
+}
+
+const OAUTH_PROVIDERS: &[OAuthProviderSpec] = &[
+    OAuthProviderSpec {
+        id: "openrouter",
+        authorize_url: "https://openrouter.ai/auth",
+        token_url: "https://openrouter.ai/api/v1/auth/keys",
+        scope: None,
+        client_env: None,
+        env_key: "OPENROUTER_API_KEY",
+        exchange: KeyExchange::OpenRouter,
+    },
+    OAuthProviderSpec {
+        id: "github-models",
+        authorize_url: "https://github.com/login/oauth/authorize",
+        token_url: "https://github.com/login/oauth/access_token",
+        scope: Some("read:user"),
+        client_env: Some("OPENAKITA_OAUTH_GITHUB"),
+        env_key: "GITHUB_MODELS_API_KEY",
+        exchange: KeyExchange::OAuth2Token,
+    },
+];
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderOAuthResult {
+    provider: String,
+    env_key: String,
+    /// `sk-or-…abcd` style preview, never the full key.
+    key_preview: String,
+}
+
+fn random_urlsafe(len: usize) -> Result<String, String> {
+    let mut buf = vec![0u8; len];
+    getrandom::fill(&mut buf).map_err(|e| format!("random source unavailable: {e}"))?;
+    Ok(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(buf))
+}
+
+/// PKCE S256 challenge for `verifier` (RFC 7636 §4.2).
+pub(crate) fn pkce_challenge(verifier: &str) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+fn key_preview(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() <= 10 {
+        return "***".into();
+    }
+    let head: String = chars[..6].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{head}…{tail}")
+}
+
+/// Extract `code` from a redirect request line after checking `state`.
+/// Returns the provider's `error` parameter as an error when present.
+/// A missing `state` is only tolerated for providers whose flow does not
+/// echo it (OpenRouter).
+pub(crate) fn parse_oauth_callback(
+    request_line: &str,
+    expected_state: &str,
+    provider: &str,
+) -> Result<String, String> {
+    let require_state = OAUTH_PROVIDERS
+        .iter()
+        .find(|p| p.id == provider)
+        .is_none_or(|p| p.exchange.requires_state());
+    let target = request_line
+        .split_whitespace()
+        .nth(1)
+        .ok_or("malformed callback request")?;
+    let url = reqwest::Url::parse(&format!("http://localhost{target}"))
+        .map_err(|e| format!("malformed callback URL: {e}"))?;
+    if url.path() != OAUTH_CALLBACK_PATH {
+        return Err(format!("unexpected callback path: {}", url.path()));
+    }
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    };
+    if let Some(err) = param("error") {
+        let desc = param("error_description").unwrap_or_default();
+        return Err(format!("OAUTH_DENIED|{err} {desc}").trim().to_string());
+    }
+    match param("state") {
+        Some(state) if state != expected_state => {
+            return Err("OAUTH_STATE_MISMATCH|callback state does not match".into());
+        }
+        None if require_state => {
+            return Err("OAUTH_STATE_MISMATCH|callback is missing `state`".into());
+        }
+        _ => {}
+    }
+    param("code").ok_or_else(|| "callback is missing `code`".to_string())
+}
+
+async fn wait_for_callback(
+    listener: tokio::net::TcpListener,
+    expected_state: &str,
+    provider: &str,
+) -> Result<String, String> {
+    let accept_loop = async {
+        loop {
+            let (mut stream, _) = listener
+                .accept()
+                .await
+                .map_err(|e| format!("accept failed: {e}"))?;
+            let mut buf = vec![0u8; 8192];
+            let n = stream.read(&mut buf).await.unwrap_or(0);
+            let head = String::from_utf8_lossy(&buf[..n]);
+            let request_line = head.lines().next().unwrap_or_default();
+            // 浏览器会顺带请求 /favicon.ico 等，忽略非回调路径继续等待
+            if !request_line.contains(OAUTH_CALLBACK_PATH) {
+                let _ = stream
+                    .write_all(
+                        b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    )
+                    .await;
+                continue;
+            }
+            let result = parse_oauth_callback(request_line, expected_state, provider);
+            let body = if result.is_ok() {
+                "<h3>OpenAkita: 授权完成，可以关闭此页面。</h3>"
+            } else {
+                "<h3>OpenAkita: 授权失败，请回到 Setup Center 查看原因。</h3>"
+            };
+            let resp = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = stream.write_all(resp.as_bytes()).await;
+            return result;
+        }
+    };
+    tokio::time::timeout(OAUTH_CALLBACK_TIMEOUT, accept_loop)
+        .await
+        .map_err(|_| "OAUTH_TIMEOUT|等待浏览器授权超时（5 分钟）".to_string())?
+}
+
+async fn exchange_code(
+    spec: &OAuthProviderSpec,
+    code: &str,
+    verifier: &str,
+    redirect_uri: &str,
+    client: Option<&(String, String)>,
+) -> Result<String, String> {
+    let req = match spec.exchange {
+        KeyExchange::OpenRouter => {
+            net::http_client()
+                .post(spec.token_url)
+                .json(&serde_json::json!({
+                    "code": code,
+                    "code_verifier": verifier,
+                    "code_challenge_method": "S256",
+                }))
+        }
+        KeyExchange::OAuth2Token => {
+            let (id, secret) = client.ok_or("missing client credentials")?;
+            net::http_client()
+                .post(spec.token_url)
+                .header("Accept", "application/json")
+                .form(&[
+                    ("grant_type", "authorization_code"),
+                    ("code", code),
+                    ("redirect_uri", redirect_uri),
+                    ("client_id", id.as_str()),
+                    ("client_secret", secret.as_str()),
+                    ("code_verifier", verifier),
+                ])
+        }
+    };
+    let resp = req
+        .timeout(Duration::from_secs(30))
+        .send()
+        .await
+        .map_err(|e| format!("token exchange failed: {e}"))?;
+    let status = resp.status();
+    let body: serde_json::Value = resp
+        .json()
+        .await
+        .map_err(|e| format!("parse token response failed: {e}"))?;
+    if !status.is_success() || body.get("error").is_some() {
+        return Err(format!("token exchange failed ({status}): {body}"));
+    }
+    let field = match spec.exchange {
+        KeyExchange::OpenRouter => "key",
+        KeyExchange::OAuth2Token => "access_token",
+    };
+    body.get(field)
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .ok_or_else(|| format!("token response missing `{field}`"))
+}
+
+fn emit_oauth(app: &tauri::AppHandle, provider: &str, status: &str, detail: Option<&str>) {
+    emit_if_ui_live(
+        app,
+        "provider-oauth",
+        serde_json::json!({ "provider": provider, "status": status, "detail": detail }),
+    );
+}
+
+/// 浏览器授权登录供应商，成功后把 key 写入工作区 `.env`。
+#[tauri::command]
+pub async fn begin_provider_oauth(
+    app: tauri::AppHandle,
+    provider: String,
+    workspace_id: String,
+) -> CmdResult<ProviderOAuthResult> {
+    let spec = OAUTH_PROVIDERS
+        .iter()
+        .find(|p| p.id == provider)
+        .ok_or_else(|| {
+            let ids: Vec<&str> = OAUTH_PROVIDERS.iter().map(|p| p.id).collect();
+            format!(
+                "OAUTH_UNSUPPORTED|{provider} 不支持浏览器授权（支持: {}）",
+                ids.join(", ")
+            )
+        })?;
+    let client = match spec.client_env {
+        Some(prefix) => {
+            let id = std::env::var(format!("{prefix}_CLIENT_ID")).unwrap_or_default();
+            let secret = std::env::var(format!("{prefix}_CLIENT_SECRET")).unwrap_or_default();
+            if id.trim().is_empty() || secret.trim().is_empty() {
+                return Err(format!(
+                    "OAUTH_NOT_CONFIGURED|{provider} 需要配置 {prefix}_CLIENT_ID / {prefix}_CLIENT_SECRET"
+                ).into());
+            }
+            Some((id.trim().to_string(), secret.trim().to_string()))
+        }
+        None => None,
+    };
+    net::ensure_online("供应商授权登录")?;
+
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+        .await
+        .map_err(|e| format!("bind callback listener failed: {e}"))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("callback listener address: {e}"))?
+        .port();
+    let redirect_uri = format!("http://127.0.0.1:{port}{OAUTH_CALLBACK_PATH}");
+    let verifier = random_urlsafe(48)?;
+    let state = random_urlsafe(16)?;
+
+    let mut auth_url =
+        reqwest::Url::parse(spec.authorize_url).map_err(|e| format!("bad authorize URL: {e}"))?;
+    {
+        let mut q = auth_url.query_pairs_mut();
+        match spec.exchange {
+            KeyExchange::OpenRouter => {
+                q.append_pair("callback_url", &redirect_uri);
+            }
+            KeyExchange::OAuth2Token => {
+                q.append_pair("response_type", "code");
+                q.append_pair("redirect_uri", &redirect_uri);
+                if let Some((id, _)) = client.as_ref() {
+                    q.append_pair("client_id", id);
+                }
+                if let Some(scope) = spec.scope {
+                    q.append_pair("scope", scope);
+                }
+            }
+        }
+        q.append_pair("code_challenge", &pkce_challenge(&verifier));
+        q.append_pair("code_challenge_method", "S256");
+        q.append_pair("state", &state);
+    }
+
+    log_to_file(&format!(
+        "[oauth] begin provider={provider} callback_port={port}"
+    ));
+    open_external_url(auth_url.to_string())?;
+    emit_oauth(&app, &provider, "waiting", None);
+
+    let result = async {
+        let code = wait_for_callback(listener, &state, spec.id).await?;
+        emit_oauth(&app, &provider, "exchanging", None);
+        let key = exchange_code(spec, &code, &verifier, &redirect_uri, client.as_ref()).await?;
+
+        let dir = workspace_dir(&workspace_id);
+        ensure_workspace_scaffold(&dir)?;
+        let env_path = dir.join(".env");
+        let updated = update_env_content(
+            &read_text_lossy(&env_path),
+            &[EnvEntry {
+                key: spec.env_key.to_string(),
+                value: key.clone(),
+            }],
+        );
+        atomic_write(&env_path, updated).map_err(|e| format!("write .env failed: {e}"))?;
+        Ok::<_, String>(key)
+    }
+    .await;
+
+    match result {
+        Ok(key) => {
+            log_to_file(&format!(
+                "[oauth] provider={provider} stored {}",
+                spec.env_key
+            ));
+            emit_oauth(&app, &provider, "done", None);
+            Ok(ProviderOAuthResult {
+                provider,
+                env_key: spec.env_key.to_string(),
+                key_preview: key_preview(&key),
+            })
+        }
+        Err(e) => {
+            log_to_file(&format!("[oauth] provider={provider} failed: {e}"));
+            emit_oauth(&app, &provider, "error", Some(&e));
+            Err(e.into())
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! Read-only observer workspaces.
+//!
+//! An observer workspace has no files and no process of its own: it stores
+//! the host, port and (optional) bearer token of a backend started
+//! elsewhere — a server, another user's machine — and the desktop only
+//! watches it.  Requests that go through [`backend_ipc::workspace_request`]
+//! (status, service log, sessions, `call_backend`) are sent to that host;
+//! anything but `GET` / `HEAD` is refused there, and the commands that
+//! start, stop or reconfigure a workspace refuse observers up front with
+//! `OBSERVER_READ_ONLY`.
+//!
+//! The token lives in `state.json` beside the workspace, like the GitHub
+//! token, and is never sent back to the frontend (`hasToken` only).
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::errors::CmdResult;
+use crate::{
+    backend_ipc, build_service_status, normalize_workspace_id, read_state_file,
+    spawn_blocking_result, unique_workspace_id, write_state_file, ServiceLogChunk, ServiceStatus,
+    WorkspaceMeta, WorkspaceSummary, STATE_FILE_LOCK,
+};
+
+const READ_METHODS: &[&str] = &["GET", "HEAD"];
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(3);
+const LOG_TIMEOUT: Duration = Duration::from_secs(10);
+/// `managed_by` of an observer's [`ServiceStatus`].
+pub(crate) const MANAGED_BY: &str = "observer";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObserverTarget {
+    /// Host name or IP address, without scheme, port or brackets.
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub tls: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl ObserverTarget {
+    pub(crate) fn base_url(&self) -> String {
+        let scheme = if self.tls { "https" } else { "http" };
+        if self.host.contains(':') {
+            format!("{scheme}://[{}]:{}", self.host, self.port)
+        } else {
+            format!("{scheme}://{}:{}", self.host, self.port)
+        }
+    }
+
+    /// Headers for a request to the observed backend; refuses anything
+    /// that could change it.
+    pub(crate) fn request_headers(
+        &self,
+        workspace_id: &str,
+        method: &str,
+        headers: &[(String, String)],
+    ) -> Result<Vec<(String, String)>, String> {
+        let method = method.to_ascii_uppercase();
+        if !READ_METHODS.contains(&method.as_str()) {
+            return Err(read_only_error(
+                workspace_id,
+                &format!("发送 {method} 请求"),
+            ));
+        }
+        let mut out = headers.to_vec();
+        let has_auth = headers
+            .iter()
+            .any(|(k, _)| k.eq_ignore_ascii_case("authorization"));
+        if let (Some(token), false) = (self.token.as_deref(), has_auth) {
+            out.push(("Authorization".to_string(), format!("Bearer {token}")));
+        }
+        Ok(out)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObserverInfo {
+    pub workspace_id: String,
+    pub host: String,
+    pub port: u16,
+    pub tls: bool,
+    pub has_token: bool,
+    pub reachable: bool,
+    /// openakita version from the backend's `/api/health`.
+    pub version: Option<String>,
+}
+
+fn read_only_error(workspace_id: &str, what: &str) -> String {
+    format!("OBSERVER_READ_ONLY|工作区 {workspace_id} 是只读观察者，不能{what}")
+}
+
+/// Host as stored: trimmed, brackets removed; no scheme, path or port.
+pub(crate) fn normalize_host(host: &str) -> Result<String, String> {
+    let host = host.trim();
+    let host = host
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host);
+    let valid = !host.is_empty()
+        && host.len() <= 253
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | ':'))
+        // 单个冒号说明带了端口（IPv6 至少两个冒号）
+        && host.matches(':').count() != 1;
+    if valid {
+        Ok(host.to_string())
+    } else {
+        Err(format!(
+            "INVALID_ARGUMENT|无效的主机名: {host}（只填主机名或 IP，端口单独填写）"
+        ))
+    }
+}
+
+pub(crate) fn target_of(workspace_id: &str) -> Option<ObserverTarget> {
+    read_state_file()
+        .workspaces
+        .into_iter()
+        .find(|w| w.id == workspace_id)
+        .and_then(|w| w.observer)
+}
+
+/// Refuse to change an observer workspace.
+pub(crate) fn ensure_writable(workspace_id: &str) -> Result<(), String> {
+    match target_of(workspace_id) {
+        Some(_) => Err(read_only_error(workspace_id, "启动、停止或修改配置")),
+        None => Ok(()),
+    }
+}
+
+fn probe(workspace_id: &str) -> Option<Value> {
+    let resp = backend_ipc::workspace_request_blocking(
+        workspace_id,
+        "GET",
+        "/api/health",
+        &[],
+        None,
+        HEALTH_TIMEOUT,
+    )
+    .ok()?;
+    resp.is_success().then(|| resp.json().ok()).flatten()
+}
+
+/// Status of the observed backend: running means `/api/health` answered.
+pub(crate) fn status(workspace_id: &str, target: &ObserverTarget) -> ServiceStatus {
+    let healthy = probe(workspace_id).is_some();
+    let mut status = build_service_status(
+        workspace_id,
+        healthy,
+        None,
+        target.base_url(),
+        MANAGED_BY,
+        false,
+    );
+    status.heartbeat_http_ready = healthy;
+    status
+}
+
+/// Service log tail through the observed backend's `/api/logs/service`.
+pub(crate) fn service_log(workspace_id: &str, tail: u64) -> Result<ServiceLogChunk, String> {
+    let path = format!("/api/logs/service?tail_bytes={tail}");
+    let resp =
+        backend_ipc::workspace_request_blocking(workspace_id, "GET", &path, &[], None, LOG_TIMEOUT)
+            .map_err(|e| format!("BACKEND_NOT_RUNNING|{e}"))?;
+    if !resp.is_success() {
+        return Err(format!("HTTP {} from {path}", resp.status));
+    }
+    serde_json::from_slice(&resp.body).map_err(|e| format!("parse service log failed: {e}"))
+}
+
+fn info(workspace_id: &str, target: &ObserverTarget) -> ObserverInfo {
+    let health = probe(workspace_id);
+    ObserverInfo {
+        workspace_id: workspace_id.to_string(),
+        host: target.host.clone(),
+        port: target.port,
+        tls: target.tls,
+        has_token: target.token.is_some(),
+        reachable: health.is_some(),
+        version: health
+            .as_ref()
+            .and_then(|h| h.get("version"))
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    }
+}
+
+fn validate_target(host: &str, port: u16) -> Result<String, String> {
+    if port == 0 {
+        return Err("INVALID_ARGUMENT|端口不能为 0".into());
+    }
+    normalize_host(host)
+}
+
+/// Add an observer workspace watching the backend at `host:port`.
+#[tauri::command]
+pub fn add_observer_workspace(
+    name: String,
+    host: String,
+    port: u16,
+    token: Option<String>,
+    tls: Option<bool>,
+) -> CmdResult<WorkspaceSummary> {
+    if name.trim().is_empty() {
+        return Err("INVALID_ARGUMENT|workspace name is empty".into());
+    }
+    let host = validate_target(&host, port)?;
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    let existing: Vec<String> = state.workspaces.iter().map(|w| w.id.clone()).collect();
+    let id = unique_workspace_id(&normalize_workspace_id(&name), &existing);
+    let target = ObserverTarget {
+        host,
+        port,
+        tls: tls.unwrap_or(false),
+        token: token
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty()),
+    };
+    state.workspaces.push(WorkspaceMeta {
+        id: id.clone(),
+        name: name.clone(),
+        env_profile: None,
+        resource_limits: None,
+        ephemeral: false,
+        observer: Some(target.clone()),
+        dir_overrides: None,
+        shutdown_timeout_secs: None,
+    });
+    write_state_file(&state)?;
+    Ok(WorkspaceSummary {
+        id: id.clone(),
+        name,
+        path: target.base_url(),
+        is_current: state.current_workspace_id.as_deref() == Some(&id),
+        ephemeral: false,
+        observer: true,
+    })
+}
+
+/// Change where an observer workspace points.  `token`: omitted keeps the
+/// stored one, an empty string clears it.
+#[tauri::command]
+pub async fn set_observer_target(
+    workspace_id: String,
+    host: String,
+    port: u16,
+    token: Option<String>,
+    tls: Option<bool>,
+) -> CmdResult<ObserverInfo> {
+    let host = validate_target(&host, port)?;
+    let target = {
+        let _lock = STATE_FILE_LOCK
+            .lock()
+            .map_err(|e| format!("state lock failed: {e}"))?;
+        let mut state = read_state_file();
+        let current = state
+            .workspaces
+            .iter_mut()
+            .find(|w| w.id == workspace_id)
+            .and_then(|w| w.observer.as_mut())
+            .ok_or_else(|| format!("NOT_FOUND|{workspace_id} 不是观察者工作区"))?;
+        current.host = host;
+        current.port = port;
+        current.tls = tls.unwrap_or(current.tls);
+        if let Some(token) = token {
+            current.token = Some(token.trim().to_string()).filter(|t| !t.is_empty());
+        }
+        let target = current.clone();
+        write_state_file(&state)?;
+        target
+    };
+    spawn_blocking_result(move || Ok(info(&workspace_id, &target)))
+        .await
+        .map_err(Into::into)
+}
+
+/// Where an observer workspace points and whether it answers.
+#[tauri::command]
+pub async fn get_observer_info(workspace_id: String) -> CmdResult<ObserverInfo> {
+    let target = target_of(&workspace_id)
+        .ok_or_else(|| format!("NOT_FOUND|{workspace_id} 不是观察者工作区"))?;
+    spawn_blocking_result(move || Ok(info(&workspace_id, &target)))
+        .await
+        .map_err(Into::into)
+}
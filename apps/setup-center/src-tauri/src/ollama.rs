@@ -0,0 +1,702 @@
+//! Ollama integration: detection, lifecycle, models and endpoint setup.
+//!
+//! Setting up a local model used to mean installing Ollama, starting it,
+//! pulling a model in a terminal and then typing the right base URL into
+//! the LLM page.  This module does each step from the desktop:
+//!
+//! * [`detect_ollama`] reports the binary (PATH or the installer's default
+//!   location), whether the daemon answers and on which host/port — the
+//!   address follows `OLLAMA_HOST` like the Ollama CLI does;
+//! * [`start_ollama`] / [`stop_ollama`] run `ollama serve` in the
+//!   background (only a daemon we started is ever stopped), and with
+//!   `ollamaAutoStart` on, `service_start` brings it up first whenever the
+//!   workspace has an Ollama endpoint;
+//! * [`ollama_list_models`] / [`ollama_pull_model`] wrap `/api/tags` and
+//!   `/api/pull`; pull progress is emitted as `ollama-pull` events and the
+//!   `pullId` doubles as the `cancel_http_request` id;
+//! * [`ollama_write_endpoint`] adds (or refreshes) the matching entry in
+//!   the workspace's `data/llm_endpoints.json`, with capabilities and
+//!   context window taken from `/api/show`.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::errors::CmdResult;
+use crate::{
+    apply_no_window, atomic_write_with_backup, emit_if_ui_live, home_dir, log_to_file, net, now_ms,
+    observer, read_state_file, setup_logs_dir, spawn_blocking_result, workspace_dir,
+    write_state_file, STATE_FILE_LOCK,
+};
+
+const EVENT: &str = "ollama-pull";
+const DEFAULT_PORT: u16 = 11434;
+const START_TIMEOUT: Duration = Duration::from_secs(20);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+/// Ollama reports pull progress several times a second; silence this long
+/// means the pull is stuck.
+const PULL_STALL_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The `ollama serve` we spawned, if any.
+static SERVE_CHILD: Lazy<Mutex<Option<Child>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OllamaStatus {
+    pub installed: bool,
+    pub binary_path: Option<String>,
+    pub running: bool,
+    pub version: Option<String>,
+    pub host: String,
+    pub port: u16,
+    /// OpenAI-compatible base URL for `llm_endpoints.json`.
+    pub base_url: String,
+    /// The daemon was started by this desktop session.
+    pub started_by_app: bool,
+    pub auto_start: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OllamaModel {
+    pub name: String,
+    pub size_bytes: u64,
+    pub modified_at: Option<String>,
+    pub family: Option<String>,
+    pub parameter_size: Option<String>,
+    pub quantization: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PullLine {
+    pub status: String,
+    pub completed: Option<u64>,
+    pub total: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PullEvent<'a> {
+    pull_id: &'a str,
+    model: &'a str,
+    /// Ollama's own status line, or done / cancelled / error.
+    status: &'a str,
+    percent: Option<u8>,
+    completed: Option<u64>,
+    total: Option<u64>,
+    message: String,
+}
+
+/// Host and port from an `OLLAMA_HOST` value (`host`, `host:port`,
+/// `:port`, `http://host:port`).  Bind-all addresses map to loopback.
+pub(crate) fn parse_ollama_host(value: Option<&str>) -> (String, u16) {
+    let raw = value.unwrap_or("").trim();
+    let raw = raw
+        .strip_prefix("http://")
+        .or_else(|| raw.strip_prefix("https://"))
+        .unwrap_or(raw)
+        .trim_end_matches('/');
+    let (host, port) = match raw.rsplit_once(':') {
+        Some((h, p))
+            if !p.is_empty()
+                && p.chars().all(|c| c.is_ascii_digit())
+                && (!h.contains(':') || h.ends_with(']')) =>
+        {
+            (h, p.parse().unwrap_or(DEFAULT_PORT))
+        }
+        _ => (raw, DEFAULT_PORT),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let host = match host {
+        "" | "0.0.0.0" | "::" | "localhost" => "127.0.0.1",
+        h => h,
+    };
+    (host.to_string(), port)
+}
+
+pub(crate) fn host_port() -> (String, u16) {
+    parse_ollama_host(std::env::var("OLLAMA_HOST").ok().as_deref())
+}
+
+fn api_base(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("http://[{host}]:{port}")
+    } else {
+        format!("http://{host}:{port}")
+    }
+}
+
+fn api_url(path: &str) -> String {
+    let (host, port) = host_port();
+    format!("{}{path}", api_base(&host, port))
+}
+
+fn is_loopback(host: &str) -> bool {
+    host == "127.0.0.1" || host == "::1"
+}
+
+fn binary_name() -> &'static str {
+    if cfg!(windows) {
+        "ollama.exe"
+    } else {
+        "ollama"
+    }
+}
+
+/// `ollama` on PATH, then where the official installers put it.
+fn find_ollama_binary() -> Option<PathBuf> {
+    let exe = binary_name();
+    let mut dirs: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|p| std::env::split_paths(&p).collect())
+        .unwrap_or_default();
+    if cfg!(windows) {
+        if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+            dirs.push(PathBuf::from(local).join("Programs").join("Ollama"));
+        }
+    } else if cfg!(target_os = "macos") {
+        dirs.push(PathBuf::from("/Applications/Ollama.app/Contents/Resources"));
+        dirs.push(PathBuf::from("/opt/homebrew/bin"));
+        dirs.push(PathBuf::from("/usr/local/bin"));
+    } else {
+        dirs.push(PathBuf::from("/usr/local/bin"));
+        dirs.push(PathBuf::from("/usr/bin"));
+        if let Some(home) = home_dir() {
+            dirs.push(home.join(".local").join("bin"));
+        }
+    }
+    dirs.into_iter().map(|d| d.join(exe)).find(|p| p.is_file())
+}
+
+fn port_open(host: &str, port: u16) -> bool {
+    (host, port)
+        .to_socket_addrs()
+        .map(|mut addrs| addrs.any(|a| TcpStream::connect_timeout(&a, PROBE_TIMEOUT).is_ok()))
+        .unwrap_or(false)
+}
+
+fn child_alive() -> bool {
+    let Ok(mut guard) = SERVE_CHILD.lock() else {
+        return false;
+    };
+    match guard.as_mut().map(|c| c.try_wait()) {
+        Some(Ok(None)) => true,
+        Some(_) => {
+            *guard = None;
+            false
+        }
+        None => false,
+    }
+}
+
+pub(crate) fn auto_start_enabled() -> bool {
+    read_state_file().ollama_auto_start.unwrap_or(false)
+}
+
+async fn daemon_version() -> Option<String> {
+    let resp = net::local_http_client()
+        .get(api_url("/api/version"))
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?;
+    let body: Value = resp.json().await.ok()?;
+    body.get("version")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+async fn status() -> OllamaStatus {
+    let (host, port) = host_port();
+    let binary = find_ollama_binary();
+    let version = daemon_version().await;
+    OllamaStatus {
+        installed: binary.is_some(),
+        binary_path: binary.map(|p| p.to_string_lossy().to_string()),
+        running: version.is_some(),
+        version,
+        base_url: format!("{}/v1", api_base(&host, port)),
+        host,
+        port,
+        started_by_app: child_alive(),
+        auto_start: auto_start_enabled(),
+    }
+}
+
+/// Start `ollama serve` unless something already listens on the Ollama
+/// port.  Blocks until the port opens.  Returns whether it started one.
+pub(crate) fn ensure_running() -> Result<bool, String> {
+    let (host, port) = host_port();
+    if port_open(&host, port) {
+        return Ok(false);
+    }
+    if !is_loopback(&host) {
+        return Err(format!(
+            "OLLAMA_NOT_RUNNING|OLLAMA_HOST 指向远程地址 {host}:{port}，无法在本机启动"
+        ));
+    }
+    let bin = find_ollama_binary().ok_or_else(|| {
+        "NOT_FOUND|未找到 Ollama，请先从 https://ollama.com/download 安装".to_string()
+    })?;
+    let log_path = setup_logs_dir().join("ollama.log");
+    let _ = fs::create_dir_all(setup_logs_dir());
+    let log = fs::File::create(&log_path).map_err(|e| format!("create ollama log: {e}"))?;
+    let mut cmd = Command::new(&bin);
+    cmd.arg("serve")
+        .stdin(Stdio::null())
+        .stdout(Stdio::from(
+            log.try_clone()
+                .map_err(|e| format!("clone ollama log: {e}"))?,
+        ))
+        .stderr(Stdio::from(log));
+    apply_no_window(&mut cmd);
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("启动 {} serve 失败: {e}", bin.display()))?;
+    log_to_file(&format!(
+        "[ollama] spawned {} serve (pid={})",
+        bin.display(),
+        child.id()
+    ));
+    let started = Instant::now();
+    while started.elapsed() < START_TIMEOUT {
+        if port_open(&host, port) {
+            if let Ok(mut guard) = SERVE_CHILD.lock() {
+                *guard = Some(child);
+            }
+            return Ok(true);
+        }
+        if let Ok(Some(exit)) = child.try_wait() {
+            let tail = fs::read_to_string(&log_path).unwrap_or_default();
+            let tail: Vec<&str> = tail.lines().rev().take(5).collect();
+            let tail: Vec<&str> = tail.into_iter().rev().collect();
+            return Err(format!(
+                "OLLAMA_NOT_RUNNING|ollama serve 已退出 ({exit}): {}",
+                tail.join("\n")
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(250));
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+    Err(format!(
+        "OLLAMA_NOT_RUNNING|ollama serve 在 {}s 内没有监听 {host}:{port}，日志: {}",
+        START_TIMEOUT.as_secs(),
+        log_path.display()
+    ))
+}
+
+/// Whether an endpoint list in `llm_endpoints.json` points at Ollama.
+pub(crate) fn config_uses_ollama(config: &Value, port: u16) -> bool {
+    let port_suffix = format!(":{port}");
+    ["endpoints", "compiler_endpoints"]
+        .iter()
+        .filter_map(|k| config.get(*k).and_then(Value::as_array))
+        .flatten()
+        .any(|ep| {
+            ep.get("provider").and_then(Value::as_str) == Some("ollama")
+                || ep
+                    .get("base_url")
+                    .and_then(Value::as_str)
+                    .map(|u| u.trim_end_matches('/').trim_end_matches("/v1"))
+                    .is_some_and(|u| u.ends_with(&port_suffix))
+        })
+}
+
+/// `service_start` hook: with `ollamaAutoStart` on, bring Ollama up before
+/// a backend whose endpoints use it.  Failures are logged, not fatal —
+/// the backend can still fall back to other endpoints.
+pub(crate) fn auto_start_for_workspace(ws_dir: &Path) {
+    if !auto_start_enabled() {
+        return;
+    }
+    let config = fs::read_to_string(ws_dir.join("data").join("llm_endpoints.json"))
+        .ok()
+        .and_then(|s| serde_json::from_str::<Value>(&s).ok());
+    if !config.is_some_and(|c| config_uses_ollama(&c, host_port().1)) {
+        return;
+    }
+    match ensure_running() {
+        Ok(true) => log_to_file("[ollama] auto-started before backend"),
+        Ok(false) => {}
+        Err(e) => log_to_file(&format!("[ollama] auto-start failed: {e}")),
+    }
+}
+
+pub(crate) fn parse_tags(body: &Value) -> Vec<OllamaModel> {
+    let text = |v: &Value, k: &str| v.get(k).and_then(Value::as_str).map(str::to_string);
+    let mut models: Vec<OllamaModel> = body
+        .get("models")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|m| {
+            let details = m.get("details").cloned().unwrap_or(Value::Null);
+            Some(OllamaModel {
+                name: text(m, "name").or_else(|| text(m, "model"))?,
+                size_bytes: m.get("size").and_then(Value::as_u64).unwrap_or(0),
+                modified_at: text(m, "modified_at"),
+                family: text(&details, "family"),
+                parameter_size: text(&details, "parameter_size"),
+                quantization: text(&details, "quantization_level"),
+            })
+        })
+        .collect();
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+    models
+}
+
+/// One NDJSON line of `/api/pull`.  `{"error": ...}` lines become `Err`.
+pub(crate) fn parse_pull_line(line: &str) -> Result<Option<PullLine>, String> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let v: Value = serde_json::from_str(line).map_err(|e| format!("无法解析 Ollama 响应: {e}"))?;
+    if let Some(err) = v.get("error").and_then(Value::as_str) {
+        return Err(err.to_string());
+    }
+    Ok(Some(PullLine {
+        status: v
+            .get("status")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        completed: v.get("completed").and_then(Value::as_u64),
+        total: v.get("total").and_then(Value::as_u64),
+    }))
+}
+
+pub(crate) fn valid_model_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 200
+        && !name.starts_with(['-', '.', '/'])
+        && !name.contains("..")
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.:/".contains(c))
+}
+
+/// Capabilities for `llm_endpoints.json` from `/api/show`'s list
+/// (Ollama 0.6+); older daemons don't report one, so assume text.
+pub(crate) fn show_capabilities(show: &Value) -> Vec<String> {
+    let mut caps = vec!["text".to_string()];
+    for c in show
+        .get("capabilities")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+    {
+        let mapped = match c {
+            "tools" => "tools",
+            "vision" => "vision",
+            "thinking" => "thinking",
+            _ => continue,
+        };
+        if !caps.iter().any(|x| x == mapped) {
+            caps.push(mapped.to_string());
+        }
+    }
+    caps
+}
+
+/// The context Ollama actually runs the model with: a `num_ctx` set in
+/// the Modelfile.  The architecture's maximum (`model_info.*.context_length`)
+/// is not used — Ollama serves far less by default, and advertising it is
+/// what makes long chats fail with `n_keep >= n_ctx`.
+pub(crate) fn show_context_window(show: &Value) -> Option<u64> {
+    show.get("parameters")
+        .and_then(Value::as_str)?
+        .lines()
+        .find_map(|l| {
+            let mut parts = l.split_whitespace();
+            (parts.next() == Some("num_ctx"))
+                .then(|| parts.next()?.parse().ok())
+                .flatten()
+        })
+}
+
+/// Same naming as the LLM page's `suggestEndpointName`.
+pub(crate) fn endpoint_name(model: &str) -> String {
+    let clean = model.trim().replace(['/', '\\'], "-");
+    format!("ollama-{clean}").chars().take(64).collect()
+}
+
+/// Insert or replace the Ollama endpoint `entry` (matched by name) in an
+/// `llm_endpoints.json` document and return it as written.  A new entry
+/// goes after the existing ones (priority max + 10) unless `priority` is
+/// given; a replaced one keeps its priority.
+pub(crate) fn upsert_endpoint(
+    config: &mut Value,
+    mut entry: Value,
+    priority: Option<u64>,
+) -> Value {
+    if !config.is_object() {
+        *config = json!({});
+    }
+    if !config["endpoints"].is_array() {
+        config["endpoints"] = json!([]);
+    }
+    let Value::Array(list) = &mut config["endpoints"] else {
+        return entry;
+    };
+    let existing = list.iter().position(|e| e.get("name") == entry.get("name"));
+    let prio = priority
+        .or_else(|| existing.and_then(|i| list[i].get("priority").and_then(Value::as_u64)))
+        .unwrap_or_else(|| {
+            list.iter()
+                .filter_map(|e| e.get("priority").and_then(Value::as_u64))
+                .max()
+                .map_or(1, |m| m + 10)
+        });
+    entry["priority"] = json!(prio);
+    match existing {
+        Some(i) => list[i] = entry.clone(),
+        None => list.push(entry.clone()),
+    }
+    entry
+}
+
+#[tauri::command]
+pub async fn detect_ollama() -> OllamaStatus {
+    status().await
+}
+
+/// Start `ollama serve` if nothing is listening yet.
+#[tauri::command]
+pub async fn start_ollama() -> CmdResult<OllamaStatus> {
+    spawn_blocking_result(ensure_running).await?;
+    Ok(status().await)
+}
+
+/// Stop the daemon started by [`start_ollama`]; one started elsewhere (the
+/// Ollama tray app, a service) is left alone.
+#[tauri::command]
+pub async fn stop_ollama() -> CmdResult<OllamaStatus> {
+    let child = SERVE_CHILD
+        .lock()
+        .map_err(|e| format!("ollama lock failed: {e}"))?
+        .take();
+    if let Some(mut child) = child {
+        let _ = child.kill();
+        let _ = child.wait();
+        log_to_file(&format!("[ollama] stopped pid={}", child.id()));
+    }
+    Ok(status().await)
+}
+
+#[tauri::command]
+pub fn get_ollama_auto_start() -> bool {
+    auto_start_enabled()
+}
+
+#[tauri::command]
+pub fn set_ollama_auto_start(enabled: bool) -> CmdResult<()> {
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    state.ollama_auto_start = Some(enabled);
+    write_state_file(&state).map_err(Into::into)
+}
+
+async fn get_json(path: &str) -> Result<Value, String> {
+    net::local_http_client()
+        .get(api_url(path))
+        .timeout(Duration::from_secs(15))
+        .send()
+        .await
+        .map_err(|e| format!("OLLAMA_NOT_RUNNING|无法连接 Ollama: {e}"))?
+        .error_for_status()
+        .map_err(|e| format!("Ollama {path} 失败: {e}"))?
+        .json()
+        .await
+        .map_err(|e| format!("Ollama {path} 响应无效: {e}"))
+}
+
+async fn show_model(model: &str) -> Result<Value, String> {
+    let resp = net::local_http_client()
+        .post(api_url("/api/show"))
+        .timeout(Duration::from_secs(15))
+        .json(&json!({ "model": model, "name": model }))
+        .send()
+        .await
+        .map_err(|e| format!("OLLAMA_NOT_RUNNING|无法连接 Ollama: {e}"))?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(format!("NOT_FOUND|Ollama 中没有模型 {model}，请先拉取"));
+    }
+    resp.error_for_status()
+        .map_err(|e| format!("Ollama /api/show 失败: {e}"))?
+        .json()
+        .await
+        .map_err(|e| format!("Ollama /api/show 响应无效: {e}"))
+}
+
+/// Models already pulled into Ollama.
+#[tauri::command]
+pub async fn ollama_list_models() -> CmdResult<Vec<OllamaModel>> {
+    Ok(parse_tags(&get_json("/api/tags").await?))
+}
+
+async fn pull(name: &str, mut on_line: impl FnMut(&PullLine)) -> Result<(), String> {
+    let mut resp = net::local_http_client()
+        .post(api_url("/api/pull"))
+        .json(&json!({ "model": name, "name": name, "stream": true }))
+        .send()
+        .await
+        .map_err(|e| format!("OLLAMA_NOT_RUNNING|无法连接 Ollama: {e}"))?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        let msg = parse_pull_line(&body).err().unwrap_or(body);
+        return Err(format!("拉取 {name} 失败 ({status}): {msg}"));
+    }
+    let mut buf = Vec::new();
+    let mut success = false;
+    while let Some(chunk) = tokio::time::timeout(PULL_STALL_TIMEOUT, resp.chunk())
+        .await
+        .map_err(|_| {
+            format!(
+                "拉取 {name} 超时：{}s 没有进度",
+                PULL_STALL_TIMEOUT.as_secs()
+            )
+        })?
+        .map_err(|e| format!("拉取 {name} 中断: {e}"))?
+    {
+        buf.extend_from_slice(&chunk);
+        while let Some(pos) = buf.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buf.drain(..=pos).collect();
+            if let Some(l) = parse_pull_line(&String::from_utf8_lossy(&line))? {
+                success |= l.status == "success";
+                on_line(&l);
+            }
+        }
+    }
+    if let Some(l) = parse_pull_line(&String::from_utf8_lossy(&buf))? {
+        success |= l.status == "success";
+        on_line(&l);
+    }
+    if !success {
+        return Err(format!("拉取 {name} 未完成：Ollama 提前结束了响应"));
+    }
+    Ok(())
+}
+
+/// Pull `name` (e.g. `qwen3:8b`) into Ollama.  Interrupted pulls resume on
+/// the next call — Ollama keeps the partial blobs itself.
+#[tauri::command]
+pub async fn ollama_pull_model(
+    app: tauri::AppHandle,
+    name: String,
+    pull_id: Option<String>,
+) -> CmdResult<Vec<OllamaModel>> {
+    let name = name.trim().to_string();
+    if !valid_model_name(&name) {
+        return Err(format!("INVALID_ARGUMENT|模型名无效: {name}").into());
+    }
+    net::ensure_online("拉取 Ollama 模型")?;
+    let pull_id = pull_id.unwrap_or_else(|| format!("ollama-{}", now_ms()));
+    let emit = |status: &str, line: Option<&PullLine>, message: String| {
+        let (completed, total) = line.map_or((None, None), |l| (l.completed, l.total));
+        emit_if_ui_live(
+            &app,
+            EVENT,
+            PullEvent {
+                pull_id: &pull_id,
+                model: &name,
+                status,
+                percent: completed.and_then(|c| net::download_percent(c, total)),
+                completed,
+                total,
+                message,
+            },
+        );
+    };
+    let mut last = (String::new(), None);
+    let result = net::run_cancellable(
+        Some(&pull_id),
+        pull(&name, |l| {
+            let percent = l.completed.and_then(|c| net::download_percent(c, l.total));
+            if last.0 != l.status || last.1 != percent {
+                last = (l.status.clone(), percent);
+                emit(&l.status, Some(l), String::new());
+            }
+        }),
+    )
+    .await;
+    match result {
+        Ok(()) => {
+            log_to_file(&format!("[ollama] pulled {name}"));
+            emit("done", None, String::new());
+            ollama_list_models().await
+        }
+        Err(e) if e.starts_with(net::REQUEST_CANCELLED_ERROR_PREFIX) => {
+            emit("cancelled", None, String::new());
+            Err(e.into())
+        }
+        Err(e) => {
+            log_to_file(&format!("[ollama] pull {name} failed: {e}"));
+            emit("error", None, e.clone());
+            Err(e.into())
+        }
+    }
+}
+
+/// Add `model` as an endpoint of workspace `workspace_id` (or refresh the
+/// existing `ollama-<model>` entry).  Returns the entry written.
+#[tauri::command]
+pub async fn ollama_write_endpoint(
+    workspace_id: String,
+    model: String,
+    priority: Option<u64>,
+) -> CmdResult<Value> {
+    observer::ensure_writable(&workspace_id)?;
+    let ws_dir = workspace_dir(&workspace_id);
+    if workspace_id.is_empty() || !ws_dir.is_dir() {
+        return Err(format!("NOT_FOUND|工作区不存在: {workspace_id}").into());
+    }
+    let model = model.trim().to_string();
+    if !valid_model_name(&model) {
+        return Err(format!("INVALID_ARGUMENT|模型名无效: {model}").into());
+    }
+    let show = show_model(&model).await?;
+    let (host, port) = host_port();
+    let entry = json!({
+        "name": endpoint_name(&model),
+        "provider": "ollama",
+        "api_type": "openai",
+        "base_url": format!("{}/v1", api_base(&host, port)),
+        "model": model,
+        "max_tokens": 0,
+        "context_window": show_context_window(&show).unwrap_or(0),
+        "timeout": 180,
+        "capabilities": show_capabilities(&show),
+    });
+    let path = ws_dir.join("data").join("llm_endpoints.json");
+    let written = spawn_blocking_result(move || {
+        let mut config = match fs::read_to_string(&path) {
+            Ok(s) => serde_json::from_str::<Value>(&s)
+                .map_err(|e| format!("llm_endpoints.json 不是有效 JSON，未修改: {e}"))?,
+            Err(_) => json!({ "endpoints": [] }),
+        };
+        let written = upsert_endpoint(&mut config, entry, priority);
+        let data =
+            serde_json::to_string_pretty(&config).map_err(|e| format!("serialize failed: {e}"))?;
+        atomic_write_with_backup(&path, data.as_bytes())?;
+        Ok(written)
+    })
+    .await?;
+    log_to_file(&format!(
+        "[ollama] wrote endpoint {} for workspace {workspace_id}",
+        written["name"]
+    ));
+    Ok(written)
+}
@@ -0,0 +1,320 @@
+//! Keep a workspace's config pointing at the workspace.
+//!
+//! `openakita_service_start` tells the backend where its endpoints file is
+//! (`LLM_ENDPOINTS_CONFIG`), but a backend started from a terminal
+//! (`openakita serve` in the workspace) only has what `.env` says.  And once
+//! a workspace moves — a custom data root, a restored backup, a copied
+//! folder — absolute paths in its configs still name the old location.
+//!
+//! [`verify`] runs with the workspace scaffold (every start, create and
+//! import), best effort:
+//!
+//! * `.env` gets `LLM_ENDPOINTS_CONFIG=<workspace>/data/llm_endpoints.json`
+//!   (the `/mnt/..` form for WSL workspaces), replacing a stale value, so
+//!   CLI starts read the same file as desktop starts;
+//! * absolute paths under `…/workspaces/<id>/` in `.env`, `.env.*`,
+//!   `data/llm_endpoints.json`, `data/skills.json` and
+//!   `data/mcp/servers/*.json` are rewritten to this workspace when they
+//!   name this workspace at another location, or a workspace folder that no
+//!   longer exists.  Paths into other live workspaces are left alone.  JSON
+//!   files are edited as text, so formatting and key order survive.
+//!
+//! `repair_workspace_paths(workspace_id)` runs the same pass on demand and
+//! reports every rewrite.
+
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::errors::CmdResult;
+use crate::{
+    atomic_write, clean_env_value, comparable_path, log_to_file, observer, read_state_file,
+    read_text_lossy, spawn_blocking_result, update_env_content, workspace_dir, wsl, EnvEntry,
+};
+
+pub(crate) const ENDPOINTS_ENV_KEY: &str = "LLM_ENDPOINTS_CONFIG";
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PathRewrite {
+    /// Relative to the workspace.
+    pub file: String,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathRepairReport {
+    pub workspace_id: String,
+    pub endpoints_config: String,
+    /// `.env` got (or had its stale) `LLM_ENDPOINTS_CONFIG` written.
+    pub env_updated: bool,
+    pub rewrites: Vec<PathRewrite>,
+}
+
+/// The endpoints file the backend of the workspace at `ws_dir` uses.
+pub(crate) fn endpoints_config_path(ws_dir: &Path) -> PathBuf {
+    ws_dir.join("data").join("llm_endpoints.json")
+}
+
+fn is_absolute_like(value: &str) -> bool {
+    let b = value.as_bytes();
+    value.starts_with('/')
+        || value.starts_with("\\\\")
+        || (b.len() > 2
+            && b[0].is_ascii_alphabetic()
+            && b[1] == b':'
+            && matches!(b[2], b'/' | b'\\'))
+}
+
+/// Where `value` should point if it is a path into a moved workspace: the
+/// part up to `workspaces/<id>` is swapped for `ws_dir` when `<id>` is
+/// `ws_id` at another location, or a folder `exists` says is gone.
+pub(crate) fn relocate(
+    value: &str,
+    ws_id: &str,
+    ws_dir: &str,
+    exists: &dyn Fn(&str) -> bool,
+) -> Option<String> {
+    // 只处理单个路径；PATH 式的列表不动
+    let listy = value.contains(';') || value.get(2..).is_some_and(|r| r.contains(":/"));
+    if !is_absolute_like(value) || listy {
+        return None;
+    }
+    let norm = value.replace('\\', "/");
+    let start = norm.find("/workspaces/")? + "/workspaces/".len();
+    let seg_len = norm[start..].find('/').unwrap_or(norm.len() - start);
+    if seg_len == 0 {
+        return None;
+    }
+    let end = start + seg_len;
+    let (prefix, rest) = (&value[..end], &value[end..]);
+    if comparable_path(Path::new(prefix)) == comparable_path(Path::new(ws_dir)) {
+        return None;
+    }
+    if &norm[start..end] != ws_id && exists(prefix) {
+        return None;
+    }
+    Some(format!("{ws_dir}{rest}"))
+}
+
+/// Rewrite the values of `KEY=VALUE` lines.
+pub(crate) fn repair_env_text(
+    text: &str,
+    fix: &dyn Fn(&str) -> Option<String>,
+) -> (String, Vec<(String, String)>) {
+    let mut changes = Vec::new();
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| {
+            let t = line.trim_start();
+            if t.starts_with('#') {
+                return line.to_string();
+            }
+            let Some((key, raw)) = line.split_once('=') else {
+                return line.to_string();
+            };
+            let value = clean_env_value(raw);
+            match fix(&value) {
+                Some(new) if raw.contains(&value) => {
+                    let line = format!("{key}={}", raw.replacen(&value, &new, 1));
+                    changes.push((value, new));
+                    line
+                }
+                _ => line.to_string(),
+            }
+        })
+        .collect();
+    let mut out = lines.join("\n");
+    if text.ends_with('\n') {
+        out.push('\n');
+    }
+    (out, changes)
+}
+
+fn collect_strings(value: &serde_json::Value, out: &mut Vec<String>) {
+    match value {
+        serde_json::Value::String(s) => out.push(s.clone()),
+        serde_json::Value::Array(items) => items.iter().for_each(|v| collect_strings(v, out)),
+        serde_json::Value::Object(map) => map.values().for_each(|v| collect_strings(v, out)),
+        _ => {}
+    }
+}
+
+/// Rewrite path strings of a JSON document in place (as text).  `None`
+/// when it is not JSON.
+pub(crate) fn repair_json_text(
+    text: &str,
+    fix: &dyn Fn(&str) -> Option<String>,
+) -> Option<(String, Vec<(String, String)>)> {
+    let doc: serde_json::Value = serde_json::from_str(text).ok()?;
+    let mut strings = Vec::new();
+    collect_strings(&doc, &mut strings);
+    let mut out = text.to_string();
+    let mut changes: Vec<(String, String)> = Vec::new();
+    for s in strings {
+        let Some(new) = fix(&s) else { continue };
+        if changes.iter().any(|(old, _)| old == &s) {
+            continue;
+        }
+        let (from, to) = (
+            serde_json::to_string(&s).ok()?,
+            serde_json::to_string(&new).ok()?,
+        );
+        if out.contains(&from) {
+            out = out.replace(&from, &to);
+            changes.push((s, new));
+        }
+    }
+    Some((out, changes))
+}
+
+fn config_files(ws_dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(ws_dir)
+        .map(|rd| {
+            rd.flatten()
+                .map(|e| e.path())
+                .filter(|p| {
+                    let name = p.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                    p.is_file() && (name == ".env" || name.starts_with(".env."))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    let data = ws_dir.join("data");
+    files.push(data.join("llm_endpoints.json"));
+    files.push(data.join("skills.json"));
+    if let Ok(rd) = fs::read_dir(data.join("mcp").join("servers")) {
+        let mut servers: Vec<PathBuf> = rd
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "json"))
+            .collect();
+        servers.sort();
+        files.extend(servers);
+    }
+    files.retain(|p| p.is_file());
+    files
+}
+
+/// The `.env` value of `key`, if set.
+fn env_value(text: &str, key: &str) -> Option<String> {
+    // 同一键写了多次时以最后一行为准
+    text.lines()
+        .rev()
+        .map(str::trim)
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|l| l.split_once('='))
+        .find(|(k, _)| k.trim() == key)
+        .map(|(_, v)| clean_env_value(v))
+}
+
+fn workspace_id_of(ws_dir: &Path) -> String {
+    read_state_file()
+        .workspaces
+        .into_iter()
+        .map(|w| w.id)
+        .find(|id| workspace_dir(id) == ws_dir)
+        .or_else(|| ws_dir.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_default()
+}
+
+fn repair(ws_dir: &Path) -> Result<PathRepairReport, String> {
+    let workspace_id = workspace_id_of(ws_dir);
+    let ws_text = ws_dir.to_string_lossy().to_string();
+    // WSL 工作区的后端在发行版里读 .env，路径要写成 /mnt/.. 形式
+    let wsl_dir =
+        wsl::active_config(&workspace_id).and_then(|_| wsl::windows_to_wsl_path(&ws_text));
+    let endpoints = endpoints_config_path(ws_dir).to_string_lossy().to_string();
+    let endpoints = match &wsl_dir {
+        Some(_) => wsl::windows_to_wsl_path(&endpoints).unwrap_or(endpoints),
+        None => endpoints,
+    };
+
+    let exists = |p: &str| Path::new(p).exists();
+    let fix = |value: &str| {
+        let target = match &wsl_dir {
+            Some(w) if value.starts_with('/') => w.as_str(),
+            _ => ws_text.as_str(),
+        };
+        relocate(value, &workspace_id, target, &exists)
+    };
+    let mut rewrites = Vec::new();
+    for path in config_files(ws_dir) {
+        let text = read_text_lossy(&path);
+        let is_json = path.extension().is_some_and(|e| e == "json");
+        let (updated, changes) = if is_json {
+            match repair_json_text(&text, &fix) {
+                Some(r) => r,
+                None => continue,
+            }
+        } else {
+            repair_env_text(&text, &fix)
+        };
+        if changes.is_empty() {
+            continue;
+        }
+        atomic_write(&path, &updated)?;
+        let file = path
+            .strip_prefix(ws_dir)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        for (before, after) in changes {
+            rewrites.push(PathRewrite {
+                file: file.clone(),
+                before,
+                after,
+            });
+        }
+    }
+
+    let env_path = ws_dir.join(".env");
+    let text = read_text_lossy(&env_path);
+    let env_updated = env_value(&text, ENDPOINTS_ENV_KEY).as_deref() != Some(&endpoints);
+    if env_updated {
+        let entry = EnvEntry {
+            key: ENDPOINTS_ENV_KEY.to_string(),
+            value: endpoints.clone(),
+        };
+        atomic_write(&env_path, update_env_content(&text, &[entry]))?;
+    }
+    if env_updated || !rewrites.is_empty() {
+        log_to_file(&format!(
+            "[path_repair] {workspace_id}: {} path(s) rewritten, {ENDPOINTS_ENV_KEY} {}",
+            rewrites.len(),
+            if env_updated { "written" } else { "ok" }
+        ));
+    }
+    Ok(PathRepairReport {
+        workspace_id,
+        endpoints_config: endpoints,
+        env_updated,
+        rewrites,
+    })
+}
+
+/// Scaffold hook: repair the workspace at `ws_dir`, logging failures.
+pub(crate) fn verify(ws_dir: &Path) {
+    if let Err(e) = repair(ws_dir) {
+        log_to_file(&format!("[path_repair] {} failed: {e}", ws_dir.display()));
+    }
+}
+
+/// Write `LLM_ENDPOINTS_CONFIG` into `.env` and rewrite paths that still
+/// point at the workspace's old location.
+#[tauri::command]
+pub async fn repair_workspace_paths(workspace_id: String) -> CmdResult<PathRepairReport> {
+    observer::ensure_writable(&workspace_id)?;
+    spawn_blocking_result(move || {
+        let dir = workspace_dir(&workspace_id);
+        if !dir.is_dir() {
+            return Err(format!("NOT_FOUND|工作区不存在: {workspace_id}"));
+        }
+        repair(&dir)
+    })
+    .await
+    .map_err(Into::into)
+}
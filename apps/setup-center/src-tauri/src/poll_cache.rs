@@ -0,0 +1,149 @@
+//! Debounce / coalescing for frontend-driven polling commands.
+//!
+//! The webview polls `openakita_service_status`, `openakita_service_log` and
+//! `openakita_check_pid_alive` on timers and fires health checks from several
+//! views.  After a frontend bug (an effect re-subscribing on every render, a
+//! retry loop without back-off) those timers turn into tight loops, and every
+//! call stats PID files, inspects processes or re-reads and re-redacts a log
+//! tail — enough to pin a CPU core.
+//!
+//! * [`PollCache`] — results are reused for a short TTL per key; concurrent
+//!   callers with the same key wait for the one computation in progress.
+//!   Errors are never cached.  Lifecycle commands call
+//!   [`PollCache::invalidate`] so a start/stop is visible immediately.
+//! * [`InFlight`] — for slow async work (bridge health checks): identical
+//!   calls that arrive while one is running share its result instead of
+//!   starting another Python process.  Service start / stop use it too, so a
+//!   double click joins the operation already running instead of spawning or
+//!   killing a second time.
+
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::{ServiceLogChunk, ServiceStatus};
+
+/// `openakita_service_status`: the UI polls every 2–5 s; 1 s hides nothing.
+pub(crate) static SERVICE_STATUS: Lazy<PollCache<ServiceStatus>> =
+    Lazy::new(|| PollCache::new(Duration::from_secs(1)));
+pub(crate) static PID_ALIVE: Lazy<PollCache<bool>> =
+    Lazy::new(|| PollCache::new(Duration::from_secs(1)));
+/// Keyed by workspace + tail size.
+pub(crate) static SERVICE_LOG: Lazy<PollCache<ServiceLogChunk>> =
+    Lazy::new(|| PollCache::new(Duration::from_millis(750)));
+
+pub(crate) static HEALTH_CHECKS: Lazy<InFlight<String>> = Lazy::new(InFlight::new);
+
+/// `openakita_service_start` / `_stop`, keyed by `kind|workspace`: the value
+/// is the operation id and its result.
+pub(crate) static SERVICE_LIFECYCLE: Lazy<InFlight<LifecycleOutcome>> = Lazy::new(InFlight::new);
+
+pub(crate) type LifecycleOutcome = (u64, Result<ServiceStatus, String>);
+
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+type Slot<V> = Arc<Mutex<Option<(Instant, V)>>>;
+
+pub(crate) struct PollCache<V> {
+    ttl: Duration,
+    slots: Mutex<HashMap<String, Slot<V>>>,
+}
+
+impl<V: Clone> PollCache<V> {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        PollCache {
+            ttl,
+            slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The value cached for `key` if it is younger than the TTL, otherwise
+    /// the result of `compute` (cached on success).
+    pub(crate) fn get_or_compute(
+        &self,
+        key: &str,
+        compute: impl FnOnce() -> Result<V, String>,
+    ) -> Result<V, String> {
+        let slot = lock(&self.slots)
+            .entry(key.to_string())
+            .or_default()
+            .clone();
+        // 同 key 的并发调用在这里排队，等前一个算完直接拿结果
+        let mut entry = lock(&slot);
+        if let Some((at, value)) = entry.as_ref() {
+            if at.elapsed() < self.ttl {
+                return Ok(value.clone());
+            }
+        }
+        let value = compute()?;
+        *entry = Some((Instant::now(), value.clone()));
+        Ok(value)
+    }
+
+    /// Drop every cached entry whose key starts with `prefix` (`""` = all).
+    pub(crate) fn invalidate(&self, prefix: &str) {
+        lock(&self.slots).retain(|k, _| !k.starts_with(prefix));
+    }
+}
+
+type Call<V> = Arc<tokio::sync::OnceCell<Result<V, String>>>;
+
+pub(crate) struct InFlight<V> {
+    calls: Mutex<HashMap<String, Call<V>>>,
+}
+
+/// Removes a finished call from the table once a caller that saw it finish
+/// returns (or is cancelled), so the next call with that key runs afresh.
+/// A call whose runner was cancelled mid-way stays; the next caller with the
+/// same key picks it up and runs it.
+struct Forget<'a, V> {
+    table: &'a InFlight<V>,
+    key: &'a str,
+    call: Call<V>,
+}
+
+impl<V> Drop for Forget<'_, V> {
+    fn drop(&mut self) {
+        if !self.call.initialized() {
+            return;
+        }
+        let mut calls = lock(&self.table.calls);
+        if calls
+            .get(self.key)
+            .is_some_and(|c| Arc::ptr_eq(c, &self.call))
+        {
+            calls.remove(self.key);
+        }
+    }
+}
+
+impl<V: Clone> InFlight<V> {
+    pub(crate) fn new() -> Self {
+        InFlight {
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Run `f` unless an identical call (same `key`) is already running, in
+    /// which case wait for and return that call's result.
+    pub(crate) async fn run<F, Fut>(&self, key: &str, f: F) -> Result<V, String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, String>>,
+    {
+        let call = lock(&self.calls)
+            .entry(key.to_string())
+            .or_default()
+            .clone();
+        let _forget = Forget {
+            table: self,
+            key,
+            call: call.clone(),
+        };
+        call.get_or_init(f).await.clone()
+    }
+}
@@ -0,0 +1,79 @@
+//! Portable mode.
+//!
+//! A `portable.flag` file next to the executable (next to the `.AppImage`
+//! on Linux) moves everything the app writes — root config, workspaces,
+//! runtime venvs, run/ and logs, crash markers, and on Windows the WebView2
+//! profile — from the user's home into a `data/` folder beside the binary,
+//! for USB sticks and no-install corporate deployments.
+//!
+//! The flag may name a different *relative* folder on its first
+//! non-comment line (`# ...` lines are ignored); absolute paths and `..`
+//! are rejected so the data can't escape the install folder.
+//!
+//! Every data path is derived from `default_root_dir()` /
+//! `openakita_root_dir()`, which consult [`portable_root`] first; backend
+//! and bridge processes receive the result as `OPENAKITA_ROOT`.  An explicit
+//! `OPENAKITA_ROOT` in the environment still wins, and the "custom data
+//! root" setting is unavailable while portable.
+
+use once_cell::sync::Lazy;
+use std::path::{Component, Path, PathBuf};
+
+pub(crate) const FLAG_FILE: &str = "portable.flag";
+const DEFAULT_DATA_DIR: &str = "data";
+
+/// Folder the user sees the app in: the `.AppImage` file's folder on Linux,
+/// otherwise the executable's.
+fn install_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "linux")]
+    if let Some(appimage) = std::env::var_os("APPIMAGE") {
+        return PathBuf::from(appimage).parent().map(Path::to_path_buf);
+    }
+    std::env::current_exe()
+        .ok()?
+        .parent()
+        .map(Path::to_path_buf)
+}
+
+/// Data root for a flag file with `flag_content` in `install_dir`.
+pub(crate) fn data_root_from_flag(install_dir: &Path, flag_content: &str) -> PathBuf {
+    let requested = flag_content
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(Path::new)
+        .filter(|p| p.components().all(|c| matches!(c, Component::Normal(_))));
+    install_dir.join(requested.unwrap_or(Path::new(DEFAULT_DATA_DIR)))
+}
+
+static PORTABLE_ROOT: Lazy<Option<PathBuf>> = Lazy::new(|| {
+    let dir = install_dir()?;
+    let flag = dir.join(FLAG_FILE);
+    if !flag.is_file() {
+        return None;
+    }
+    let root = data_root_from_flag(&dir, &std::fs::read_to_string(&flag).unwrap_or_default());
+    // log_to_file 本身依赖数据目录，这里只能写 stderr
+    eprintln!("portable mode: data root {}", root.display());
+    Some(root)
+});
+
+/// The portable data root, when running in portable mode.
+pub(crate) fn portable_root() -> Option<&'static Path> {
+    PORTABLE_ROOT.as_deref()
+}
+
+pub(crate) fn is_portable() -> bool {
+    portable_root().is_some()
+}
+
+/// Process-wide settings that must be in place before any window exists.
+pub(crate) fn apply_process_env() {
+    #[cfg(windows)]
+    if let Some(root) = portable_root() {
+        // WebView2 默认把 profile 放在 %LOCALAPPDATA%，便携模式下也跟着走
+        if std::env::var_os("WEBVIEW2_USER_DATA_FOLDER").is_none() {
+            std::env::set_var("WEBVIEW2_USER_DATA_FOLDER", root.join("webview"));
+        }
+    }
+}
@@ -0,0 +1,280 @@
+//! Named profiles of the OpenAkita root.
+//!
+//! Workspaces share one root (`~/.openakita` or the custom data root): one
+//! runtime, one state file, one `run/`.  A profile is a whole separate root
+//! — its own venvs, state, workspaces — under a name ("work", "personal").
+//! Profiles are recorded in `root_config.json` next to `custom_root`, and
+//! switching simply re-points `custom_root`, so every path helper follows
+//! through the normal `openakita_root_dir()` resolution.  The implicit
+//! `default` profile is the default root.
+//!
+//! `switch_profile` first stops the desktop-managed backends of the root
+//! being left (CLI-started "external" ones are kept and reported), then
+//! restarts the internal monitors: skill watchers are dropped, the service
+//! poll caches cleared, the heartbeat loop resets its counters (it watches
+//! [`generation`]), and the startup self-check and undo pruning rerun for
+//! the new root.  The UI gets `profile-switched` and reloads.
+//!
+//! Profiles are unavailable in portable mode and when `OPENAKITA_ROOT` is
+//! set, since both pin the root.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::errors::CmdResult;
+use crate::{
+    default_root_dir, ensure_safe_openakita_data_root, invalidate_service_polls, is_pid_running,
+    list_service_pids, log_to_file, openakita_root_dir, portable, read_root_config,
+    read_workspace_api_port, skill_watch, startup_check, stop_service_pid_entry, undo,
+    write_root_config, write_root_marker, ROOT_CONFIG_LOCK,
+};
+
+pub(crate) const DEFAULT_PROFILE: &str = "default";
+
+/// Bumped on every switch; long-running monitors compare it to notice.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn generation() -> u64 {
+    GENERATION.load(Ordering::SeqCst)
+}
+
+/// A named root, as stored in `root_config.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RootProfile {
+    pub name: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileInfo {
+    pub name: String,
+    pub path: String,
+    pub active: bool,
+    pub exists: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileSwitchResult {
+    pub profile: ProfileInfo,
+    /// Backends of the previous root that were stopped.
+    pub stopped: Vec<u32>,
+    /// CLI-started backends of the previous root left running.
+    pub kept_external: Vec<u32>,
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    let norm = |p: &Path| {
+        let s = p.to_string_lossy().replace('\\', "/");
+        let s = s.trim_end_matches('/').to_string();
+        if cfg!(windows) {
+            s.to_lowercase()
+        } else {
+            s
+        }
+    };
+    norm(a) == norm(b)
+}
+
+/// Profile names: letters, digits, `-`, `_`, at most 40 chars.
+pub(crate) fn valid_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 40
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// `default` plus the named profiles, with the one matching `current_root`
+/// marked active.
+pub(crate) fn build_profile_list(
+    default_root: &Path,
+    profiles: &[RootProfile],
+    current_root: &Path,
+) -> Vec<ProfileInfo> {
+    let default = RootProfile {
+        name: DEFAULT_PROFILE.to_string(),
+        path: default_root.to_string_lossy().to_string(),
+    };
+    std::iter::once(&default)
+        .chain(profiles.iter())
+        .map(|p| ProfileInfo {
+            name: p.name.clone(),
+            path: p.path.clone(),
+            active: same_path(Path::new(&p.path), current_root),
+            exists: Path::new(&p.path).is_dir(),
+        })
+        .collect()
+}
+
+fn ensure_profiles_allowed() -> Result<(), String> {
+    if portable::is_portable() {
+        return Err(
+            "INVALID_ARGUMENT|便携模式下不能切换配置档 / profiles are unavailable in portable mode"
+                .into(),
+        );
+    }
+    if std::env::var("OPENAKITA_ROOT").is_ok_and(|v| !v.is_empty()) {
+        return Err(
+            "INVALID_ARGUMENT|已通过 OPENAKITA_ROOT 环境变量固定数据目录 / OPENAKITA_ROOT pins the root"
+                .into(),
+        );
+    }
+    Ok(())
+}
+
+fn current_profiles() -> Vec<ProfileInfo> {
+    build_profile_list(
+        &default_root_dir(),
+        &read_root_config().profiles,
+        &openakita_root_dir(),
+    )
+}
+
+#[tauri::command]
+pub fn list_profiles() -> Vec<ProfileInfo> {
+    current_profiles()
+}
+
+/// Register a new profile rooted at `path` (created if missing).
+#[tauri::command]
+pub fn create_profile(name: String, path: String) -> CmdResult<ProfileInfo> {
+    ensure_profiles_allowed()?;
+    let name = name.trim().to_string();
+    if !valid_profile_name(&name) || name == DEFAULT_PROFILE {
+        return Err(format!("INVALID_ARGUMENT|配置档名称无效: {name}").into());
+    }
+    let target = PathBuf::from(path.trim());
+    if !target.is_absolute() {
+        return Err("INVALID_ARGUMENT|请使用绝对路径".into());
+    }
+    ensure_safe_openakita_data_root(&target)?;
+    let _lock = ROOT_CONFIG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut config = read_root_config();
+    let existing = build_profile_list(&default_root_dir(), &config.profiles, &openakita_root_dir());
+    if existing.iter().any(|p| p.name == name) {
+        return Err(format!("INVALID_ARGUMENT|配置档 {name} 已存在").into());
+    }
+    // 根目录互相嵌套会让一个配置档的清理 / 迁移误伤另一个
+    if let Some(clash) = existing.iter().find(|p| {
+        let other = Path::new(&p.path);
+        target.starts_with(other) || other.starts_with(&target)
+    }) {
+        return Err(format!(
+            "INVALID_ARGUMENT|目录与配置档 {} ({}) 重叠",
+            clash.name, clash.path
+        )
+        .into());
+    }
+    if target.exists() && !target.is_dir() {
+        return Err("INVALID_ARGUMENT|指定的路径已存在但不是目录".into());
+    }
+    write_root_marker(&target)?;
+    config.profiles.push(RootProfile {
+        name: name.clone(),
+        path: target.to_string_lossy().to_string(),
+    });
+    write_root_config(&config)?;
+    log_to_file(&format!(
+        "[profiles] created {name} at {}",
+        target.display()
+    ));
+    current_profiles()
+        .into_iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| "profile vanished after create".into())
+}
+
+/// Stop the desktop-managed backends of the current root: (stopped, kept).
+fn stop_current_root_backends() -> (Vec<u32>, Vec<u32>) {
+    let (mut stopped, mut kept) = (Vec::new(), Vec::new());
+    for ent in list_service_pids()
+        .into_iter()
+        .filter(|e| is_pid_running(e.pid))
+    {
+        if ent.started_by == "external" {
+            kept.push(ent.pid);
+            continue;
+        }
+        let port = read_workspace_api_port(&ent.workspace_id);
+        match stop_service_pid_entry(&ent, port) {
+            Ok(()) => stopped.push(ent.pid),
+            Err(e) => log_to_file(&format!(
+                "[profiles] stopping pid={} (ws={}) failed: {e}",
+                ent.pid, ent.workspace_id
+            )),
+        }
+    }
+    (stopped, kept)
+}
+
+/// Re-point root-dependent background machinery after a switch.
+fn restart_monitors() {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    skill_watch::stop_all_watchers();
+    invalidate_service_polls("");
+    std::thread::spawn(|| {
+        startup_check::run_startup_checks();
+        undo::prune_expired();
+    });
+}
+
+#[tauri::command]
+pub async fn switch_profile(app: tauri::AppHandle, name: String) -> CmdResult<ProfileSwitchResult> {
+    ensure_profiles_allowed()?;
+    let result = crate::spawn_blocking_result(move || {
+        let config = read_root_config();
+        let target = if name == DEFAULT_PROFILE {
+            None
+        } else {
+            let profile = config
+                .profiles
+                .iter()
+                .find(|p| p.name == name)
+                .ok_or_else(|| format!("NOT_FOUND|配置档不存在: {name}"))?;
+            Some(profile.path.clone())
+        };
+        let target_root = target
+            .as_deref()
+            .map(PathBuf::from)
+            .unwrap_or_else(default_root_dir);
+        if same_path(&target_root, &openakita_root_dir()) {
+            return Err(format!("INVALID_ARGUMENT|已经是当前配置档: {name}"));
+        }
+        // 连父目录都不在（如移动硬盘没插）就别切过去
+        if !target_root.exists() && !target_root.parent().is_some_and(Path::exists) {
+            return Err(format!(
+                "INVALID_ARGUMENT|配置档目录不可访问: {}",
+                target_root.display()
+            ));
+        }
+
+        let (stopped, kept_external) = stop_current_root_backends();
+        {
+            let _lock = ROOT_CONFIG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            let mut config = read_root_config();
+            config.custom_root = target;
+            write_root_config(&config)?;
+        }
+        restart_monitors();
+        log_to_file(&format!(
+            "[profiles] switched to {name} ({}), stopped={stopped:?}, kept_external={kept_external:?}",
+            target_root.display()
+        ));
+        let profile = current_profiles()
+            .into_iter()
+            .find(|p| p.name == name)
+            .ok_or_else(|| "profile vanished after switch".to_string())?;
+        Ok(ProfileSwitchResult {
+            profile,
+            stopped,
+            kept_external,
+        })
+    })
+    .await?;
+    crate::emit_if_ui_live(&app, "profile-switched", result.clone());
+    Ok(result)
+}
@@ -0,0 +1,277 @@
+//! Secret redaction for log and error surfaces.
+//!
+//! Serve logs and pip output sometimes echo API keys and tokens, and several
+//! commands hand raw log tails back to the UI inside error strings.  Text that
+//! leaves the app through `openakita_service_log`, `crash.log`, the
+//! diagnostic / feedback bundles or bridge / pip errors goes through
+//! [`redact_text`] first:
+//!
+//! 1. values of secrets configured in workspace `.env` files are replaced
+//!    verbatim (whatever their shape);
+//! 2. `NAME=value` / `"name": "value"` where the name looks like a secret
+//!    (`*_KEY`, `*token`, `*secret`, `password`, ...);
+//! 3. well-known token shapes (`sk-…`, `ghp_…`, `xoxb-…`, `AKIA…`, JWTs, ...);
+//! 4. `Bearer <token>`.
+//!
+//! Masked spans become `***`; the prefix of a recognised token is kept so a
+//! support engineer can still tell *which* key was involved.
+
+use once_cell::sync::Lazy;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{env_overlay, read_env_kv, trace, workspace_dir, workspaces_dir};
+
+const MASK: &str = "***";
+/// Shorter configured values (ports, flags, `true`) would mask half the log.
+const MIN_KNOWN_LEN: usize = 8;
+
+/// Bundles read hundreds of files; re-reading every `.env` per file is waste.
+const SECRETS_CACHE_TTL: Duration = Duration::from_secs(10);
+
+type SecretsSnapshot = (Instant, Vec<String>);
+
+static SECRETS_CACHE: Lazy<Mutex<Option<SecretsSnapshot>>> = Lazy::new(|| Mutex::new(None));
+
+/// (prefix, minimum length of the random part after it)
+const TOKEN_PREFIXES: &[(&str, usize)] = &[
+    ("sk-ant-", 16),
+    ("sk-proj-", 16),
+    ("sk-", 16),
+    ("github_pat_", 20),
+    ("ghp_", 20),
+    ("gho_", 20),
+    ("ghs_", 20),
+    ("hf_", 20),
+    ("xoxb-", 10),
+    ("xoxp-", 10),
+    ("xapp-", 10),
+    ("AKIA", 16),
+    ("AIza", 30),
+    ("eyJ", 20),
+];
+
+const SECRET_NAME_SUFFIXES: &[&str] = &[
+    "key",
+    "token",
+    "secret",
+    "password",
+    "passwd",
+    "authorization",
+    "cookie",
+    "credential",
+    "credentials",
+];
+
+fn is_token_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.')
+}
+
+/// Stricter than `trace::is_secret_name`: log lines are full of
+/// `input_tokens=…` / `cache_key_count=…`, so only names *ending* in a
+/// secret word count.
+fn is_secret_key_name(name: &str) -> bool {
+    let n = name.to_ascii_lowercase();
+    SECRET_NAME_SUFFIXES.iter().any(|s| n.ends_with(s))
+}
+
+fn replace_known(text: &str, known: &[String]) -> String {
+    let mut values: Vec<&str> = known
+        .iter()
+        .map(|v| v.trim())
+        .filter(|v| v.len() >= MIN_KNOWN_LEN)
+        .collect();
+    // 长的先替换，避免一个值是另一个值前缀时只遮一半
+    values.sort_by_key(|v| std::cmp::Reverse(v.len()));
+    values.dedup();
+    let mut out = text.to_string();
+    for v in values {
+        if out.contains(v) {
+            out = out.replace(v, MASK);
+        }
+    }
+    out
+}
+
+/// `NAME=value`, `name: value`, `"name": "value"` with a secret-looking name.
+fn mask_key_values(text: &str) -> String {
+    let b = text.as_bytes();
+    let mut out: Vec<u8> = Vec::with_capacity(b.len());
+    let mut i = 0;
+    while i < b.len() {
+        if !is_token_byte(b[i]) || (i > 0 && is_token_byte(b[i - 1])) {
+            out.push(b[i]);
+            i += 1;
+            continue;
+        }
+        let name_start = i;
+        while i < b.len() && is_token_byte(b[i]) {
+            i += 1;
+        }
+        out.extend_from_slice(&b[name_start..i]);
+        if !is_secret_key_name(&text[name_start..i]) {
+            continue;
+        }
+        // 分隔符：可选引号、空格，然后 = 或 :
+        let mut j = i;
+        if j < b.len() && matches!(b[j], b'"' | b'\'') {
+            j += 1;
+        }
+        while j < b.len() && b[j] == b' ' {
+            j += 1;
+        }
+        if j >= b.len() || !matches!(b[j], b'=' | b':') {
+            continue;
+        }
+        j += 1;
+        while j < b.len() && b[j] == b' ' {
+            j += 1;
+        }
+        if j < b.len() && matches!(b[j], b'"' | b'\'') {
+            j += 1;
+        }
+        let value_start = j;
+        while j < b.len()
+            && !matches!(
+                b[j],
+                b' ' | b'\t' | b'\r' | b'\n' | b'"' | b'\'' | b',' | b';' | b'}' | b'&'
+            )
+        {
+            j += 1;
+        }
+        let value = &text[value_start..j];
+        let keep = value.len() < 4
+            || value == MASK
+            || value.bytes().all(|c| c.is_ascii_digit())
+            || value.eq_ignore_ascii_case("bearer")
+            || value.eq_ignore_ascii_case("none")
+            || value.eq_ignore_ascii_case("null");
+        if keep {
+            continue;
+        }
+        out.extend_from_slice(&b[i..value_start]);
+        out.extend_from_slice(MASK.as_bytes());
+        i = j;
+    }
+    String::from_utf8(out).unwrap_or_else(|_| text.to_string())
+}
+
+/// Well-known token shapes and `Bearer <token>`.
+fn mask_token_shapes(text: &str) -> String {
+    let b = text.as_bytes();
+    let mut out: Vec<u8> = Vec::with_capacity(b.len());
+    let mut i = 0;
+    'outer: while i < b.len() {
+        let at_boundary = i == 0 || !is_token_byte(b[i - 1]);
+        if at_boundary {
+            for (prefix, min_len) in TOKEN_PREFIXES {
+                if !b[i..].starts_with(prefix.as_bytes()) {
+                    continue;
+                }
+                let rest = i + prefix.len();
+                let mut end = rest;
+                while end < b.len() && is_token_byte(b[end]) {
+                    end += 1;
+                }
+                // 随机串里总有数字；纯单词（sk-learn-extensions）不算
+                if end - rest >= *min_len && b[rest..end].iter().any(u8::is_ascii_digit) {
+                    out.extend_from_slice(prefix.as_bytes());
+                    out.extend_from_slice(MASK.as_bytes());
+                    i = end;
+                    continue 'outer;
+                }
+            }
+            if b.len() - i > 7 && b[i..i + 7].eq_ignore_ascii_case(b"bearer ") {
+                let rest = i + 7;
+                let mut end = rest;
+                while end < b.len()
+                    && (is_token_byte(b[end]) || matches!(b[end], b'=' | b'+' | b'/'))
+                {
+                    end += 1;
+                }
+                if end - rest >= 8 {
+                    out.extend_from_slice(&b[i..rest]);
+                    out.extend_from_slice(MASK.as_bytes());
+                    i = end;
+                    continue;
+                }
+            }
+        }
+        out.push(b[i]);
+        i += 1;
+    }
+    String::from_utf8(out).unwrap_or_else(|_| text.to_string())
+}
+
+/// Mask secrets in `text`; `known` are literal secret values to remove.
+pub(crate) fn redact_text(text: &str, known: &[String]) -> String {
+    let text = replace_known(text, known);
+    let text = mask_key_values(&text);
+    mask_token_shapes(&text)
+}
+
+/// Secret values configured in one workspace's `.env`.
+pub(crate) fn workspace_secret_values(workspace_id: &str) -> Vec<String> {
+    env_overlay::env_files(&workspace_dir(workspace_id))
+        .iter()
+        .flat_map(|p| read_env_kv(p))
+        .filter(|(k, v)| trace::is_secret_name(k) && v.len() >= MIN_KNOWN_LEN)
+        .map(|(_, v)| v)
+        .collect()
+}
+
+/// Secret values across every workspace on disk.  Walks the workspaces
+/// directory instead of `state.json` so it is safe from the panic hook.
+pub(crate) fn all_secret_values() -> Vec<String> {
+    let Ok(rd) = fs::read_dir(workspaces_dir()) else {
+        return Vec::new();
+    };
+    rd.flatten()
+        .filter(|e| e.path().is_dir())
+        .flat_map(|e| workspace_secret_values(&e.file_name().to_string_lossy()))
+        .collect()
+}
+
+/// [`redact_text`] with the secrets of one workspace.
+pub(crate) fn redact_for_workspace(text: &str, workspace_id: &str) -> String {
+    redact_text(text, &workspace_secret_values(workspace_id))
+}
+
+/// [`redact_text`] with the secrets of all workspaces.
+pub(crate) fn redact(text: &str) -> String {
+    redact_text(text, &all_secret_values())
+}
+
+fn cached_secret_values() -> Vec<String> {
+    let Ok(mut cache) = SECRETS_CACHE.lock() else {
+        return all_secret_values();
+    };
+    if let Some((at, values)) = cache.as_ref() {
+        if at.elapsed() < SECRETS_CACHE_TTL {
+            return values.clone();
+        }
+    }
+    let values = all_secret_values();
+    *cache = Some((Instant::now(), values.clone()));
+    values
+}
+
+/// Redact UTF-8 text content; binary data (NUL bytes / invalid UTF-8) is
+/// returned untouched.
+pub(crate) fn redact_bytes(data: Vec<u8>, known: &[String]) -> Vec<u8> {
+    if data.contains(&0) {
+        return data;
+    }
+    match String::from_utf8(data) {
+        Ok(text) => redact_text(&text, known).into_bytes(),
+        Err(e) => e.into_bytes(),
+    }
+}
+
+/// Read a file for a support bundle with secrets masked (empty on error).
+pub(crate) fn read_redacted(path: &Path) -> Vec<u8> {
+    let data = fs::read(path).unwrap_or_default();
+    redact_bytes(data, &cached_secret_values())
+}
@@ -0,0 +1,562 @@
+//! Per-workspace resource limits for the backend process.
+//!
+//! A memory-consolidation run or a runaway tool can take the backend to
+//! many GB and pin every core, and the desktop (and the rest of the
+//! machine) goes down with it.  `set_resource_limits(workspace_id, limits)`
+//! stores caps that are applied at the next start:
+//!
+//! * Linux — a cgroup v2 group `openakita-<ws>` next to the desktop's own
+//!   (`memory.max`, `cpu.max`; the backend joins it before `exec`).  When the
+//!   cgroup tree is not delegated to the user, memory falls back to
+//!   `RLIMIT_AS` (address space, so leave headroom) and the CPU cap is
+//!   skipped.  Affinity uses `sched_setaffinity`.
+//! * Windows — a Job Object with a per-process committed-memory limit and a
+//!   hard CPU rate cap, plus `SetProcessAffinityMask`.  Allocations past the
+//!   limit fail (Python raises `MemoryError`) rather than the process being
+//!   killed.
+//! * macOS — `RLIMIT_AS` only; the CPU cap and affinity are not available.
+//!
+//! What was applied, or why not, is written to the desktop log at start.
+//! WSL workspaces are not limited: the desktop only sees `wsl.exe`.
+//!
+//! Independent of limits, on Windows every backend the desktop owns (started
+//! from the GUI or the supervisor) also goes into a Job Object with
+//! `JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE` ([`tie_to_app`]).  Setup Center
+//! holds the only handle, so when it exits — even killed from Task Manager
+//! — Windows ends the backend and everything it started instead of leaving
+//! orphans for `kill_openakita_orphans`.  Backends started by the CLI
+//! `start` command stay out of it: the CLI exits once the backend is up.
+
+use serde::{Deserialize, Serialize};
+use std::process::{Child, Command};
+
+use crate::errors::CmdResult;
+use crate::{log_to_file, observer, read_state_file, write_state_file, wsl, STATE_FILE_LOCK};
+
+const MIN_MEMORY_MB: u64 = 256;
+/// `cpu.max` period, in microseconds.
+const CPU_PERIOD_US: u64 = 100_000;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResourceLimits {
+    /// Memory cap in MiB.
+    pub max_memory_mb: Option<u64>,
+    /// Share of the whole machine's CPU time, 1–100.
+    pub cpu_percent: Option<u32>,
+    /// Cores the backend may run on, 0-based.
+    pub cpu_affinity: Option<Vec<usize>>,
+}
+
+impl ResourceLimits {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.max_memory_mb.is_none() && self.cpu_percent.is_none() && self.cpu_affinity.is_none()
+    }
+
+    pub(crate) fn validate(&self, cpus: usize) -> Result<(), String> {
+        if self.max_memory_mb.is_some_and(|mb| mb < MIN_MEMORY_MB) {
+            return Err(format!(
+                "INVALID_ARGUMENT|maxMemoryMb 不能小于 {MIN_MEMORY_MB}"
+            ));
+        }
+        if self.cpu_percent.is_some_and(|p| p == 0 || p > 100) {
+            return Err("INVALID_ARGUMENT|cpuPercent 需在 1-100 之间".into());
+        }
+        if let Some(cores) = &self.cpu_affinity {
+            if cores.is_empty() {
+                return Err("INVALID_ARGUMENT|cpuAffinity 不能为空".into());
+            }
+            if let Some(c) = cores.iter().find(|c| **c >= cpus) {
+                return Err(format!(
+                    "INVALID_ARGUMENT|CPU {c} 不存在（共 {cpus} 个逻辑核）"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// `cpu.max` contents ("<quota> <period>") for `cpus` logical cores.
+    pub(crate) fn cpu_max(&self, cpus: usize) -> Option<String> {
+        let pct = u64::from(self.cpu_percent?);
+        let quota = (CPU_PERIOD_US * cpus as u64 * pct / 100).max(1000);
+        Some(format!("{quota} {CPU_PERIOD_US}"))
+    }
+
+    /// Affinity bitmask; cores past 63 are ignored.
+    pub(crate) fn affinity_mask(&self) -> Option<u64> {
+        let mask = self
+            .cpu_affinity
+            .as_ref()?
+            .iter()
+            .filter(|c| **c < 64)
+            .fold(0u64, |m, c| m | (1 << c));
+        (mask != 0).then_some(mask)
+    }
+}
+
+pub(crate) fn cpu_count() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+pub(crate) fn limits_of(workspace_id: &str) -> ResourceLimits {
+    read_state_file()
+        .workspaces
+        .into_iter()
+        .find(|w| w.id == workspace_id)
+        .and_then(|w| w.resource_limits)
+        .unwrap_or_default()
+}
+
+fn log_notes(workspace_id: &str, notes: &[String]) {
+    for note in notes {
+        log_to_file(&format!("[resource-limits] {workspace_id}: {note}"));
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod cgroup {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::ResourceLimits;
+
+    const ROOT: &str = "/sys/fs/cgroup";
+
+    /// Create (or reuse) the workspace's cgroup beside the desktop's own and
+    /// write its limits.  Returns the group's `cgroup.procs`.
+    pub(super) fn prepare(
+        workspace_id: &str,
+        limits: &ResourceLimits,
+        cpus: usize,
+    ) -> Result<PathBuf, String> {
+        if !std::path::Path::new(ROOT)
+            .join("cgroup.controllers")
+            .exists()
+        {
+            return Err("cgroup v2 not mounted".into());
+        }
+        let own = fs::read_to_string("/proc/self/cgroup").map_err(|e| e.to_string())?;
+        let rel = own
+            .lines()
+            .find_map(|l| l.strip_prefix("0::"))
+            .ok_or("no cgroup v2 entry in /proc/self/cgroup")?;
+        let current = PathBuf::from(ROOT).join(rel.trim().trim_start_matches('/'));
+        let parent = current.parent().ok_or("desktop runs in the root cgroup")?;
+        let name: String = workspace_id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let dir = parent.join(format!("openakita-{name}"));
+        if !dir.is_dir() {
+            fs::create_dir(&dir).map_err(|e| format!("create {}: {e}", dir.display()))?;
+        }
+        // 控制器可能已启用，也可能不允许由我们启用；以下面写 memory.max / cpu.max 的结果为准
+        for controller in ["+memory", "+cpu"] {
+            let _ = fs::write(parent.join("cgroup.subtree_control"), controller);
+        }
+        let memory = limits
+            .max_memory_mb
+            .map_or("max".to_string(), |mb| (mb * 1024 * 1024).to_string());
+        fs::write(dir.join("memory.max"), memory).map_err(|e| format!("memory.max: {e}"))?;
+        let cpu = limits
+            .cpu_max(cpus)
+            .unwrap_or_else(|| format!("max {}", super::CPU_PERIOD_US));
+        fs::write(dir.join("cpu.max"), cpu).map_err(|e| format!("cpu.max: {e}"))?;
+        Ok(dir.join("cgroup.procs"))
+    }
+}
+
+/// Install the limits that must be set before `exec` (cgroup membership,
+/// rlimit, affinity).  Call right before `spawn()`.
+pub(crate) fn prepare(cmd: &mut Command, workspace_id: &str) {
+    let limits = limits_of(workspace_id);
+    if limits.is_empty() || wsl::active_config(workspace_id).is_some() {
+        return;
+    }
+    #[cfg(unix)]
+    {
+        let mut notes = Vec::new();
+        #[allow(unused_mut)]
+        let mut cgroup_procs: Option<std::ffi::CString> = None;
+        #[cfg(target_os = "linux")]
+        if limits.max_memory_mb.is_some() || limits.cpu_percent.is_some() {
+            match cgroup::prepare(workspace_id, &limits, cpu_count()) {
+                Ok(procs) => {
+                    notes.push(format!("cgroup {}", procs.parent().unwrap().display()));
+                    cgroup_procs =
+                        std::ffi::CString::new(procs.to_string_lossy().into_owned()).ok();
+                }
+                Err(e) => {
+                    notes.push(format!("cgroup unavailable ({e})"));
+                    if limits.cpu_percent.is_some() {
+                        notes.push("cpuPercent not enforced".into());
+                    }
+                }
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            if limits.cpu_percent.is_some() {
+                notes.push("cpuPercent not supported on this platform".into());
+            }
+            if limits.cpu_affinity.is_some() {
+                notes.push("cpuAffinity not supported on this platform".into());
+            }
+        }
+        if let Some(mb) = limits.max_memory_mb {
+            if cgroup_procs.is_none() {
+                notes.push(format!("memory {mb} MiB via RLIMIT_AS"));
+            }
+        }
+        log_notes(workspace_id, &notes);
+        unix::pre_exec(
+            cmd,
+            cgroup_procs,
+            limits.max_memory_mb.map(|mb| mb * 1024 * 1024),
+            limits.affinity_mask(),
+        );
+    }
+    #[cfg(not(unix))]
+    let _ = cmd;
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::ffi::CString;
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+
+    pub(super) fn pre_exec(
+        cmd: &mut Command,
+        cgroup_procs: Option<CString>,
+        memory_bytes: Option<u64>,
+        #[allow(unused_variables)] affinity: Option<u64>,
+    ) {
+        // 只做 async-signal-safe 的系统调用：open / write / setrlimit / sched_setaffinity
+        unsafe {
+            cmd.pre_exec(move || {
+                let mut in_cgroup = false;
+                if let Some(path) = &cgroup_procs {
+                    let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+                    if fd >= 0 {
+                        // 向 cgroup.procs 写 "0" 表示迁移写入者自身
+                        in_cgroup = libc::write(fd, b"0".as_ptr().cast(), 1) == 1;
+                        libc::close(fd);
+                    }
+                }
+                if let (false, Some(bytes)) = (in_cgroup, memory_bytes) {
+                    let limit = libc::rlimit {
+                        rlim_cur: bytes as libc::rlim_t,
+                        rlim_max: bytes as libc::rlim_t,
+                    };
+                    libc::setrlimit(libc::RLIMIT_AS, &limit);
+                }
+                #[cfg(target_os = "linux")]
+                if let Some(mask) = affinity {
+                    let mut set: libc::cpu_set_t = std::mem::zeroed();
+                    for c in (0..64).filter(|c| mask & (1 << c) != 0) {
+                        libc::CPU_SET(c, &mut set);
+                    }
+                    libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set);
+                }
+                Ok(())
+            });
+        }
+    }
+}
+
+#[cfg(windows)]
+#[allow(non_snake_case, non_camel_case_types, clippy::upper_case_acronyms)]
+mod job {
+    use std::ffi::c_void;
+
+    pub type HANDLE = *mut c_void;
+    pub const JOB_OBJECT_EXTENDED_LIMIT_INFORMATION_CLASS: i32 = 9;
+    pub const JOB_OBJECT_CPU_RATE_CONTROL_INFORMATION_CLASS: i32 = 15;
+    pub const JOB_OBJECT_LIMIT_PROCESS_MEMORY: u32 = 0x0000_0100;
+    pub const JOB_OBJECT_LIMIT_BREAKAWAY_OK: u32 = 0x0000_0800;
+    pub const JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE: u32 = 0x0000_2000;
+    pub const CREATE_BREAKAWAY_FROM_JOB: u32 = 0x0100_0000;
+    pub const JOB_OBJECT_CPU_RATE_CONTROL_ENABLE: u32 = 0x1;
+    pub const JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP: u32 = 0x4;
+
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct JOBOBJECT_BASIC_LIMIT_INFORMATION {
+        pub PerProcessUserTimeLimit: i64,
+        pub PerJobUserTimeLimit: i64,
+        pub LimitFlags: u32,
+        pub MinimumWorkingSetSize: usize,
+        pub MaximumWorkingSetSize: usize,
+        pub ActiveProcessLimit: u32,
+        pub Affinity: usize,
+        pub PriorityClass: u32,
+        pub SchedulingClass: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct IO_COUNTERS {
+        pub ReadOperationCount: u64,
+        pub WriteOperationCount: u64,
+        pub OtherOperationCount: u64,
+        pub ReadTransferCount: u64,
+        pub WriteTransferCount: u64,
+        pub OtherTransferCount: u64,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct JOBOBJECT_EXTENDED_LIMIT_INFORMATION {
+        pub BasicLimitInformation: JOBOBJECT_BASIC_LIMIT_INFORMATION,
+        pub IoInfo: IO_COUNTERS,
+        pub ProcessMemoryLimit: usize,
+        pub JobMemoryLimit: usize,
+        pub PeakProcessMemoryUsed: usize,
+        pub PeakJobMemoryUsed: usize,
+    }
+
+    /// `CpuRate` arm of the union; the rate is in 1/100 of a percent.
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct JOBOBJECT_CPU_RATE_CONTROL_INFORMATION {
+        pub ControlFlags: u32,
+        pub CpuRate: u32,
+    }
+
+    extern "system" {
+        pub fn CreateJobObjectW(attributes: *const c_void, name: *const u16) -> HANDLE;
+        pub fn SetInformationJobObject(
+            job: HANDLE,
+            class: i32,
+            info: *const c_void,
+            len: u32,
+        ) -> i32;
+        pub fn AssignProcessToJobObject(job: HANDLE, process: HANDLE) -> i32;
+        pub fn IsProcessInJob(process: HANDLE, job: HANDLE, result: *mut i32) -> i32;
+        pub fn QueryInformationJobObject(
+            job: HANDLE,
+            class: i32,
+            info: *mut c_void,
+            len: u32,
+            ret_len: *mut u32,
+        ) -> i32;
+        pub fn GetCurrentProcess() -> HANDLE;
+        pub fn SetProcessAffinityMask(process: HANDLE, mask: usize) -> i32;
+        pub fn CloseHandle(handle: HANDLE) -> i32;
+    }
+
+    pub fn set_info<T>(job: HANDLE, class: i32, info: &T) -> bool {
+        unsafe {
+            SetInformationJobObject(
+                job,
+                class,
+                info as *const T as *const c_void,
+                std::mem::size_of::<T>() as u32,
+            ) != 0
+        }
+    }
+}
+
+/// Apply the limits that attach to a running process (Job Object and
+/// affinity on Windows).  Call right after `spawn()`.
+pub(crate) fn attach(child: &Child, workspace_id: &str) {
+    let limits = limits_of(workspace_id);
+    if limits.is_empty() || wsl::active_config(workspace_id).is_some() {
+        return;
+    }
+    #[cfg(windows)]
+    {
+        use job::*;
+        use std::os::windows::io::AsRawHandle;
+        let process = child.as_raw_handle() as HANDLE;
+        let mut notes = Vec::new();
+        if limits.max_memory_mb.is_some() || limits.cpu_percent.is_some() {
+            // 不设 KILL_ON_JOB_CLOSE：关掉句柄后 Job 随进程存在，限制继续生效
+            let job = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+            if job.is_null() {
+                notes.push(format!(
+                    "CreateJobObject failed: {}",
+                    std::io::Error::last_os_error()
+                ));
+            } else {
+                if let Some(mb) = limits.max_memory_mb {
+                    let mut info = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+                    info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_PROCESS_MEMORY;
+                    info.ProcessMemoryLimit = (mb * 1024 * 1024) as usize;
+                    let ok = set_info(job, JOB_OBJECT_EXTENDED_LIMIT_INFORMATION_CLASS, &info);
+                    notes.push(format!("memory {mb} MiB via Job Object: ok={ok}"));
+                }
+                if let Some(pct) = limits.cpu_percent {
+                    let info = JOBOBJECT_CPU_RATE_CONTROL_INFORMATION {
+                        ControlFlags: JOB_OBJECT_CPU_RATE_CONTROL_ENABLE
+                            | JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP,
+                        CpuRate: pct * 100,
+                    };
+                    let ok = set_info(job, JOB_OBJECT_CPU_RATE_CONTROL_INFORMATION_CLASS, &info);
+                    notes.push(format!("cpu {pct}% via Job Object: ok={ok}"));
+                }
+                if unsafe { AssignProcessToJobObject(job, process) } == 0 {
+                    notes.push(format!(
+                        "AssignProcessToJobObject failed: {}",
+                        std::io::Error::last_os_error()
+                    ));
+                }
+                unsafe { CloseHandle(job) };
+            }
+        }
+        if let Some(mask) = limits.affinity_mask() {
+            let ok = unsafe { SetProcessAffinityMask(process, mask as usize) } != 0;
+            notes.push(format!("affinity {mask:#x}: ok={ok}"));
+        }
+        log_notes(workspace_id, &notes);
+    }
+    #[cfg(not(windows))]
+    let _ = child;
+}
+
+/// `CREATE_BREAKAWAY_FROM_JOB` when Setup Center itself runs inside a job
+/// (some launchers and terminals) that lets children leave it, so the
+/// backend's own job is not nested under one we do not control.  Without
+/// `BREAKAWAY_OK` the flag would make `CreateProcess` fail; the backend
+/// then stays in that job and ours nests below it (Windows 8+).
+#[cfg(windows)]
+pub(crate) fn breakaway_flag() -> u32 {
+    use job::*;
+    let mut in_job = 0;
+    let queried =
+        unsafe { IsProcessInJob(GetCurrentProcess(), std::ptr::null_mut(), &mut in_job) } != 0;
+    if !queried || in_job == 0 {
+        return 0;
+    }
+    let mut info = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+    // job 传空 = 调用进程所在的 Job
+    let ok = unsafe {
+        QueryInformationJobObject(
+            std::ptr::null_mut(),
+            JOB_OBJECT_EXTENDED_LIMIT_INFORMATION_CLASS,
+            &mut info as *mut _ as *mut std::ffi::c_void,
+            std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+            std::ptr::null_mut(),
+        )
+    } != 0;
+    if ok && info.BasicLimitInformation.LimitFlags & JOB_OBJECT_LIMIT_BREAKAWAY_OK != 0 {
+        CREATE_BREAKAWAY_FROM_JOB
+    } else {
+        0
+    }
+}
+
+/// Kill-on-close jobs of the backends started by this process: (pid, job).
+#[cfg(windows)]
+static APP_JOBS: std::sync::Mutex<Vec<(u32, usize)>> = std::sync::Mutex::new(Vec::new());
+
+/// Whether a backend goes into the kill-on-close job: only when the desktop
+/// owns its lifetime.
+pub(crate) fn joins_app_job(owned_by_app: bool) -> bool {
+    owned_by_app && cfg!(windows)
+}
+
+/// Put the backend in a kill-on-close Job Object (Windows), so it and its
+/// children end with Setup Center.  Call right after `spawn()`, and only
+/// when [`joins_app_job`].
+pub(crate) fn tie_to_app(child: &Child, workspace_id: &str) {
+    #[cfg(windows)]
+    {
+        use job::*;
+        use std::os::windows::io::AsRawHandle;
+        let mut jobs = APP_JOBS.lock().unwrap_or_else(|e| e.into_inner());
+        // 已退出的后端：关掉它的 Job，顺带结束它留下的子进程
+        jobs.retain(|(pid, job)| {
+            let alive = crate::is_pid_running(*pid);
+            if !alive {
+                unsafe { CloseHandle(*job as HANDLE) };
+            }
+            alive
+        });
+        let job = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+        if job.is_null() {
+            let e = std::io::Error::last_os_error();
+            log_notes(
+                workspace_id,
+                &[format!("kill-on-close job: create failed: {e}")],
+            );
+            return;
+        }
+        let mut info = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+        info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        // 先设标志再放进程：失败时关句柄不会误杀后端
+        let error = if !set_info(job, JOB_OBJECT_EXTENDED_LIMIT_INFORMATION_CLASS, &info) {
+            Some(format!(
+                "set limit failed: {}",
+                std::io::Error::last_os_error()
+            ))
+        } else if unsafe { AssignProcessToJobObject(job, child.as_raw_handle() as HANDLE) } == 0 {
+            Some(format!(
+                "assign failed: {}",
+                std::io::Error::last_os_error()
+            ))
+        } else {
+            None
+        };
+        match error {
+            Some(e) => {
+                unsafe { CloseHandle(job) };
+                log_notes(workspace_id, &[format!("kill-on-close job: {e}")]);
+            }
+            None => jobs.push((child.id(), job as usize)),
+        }
+    }
+    #[cfg(not(windows))]
+    let _ = (child, workspace_id);
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceLimitsInfo {
+    pub workspace_id: String,
+    pub limits: ResourceLimits,
+    pub cpu_count: usize,
+    /// Limits this platform can enforce: "maxMemoryMb" | "cpuPercent" |
+    /// "cpuAffinity".
+    pub supported: Vec<&'static str>,
+}
+
+fn supported() -> Vec<&'static str> {
+    if cfg!(any(target_os = "linux", windows)) {
+        vec!["maxMemoryMb", "cpuPercent", "cpuAffinity"]
+    } else {
+        vec!["maxMemoryMb"]
+    }
+}
+
+#[tauri::command]
+pub fn get_resource_limits(workspace_id: String) -> ResourceLimitsInfo {
+    ResourceLimitsInfo {
+        limits: limits_of(&workspace_id),
+        workspace_id,
+        cpu_count: cpu_count(),
+        supported: supported(),
+    }
+}
+
+/// Store the workspace's limits; they apply from the next backend start.
+/// Empty limits remove them.
+#[tauri::command]
+pub fn set_resource_limits(workspace_id: String, limits: ResourceLimits) -> CmdResult<()> {
+    observer::ensure_writable(&workspace_id)?;
+    limits.validate(cpu_count())?;
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    let ws = state
+        .workspaces
+        .iter_mut()
+        .find(|w| w.id == workspace_id)
+        .ok_or_else(|| format!("NOT_FOUND|工作区不存在: {workspace_id}"))?;
+    ws.resource_limits = (!limits.is_empty()).then_some(limits);
+    write_state_file(&state).map_err(Into::into)
+}
@@ -0,0 +1,509 @@
+//! Retention policies for what `~/.openakita` accumulates.
+//!
+//! Nothing used to clean up after the app: logs, crash dumps, half-finished
+//! downloads and per-session debug output piled up until the disk filled.
+//! Each category below has a policy — `maxAgeDays` (0 = no age limit) and
+//! an optional `maxTotalMb` cap that removes the oldest items first:
+//!
+//! | category           | what                                                      |
+//! |--------------------|-----------------------------------------------------------|
+//! | `logs`             | `logs/`, `runtime/logs/`, `run/ops/`, each workspace's `logs/`, `data/logs/` |
+//! | `crashReports`     | `crashdumps/`, panic reports in `run/`                    |
+//! | `browserProfiles`  | cache folders of the agent's Chrome profile (logins stay) |
+//! | `tempDownloads`    | `.part` model downloads, the HTTP cache, `data/temp/`     |
+//! | `sessionArtifacts` | `uploads/` and each workspace's `data/llm_debug`, traces, tool overflow, media |
+//!
+//! `preview_cleanup(workspace_id)` reports what a run would reclaim per
+//! category; `run_cleanup` does it.  With `scheduled` on, a background
+//! thread runs every `intervalHours` over all workspaces.  Items touched
+//! within the last hour are never removed (an active log, a running
+//! download), and the browser caches are skipped while any backend runs.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::errors::CmdResult;
+use crate::{
+    atomic_write, crashdumps_dir, home_dir, is_pid_file_valid, log_to_file, models, net,
+    now_epoch_secs, now_ms, openakita_root_dir, operation_logs_dir, read_pid_file, read_state_file,
+    run_dir, runtime_logs_dir, setup_logs_dir, spawn_blocking_result, workspace_dir,
+    workspaces_dir, write_state_file, PANIC_REPORT_PREFIX, STATE_FILE_LOCK,
+};
+
+/// Anything modified more recently than this is in use.
+const MIN_AGE_SECS: u64 = 3600;
+const DAY_SECS: u64 = 24 * 3600;
+const SCHEDULER_FIRST_DELAY: Duration = Duration::from_secs(10 * 60);
+const SCHEDULER_TICK: Duration = Duration::from_secs(3600);
+const LAST_RUN_FILE: &str = "retention-last.json";
+/// Chrome profile folders that only hold caches.
+const BROWSER_CACHE_DIRS: &[&str] = &[
+    "Cache",
+    "Code Cache",
+    "GPUCache",
+    "DawnCache",
+    "GrShaderCache",
+    "ShaderCache",
+    "Default/Cache",
+    "Default/Code Cache",
+    "Default/GPUCache",
+    "Default/DawnCache",
+    "Default/Service Worker/CacheStorage",
+];
+const SESSION_ARTIFACT_DIRS: &[&str] = &[
+    "llm_debug",
+    "react_traces",
+    "traces",
+    "tool_overflow",
+    "delegation_logs",
+    "failure_analysis",
+    "media",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CleanupCategory {
+    Logs,
+    CrashReports,
+    BrowserProfiles,
+    TempDownloads,
+    SessionArtifacts,
+}
+
+impl CleanupCategory {
+    pub(crate) const ALL: [CleanupCategory; 5] = [
+        CleanupCategory::Logs,
+        CleanupCategory::CrashReports,
+        CleanupCategory::BrowserProfiles,
+        CleanupCategory::TempDownloads,
+        CleanupCategory::SessionArtifacts,
+    ];
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionPolicy {
+    pub enabled: bool,
+    /// Remove items older than this; 0 = no age limit.
+    pub max_age_days: u32,
+    /// Keep the category under this size, oldest items first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_total_mb: Option<u64>,
+}
+
+impl RetentionPolicy {
+    const fn new(max_age_days: u32, max_total_mb: Option<u64>) -> Self {
+        Self {
+            enabled: true,
+            max_age_days,
+            max_total_mb,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetentionConfig {
+    /// Run automatically every `interval_hours`.
+    pub scheduled: bool,
+    pub interval_hours: u32,
+    pub logs: RetentionPolicy,
+    pub crash_reports: RetentionPolicy,
+    pub browser_profiles: RetentionPolicy,
+    pub temp_downloads: RetentionPolicy,
+    pub session_artifacts: RetentionPolicy,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            scheduled: false,
+            interval_hours: 24,
+            logs: RetentionPolicy::new(30, None),
+            crash_reports: RetentionPolicy::new(30, None),
+            browser_profiles: RetentionPolicy::new(0, Some(512)),
+            temp_downloads: RetentionPolicy::new(7, None),
+            session_artifacts: RetentionPolicy::new(60, None),
+        }
+    }
+}
+
+impl RetentionConfig {
+    pub(crate) fn policy(&self, category: CleanupCategory) -> &RetentionPolicy {
+        match category {
+            CleanupCategory::Logs => &self.logs,
+            CleanupCategory::CrashReports => &self.crash_reports,
+            CleanupCategory::BrowserProfiles => &self.browser_profiles,
+            CleanupCategory::TempDownloads => &self.temp_downloads,
+            CleanupCategory::SessionArtifacts => &self.session_artifacts,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Candidate {
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// Newest modification inside, unix seconds.
+    pub modified: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryUsage {
+    pub category: CleanupCategory,
+    pub enabled: bool,
+    pub items: usize,
+    pub bytes: u64,
+    pub reclaimable_items: usize,
+    pub reclaimable_bytes: u64,
+    /// Why nothing is reclaimable right now, e.g. a backend is running.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupReport {
+    pub at_ms: u64,
+    /// "preview" | "manual" | "scheduled"
+    pub trigger: String,
+    pub workspaces: Vec<String>,
+    pub categories: Vec<CategoryUsage>,
+    pub reclaimed_bytes: u64,
+    #[serde(default)]
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionStatus {
+    pub config: RetentionConfig,
+    pub last_run: Option<CleanupReport>,
+}
+
+/// Which items a policy removes, as indices into `items`.
+pub(crate) fn select_for_removal(
+    items: &[Candidate],
+    policy: &RetentionPolicy,
+    now: u64,
+) -> Vec<usize> {
+    if !policy.enabled {
+        return Vec::new();
+    }
+    let settled = |c: &Candidate| c.modified + MIN_AGE_SECS <= now;
+    let max_age = u64::from(policy.max_age_days) * DAY_SECS;
+    let mut remove: Vec<usize> = (0..items.len())
+        .filter(|&i| max_age > 0 && settled(&items[i]) && items[i].modified + max_age < now)
+        .collect();
+    if let Some(cap) = policy.max_total_mb.map(|mb| mb * 1024 * 1024) {
+        let mut total: u64 = items.iter().map(|c| c.bytes).sum::<u64>()
+            - remove.iter().map(|&i| items[i].bytes).sum::<u64>();
+        let mut oldest: Vec<usize> = (0..items.len())
+            .filter(|i| !remove.contains(i) && settled(&items[*i]))
+            .collect();
+        oldest.sort_by_key(|&i| items[i].modified);
+        for i in oldest {
+            if total <= cap {
+                break;
+            }
+            total -= items[i].bytes;
+            remove.push(i);
+        }
+    }
+    remove.sort_unstable();
+    remove
+}
+
+fn mtime_secs(meta: &fs::Metadata) -> u64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Size and newest modification of a file or directory tree.  Symlinks
+/// are counted as links, never followed.
+pub(crate) fn measure(path: &Path) -> Option<Candidate> {
+    let meta = fs::symlink_metadata(path).ok()?;
+    let mut c = Candidate {
+        path: path.to_path_buf(),
+        bytes: if meta.is_dir() { 0 } else { meta.len() },
+        modified: mtime_secs(&meta),
+    };
+    if meta.is_dir() {
+        for entry in fs::read_dir(path).into_iter().flatten().flatten() {
+            if let Some(sub) = measure(&entry.path()) {
+                c.bytes += sub.bytes;
+                c.modified = c.modified.max(sub.modified);
+            }
+        }
+    }
+    Some(c)
+}
+
+/// Direct children of `dir` whose file name passes `keep`.
+fn children(dir: &Path, keep: impl Fn(&str) -> bool) -> Vec<Candidate> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| keep(&e.file_name().to_string_lossy()))
+        .filter_map(|e| measure(&e.path()))
+        .collect()
+}
+
+/// Files named `*<suffix>` anywhere below `dir` (a few levels deep).
+fn files_with_suffix(dir: &Path, suffix: &str, depth: usize, out: &mut Vec<Candidate>) {
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let Ok(ft) = entry.file_type() else { continue };
+        if ft.is_dir() && depth > 0 {
+            files_with_suffix(&path, suffix, depth - 1, out);
+        } else if ft.is_file() && entry.file_name().to_string_lossy().ends_with(suffix) {
+            out.extend(measure(&path));
+        }
+    }
+}
+
+/// The dedicated Chrome profile `chrome_finder.get_openakita_chrome_profile`
+/// uses.
+fn browser_profile_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home_dir().map(|h| h.join("Library").join("Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home_dir().map(|h| h.join(".local").join("share")))
+    }?;
+    Some(base.join("OpenAkita").join("ChromeProfile"))
+}
+
+pub(crate) fn collect(category: CleanupCategory, ws_dirs: &[PathBuf]) -> Vec<Candidate> {
+    let root = openakita_root_dir();
+    let all = |_: &str| true;
+    let mut out = Vec::new();
+    match category {
+        CleanupCategory::Logs => {
+            out.extend(children(&setup_logs_dir(), all));
+            out.extend(children(&runtime_logs_dir(), all));
+            out.extend(children(&operation_logs_dir(), all));
+            for ws in ws_dirs {
+                out.extend(children(&ws.join("logs"), all));
+                out.extend(children(&ws.join("data").join("logs"), all));
+            }
+        }
+        CleanupCategory::CrashReports => {
+            out.extend(children(&crashdumps_dir(), all));
+            out.extend(children(&run_dir(), |n| n.starts_with(PANIC_REPORT_PREFIX)));
+        }
+        CleanupCategory::BrowserProfiles => {
+            if let Some(profile) = browser_profile_dir() {
+                out.extend(
+                    BROWSER_CACHE_DIRS
+                        .iter()
+                        .filter_map(|d| measure(&profile.join(d))),
+                );
+            }
+        }
+        CleanupCategory::TempDownloads => {
+            files_with_suffix(&models::models_root(), ".part", 3, &mut out);
+            out.extend(children(&net::http_cache_dir(), all));
+            for ws in ws_dirs {
+                out.extend(children(&ws.join("data").join("temp"), all));
+            }
+        }
+        CleanupCategory::SessionArtifacts => {
+            out.extend(children(&root.join("uploads"), all));
+            for ws in ws_dirs {
+                for d in SESSION_ARTIFACT_DIRS {
+                    out.extend(children(&ws.join("data").join(d), all));
+                }
+            }
+        }
+    }
+    out
+}
+
+fn any_backend_running() -> bool {
+    fs::read_dir(workspaces_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .any(|e| {
+            read_pid_file(&e.file_name().to_string_lossy())
+                .as_ref()
+                .is_some_and(is_pid_file_valid)
+        })
+}
+
+pub(crate) fn config() -> RetentionConfig {
+    read_state_file().retention.unwrap_or_default()
+}
+
+fn last_run_file() -> PathBuf {
+    run_dir().join(LAST_RUN_FILE)
+}
+
+fn read_last_run() -> Option<CleanupReport> {
+    serde_json::from_str(&fs::read_to_string(last_run_file()).ok()?).ok()
+}
+
+fn workspace_dirs(workspace_id: Option<&str>) -> Result<Vec<(String, PathBuf)>, String> {
+    if let Some(id) = workspace_id {
+        let dir = workspace_dir(id);
+        if id.is_empty() || !dir.is_dir() {
+            return Err(format!("NOT_FOUND|工作区不存在: {id}"));
+        }
+        return Ok(vec![(id.to_string(), dir)]);
+    }
+    let mut all: Vec<(String, PathBuf)> = fs::read_dir(workspaces_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .map(|e| (e.file_name().to_string_lossy().to_string(), e.path()))
+        .collect();
+    all.sort();
+    Ok(all)
+}
+
+/// Measure (and with `apply`, remove) what the policies select.
+fn run(
+    workspace_id: Option<&str>,
+    categories: Option<&[CleanupCategory]>,
+    trigger: &str,
+) -> Result<CleanupReport, String> {
+    let cfg = config();
+    let workspaces = workspace_dirs(workspace_id)?;
+    let ws_dirs: Vec<PathBuf> = workspaces.iter().map(|(_, d)| d.clone()).collect();
+    let apply = trigger != "preview";
+    let now = now_epoch_secs();
+    let backend_running = any_backend_running();
+    let mut report = CleanupReport {
+        at_ms: now_ms(),
+        trigger: trigger.to_string(),
+        workspaces: workspaces.into_iter().map(|(id, _)| id).collect(),
+        categories: Vec::new(),
+        reclaimed_bytes: 0,
+        errors: Vec::new(),
+    };
+    for category in CleanupCategory::ALL {
+        if categories.is_some_and(|c| !c.contains(&category)) {
+            continue;
+        }
+        let policy = cfg.policy(category);
+        let items = collect(category, &ws_dirs);
+        let skipped = (category == CleanupCategory::BrowserProfiles && backend_running)
+            .then(|| "后端运行中，浏览器正在使用这些缓存".to_string());
+        let selected = if skipped.is_some() {
+            Vec::new()
+        } else {
+            select_for_removal(&items, policy, now)
+        };
+        let mut usage = CategoryUsage {
+            category,
+            enabled: policy.enabled,
+            items: items.len(),
+            bytes: items.iter().map(|c| c.bytes).sum(),
+            reclaimable_items: 0,
+            reclaimable_bytes: 0,
+            skipped,
+        };
+        for i in selected {
+            let item = &items[i];
+            if apply {
+                let removed = if item.path.is_dir() {
+                    fs::remove_dir_all(&item.path)
+                } else {
+                    fs::remove_file(&item.path)
+                };
+                if let Err(e) = removed {
+                    report.errors.push(format!("{}: {e}", item.path.display()));
+                    continue;
+                }
+            }
+            usage.reclaimable_items += 1;
+            usage.reclaimable_bytes += item.bytes;
+        }
+        report.categories.push(usage);
+    }
+    if apply {
+        report.reclaimed_bytes = report.categories.iter().map(|c| c.reclaimable_bytes).sum();
+        log_to_file(&format!(
+            "[retention] {trigger} cleanup reclaimed {} bytes ({} error(s))",
+            report.reclaimed_bytes,
+            report.errors.len()
+        ));
+        if let Ok(json) = serde_json::to_string_pretty(&report) {
+            let _ = atomic_write(last_run_file(), json);
+        }
+    }
+    Ok(report)
+}
+
+/// Background runs for `scheduled`; checks hourly whether a run is due.
+pub(crate) fn spawn_scheduler() {
+    std::thread::spawn(|| {
+        std::thread::sleep(SCHEDULER_FIRST_DELAY);
+        loop {
+            let cfg = config();
+            let due = cfg.scheduled && {
+                let interval_ms = u64::from(cfg.interval_hours.max(1)) * 3600 * 1000;
+                read_last_run().map_or(0, |r| r.at_ms) + interval_ms <= now_ms()
+            };
+            if due {
+                if let Err(e) = run(None, None, "scheduled") {
+                    log_to_file(&format!("[retention] scheduled cleanup failed: {e}"));
+                }
+            }
+            std::thread::sleep(SCHEDULER_TICK);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_retention_config() -> RetentionStatus {
+    RetentionStatus {
+        config: config(),
+        last_run: read_last_run(),
+    }
+}
+
+#[tauri::command]
+pub fn set_retention_config(config: RetentionConfig) -> CmdResult<()> {
+    if config.interval_hours == 0 {
+        return Err("INVALID_ARGUMENT|intervalHours 必须大于 0".into());
+    }
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    state.retention = Some(config);
+    write_state_file(&state).map_err(Into::into)
+}
+
+/// Space per category and what the current policies would reclaim, for
+/// `workspace_id` plus the shared folders.
+#[tauri::command]
+pub async fn preview_cleanup(workspace_id: String) -> CmdResult<CleanupReport> {
+    spawn_blocking_result(move || run(Some(&workspace_id), None, "preview"))
+        .await
+        .map_err(Into::into)
+}
+
+/// Apply the policies now — to one workspace, or to all when
+/// `workspace_id` is omitted — optionally limited to `categories`.
+#[tauri::command]
+pub async fn run_cleanup(
+    workspace_id: Option<String>,
+    categories: Option<Vec<CleanupCategory>>,
+) -> CmdResult<CleanupReport> {
+    spawn_blocking_result(move || run(workspace_id.as_deref(), categories.as_deref(), "manual"))
+        .await
+        .map_err(Into::into)
+}
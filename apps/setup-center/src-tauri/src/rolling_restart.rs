@@ -0,0 +1,298 @@
+//! Blue-green ("rolling") backend restart.
+//!
+//! A plain restart stops the backend before starting the next one, so IM
+//! bots are offline for the minutes Python needs to import and initialise.
+//! `openakita_service_rolling_restart(workspace_id)` instead:
+//!
+//! 1. starts the new backend on a standby port just above the workspace's
+//!    `API_PORT` (pinned with `OPENAKITA_API_PORT_PIN`, so the backend's own
+//!    `.env` load cannot move it back);
+//! 2. waits for its `/api/health` while the old backend keeps serving — if
+//!    the new one exits or never gets healthy it is dropped and nothing
+//!    else changes;
+//! 3. switches: the desktop's forwarder on `API_PORT` is pointed at the new
+//!    backend and the old backend is stopped.
+//!
+//! The first rolling restart has no forwarder yet: the old backend owns
+//! `API_PORT`, so it is stopped first and the forwarder binds the port as
+//! soon as it is free — the gap is the old backend's shutdown, not the new
+//! one's start.  Later rolling restarts only swap the forwarder's target.
+//! The forwarder copies raw TCP on 127.0.0.1 (HTTP, SSE and WebSockets
+//! alike) and goes away with the next stop or plain start, when the backend
+//! binds `API_PORT` itself again.  While both backends run, both connect
+//! their IM channels; that overlap lasts as long as the old one's shutdown.
+
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::io;
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{
+    backend_command, backend_ipc, build_service_status, check_port_available,
+    is_backend_http_healthy, is_pid_running, launch_env, log_capture, log_to_file, now_epoch_secs,
+    observer, read_workspace_api_port, resource_limits, service_pid_file, stop_pid_with_report,
+    wait_for_port_free, workspace_dir, write_last_clean_shutdown_marker, write_pid_file, wsl,
+    ManagedProcess, ServiceStatus, BACKEND_LIFECYCLE_LOCK, MANAGED_CHILD,
+};
+
+/// Standby ports are looked for in `API_PORT + 1 ..= API_PORT + STANDBY_RANGE`.
+const STANDBY_RANGE: u16 = 50;
+/// How long the new backend may take to answer `/api/health`.
+const WARMUP_TIMEOUT: Duration = Duration::from_secs(300);
+const POLL: Duration = Duration::from_millis(500);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Forwarder {
+    public_port: u16,
+    target: Arc<AtomicU16>,
+    stop: Arc<AtomicBool>,
+}
+
+static FORWARDERS: Lazy<Mutex<HashMap<String, Forwarder>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// First port above `public` that is free and not `current` (the port the
+/// running backend listens on).
+pub(crate) fn pick_standby_port(
+    public: u16,
+    current: u16,
+    is_free: impl Fn(u16) -> bool,
+) -> Option<u16> {
+    (1..=STANDBY_RANGE)
+        .filter_map(|i| public.checked_add(i))
+        .find(|p| *p != current && is_free(*p))
+}
+
+/// Port the workspace's backend listens on when it sits behind the
+/// forwarder (i.e. differs from `API_PORT`).
+pub(crate) fn backend_port(workspace_id: &str) -> Option<u16> {
+    let forwarders = FORWARDERS.lock().ok()?;
+    let f = forwarders.get(workspace_id)?;
+    Some(f.target.load(Ordering::SeqCst))
+}
+
+fn pipe(mut from: TcpStream, mut to: TcpStream) {
+    let _ = io::copy(&mut from, &mut to);
+    let _ = to.shutdown(Shutdown::Write);
+}
+
+fn forward(client: TcpStream, port: u16) {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    // 连不上后端就直接断开客户端，客户端看到的和后端没起来一样
+    let Ok(upstream) = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) else {
+        return;
+    };
+    let _ = client.set_nodelay(true);
+    let _ = upstream.set_nodelay(true);
+    let (Ok(client_read), Ok(upstream_write)) = (client.try_clone(), upstream.try_clone()) else {
+        return;
+    };
+    thread::spawn(move || pipe(client_read, upstream_write));
+    pipe(upstream, client);
+}
+
+fn serve(listener: TcpListener, target: Arc<AtomicU16>, stop: Arc<AtomicBool>) {
+    while !stop.load(Ordering::SeqCst) {
+        match listener.accept() {
+            Ok((client, _)) => {
+                // 部分平台上 accept 出来的连接会继承监听端的非阻塞模式
+                let _ = client.set_nonblocking(false);
+                let port = target.load(Ordering::SeqCst);
+                thread::spawn(move || forward(client, port));
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(20));
+            }
+            Err(e) => {
+                log_to_file(&format!("[rolling-restart] accept failed: {e}"));
+                thread::sleep(Duration::from_millis(200));
+            }
+        }
+    }
+}
+
+fn start_forwarder(workspace_id: &str, public_port: u16, target_port: u16) -> io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", public_port))?;
+    listener.set_nonblocking(true)?;
+    let target = Arc::new(AtomicU16::new(target_port));
+    let stop = Arc::new(AtomicBool::new(false));
+    let (t, s) = (target.clone(), stop.clone());
+    thread::spawn(move || serve(listener, t, s));
+    if let Ok(mut forwarders) = FORWARDERS.lock() {
+        forwarders.insert(
+            workspace_id.to_string(),
+            Forwarder {
+                public_port,
+                target,
+                stop,
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Point an existing forwarder at `port`.  False if there is none.
+fn retarget(workspace_id: &str, public_port: u16, port: u16) -> bool {
+    let Ok(forwarders) = FORWARDERS.lock() else {
+        return false;
+    };
+    match forwarders.get(workspace_id) {
+        Some(f) if f.public_port == public_port => {
+            f.target.store(port, Ordering::SeqCst);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Close the workspace's forwarder, if any, releasing `API_PORT`.
+/// Connections already open keep going until either side closes them.
+pub(crate) fn stop_proxy(workspace_id: &str) {
+    let removed = FORWARDERS
+        .lock()
+        .ok()
+        .and_then(|mut f| f.remove(workspace_id));
+    if let Some(f) = removed {
+        f.stop.store(true, Ordering::SeqCst);
+        let target = f.target.load(Ordering::SeqCst);
+        backend_ipc::remove_backend_socket(target);
+        // 等 accept 循环退出并释放监听端口
+        thread::sleep(Duration::from_millis(100));
+        log_to_file(&format!(
+            "[rolling-restart] {workspace_id}: forwarder :{} → :{target} closed",
+            f.public_port
+        ));
+    }
+}
+
+/// Run a blue-green restart of the workspace's desktop-started backend.
+pub(crate) fn restart(venv_dir: String, workspace_id: String) -> Result<ServiceStatus, String> {
+    observer::ensure_writable(&workspace_id)?;
+    let _lifecycle_guard = BACKEND_LIFECYCLE_LOCK.lock().unwrap();
+    if wsl::active_config(&workspace_id).is_some() {
+        return Err("INVALID_ARGUMENT|WSL 工作区不支持滚动重启，请使用普通重启".into());
+    }
+    let (old_pid, old_started_at) = {
+        let guard = MANAGED_CHILD.lock().unwrap();
+        match guard.as_ref() {
+            Some(mp) if mp.workspace_id == workspace_id && is_pid_running(mp.pid) => {
+                (mp.pid, mp.started_at)
+            }
+            _ => {
+                return Err(
+                    "BACKEND_NOT_RUNNING|滚动重启需要一个由桌面端启动且正在运行的后端".into(),
+                )
+            }
+        }
+    };
+    let public = read_workspace_api_port(&workspace_id).unwrap_or(18900);
+    let current = backend_port(&workspace_id).unwrap_or(public);
+    let standby = pick_standby_port(public, current, check_port_available).ok_or_else(|| {
+        format!(
+            "端口 {}-{} 均被占用，找不到备用端口",
+            public.saturating_add(1),
+            public.saturating_add(STANDBY_RANGE)
+        )
+    })?;
+
+    // ── 1. 新实例在备用端口启动 ──
+    let ws_dir = workspace_dir(&workspace_id);
+    let (mut cmd, log_path) =
+        backend_command(&venv_dir, &workspace_id, &ws_dir, standby, true, true)?;
+    let launch = launch_env::capture(&cmd, &workspace_id, &ws_dir, Path::new(&venv_dir));
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("spawn openakita serve failed: {e}"))?;
+    let pid = child.id();
+    resource_limits::tie_to_app(&child, &workspace_id);
+    resource_limits::attach(&child, &workspace_id);
+    log_capture::attach(&mut child, &workspace_id, &log_path, true);
+    let started_at = now_epoch_secs();
+    log_to_file(&format!(
+        "[rolling-restart] {workspace_id}: standby pid={pid} on :{standby}, old pid={old_pid} on :{current}"
+    ));
+
+    // ── 2. 预热：旧后端继续服务，直到新实例 health 正常 ──
+    let warmup = Instant::now();
+    loop {
+        if let Ok(Some(status)) = child.try_wait() {
+            backend_ipc::remove_backend_socket(standby);
+            return Err(format!(
+                "新后端（PID={pid}）预热时退出（{status}），旧后端继续运行。\n请查看服务日志：{}",
+                log_path.display()
+            ));
+        }
+        if is_backend_http_healthy(Some(standby)) {
+            break;
+        }
+        if warmup.elapsed() >= WARMUP_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            backend_ipc::remove_backend_socket(standby);
+            return Err(format!(
+                "BACKEND_TIMEOUT|新后端 {}s 内未就绪，已放弃，旧后端继续运行",
+                WARMUP_TIMEOUT.as_secs()
+            ));
+        }
+        thread::sleep(POLL);
+    }
+    let warmup_ms = warmup.elapsed().as_millis();
+
+    // ── 3. 切换 ──
+    let old = MANAGED_CHILD.lock().unwrap().replace(ManagedProcess {
+        child,
+        workspace_id: workspace_id.clone(),
+        pid,
+        started_at,
+    });
+    write_pid_file(&workspace_id, pid, "tauri")?;
+    launch_env::record(launch, pid, started_at);
+    launch_env::mark_success(&workspace_id, pid);
+
+    let switch_started = Instant::now();
+    let retargeted = retarget(&workspace_id, public, standby);
+    // 没有转发时旧后端自己占着公开端口：先停它，端口一释放就接管
+    let report = stop_pid_with_report(&workspace_id, old_pid, Some(current));
+    if let Some(mut old) = old.filter(|mp| mp.pid == old_pid) {
+        if is_pid_running(old_pid) {
+            let _ = old.child.kill();
+        }
+        let _ = old.child.wait();
+    }
+    if report.outcome().unwrap_or(false) && !is_pid_running(old_pid) {
+        write_last_clean_shutdown_marker(
+            &workspace_id,
+            old_pid,
+            old_started_at.saturating_mul(1000),
+        );
+    }
+    backend_ipc::remove_backend_socket(current);
+    if !retargeted {
+        let _ = wait_for_port_free(public, 10_000);
+        start_forwarder(&workspace_id, public, standby).map_err(|e| {
+            format!("新后端已在端口 {standby} 运行（PID={pid}），但无法接管端口 {public}: {e}")
+        })?;
+    }
+    log_to_file(&format!(
+        "[rolling-restart] {workspace_id}: switched :{public} → :{standby} in {}ms (warmup {warmup_ms}ms)",
+        switch_started.elapsed().as_millis()
+    ));
+
+    let mut status = build_service_status(
+        &workspace_id,
+        true,
+        Some(pid),
+        service_pid_file(&workspace_id)
+            .to_string_lossy()
+            .to_string(),
+        "tauri",
+        true,
+    );
+    status.stop_report = Some(report);
+    Ok(status)
+}
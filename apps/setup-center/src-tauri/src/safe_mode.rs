@@ -0,0 +1,59 @@
+//! Safe start: a minimal backend for repairing a config that crash-loops.
+//!
+//! A broken MCP server, skill or endpoint entry can make the backend die
+//! on every start, and then there is no UI or API left to fix it from.
+//! `openakita_service_start_safe(workspace_id)` starts the workspace's
+//! backend with `OPENAKITA_SAFE_MODE=1`, which the backend honours over
+//! whatever `.env` says:
+//!
+//! * MCP off (`MCP_ENABLED=false`);
+//! * no external skills (system skills stay);
+//! * only the highest-priority endpoint of `llm_endpoints.json`;
+//! * `LOG_LEVEL=DEBUG`.
+//!
+//! Nothing on disk changes, so what the user edits meanwhile is kept.  The
+//! mode sticks to the workspace for restarts made by the heartbeat, the
+//! crash watchdog or a rolling restart; a regular start or restart leaves
+//! it.  `ServiceStatus.safeMode` tells the UI.
+
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use std::process::Command;
+use std::sync::Mutex;
+
+use crate::log_to_file;
+
+pub(crate) const SAFE_MODE_ENV: &str = "OPENAKITA_SAFE_MODE";
+
+static ACTIVE: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+pub(crate) fn is_active(workspace_id: &str) -> bool {
+    ACTIVE.lock().unwrap().contains(workspace_id)
+}
+
+/// Start the workspace's next backends in safe mode (`true`) or normally.
+pub(crate) fn set(workspace_id: &str, on: bool) {
+    let mut active = ACTIVE.lock().unwrap();
+    let changed = if on {
+        active.insert(workspace_id.to_string())
+    } else {
+        active.remove(workspace_id)
+    };
+    if changed {
+        log_to_file(&format!(
+            "[safe_mode] {workspace_id}: {}",
+            if on { "on" } else { "off" }
+        ));
+    }
+}
+
+/// Backend spawn hook (`backend_command`).
+pub(crate) fn apply(cmd: &mut Command, workspace_id: &str) {
+    if !is_active(workspace_id) {
+        return;
+    }
+    // 后端读完 .env 后会再按 OPENAKITA_SAFE_MODE 盖一次，这两项只是让最早的日志也对得上
+    cmd.env(SAFE_MODE_ENV, "1");
+    cmd.env("MCP_ENABLED", "false");
+    cmd.env("LOG_LEVEL", "DEBUG");
+}
@@ -0,0 +1,389 @@
+//! Scheduled jobs of a workspace's backend, editable from the desktop.
+//!
+//! The backend's scheduler keeps its jobs in `data/scheduler/tasks.json`
+//! and exposes CRUD under `/api/scheduler/tasks`.  The built-in
+//! `system_daily_memory` job runs at 03:00; on machines that sleep or are
+//! busy at that time it keeps failing, and moving it used to mean editing
+//! the JSON by hand.
+//!
+//! * `list_schedules` reads `tasks.json` directly, so it works with the
+//!   backend stopped;
+//! * `upsert_schedule` validates the job here — cron expressions with the
+//!   same syntax the backend's `CronTrigger` accepts (`*`, `N`, `N-M`,
+//!   `*/S`, `N-M/S`, lists), but with ranges checked — and only then
+//!   applies it through the running backend, which owns the file while up;
+//! * `pause_schedule` disables (or re-enables) a job.
+//!
+//! Applying needs the backend: `BACKEND_NOT_RUNNING` otherwise.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::errors::CmdResult;
+use crate::{
+    backend_ipc, is_pid_file_valid, log_to_file, observer, read_pid_file, read_workspace_api_port,
+    workspace_dir,
+};
+
+const APPLY_TIMEOUT: Duration = Duration::from_secs(15);
+/// (name, min, max) of the five cron fields.  Weekday 0 = Sunday, as in
+/// the backend; 7 is not accepted there.
+const CRON_FIELDS: [(&str, u32, u32); 5] = [
+    ("分钟", 0, 59),
+    ("小时", 0, 23),
+    ("日", 1, 31),
+    ("月", 1, 12),
+    ("星期", 0, 6),
+];
+
+fn default_true() -> bool {
+    true
+}
+
+/// One job as stored in `tasks.json` / returned by the backend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct ScheduleJob {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// "once" | "interval" | "cron"
+    pub trigger_type: String,
+    /// `{"cron": "0 3 * * *"}`, `{"interval": 30}` (minutes), `{"run_at": ...}`
+    #[serde(default)]
+    pub trigger_config: Value,
+    #[serde(default)]
+    pub task_type: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub status: String,
+    /// System jobs can be paused but not deleted.
+    #[serde(default = "default_true")]
+    pub deletable: bool,
+    #[serde(default)]
+    pub task_source: String,
+    #[serde(default)]
+    pub last_run: Option<String>,
+    #[serde(default)]
+    pub next_run: Option<String>,
+    #[serde(default)]
+    pub run_count: u64,
+    #[serde(default)]
+    pub fail_count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleList {
+    pub jobs: Vec<ScheduleJob>,
+    /// Whether changes can be applied right now.
+    pub backend_running: bool,
+}
+
+/// A new job (no `id`) or the fields to change on an existing one.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleJobInput {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub task_type: Option<String>,
+    #[serde(default)]
+    pub trigger_type: Option<String>,
+    #[serde(default)]
+    pub trigger_config: Option<Value>,
+    #[serde(default)]
+    pub prompt: Option<String>,
+    #[serde(default)]
+    pub reminder_message: Option<String>,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+}
+
+fn cron_value(s: &str, name: &str, min: u32, max: u32) -> Result<u32, String> {
+    let v: u32 = s
+        .parse()
+        .map_err(|_| format!("{name}字段的“{s}”不是数字"))?;
+    if v < min || v > max {
+        return Err(format!("{name}字段的 {v} 超出范围 {min}-{max}"));
+    }
+    Ok(v)
+}
+
+fn cron_range(s: &str, name: &str, min: u32, max: u32) -> Result<(), String> {
+    match s.split_once('-') {
+        Some((a, b)) => {
+            let (a, b) = (
+                cron_value(a, name, min, max)?,
+                cron_value(b, name, min, max)?,
+            );
+            if a > b {
+                return Err(format!("{name}字段的范围 {a}-{b} 起点大于终点"));
+            }
+            Ok(())
+        }
+        None => cron_value(s, name, min, max).map(drop),
+    }
+}
+
+/// Check a 5-field cron expression (minute hour day month weekday).
+pub(crate) fn validate_cron(expr: &str) -> Result<(), String> {
+    let parts: Vec<&str> = expr.split_whitespace().collect();
+    if parts.len() != CRON_FIELDS.len() {
+        return Err(format!(
+            "cron 表达式需要 5 个字段（分 时 日 月 周），“{expr}”有 {} 个",
+            parts.len()
+        ));
+    }
+    for (field, (name, min, max)) in parts.iter().zip(CRON_FIELDS) {
+        for part in field.split(',') {
+            if part.is_empty() {
+                return Err(format!("{name}字段“{field}”里有空项"));
+            }
+            match part.split_once('/') {
+                Some((base, step)) => {
+                    let step: u32 = step
+                        .parse()
+                        .ok()
+                        .filter(|s| *s > 0)
+                        .ok_or_else(|| format!("{name}字段的步长“{step}”必须是正整数"))?;
+                    if step > max {
+                        return Err(format!("{name}字段的步长 {step} 大于 {max}"));
+                    }
+                    if base != "*" {
+                        cron_range(base, name, min, max)?;
+                    }
+                }
+                None if part == "*" => {}
+                None => cron_range(part, name, min, max)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check `trigger_config` against `trigger_type` the way the backend's
+/// triggers read it.
+pub(crate) fn validate_trigger(trigger_type: &str, config: &Value) -> Result<(), String> {
+    match trigger_type {
+        "cron" => {
+            let expr = config
+                .get("cron")
+                .and_then(Value::as_str)
+                .ok_or("cron 任务需要 triggerConfig.cron")?;
+            validate_cron(expr)
+        }
+        "interval" => {
+            // 与后端一致：简写 `interval`（分钟）覆盖 interval_minutes
+            let minutes_key = if config.get("interval").is_some() {
+                "interval"
+            } else {
+                "interval_minutes"
+            };
+            let total: f64 = [
+                ("interval_seconds", 1.0),
+                (minutes_key, 60.0),
+                ("interval_hours", 3600.0),
+                ("interval_days", 86400.0),
+            ]
+            .iter()
+            .filter_map(|(k, secs)| config.get(*k).and_then(Value::as_f64).map(|v| v * secs))
+            .sum();
+            if total <= 0.0 {
+                return Err("interval 任务需要大于 0 的间隔（interval 单位为分钟）".into());
+            }
+            Ok(())
+        }
+        "once" => match config.get("run_at") {
+            Some(Value::String(s)) if !s.trim().is_empty() => Ok(()),
+            Some(Value::Number(_)) => Ok(()),
+            _ => Err("once 任务需要 triggerConfig.run_at".into()),
+        },
+        other => Err(format!("未知的触发类型: {other}")),
+    }
+}
+
+/// Validate `job` and turn it into the backend's request body (snake_case,
+/// only the fields that are set).
+pub(crate) fn request_body(job: &ScheduleJobInput) -> Result<Value, String> {
+    let create = job.id.is_none();
+    let mut body = Map::new();
+    if let Some(name) = &job.name {
+        if name.trim().is_empty() {
+            return Err("任务名称不能为空".into());
+        }
+        body.insert("name".into(), json!(name.trim()));
+    } else if create {
+        return Err("新建任务需要名称".into());
+    }
+    match (&job.trigger_type, &job.trigger_config) {
+        (Some(t), Some(c)) => {
+            validate_trigger(t, c)?;
+            body.insert("trigger_type".into(), json!(t));
+            body.insert("trigger_config".into(), c.clone());
+        }
+        (None, None) if !create => {}
+        _ => return Err("triggerType 与 triggerConfig 需要一起提供".into()),
+    }
+    if let Some(t) = &job.task_type {
+        if !matches!(t.as_str(), "reminder" | "task") {
+            return Err(format!("未知的任务类型: {t}"));
+        }
+        body.insert("task_type".into(), json!(t));
+    }
+    for (key, v) in [
+        ("prompt", &job.prompt),
+        ("reminder_message", &job.reminder_message),
+    ] {
+        if let Some(v) = v {
+            body.insert(key.into(), json!(v));
+        }
+    }
+    if let Some(e) = job.enabled {
+        body.insert("enabled".into(), json!(e));
+    }
+    Ok(Value::Object(body))
+}
+
+/// Job ids go into the request path.
+fn job_path(id: &str) -> Result<String, String> {
+    let ok = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !ok {
+        return Err(format!("INVALID_ARGUMENT|无效的任务 id: {id}"));
+    }
+    Ok(format!("/api/scheduler/tasks/{id}"))
+}
+
+fn tasks_file(ws_dir: &Path) -> PathBuf {
+    ws_dir.join("data").join("scheduler").join("tasks.json")
+}
+
+pub(crate) fn parse_tasks(content: &str) -> Result<Vec<ScheduleJob>, String> {
+    let items: Vec<Value> =
+        serde_json::from_str(content).map_err(|e| format!("tasks.json 解析失败: {e}"))?;
+    // 单条损坏的任务不影响其余的展示（后端加载时同样跳过）
+    Ok(items
+        .into_iter()
+        .filter_map(|v| serde_json::from_value(v).ok())
+        .collect())
+}
+
+fn backend_running(workspace_id: &str) -> bool {
+    read_pid_file(workspace_id)
+        .as_ref()
+        .is_some_and(is_pid_file_valid)
+}
+
+fn check_workspace(workspace_id: &str) -> Result<PathBuf, String> {
+    let dir = workspace_dir(workspace_id);
+    if workspace_id.is_empty() || !dir.is_dir() {
+        return Err(format!("NOT_FOUND|工作区不存在: {workspace_id}"));
+    }
+    Ok(dir)
+}
+
+/// Send one change to the running backend and return the job it reports.
+async fn apply(
+    workspace_id: &str,
+    method: &str,
+    path: &str,
+    body: &Value,
+) -> Result<ScheduleJob, String> {
+    check_workspace(workspace_id)?;
+    if !backend_running(workspace_id) {
+        return Err(format!(
+            "BACKEND_NOT_RUNNING|请先启动工作区 {workspace_id} 的后端再保存定时任务"
+        ));
+    }
+    let port = read_workspace_api_port(workspace_id).unwrap_or(18900);
+    let headers = vec![("Content-Type".to_string(), "application/json".to_string())];
+    let payload = body.to_string();
+    let resp = backend_ipc::backend_request(
+        port,
+        method,
+        path,
+        &headers,
+        Some(payload.as_bytes()),
+        APPLY_TIMEOUT,
+    )
+    .await?;
+    let value = resp.json().unwrap_or(Value::Null);
+    if !resp.is_success() {
+        let msg = value
+            .get("error")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| resp.text());
+        let code = match resp.status {
+            404 => "NOT_FOUND",
+            422 => "INVALID_ARGUMENT",
+            503 => "BACKEND_NOT_RUNNING",
+            _ => "UNKNOWN",
+        };
+        return Err(format!("{code}|{msg}"));
+    }
+    let job: ScheduleJob = value
+        .get("task")
+        .cloned()
+        .and_then(|t| serde_json::from_value(t).ok())
+        .ok_or_else(|| format!("unexpected scheduler response: {}", resp.text()))?;
+    log_to_file(&format!(
+        "[schedules] {method} ws={workspace_id} job={} trigger={} {}",
+        job.id, job.trigger_type, job.trigger_config
+    ));
+    Ok(job)
+}
+
+/// The workspace's scheduled jobs, read from disk.
+#[tauri::command]
+pub fn list_schedules(workspace_id: String) -> CmdResult<ScheduleList> {
+    let dir = check_workspace(&workspace_id)?;
+    let jobs = match fs::read_to_string(tasks_file(&dir)) {
+        Ok(content) => parse_tasks(&content)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(format!("读取 tasks.json 失败: {e}").into()),
+    };
+    Ok(ScheduleList {
+        jobs,
+        backend_running: backend_running(&workspace_id),
+    })
+}
+
+/// Create a job (`job.id` unset) or change an existing one.
+#[tauri::command]
+pub async fn upsert_schedule(
+    workspace_id: String,
+    job: ScheduleJobInput,
+) -> CmdResult<ScheduleJob> {
+    observer::ensure_writable(&workspace_id)?;
+    let body = request_body(&job).map_err(|e| format!("INVALID_ARGUMENT|{e}"))?;
+    let result = match &job.id {
+        Some(id) => apply(&workspace_id, "PUT", &job_path(id)?, &body).await,
+        None => apply(&workspace_id, "POST", "/api/scheduler/tasks", &body).await,
+    };
+    result.map_err(Into::into)
+}
+
+/// Pause a job, or resume it with `paused: false`.
+#[tauri::command]
+pub async fn pause_schedule(
+    workspace_id: String,
+    id: String,
+    paused: Option<bool>,
+) -> CmdResult<ScheduleJob> {
+    observer::ensure_writable(&workspace_id)?;
+    let body = json!({ "enabled": !paused.unwrap_or(true) });
+    apply(&workspace_id, "PUT", &job_path(&id)?, &body)
+        .await
+        .map_err(Into::into)
+}
@@ -0,0 +1,288 @@
+//! Conversation history from the backend, with a local cache.
+//!
+//! The history view talks to `/api/sessions` and
+//! `/api/sessions/{id}/history`; while the backend restarts (an update, a
+//! config change, a crash-restart) those calls fail and the view went
+//! blank.  `list_sessions` / `get_session` go through the desktop instead:
+//! every successful answer is written to
+//! `~/.openakita/cache/sessions/<workspace>/`, and when the backend does not
+//! answer, a copy younger than [`CACHE_MAX_AGE`] is served with
+//! `cached: true`.  Only the most recent [`MAX_CACHED_TRANSCRIPTS`]
+//! transcripts per workspace are kept.
+//!
+//! [`transcripts_for_feedback`] hands the feedback report the exact
+//! messages of the conversations the user picked, redacted — fresh when the
+//! backend is up, from the cache (of any age) otherwise.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::errors::CmdResult;
+use crate::{
+    atomic_write, backend_ipc, data_dirs, log_to_file, net, now_ms, redact, spawn_blocking_result,
+    workspace_dir,
+};
+
+/// How long a cached answer may stand in for an unreachable backend.
+const CACHE_MAX_AGE: Duration = Duration::from_secs(10 * 60);
+const MAX_CACHED_TRANSCRIPTS: usize = 50;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(8);
+const DEFAULT_PAGE_SIZE: usize = 30;
+const MAX_PAGE_SIZE: usize = 200;
+/// The backend's `_MAX_HISTORY_LIMIT`.
+const HISTORY_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionPage {
+    /// Items as the backend serializes them (`id`, `title`, `lastMessage`,
+    /// `timestamp`, `messageCount`, ...).
+    pub sessions: Vec<Value>,
+    pub page: usize,
+    pub page_size: usize,
+    pub total: usize,
+    /// Served from the local cache because the backend did not answer.
+    pub cached: bool,
+    pub fetched_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionTranscript {
+    pub id: String,
+    /// `/api/sessions/{id}/history` as returned (`messages`, `total`, ...).
+    pub history: Value,
+    pub cached: bool,
+    pub fetched_at_ms: u64,
+}
+
+/// Conversation ids go into the request path; the backend's own
+/// `_validate_id` is stricter still.
+pub(crate) fn valid_session_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 256
+        && id != "."
+        && id != ".."
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':' | '@'))
+}
+
+/// Page `page` (0-based) of `all`.
+pub(crate) fn paginate(all: &[Value], page: usize, page_size: usize) -> Vec<Value> {
+    all.iter()
+        .skip(page.saturating_mul(page_size))
+        .take(page_size)
+        .cloned()
+        .collect()
+}
+
+/// Cached body if written within `max_age`; `None` = any age.
+pub(crate) fn read_cached(
+    content: &str,
+    now: u64,
+    max_age: Option<Duration>,
+) -> Option<(u64, Value)> {
+    let v: Value = serde_json::from_str(content).ok()?;
+    let at = v.get("fetchedAtMs")?.as_u64()?;
+    let fresh = max_age.is_none_or(|a| now.saturating_sub(at) <= a.as_millis() as u64);
+    fresh.then(|| (at, v.get("body").cloned().unwrap_or(Value::Null)))
+}
+
+fn cache_dir(workspace_id: &str) -> PathBuf {
+    data_dirs::cache_root(workspace_id)
+        .join("sessions")
+        .join(workspace_id)
+}
+
+fn list_cache_file(workspace_id: &str) -> PathBuf {
+    cache_dir(workspace_id).join("list.json")
+}
+
+fn transcript_cache_file(workspace_id: &str, id: &str) -> PathBuf {
+    cache_dir(workspace_id).join(format!("t-{}.json", net::cache_key_hash(id)))
+}
+
+fn write_cache(path: PathBuf, body: &Value, at: u64) {
+    let doc = serde_json::json!({ "fetchedAtMs": at, "body": body });
+    if let Err(e) = atomic_write(&path, doc.to_string()) {
+        log_to_file(&format!(
+            "[sessions] cache write {} failed: {e}",
+            path.display()
+        ));
+    }
+}
+
+/// Keep only the newest transcripts.
+fn prune_transcripts(workspace_id: &str) {
+    let mut files: Vec<(std::time::SystemTime, PathBuf)> = fs::read_dir(cache_dir(workspace_id))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with("t-"))
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .collect();
+    if files.len() <= MAX_CACHED_TRANSCRIPTS {
+        return;
+    }
+    files.sort_by_key(|f| std::cmp::Reverse(f.0));
+    for (_, path) in files.into_iter().skip(MAX_CACHED_TRANSCRIPTS) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+fn fetch(workspace_id: &str, path: &str) -> Result<Value, String> {
+    let resp = backend_ipc::workspace_request_blocking(
+        workspace_id,
+        "GET",
+        path,
+        &[],
+        None,
+        REQUEST_TIMEOUT,
+    )?;
+    if !resp.is_success() {
+        return Err(format!("HTTP {} from {path}", resp.status));
+    }
+    resp.json()
+}
+
+/// Fetch `path`, caching a good answer in `cache`; fall back to the cache
+/// (`max_age`) when the backend is unavailable.
+fn fetch_or_cached(
+    workspace_id: &str,
+    path: &str,
+    cache: PathBuf,
+    max_age: Option<Duration>,
+    usable: impl Fn(&Value) -> bool,
+) -> Result<(Value, bool, u64), String> {
+    let err = match fetch(workspace_id, path) {
+        Ok(body) if usable(&body) => {
+            let at = now_ms();
+            write_cache(cache, &body, at);
+            return Ok((body, false, at));
+        }
+        Ok(_) => "后端尚未加载完会话".to_string(),
+        Err(e) => e,
+    };
+    let cached = fs::read_to_string(&cache)
+        .ok()
+        .and_then(|c| read_cached(&c, now_ms(), max_age));
+    match cached {
+        Some((at, body)) => Ok((body, true, at)),
+        None => Err(format!(
+            "BACKEND_NOT_RUNNING|无法获取会话（{err}），也没有可用的缓存"
+        )),
+    }
+}
+
+fn check_workspace(workspace_id: &str) -> Result<(), String> {
+    if workspace_id.is_empty() || !workspace_dir(workspace_id).is_dir() {
+        return Err(format!("NOT_FOUND|工作区不存在: {workspace_id}"));
+    }
+    Ok(())
+}
+
+fn load_transcript(
+    workspace_id: &str,
+    id: &str,
+    max_age: Option<Duration>,
+) -> Result<SessionTranscript, String> {
+    if !valid_session_id(id) {
+        return Err(format!("INVALID_ARGUMENT|无效的会话 id: {id}"));
+    }
+    let (history, cached, at) = fetch_or_cached(
+        workspace_id,
+        &format!("/api/sessions/{id}/history?limit={HISTORY_LIMIT}"),
+        transcript_cache_file(workspace_id, id),
+        max_age,
+        |v| v.get("messages").is_some(),
+    )?;
+    if !cached {
+        prune_transcripts(workspace_id);
+    }
+    Ok(SessionTranscript {
+        id: id.to_string(),
+        history,
+        cached,
+        fetched_at_ms: at,
+    })
+}
+
+/// `(zip name, redacted JSON)` per conversation for the feedback report.
+/// Conversations that can be neither fetched nor found in the cache are
+/// skipped.
+pub(crate) fn transcripts_for_feedback(
+    workspace_id: &str,
+    ids: &[String],
+) -> Vec<(String, Vec<u8>)> {
+    ids.iter()
+        .filter_map(|id| match load_transcript(workspace_id, id, None) {
+            Ok(t) => {
+                let json = serde_json::to_string_pretty(&t).unwrap_or_default();
+                let text = redact::redact_for_workspace(&json, workspace_id);
+                Some((
+                    format!("sessions/{}.json", net::cache_key_hash(id)),
+                    text.into_bytes(),
+                ))
+            }
+            Err(e) => {
+                log_to_file(&format!("[sessions] feedback transcript {id} skipped: {e}"));
+                None
+            }
+        })
+        .collect()
+}
+
+/// Conversations of the workspace's desktop channel, newest first, in
+/// pages of `page_size` (default 30).
+#[tauri::command]
+pub async fn list_sessions(
+    workspace_id: String,
+    page: Option<usize>,
+    page_size: Option<usize>,
+) -> CmdResult<SessionPage> {
+    spawn_blocking_result(move || {
+        check_workspace(&workspace_id)?;
+        let (body, cached, at) = fetch_or_cached(
+            &workspace_id,
+            "/api/sessions",
+            list_cache_file(&workspace_id),
+            Some(CACHE_MAX_AGE),
+            // 后端刚启动、会话还没加载完时返回 ready=false 的空列表，不能覆盖缓存
+            |v| v.get("ready").and_then(Value::as_bool) != Some(false),
+        )?;
+        let all = body
+            .get("sessions")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        let page = page.unwrap_or(0);
+        let page_size = page_size
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+        Ok(SessionPage {
+            sessions: paginate(&all, page, page_size),
+            page,
+            page_size,
+            total: all.len(),
+            cached,
+            fetched_at_ms: at,
+        })
+    })
+    .await
+    .map_err(Into::into)
+}
+
+/// One conversation's messages (the latest 200).
+#[tauri::command]
+pub async fn get_session(workspace_id: String, id: String) -> CmdResult<SessionTranscript> {
+    spawn_blocking_result(move || {
+        check_workspace(&workspace_id)?;
+        load_transcript(&workspace_id, &id, Some(CACHE_MAX_AGE))
+    })
+    .await
+    .map_err(Into::into)
+}
@@ -0,0 +1,547 @@
+//! How a backend process is asked to stop.
+//!
+//! `graceful_stop_pid` used to know one way: `POST /api/shutdown` on the
+//! workspace's port, then kill.  Older backends expose the route elsewhere,
+//! some users turn the HTTP API off, and on Windows the kill that followed
+//! never gave Python a chance to flush anything.  [`stop_pid`] now walks a
+//! chain of [`StopMethod`]s until the process is gone:
+//!
+//! * `api` — POST each of `api_paths` until one answers 2xx, then wait;
+//! * `signal` — SIGTERM on Unix; on Windows CTRL_BREAK_EVENT delivered to
+//!   the backend's console (it runs in its own process group);
+//! * `kill` — SIGKILL / TerminateProcess.
+//!
+//! The default chain is api → signal → kill, so CTRL_BREAK is the graceful
+//! path on Windows whenever the API is absent; `signal_first` tries the
+//! signal before the API.  Every step waits up to its own timeout (the
+//! shutdown request itself included), and the [`StopReport`] returned (and
+//! attached to `service_stop`'s status) lists what each step did and how
+//! long it took.
+//!
+//! A backend flushing memory consolidation state can need longer than the
+//! global timeouts.  A workspace's `shutdownTimeoutSecs` in `state.json`,
+//! or else `SHUTDOWN_TIMEOUT_SECS` in its `.env`, replaces the `api` and
+//! `signal` timeouts for that workspace's backend ([`config_for`]); service
+//! stop, restart and the tray quit use it.  The tray quit stops every
+//! workspace at once ([`stop_all`]) under a single deadline — the longest
+//! of their graceful budgets — and force-kills whatever is left then.
+//!
+//! On Unix the backend is started as the leader of its own process group
+//! (`process_group(0)`), and signals go to the whole group, so browsers and
+//! MCP stdio servers it spawned stop with it.  Members still alive once the
+//! backend is gone get SIGTERM, then SIGKILL after [`GROUP_GRACE`].
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::errors::CmdResult;
+use crate::{
+    backend_ipc, env_overlay, is_pid_running, log_to_file, observer, read_state_file,
+    write_state_file, STATE_FILE_LOCK,
+};
+
+pub(crate) const TIMEOUT_ENV_KEY: &str = "SHUTDOWN_TIMEOUT_SECS";
+const MAX_TIMEOUT_SECS: u64 = 600;
+const POLL: Duration = Duration::from_millis(200);
+/// How long leftover members of the backend's process group get between
+/// SIGTERM and SIGKILL.
+#[cfg(unix)]
+const GROUP_GRACE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StopMethod {
+    Api,
+    Signal,
+    Kill,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ShutdownConfig {
+    pub chain: Vec<StopMethod>,
+    /// Try `signal` before `api`.
+    pub signal_first: bool,
+    /// Shutdown routes, tried in order (older backends use other paths).
+    pub api_paths: Vec<String>,
+    pub api_timeout_secs: u64,
+    pub signal_timeout_secs: u64,
+    pub kill_timeout_secs: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            chain: vec![StopMethod::Api, StopMethod::Signal, StopMethod::Kill],
+            signal_first: false,
+            api_paths: vec!["/api/shutdown".into()],
+            api_timeout_secs: 10,
+            signal_timeout_secs: 5,
+            kill_timeout_secs: 3,
+        }
+    }
+}
+
+impl ShutdownConfig {
+    /// Steps in the order they run.
+    pub(crate) fn steps(&self) -> Vec<StopMethod> {
+        let mut steps = self.chain.clone();
+        if self.signal_first {
+            if let (Some(api), Some(signal)) = (
+                steps.iter().position(|m| *m == StopMethod::Api),
+                steps.iter().position(|m| *m == StopMethod::Signal),
+            ) {
+                if signal > api {
+                    let s = steps.remove(signal);
+                    steps.insert(api, s);
+                }
+            }
+        }
+        steps
+    }
+
+    pub(crate) fn timeout(&self, method: StopMethod) -> Duration {
+        Duration::from_secs(match method {
+            StopMethod::Api => self.api_timeout_secs,
+            StopMethod::Signal => self.signal_timeout_secs,
+            StopMethod::Kill => self.kill_timeout_secs,
+        })
+    }
+
+    /// This config with `api` and `signal` waiting `secs` each.
+    pub(crate) fn with_graceful_timeout(mut self, secs: Option<u64>) -> Self {
+        if let Some(secs) = secs {
+            self.api_timeout_secs = secs;
+            self.signal_timeout_secs = secs;
+        }
+        self
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self.chain.is_empty() {
+            return Err("INVALID_ARGUMENT|chain 不能为空".into());
+        }
+        for (i, m) in self.chain.iter().enumerate() {
+            if self.chain[..i].contains(m) {
+                return Err(format!("INVALID_ARGUMENT|chain 中 {m:?} 重复"));
+            }
+        }
+        if self.chain.contains(&StopMethod::Api)
+            && !self.api_paths.iter().any(|p| p.starts_with('/'))
+        {
+            return Err("INVALID_ARGUMENT|apiPaths 需要至少一个以 / 开头的路径".into());
+        }
+        if self.chain.iter().any(|m| self.timeout(*m).is_zero()) {
+            return Err("INVALID_ARGUMENT|各步骤的超时必须大于 0".into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StopStep {
+    pub method: StopMethod,
+    /// The process was gone when the step finished.
+    pub exited: bool,
+    pub timeout_ms: u64,
+    pub elapsed_ms: u64,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StopReport {
+    pub pid: u32,
+    /// Stopped by `api` or `signal`, i.e. without a kill.
+    pub graceful: bool,
+    pub stopped_by: Option<StopMethod>,
+    pub steps: Vec<StopStep>,
+    pub elapsed_ms: u64,
+}
+
+impl StopReport {
+    /// `Ok(graceful)` when the process is gone (or was never running), what
+    /// each step reported otherwise.
+    pub(crate) fn outcome(&self) -> Result<bool, String> {
+        if self.stopped_by.is_some() || self.steps.is_empty() {
+            return Ok(self.graceful);
+        }
+        let tried: Vec<String> = self
+            .steps
+            .iter()
+            .map(|s| format!("{:?}: {}", s.method, s.detail))
+            .collect();
+        Err(format!(
+            "pid {} still running after {}",
+            self.pid,
+            tried.join("; ")
+        ))
+    }
+}
+
+pub(crate) fn config() -> ShutdownConfig {
+    read_state_file().shutdown.unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShutdownTimeout {
+    pub workspace_id: String,
+    pub timeout_secs: Option<u64>,
+    /// "state" | "env"; None when the global timeouts apply.
+    pub source: Option<&'static str>,
+}
+
+/// `SHUTDOWN_TIMEOUT_SECS` as written in `.env`; out of range counts as unset.
+pub(crate) fn parse_timeout(value: &str) -> Option<u64> {
+    value
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|s| (1..=MAX_TIMEOUT_SECS).contains(s))
+}
+
+fn workspace_timeout(workspace_id: &str) -> ShutdownTimeout {
+    let from_state = read_state_file()
+        .workspaces
+        .into_iter()
+        .find(|w| w.id == workspace_id)
+        .and_then(|w| w.shutdown_timeout_secs)
+        .filter(|s| *s > 0);
+    let (timeout_secs, source) = match from_state {
+        Some(secs) => (Some(secs), Some("state")),
+        None => match env_overlay::workspace_var(workspace_id, TIMEOUT_ENV_KEY)
+            .as_deref()
+            .and_then(parse_timeout)
+        {
+            Some(secs) => (Some(secs), Some("env")),
+            None => (None, None),
+        },
+    };
+    ShutdownTimeout {
+        workspace_id: workspace_id.to_string(),
+        timeout_secs,
+        source,
+    }
+}
+
+/// The chain for `workspace_id`'s backend: the global config with the
+/// workspace's shutdown timeout, if it has one.
+pub(crate) fn config_for(workspace_id: &str) -> ShutdownConfig {
+    config().with_graceful_timeout(workspace_timeout(workspace_id).timeout_secs)
+}
+
+pub(crate) fn wait_exit(pid: u32, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if !is_pid_running(pid) {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(POLL);
+    }
+}
+
+/// POST the first shutdown route that answers 2xx within `budget`.
+fn request_api_shutdown(port: u16, paths: &[String], budget: Duration) -> Result<String, String> {
+    let deadline = Instant::now() + budget;
+    let mut tried = Vec::new();
+    for path in paths.iter().filter(|p| p.starts_with('/')) {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        if timeout.is_zero() {
+            tried.push(format!("{path}: timed out"));
+            break;
+        }
+        match backend_ipc::backend_request_blocking(port, "POST", path, &[], None, timeout) {
+            Ok(r) if r.is_success() => return Ok(format!("{path} → {}", r.status)),
+            Ok(r) => tried.push(format!("{path} → {}", r.status)),
+            Err(e) => tried.push(format!("{path}: {e}")),
+        }
+    }
+    Err(tried.join(", "))
+}
+
+#[cfg(windows)]
+#[allow(non_snake_case)]
+mod console {
+    extern "system" {
+        pub fn AttachConsole(dwProcessId: u32) -> i32;
+        pub fn FreeConsole() -> i32;
+        pub fn GenerateConsoleCtrlEvent(dwCtrlEvent: u32, dwProcessGroupId: u32) -> i32;
+        pub fn SetConsoleCtrlHandler(handler: *const std::ffi::c_void, add: i32) -> i32;
+    }
+    pub const CTRL_BREAK_EVENT: u32 = 1;
+}
+
+/// Send `sig` to `pid`'s process group when `pid` leads one (backends are
+/// started that way), else to `pid` alone.
+#[cfg(unix)]
+pub(crate) fn signal_tree(pid: u32, sig: libc::c_int) -> std::io::Result<()> {
+    let pid = pid as libc::pid_t;
+    // 旧版本启动的后端不是组长，只能发给它自己
+    let target = if unsafe { libc::getpgid(pid) } == pid {
+        -pid
+    } else {
+        pid
+    };
+    if unsafe { libc::kill(target, sig) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(unix)]
+fn group_alive(pgid: u32) -> bool {
+    unsafe { libc::kill(-(pgid as libc::pid_t), 0) == 0 }
+}
+
+/// SIGTERM → SIGKILL for what is left of the group `pgid` after its leader
+/// exited.  `None` when nothing was left.
+#[cfg(unix)]
+fn reap_group(pgid: u32) -> Option<String> {
+    if !group_alive(pgid) {
+        return None;
+    }
+    let group = -(pgid as libc::pid_t);
+    unsafe { libc::kill(group, libc::SIGTERM) };
+    let deadline = Instant::now() + GROUP_GRACE;
+    while Instant::now() < deadline {
+        std::thread::sleep(POLL);
+        if !group_alive(pgid) {
+            return Some("leftover group members exited on SIGTERM".into());
+        }
+    }
+    unsafe { libc::kill(group, libc::SIGKILL) };
+    Some("leftover group members sent SIGKILL".into())
+}
+
+/// Ask `pid` to exit: SIGTERM, or CTRL_BREAK on Windows.  The backend is
+/// started with `CREATE_NEW_PROCESS_GROUP`, so its pid is also its group id.
+fn send_terminate(pid: u32) -> Result<String, String> {
+    #[cfg(windows)]
+    {
+        use console::*;
+        // 借用目标进程的控制台发送 CTRL_BREAK；自身先忽略 Ctrl 事件，以免一并退出
+        unsafe {
+            FreeConsole();
+            if AttachConsole(pid) == 0 {
+                return Err(format!(
+                    "AttachConsole failed: {}",
+                    std::io::Error::last_os_error()
+                ));
+            }
+            SetConsoleCtrlHandler(std::ptr::null(), 1);
+            let ok = GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid);
+            let err = std::io::Error::last_os_error();
+            FreeConsole();
+            // 等事件分发完再恢复，否则本进程也可能收到
+            std::thread::sleep(Duration::from_millis(50));
+            SetConsoleCtrlHandler(std::ptr::null(), 0);
+            if ok == 0 {
+                return Err(format!("GenerateConsoleCtrlEvent failed: {err}"));
+            }
+        }
+        Ok("CTRL_BREAK_EVENT sent".into())
+    }
+    #[cfg(not(windows))]
+    {
+        signal_tree(pid, libc::SIGTERM).map_err(|e| format!("kill -TERM failed: {e}"))?;
+        Ok("SIGTERM sent".into())
+    }
+}
+
+fn force_kill(pid: u32) -> Result<String, String> {
+    #[cfg(windows)]
+    {
+        crate::kill_pid(pid).map(|_| "TerminateProcess".into())
+    }
+    #[cfg(not(windows))]
+    {
+        if let Err(e) = signal_tree(pid, libc::SIGKILL) {
+            if is_pid_running(pid) {
+                return Err(format!("kill -KILL failed: {e}"));
+            }
+        }
+        Ok("SIGKILL sent".into())
+    }
+}
+
+/// Stop `pid` (the backend on `port`) with `config`'s chain.
+pub(crate) fn stop_pid(pid: u32, port: u16, config: &ShutdownConfig) -> StopReport {
+    let started = Instant::now();
+    let mut report = StopReport {
+        pid,
+        graceful: false,
+        stopped_by: None,
+        steps: Vec::new(),
+        elapsed_ms: 0,
+    };
+    if !is_pid_running(pid) {
+        report.graceful = true;
+        return report;
+    }
+    // 组长退出后 getpgid 就查不到了，先记下
+    #[cfg(unix)]
+    let leads_group = unsafe { libc::getpgid(pid as libc::pid_t) } == pid as libc::pid_t;
+    for method in config.steps() {
+        let step_started = Instant::now();
+        let timeout = config.timeout(method);
+        let sent = match method {
+            StopMethod::Api => request_api_shutdown(port, &config.api_paths, timeout),
+            StopMethod::Signal => send_terminate(pid),
+            StopMethod::Kill => force_kill(pid),
+        };
+        // 请求没送达就不必等满超时，直接进入下一步
+        let exited = match &sent {
+            Ok(_) => wait_exit(pid, timeout.saturating_sub(step_started.elapsed())),
+            Err(_) => !is_pid_running(pid),
+        };
+        let detail = match sent {
+            Ok(d) if exited => d,
+            Ok(d) => format!("{d}; still running after {}s", timeout.as_secs()),
+            Err(e) => e,
+        };
+        log_to_file(&format!(
+            "[quit] {method:?} pid={pid} port={port} exited={exited} elapsed_ms={} total_elapsed_ms={} {detail}",
+            step_started.elapsed().as_millis(),
+            started.elapsed().as_millis()
+        ));
+        report.steps.push(StopStep {
+            method,
+            exited,
+            timeout_ms: timeout.as_millis() as u64,
+            elapsed_ms: step_started.elapsed().as_millis() as u64,
+            detail,
+        });
+        if exited {
+            report.stopped_by = Some(method);
+            report.graceful = method != StopMethod::Kill;
+            break;
+        }
+    }
+    #[cfg(unix)]
+    if leads_group && report.stopped_by.is_some() {
+        if let Some(detail) = reap_group(pid) {
+            log_to_file(&format!("[quit] group pid={pid}: {detail}"));
+        }
+    }
+    report.elapsed_ms = started.elapsed().as_millis() as u64;
+    report
+}
+
+/// A backend for [`stop_all`].
+pub(crate) struct StopTarget {
+    pub workspace_id: String,
+    pub pid: u32,
+    pub port: u16,
+}
+
+/// How long the tray quit gives all backends together: the longest
+/// graceful budget (every step but `kill`) among their chains, capped at
+/// [`MAX_TIMEOUT_SECS`].
+pub(crate) fn quit_deadline(configs: &[ShutdownConfig]) -> Duration {
+    configs
+        .iter()
+        .map(|c| {
+            c.steps()
+                .into_iter()
+                .filter(|m| *m != StopMethod::Kill)
+                .map(|m| c.timeout(m))
+                .sum::<Duration>()
+        })
+        .max()
+        .unwrap_or_default()
+        .min(Duration::from_secs(MAX_TIMEOUT_SECS))
+}
+
+/// Stop all `targets` at once, each with its workspace's chain minus the
+/// `kill` step, under one [`quit_deadline`]; whatever is still running
+/// then is force-killed.  Returns the force-killed pids.
+pub(crate) fn stop_all(targets: &[StopTarget]) -> Vec<u32> {
+    let configs: Vec<ShutdownConfig> = targets
+        .iter()
+        .map(|t| {
+            let mut config = config_for(&t.workspace_id);
+            config.chain.retain(|m| *m != StopMethod::Kill);
+            config
+        })
+        .collect();
+    let deadline = Instant::now() + quit_deadline(&configs);
+    let (tx, rx) = std::sync::mpsc::channel();
+    for (target, config) in targets.iter().zip(configs) {
+        let (pid, port, tx) = (target.pid, target.port, tx.clone());
+        // 超过截止时间的线程不再等，强杀后它的 wait_exit 很快返回
+        std::thread::spawn(move || {
+            let _ = tx.send(stop_pid(pid, port, &config));
+        });
+    }
+    drop(tx);
+    for _ in targets {
+        if rx
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            .is_err()
+        {
+            break;
+        }
+    }
+
+    let kill_wait = config().timeout(StopMethod::Kill);
+    let mut killed = Vec::new();
+    for target in targets.iter().filter(|t| is_pid_running(t.pid)) {
+        let detail = force_kill(target.pid).unwrap_or_else(|e| e);
+        let exited = wait_exit(target.pid, kill_wait);
+        log_to_file(&format!(
+            "[quit] deadline force-kill pid={} workspace={} exited={exited} {detail}",
+            target.pid, target.workspace_id
+        ));
+        killed.push(target.pid);
+    }
+    killed
+}
+
+#[tauri::command]
+pub fn get_shutdown_config() -> ShutdownConfig {
+    config()
+}
+
+#[tauri::command]
+pub fn set_shutdown_config(config: ShutdownConfig) -> CmdResult<()> {
+    config.validate()?;
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    state.shutdown = Some(config);
+    write_state_file(&state).map_err(Into::into)
+}
+
+/// The workspace's shutdown timeout and where it comes from.
+#[tauri::command]
+pub fn get_shutdown_timeout(workspace_id: String) -> ShutdownTimeout {
+    workspace_timeout(&workspace_id)
+}
+
+/// Store the workspace's shutdown timeout in `state.json` (`None` falls
+/// back to `.env`, then the global timeouts).  Applies to the next stop.
+#[tauri::command]
+pub fn set_shutdown_timeout(workspace_id: String, timeout_secs: Option<u64>) -> CmdResult<()> {
+    observer::ensure_writable(&workspace_id)?;
+    if timeout_secs.is_some_and(|s| !(1..=MAX_TIMEOUT_SECS).contains(&s)) {
+        return Err(format!("INVALID_ARGUMENT|timeoutSecs 须在 1–{MAX_TIMEOUT_SECS} 之间").into());
+    }
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    let ws = state
+        .workspaces
+        .iter_mut()
+        .find(|w| w.id == workspace_id)
+        .ok_or_else(|| format!("NOT_FOUND|工作区不存在: {workspace_id}"))?;
+    ws.shutdown_timeout_secs = timeout_secs;
+    write_state_file(&state).map_err(Into::into)
+}
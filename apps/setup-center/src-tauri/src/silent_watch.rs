@@ -0,0 +1,246 @@
+//! "Silent backend" watchdog.
+//!
+//! Some hangs leave a live PID and an `/api/health` that still answers (the
+//! event loop is fine, the agent's worker threads are not) while the
+//! backend writes nothing for a long time.  The 5 s heartbeat thread calls
+//! [`check`] whenever the backend looks healthy; when
+//! `logs/openakita-serve.log` has not been written for `thresholdSecs`
+//! (counted from the backend's start at the earliest) the workspace is
+//! marked silent:
+//!
+//! * `ServiceStatus.silentForSecs` is set, so the UI can show a degraded
+//!   state, and a `backend:silent` event (`{ workspaceId, silent,
+//!   silentForSecs, threadDumpPath }`) fires on entering and leaving it;
+//! * with `threadDump` on, the backend is asked once per episode for a
+//!   stack dump of all threads (`POST /api/debug/thread-dump`), saved as
+//!   `logs/thread-dump-<time>.txt` in the workspace.
+//!
+//! Config lives in `state.json` (`silentWatch`); long-idle installs that
+//! really have nothing to log can raise the threshold or turn it off.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::errors::CmdResult;
+use crate::{
+    atomic_write, backend_ipc, chrono_like_timestamp, emit_global, log_to_file, now_ms,
+    read_pid_file, read_state_file, timeline, workspace_dir, write_state_file, STATE_FILE_LOCK,
+};
+
+const DUMP_TIMEOUT: Duration = Duration::from_secs(15);
+const MIN_THRESHOLD_SECS: u64 = 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SilentWatchConfig {
+    pub enabled: bool,
+    /// Seconds without a write to the serve log before the backend counts
+    /// as silent.
+    pub threshold_secs: u64,
+    /// Ask the backend for a thread dump when it goes silent.
+    pub thread_dump: bool,
+}
+
+impl Default for SilentWatchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold_secs: 15 * 60,
+            thread_dump: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SilentState {
+    pub workspace_id: String,
+    pub silent: bool,
+    pub silent_for_secs: Option<u64>,
+    /// Last write to the serve log, epoch ms.
+    pub last_log_write_ms: Option<u64>,
+    pub thread_dump_path: Option<String>,
+}
+
+static STATES: Lazy<Mutex<HashMap<String, SilentState>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub(crate) fn config() -> SilentWatchConfig {
+    read_state_file().silent_watch.unwrap_or_default()
+}
+
+fn last_log_write_ms(workspace_id: &str) -> Option<u64> {
+    let path = workspace_dir(workspace_id)
+        .join("logs")
+        .join("openakita-serve.log");
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64)
+}
+
+/// How long the backend has been silent past `threshold_ms`, or `None`
+/// while it is not.  Silence is counted from the later of the last log
+/// write and the backend's start; with neither known it is never silent.
+pub(crate) fn silent_for_ms(
+    last_write_ms: Option<u64>,
+    started_ms: Option<u64>,
+    now_ms: u64,
+    threshold_ms: u64,
+) -> Option<u64> {
+    let since = last_write_ms.max(started_ms)?;
+    let quiet = now_ms.saturating_sub(since);
+    (quiet >= threshold_ms).then_some(quiet)
+}
+
+/// Seconds the workspace's backend has been silent (for `ServiceStatus`).
+pub(crate) fn silent_for_secs(workspace_id: &str) -> Option<u64> {
+    STATES
+        .lock()
+        .ok()?
+        .get(workspace_id)
+        .and_then(|s| s.silent_for_secs)
+}
+
+fn request_thread_dump(workspace_id: &str) -> Result<String, String> {
+    let resp = backend_ipc::workspace_request_blocking(
+        workspace_id,
+        "POST",
+        "/api/debug/thread-dump",
+        &[],
+        None,
+        DUMP_TIMEOUT,
+    )?;
+    if !resp.is_success() {
+        return Err(format!("thread dump: HTTP {}", resp.status));
+    }
+    let text = match resp.json() {
+        Ok(v) => v
+            .get("dump")
+            .and_then(|d| d.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| v.to_string()),
+        Err(_) => String::from_utf8_lossy(&resp.body).into_owned(),
+    };
+    let path = workspace_dir(workspace_id)
+        .join("logs")
+        .join(format!("thread-dump-{}.txt", chrono_like_timestamp()));
+    atomic_write(&path, text)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+fn emit(state: &SilentState) {
+    emit_global("backend:silent", state.clone());
+}
+
+/// Forget the workspace's silence (backend stopped or unhealthy).
+pub(crate) fn clear(workspace_id: &str) {
+    let removed = STATES.lock().ok().and_then(|mut s| s.remove(workspace_id));
+    if let Some(mut state) = removed.filter(|s| s.silent) {
+        state.silent = false;
+        state.silent_for_secs = None;
+        emit(&state);
+    }
+}
+
+/// Called by the heartbeat thread while the backend answers health checks.
+pub(crate) fn check(workspace_id: &str) {
+    let cfg = config();
+    if !cfg.enabled {
+        clear(workspace_id);
+        return;
+    }
+    let last_write = last_log_write_ms(workspace_id);
+    let started = read_pid_file(workspace_id)
+        .map(|p| p.started_at * 1000)
+        .filter(|ms| *ms > 0);
+    let threshold_ms = cfg.threshold_secs.max(MIN_THRESHOLD_SECS) * 1000;
+    let silent_ms = silent_for_ms(last_write, started, now_ms(), threshold_ms);
+
+    let (entered, mut state) = {
+        let Ok(mut states) = STATES.lock() else {
+            return;
+        };
+        let state = states
+            .entry(workspace_id.to_string())
+            .or_insert_with(|| SilentState {
+                workspace_id: workspace_id.to_string(),
+                ..Default::default()
+            });
+        let was_silent = state.silent;
+        state.last_log_write_ms = last_write;
+        state.silent = silent_ms.is_some();
+        state.silent_for_secs = silent_ms.map(|ms| ms / 1000);
+        if !state.silent {
+            state.thread_dump_path = None;
+        }
+        if was_silent == state.silent {
+            return;
+        }
+        (state.silent, state.clone())
+    };
+    if !entered {
+        log_to_file(&format!(
+            "[silent-watch] {workspace_id}: backend writing again"
+        ));
+        timeline::record_health(workspace_id, "writing", None);
+        emit(&state);
+        return;
+    }
+    let quiet = format!(
+        "no serve-log output for {}s",
+        state.silent_for_secs.unwrap_or(0)
+    );
+    log_to_file(&format!("[silent-watch] {workspace_id}: {quiet}"));
+    timeline::record_health(workspace_id, "silent", Some(quiet));
+    if cfg.thread_dump {
+        match request_thread_dump(workspace_id) {
+            Ok(path) => {
+                log_to_file(&format!("[silent-watch] thread dump saved to {path}"));
+                state.thread_dump_path = Some(path.clone());
+                if let Some(s) = STATES
+                    .lock()
+                    .ok()
+                    .as_mut()
+                    .and_then(|s| s.get_mut(workspace_id))
+                {
+                    s.thread_dump_path = Some(path);
+                }
+            }
+            Err(e) => log_to_file(&format!("[silent-watch] thread dump failed: {e}")),
+        }
+    }
+    emit(&state);
+}
+
+#[tauri::command]
+pub fn get_silent_watch_config() -> SilentWatchConfig {
+    config()
+}
+
+#[tauri::command]
+pub fn set_silent_watch_config(config: SilentWatchConfig) -> CmdResult<()> {
+    if config.threshold_secs < MIN_THRESHOLD_SECS {
+        return Err(format!("INVALID_ARGUMENT|thresholdSecs 不能小于 {MIN_THRESHOLD_SECS}").into());
+    }
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    state.silent_watch = Some(config);
+    write_state_file(&state).map_err(Into::into)
+}
+
+/// Whether the workspace's backend is currently considered silent.
+#[tauri::command]
+pub fn get_silent_state(workspace_id: String) -> SilentState {
+    STATES
+        .lock()
+        .ok()
+        .and_then(|s| s.get(&workspace_id).cloned())
+        .unwrap_or(SilentState {
+            workspace_id,
+            ..Default::default()
+        })
+}
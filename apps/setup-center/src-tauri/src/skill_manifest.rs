@@ -0,0 +1,429 @@
+//! Bulk skill provisioning from a manifest file.
+//!
+//! `apply_skill_manifest(workspace_id, path)` makes a workspace's skills
+//! match a JSON or YAML file that a team keeps next to its agent config:
+//!
+//! ```yaml
+//! prune: false            # true = uninstall skills not listed here
+//! skills:
+//!   - name: weather-now
+//!     source: git+https://example.com/skills.git@v1.2#weather-now
+//!     version: "1.2.0"
+//!     config: { api_key: "..." }
+//!   - name: pdf-tools
+//!     source: { kind: url, location: https://example.com/pdf.zip, sha256: "..." }
+//!   - name: old-skill
+//!     remove: true
+//! ```
+//!
+//! A bare top-level list is accepted as well.  `source` is either a
+//! [`SkillSource`] object or a string: `git+<url>[@ref][#subdir]` (the
+//! `.openakita-source` format) goes through `openakita_install_skill_source`,
+//! anything else is handed to the bridge like `openakita_install_skill`.
+//!
+//! A listed skill is (re)installed when it is missing, its `SKILL.md`
+//! `version` differs from the manifest, or it was installed from a
+//! different source.  Updates move the old directory aside and put it back
+//! if the new install fails.  YAML is parsed by the bridge (`load-yaml`),
+//! which already ships PyYAML.  Every item reports its own result; one
+//! failing item does not stop the rest.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::errors::CmdResult;
+use crate::{
+    log_to_file, now_ms, observer, openakita_install_skill, openakita_uninstall_skill,
+    run_python_module_json, skill_watch, skills, spawn_blocking_result, workspace_dir,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum ManifestSource {
+    Spec(skills::SkillSource),
+    Url(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ManifestEntry {
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) source: Option<ManifestSource>,
+    #[serde(default)]
+    pub(crate) version: Option<String>,
+    #[serde(default)]
+    pub(crate) config: Option<Map<String, Value>>,
+    #[serde(default)]
+    pub(crate) remove: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct SkillManifest {
+    #[serde(default)]
+    pub(crate) skills: Vec<ManifestEntry>,
+    #[serde(default)]
+    pub(crate) prune: bool,
+}
+
+/// Accept `{skills: [...], prune}` or a bare list; names must be unique.
+pub(crate) fn parse_manifest(value: Value) -> Result<SkillManifest, String> {
+    let manifest = if value.is_array() {
+        SkillManifest {
+            skills: serde_json::from_value(value)
+                .map_err(|e| format!("SKILL_MANIFEST_INVALID|{e}"))?,
+            prune: false,
+        }
+    } else {
+        serde_json::from_value(value).map_err(|e| format!("SKILL_MANIFEST_INVALID|{e}"))?
+    };
+    let mut seen = std::collections::HashSet::new();
+    for entry in &manifest.skills {
+        let name = entry.name.trim();
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(format!(
+                "SKILL_MANIFEST_INVALID|invalid skill name: {:?}",
+                entry.name
+            ));
+        }
+        if !seen.insert(name.to_string()) {
+            return Err(format!("SKILL_MANIFEST_INVALID|duplicate skill: {name}"));
+        }
+    }
+    Ok(manifest)
+}
+
+/// Parse `git+<url>[@ref][#subdir]`; `None` for any other string.
+pub(crate) fn parse_git_origin(s: &str) -> Option<skills::SkillSource> {
+    let rest = s.trim().strip_prefix("git+")?;
+    let (rest, subdir) = match rest.rsplit_once('#') {
+        Some((r, d)) => (r, Some(d.to_string()).filter(|d| !d.is_empty())),
+        None => (rest, None),
+    };
+    // 只有最后一个 / 之后的 @ 才是 ref（git@host:org/repo.git 里的 @ 不算）
+    let slash = rest.rfind('/').unwrap_or(0);
+    let (location, git_ref) = match rest.rfind('@') {
+        Some(at) if at > slash => (&rest[..at], Some(rest[at + 1..].to_string())),
+        _ => (rest, None),
+    };
+    if location.is_empty() {
+        return None;
+    }
+    Some(skills::SkillSource {
+        kind: "git".into(),
+        location: location.to_string(),
+        git_ref: git_ref.filter(|r| !r.is_empty()),
+        subdir,
+        sha256: None,
+    })
+}
+
+impl ManifestSource {
+    fn origin(&self) -> String {
+        match self {
+            ManifestSource::Spec(src) => src.origin(),
+            ManifestSource::Url(s) => s.trim().to_string(),
+        }
+    }
+}
+
+/// What has to happen to one listed skill, given what is on disk.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ManifestAction {
+    Install,
+    Update,
+    Keep,
+    Remove,
+    Absent,
+}
+
+pub(crate) fn plan_entry(
+    entry: &ManifestEntry,
+    installed: bool,
+    current_version: Option<&str>,
+    current_origin: Option<&str>,
+) -> ManifestAction {
+    if entry.remove {
+        return if installed {
+            ManifestAction::Remove
+        } else {
+            ManifestAction::Absent
+        };
+    }
+    if !installed {
+        return ManifestAction::Install;
+    }
+    let version_differs = entry
+        .version
+        .as_deref()
+        .is_some_and(|v| Some(v.trim()) != current_version);
+    let origin_differs = match (entry.source.as_ref(), current_origin) {
+        (Some(src), Some(cur)) => src.origin() != cur.trim(),
+        _ => false,
+    };
+    if version_differs || origin_differs {
+        ManifestAction::Update
+    } else {
+        ManifestAction::Keep
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestItemResult {
+    name: String,
+    /// `installed` | `updated` | `removed` | `unchanged` | `failed`
+    action: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    configured: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestApplyResult {
+    items: Vec<ManifestItemResult>,
+    /// Whether a running backend picked the changes up.
+    reloaded: bool,
+}
+
+fn load_manifest_file(venv_dir: &str, path: &Path) -> Result<SkillManifest, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("SKILL_MANIFEST_INVALID|read {}: {e}", path.display()))?;
+    let is_yaml = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("yaml") || e.eq_ignore_ascii_case("yml"));
+    let value: Value = if is_yaml {
+        let p = path.to_string_lossy().to_string();
+        let out = run_python_module_json(
+            venv_dir,
+            "openakita.setup_center.bridge",
+            &["load-yaml", "--path", &p],
+            &[],
+        )?;
+        serde_json::from_str(&out).map_err(|e| format!("SKILL_MANIFEST_INVALID|{e}"))?
+    } else {
+        serde_json::from_str(&text).map_err(|e| format!("SKILL_MANIFEST_INVALID|{e}"))?
+    };
+    parse_manifest(value)
+}
+
+/// Path of an installed skill relative to `skills/` (`<name>` or
+/// `<category>/<name>`).
+fn find_installed(
+    on_disk: &std::collections::BTreeMap<String, skill_watch::SkillStamp>,
+    name: &str,
+) -> Option<String> {
+    on_disk
+        .iter()
+        .filter(|(_, s)| s.has_manifest)
+        .map(|(rel, _)| rel)
+        .find(|rel| rel.as_str() == name || rel.rsplit('/').next() == Some(name))
+        .cloned()
+}
+
+async fn install_from(
+    app: &tauri::AppHandle,
+    venv_dir: &str,
+    workspace_id: &str,
+    source: &ManifestSource,
+) -> Result<(), String> {
+    let install_id = Some(format!("manifest-{}", now_ms()));
+    let spec = match source {
+        ManifestSource::Spec(src) => Some(src.clone()),
+        ManifestSource::Url(s) => parse_git_origin(s),
+    };
+    match spec {
+        Some(src) => skills::openakita_install_skill_source(
+            app.clone(),
+            venv_dir.to_string(),
+            workspace_id.to_string(),
+            src,
+            install_id,
+        )
+        .await
+        .map(|_| ())
+        .map_err(String::from),
+        None => openakita_install_skill(
+            app.clone(),
+            venv_dir.to_string(),
+            workspace_id.to_string(),
+            source.origin(),
+            install_id,
+        )
+        .await
+        .map(|_| ())
+        .map_err(String::from),
+    }
+}
+
+/// Install / update / remove skills so the workspace matches `path`.
+#[tauri::command]
+pub async fn apply_skill_manifest(
+    app: tauri::AppHandle,
+    venv_dir: String,
+    workspace_id: String,
+    path: String,
+) -> CmdResult<ManifestApplyResult> {
+    observer::ensure_writable(&workspace_id)?;
+    let manifest_path = PathBuf::from(&path);
+    let venv = venv_dir.clone();
+    let manifest = spawn_blocking_result(move || load_manifest_file(&venv, &manifest_path)).await?;
+    let skills_root = workspace_dir(&workspace_id).join("skills");
+    log_to_file(&format!(
+        "[skill-manifest] apply {path} workspace={workspace_id} items={} prune={}",
+        manifest.skills.len(),
+        manifest.prune
+    ));
+
+    let mut items = Vec::new();
+    let mut changed = false;
+    for entry in &manifest.skills {
+        let name = entry.name.trim().to_string();
+        let on_disk = skill_watch::scan_skills(&skills_root);
+        let rel = find_installed(&on_disk, &name);
+        let dir = rel.as_ref().map(|r| skills_root.join(r));
+        let current_version = dir
+            .as_ref()
+            .and_then(|d| fs::read_to_string(d.join("SKILL.md")).ok())
+            .and_then(|md| skills::skill_md_version(&md));
+        let current_origin = dir
+            .as_ref()
+            .and_then(|d| fs::read_to_string(d.join(".openakita-source")).ok());
+        let action = plan_entry(
+            entry,
+            rel.is_some(),
+            current_version.as_deref(),
+            current_origin.as_deref(),
+        );
+
+        let outcome: Result<&'static str, String> = match action {
+            ManifestAction::Keep => Ok("unchanged"),
+            ManifestAction::Absent => Ok("unchanged"),
+            ManifestAction::Remove => openakita_uninstall_skill(
+                app.clone(),
+                venv_dir.clone(),
+                workspace_id.clone(),
+                name.clone(),
+            )
+            .await
+            .map(|_| "removed")
+            .map_err(String::from),
+            ManifestAction::Install => match entry.source.as_ref() {
+                None => Err(format!("{name} 未安装且没有指定 source")),
+                Some(src) => install_from(&app, &venv_dir, &workspace_id, src)
+                    .await
+                    .map(|_| "installed"),
+            },
+            ManifestAction::Update => match entry.source.as_ref() {
+                None => Err(format!(
+                    "{name} 版本不符（当前 {}，需要 {}）但没有指定 source",
+                    current_version.as_deref().unwrap_or("-"),
+                    entry.version.as_deref().unwrap_or("-")
+                )),
+                Some(src) => {
+                    // 更新：先把旧目录挪开，安装失败再放回去
+                    let old = dir.clone().unwrap_or_default();
+                    let backup = old.with_file_name(format!(".{name}.manifest-{}", now_ms()));
+                    match fs::rename(&old, &backup) {
+                        Err(e) => Err(format!("move {} aside: {e}", old.display())),
+                        Ok(()) => match install_from(&app, &venv_dir, &workspace_id, src).await {
+                            Ok(()) => {
+                                let _ = fs::remove_dir_all(&backup);
+                                Ok("updated")
+                            }
+                            Err(e) => {
+                                let _ = fs::remove_dir_all(&old);
+                                let _ = fs::rename(&backup, &old);
+                                Err(e)
+                            }
+                        },
+                    }
+                }
+            },
+        };
+
+        let mut result = ManifestItemResult {
+            name: name.clone(),
+            action: "failed",
+            version: None,
+            configured: false,
+            error: None,
+        };
+        match outcome {
+            Ok(action) => {
+                changed |= action != "unchanged";
+                result.action = action;
+                if action != "removed" && !entry.remove {
+                    let on_disk = skill_watch::scan_skills(&skills_root);
+                    result.version = find_installed(&on_disk, &name)
+                        .and_then(|r| fs::read_to_string(skills_root.join(r).join("SKILL.md")).ok())
+                        .and_then(|md| skills::skill_md_version(&md));
+                    if let Some(config) = entry.config.as_ref() {
+                        match skills::openakita_set_skill_config(
+                            venv_dir.clone(),
+                            workspace_id.clone(),
+                            name.clone(),
+                            Value::Object(config.clone()).to_string(),
+                            Some(false),
+                        )
+                        .await
+                        {
+                            Ok(_) => {
+                                result.configured = true;
+                                changed = true;
+                            }
+                            Err(e) => result.error = Some(e.to_string()),
+                        }
+                    }
+                }
+            }
+            Err(e) => result.error = Some(e),
+        }
+        items.push(result);
+    }
+
+    if manifest.prune {
+        let listed: Vec<&str> = manifest.skills.iter().map(|e| e.name.trim()).collect();
+        let on_disk = skill_watch::scan_skills(&skills_root);
+        for rel in on_disk
+            .iter()
+            .filter(|(_, s)| s.has_manifest)
+            .map(|(rel, _)| rel)
+        {
+            let name = rel.rsplit('/').next().unwrap_or(rel).to_string();
+            if listed.contains(&name.as_str()) {
+                continue;
+            }
+            let outcome = openakita_uninstall_skill(
+                app.clone(),
+                venv_dir.clone(),
+                workspace_id.clone(),
+                name.clone(),
+            )
+            .await;
+            changed |= outcome.is_ok();
+            items.push(ManifestItemResult {
+                name,
+                action: if outcome.is_ok() { "removed" } else { "failed" },
+                version: None,
+                configured: false,
+                error: outcome.err().map(String::from),
+            });
+        }
+    }
+
+    for item in items.iter().filter(|i| i.action == "failed") {
+        log_to_file(&format!(
+            "[skill-manifest] {} failed: {}",
+            item.name,
+            item.error.as_deref().unwrap_or("")
+        ));
+    }
+    let reloaded = changed && skills::notify_backend_reload(&workspace_id, None).await;
+    Ok(ManifestApplyResult { items, reloaded })
+}
@@ -0,0 +1,187 @@
+//! Private skill registries with per-registry auth tokens.
+//!
+//! Besides the mirror registry (`set_mirrors({marketplaceRegistry})`),
+//! any number of private catalogs can be added with
+//! `add_skill_registry(url, token_ref)`; they live in `state.json` as
+//! `skillRegistries`.  The token itself never touches `state.json`: it is
+//! stored in the OS keyring (service `openakita-skill-registry`, user
+//! `token_ref`) by `set_skill_registry_token(venv_dir, token_ref, token)`,
+//! which goes through the bridge's `keyring` and hands the token over on
+//! stdin (`run_bridge_with_secrets`).
+//!
+//! The registry list reaches the bridge as `OPENAKITA_SKILL_REGISTRIES`
+//! (see [`crate::mirrors::bridge_env`]).  The bridge looks the tokens up
+//! and sends `Authorization: Bearer <token>` to every URL on the same
+//! origin as a registry:
+//!
+//! * `list-marketplace` fetches every registry and merges the items, each
+//!   tagged with `origin` (the registry URL, `builtin` for the built-in
+//!   examples);
+//! * `install-skill` adds the header to `git clone` of such URLs;
+//! * `kind: "url"` archives from such a registry are downloaded by the
+//!   bridge (`download-registry-artifact`) instead of in Rust, so the
+//!   token stays out of this process and the bridge trace.
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::CmdResult;
+use crate::{
+    net, read_state_file, run_bridge_with_secrets, run_python_module_json, spawn_blocking_result,
+    write_state_file, STATE_FILE_LOCK,
+};
+
+pub(crate) const REGISTRIES_ENV: &str = "OPENAKITA_SKILL_REGISTRIES";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillRegistry {
+    /// Catalog endpoint (JSON list, or `{ "skills": [...] }`).
+    pub url: String,
+    /// Keyring entry holding the registry's token; `None` for public ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_ref: Option<String>,
+}
+
+pub(crate) fn configured() -> Vec<SkillRegistry> {
+    read_state_file().skill_registries.unwrap_or_default()
+}
+
+pub(crate) fn is_valid_token_ref(r: &str) -> bool {
+    !r.is_empty()
+        && r.len() <= 64
+        && r.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// `scheme://host[:port]` of an http(s) URL, lowercased.
+fn origin_of(url: &str) -> Option<String> {
+    let parsed = reqwest::Url::parse(url.trim()).ok()?;
+    Some(parsed.origin().ascii_serialization())
+}
+
+/// Registry with a token that `url` belongs to (same origin).
+pub(crate) fn authorized_registry_for<'a>(
+    registries: &'a [SkillRegistry],
+    url: &str,
+) -> Option<&'a SkillRegistry> {
+    let origin = origin_of(url)?;
+    registries
+        .iter()
+        .find(|r| r.token_ref.is_some() && origin_of(&r.url).as_deref() == Some(origin.as_str()))
+}
+
+/// Value of [`REGISTRIES_ENV`] for the bridge; `None` when none configured.
+pub(crate) fn bridge_env_value(registries: &[SkillRegistry]) -> Option<String> {
+    if registries.is_empty() {
+        return None;
+    }
+    serde_json::to_string(registries).ok()
+}
+
+/// Part of the marketplace cache key: the merged catalog depends on the
+/// registry set (not on the tokens).
+pub(crate) fn cache_tag(registries: &[SkillRegistry]) -> String {
+    registries
+        .iter()
+        .map(|r| r.url.as_str())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Download a private registry's archive through the bridge (which holds
+/// the token).  Blocking.
+pub(crate) fn download_artifact(venv_dir: &str, url: &str, dest: &str) -> Result<(), String> {
+    net::ensure_online("下载技能包")?;
+    let args = vec!["download-registry-artifact", "--url", url, "--dest", dest];
+    let env = bridge_env_value(&configured()).unwrap_or_default();
+    run_python_module_json(
+        venv_dir,
+        "openakita.setup_center.bridge",
+        &args,
+        &[(REGISTRIES_ENV, env.as_str())],
+    )
+    .map(|_| ())
+    .map_err(|e| format!("download {url} failed: {e}"))
+}
+
+fn save(registries: Vec<SkillRegistry>) -> CmdResult<Vec<SkillRegistry>> {
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    state.skill_registries = if registries.is_empty() {
+        None
+    } else {
+        Some(registries.clone())
+    };
+    write_state_file(&state)?;
+    Ok(registries)
+}
+
+#[tauri::command]
+pub fn list_skill_registries() -> Vec<SkillRegistry> {
+    configured()
+}
+
+/// Add a registry, or update the token reference of an existing one.
+#[tauri::command]
+pub fn add_skill_registry(url: String, token_ref: Option<String>) -> CmdResult<Vec<SkillRegistry>> {
+    let url = url.trim().to_string();
+    let parsed = reqwest::Url::parse(&url)
+        .map_err(|e| format!("INVALID_ARGUMENT|注册表地址不是有效的 URL: {e}"))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(format!("INVALID_ARGUMENT|注册表仅支持 http/https 地址: {url}").into());
+    }
+    let token_ref = token_ref
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty());
+    if let Some(ref r) = token_ref {
+        if !is_valid_token_ref(r) {
+            return Err(format!(
+                "INVALID_ARGUMENT|tokenRef 只能包含字母、数字、-、_、.（最长 64 个字符）: {r}"
+            )
+            .into());
+        }
+    }
+    let mut registries = configured();
+    match registries.iter_mut().find(|r| r.url == url) {
+        Some(existing) => existing.token_ref = token_ref,
+        None => registries.push(SkillRegistry { url, token_ref }),
+    }
+    save(registries)
+}
+
+#[tauri::command]
+pub fn remove_skill_registry(url: String) -> CmdResult<Vec<SkillRegistry>> {
+    let mut registries = configured();
+    let before = registries.len();
+    registries.retain(|r| r.url != url.trim());
+    if registries.len() == before {
+        return Err(format!("NOT_FOUND|未找到技能注册表: {url}").into());
+    }
+    save(registries)
+}
+
+/// Store `token` in the keyring under `token_ref`; `None` / empty deletes it.
+#[tauri::command]
+pub async fn set_skill_registry_token(
+    venv_dir: String,
+    token_ref: String,
+    token: Option<String>,
+) -> CmdResult<()> {
+    if !is_valid_token_ref(&token_ref) {
+        return Err(format!("INVALID_ARGUMENT|无效的 tokenRef: {token_ref}").into());
+    }
+    spawn_blocking_result(move || {
+        let token = token.unwrap_or_default();
+        let args = vec!["set-registry-token", "--token-ref", &token_ref];
+        run_bridge_with_secrets(
+            &venv_dir,
+            &args,
+            &serde_json::json!({ "registry_token": token.trim() }),
+        )
+        .map(|_| ())
+    })
+    .await
+    .map_err(Into::into)
+}
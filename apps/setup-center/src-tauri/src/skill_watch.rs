@@ -0,0 +1,305 @@
+//! Workspace skills directory watcher and drift detection.
+//!
+//! Users edit, copy in or delete skill folders by hand.  The skills page
+//! used to keep showing what the last `list-skills` call returned until the
+//! next manual refresh.  `start_skills_watcher(workspace_id)` polls
+//! `<workspace>/skills` (every [`WATCH_INTERVAL`], no native fs-notify
+//! dependency) and emits a `skills_changed` event
+//! (`{ workspaceId, added, removed, modified }`, skill paths relative to
+//! `skills/`) whenever the set of skills or their files change.
+//!
+//! `detect_skill_drift(workspace_id)` compares the skills on disk with the
+//! installs the app recorded (the version history in
+//! `cache/skills/<workspace>/`, see `skills::archive_installed_version`):
+//!
+//! * `missing` — installed by the app, directory gone;
+//! * `modified` — files changed after the install, or `SKILL.md`'s
+//!   `version` no longer matches the recorded one;
+//! * `untracked` — a skill folder the app never installed (copied in by
+//!   hand or scaffolded);
+//! * `invalid` — a folder without `SKILL.md`.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::errors::CmdResult;
+use crate::{emit_if_ui_live, log_to_file, skills, workspace_dir};
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+/// Files written by the install itself land slightly before the archive id.
+const INSTALL_MTIME_SLACK_MS: u64 = 5_000;
+
+static SKILL_WATCHERS: Lazy<Mutex<HashMap<String, Arc<AtomicBool>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Cheap per-skill fingerprint: newest mtime and total size of its files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SkillStamp {
+    pub(crate) mtime_ms: u64,
+    pub(crate) bytes: u64,
+    pub(crate) has_manifest: bool,
+}
+
+fn stamp_dir(dir: &Path, stamp: &mut SkillStamp) {
+    let Ok(rd) = fs::read_dir(dir) else { return };
+    for entry in rd.flatten() {
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else { continue };
+        if meta.is_dir() {
+            stamp_dir(&entry.path(), stamp);
+            continue;
+        }
+        let mtime = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        stamp.mtime_ms = stamp.mtime_ms.max(mtime);
+        stamp.bytes += meta.len();
+    }
+}
+
+/// Skills under `root`: `<id>/SKILL.md` or `<category>/<id>/SKILL.md`.
+/// Folders with neither are reported with `has_manifest: false`.
+pub(crate) fn scan_skills(root: &Path) -> BTreeMap<String, SkillStamp> {
+    let mut out = BTreeMap::new();
+    let Ok(rd) = fs::read_dir(root) else {
+        return out;
+    };
+    for entry in rd.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') || !path.is_dir() {
+            continue;
+        }
+        if path.join("SKILL.md").is_file() {
+            let mut stamp = SkillStamp {
+                mtime_ms: 0,
+                bytes: 0,
+                has_manifest: true,
+            };
+            stamp_dir(&path, &mut stamp);
+            out.insert(name, stamp);
+            continue;
+        }
+        // 分类目录：再下一层
+        let mut found = false;
+        if let Ok(sub) = fs::read_dir(&path) {
+            for child in sub.flatten() {
+                let cpath = child.path();
+                if cpath.is_dir() && cpath.join("SKILL.md").is_file() {
+                    let mut stamp = SkillStamp {
+                        mtime_ms: 0,
+                        bytes: 0,
+                        has_manifest: true,
+                    };
+                    stamp_dir(&cpath, &mut stamp);
+                    out.insert(
+                        format!("{name}/{}", child.file_name().to_string_lossy()),
+                        stamp,
+                    );
+                    found = true;
+                }
+            }
+        }
+        if !found {
+            let mut stamp = SkillStamp {
+                mtime_ms: 0,
+                bytes: 0,
+                has_manifest: false,
+            };
+            stamp_dir(&path, &mut stamp);
+            out.insert(name, stamp);
+        }
+    }
+    out
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillsChanged {
+    pub(crate) added: Vec<String>,
+    pub(crate) removed: Vec<String>,
+    pub(crate) modified: Vec<String>,
+}
+
+impl SkillsChanged {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+pub(crate) fn diff_scans(
+    old: &BTreeMap<String, SkillStamp>,
+    new: &BTreeMap<String, SkillStamp>,
+) -> SkillsChanged {
+    let mut changed = SkillsChanged::default();
+    for (name, stamp) in new {
+        match old.get(name) {
+            None => changed.added.push(name.clone()),
+            Some(prev) if prev != stamp => changed.modified.push(name.clone()),
+            Some(_) => {}
+        }
+    }
+    for name in old.keys() {
+        if !new.contains_key(name) {
+            changed.removed.push(name.clone());
+        }
+    }
+    changed
+}
+
+/// Start watching a workspace's skills directory (no-op if already watched).
+#[tauri::command]
+pub fn start_skills_watcher(app: tauri::AppHandle, workspace_id: String) -> CmdResult<()> {
+    let mut watchers = SKILL_WATCHERS
+        .lock()
+        .map_err(|e| format!("skills watcher lock failed: {e}"))?;
+    if watchers.contains_key(&workspace_id) {
+        return Ok(());
+    }
+    let stop = Arc::new(AtomicBool::new(false));
+    watchers.insert(workspace_id.clone(), stop.clone());
+    drop(watchers);
+
+    let root = workspace_dir(&workspace_id).join("skills");
+    log_to_file(&format!(
+        "[skill-watch] start workspace={workspace_id} dir={}",
+        root.display()
+    ));
+    std::thread::spawn(move || {
+        let mut last = scan_skills(&root);
+        while !stop.load(Ordering::SeqCst) {
+            std::thread::sleep(WATCH_INTERVAL);
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+            let current = scan_skills(&root);
+            let changed = diff_scans(&last, &current);
+            if !changed.is_empty() {
+                emit_if_ui_live(
+                    &app,
+                    "skills_changed",
+                    serde_json::json!({
+                        "workspaceId": workspace_id,
+                        "added": changed.added,
+                        "removed": changed.removed,
+                        "modified": changed.modified,
+                    }),
+                );
+                last = current;
+            }
+        }
+        log_to_file(&format!("[skill-watch] stop workspace={workspace_id}"));
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_skills_watcher(workspace_id: String) {
+    if let Ok(mut watchers) = SKILL_WATCHERS.lock() {
+        if let Some(stop) = watchers.remove(&workspace_id) {
+            stop.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Stop every watcher (app exit).
+pub(crate) fn stop_all_watchers() {
+    if let Ok(mut watchers) = SKILL_WATCHERS.lock() {
+        for (_, stop) in watchers.drain() {
+            stop.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillDrift {
+    /// Path relative to the workspace `skills/` dir.
+    pub(crate) skill: String,
+    /// `missing` | `modified` | `untracked` | `invalid`
+    pub(crate) status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recorded_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current_version: Option<String>,
+}
+
+/// Compare disk against recorded installs; `recorded` is `(skill_rel,
+/// install id in epoch ms, recorded version)`.
+pub(crate) fn compute_drift(
+    on_disk: &BTreeMap<String, SkillStamp>,
+    current_versions: &HashMap<String, String>,
+    recorded: &[(String, u64, Option<String>)],
+) -> Vec<SkillDrift> {
+    let mut out = Vec::new();
+    for (rel, installed_ms, version) in recorded {
+        let Some(stamp) = on_disk.get(rel) else {
+            out.push(SkillDrift {
+                skill: rel.clone(),
+                status: "missing",
+                recorded_version: version.clone(),
+                current_version: None,
+            });
+            continue;
+        };
+        let current = current_versions.get(rel).cloned();
+        let version_changed = version.is_some() && current != *version;
+        if version_changed || stamp.mtime_ms > installed_ms + INSTALL_MTIME_SLACK_MS {
+            out.push(SkillDrift {
+                skill: rel.clone(),
+                status: "modified",
+                recorded_version: version.clone(),
+                current_version: current,
+            });
+        }
+    }
+    for (rel, stamp) in on_disk {
+        if !stamp.has_manifest {
+            out.push(SkillDrift {
+                skill: rel.clone(),
+                status: "invalid",
+                recorded_version: None,
+                current_version: None,
+            });
+        } else if !recorded.iter().any(|(r, _, _)| r == rel) {
+            out.push(SkillDrift {
+                skill: rel.clone(),
+                status: "untracked",
+                recorded_version: None,
+                current_version: current_versions.get(rel).cloned(),
+            });
+        }
+    }
+    out
+}
+
+/// Skills whose on-disk state no longer matches what the app installed.
+#[tauri::command]
+pub fn detect_skill_drift(workspace_id: String) -> Vec<SkillDrift> {
+    let root = workspace_dir(&workspace_id).join("skills");
+    let on_disk = scan_skills(&root);
+    let current_versions: HashMap<String, String> = on_disk
+        .iter()
+        .filter(|(_, s)| s.has_manifest)
+        .filter_map(|(rel, _)| {
+            let md = fs::read_to_string(root.join(rel).join("SKILL.md")).ok()?;
+            Some((rel.clone(), skills::skill_md_version(&md)?))
+        })
+        .collect();
+    let recorded: Vec<(String, u64, Option<String>)> = skills::recorded_installs(&workspace_id)
+        .into_iter()
+        .map(|v| (v.skill_rel, v.id, v.version))
+        .collect();
+    compute_drift(&on_disk, &current_versions, &recorded)
+}
@@ -0,0 +1,1397 @@
+//! Skill sources beyond the marketplace: local directories, zip archives
+//! and arbitrary git repositories (private or in development).
+//!
+//! `openakita_install_skill_source({kind, location, ref, subdir})` resolves
+//! the source in Rust — `git clone` (optionally at a branch / tag / commit),
+//! zip extraction, or a plain directory — into a staging directory named
+//! after the skill, then hands the local path to the bridge's
+//! `install-skill`, the same flow (progress events, cancellation) as
+//! `openakita_install_skill`.  The original source is written to the
+//! installed skill's `.openakita-source` so it isn't recorded as the
+//! temporary staging path.
+//!
+//! Integrity: a source may carry a `sha256` (registries publish it next to
+//! `archive_url`).  Archives — local zips and `kind: "url"` downloads — are
+//! hashed in Rust before extraction and rejected with
+//! `SKILL_CHECKSUM_MISMATCH|...` when it differs.  With the
+//! `requireSignedSkills` policy on (`set_require_signed_skills(true)`,
+//! persisted in `state.json`), installs that can't be verified are refused
+//! with `SKILL_UNVERIFIED|...`: archives need a `sha256`, git sources need a
+//! full commit hash as `ref`, and the bridge-side remote installs of
+//! `openakita_install_skill` are blocked.  Local directories stay allowed.
+//! Detached signatures (minisign / sigstore) are not supported yet.
+//!
+//! Skill configuration: `openakita_set_skill_config` validates values
+//! against the `config` schema from `SKILL.md` (the same one
+//! `openakita_get_skill_config` returns), writes them through the bridge to
+//! `data/skill_configs.json`, and can ask a running backend to reload the
+//! skill.
+//!
+//! Versions: every successful install is also zipped to
+//! `~/.openakita/cache/skills/<workspace>/<skill>/<ts>.zip` (last
+//! [`SKILL_VERSIONS_KEPT`] kept, with a `<ts>.json` sidecar holding origin
+//! and `version`).  `rollback_skill(workspace_id, skill_name)` replaces the
+//! installed directory with the previous archive and drops the newest one,
+//! so repeated rollbacks walk further back.
+//!
+//! Scaffolding: `create_skill_scaffold(workspace_id, name, {template})`
+//! writes a ready-to-edit skill (`SKILL.md` with a `config` schema, an
+//! entry script for the `python` / `node` templates, `README.md`) from the
+//! templates embedded below, validates it with the bridge
+//! (`register-skill`, which also adds it to an existing allowlist) and asks
+//! a running backend to rescan.
+//!
+//! Marketplace search: `search_marketplace(query, {category, sort})` ranks
+//! and filters the on-disk catalog cache (the one `openakita_list_marketplace`
+//! fills, keyed by registry) in Rust, so typing in the search box doesn't
+//! spawn Python.  An enterprise-internal registry is configured through
+//! `set_mirrors({marketplaceRegistry})`; registries that need a token are
+//! added with `add_skill_registry` (see `skill_registry`).
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+
+use crate::errors::CmdResult;
+use crate::{
+    apply_no_window, archive, atomic_write, backend_ipc, bridge_cache, data_dirs,
+    emit_skill_install_event, load_marketplace_catalog, log_to_file, net, now_ms, observer,
+    read_state_file, read_workspace_api_port, register_skill_install, run_bridge_with_secrets,
+    run_python_module_json, run_skill_install_bridge, skill_registry, spawn_blocking_result,
+    unregister_skill_install, workspace_dir, write_state_file, SkillInstallResult, STATE_FILE_LOCK,
+};
+
+/// Zip archives larger than this (uncompressed) are refused.
+const SKILL_ZIP_MAX_UNPACKED_BYTES: u64 = 512 * 1024 * 1024;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillSource {
+    /// `git` | `zip` | `dir`
+    pub kind: String,
+    /// Git URL, or a local zip / directory path.
+    pub location: String,
+    /// Git branch, tag or commit (git only).
+    #[serde(default, rename = "ref")]
+    pub git_ref: Option<String>,
+    /// Sub-directory inside the repo / archive that holds `SKILL.md`.
+    #[serde(default)]
+    pub subdir: Option<String>,
+    /// Expected SHA-256 (hex) of the archive (`zip` / `url`).
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+impl SkillSource {
+    /// Value recorded in `.openakita-source`, e.g. `git+https://…/repo.git@v1.2#skills/x`.
+    pub(crate) fn origin(&self) -> String {
+        let mut s = match self.kind.as_str() {
+            "git" => format!("git+{}", self.location),
+            _ => self.location.clone(),
+        };
+        if let Some(r) = self.git_ref.as_deref().filter(|r| !r.is_empty()) {
+            s.push('@');
+            s.push_str(r);
+        }
+        if let Some(sub) = self.subdir.as_deref().filter(|d| !d.is_empty()) {
+            s.push('#');
+            s.push_str(sub);
+        }
+        s
+    }
+}
+
+/// Skill directory name derived from the source: the last `subdir`
+/// component, otherwise the repo / archive / folder name.
+pub(crate) fn skill_name_for(src: &SkillSource) -> String {
+    let from_subdir = src
+        .subdir
+        .as_deref()
+        .and_then(|d| d.trim_matches(['/', '\\']).rsplit(['/', '\\']).next())
+        .filter(|n| !n.is_empty());
+    let raw = from_subdir.unwrap_or_else(|| {
+        let loc = src.location.trim_end_matches(['/', '\\']);
+        let last = loc.rsplit(['/', '\\', ':']).next().unwrap_or(loc);
+        last.strip_suffix(".git")
+            .or_else(|| last.strip_suffix(".zip"))
+            .unwrap_or(last)
+    });
+    let name: String = raw
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let name = name.trim_matches(['-', '.']).to_string();
+    if name.is_empty() {
+        "skill".into()
+    } else {
+        name
+    }
+}
+
+/// Join a user-supplied relative sub-path, refusing absolute paths and `..`.
+fn join_subdir(root: &Path, subdir: Option<&str>) -> Result<PathBuf, String> {
+    let Some(sub) = subdir.map(str::trim).filter(|s| !s.is_empty()) else {
+        return Ok(root.to_path_buf());
+    };
+    let rel = Path::new(sub);
+    if rel
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(format!("subdir 必须是相对路径且不能包含 ..: {sub}"));
+    }
+    Ok(root.join(rel))
+}
+
+fn run_git(args: &[&str], cancel: &AtomicBool) -> Result<(), String> {
+    let mut cmd = Command::new("git");
+    cmd.args(args)
+        // 私有仓库没有凭据时直接失败，而不是卡在终端提示上
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    apply_no_window(&mut cmd);
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("未找到 git 命令。请安装 Git (https://git-scm.com) 后重试: {e}"))?;
+    loop {
+        if cancel.load(Ordering::SeqCst) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err("BRIDGE_CANCELLED|git clone cancelled".into());
+        }
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => {
+                let mut stderr = String::new();
+                if let Some(mut e) = child.stderr.take() {
+                    use std::io::Read as _;
+                    let _ = e.read_to_string(&mut stderr);
+                }
+                return Err(format!(
+                    "git {} failed ({status}): {}",
+                    args[0],
+                    stderr.trim()
+                ));
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(200)),
+            Err(e) => return Err(format!("wait for git failed: {e}")),
+        }
+    }
+}
+
+fn clone_git(src: &SkillSource, dest: &Path, cancel: &AtomicBool) -> Result<(), String> {
+    let dest_str = dest.to_string_lossy().to_string();
+    let git_ref = src
+        .git_ref
+        .as_deref()
+        .map(str::trim)
+        .filter(|r| !r.is_empty());
+    let Some(git_ref) = git_ref else {
+        return run_git(&["clone", "--depth", "1", &src.location, &dest_str], cancel);
+    };
+    // 分支 / 标签可以浅克隆；提交哈希需要完整克隆后再 checkout
+    if run_git(
+        &[
+            "clone",
+            "--depth",
+            "1",
+            "--branch",
+            git_ref,
+            &src.location,
+            &dest_str,
+        ],
+        cancel,
+    )
+    .is_ok()
+    {
+        return Ok(());
+    }
+    if cancel.load(Ordering::SeqCst) {
+        return Err("BRIDGE_CANCELLED|git clone cancelled".into());
+    }
+    let _ = fs::remove_dir_all(dest);
+    run_git(&["clone", &src.location, &dest_str], cancel)?;
+    run_git(&["-C", &dest_str, "checkout", "--detach", git_ref], cancel)
+        .map_err(|e| format!("ref {git_ref} 不存在: {e}"))
+}
+
+fn extract_zip(zip_path: &Path, dest: &Path) -> Result<(), String> {
+    archive::extract_archive(
+        zip_path,
+        dest,
+        &mut archive::ExtractOptions {
+            max_unpacked_bytes: Some(SKILL_ZIP_MAX_UNPACKED_BYTES),
+            ..Default::default()
+        },
+    )
+    .map(|_| ())
+}
+
+/// GitHub / GitLab archives wrap everything in one `repo-main/` folder.
+fn unwrap_single_dir(root: &Path) -> PathBuf {
+    let entries: Vec<PathBuf> = fs::read_dir(root)
+        .map(|rd| rd.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default();
+    match entries.as_slice() {
+        [only] if only.is_dir() && !root.join("SKILL.md").exists() => only.clone(),
+        _ => root.to_path_buf(),
+    }
+}
+
+/// Resolve `src` into a local directory containing `SKILL.md`, named after
+/// the skill.  Anything created lives under `staging`.
+fn prepare_source(
+    src: &SkillSource,
+    staging: &Path,
+    cancel: &AtomicBool,
+) -> Result<PathBuf, String> {
+    let name = skill_name_for(src);
+    let skill_root = match src.kind.as_str() {
+        "dir" => {
+            let dir = PathBuf::from(&src.location);
+            if !dir.is_dir() {
+                return Err(format!("目录不存在: {}", src.location));
+            }
+            let root = join_subdir(&dir, src.subdir.as_deref())?;
+            if src.subdir.is_none() {
+                // 目录安装直接交给 bridge，不复制
+                return check_skill_dir(root);
+            }
+            root
+        }
+        "zip" | "url" => {
+            let zip_path = if src.kind == "url" {
+                staging.join(STAGED_ARCHIVE_NAME)
+            } else {
+                PathBuf::from(&src.location)
+            };
+            if !zip_path.is_file() {
+                return Err(format!("zip 文件不存在: {}", src.location));
+            }
+            if let Some(expected) = src.sha256.as_deref() {
+                verify_sha256(&zip_path, expected)?;
+            }
+            let out = staging.join("extract");
+            extract_zip(&zip_path, &out)?;
+            join_subdir(&unwrap_single_dir(&out), src.subdir.as_deref())?
+        }
+        "git" => {
+            if !src.location.starts_with("file://") && !Path::new(&src.location).exists() {
+                net::ensure_online("克隆技能仓库")?;
+            }
+            let out = staging.join("repo");
+            clone_git(src, &out, cancel)?;
+            let _ = fs::remove_dir_all(out.join(".git"));
+            join_subdir(&out, src.subdir.as_deref())?
+        }
+        other => {
+            return Err(format!(
+                "不支持的技能来源类型: {other}（git | zip | url | dir）"
+            ))
+        }
+    };
+    let skill_root = check_skill_dir(skill_root)?;
+    // bridge 以目录名作为技能名，挪到 staging/<name>
+    let named = staging.join("named").join(&name);
+    fs::create_dir_all(named.parent().unwrap_or(staging))
+        .map_err(|e| format!("create staging dir: {e}"))?;
+    if skill_root.starts_with(staging) {
+        fs::rename(&skill_root, &named).map_err(|e| format!("stage skill dir: {e}"))?;
+    } else {
+        copy_dir(&skill_root, &named)?;
+    }
+    Ok(named)
+}
+
+fn check_skill_dir(dir: PathBuf) -> Result<PathBuf, String> {
+    if dir.join("SKILL.md").is_file() {
+        Ok(dir)
+    } else {
+        Err(format!("未找到 SKILL.md: {}", dir.display()))
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<(), String> {
+    fs::create_dir_all(to).map_err(|e| format!("create dir: {e}"))?;
+    for entry in fs::read_dir(from)
+        .map_err(|e| format!("read dir: {e}"))?
+        .flatten()
+    {
+        let path = entry.path();
+        let dest = to.join(entry.file_name());
+        let ft = entry.file_type().map_err(|e| format!("stat: {e}"))?;
+        if ft.is_dir() {
+            if entry.file_name() == ".git" {
+                continue;
+            }
+            copy_dir(&path, &dest)?;
+        } else if ft.is_file() {
+            fs::copy(&path, &dest).map_err(|e| format!("copy {}: {e}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+// ── Integrity policy ──
+
+const STAGED_ARCHIVE_NAME: &str = "download.zip";
+
+pub(crate) fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = fs::File::open(path).map_err(|e| format!("open {}: {e}", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| format!("read {}: {e}", path.display()))?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+pub(crate) fn verify_sha256(path: &Path, expected: &str) -> Result<(), String> {
+    let expected = expected
+        .trim()
+        .trim_start_matches("sha256:")
+        .to_ascii_lowercase();
+    let actual = sha256_file(path)?;
+    if actual != expected {
+        return Err(format!(
+            "SKILL_CHECKSUM_MISMATCH|技能包校验失败: expected {expected}, got {actual}"
+        ));
+    }
+    Ok(())
+}
+
+fn is_commit_hash(r: &str) -> bool {
+    matches!(r.len(), 40 | 64) && r.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+pub(crate) fn require_signed_skills() -> bool {
+    read_state_file().require_signed_skills.unwrap_or(false)
+}
+
+/// Under `requireSignedSkills`, refuse sources whose content can't be pinned.
+pub(crate) fn check_source_policy(src: &SkillSource, required: bool) -> Result<(), String> {
+    if !required {
+        return Ok(());
+    }
+    let has_sha = src.sha256.as_deref().is_some_and(|h| !h.trim().is_empty());
+    match src.kind.as_str() {
+        "dir" => Ok(()),
+        "zip" | "url" if has_sha => Ok(()),
+        "git" if src.git_ref.as_deref().is_some_and(is_commit_hash) => Ok(()),
+        "git" => {
+            Err("SKILL_UNVERIFIED|已开启仅安装已验证技能：git 来源需以完整提交哈希作为 ref".into())
+        }
+        _ => Err("SKILL_UNVERIFIED|已开启仅安装已验证技能：技能包缺少 sha256 校验值".into()),
+    }
+}
+
+/// Policy check for `openakita_install_skill`, where the bridge downloads
+/// remote sources itself and nothing can be verified before install.
+pub(crate) fn check_url_install_policy(url: &str) -> Result<(), String> {
+    let t = url.trim();
+    let remote = t.starts_with("http://")
+        || t.starts_with("https://")
+        || t.starts_with("git@")
+        || t.starts_with("github:")
+        || (!Path::new(t).exists() && t.contains('/'));
+    if remote && require_signed_skills() {
+        return Err(
+            "SKILL_UNVERIFIED|已开启仅安装已验证技能：请通过带 sha256 的技能包或固定提交的 git 来源安装"
+                .into(),
+        );
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_require_signed_skills() -> bool {
+    require_signed_skills()
+}
+
+#[tauri::command]
+pub fn set_require_signed_skills(enabled: bool) -> CmdResult<()> {
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    state.require_signed_skills = Some(enabled);
+    write_state_file(&state).map_err(Into::into)
+}
+
+async fn download_archive(url: &str, dest: &Path) -> Result<(), String> {
+    use std::io::Write as _;
+
+    net::ensure_online("下载技能包")?;
+    let mut resp = net::http_client()
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("download {url} failed: {e}"))?;
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("create staging dir: {e}"))?;
+    }
+    let mut file = fs::File::create(dest).map_err(|e| format!("write archive: {e}"))?;
+    let mut total = 0u64;
+    while let Some(chunk) = tokio::time::timeout(Duration::from_secs(30), resp.chunk())
+        .await
+        .map_err(|_| format!("download {url} stalled"))?
+        .map_err(|e| format!("download {url} failed: {e}"))?
+    {
+        total += chunk.len() as u64;
+        if total > SKILL_ZIP_MAX_UNPACKED_BYTES {
+            return Err("技能包超过 512 MB，已拒绝".into());
+        }
+        net::throttle_download(chunk.len()).await;
+        file.write_all(&chunk)
+            .map_err(|e| format!("write archive: {e}"))?;
+    }
+    Ok(())
+}
+
+/// Install a skill from a git repo (optionally at `ref`), a zip archive
+/// (local, or `kind: "url"` downloaded here) or a local directory.  Emits the same `skill_install_event` stages as
+/// `openakita_install_skill` and honours `cancel_skill_install(install_id)`.
+#[tauri::command]
+pub async fn openakita_install_skill_source(
+    app: tauri::AppHandle,
+    venv_dir: String,
+    workspace_id: String,
+    source: SkillSource,
+    install_id: Option<String>,
+) -> CmdResult<SkillInstallResult> {
+    observer::ensure_writable(&workspace_id)?;
+    check_source_policy(&source, require_signed_skills())?;
+    let install_id = install_id.unwrap_or_else(|| format!("skill-{}", now_ms()));
+    let staging = std::env::temp_dir().join(format!("openakita-skill-src-{}", now_ms()));
+    let origin = source.origin();
+    emit_skill_install_event(
+        &app,
+        &install_id,
+        &origin,
+        "fetch",
+        serde_json::json!({ "kind": source.kind }),
+    );
+    if source.kind == "url" {
+        let archive = staging.join(STAGED_ARCHIVE_NAME);
+        let registries = skill_registry::configured();
+        let downloaded =
+            if skill_registry::authorized_registry_for(&registries, &source.location).is_some() {
+                // 私有注册表的包交给 bridge 带令牌下载，令牌不经过这里
+                let (venv, url) = (venv_dir.clone(), source.location.clone());
+                let dest = archive.to_string_lossy().to_string();
+                spawn_blocking_result(move || skill_registry::download_artifact(&venv, &url, &dest))
+                    .await
+            } else {
+                download_archive(&source.location, &archive).await
+            };
+        if let Err(e) = downloaded {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e.into());
+        }
+    }
+    let cancel = register_skill_install(&install_id);
+    let id_for_task = install_id.clone();
+    let result = spawn_blocking_result(move || {
+        let result = prepare_source(&source, &staging, &cancel).and_then(|dir| {
+            let dir = dir.to_string_lossy().to_string();
+            run_skill_install_bridge(
+                &app,
+                &venv_dir,
+                &workspace_id,
+                &dir,
+                &id_for_task,
+                &cancel,
+                Some(&origin),
+            )
+        });
+        let _ = fs::remove_dir_all(&staging);
+        result
+    })
+    .await;
+    unregister_skill_install(&install_id);
+    result.map_err(Into::into)
+}
+
+// ── Skill configuration ──
+
+fn option_matches(opt: &Value, value: &Value) -> bool {
+    match opt {
+        Value::Object(o) => o.get("value") == Some(value),
+        other => other == value,
+    }
+}
+
+/// Check `values` against a skill's `config` schema (list of
+/// `{key, type, required, options, min, max}`) and return them normalised:
+/// numeric strings become numbers, `"true"`/`"false"` become booleans.
+/// Errors are `SKILL_CONFIG_INVALID|<key>: <reason>`.
+pub(crate) fn validate_skill_config(
+    schema: &[Value],
+    values: &Map<String, Value>,
+) -> Result<Map<String, Value>, String> {
+    let invalid = |key: &str, reason: &str| format!("SKILL_CONFIG_INVALID|{key}: {reason}");
+    let mut out = Map::new();
+    for (key, _) in values {
+        if !schema
+            .iter()
+            .any(|f| f.get("key").and_then(Value::as_str) == Some(key))
+        {
+            return Err(invalid(key, "不在技能配置 schema 中"));
+        }
+    }
+    for field in schema {
+        let Some(key) = field.get("key").and_then(Value::as_str) else {
+            continue;
+        };
+        let required = field
+            .get("required")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let value = match values.get(key) {
+            None | Some(Value::Null) => {
+                if required && field.get("default").is_none_or(Value::is_null) {
+                    return Err(invalid(key, "必填"));
+                }
+                continue;
+            }
+            Some(Value::String(s)) if s.is_empty() => {
+                if required {
+                    return Err(invalid(key, "必填"));
+                }
+                out.insert(key.to_string(), Value::String(String::new()));
+                continue;
+            }
+            Some(v) => v,
+        };
+        let ty = field.get("type").and_then(Value::as_str).unwrap_or("text");
+        let normalized = match ty {
+            "number" => {
+                let n = match value {
+                    Value::Number(n) => n.as_f64(),
+                    Value::String(s) => s.trim().parse::<f64>().ok(),
+                    _ => None,
+                }
+                .ok_or_else(|| invalid(key, "需要数字"))?;
+                if let Some(min) = field.get("min").and_then(Value::as_f64) {
+                    if n < min {
+                        return Err(invalid(key, &format!("不能小于 {min}")));
+                    }
+                }
+                if let Some(max) = field.get("max").and_then(Value::as_f64) {
+                    if n > max {
+                        return Err(invalid(key, &format!("不能大于 {max}")));
+                    }
+                }
+                match value {
+                    Value::Number(_) => value.clone(),
+                    _ if n.fract() == 0.0 && n.abs() < 9.0e15 => Value::from(n as i64),
+                    _ => Value::from(n),
+                }
+            }
+            "bool" | "boolean" => match value {
+                Value::Bool(_) => value.clone(),
+                Value::String(s) if s == "true" || s == "false" => Value::Bool(s == "true"),
+                _ => return Err(invalid(key, "需要布尔值")),
+            },
+            "select" => {
+                let options = field.get("options").and_then(Value::as_array);
+                if let Some(options) = options {
+                    if !options.iter().any(|o| option_matches(o, value)) {
+                        return Err(invalid(key, "不是可选值之一"));
+                    }
+                }
+                value.clone()
+            }
+            _ => match value {
+                Value::String(_) => value.clone(),
+                Value::Number(_) | Value::Bool(_) => Value::String(value.to_string()),
+                _ => return Err(invalid(key, "需要字符串")),
+            },
+        };
+        out.insert(key.to_string(), normalized);
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillConfigWriteResult {
+    skill: String,
+    config: Map<String, Value>,
+    /// Whether a running backend acknowledged the reload request.
+    reloaded: bool,
+}
+
+/// Ask the workspace's running backend to reload one skill (or rescan all
+/// with `None`).  Returns false when no backend answered or it reported an
+/// error.
+pub(crate) async fn notify_backend_reload(workspace_id: &str, skill_name: Option<&str>) -> bool {
+    let port = read_workspace_api_port(workspace_id).unwrap_or(18900);
+    let body = serde_json::json!({ "skill_name": skill_name.unwrap_or("") }).to_string();
+    let headers = vec![("Content-Type".to_string(), "application/json".to_string())];
+    match backend_ipc::backend_request(
+        port,
+        "POST",
+        "/api/skills/reload",
+        &headers,
+        Some(body.as_bytes()),
+        Duration::from_secs(10),
+    )
+    .await
+    {
+        Ok(resp) => {
+            resp.is_success()
+                && resp
+                    .json()
+                    .map(|v| v.get("error").is_none())
+                    .unwrap_or(false)
+        }
+        Err(_) => false,
+    }
+}
+
+/// Validate and persist a skill's configuration.  `values_json` is a JSON
+/// object keyed by schema `key`; `reload` (default true) asks the
+/// workspace's running backend to reload the skill afterwards.
+#[tauri::command]
+pub async fn openakita_set_skill_config(
+    venv_dir: String,
+    workspace_id: String,
+    skill_name: String,
+    values_json: String,
+    reload: Option<bool>,
+) -> CmdResult<SkillConfigWriteResult> {
+    observer::ensure_writable(&workspace_id)?;
+    let values: Map<String, Value> = serde_json::from_str(&values_json)
+        .map_err(|e| format!("SKILL_CONFIG_INVALID|values_json 不是 JSON 对象: {e}"))?;
+    let ws = workspace_id.clone();
+    let name = skill_name.clone();
+    let config = spawn_blocking_result(move || {
+        let wd = workspace_dir(&ws).to_string_lossy().to_string();
+        let schema_out = run_python_module_json(
+            &venv_dir,
+            "openakita.setup_center.bridge",
+            &[
+                "get-skill-config",
+                "--workspace-dir",
+                &wd,
+                "--skill-name",
+                &name,
+            ],
+            &[],
+        )?;
+        let schema: Value = serde_json::from_str(&schema_out)
+            .map_err(|e| format!("get-skill-config output: {e}"))?;
+        let schema = schema
+            .get("config")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        let config = validate_skill_config(&schema, &values)?;
+        // 值可能含密钥：经 stdin 传给 bridge，不走命令行参数或环境变量
+        run_bridge_with_secrets(
+            &venv_dir,
+            &[
+                "set-skill-config",
+                "--workspace-dir",
+                &wd,
+                "--skill-name",
+                &name,
+            ],
+            &serde_json::json!({ "values": config }),
+        )?;
+        bridge_cache::invalidate_workspace(&wd);
+        Ok(config)
+    })
+    .await?;
+
+    let reloaded =
+        reload.unwrap_or(true) && notify_backend_reload(&workspace_id, Some(&skill_name)).await;
+    Ok(SkillConfigWriteResult {
+        skill: skill_name,
+        config,
+        reloaded,
+    })
+}
+
+// ── Marketplace search ──
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketplaceSearchOptions {
+    /// Matches an item's `category` or any of its `tags` (case-insensitive).
+    #[serde(default)]
+    pub category: Option<String>,
+    /// `relevance` (default with a query) | `stars` (default without) | `name` | `updated`
+    #[serde(default)]
+    pub sort: Option<String>,
+    /// Bypass the catalog cache.
+    #[serde(default)]
+    pub refresh: bool,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketplaceSearchResult {
+    total: usize,
+    items: Vec<Value>,
+    /// All categories present in the catalog, for the filter dropdown.
+    categories: Vec<String>,
+}
+
+fn item_str<'a>(item: &'a Value, key: &str) -> &'a str {
+    item.get(key).and_then(Value::as_str).unwrap_or("")
+}
+
+fn item_tags(item: &Value) -> Vec<&str> {
+    item.get("tags")
+        .and_then(Value::as_array)
+        .map(|tags| tags.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default()
+}
+
+/// Relevance of `item` for a lower-cased, non-empty `query`; 0 = no match.
+fn relevance(item: &Value, query: &str) -> u32 {
+    let name = item_str(item, "name").to_lowercase();
+    let mut score = if name == query {
+        100
+    } else if name.starts_with(query) {
+        60
+    } else if name.contains(query) {
+        40
+    } else {
+        0
+    };
+    if item_tags(item)
+        .iter()
+        .any(|t| t.to_lowercase().contains(query))
+    {
+        score += 20;
+    }
+    if item_str(item, "description").to_lowercase().contains(query) {
+        score += 10;
+    }
+    if item_str(item, "author").to_lowercase().contains(query) {
+        score += 5;
+    }
+    score
+}
+
+/// Filter and rank a catalog (JSON array of skill objects).
+pub(crate) fn search_catalog(
+    catalog: &[Value],
+    query: &str,
+    opts: &MarketplaceSearchOptions,
+) -> Vec<Value> {
+    let query = query.trim().to_lowercase();
+    let category = opts
+        .category
+        .as_deref()
+        .map(|c| c.trim().to_lowercase())
+        .filter(|c| !c.is_empty());
+    let mut hits: Vec<(u32, &Value)> = catalog
+        .iter()
+        .filter(|item| match category {
+            None => true,
+            Some(ref c) => {
+                item_str(item, "category").to_lowercase() == *c
+                    || item_tags(item).iter().any(|t| t.to_lowercase() == *c)
+            }
+        })
+        .filter_map(|item| {
+            if query.is_empty() {
+                return Some((0, item));
+            }
+            let score = relevance(item, &query);
+            (score > 0).then_some((score, item))
+        })
+        .collect();
+    let stars = |v: &Value| v.get("stars").and_then(Value::as_u64).unwrap_or(0);
+    let updated = |v: &Value| {
+        v.get("updated_at")
+            .or_else(|| v.get("updatedAt"))
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string()
+    };
+    let sort = opts.sort.as_deref().unwrap_or(if query.is_empty() {
+        "stars"
+    } else {
+        "relevance"
+    });
+    match sort {
+        "name" => hits.sort_by_key(|(_, v)| item_str(v, "name").to_lowercase()),
+        "stars" => hits.sort_by_key(|(_, v)| std::cmp::Reverse(stars(v))),
+        "updated" => hits.sort_by_key(|(_, v)| std::cmp::Reverse(updated(v))),
+        _ => hits.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| stars(b.1).cmp(&stars(a.1)))),
+    }
+    hits.into_iter().map(|(_, v)| v.clone()).collect()
+}
+
+fn catalog_categories(catalog: &[Value]) -> Vec<String> {
+    let mut cats: Vec<String> = catalog
+        .iter()
+        .map(|item| item_str(item, "category").to_string())
+        .filter(|c| !c.is_empty())
+        .collect();
+    cats.sort();
+    cats.dedup();
+    cats
+}
+
+/// Search the cached marketplace catalog.  The catalog is fetched (and
+/// cached) on first use or when `options.refresh` is set.
+#[tauri::command]
+pub async fn search_marketplace(
+    venv_dir: String,
+    query: String,
+    options: Option<MarketplaceSearchOptions>,
+) -> CmdResult<MarketplaceSearchResult> {
+    let opts = options.unwrap_or_default();
+    let raw = spawn_blocking_result({
+        let refresh = opts.refresh;
+        move || load_marketplace_catalog(&venv_dir, refresh)
+    })
+    .await?;
+    let catalog: Vec<Value> = match serde_json::from_str::<Value>(&raw) {
+        Ok(Value::Array(items)) => items,
+        Ok(Value::Object(mut obj)) => match obj.remove("skills") {
+            Some(Value::Array(items)) => items,
+            _ => Vec::new(),
+        },
+        _ => return Err(format!("marketplace catalog is not a JSON array: {raw}").into()),
+    };
+    let mut items = search_catalog(&catalog, &query, &opts);
+    let total = items.len();
+    if let Some(limit) = opts.limit {
+        items.truncate(limit);
+    }
+    Ok(MarketplaceSearchResult {
+        total,
+        items,
+        categories: catalog_categories(&catalog),
+    })
+}
+
+// ── Installed versions / rollback ──
+
+pub(crate) const SKILL_VERSIONS_KEPT: usize = 5;
+
+fn skill_versions_dir(workspace_id: &str, skill_rel: &str) -> PathBuf {
+    // 分类技能（skills/<category>/<id>）的 / 换成 __，保持单层目录
+    data_dirs::cache_root(workspace_id)
+        .join("skills")
+        .join(workspace_id)
+        .join(skill_rel.replace(['/', '\\'], "__"))
+}
+
+/// `version:` from the SKILL.md front matter, if any.
+pub(crate) fn skill_md_version(skill_md: &str) -> Option<String> {
+    let body = skill_md.strip_prefix("---")?;
+    let front = &body[..body.find("\n---")?];
+    front.lines().find_map(|l| {
+        let v = l.trim().strip_prefix("version:")?.trim();
+        let v = v.trim_matches(['"', '\'']);
+        (!v.is_empty()).then(|| v.to_string())
+    })
+}
+
+fn zip_dir(src: &Path, dest: &Path) -> Result<(), String> {
+    use std::io::Write as _;
+
+    fn walk(dir: &Path, out: &mut Vec<PathBuf>) {
+        let Ok(rd) = fs::read_dir(dir) else { return };
+        for entry in rd.flatten() {
+            let path = entry.path();
+            if entry.file_name() == ".git" {
+                continue;
+            }
+            if path.is_dir() {
+                walk(&path, out);
+            } else {
+                out.push(path);
+            }
+        }
+    }
+    let mut files = Vec::new();
+    walk(src, &mut files);
+    let file = fs::File::create(dest).map_err(|e| format!("create {}: {e}", dest.display()))?;
+    let mut zw = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for path in files {
+        let Ok(rel) = path.strip_prefix(src) else {
+            continue;
+        };
+        let name = rel.to_string_lossy().replace('\\', "/");
+        let data = fs::read(&path).map_err(|e| format!("read {}: {e}", path.display()))?;
+        zw.start_file(name, options)
+            .map_err(|e| format!("zip write: {e}"))?;
+        zw.write_all(&data).map_err(|e| format!("zip write: {e}"))?;
+    }
+    zw.finish().map_err(|e| format!("zip finish: {e}"))?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillVersion {
+    /// Archive id (epoch millis of the install).
+    pub(crate) id: u64,
+    installed_at: u64,
+    #[serde(default)]
+    pub(crate) version: Option<String>,
+    #[serde(default)]
+    origin: Option<String>,
+    /// Path of the skill relative to the workspace `skills/` dir.
+    pub(crate) skill_rel: String,
+}
+
+/// Marker left in a skill's version dir when it was uninstalled through the
+/// app, so drift detection doesn't report it as missing.
+const UNINSTALLED_MARKER: &str = "uninstalled";
+
+/// Snapshot a freshly installed skill into the version cache.
+/// Best effort: failures are logged, never fail the install.
+pub(crate) fn archive_installed_version(workspace_id: &str, skills_root: &Path, skill_dir: &Path) {
+    let Ok(rel) = skill_dir.strip_prefix(skills_root) else {
+        return;
+    };
+    let skill_rel = rel.to_string_lossy().replace('\\', "/");
+    let dir = skill_versions_dir(workspace_id, &skill_rel);
+    let _ = fs::remove_file(dir.join(UNINSTALLED_MARKER));
+    let id = now_ms();
+    let result = fs::create_dir_all(&dir)
+        .map_err(|e| format!("create {}: {e}", dir.display()))
+        .and_then(|_| zip_dir(skill_dir, &dir.join(format!("{id}.zip"))))
+        .and_then(|_| {
+            let meta = SkillVersion {
+                id,
+                installed_at: id / 1000,
+                version: fs::read_to_string(skill_dir.join("SKILL.md"))
+                    .ok()
+                    .and_then(|md| skill_md_version(&md)),
+                origin: fs::read_to_string(skill_dir.join(".openakita-source"))
+                    .ok()
+                    .map(|s| s.trim().to_string()),
+                skill_rel: skill_rel.clone(),
+            };
+            atomic_write(
+                dir.join(format!("{id}.json")),
+                serde_json::to_string_pretty(&meta).unwrap_or_default(),
+            )
+            .map_err(|e| format!("write version meta: {e}"))
+        });
+    if let Err(e) = result {
+        log_to_file(&format!("[skills] archive {skill_rel} failed: {e}"));
+        return;
+    }
+    let versions = list_versions(&dir);
+    for old in versions.iter().skip(SKILL_VERSIONS_KEPT) {
+        let _ = fs::remove_file(dir.join(format!("{}.zip", old.id)));
+        let _ = fs::remove_file(dir.join(format!("{}.json", old.id)));
+    }
+}
+
+/// Versions in `dir`, newest first.
+fn list_versions(dir: &Path) -> Vec<SkillVersion> {
+    let mut out: Vec<SkillVersion> = fs::read_dir(dir)
+        .map(|rd| {
+            rd.flatten()
+                .filter(|e| e.path().extension().is_some_and(|x| x == "json"))
+                .filter_map(|e| fs::read_to_string(e.path()).ok())
+                .filter_map(|s| serde_json::from_str(&s).ok())
+                .filter(|v: &SkillVersion| dir.join(format!("{}.zip", v.id)).is_file())
+                .collect()
+        })
+        .unwrap_or_default();
+    out.sort_by_key(|v| std::cmp::Reverse(v.id));
+    out
+}
+
+/// `skill_name` may be a plain id or `<category>/<id>`.
+fn find_versions_dir(workspace_id: &str, skill_name: &str) -> Result<PathBuf, String> {
+    let dir = skill_versions_dir(workspace_id, skill_name.trim_matches('/'));
+    if dir.is_dir() {
+        return Ok(dir);
+    }
+    // 只给了技能 id：在分类目录里找
+    let root = data_dirs::cache_root(workspace_id)
+        .join("skills")
+        .join(workspace_id);
+    let suffix = format!("__{skill_name}");
+    fs::read_dir(&root)
+        .ok()
+        .and_then(|rd| {
+            rd.flatten()
+                .map(|e| e.path())
+                .find(|p| p.is_dir() && p.to_string_lossy().ends_with(&suffix))
+        })
+        .ok_or_else(|| format!("SKILL_NO_HISTORY|没有 {skill_name} 的安装历史"))
+}
+
+/// Record that `skill_name` was uninstalled on purpose (history is kept for
+/// `rollback_skill`).
+pub(crate) fn mark_uninstalled(workspace_id: &str, skill_name: &str) {
+    if let Ok(dir) = find_versions_dir(workspace_id, skill_name) {
+        let _ = atomic_write(dir.join(UNINSTALLED_MARKER), now_ms().to_string());
+    }
+}
+
+/// Undo of an uninstall: the skill counts as installed again.
+pub(crate) fn clear_uninstalled(workspace_id: &str, skill_name: &str) {
+    if let Ok(dir) = find_versions_dir(workspace_id, skill_name) {
+        let _ = fs::remove_file(dir.join(UNINSTALLED_MARKER));
+    }
+}
+
+/// Latest recorded install of every skill the app installed into
+/// `workspace_id` and hasn't uninstalled since.
+pub(crate) fn recorded_installs(workspace_id: &str) -> Vec<SkillVersion> {
+    let root = data_dirs::cache_root(workspace_id)
+        .join("skills")
+        .join(workspace_id);
+    fs::read_dir(&root)
+        .map(|rd| {
+            rd.flatten()
+                .map(|e| e.path())
+                .filter(|p| p.is_dir() && !p.join(UNINSTALLED_MARKER).exists())
+                .filter_map(|p| list_versions(&p).into_iter().next())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn list_skill_versions(
+    workspace_id: String,
+    skill_name: String,
+) -> CmdResult<Vec<SkillVersion>> {
+    Ok(find_versions_dir(&workspace_id, &skill_name)
+        .map(|dir| list_versions(&dir))
+        .unwrap_or_default())
+}
+
+/// Restore the previously installed version of a skill.
+#[tauri::command]
+pub async fn rollback_skill(workspace_id: String, skill_name: String) -> CmdResult<SkillVersion> {
+    observer::ensure_writable(&workspace_id)?;
+    spawn_blocking_result(move || {
+        let dir = find_versions_dir(&workspace_id, &skill_name)?;
+        let versions = list_versions(&dir);
+        let (Some(current), Some(previous)) = (versions.first(), versions.get(1)) else {
+            return Err(format!("SKILL_NO_HISTORY|{skill_name} 没有可回滚的旧版本"));
+        };
+        let skills_root = workspace_dir(&workspace_id).join("skills");
+        let target = join_subdir(&skills_root, Some(&previous.skill_rel))?;
+
+        // 先解压到临时目录，成功后再替换，失败时原目录不动
+        let staging = std::env::temp_dir().join(format!("openakita-skill-rollback-{}", now_ms()));
+        let restored = staging.join("restored");
+        let result = extract_zip(&dir.join(format!("{}.zip", previous.id)), &restored)
+            .and_then(|_| check_skill_dir(restored.clone()))
+            .and_then(|_| {
+                let old = staging.join("replaced");
+                if target.exists() {
+                    fs::rename(&target, &old).map_err(|e| format!("move current: {e}"))?;
+                }
+                if let Some(parent) = target.parent() {
+                    let _ = fs::create_dir_all(parent);
+                }
+                if let Err(e) =
+                    fs::rename(&restored, &target).or_else(|_| copy_dir(&restored, &target))
+                {
+                    let _ = fs::remove_dir_all(&target);
+                    let _ = fs::rename(&old, &target);
+                    return Err(format!("restore {}: {e}", target.display()));
+                }
+                Ok(())
+            });
+        let _ = fs::remove_dir_all(&staging);
+        result?;
+        let _ = fs::remove_file(dir.join(format!("{}.zip", current.id)));
+        let _ = fs::remove_file(dir.join(format!("{}.json", current.id)));
+        log_to_file(&format!(
+            "[skills] rolled back {skill_name} to {} ({})",
+            previous.id,
+            previous.version.as_deref().unwrap_or("unversioned")
+        ));
+        Ok(previous.clone())
+    })
+    .await
+    .map_err(Into::into)
+}
+
+// ── Scaffold ──
+
+const SCAFFOLD_SKILL_MD: &str = r#"---
+name: {{name}}
+description: "{{description}}"
+license: MIT
+metadata:
+  author: {{author}}
+  version: "0.1.0"
+{{config}}---
+
+# {{title}}
+
+{{description}}
+
+## When to Use
+
+- 描述在什么情况下应使用这个技能
+- 例如：用户要求……时
+
+## Instructions
+
+1. 说明执行步骤
+{{usage}}"#;
+
+const SCAFFOLD_CONFIG: &str = r#"config:
+  - key: api_key
+    label: API Key
+    type: secret
+    required: false
+    help: 示例配置项，不需要可删除
+"#;
+
+const SCAFFOLD_PY_USAGE: &str = r#"2. 运行脚本：
+
+```bash
+python3 scripts/main.py "输入内容"
+```
+"#;
+
+const SCAFFOLD_PY_MAIN: &str = r#"#!/usr/bin/env python3
+"""{{title}} — 技能入口脚本。
+
+示例:
+    python3 scripts/main.py "输入内容"
+"""
+
+import argparse
+import json
+import sys
+
+
+def main() -> int:
+    parser = argparse.ArgumentParser(description="{{description}}")
+    parser.add_argument("input", help="输入内容")
+    args = parser.parse_args()
+
+    result = {"ok": True, "input": args.input}
+    json.dump(result, sys.stdout, ensure_ascii=False)
+    sys.stdout.write("\n")
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
+"#;
+
+const SCAFFOLD_NODE_USAGE: &str = r#"2. 运行脚本：
+
+```bash
+node scripts/main.js "输入内容"
+```
+"#;
+
+const SCAFFOLD_NODE_MAIN: &str = r#"#!/usr/bin/env node
+// {{title}} — 技能入口脚本。
+// 示例: node scripts/main.js "输入内容"
+
+const input = process.argv[2];
+if (!input) {
+  console.error("usage: node scripts/main.js <input>");
+  process.exit(2);
+}
+console.log(JSON.stringify({ ok: true, input }));
+"#;
+
+const SCAFFOLD_README: &str = r#"# {{title}}
+
+{{description}}
+
+- `SKILL.md` — 技能说明与元数据（name / description / config），Agent 按此调用
+{{readme_files}}
+修改 `SKILL.md` 后在 Setup Center 的技能页点击“重新加载”即可生效。
+"#;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillScaffoldOptions {
+    /// `basic` (default) | `python` | `node`
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+}
+
+/// Same rule as the Python `SkillMetadata._validate_name` simple form.
+pub(crate) fn is_valid_skill_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.split('-').all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        })
+}
+
+/// Render the files of a scaffold as `(relative path, content)`.
+pub(crate) fn render_scaffold(
+    name: &str,
+    opts: &SkillScaffoldOptions,
+) -> Result<Vec<(&'static str, String)>, String> {
+    let template = opts.template.as_deref().unwrap_or("basic");
+    let (entry, usage): (Option<(&'static str, &str)>, &str) = match template {
+        "basic" => (None, ""),
+        "python" => (
+            Some(("scripts/main.py", SCAFFOLD_PY_MAIN)),
+            SCAFFOLD_PY_USAGE,
+        ),
+        "node" => (
+            Some(("scripts/main.js", SCAFFOLD_NODE_MAIN)),
+            SCAFFOLD_NODE_USAGE,
+        ),
+        other => return Err(format!("未知的技能模板: {other}（basic | python | node）")),
+    };
+    let title = name
+        .split('-')
+        .map(|w| {
+            let mut c = w.chars();
+            c.next()
+                .map(|f| f.to_ascii_uppercase().to_string() + c.as_str())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    let description = opts
+        .description
+        .as_deref()
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .unwrap_or("TODO: 一句话描述这个技能做什么、何时使用")
+        .replace('"', "'");
+    let author = opts
+        .author
+        .as_deref()
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .unwrap_or("me");
+    let readme_files = entry
+        .map(|(path, _)| format!("- `{path}` — 入口脚本\n"))
+        .unwrap_or_default();
+    let fill = |tpl: &str| {
+        tpl.replace("{{name}}", name)
+            .replace("{{title}}", &title)
+            .replace("{{description}}", &description)
+            .replace("{{author}}", author)
+            .replace(
+                "{{config}}",
+                if entry.is_some() { SCAFFOLD_CONFIG } else { "" },
+            )
+            .replace("{{usage}}", usage)
+            .replace("{{readme_files}}", &readme_files)
+    };
+    let mut files = vec![
+        ("SKILL.md", fill(SCAFFOLD_SKILL_MD)),
+        ("README.md", fill(SCAFFOLD_README)),
+    ];
+    if let Some((path, tpl)) = entry {
+        files.push((path, fill(tpl)));
+    }
+    Ok(files)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillScaffoldResult {
+    skill_dir: String,
+    files: Vec<String>,
+    /// Whether a running backend picked the new skill up.
+    reloaded: bool,
+}
+
+/// Create a new skill in the workspace's `skills/` folder from a template.
+#[tauri::command]
+pub async fn create_skill_scaffold(
+    venv_dir: String,
+    workspace_id: String,
+    name: String,
+    options: Option<SkillScaffoldOptions>,
+) -> CmdResult<SkillScaffoldResult> {
+    observer::ensure_writable(&workspace_id)?;
+    let name = name.trim().to_string();
+    if !is_valid_skill_name(&name) {
+        return Err(format!(
+            "技能名只能包含小写字母、数字和连字符（≤64 字符），如 my-skill: {name}"
+        )
+        .into());
+    }
+    let opts = options.unwrap_or_default();
+    let files = render_scaffold(&name, &opts)?;
+    let ws = workspace_id.clone();
+    let (skill_dir, written) = spawn_blocking_result(move || {
+        let skill_dir = workspace_dir(&ws).join("skills").join(&name);
+        if skill_dir.exists() {
+            return Err(format!("技能目录已存在: {}", skill_dir.display()));
+        }
+        let mut written = Vec::new();
+        for (rel, content) in &files {
+            let path = skill_dir.join(rel);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("create dir: {e}"))?;
+            }
+            atomic_write(&path, content).map_err(|e| format!("write {}: {e}", path.display()))?;
+            written.push(rel.to_string());
+        }
+        let wd = workspace_dir(&ws).to_string_lossy().to_string();
+        let dir_str = skill_dir.to_string_lossy().to_string();
+        if let Err(e) = run_python_module_json(
+            &venv_dir,
+            "openakita.setup_center.bridge",
+            &[
+                "register-skill",
+                "--workspace-dir",
+                &wd,
+                "--skill-dir",
+                &dir_str,
+            ],
+            &[],
+        ) {
+            let _ = fs::remove_dir_all(&skill_dir);
+            return Err(e);
+        }
+        bridge_cache::invalidate_workspace(&wd);
+        Ok((dir_str, written))
+    })
+    .await?;
+    let reloaded = notify_backend_reload(&workspace_id, None).await;
+    Ok(SkillScaffoldResult {
+        skill_dir,
+        files: written,
+        reloaded,
+    })
+}
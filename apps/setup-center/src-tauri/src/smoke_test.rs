@@ -0,0 +1,325 @@
+//! First-run smoke test of a workspace's backend.
+//!
+//! Setup finishing does not mean the agent works: a wrong key, a model
+//! name the provider does not know or a broken tool runtime only show up
+//! on the first message — typically from an IM channel, where the user
+//! sees nothing.  `run_smoke_test(workspace_id)` walks the path once:
+//!
+//! 1. `backend` — starts the backend when it is not running (the same
+//!    start as the UI's, with the last venv);
+//! 2. `health` — waits for `/api/health`;
+//! 3. `chat` — one `POST /api/chat/sync` in ask mode asking for a token
+//!    back, which must answer within `timeoutSecs`;
+//! 4. `tool` — writes a file holding a random token under `data/smoke/`
+//!    and asks the agent to read it; only a real tool call can return the
+//!    token.
+//!
+//! Each finished step is also sent as a `smoke-test-step` event.  Later
+//! steps are skipped after a failure.  The two conversations stay in the
+//! backend's `api-sync` sessions.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::time::{Duration, Instant};
+
+use crate::errors::CmdResult;
+use crate::{
+    backend_ipc, crash_watch, emit_if_ui_live, launch_env, log_to_file, observer,
+    openakita_root_dir, openakita_service_start_impl, read_workspace_api_port,
+    run_lifecycle_command, set_backend_manually_stopped, workspace_dir, BACKEND_LIFECYCLE_LOCK,
+};
+
+const EVENT: &str = "smoke-test-step";
+const DEFAULT_TIMEOUT_SECS: u64 = 90;
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(120);
+const HEALTH_POLL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StepStatus {
+    Pass,
+    Fail,
+    Skip,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmokeStep {
+    /// "backend" | "health" | "chat" | "tool"
+    pub name: String,
+    pub status: StepStatus,
+    pub duration_ms: u64,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmokeReport {
+    pub workspace_id: String,
+    pub passed: bool,
+    /// The test started the backend (it was not running).
+    pub started_backend: bool,
+    pub steps: Vec<SmokeStep>,
+    pub duration_ms: u64,
+}
+
+fn random_token() -> String {
+    let mut buf = [0u8; 6];
+    let _ = getrandom::fill(&mut buf);
+    buf.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Verdict on a `/api/chat/sync` answer that should contain `token`.
+/// `strict`: the reply must contain it (the tool step); otherwise any
+/// non-empty reply passes and a missing token is only noted.
+pub(crate) fn judge_reply(
+    status: u16,
+    body: &Value,
+    token: &str,
+    strict: bool,
+) -> (StepStatus, Option<String>) {
+    let text = |k: &str| body.get(k).and_then(Value::as_str).unwrap_or("").trim();
+    match status {
+        202 => {
+            return (
+                StepStatus::Fail,
+                Some("工具调用需要审批（pending_approval），请检查工作区的权限策略".into()),
+            )
+        }
+        200..=299 => {}
+        _ => {
+            let reason = [text("message"), text("error")]
+                .into_iter()
+                .find(|s| !s.is_empty())
+                .unwrap_or("无错误信息")
+                .to_string();
+            return (StepStatus::Fail, Some(format!("HTTP {status}: {reason}")));
+        }
+    }
+    let reply = text("message");
+    if reply.is_empty() {
+        return (StepStatus::Fail, Some("模型返回了空回复".into()));
+    }
+    let snippet: String = reply.chars().take(200).collect();
+    match (reply.contains(token), strict) {
+        (true, _) => (StepStatus::Pass, Some(snippet)),
+        (false, true) => (
+            StepStatus::Fail,
+            Some(format!("回复里没有文件内容，工具似乎没有被调用：{snippet}")),
+        ),
+        (false, false) => (
+            StepStatus::Pass,
+            Some(format!("收到回复，但没有原样返回校验串：{snippet}")),
+        ),
+    }
+}
+
+async fn backend_healthy(port: u16) -> bool {
+    backend_ipc::backend_request(port, "GET", "/api/health", &[], None, HEALTH_POLL * 2)
+        .await
+        .is_ok_and(|r| r.is_success())
+}
+
+async fn chat(
+    port: u16,
+    conversation_id: &str,
+    mode: &str,
+    message: &str,
+    timeout: Duration,
+) -> Result<(u16, Value), String> {
+    let body = json!({
+        "message": message,
+        "conversation_id": conversation_id,
+        "mode": mode,
+    })
+    .to_string();
+    let headers = [("Content-Type".to_string(), "application/json".to_string())];
+    let resp = backend_ipc::backend_request(
+        port,
+        "POST",
+        "/api/chat/sync",
+        &headers,
+        Some(body.as_bytes()),
+        timeout,
+    )
+    .await?;
+    Ok((resp.status, resp.json().unwrap_or(Value::Null)))
+}
+
+struct Run {
+    app: tauri::AppHandle,
+    steps: Vec<SmokeStep>,
+}
+
+impl Run {
+    fn record(&mut self, name: &str, started: Instant, result: (StepStatus, Option<String>)) {
+        let step = SmokeStep {
+            name: name.to_string(),
+            status: result.0,
+            duration_ms: started.elapsed().as_millis() as u64,
+            detail: result.1,
+        };
+        emit_if_ui_live(&self.app, EVENT, step.clone());
+        self.steps.push(step);
+    }
+
+    fn failed(&self) -> bool {
+        self.steps.iter().any(|s| s.status == StepStatus::Fail)
+    }
+
+    fn skip(&mut self, name: &str) {
+        self.record(name, Instant::now(), (StepStatus::Skip, None));
+    }
+}
+
+/// Start (if needed) the workspace's backend and check that a chat message
+/// and a tool call go through.
+#[tauri::command]
+pub async fn run_smoke_test(
+    app: tauri::AppHandle,
+    workspace_id: String,
+    timeout_secs: Option<u64>,
+) -> CmdResult<SmokeReport> {
+    observer::ensure_writable(&workspace_id)?;
+    let ws_dir = workspace_dir(&workspace_id);
+    if !ws_dir.is_dir() {
+        return Err(format!("NOT_FOUND|工作区不存在: {workspace_id}").into());
+    }
+    let timeout = Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS).max(10));
+    let port = read_workspace_api_port(&workspace_id).unwrap_or(18900);
+    let begun = Instant::now();
+    let mut run = Run {
+        app: app.clone(),
+        steps: Vec::new(),
+    };
+
+    // ── 1. backend ──
+    let started = Instant::now();
+    let started_backend = !backend_healthy(port).await;
+    if started_backend {
+        let venv_dir = launch_env::last_venv_dir(&workspace_id).unwrap_or_else(|| {
+            openakita_root_dir()
+                .join("venv")
+                .to_string_lossy()
+                .to_string()
+        });
+        let ws = workspace_id.clone();
+        let result = run_lifecycle_command(&app, "start", workspace_id.clone(), move || {
+            {
+                let _lifecycle_guard = BACKEND_LIFECYCLE_LOCK.lock().unwrap();
+                set_backend_manually_stopped(&ws, false)?;
+            }
+            crash_watch::reset(&ws);
+            openakita_service_start_impl(venv_dir, ws)
+        })
+        .await;
+        match result {
+            Ok(status) => run.record(
+                "backend",
+                started,
+                (
+                    StepStatus::Pass,
+                    Some(format!("已启动，PID {:?}", status.pid)),
+                ),
+            ),
+            Err(e) => run.record(
+                "backend",
+                started,
+                (StepStatus::Fail, Some(format!("启动失败: {}", e.message))),
+            ),
+        }
+    } else {
+        run.record(
+            "backend",
+            started,
+            (StepStatus::Pass, Some("已在运行".into())),
+        );
+    }
+
+    // ── 2. health ──
+    if run.failed() {
+        run.skip("health");
+    } else {
+        let started = Instant::now();
+        let mut healthy = backend_healthy(port).await;
+        while !healthy && started.elapsed() < HEALTH_TIMEOUT {
+            tokio::time::sleep(HEALTH_POLL).await;
+            healthy = backend_healthy(port).await;
+        }
+        let result = if healthy {
+            (StepStatus::Pass, None)
+        } else {
+            (
+                StepStatus::Fail,
+                Some(format!(
+                    "{}s 内 /api/health 没有响应（端口 {port}）",
+                    HEALTH_TIMEOUT.as_secs()
+                )),
+            )
+        };
+        run.record("health", started, result);
+    }
+
+    // ── 3. chat ──
+    if run.failed() {
+        run.skip("chat");
+    } else {
+        let started = Instant::now();
+        let token = format!("SMOKE-{}", random_token());
+        let message = format!(
+            "这是一条连通性测试消息。请只回复下面这个校验串，不要添加任何其他内容：{token}"
+        );
+        let conversation = format!("smoke_{}", random_token());
+        let result = match chat(port, &conversation, "ask", &message, timeout).await {
+            Ok((status, body)) => judge_reply(status, &body, &token, false),
+            Err(e) => (StepStatus::Fail, Some(e)),
+        };
+        run.record("chat", started, result);
+    }
+
+    // ── 4. tool ──
+    if run.failed() {
+        run.skip("tool");
+    } else {
+        let started = Instant::now();
+        let token = format!("TOOL-{}", random_token());
+        let dir = ws_dir.join("data").join("smoke");
+        let file = dir.join(format!("smoke-{}.txt", random_token()));
+        let written = fs::create_dir_all(&dir).and_then(|_| fs::write(&file, &token));
+        let result = match written {
+            Err(e) => (StepStatus::Fail, Some(format!("写入测试文件失败: {e}"))),
+            Ok(()) => {
+                let message = format!(
+                    "这是一条工具调用测试。请用读取文件的工具读取 {}，然后只回复文件里的内容。",
+                    file.display()
+                );
+                let conversation = format!("smoke_tool_{}", random_token());
+                match chat(port, &conversation, "agent", &message, timeout).await {
+                    Ok((status, body)) => judge_reply(status, &body, &token, true),
+                    Err(e) => (StepStatus::Fail, Some(e)),
+                }
+            }
+        };
+        let _ = fs::remove_file(&file);
+        run.record("tool", started, result);
+    }
+
+    let passed = !run.failed();
+    log_to_file(&format!(
+        "[smoke-test] {workspace_id}: {} ({})",
+        if passed { "passed" } else { "failed" },
+        run.steps
+            .iter()
+            .map(|s| format!("{}={:?}", s.name, s.status))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    Ok(SmokeReport {
+        workspace_id,
+        passed,
+        started_backend,
+        steps: run.steps,
+        duration_ms: begun.elapsed().as_millis() as u64,
+    })
+}
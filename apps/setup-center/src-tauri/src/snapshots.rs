@@ -0,0 +1,477 @@
+//! Named snapshots of a workspace's setup, taken before risky changes.
+//!
+//! `create_snapshot(name)` records the current workspace (or
+//! `workspaceId`) under `<root>/snapshots/<name>/`:
+//!
+//! * `files/` — `.env` and its overlays, `data/llm_endpoints.json`,
+//!   `data/skills.json` (the enabled skill set), and the `identity/`,
+//!   `skills/`, `mcps/` and `data/mcp/` trees (`identity/runtime` is
+//!   runtime state and left out);
+//! * `packages.txt` — `pip freeze` of the venv the workspace's backend was
+//!   last started with, when that venv has its own Python;
+//! * `manifest.json` — what was captured.
+//!
+//! `restore_snapshot(name)` returns the workspace to that state: it first
+//! takes a `before-restore-<ts>` snapshot of the current one, stops the
+//! backend when it runs, rewrites the captured files and trees (removing
+//! ones that did not exist then), then installs the recorded package
+//! versions (`--no-deps`, the freeze is complete) and uninstalls packages
+//! added since.  Paths are re-checked with `path_repair` and the backend is
+//! started again if it was running.  A failed pip step does not undo the
+//! file restore; the report carries the error.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::errors::CmdResult;
+use crate::{
+    apply_no_window, atomic_write, compat, env_overlay, install_queue, invalidate_service_polls,
+    is_pid_file_valid, launch_env, log_to_file, mirrors, net, now_epoch_secs, observer,
+    openakita_root_dir, openakita_service_start_impl, path_repair, read_pid_file, read_state_file,
+    redact, run_streaming_command, service_stop_impl, set_backend_manually_stopped,
+    spawn_blocking_result, strip_harmful_python_env, venv_python_path, workspace_dir,
+    BACKEND_LIFECYCLE_LOCK, PIP_INSTALL_TOTAL_TIMEOUT_SECS, PIP_NETWORK_OPTIONS,
+};
+
+const MANIFEST: &str = "manifest.json";
+const PACKAGES: &str = "packages.txt";
+const FILES: &str = "files";
+/// Workspace files captured besides `.env` / `.env.*`.
+const CAPTURED_FILES: &[&str] = &["data/llm_endpoints.json", "data/skills.json"];
+const CAPTURED_DIRS: &[&str] = &["identity", "skills", "mcps", "data/mcp"];
+/// Never installed or removed by a restore.
+const PACKAGING_TOOLS: &[&str] = &["pip", "setuptools", "wheel"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotManifest {
+    pub name: String,
+    pub workspace_id: String,
+    /// Epoch seconds.
+    pub created_at: u64,
+    pub venv_dir: Option<String>,
+    pub openakita_version: Option<String>,
+    /// Packages in `packages.txt`; None when the venv was not captured.
+    pub packages: Option<usize>,
+    /// Captured paths, relative to the workspace.
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotRestoreReport {
+    pub name: String,
+    pub workspace_id: String,
+    /// Snapshot of the state before the restore.
+    pub safety_snapshot: String,
+    pub files_restored: usize,
+    /// Files that did not exist when the snapshot was taken.
+    pub files_removed: Vec<String>,
+    pub packages_installed: Vec<String>,
+    pub packages_removed: Vec<String>,
+    pub package_error: Option<String>,
+    pub backend_restarted: bool,
+    pub backend_error: Option<String>,
+}
+
+fn snapshots_dir() -> PathBuf {
+    openakita_root_dir().join("snapshots")
+}
+
+pub(crate) fn valid_name(name: &str) -> bool {
+    (1..=64).contains(&name.len())
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+fn snapshot_dir(name: &str) -> Result<PathBuf, String> {
+    if !valid_name(name) {
+        return Err(format!(
+            "INVALID_ARGUMENT|快照名只能包含字母、数字、- _ .，且不超过 64 个字符: {name}"
+        ));
+    }
+    Ok(snapshots_dir().join(name))
+}
+
+/// PEP 503 normalized project name.
+fn normalize(name: &str) -> String {
+    name.trim().to_ascii_lowercase().replace(['_', '.'], "-")
+}
+
+/// `pip freeze` output as normalized name → requirement line.  Editable
+/// installs (`-e`) and options are skipped: they can't be reinstalled from
+/// the line alone.
+pub(crate) fn parse_freeze(text: &str) -> BTreeMap<String, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with('-'))
+        .filter_map(|l| {
+            let name = l.split(['=', ' ', '@']).next()?;
+            (l.contains("==") || l.contains(" @ ")).then(|| (normalize(name), l.to_string()))
+        })
+        .collect()
+}
+
+/// Requirement lines to install and names to uninstall to get from
+/// `current` to `target`.
+pub(crate) fn plan_packages(
+    current: &BTreeMap<String, String>,
+    target: &BTreeMap<String, String>,
+) -> (Vec<String>, Vec<String>) {
+    let tool = |name: &str| PACKAGING_TOOLS.contains(&name);
+    let install = target
+        .iter()
+        .filter(|(name, line)| !tool(name) && current.get(*name) != Some(*line))
+        .map(|(_, line)| line.clone())
+        .collect();
+    let remove = current
+        .keys()
+        .filter(|name| !tool(name) && !target.contains_key(*name))
+        .cloned()
+        .collect();
+    (install, remove)
+}
+
+fn pip_command(venv_dir: &str) -> Command {
+    let mut cmd = Command::new(venv_python_path(venv_dir));
+    apply_no_window(&mut cmd);
+    strip_harmful_python_env(&mut cmd);
+    cmd.env("PYTHONUTF8", "1");
+    cmd.env("PYTHONIOENCODING", "utf-8");
+    cmd.args(["-m", "pip"]);
+    cmd
+}
+
+fn freeze(venv_dir: &str) -> Result<String, String> {
+    let out = pip_command(venv_dir)
+        .args(["freeze", "--disable-pip-version-check"])
+        .output()
+        .map_err(|e| format!("pip freeze failed to start: {e}"))?;
+    if !out.status.success() {
+        return Err(format!(
+            "pip freeze failed ({}): {}",
+            out.status,
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+fn run_pip(mut cmd: Command, header: &str) -> Result<(), String> {
+    cmd.env("PIP_NO_INPUT", "1");
+    let mut log = String::new();
+    let status = run_streaming_command(
+        cmd,
+        header,
+        Some(&mut log),
+        None,
+        std::time::Duration::from_secs(PIP_INSTALL_TOTAL_TIMEOUT_SECS),
+    )?;
+    if status.success() {
+        return Ok(());
+    }
+    let tail: String = log
+        .lines()
+        .rev()
+        .take(20)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect::<Vec<_>>()
+        .join("\n");
+    Err(redact::redact(&format!(
+        "{header} 失败（{status}）\n{tail}"
+    )))
+}
+
+/// The venv the workspace's backend runs from, if it has its own Python.
+fn workspace_venv(workspace_id: &str) -> Option<String> {
+    let venv = launch_env::last_venv_dir(workspace_id).unwrap_or_else(|| {
+        openakita_root_dir()
+            .join("venv")
+            .to_string_lossy()
+            .to_string()
+    });
+    venv_python_path(&venv).exists().then_some(venv)
+}
+
+fn excluded(rel: &str) -> bool {
+    rel == "identity/runtime"
+        || rel.starts_with("identity/runtime/")
+        || rel
+            .split('/')
+            .any(|c| c == "__pycache__" || c == "node_modules")
+}
+
+/// Workspace-relative paths of the files to capture.
+fn captured_files(ws_dir: &Path) -> Vec<String> {
+    fn walk(ws_dir: &Path, dir: &Path, out: &mut Vec<String>) {
+        let Ok(rd) = fs::read_dir(dir) else { return };
+        for entry in rd.flatten() {
+            let path = entry.path();
+            let Ok(rel) = path.strip_prefix(ws_dir) else {
+                continue;
+            };
+            let rel = rel.to_string_lossy().replace('\\', "/");
+            match entry.file_type() {
+                _ if excluded(&rel) => {}
+                Ok(ft) if ft.is_dir() => walk(ws_dir, &path, out),
+                Ok(ft) if ft.is_file() => out.push(rel),
+                _ => {}
+            }
+        }
+    }
+    let mut files: Vec<String> = env_overlay::env_files(ws_dir)
+        .iter()
+        .filter_map(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+        // .env.bak 之类是写入时留的备份，不算配置
+        .filter(|n| !n.ends_with(".bak") && !n.ends_with(".tmp"))
+        .collect();
+    files.extend(
+        CAPTURED_FILES
+            .iter()
+            .filter(|f| ws_dir.join(f).is_file())
+            .map(|f| f.to_string()),
+    );
+    for dir in CAPTURED_DIRS {
+        walk(ws_dir, &ws_dir.join(dir), &mut files);
+    }
+    files.sort();
+    files
+}
+
+fn copy_file(src: &Path, dst: &Path) -> Result<(), String> {
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("create {}: {e}", parent.display()))?;
+    }
+    fs::copy(src, dst)
+        .map(|_| ())
+        .map_err(|e| format!("copy {} → {}: {e}", src.display(), dst.display()))
+}
+
+fn read_manifest(dir: &Path) -> Option<SnapshotManifest> {
+    serde_json::from_str(&fs::read_to_string(dir.join(MANIFEST)).ok()?).ok()
+}
+
+fn current_workspace(workspace_id: Option<String>) -> Result<String, String> {
+    workspace_id
+        .or_else(|| read_state_file().current_workspace_id)
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| "INVALID_ARGUMENT|没有当前工作区，请指定 workspaceId".to_string())
+}
+
+fn create(name: &str, workspace_id: &str) -> Result<SnapshotManifest, String> {
+    let dir = snapshot_dir(name)?;
+    if dir.exists() {
+        return Err(format!("ALREADY_EXISTS|快照已存在: {name}"));
+    }
+    let ws_dir = workspace_dir(workspace_id);
+    if !ws_dir.is_dir() {
+        return Err(format!("NOT_FOUND|工作区不存在: {workspace_id}"));
+    }
+    // 先写到临时目录，完整了再改名，半截快照不会被当成可恢复的
+    let tmp = snapshots_dir().join(format!(".{name}.tmp"));
+    let _ = fs::remove_dir_all(&tmp);
+    let result = (|| {
+        let files = captured_files(&ws_dir);
+        for rel in &files {
+            copy_file(&ws_dir.join(rel), &tmp.join(FILES).join(rel))?;
+        }
+        let venv_dir = workspace_venv(workspace_id);
+        let mut packages = None;
+        if let Some(venv) = &venv_dir {
+            let text = freeze(venv)?;
+            packages = Some(parse_freeze(&text).len());
+            atomic_write(tmp.join(PACKAGES), text)?;
+        }
+        let manifest = SnapshotManifest {
+            name: name.to_string(),
+            workspace_id: workspace_id.to_string(),
+            created_at: now_epoch_secs(),
+            openakita_version: venv_dir.as_deref().and_then(compat::venv_openakita_version),
+            venv_dir,
+            packages,
+            files,
+        };
+        let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+        atomic_write(tmp.join(MANIFEST), json)?;
+        fs::rename(&tmp, &dir).map_err(|e| format!("rename snapshot: {e}"))?;
+        Ok(manifest)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&tmp);
+    }
+    if let Ok(m) = &result {
+        log_to_file(&format!(
+            "[snapshot] created {name} of {workspace_id}: {} file(s), {:?} package(s)",
+            m.files.len(),
+            m.packages
+        ));
+    }
+    result
+}
+
+/// Put the snapshot's files back; returns (restored, removed).
+fn restore_files(
+    snapshot: &Path,
+    manifest: &SnapshotManifest,
+    ws_dir: &Path,
+) -> Result<(usize, Vec<String>), String> {
+    let removed: Vec<String> = captured_files(ws_dir)
+        .into_iter()
+        .filter(|rel| !manifest.files.contains(rel))
+        .collect();
+    for rel in &removed {
+        fs::remove_file(ws_dir.join(rel)).map_err(|e| format!("remove {rel}: {e}"))?;
+    }
+    for rel in &manifest.files {
+        copy_file(&snapshot.join(FILES).join(rel), &ws_dir.join(rel))?;
+    }
+    Ok((manifest.files.len(), removed))
+}
+
+fn restore_packages(
+    app: &tauri::AppHandle,
+    snapshot: &Path,
+    venv_dir: &str,
+    report: &mut SnapshotRestoreReport,
+) -> Result<(), String> {
+    if !venv_python_path(venv_dir).exists() {
+        return Err(format!("venv 已不存在，跳过依赖恢复: {venv_dir}"));
+    }
+    let target =
+        fs::read_to_string(snapshot.join(PACKAGES)).map_err(|e| format!("read {PACKAGES}: {e}"))?;
+    let _slot = install_queue::acquire(app, venv_dir, "pip", "snapshot-restore", None)?;
+    let (install, remove) =
+        plan_packages(&parse_freeze(&freeze(venv_dir)?), &parse_freeze(&target));
+    if !remove.is_empty() {
+        let mut cmd = pip_command(venv_dir);
+        cmd.args(["uninstall", "-y"]).args(&remove);
+        run_pip(cmd, "pip uninstall")?;
+        report.packages_removed = remove;
+    }
+    if !install.is_empty() {
+        net::ensure_online("恢复快照依赖")?;
+        let mut cmd = pip_command(venv_dir);
+        cmd.args(["install", "--no-deps"]).args(&install);
+        cmd.args(PIP_NETWORK_OPTIONS);
+        if let Some(index) = mirrors::current().pypi_index {
+            cmd.args(["-i", &index]);
+        }
+        run_pip(cmd, "pip install")?;
+        report.packages_installed = install;
+    }
+    compat::invalidate();
+    Ok(())
+}
+
+fn restore(app: &tauri::AppHandle, name: &str) -> Result<SnapshotRestoreReport, String> {
+    let dir = snapshot_dir(name)?;
+    let manifest = read_manifest(&dir).ok_or_else(|| format!("NOT_FOUND|快照不存在: {name}"))?;
+    let ws = manifest.workspace_id.clone();
+    observer::ensure_writable(&ws)?;
+    let ws_dir = workspace_dir(&ws);
+    if !ws_dir.is_dir() {
+        return Err(format!("NOT_FOUND|快照所属的工作区不存在: {ws}"));
+    }
+    let safety = format!("before-restore-{}", now_epoch_secs());
+    create(&safety, &ws)?;
+    let mut report = SnapshotRestoreReport {
+        name: name.to_string(),
+        workspace_id: ws.clone(),
+        safety_snapshot: safety,
+        ..Default::default()
+    };
+
+    let was_running = read_pid_file(&ws).is_some_and(|d| is_pid_file_valid(&d));
+    if was_running {
+        let stopped = service_stop_impl(ws.clone()).map_err(String::from);
+        invalidate_service_polls(&ws);
+        stopped?;
+    }
+
+    let (restored, removed) = restore_files(&dir, &manifest, &ws_dir)?;
+    report.files_restored = restored;
+    report.files_removed = removed;
+    path_repair::verify(&ws_dir);
+
+    let venv_dir = manifest
+        .venv_dir
+        .clone()
+        .or_else(|| workspace_venv(&ws))
+        .unwrap_or_default();
+    if manifest.packages.is_some() {
+        if let Err(e) = restore_packages(app, &dir, &venv_dir, &mut report) {
+            log_to_file(&format!("[snapshot] restore {name}: packages: {e}"));
+            report.package_error = Some(e);
+        }
+    }
+
+    if was_running {
+        let started = {
+            let _lifecycle_guard = BACKEND_LIFECYCLE_LOCK.lock().unwrap();
+            set_backend_manually_stopped(&ws, false)
+        }
+        .and_then(|_| openakita_service_start_impl(venv_dir, ws.clone()));
+        report.backend_restarted = started.is_ok();
+        report.backend_error = started.err();
+    }
+    log_to_file(&format!(
+        "[snapshot] restored {name} into {ws}: {} file(s), -{} file(s), +{} / -{} package(s)",
+        report.files_restored,
+        report.files_removed.len(),
+        report.packages_installed.len(),
+        report.packages_removed.len()
+    ));
+    Ok(report)
+}
+
+/// Snapshot the current workspace (or `workspace_id`) under `name`.
+#[tauri::command]
+pub async fn create_snapshot(
+    name: String,
+    workspace_id: Option<String>,
+) -> CmdResult<SnapshotManifest> {
+    spawn_blocking_result(move || create(&name, &current_workspace(workspace_id)?))
+        .await
+        .map_err(Into::into)
+}
+
+/// Return the snapshot's workspace to the recorded state.
+#[tauri::command]
+pub async fn restore_snapshot(
+    app: tauri::AppHandle,
+    name: String,
+) -> CmdResult<SnapshotRestoreReport> {
+    spawn_blocking_result(move || restore(&app, &name))
+        .await
+        .map_err(Into::into)
+}
+
+/// All snapshots, newest first.
+#[tauri::command]
+pub fn list_snapshots() -> Vec<SnapshotManifest> {
+    let mut list: Vec<SnapshotManifest> = fs::read_dir(snapshots_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|e| read_manifest(&e.path()))
+        .collect();
+    list.sort_by_key(|m| std::cmp::Reverse(m.created_at));
+    list
+}
+
+#[tauri::command]
+pub fn delete_snapshot(name: String) -> CmdResult<()> {
+    let dir = snapshot_dir(&name)?;
+    if !dir.join(MANIFEST).is_file() {
+        return Err(format!("NOT_FOUND|快照不存在: {name}").into());
+    }
+    fs::remove_dir_all(&dir).map_err(|e| format!("delete snapshot {name}: {e}").into())
+}
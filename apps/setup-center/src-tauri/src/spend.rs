@@ -0,0 +1,543 @@
+//! API spend tracking across workspaces.
+//!
+//! Each backend records token usage per request in its own database and
+//! only answers for the last N days while it runs, so there was no way to
+//! see what a month of agent work cost.  A background thread pulls each
+//! running backend's per-endpoint totals (`/api/stats/tokens/summary`) for
+//! today and yesterday every 15 minutes — the last 31 days the first time
+//! a workspace is seen — and keeps daily aggregates in
+//! `~/.openakita/usage/daily.json`.  Days are UTC, as the backend stores
+//! them.
+//!
+//! Cost uses the user's per-model prices (`set_spend_config`) where given
+//! and the backend's own estimate otherwise.  With a daily or monthly
+//! budget set, crossing `alertAtPercent` and then 100% raises one system
+//! notification and a `spend-alert` event per period.
+//! `get_spend_report(range)` syncs first, then aggregates.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::errors::CmdResult;
+use crate::{
+    atomic_write, backend_ipc, emit_if_ui_live, is_pid_file_valid, log_to_file, notify,
+    now_epoch_secs, now_ms, openakita_root_dir, read_pid_file, read_state_file,
+    read_workspace_api_port, spawn_blocking_result, time_from_epoch, workspace_dir, workspaces_dir,
+    write_state_file, STATE_FILE_LOCK,
+};
+
+const EVENT: &str = "spend-alert";
+const SYNC_FIRST_DELAY: Duration = Duration::from_secs(2 * 60);
+const SYNC_INTERVAL: Duration = Duration::from_secs(15 * 60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Days pulled the first time a workspace shows up.
+const BACKFILL_DAYS: u64 = 31;
+const DAY_SECS: u64 = 86400;
+
+/// Serializes read-modify-write of the usage file (sync thread vs command).
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Price per million tokens.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPrice {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+    /// Cache reads, when the provider bills them separately.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_per_mtok: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SpendConfig {
+    /// Keyed by model id as configured on the endpoint.
+    pub prices: BTreeMap<String, ModelPrice>,
+    pub currency: String,
+    pub daily_budget: Option<f64>,
+    pub monthly_budget: Option<f64>,
+    /// First alert at this share of a budget; the second one at 100%.
+    pub alert_at_percent: u8,
+}
+
+impl Default for SpendConfig {
+    fn default() -> Self {
+        Self {
+            prices: BTreeMap::new(),
+            currency: "CNY".into(),
+            daily_budget: None,
+            monthly_budget: None,
+            alert_at_percent: 80,
+        }
+    }
+}
+
+/// One endpoint's usage on one day.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DailyUsage {
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub requests: u64,
+    /// The backend's `estimated_cost`, from its built-in price table.
+    pub backend_cost: f64,
+}
+
+/// day (`YYYY-MM-DD`) → workspace → endpoint → usage
+type Days = BTreeMap<String, BTreeMap<String, BTreeMap<String, DailyUsage>>>;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UsageStore {
+    pub days: Days,
+    /// Alerts already raised, e.g. `monthly:2026-10:80`.
+    pub alerts_sent: Vec<String>,
+    pub last_sync_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DaySpend {
+    pub date: String,
+    pub tokens: u64,
+    pub cost: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelSpend {
+    pub model: String,
+    pub endpoints: Vec<String>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub requests: u64,
+    pub cost: f64,
+    /// Whether `cost` comes from the user's price rather than the backend.
+    pub user_priced: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetStatus {
+    pub limit: f64,
+    pub spent: f64,
+    pub percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpendReport {
+    pub range: String,
+    pub start: String,
+    pub end: String,
+    pub currency: String,
+    pub total_tokens: u64,
+    pub total_cost: f64,
+    pub by_day: Vec<DaySpend>,
+    pub by_model: Vec<ModelSpend>,
+    pub daily_budget: Option<BudgetStatus>,
+    pub monthly_budget: Option<BudgetStatus>,
+    pub last_sync_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AlertEvent {
+    /// "daily" | "monthly"
+    period: String,
+    threshold: u8,
+    spent: f64,
+    limit: f64,
+    currency: String,
+}
+
+pub(crate) fn utc_date(epoch_secs: u64) -> String {
+    let (y, m, d, ..) = time_from_epoch(epoch_secs);
+    format!("{y:04}-{m:02}-{d:02}")
+}
+
+/// Cost of one day's usage: the user's price for the model if set, else
+/// the backend's estimate.
+pub(crate) fn usage_cost(u: &DailyUsage, prices: &BTreeMap<String, ModelPrice>) -> (f64, bool) {
+    match prices.get(&u.model) {
+        Some(p) => {
+            let cache = p.cache_read_per_mtok.unwrap_or(0.0) * u.cache_read_tokens as f64;
+            let cost = (p.input_per_mtok * u.input_tokens as f64
+                + p.output_per_mtok * u.output_tokens as f64
+                + cache)
+                / 1_000_000.0;
+            (cost, true)
+        }
+        None => (u.backend_cost, false),
+    }
+}
+
+/// Rows of `/api/stats/tokens/summary?group_by=endpoint_name`, with the
+/// model looked up from the workspace's endpoint config.
+pub(crate) fn parse_summary(
+    body: &Value,
+    models: &BTreeMap<String, String>,
+) -> BTreeMap<String, DailyUsage> {
+    let num = |row: &Value, k: &str| row.get(k).and_then(Value::as_u64).unwrap_or(0);
+    body.get("data")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|row| {
+            let endpoint = row
+                .get("group_key")
+                .and_then(Value::as_str)
+                .unwrap_or("(unknown)")
+                .to_string();
+            let usage = DailyUsage {
+                model: models.get(&endpoint).cloned().unwrap_or_default(),
+                input_tokens: num(row, "total_input"),
+                output_tokens: num(row, "total_output"),
+                cache_read_tokens: num(row, "total_cache_read"),
+                requests: num(row, "request_count"),
+                backend_cost: row.get("total_cost").and_then(Value::as_f64).unwrap_or(0.0),
+            };
+            (endpoint, usage)
+        })
+        .collect()
+}
+
+/// Aggregate `days` in `[start, end]` (inclusive dates).
+pub(crate) fn aggregate(
+    days: &Days,
+    start: &str,
+    end: &str,
+    workspace_id: Option<&str>,
+    prices: &BTreeMap<String, ModelPrice>,
+) -> (Vec<DaySpend>, Vec<ModelSpend>) {
+    let mut by_day = Vec::new();
+    let mut by_model: BTreeMap<String, ModelSpend> = BTreeMap::new();
+    for (date, workspaces) in days.range(start.to_string()..=end.to_string()) {
+        let mut day = DaySpend {
+            date: date.clone(),
+            ..Default::default()
+        };
+        for (ws, endpoints) in workspaces {
+            if workspace_id.is_some_and(|id| id != ws) {
+                continue;
+            }
+            for (endpoint, u) in endpoints {
+                let (cost, user_priced) = usage_cost(u, prices);
+                day.tokens += u.input_tokens + u.output_tokens;
+                day.cost += cost;
+                let key = if u.model.is_empty() {
+                    endpoint
+                } else {
+                    &u.model
+                };
+                let m = by_model.entry(key.clone()).or_insert_with(|| ModelSpend {
+                    model: key.clone(),
+                    user_priced,
+                    ..Default::default()
+                });
+                if !m.endpoints.contains(endpoint) {
+                    m.endpoints.push(endpoint.clone());
+                }
+                m.input_tokens += u.input_tokens;
+                m.output_tokens += u.output_tokens;
+                m.requests += u.requests;
+                m.cost += cost;
+            }
+        }
+        by_day.push(day);
+    }
+    let mut by_model: Vec<ModelSpend> = by_model.into_values().collect();
+    by_model.sort_by(|a, b| b.cost.total_cmp(&a.cost));
+    (by_day, by_model)
+}
+
+/// `(start, end)` dates of a report range ending today.
+pub(crate) fn range_dates(range: &str, now: u64) -> Result<(String, String), String> {
+    let today = utc_date(now);
+    let start = match range {
+        "today" => today.clone(),
+        "month" => format!("{}-01", &today[..7]),
+        "7d" => utc_date(now - 6 * DAY_SECS),
+        "30d" => utc_date(now - 29 * DAY_SECS),
+        "90d" => utc_date(now - 89 * DAY_SECS),
+        other => return Err(format!("INVALID_ARGUMENT|未知的统计范围: {other}")),
+    };
+    Ok((start, today))
+}
+
+fn store_path() -> PathBuf {
+    openakita_root_dir().join("usage").join("daily.json")
+}
+
+fn load_store() -> UsageStore {
+    fs::read_to_string(store_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_store(store: &UsageStore) -> Result<(), String> {
+    let json = serde_json::to_string(store).map_err(|e| format!("serialize usage: {e}"))?;
+    atomic_write(store_path(), json)
+}
+
+pub(crate) fn config() -> SpendConfig {
+    read_state_file().spend.unwrap_or_default()
+}
+
+/// Endpoint name → model from the workspace's `llm_endpoints.json`.
+fn endpoint_models(workspace_id: &str) -> BTreeMap<String, String> {
+    let path = workspace_dir(workspace_id)
+        .join("data")
+        .join("llm_endpoints.json");
+    let config: Value = fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or(Value::Null);
+    ["endpoints", "compiler_endpoints"]
+        .iter()
+        .filter_map(|k| config.get(*k).and_then(Value::as_array))
+        .flatten()
+        .filter_map(|ep| {
+            let name = ep.get("name")?.as_str()?;
+            let model = ep.get("model")?.as_str()?;
+            Some((name.to_string(), model.to_string()))
+        })
+        .collect()
+}
+
+fn running_workspaces() -> Vec<String> {
+    fs::read_dir(workspaces_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|id| read_pid_file(id).as_ref().is_some_and(is_pid_file_valid))
+        .collect()
+}
+
+fn pull_day(workspace_id: &str, date: &str) -> Result<BTreeMap<String, DailyUsage>, String> {
+    let port = read_workspace_api_port(workspace_id).unwrap_or(18900);
+    let path = format!(
+        "/api/stats/tokens/summary?group_by=endpoint_name&start={date}T00:00:00&end={date}T23:59:59"
+    );
+    let resp =
+        backend_ipc::backend_request_blocking(port, "GET", &path, &[], None, REQUEST_TIMEOUT)?;
+    let body = resp.json()?;
+    if let Some(err) = body.get("error").and_then(Value::as_str) {
+        return Err(format!("token stats: {err}"));
+    }
+    Ok(parse_summary(&body, &endpoint_models(workspace_id)))
+}
+
+/// Pull recent usage from every running backend into the store.
+fn sync() -> UsageStore {
+    let now = now_epoch_secs();
+    let mut pulled: Vec<(String, String, BTreeMap<String, DailyUsage>)> = Vec::new();
+    let known = load_store();
+    for ws in running_workspaces() {
+        let seen = known.days.values().any(|d| d.contains_key(&ws));
+        let n = if seen { 2 } else { BACKFILL_DAYS };
+        for back in 0..n {
+            let date = utc_date(now - back * DAY_SECS);
+            match pull_day(&ws, &date) {
+                Ok(usage) => pulled.push((date, ws.clone(), usage)),
+                Err(e) => {
+                    log_to_file(&format!("[spend] sync ws={ws} {date} failed: {e}"));
+                    break;
+                }
+            }
+        }
+    }
+    let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut store = load_store();
+    for (date, ws, usage) in pulled {
+        let day = store.days.entry(date).or_default();
+        if usage.is_empty() {
+            day.remove(&ws);
+        } else {
+            // 每次拉到的是当天全量，直接覆盖
+            day.insert(ws, usage);
+        }
+    }
+    store.days.retain(|_, d| !d.is_empty());
+    store.last_sync_ms = now_ms();
+    if let Err(e) = save_store(&store) {
+        log_to_file(&format!("[spend] save usage failed: {e}"));
+    }
+    store
+}
+
+fn budget(limit: Option<f64>, spent: f64) -> Option<BudgetStatus> {
+    limit.filter(|l| *l > 0.0).map(|limit| BudgetStatus {
+        limit,
+        spent,
+        percent: spent / limit * 100.0,
+    })
+}
+
+fn period_spend(store: &UsageStore, cfg: &SpendConfig, start: &str, end: &str) -> f64 {
+    let (days, _) = aggregate(&store.days, start, end, None, &cfg.prices);
+    days.iter().map(|d| d.cost).sum()
+}
+
+/// Alerts due for `status` that have not been raised yet; records them in
+/// `sent`.
+pub(crate) fn due_alerts(
+    period_key: &str,
+    status: &BudgetStatus,
+    alert_at_percent: u8,
+    sent: &mut Vec<String>,
+) -> Vec<u8> {
+    let mut due = Vec::new();
+    for threshold in [alert_at_percent.clamp(1, 100), 100] {
+        let key = format!("{period_key}:{threshold}");
+        if status.percent >= f64::from(threshold) && !sent.contains(&key) {
+            sent.push(key);
+            due.push(threshold);
+        }
+    }
+    due
+}
+
+fn check_budgets(app: &tauri::AppHandle, store: &mut UsageStore) {
+    let cfg = config();
+    let today = utc_date(now_epoch_secs());
+    let month_start = format!("{}-01", &today[..7]);
+    let periods = [
+        (
+            "daily",
+            format!("daily:{today}"),
+            cfg.daily_budget,
+            today.clone(),
+        ),
+        (
+            "monthly",
+            format!("monthly:{}", &today[..7]),
+            cfg.monthly_budget,
+            month_start,
+        ),
+    ];
+    let before = store.alerts_sent.len();
+    for (period, key, limit, start) in periods {
+        let Some(status) = budget(limit, period_spend(store, &cfg, &start, &today)) else {
+            continue;
+        };
+        for threshold in due_alerts(&key, &status, cfg.alert_at_percent, &mut store.alerts_sent) {
+            let label = if period == "daily" {
+                "今日"
+            } else {
+                "本月"
+            };
+            notify::send(
+                app,
+                "OpenAkita",
+                &format!(
+                    "{label} API 花费已达预算的 {threshold}%（{:.2} / {:.2} {}）",
+                    status.spent, status.limit, cfg.currency
+                ),
+            );
+            emit_if_ui_live(
+                app,
+                EVENT,
+                AlertEvent {
+                    period: period.to_string(),
+                    threshold,
+                    spent: status.spent,
+                    limit: status.limit,
+                    currency: cfg.currency.clone(),
+                },
+            );
+        }
+    }
+    if store.alerts_sent.len() != before {
+        // 只留本月的记录
+        store.alerts_sent.retain(|k| k.contains(&today[..7]));
+        let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut saved = load_store();
+        saved.alerts_sent = store.alerts_sent.clone();
+        if let Err(e) = save_store(&saved) {
+            log_to_file(&format!("[spend] save alerts failed: {e}"));
+        }
+    }
+}
+
+pub(crate) fn spawn_sync(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        std::thread::sleep(SYNC_FIRST_DELAY);
+        loop {
+            let mut store = sync();
+            check_budgets(&app, &mut store);
+            std::thread::sleep(SYNC_INTERVAL);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_spend_config() -> SpendConfig {
+    config()
+}
+
+#[tauri::command]
+pub fn set_spend_config(config: SpendConfig) -> CmdResult<()> {
+    let bad_price = config.prices.iter().find(|(_, p)| {
+        p.input_per_mtok < 0.0
+            || p.output_per_mtok < 0.0
+            || p.cache_read_per_mtok.is_some_and(|c| c < 0.0)
+    });
+    if let Some((model, _)) = bad_price {
+        return Err(format!("INVALID_ARGUMENT|{model} 的价格不能为负数").into());
+    }
+    if config.alert_at_percent == 0 || config.alert_at_percent > 100 {
+        return Err("INVALID_ARGUMENT|alertAtPercent 需在 1-100 之间".into());
+    }
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    state.spend = Some(config);
+    write_state_file(&state).map_err(Into::into)
+}
+
+/// Spend over `range` ("today" | "7d" | "30d" | "90d" | "month"), for one
+/// workspace or all.  Pulls fresh numbers from running backends first.
+#[tauri::command]
+pub async fn get_spend_report(
+    range: String,
+    workspace_id: Option<String>,
+) -> CmdResult<SpendReport> {
+    spawn_blocking_result(move || {
+        let now = now_epoch_secs();
+        let (start, end) = range_dates(&range, now)?;
+        let store = sync();
+        let cfg = config();
+        let ws = workspace_id.as_deref().filter(|id| !id.is_empty());
+        let (by_day, by_model) = aggregate(&store.days, &start, &end, ws, &cfg.prices);
+        let month_start = format!("{}-01", &end[..7]);
+        Ok(SpendReport {
+            total_tokens: by_day.iter().map(|d| d.tokens).sum(),
+            total_cost: by_day.iter().map(|d| d.cost).sum(),
+            daily_budget: budget(cfg.daily_budget, period_spend(&store, &cfg, &end, &end)),
+            monthly_budget: budget(
+                cfg.monthly_budget,
+                period_spend(&store, &cfg, &month_start, &end),
+            ),
+            range,
+            start,
+            end,
+            currency: cfg.currency,
+            by_day,
+            by_model,
+            last_sync_ms: store.last_sync_ms,
+        })
+    })
+    .await
+    .map_err(Into::into)
+}
@@ -0,0 +1,226 @@
+//! Startup permission / environment self-check.
+//!
+//! Permission problems used to surface much later and far from their cause:
+//! a "write pid file" error on the first service start, a migration that
+//! silently didn't persist.  Once per session (kicked off from `setup` right
+//! after the startup reconcile) this module probes every directory the app
+//! writes to — `~/.openakita`, `run/`, the runtime root and each workspace
+//! (plus its `data/`) — by creating, syncing and deleting a small file.
+//!
+//! On Windows it also asks Defender whether Controlled Folder Access is on:
+//! with it enabled, writes from an unlisted app into protected folders fail
+//! with a plain "access denied", so a failed probe (or a root inside a
+//! protected folder) is reported with the allowlisting hint instead.
+//!
+//! `get_startup_issues({refresh})` returns the findings; an empty list means
+//! everything is writable.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::errors::CmdResult;
+use crate::{
+    log_to_file, openakita_root_dir, run_dir, runtime_root_dir, spawn_blocking_result,
+    workspaces_dir,
+};
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupIssue {
+    /// `NOT_WRITABLE` | `NOT_A_DIRECTORY` | `READ_ONLY_FS` | `IO_ERROR` |
+    /// `CONTROLLED_FOLDER_ACCESS`
+    pub code: String,
+    /// `error` (the app will fail when it gets there) | `warning`
+    pub severity: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub message: String,
+    pub hint: String,
+}
+
+static STARTUP_ISSUES: Lazy<Mutex<Option<Vec<StartupIssue>>>> = Lazy::new(|| Mutex::new(None));
+
+fn issue(code: &str, severity: &str, path: &Path, message: String, hint: &str) -> StartupIssue {
+    StartupIssue {
+        code: code.to_string(),
+        severity: severity.to_string(),
+        path: Some(path.to_string_lossy().to_string()),
+        message,
+        hint: hint.to_string(),
+    }
+}
+
+/// Create `dir` if needed and prove a file can be written, synced and
+/// removed there.  `None` when it can.
+pub(crate) fn probe_dir(dir: &Path) -> Option<StartupIssue> {
+    if dir.exists() && !dir.is_dir() {
+        return Some(issue(
+            "NOT_A_DIRECTORY",
+            "error",
+            dir,
+            format!("{} 是文件而不是目录", dir.display()),
+            "删除或改名这个文件后重启应用 / remove or rename the file and restart",
+        ));
+    }
+    let probe = dir.join(format!(".oa-write-probe-{}", std::process::id()));
+    let result = fs::create_dir_all(dir).and_then(|_| {
+        let mut f = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&probe)?;
+        f.write_all(b"probe")?;
+        f.sync_all()
+    });
+    let _ = fs::remove_file(&probe);
+    let e = result.err()?;
+    #[cfg(unix)]
+    let read_only = e.raw_os_error() == Some(30); // EROFS
+    #[cfg(not(unix))]
+    let read_only = false;
+    Some(if read_only {
+        issue(
+            "READ_ONLY_FS",
+            "error",
+            dir,
+            format!("{} 所在的文件系统是只读的: {e}", dir.display()),
+            "把数据目录迁到可写的卷上 / move the data root to a writable volume",
+        )
+    } else if e.kind() == std::io::ErrorKind::PermissionDenied {
+        issue(
+            "NOT_WRITABLE",
+            "error",
+            dir,
+            format!("没有 {} 的写权限: {e}", dir.display()),
+            "检查目录所有者，或用「修复权限」把它交还当前用户；杀软 / 域策略也可能拦截 \
+             / fix the ownership (\"repair permissions\") or allowlist the folder in your antivirus",
+        )
+    } else {
+        issue(
+            "IO_ERROR",
+            "warning",
+            dir,
+            format!("写入 {} 失败: {e}", dir.display()),
+            "检查磁盘空间和目录状态 / check free disk space and the folder",
+        )
+    })
+}
+
+fn dirs_to_check() -> Vec<PathBuf> {
+    let mut dirs = vec![openakita_root_dir(), run_dir(), runtime_root_dir()];
+    if let Ok(rd) = fs::read_dir(workspaces_dir()) {
+        for ws in rd.flatten().map(|e| e.path()).filter(|p| p.is_dir()) {
+            dirs.push(ws.join("data"));
+            dirs.push(ws);
+        }
+    }
+    dirs
+}
+
+/// Whether Windows Defender's Controlled Folder Access is enabled (`1`) or
+/// in audit mode (`2` — logged, not blocked).  `None` when it can't be read.
+#[cfg(windows)]
+fn controlled_folder_access_mode() -> Option<u32> {
+    let mut c = std::process::Command::new("powershell");
+    c.args([
+        "-NoProfile",
+        "-NonInteractive",
+        "-Command",
+        "(Get-MpPreference).EnableControlledFolderAccess",
+    ]);
+    crate::apply_no_window(&mut c);
+    let out = c.output().ok()?;
+    String::from_utf8_lossy(&out.stdout).trim().parse().ok()
+}
+
+/// Folders Controlled Folder Access protects by default.
+#[cfg(windows)]
+fn cfa_protected_roots() -> Vec<PathBuf> {
+    [
+        dirs_next::document_dir(),
+        dirs_next::desktop_dir(),
+        dirs_next::picture_dir(),
+        dirs_next::video_dir(),
+        dirs_next::audio_dir(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+#[cfg(windows)]
+fn check_controlled_folder_access(root: &Path, failed: &[StartupIssue]) -> Option<StartupIssue> {
+    if controlled_folder_access_mode()? != 1 {
+        return None;
+    }
+    let in_protected = cfa_protected_roots().iter().any(|p| root.starts_with(p));
+    let denied = failed.iter().any(|i| i.code == "NOT_WRITABLE");
+    if !in_protected && !denied {
+        return None;
+    }
+    Some(issue(
+        "CONTROLLED_FOLDER_ACCESS",
+        if denied { "error" } else { "warning" },
+        root,
+        "Windows 安全中心的「受控文件夹访问」已开启，可能拦截 OpenAkita 的写入".to_string(),
+        "在 Windows 安全中心 → 勒索软件防护 → 允许应用通过受控文件夹访问 中添加 OpenAkita，\
+         或把数据目录移出 文档/桌面 / allow OpenAkita under Ransomware protection, \
+         or move the data root out of Documents/Desktop",
+    ))
+}
+
+fn run_checks() -> Vec<StartupIssue> {
+    let mut issues: Vec<StartupIssue> = Vec::new();
+    for dir in dirs_to_check() {
+        // 父目录已经报过的不再逐个重复
+        if issues
+            .iter()
+            .any(|i| i.path.as_deref().is_some_and(|p| dir.starts_with(p)))
+        {
+            continue;
+        }
+        if let Some(found) = probe_dir(&dir) {
+            issues.push(found);
+        }
+    }
+    #[cfg(windows)]
+    if let Some(cfa) = check_controlled_folder_access(&openakita_root_dir(), &issues) {
+        issues.push(cfa);
+    }
+    issues
+}
+
+/// Run the checks and remember the result for this session.
+pub(crate) fn run_startup_checks() -> Vec<StartupIssue> {
+    let issues = run_checks();
+    for i in &issues {
+        log_to_file(&format!(
+            "[startup_check] {} {}: {}",
+            i.severity, i.code, i.message
+        ));
+    }
+    *STARTUP_ISSUES.lock().unwrap_or_else(|e| e.into_inner()) = Some(issues.clone());
+    issues
+}
+
+/// Problems found by the startup self-check.  The first call in a session
+/// (or `refresh: true`) runs the checks; later calls return the cached list.
+#[tauri::command]
+pub async fn get_startup_issues(refresh: Option<bool>) -> CmdResult<Vec<StartupIssue>> {
+    if !refresh.unwrap_or(false) {
+        if let Some(cached) = STARTUP_ISSUES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+        {
+            return Ok(cached);
+        }
+    }
+    spawn_blocking_result(|| Ok(run_startup_checks()))
+        .await
+        .map_err(Into::into)
+}
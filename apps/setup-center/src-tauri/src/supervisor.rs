@@ -0,0 +1,456 @@
+//! Companion watchdog that keeps the backend up without the GUI.
+//!
+//! The heartbeat thread in `setup()` restarts a crashed backend only while
+//! Setup Center is open; after the window is quit a crash stays a crash.
+//! `install_watchdog(true)` registers `openakita-setup-center supervise`
+//! to start at login (HKCU `Run` key / LaunchAgent / XDG autostart) and
+//! spawns it right away.  The supervisor is the same executable without a
+//! window:
+//!
+//! * every [`CHECK_INTERVAL`] it starts the workspace's backend when it is
+//!   neither running nor manually stopped, with a breaker of
+//!   [`MAX_RESTARTS`] restarts per [`RESTART_WINDOW`];
+//! * while a Setup Center instance is attached it stands by and leaves
+//!   restarts to the GUI's own heartbeat;
+//! * it answers one-line JSON requests on a loopback port, recorded with a
+//!   random token in `run/watchdog.json` (`status`, `attach`, `shutdown`).
+//!
+//! `watchdog_status` asks it over that socket.  Only one supervisor runs
+//! at a time.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::errors::CmdResult;
+use crate::{
+    atomic_write, backend_was_manually_stopped, invalidate_service_polls, is_pid_running,
+    log_to_file, now_ms, openakita_service_start_impl, read_state_file, run_dir,
+    service_status_uncached, BACKEND_LIFECYCLE_LOCK,
+};
+
+const INFO_FILE: &str = "watchdog.json";
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+const MAX_RESTARTS: usize = 5;
+const RESTART_WINDOW: Duration = Duration::from_secs(15 * 60);
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(2);
+/// Name of the `Run` value / autostart entry.
+#[cfg(not(target_os = "macos"))]
+const ENTRY_NAME: &str = "OpenAkitaWatchdog";
+
+/// Written by the running supervisor to `run/watchdog.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WatchdogInfo {
+    pid: u32,
+    port: u16,
+    token: String,
+    workspace_id: String,
+    started_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SupervisorState {
+    pub workspace_id: String,
+    /// "supervising" | "standby" (GUI attached) | "manual-stop" | "breaker-open"
+    pub state: String,
+    pub gui_pid: Option<u32>,
+    pub restarts: u32,
+    pub last_restart_ms: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchdogStatus {
+    /// Starts at login.
+    pub installed: bool,
+    pub running: bool,
+    pub pid: Option<u32>,
+    pub started_at_ms: Option<u64>,
+    pub supervisor: Option<SupervisorState>,
+}
+
+static STATE: Mutex<Option<SupervisorState>> = Mutex::new(None);
+
+/// Restart times still inside `window` of `now`, and whether another
+/// restart is allowed.
+pub(crate) fn breaker(
+    restarts: &[u64],
+    now_ms: u64,
+    window: Duration,
+    max: usize,
+) -> (Vec<u64>, bool) {
+    let since = now_ms.saturating_sub(window.as_millis() as u64);
+    let recent: Vec<u64> = restarts.iter().copied().filter(|t| *t >= since).collect();
+    let allowed = recent.len() < max;
+    (recent, allowed)
+}
+
+/// Answer one request line; `None` = unauthorized or malformed.
+pub(crate) fn handle_request(
+    line: &str,
+    token: &str,
+    state: &mut SupervisorState,
+) -> Option<(Value, bool)> {
+    let req: Value = serde_json::from_str(line).ok()?;
+    if req.get("token").and_then(Value::as_str) != Some(token) {
+        return None;
+    }
+    let shutdown = match req.get("cmd").and_then(Value::as_str)? {
+        "status" => false,
+        "attach" => {
+            state.gui_pid = req.get("guiPid").and_then(Value::as_u64).map(|p| p as u32);
+            false
+        }
+        "detach" => {
+            state.gui_pid = None;
+            false
+        }
+        "shutdown" => true,
+        _ => return None,
+    };
+    Some((json!({ "ok": true, "state": state }), shutdown))
+}
+
+fn info_path() -> PathBuf {
+    run_dir().join(INFO_FILE)
+}
+
+fn read_info() -> Option<WatchdogInfo> {
+    let info: WatchdogInfo =
+        serde_json::from_str(&std::fs::read_to_string(info_path()).ok()?).ok()?;
+    is_pid_running(info.pid).then_some(info)
+}
+
+/// One request to the running supervisor.
+fn request(info: &WatchdogInfo, cmd: &str, extra: Value) -> Result<Value, String> {
+    let addr = std::net::SocketAddr::from(([127, 0, 0, 1], info.port));
+    let mut stream = TcpStream::connect_timeout(&addr, SOCKET_TIMEOUT)
+        .map_err(|e| format!("connect watchdog: {e}"))?;
+    let _ = stream.set_read_timeout(Some(SOCKET_TIMEOUT));
+    let mut req = json!({ "token": info.token, "cmd": cmd });
+    if let (Some(obj), Some(extra)) = (req.as_object_mut(), extra.as_object()) {
+        obj.extend(extra.clone());
+    }
+    writeln!(stream, "{req}").map_err(|e| format!("write watchdog: {e}"))?;
+    let mut line = String::new();
+    BufReader::new(&stream)
+        .read_line(&mut line)
+        .map_err(|e| format!("read watchdog: {e}"))?;
+    serde_json::from_str(&line).map_err(|_| "watchdog rejected the request".to_string())
+}
+
+fn serve(listener: TcpListener, token: String) {
+    for stream in listener.incoming().flatten() {
+        let _ = stream.set_read_timeout(Some(SOCKET_TIMEOUT));
+        let mut line = String::new();
+        if BufReader::new(&stream).read_line(&mut line).is_err() {
+            continue;
+        }
+        let answer = {
+            let mut guard = STATE.lock().unwrap();
+            guard
+                .as_mut()
+                .and_then(|s| handle_request(line.trim(), &token, s))
+        };
+        let mut stream = stream;
+        match answer {
+            Some((reply, shutdown)) => {
+                let _ = writeln!(stream, "{reply}");
+                if shutdown {
+                    log_to_file("[supervisor] shutdown requested");
+                    let _ = stream.shutdown(Shutdown::Both);
+                    let _ = std::fs::remove_file(info_path());
+                    std::process::exit(0);
+                }
+            }
+            None => {
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+        }
+    }
+}
+
+fn update_state(f: impl FnOnce(&mut SupervisorState)) {
+    if let Some(s) = STATE.lock().unwrap().as_mut() {
+        f(s);
+    }
+}
+
+/// `openakita-setup-center supervise`: never returns unless another
+/// supervisor is already running.
+pub(crate) fn run(workspace_id: String, venv_dir: String) -> Result<(), String> {
+    if let Some(info) = read_info() {
+        if request(&info, "status", json!({})).is_ok() {
+            println!("watchdog already running (pid {})", info.pid);
+            return Ok(());
+        }
+    }
+    let listener =
+        TcpListener::bind(("127.0.0.1", 0)).map_err(|e| format!("bind watchdog socket: {e}"))?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    let mut raw = [0u8; 16];
+    let _ = getrandom::fill(&mut raw);
+    let info = WatchdogInfo {
+        pid: std::process::id(),
+        port,
+        token: raw.iter().map(|b| format!("{b:02x}")).collect(),
+        workspace_id: workspace_id.clone(),
+        started_at_ms: now_ms(),
+    };
+    atomic_write(
+        info_path(),
+        serde_json::to_string_pretty(&info).map_err(|e| e.to_string())?,
+    )?;
+    *STATE.lock().unwrap() = Some(SupervisorState {
+        workspace_id: workspace_id.clone(),
+        state: "supervising".into(),
+        ..Default::default()
+    });
+    log_to_file(&format!(
+        "[supervisor] started pid={} port={port} ws={workspace_id}",
+        info.pid
+    ));
+    let token = info.token.clone();
+    std::thread::spawn(move || serve(listener, token));
+
+    let mut restarts: Vec<u64> = Vec::new();
+    loop {
+        std::thread::sleep(CHECK_INTERVAL);
+        let gui = STATE
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|s| s.gui_pid)
+            .filter(|p| is_pid_running(*p));
+        if gui.is_some() {
+            update_state(|s| s.state = "standby".into());
+            continue;
+        }
+        if backend_was_manually_stopped(&workspace_id) {
+            update_state(|s| s.state = "manual-stop".into());
+            continue;
+        }
+        if service_status_uncached(&workspace_id).is_ok_and(|s| s.running) {
+            update_state(|s| s.state = "supervising".into());
+            continue;
+        }
+        let (recent, allowed) = breaker(&restarts, now_ms(), RESTART_WINDOW, MAX_RESTARTS);
+        restarts = recent;
+        if !allowed {
+            update_state(|s| s.state = "breaker-open".into());
+            continue;
+        }
+        restarts.push(now_ms());
+        let result = {
+            let _lifecycle_guard = BACKEND_LIFECYCLE_LOCK.lock().unwrap();
+            openakita_service_start_impl(venv_dir.clone(), workspace_id.clone())
+        };
+        invalidate_service_polls(&workspace_id);
+        log_to_file(&format!(
+            "[supervisor] backend down, restart #{} for ws={workspace_id}: {}",
+            restarts.len(),
+            result.as_ref().map_or_else(|e| e.clone(), |_| "ok".into())
+        ));
+        update_state(|s| {
+            s.state = "supervising".into();
+            s.restarts += 1;
+            s.last_restart_ms = Some(now_ms());
+            s.last_error = result.err();
+        });
+    }
+}
+
+fn spawn_supervisor(workspace_id: &str) -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| format!("current_exe failed: {e}"))?;
+    let mut cmd = Command::new(exe);
+    cmd.args(["supervise", "--workspace", workspace_id])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt as _;
+        // DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP | CREATE_NO_WINDOW
+        cmd.creation_flags(0x00000008 | 0x00000200 | 0x0800_0000);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt as _;
+        // 独立进程组：关闭终端 / 退出 GUI 不会连带信号
+        cmd.process_group(0);
+    }
+    cmd.spawn()
+        .map(|child| log_to_file(&format!("[supervisor] spawned pid={}", child.id())))
+        .map_err(|e| format!("spawn watchdog failed: {e}"))
+}
+
+// ── 登录自启项 ──
+
+#[cfg(windows)]
+const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+
+#[cfg(windows)]
+fn reg(args: &[&str]) -> bool {
+    let mut cmd = Command::new("reg");
+    cmd.args(args);
+    crate::apply_no_window(&mut cmd);
+    cmd.output().is_ok_and(|o| o.status.success())
+}
+
+#[cfg(target_os = "macos")]
+fn login_entry_path() -> Option<PathBuf> {
+    dirs_next::home_dir().map(|h| {
+        h.join("Library")
+            .join("LaunchAgents")
+            .join("com.openakita.watchdog.plist")
+    })
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn login_entry_path() -> Option<PathBuf> {
+    dirs_next::config_dir().map(|c| c.join("autostart").join("openakita-watchdog.desktop"))
+}
+
+fn login_entry_installed() -> bool {
+    #[cfg(windows)]
+    {
+        reg(&["query", RUN_KEY, "/v", ENTRY_NAME])
+    }
+    #[cfg(not(windows))]
+    {
+        login_entry_path().is_some_and(|p| p.is_file())
+    }
+}
+
+fn set_login_entry(workspace_id: Option<&str>) -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| format!("current_exe failed: {e}"))?;
+    let exe = exe.to_string_lossy();
+    #[cfg(windows)]
+    {
+        let ok = match workspace_id {
+            Some(ws) => {
+                let value = format!("\"{exe}\" supervise --workspace {ws}");
+                reg(&[
+                    "add",
+                    RUN_KEY,
+                    "/v",
+                    ENTRY_NAME,
+                    "/t",
+                    "REG_SZ",
+                    "/d",
+                    value.as_str(),
+                    "/f",
+                ])
+            }
+            None => !login_entry_installed() || reg(&["delete", RUN_KEY, "/v", ENTRY_NAME, "/f"]),
+        };
+        if !ok {
+            return Err("更新注册表 Run 项失败".into());
+        }
+        Ok(())
+    }
+    #[cfg(not(windows))]
+    {
+        let path = login_entry_path().ok_or("无法确定用户配置目录")?;
+        let Some(ws) = workspace_id else {
+            return match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    Err(format!("remove {}: {e}", path.display()))
+                }
+                _ => Ok(()),
+            };
+        };
+        #[cfg(target_os = "macos")]
+        let content = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>Label</key><string>com.openakita.watchdog</string>
+  <key>ProgramArguments</key>
+  <array><string>{exe}</string><string>supervise</string><string>--workspace</string><string>{ws}</string></array>
+  <key>RunAtLoad</key><true/>
+  <key>KeepAlive</key><dict><key>SuccessfulExit</key><false/></dict>
+</dict>
+</plist>
+"#
+        );
+        #[cfg(not(target_os = "macos"))]
+        let content = format!(
+            "[Desktop Entry]\nType=Application\nName={ENTRY_NAME}\n\
+             Exec=\"{exe}\" supervise --workspace {ws}\nNoDisplay=true\n\
+             X-GNOME-Autostart-enabled=true\n"
+        );
+        atomic_write(&path, content)
+    }
+}
+
+/// Tell a running supervisor that this Setup Center instance is up, so it
+/// stands by instead of racing the GUI's own restarts.  Repeated because
+/// the supervisor may start later than the GUI.
+pub(crate) fn spawn_gui_attach() {
+    std::thread::spawn(|| loop {
+        if let Some(info) = read_info() {
+            let _ = request(&info, "attach", json!({ "guiPid": std::process::id() }));
+        }
+        std::thread::sleep(Duration::from_secs(30));
+    });
+}
+
+#[tauri::command]
+pub fn watchdog_status() -> WatchdogStatus {
+    let info = read_info();
+    let supervisor = info
+        .as_ref()
+        .and_then(|i| request(i, "status", json!({})).ok())
+        .and_then(|v| serde_json::from_value::<SupervisorState>(v.get("state")?.clone()).ok());
+    WatchdogStatus {
+        installed: login_entry_installed(),
+        running: supervisor.is_some(),
+        pid: info.as_ref().map(|i| i.pid),
+        started_at_ms: info.as_ref().map(|i| i.started_at_ms),
+        supervisor,
+    }
+}
+
+/// Install (start at login and now) or remove the companion watchdog for
+/// `workspace_id` (default: the current workspace).
+#[tauri::command]
+pub fn install_watchdog(enabled: bool, workspace_id: Option<String>) -> CmdResult<WatchdogStatus> {
+    let running = read_info();
+    if enabled {
+        let ws = workspace_id
+            .filter(|w| !w.is_empty())
+            .or_else(|| read_state_file().current_workspace_id)
+            .ok_or("INVALID_ARGUMENT|没有当前工作区，请指定 workspaceId")?;
+        if !crate::workspace_dir(&ws).is_dir() {
+            return Err(format!("NOT_FOUND|工作区不存在: {ws}").into());
+        }
+        set_login_entry(Some(&ws))?;
+        // 换了工作区：先停掉旧的 supervisor
+        if let Some(info) = running.filter(|i| i.workspace_id != ws) {
+            let _ = request(&info, "shutdown", json!({}));
+            std::thread::sleep(Duration::from_millis(300));
+        }
+        if read_info().is_none() {
+            spawn_supervisor(&ws)?;
+            std::thread::sleep(Duration::from_millis(500));
+        }
+        log_to_file(&format!("[supervisor] installed for ws={ws}"));
+    } else {
+        set_login_entry(None)?;
+        if let Some(info) = running {
+            let _ = request(&info, "shutdown", json!({}));
+        }
+        log_to_file("[supervisor] removed");
+    }
+    Ok(watchdog_status())
+}
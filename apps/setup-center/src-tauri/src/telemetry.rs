@@ -0,0 +1,401 @@
+//! Opt-in crash report upload.
+//!
+//! Off by default.  Once the user enables it (`set_telemetry_settings`),
+//! crashes are queued as redacted text under `~/.openakita/telemetry/queue/`
+//! and a background thread POSTs them to the configured endpoint together
+//! with a random installation id — no account, machine or user name:
+//!
+//! * Setup Center: panic reports (`run/setupcenter-crash-*.log`) and the
+//!   event log next to native crash dumps (`crashdumps/*.events.txt`) are
+//!   picked up at the next start; the dumps themselves stay local.
+//! * Backend: a managed backend that exits without being asked to
+//!   contributes the tail of its serve log.
+//!
+//! Only crashes after the moment of consent are queued.  A failed upload
+//! (offline, endpoint down, offline mode) stays queued and is retried with
+//! exponential backoff; entries older than [`MAX_QUEUE_AGE`] or beyond
+//! [`MAX_QUEUED`] are dropped.  Turning telemetry off empties the queue.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::errors::CmdResult;
+use crate::{
+    atomic_write, crashdumps_dir, feedback, list_panic_reports, log_to_file, net, now_epoch_secs,
+    now_ms, openakita_root_dir, read_state_file, redact, run_dir, workspace_dir, write_state_file,
+    STATE_FILE_LOCK,
+};
+
+pub(crate) const SCHEMA: &str = "openakita-crash/1";
+const MAX_QUEUED: usize = 50;
+const MAX_QUEUE_AGE: Duration = Duration::from_secs(14 * 24 * 3600);
+/// Reports are cut to their last this-many bytes before queueing.
+const MAX_REPORT_BYTES: usize = 64 * 1024;
+const BACKEND_LOG_LINES: usize = 200;
+const RETRY_BASE: Duration = Duration::from_secs(60);
+const RETRY_MAX: Duration = Duration::from_secs(6 * 3600);
+const FLUSH_FIRST_DELAY: Duration = Duration::from_secs(30);
+const FLUSH_TICK: Duration = Duration::from_secs(60);
+const POST_TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TelemetrySettings {
+    pub enabled: bool,
+    pub endpoint: Option<String>,
+    /// Random, generated on first opt-in; identifies the installation only.
+    pub installation_id: Option<String>,
+    /// Crash files older than this (epoch secs) are never queued — set at
+    /// the moment of consent, then advanced by each scan.
+    pub scanned_until_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TelemetryStatus {
+    pub enabled: bool,
+    pub endpoint: Option<String>,
+    pub installation_id: Option<String>,
+    /// Reports waiting for upload.
+    pub queued: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct QueuedReport {
+    pub id: String,
+    /// "setup-center" | "backend"
+    pub kind: String,
+    pub created_at_ms: u64,
+    #[serde(default)]
+    pub workspace_id: Option<String>,
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    /// Already redacted.
+    pub report: String,
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub next_attempt_ms: u64,
+}
+
+/// Delay before retry number `attempts` (1-based): 1 min, 2 min, 4 min, …
+/// capped at 6 h.
+pub(crate) fn backoff(attempts: u32) -> Duration {
+    let factor = 1u64 << attempts.saturating_sub(1).min(16);
+    RETRY_BASE.saturating_mul(factor as u32).min(RETRY_MAX)
+}
+
+/// The last `max_bytes` of `text`, cut at a character boundary.
+pub(crate) fn clip_tail(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut start = text.len() - max_bytes;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    &text[start..]
+}
+
+/// Body POSTed for one queued report.
+pub(crate) fn payload(entry: &QueuedReport, installation_id: &str) -> Value {
+    json!({
+        "schema": SCHEMA,
+        "id": entry.id,
+        "installationId": installation_id,
+        "kind": entry.kind,
+        "createdAtMs": entry.created_at_ms,
+        "app": {
+            "version": env!("CARGO_PKG_VERSION"),
+            "os": std::env::consts::OS,
+            "arch": std::env::consts::ARCH,
+        },
+        "workspaceId": entry.workspace_id,
+        "exitCode": entry.exit_code,
+        "report": entry.report,
+    })
+}
+
+/// Entries to keep: none older than `max_age`, at most `max` (newest win).
+/// Returns the ids to delete.
+pub(crate) fn overflow(
+    entries: &[(String, u64)],
+    now_ms: u64,
+    max_age: Duration,
+    max: usize,
+) -> Vec<String> {
+    let cutoff = now_ms.saturating_sub(max_age.as_millis() as u64);
+    let mut live: Vec<&(String, u64)> = entries.iter().filter(|(_, at)| *at >= cutoff).collect();
+    live.sort_by_key(|e| std::cmp::Reverse(e.1));
+    let keep: Vec<&str> = live.iter().take(max).map(|e| e.0.as_str()).collect();
+    entries
+        .iter()
+        .filter(|(id, _)| !keep.contains(&id.as_str()))
+        .map(|(id, _)| id.clone())
+        .collect()
+}
+
+fn random_hex(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    let _ = getrandom::fill(&mut buf);
+    buf.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn queue_dir() -> PathBuf {
+    openakita_root_dir().join("telemetry").join("queue")
+}
+
+pub(crate) fn settings() -> TelemetrySettings {
+    read_state_file().telemetry.unwrap_or_default()
+}
+
+fn update_settings(f: impl FnOnce(&mut TelemetrySettings)) -> Result<TelemetrySettings, String> {
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    let mut s = state.telemetry.take().unwrap_or_default();
+    f(&mut s);
+    state.telemetry = Some(s.clone());
+    write_state_file(&state)?;
+    Ok(s)
+}
+
+fn read_queue() -> Vec<(PathBuf, QueuedReport)> {
+    let Ok(rd) = fs::read_dir(queue_dir()) else {
+        return Vec::new();
+    };
+    let mut out: Vec<(PathBuf, QueuedReport)> = rd
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|x| x == "json"))
+        .filter_map(|p| {
+            let entry = serde_json::from_str(&fs::read_to_string(&p).ok()?).ok()?;
+            Some((p, entry))
+        })
+        .collect();
+    out.sort_by_key(|(_, e)| e.created_at_ms);
+    out
+}
+
+fn prune_queue() {
+    let queue = read_queue();
+    let entries: Vec<(String, u64)> = queue
+        .iter()
+        .map(|(_, e)| (e.id.clone(), e.created_at_ms))
+        .collect();
+    let drop = overflow(&entries, now_ms(), MAX_QUEUE_AGE, MAX_QUEUED);
+    for (path, e) in &queue {
+        if drop.contains(&e.id) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn enqueue(kind: &str, workspace_id: Option<&str>, exit_code: Option<i32>, text: &str) {
+    let report = match workspace_id {
+        Some(ws) => redact::redact_for_workspace(text, ws),
+        None => redact::redact(text),
+    };
+    let entry = QueuedReport {
+        id: format!("{}-{}", now_ms(), random_hex(4)),
+        kind: kind.to_string(),
+        created_at_ms: now_ms(),
+        workspace_id: workspace_id.map(str::to_string),
+        exit_code,
+        report: clip_tail(&report, MAX_REPORT_BYTES).to_string(),
+        attempts: 0,
+        next_attempt_ms: 0,
+    };
+    let path = queue_dir().join(format!("{}.json", entry.id));
+    match atomic_write(&path, serde_json::to_string(&entry).unwrap_or_default()) {
+        Ok(()) => log_to_file(&format!("[telemetry] queued {kind} crash {}", entry.id)),
+        Err(e) => log_to_file(&format!("[telemetry] queue write failed: {e}")),
+    }
+    prune_queue();
+}
+
+/// A managed backend went away without a stop request.  `status` is `None`
+/// when the exit code is unknown (e.g. it died right after spawn).
+pub(crate) fn report_backend_exit(workspace_id: &str, status: Option<std::process::ExitStatus>) {
+    if status.is_some_and(|s| s.success()) || !settings().enabled {
+        return;
+    }
+    let log = feedback::read_tail(
+        &workspace_dir(workspace_id)
+            .join("logs")
+            .join("openakita-serve.log"),
+        MAX_REPORT_BYTES as u64,
+    );
+    let code = status.and_then(|s| s.code());
+    let text = format!(
+        "backend exited unexpectedly (exit code: {})\n\n{}",
+        code.map_or("unknown".to_string(), |c| c.to_string()),
+        feedback::last_lines(&log, BACKEND_LOG_LINES)
+    );
+    enqueue("backend", Some(workspace_id), code, &text);
+}
+
+fn modified_secs(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Queue Setup Center crash files written since the last scan.
+fn scan_setup_center_crashes() {
+    let s = settings();
+    if !s.enabled {
+        return;
+    }
+    let scan_started = now_epoch_secs();
+    let native_events: Vec<PathBuf> = fs::read_dir(crashdumps_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.to_string_lossy().ends_with(".events.txt"))
+        .collect();
+    let mut found = 0;
+    for path in list_panic_reports(&run_dir())
+        .into_iter()
+        .chain(native_events)
+    {
+        if modified_secs(&path) <= s.scanned_until_secs {
+            continue;
+        }
+        if let Ok(text) = fs::read_to_string(&path) {
+            enqueue("setup-center", None, None, &text);
+            found += 1;
+        }
+    }
+    if let Err(e) = update_settings(|s| s.scanned_until_secs = scan_started) {
+        log_to_file(&format!("[telemetry] saving scan mark failed: {e}"));
+    }
+    if found > 0 {
+        log_to_file(&format!(
+            "[telemetry] {found} new Setup Center crash report(s)"
+        ));
+    }
+}
+
+async fn post(url: &str, body: String) -> Result<(), String> {
+    net::ensure_online("上传崩溃报告")?;
+    let resp = net::http_client()
+        .post(url)
+        .header("Content-Type", "application/json")
+        .timeout(POST_TIMEOUT)
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| format!("POST {url} failed: {e}"))?;
+    if !resp.status().is_success() {
+        return Err(format!("POST {url}: HTTP {}", resp.status()));
+    }
+    Ok(())
+}
+
+/// Upload every due entry; failures are rescheduled with backoff.
+fn flush() {
+    let s = settings();
+    let (Some(url), Some(install_id)) = (s.endpoint.as_deref(), s.installation_id.as_deref())
+    else {
+        return;
+    };
+    if !s.enabled {
+        return;
+    }
+    let now = now_ms();
+    for (path, mut entry) in read_queue() {
+        if entry.next_attempt_ms > now {
+            continue;
+        }
+        let body = payload(&entry, install_id).to_string();
+        match tauri::async_runtime::block_on(post(url, body)) {
+            Ok(()) => {
+                let _ = fs::remove_file(&path);
+                log_to_file(&format!("[telemetry] uploaded {}", entry.id));
+            }
+            Err(e) => {
+                entry.attempts += 1;
+                entry.next_attempt_ms = now + backoff(entry.attempts).as_millis() as u64;
+                let _ = atomic_write(&path, serde_json::to_string(&entry).unwrap_or_default());
+                log_to_file(&format!(
+                    "[telemetry] upload {} failed (attempt {}): {e}",
+                    entry.id, entry.attempts
+                ));
+                // 离线或服务端不可用时其余条目大概率也会失败，等下一轮
+                break;
+            }
+        }
+    }
+}
+
+pub(crate) fn spawn_uploader() {
+    std::thread::spawn(|| {
+        std::thread::sleep(FLUSH_FIRST_DELAY);
+        scan_setup_center_crashes();
+        loop {
+            flush();
+            std::thread::sleep(FLUSH_TICK);
+        }
+    });
+}
+
+fn status(s: TelemetrySettings) -> TelemetryStatus {
+    TelemetryStatus {
+        enabled: s.enabled,
+        endpoint: s.endpoint,
+        installation_id: s.installation_id,
+        queued: read_queue().len(),
+    }
+}
+
+#[tauri::command]
+pub fn get_telemetry_settings() -> TelemetryStatus {
+    status(settings())
+}
+
+/// Turn crash upload on or off and set its endpoint.  Turning it off drops
+/// anything still queued; `resetInstallationId` issues a fresh id.
+#[tauri::command]
+pub fn set_telemetry_settings(
+    enabled: bool,
+    endpoint: Option<String>,
+    reset_installation_id: Option<bool>,
+) -> CmdResult<TelemetryStatus> {
+    let endpoint = endpoint
+        .map(|u| u.trim().to_string())
+        .filter(|u| !u.is_empty());
+    if let Some(u) = &endpoint {
+        let parsed = reqwest::Url::parse(u).map_err(|e| format!("INVALID_ARGUMENT|{u}: {e}"))?;
+        if !matches!(parsed.scheme(), "https" | "http") {
+            return Err(format!("INVALID_ARGUMENT|只支持 http(s) 地址: {u}").into());
+        }
+    }
+    let s = update_settings(|s| {
+        if enabled && !s.enabled {
+            // 只上报同意之后发生的崩溃
+            s.scanned_until_secs = now_epoch_secs();
+        }
+        if reset_installation_id == Some(true) || (enabled && s.installation_id.is_none()) {
+            s.installation_id = Some(random_hex(16));
+        }
+        s.enabled = enabled;
+        s.endpoint = endpoint;
+    })?;
+    if !enabled {
+        let _ = fs::remove_dir_all(queue_dir());
+    }
+    log_to_file(&format!(
+        "[telemetry] crash upload {}",
+        if enabled { "enabled" } else { "disabled" }
+    ));
+    Ok(status(s))
+}
@@ -0,0 +1,339 @@
+//! One chronological view of an incident.
+//!
+//! `get_correlated_timeline(workspace_id, from_ms, to_ms)` merges what the
+//! desktop and the backend each recorded in that window:
+//!
+//! | source        | from                                                         |
+//! |---------------|--------------------------------------------------------------|
+//! | `setupCenter` | Setup Center's own action log (`logs/autostart.log` + `.1`)  |
+//! | `health`      | backend up / down / crashed / silent transitions (`logs/health-transitions.log`) |
+//! | `crash`       | `logs/crash.log`, panic reports in `run/`, native dumps in `crashdumps/` |
+//! | `backend`     | error entries of the workspace's serve log (see `log_query`) |
+//!
+//! Health transitions are written here by the heartbeat thread and the
+//! silent-backend and crash watchdogs (one JSON line each); everything else
+//! is read from files that already exist.  Text is redacted.  At most
+//! [`MAX_ENTRIES`] entries are returned, the latest ones.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use crate::errors::CmdResult;
+use crate::log_capture::Level;
+use crate::log_query::{self, LogRange};
+use crate::{
+    crashdumps_dir, list_panic_reports, now_ms, redact, rotate_autostart_log_if_needed, run_dir,
+    setup_logs_dir, spawn_blocking_result,
+};
+
+pub(crate) const MAX_ENTRIES: usize = 5000;
+const HEALTH_LOG: &str = "health-transitions.log";
+/// Longest message kept per entry.
+const MESSAGE_CHARS: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TimelineSource {
+    SetupCenter,
+    Health,
+    Crash,
+    Backend,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineEntry {
+    pub ts_ms: u64,
+    pub source: TimelineSource,
+    pub level: Level,
+    pub message: String,
+    /// Health state, crash file, backend module …
+    pub detail: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Timeline {
+    pub workspace_id: String,
+    pub from_ms: u64,
+    pub to_ms: u64,
+    pub entries: Vec<TimelineEntry>,
+    /// Older entries in the window were dropped (over [`MAX_ENTRIES`]).
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HealthTransition {
+    ts_ms: u64,
+    workspace_id: String,
+    /// "up" | "down" | "crashed" | "silent" | "writing"
+    state: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+/// Append a health transition of `workspace_id` to the journal.
+pub(crate) fn record_health(workspace_id: &str, state: &str, detail: Option<String>) {
+    let dir = setup_logs_dir();
+    let _ = fs::create_dir_all(&dir);
+    let path = dir.join(HEALTH_LOG);
+    rotate_autostart_log_if_needed(&path);
+    let entry = HealthTransition {
+        ts_ms: now_ms(),
+        workspace_id: workspace_id.to_string(),
+        state: state.to_string(),
+        detail,
+    };
+    let Ok(mut line) = serde_json::to_string(&entry) else {
+        return;
+    };
+    line.push('\n');
+    let _ = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut f| f.write_all(line.as_bytes()));
+}
+
+/// Contents of `<name>.1` then `<name>`.
+fn read_rotated(path: &Path) -> String {
+    let mut out = String::new();
+    for p in [path.with_extension("log.1"), path.to_path_buf()] {
+        if let Ok(bytes) = fs::read(p) {
+            out.push_str(&String::from_utf8_lossy(&bytes));
+        }
+    }
+    out
+}
+
+fn clip(text: &str) -> String {
+    text.trim().chars().take(MESSAGE_CHARS).collect()
+}
+
+fn entry(
+    ts_ms: u64,
+    source: TimelineSource,
+    level: Level,
+    message: &str,
+    detail: Option<serde_json::Value>,
+) -> TimelineEntry {
+    TimelineEntry {
+        ts_ms,
+        source,
+        level,
+        message: clip(message),
+        detail,
+    }
+}
+
+/// `[<epoch secs>] message` lines of `autostart.log`.
+pub(crate) fn parse_action_log(content: &str) -> Vec<TimelineEntry> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (secs, msg) = line.strip_prefix('[')?.split_once("] ")?;
+            let secs: u64 = secs.parse().ok()?;
+            let lower = msg.to_ascii_lowercase();
+            let level = if lower.contains("failed") || lower.contains("error") {
+                Level::Error
+            } else if lower.contains("warn") {
+                Level::Warning
+            } else {
+                Level::Info
+            };
+            Some(entry(
+                secs * 1000,
+                TimelineSource::SetupCenter,
+                level,
+                msg,
+                None,
+            ))
+        })
+        .collect()
+}
+
+fn parse_health_log(content: &str, workspace_id: &str) -> Vec<TimelineEntry> {
+    content
+        .lines()
+        .filter_map(|l| serde_json::from_str::<HealthTransition>(l).ok())
+        .filter(|t| t.workspace_id == workspace_id)
+        .map(|t| {
+            let level = match t.state.as_str() {
+                "down" | "crashed" => Level::Error,
+                "silent" => Level::Warning,
+                _ => Level::Info,
+            };
+            let message = match &t.detail {
+                Some(d) => format!("backend {}: {d}", t.state),
+                None => format!("backend {}", t.state),
+            };
+            entry(
+                t.ts_ms,
+                TimelineSource::Health,
+                level,
+                &message,
+                Some(serde_json::json!({ "state": t.state })),
+            )
+        })
+        .collect()
+}
+
+/// Entries of `crash.log`: `[<epoch secs>] exe=… cwd=… home=…`, the
+/// message, then `---`.
+pub(crate) fn parse_crash_log(content: &str) -> Vec<TimelineEntry> {
+    content
+        .split("\n---\n")
+        .filter_map(|block| {
+            let block = block.trim_start_matches('\n');
+            let (head, message) = block.split_once('\n').unwrap_or((block, ""));
+            let (secs, _) = head.strip_prefix('[')?.split_once(']')?;
+            let secs: u64 = secs.parse().ok()?;
+            Some(entry(
+                secs * 1000,
+                TimelineSource::Crash,
+                Level::Error,
+                message,
+                Some(serde_json::json!({ "file": "crash.log" })),
+            ))
+        })
+        .collect()
+}
+
+fn modified_ms(path: &Path) -> Option<u64> {
+    let t = fs::metadata(path).ok()?.modified().ok()?;
+    Some(t.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64)
+}
+
+fn crash_files() -> Vec<TimelineEntry> {
+    let mut out = Vec::new();
+    for report in list_panic_reports(&run_dir()) {
+        let Some(ts) = modified_ms(&report) else {
+            continue;
+        };
+        let first = fs::read_to_string(&report)
+            .ok()
+            .and_then(|t| t.lines().find(|l| !l.trim().is_empty()).map(str::to_string))
+            .unwrap_or_default();
+        out.push(entry(
+            ts,
+            TimelineSource::Crash,
+            Level::Error,
+            &format!("Setup Center panic: {first}"),
+            Some(serde_json::json!({ "file": report.to_string_lossy() })),
+        ));
+    }
+    if let Ok(rd) = fs::read_dir(crashdumps_dir()) {
+        for path in rd.flatten().map(|e| e.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("dmp") {
+                continue;
+            }
+            if let Some(ts) = modified_ms(&path) {
+                out.push(entry(
+                    ts,
+                    TimelineSource::Crash,
+                    Level::Error,
+                    "Setup Center native crash dump",
+                    Some(serde_json::json!({ "file": path.to_string_lossy() })),
+                ));
+            }
+        }
+    }
+    out
+}
+
+fn backend_errors(workspace_id: &str, range: LogRange) -> Vec<TimelineEntry> {
+    let Ok(content) = log_query::full_log(workspace_id, true) else {
+        return Vec::new();
+    };
+    log_query::in_range(log_query::parse_entries(&content), range)
+        .into_iter()
+        .filter(|e| e.level == Level::Error)
+        .filter_map(|e| {
+            let detail = e
+                .module
+                .as_ref()
+                .map(|m| serde_json::json!({ "module": m }));
+            Some(entry(
+                e.ts_ms?,
+                TimelineSource::Backend,
+                e.level,
+                &e.message,
+                detail,
+            ))
+        })
+        .collect()
+}
+
+/// Entries inside `[from_ms, to_ms]`, oldest first (stable for equal
+/// times, so each source keeps its own order), at most `max` — the latest.
+pub(crate) fn merge(
+    sources: Vec<Vec<TimelineEntry>>,
+    from_ms: u64,
+    to_ms: u64,
+    max: usize,
+) -> (Vec<TimelineEntry>, bool) {
+    let mut all: Vec<TimelineEntry> = sources
+        .into_iter()
+        .flatten()
+        .filter(|e| (from_ms..=to_ms).contains(&e.ts_ms))
+        .collect();
+    all.sort_by_key(|e| e.ts_ms);
+    let truncated = all.len() > max;
+    let all = all.split_off(all.len().saturating_sub(max));
+    (all, truncated)
+}
+
+fn build(workspace_id: &str, from_ms: u64, to_ms: u64) -> Timeline {
+    let logs = setup_logs_dir();
+    let range = LogRange {
+        from_ms: Some(from_ms),
+        to_ms: Some(to_ms),
+    };
+    let mut actions = parse_action_log(&read_rotated(&logs.join("autostart.log")));
+    for a in &mut actions {
+        a.message = redact::redact(&a.message);
+    }
+    let mut crashes =
+        parse_crash_log(&fs::read_to_string(logs.join("crash.log")).unwrap_or_default());
+    crashes.extend(crash_files());
+    let (entries, truncated) = merge(
+        vec![
+            actions,
+            parse_health_log(&read_rotated(&logs.join(HEALTH_LOG)), workspace_id),
+            crashes,
+            backend_errors(workspace_id, range),
+        ],
+        from_ms,
+        to_ms,
+        MAX_ENTRIES,
+    );
+    Timeline {
+        workspace_id: workspace_id.to_string(),
+        from_ms,
+        to_ms,
+        entries,
+        truncated,
+    }
+}
+
+/// Setup Center actions, health transitions, crashes and backend errors of
+/// a workspace between `from_ms` and `to_ms` (epoch ms; `to_ms` defaults
+/// to now), in order.
+#[tauri::command]
+pub async fn get_correlated_timeline(
+    workspace_id: String,
+    from_ms: u64,
+    to_ms: Option<u64>,
+) -> CmdResult<Timeline> {
+    let to_ms = to_ms.unwrap_or_else(now_ms);
+    if to_ms < from_ms {
+        return Err("INVALID_ARGUMENT|toMs 早于 fromMs".into());
+    }
+    spawn_blocking_result(move || Ok(build(&workspace_id, from_ms, to_ms)))
+        .await
+        .map_err(Into::into)
+}
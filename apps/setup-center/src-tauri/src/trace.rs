@@ -0,0 +1,247 @@
+//! Opt-in request/response trace for bridge and HTTP calls.
+//!
+//! When enabled (`set_bridge_trace(true)`, persisted as `bridgeTrace` in
+//! `state.json`, or `OPENAKITA_BRIDGE_TRACE=1`), every Python bridge
+//! invocation, backend HTTP call and external download is appended as one
+//! JSON line to `~/.openakita/logs/bridge-trace.log`: kind, target, timing,
+//! status, truncated request/response bodies and the error if any.  The
+//! file rotates to `bridge-trace.log.1` at 5 MB, like `autostart.log`.
+//!
+//! Credential-looking CLI flags (`--api-key` …) and `Authorization`-style
+//! headers are masked before anything is written, and bodies and errors go
+//! through [`redact::redact`] like other log surfaces.  `get_bridge_trace(tail)`
+//! returns the most recent entries for the diagnostics page.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::errors::CmdResult;
+use crate::{now_ms, read_state_file, redact, setup_logs_dir, write_state_file, STATE_FILE_LOCK};
+
+const TRACE_LOG_MAX_BYTES: u64 = 5 * 1024 * 1024;
+/// Request / response bodies are cut to this many chars per entry.
+const TRACE_BODY_MAX_CHARS: usize = 2000;
+const DEFAULT_TRACE_TAIL: usize = 200;
+
+static TRACE_ENABLED: Lazy<AtomicBool> = Lazy::new(|| {
+    let env_on = matches!(
+        std::env::var("OPENAKITA_BRIDGE_TRACE").ok().as_deref(),
+        Some("1") | Some("true") | Some("yes")
+    );
+    AtomicBool::new(env_on || read_state_file().bridge_trace.unwrap_or(false))
+});
+
+/// Serialises appends so concurrent calls don't interleave partial lines.
+static TRACE_WRITE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+pub(crate) fn enabled() -> bool {
+    TRACE_ENABLED.load(Ordering::Relaxed)
+}
+
+fn trace_log_path() -> PathBuf {
+    setup_logs_dir().join("bridge-trace.log")
+}
+
+fn rotate_if_needed(path: &Path) {
+    let len = match fs::metadata(path) {
+        Ok(m) => m.len(),
+        Err(_) => return,
+    };
+    if len < TRACE_LOG_MAX_BYTES {
+        return;
+    }
+    let rotated = path.with_extension("log.1");
+    let _ = fs::remove_file(&rotated);
+    let _ = fs::rename(path, &rotated);
+}
+
+fn truncate(s: &str) -> String {
+    match s.char_indices().nth(TRACE_BODY_MAX_CHARS) {
+        Some((idx, _)) => format!("{}…(+{} bytes)", &s[..idx], s.len() - idx),
+        None => s.to_string(),
+    }
+}
+
+pub(crate) fn is_secret_name(name: &str) -> bool {
+    let n = name.to_ascii_lowercase();
+    [
+        "key",
+        "token",
+        "secret",
+        "password",
+        "authorization",
+        "cookie",
+    ]
+    .iter()
+    .any(|needle| n.contains(needle))
+}
+
+/// Render CLI args for the log, masking the value after secret-looking
+/// flags (`--api-key sk-…` / `--token=…`).
+pub(crate) fn redact_args(args: &[&str]) -> String {
+    let mut out: Vec<String> = Vec::with_capacity(args.len());
+    let mut mask_next = false;
+    for arg in args {
+        if mask_next {
+            out.push("***".into());
+            mask_next = false;
+            continue;
+        }
+        if let Some(flag) = arg.strip_prefix("--") {
+            if let Some((name, _)) = flag.split_once('=') {
+                if is_secret_name(name) {
+                    out.push(format!("--{name}=***"));
+                    continue;
+                }
+            } else if is_secret_name(flag) {
+                mask_next = true;
+            }
+        }
+        out.push((*arg).to_string());
+    }
+    out.join(" ")
+}
+
+/// Render request headers for the log with credential values masked.
+pub(crate) fn redact_headers<'a, I>(headers: I) -> String
+where
+    I: IntoIterator<Item = (&'a String, &'a String)>,
+{
+    headers
+        .into_iter()
+        .map(|(k, v)| {
+            if is_secret_name(k) {
+                format!("{k}: ***")
+            } else {
+                format!("{k}: {v}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TraceLine<'a> {
+    ts_ms: u64,
+    kind: &'a str,
+    target: &'a str,
+    duration_ms: u64,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Serialise one trace entry.  Bodies and the error are redacted before
+/// they are cut, so a secret straddling the cut point is still masked.
+pub(crate) fn trace_line(
+    kind: &str,
+    target: &str,
+    duration_ms: u64,
+    status: Option<u16>,
+    request: Option<&str>,
+    result: Result<&str, &str>,
+) -> Option<String> {
+    let scrub = |text: &str| truncate(&redact::redact(text));
+    let line = TraceLine {
+        ts_ms: now_ms(),
+        kind,
+        target,
+        duration_ms,
+        ok: result.is_ok(),
+        status,
+        request: request.filter(|r| !r.is_empty()).map(scrub),
+        response: result.ok().map(scrub),
+        error: result.err().map(scrub),
+    };
+    serde_json::to_string(&line).ok()
+}
+
+/// Append one call to the trace log (no-op unless tracing is enabled).
+///
+/// `kind` is `bridge` / `backend` / `http` / `download`; `target` is the
+/// module + args or `METHOD url`.  `result` carries the response body on
+/// success and the error message on failure.
+pub(crate) fn record(
+    kind: &str,
+    target: &str,
+    started: Instant,
+    status: Option<u16>,
+    request: Option<&str>,
+    result: Result<&str, &str>,
+) {
+    if !enabled() {
+        return;
+    }
+    let duration_ms = started.elapsed().as_millis() as u64;
+    let Some(mut json) = trace_line(kind, target, duration_ms, status, request, result) else {
+        return;
+    };
+    json.push('\n');
+
+    let _guard = TRACE_WRITE_LOCK.lock();
+    let path = trace_log_path();
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    rotate_if_needed(&path);
+    let _ = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut f| f.write_all(json.as_bytes()));
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeTrace {
+    enabled: bool,
+    path: String,
+    entries: Vec<serde_json::Value>,
+}
+
+/// Return the last `tail` entries (default 200), oldest first.
+#[tauri::command]
+pub fn get_bridge_trace(tail: Option<usize>) -> CmdResult<BridgeTrace> {
+    let path = trace_log_path();
+    let tail = tail.unwrap_or(DEFAULT_TRACE_TAIL).clamp(1, 5000);
+    let content = match fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("read {} failed: {e}", path.display()).into()),
+    };
+    let lines: Vec<&str> = content.lines().filter(|l| !l.trim().is_empty()).collect();
+    let entries = lines[lines.len().saturating_sub(tail)..]
+        .iter()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .collect();
+    Ok(BridgeTrace {
+        enabled: enabled(),
+        path: path.to_string_lossy().to_string(),
+        entries,
+    })
+}
+
+#[tauri::command]
+pub fn set_bridge_trace(enabled: bool) -> CmdResult<()> {
+    let _lock = STATE_FILE_LOCK
+        .lock()
+        .map_err(|e| format!("state lock failed: {e}"))?;
+    let mut state = read_state_file();
+    state.bridge_trace = Some(enabled);
+    write_state_file(&state)?;
+    TRACE_ENABLED.store(enabled, Ordering::Relaxed);
+    Ok(())
+}
@@ -0,0 +1,144 @@
+//! System tray availability.
+//!
+//! The app normally lives in the tray: closing the window hides it and
+//! `--background` starts hidden.  On Linux the tray icon is a
+//! StatusNotifierItem (libappindicator), which only shows up when something
+//! owns `org.kde.StatusNotifierWatcher` on the session bus — KDE, most
+//! other desktops, or GNOME *with* the AppIndicator extension.  Stock GNOME
+//! has no watcher and no legacy XEmbed tray either, so the icon silently
+//! never appears and a hidden window can't be brought back.
+//!
+//! [`detect`] runs once at startup.  When the tray isn't available, closing
+//! the main window quits the app (with the usual exit cleanup) instead of
+//! hiding it, and a background start minimizes instead of hiding, so the
+//! app keeps a taskbar entry.  `get_tray_capability()` tells the UI.
+
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+
+use crate::log_to_file;
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrayCapability {
+    pub available: bool,
+    /// `native` (Windows / macOS) | `statusNotifier` | `xembed` | `none`
+    pub method: String,
+    /// `XDG_CURRENT_DESKTOP` on Linux.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub desktop: Option<String>,
+    /// Why the tray is considered unavailable / how it was decided.
+    pub detail: String,
+}
+
+static CAPABILITY: OnceCell<TrayCapability> = OnceCell::new();
+
+/// Linux decision.  `watcher`: whether `org.kde.StatusNotifierWatcher` has
+/// an owner (`None` = couldn't ask the bus).
+pub(crate) fn linux_tray_capability(
+    watcher: Option<bool>,
+    desktop: &str,
+    wayland: bool,
+) -> TrayCapability {
+    let is_gnome = desktop
+        .split(':')
+        .any(|d| d.eq_ignore_ascii_case("gnome") || d.eq_ignore_ascii_case("ubuntu"));
+    let desktop_field = (!desktop.is_empty()).then(|| desktop.to_string());
+    let (available, method, detail) = match watcher {
+        Some(true) => (true, "statusNotifier", "StatusNotifierWatcher present"),
+        // 没有 SNI watcher：GNOME 和 Wayland 下也没有 XEmbed 托盘可退
+        _ if is_gnome => (
+            false,
+            "none",
+            "GNOME without the AppIndicator/KStatusNotifierItem extension",
+        ),
+        _ if wayland => (
+            false,
+            "none",
+            "Wayland session without a StatusNotifierWatcher",
+        ),
+        Some(false) => (
+            true,
+            "xembed",
+            "no StatusNotifierWatcher; assuming an XEmbed tray",
+        ),
+        None => (true, "xembed", "session bus not queried; assuming a tray"),
+    };
+    TrayCapability {
+        available,
+        method: method.to_string(),
+        desktop: desktop_field,
+        detail: detail.to_string(),
+    }
+}
+
+/// Ask the session bus whether the StatusNotifierWatcher name is owned,
+/// via `gdbus` (GLib, present wherever GTK is) or `dbus-send`.
+#[cfg(target_os = "linux")]
+fn status_notifier_watcher_owned() -> Option<bool> {
+    use std::process::Command;
+    const NAME: &str = "org.kde.StatusNotifierWatcher";
+    if let Ok(out) = Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.freedesktop.DBus",
+            "--object-path",
+            "/org/freedesktop/DBus",
+            "--method",
+            "org.freedesktop.DBus.NameHasOwner",
+            NAME,
+        ])
+        .output()
+    {
+        if out.status.success() {
+            return Some(String::from_utf8_lossy(&out.stdout).contains("true"));
+        }
+    }
+    let out = Command::new("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus.NameHasOwner",
+            &format!("string:{NAME}"),
+        ])
+        .output()
+        .ok()?;
+    out.status
+        .success()
+        .then(|| String::from_utf8_lossy(&out.stdout).contains("boolean true"))
+}
+
+/// Detect tray support (once; later calls return the first result).
+pub(crate) fn detect() -> &'static TrayCapability {
+    CAPABILITY.get_or_init(|| {
+        #[cfg(target_os = "linux")]
+        let cap = linux_tray_capability(
+            status_notifier_watcher_owned(),
+            &std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default(),
+            std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t == "wayland"),
+        );
+        #[cfg(not(target_os = "linux"))]
+        let cap = TrayCapability {
+            available: true,
+            method: "native".to_string(),
+            desktop: None,
+            detail: String::new(),
+        };
+        log_to_file(&format!("[tray] capability: {cap:?}"));
+        cap
+    })
+}
+
+/// Whether hiding the main window is safe (the tray can bring it back).
+pub(crate) fn can_hide_to_tray() -> bool {
+    detect().available
+}
+
+#[tauri::command]
+pub fn get_tray_capability() -> TrayCapability {
+    detect().clone()
+}
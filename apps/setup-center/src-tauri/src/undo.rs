@@ -0,0 +1,352 @@
+//! Undo records for destructive operations.
+//!
+//! Before an operation throws user data away it stashes what is needed to
+//! put it back under `~/.openakita/trash/<id>/payload` and registers an
+//! [`UndoRecord`] in `trash/undo.json`:
+//!
+//! * `envKeysRemoved` — `workspace_update_env` deleted keys from `.env`; the
+//!   payload is the previous file content.
+//! * `skillUninstall` — `openakita_uninstall_skill`; the payload is a copy of
+//!   the skill directory taken before the bridge removes it.
+//!
+//! `list_undoable_actions()` returns the live records (newest first) and
+//! `undo_action(id)` restores one.  Records expire after
+//! [`UNDO_RETENTION_SECS`]; expired ones and their payloads are pruned on
+//! every list / record and at startup.  An undo refuses with
+//! `UNDO_CONFLICT|...` instead of overwriting something changed since —
+//! a `.env` edited again afterwards, a skill reinstalled under that name.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::errors::CmdResult;
+use crate::{
+    atomic_write, log_to_file, now_epoch_secs, now_ms, openakita_root_dir, sha256_hex, skills,
+    spawn_blocking_result,
+};
+
+/// How long an undo record stays valid.
+pub(crate) const UNDO_RETENTION_SECS: u64 = 24 * 3600;
+
+const PAYLOAD: &str = "payload";
+
+static UNDO_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UndoKind {
+    EnvKeysRemoved,
+    SkillUninstall,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoRecord {
+    pub id: String,
+    pub kind: UndoKind,
+    pub workspace_id: String,
+    /// What was affected: the removed keys (comma-separated) or the skill name.
+    pub subject: String,
+    pub created_at: u64,
+    pub expires_at: u64,
+    /// File or directory the payload is restored to.
+    pub target: String,
+    /// sha256 of the file right after the operation (file payloads only);
+    /// a different hash at undo time means it was edited since.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_sha256: Option<String>,
+}
+
+fn trash_dir() -> PathBuf {
+    openakita_root_dir().join("trash")
+}
+
+fn index_path(trash: &Path) -> PathBuf {
+    trash.join("undo.json")
+}
+
+fn read_index(trash: &Path) -> Vec<UndoRecord> {
+    fs::read_to_string(index_path(trash))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn write_index(trash: &Path, records: &[UndoRecord]) -> Result<(), String> {
+    fs::create_dir_all(trash).map_err(|e| format!("create {}: {e}", trash.display()))?;
+    let json = serde_json::to_string_pretty(records).map_err(|e| e.to_string())?;
+    atomic_write(index_path(trash), json).map_err(|e| format!("write undo index: {e}"))
+}
+
+fn remove_any(path: &Path) {
+    let _ = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+}
+
+/// Drop expired records and payload dirs that no record refers to (left
+/// behind by a crash between staging and registering).  Orphans younger than
+/// an hour are kept: they may belong to an operation still in progress.
+fn prune(trash: &Path, now: u64) -> Vec<UndoRecord> {
+    let (live, expired): (Vec<_>, Vec<_>) = read_index(trash)
+        .into_iter()
+        .partition(|r| r.expires_at > now);
+    if !expired.is_empty() {
+        let _ = write_index(trash, &live);
+    }
+    if let Ok(rd) = fs::read_dir(trash) {
+        for p in rd.flatten().map(|e| e.path()).filter(|p| p.is_dir()) {
+            let name = p.file_name().unwrap_or_default().to_string_lossy();
+            let staged_at = name
+                .split('-')
+                .next()
+                .and_then(|ms| ms.parse::<u64>().ok())
+                .unwrap_or(0)
+                / 1000;
+            if staged_at + 3600 < now && !live.iter().any(|r| r.id == name) {
+                remove_any(&p);
+            }
+        }
+    }
+    live
+}
+
+pub(crate) fn prune_expired() {
+    let _guard = UNDO_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    prune(&trash_dir(), now_epoch_secs());
+}
+
+fn new_id() -> String {
+    static SEQ: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+    let seq = SEQ.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    format!("{}-{seq}", now_ms())
+}
+
+fn register(trash: &Path, record: UndoRecord) -> Result<(), String> {
+    let _guard = UNDO_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut records = prune(trash, record.created_at);
+    records.push(record);
+    write_index(trash, &records)
+}
+
+pub(crate) fn record_file_change_in(
+    trash: &Path,
+    kind: UndoKind,
+    workspace_id: &str,
+    subject: &str,
+    target: &Path,
+    previous: &str,
+    current: &str,
+) -> Result<String, String> {
+    let id = new_id();
+    let dir = trash.join(&id);
+    fs::create_dir_all(&dir).map_err(|e| format!("create {}: {e}", dir.display()))?;
+    if let Err(e) = atomic_write(dir.join(PAYLOAD), previous) {
+        remove_any(&dir);
+        return Err(format!("write undo payload: {e}"));
+    }
+    let now = now_epoch_secs();
+    register(
+        trash,
+        UndoRecord {
+            id: id.clone(),
+            kind,
+            workspace_id: workspace_id.to_string(),
+            subject: subject.to_string(),
+            created_at: now,
+            expires_at: now + UNDO_RETENTION_SECS,
+            target: target.to_string_lossy().to_string(),
+            target_sha256: Some(sha256_hex(current.as_bytes())),
+        },
+    )
+    .inspect_err(|_| remove_any(&dir))?;
+    Ok(id)
+}
+
+/// Register an undo record for a file rewritten from `previous` to
+/// `current`.  Best effort: a failure is logged and never fails the edit.
+pub(crate) fn record_file_change(
+    kind: UndoKind,
+    workspace_id: &str,
+    subject: &str,
+    target: &Path,
+    previous: &str,
+    current: &str,
+) {
+    if let Err(e) = record_file_change_in(
+        &trash_dir(),
+        kind,
+        workspace_id,
+        subject,
+        target,
+        previous,
+        current,
+    ) {
+        log_to_file(&format!("[undo] record {kind:?} failed: {e}"));
+    }
+}
+
+/// Copy of a directory taken before a destructive call.  Registered with
+/// [`StagedDir::commit`] once the call succeeded; dropped otherwise, which
+/// removes the copy.
+pub(crate) struct StagedDir {
+    trash: PathBuf,
+    id: String,
+    target: PathBuf,
+    committed: bool,
+}
+
+fn copy_tree(from: &Path, to: &Path) -> Result<(), String> {
+    fs::create_dir_all(to).map_err(|e| format!("create {}: {e}", to.display()))?;
+    for entry in fs::read_dir(from)
+        .map_err(|e| format!("read {}: {e}", from.display()))?
+        .flatten()
+    {
+        let path = entry.path();
+        let dest = to.join(entry.file_name());
+        let ft = entry.file_type().map_err(|e| format!("stat: {e}"))?;
+        if ft.is_dir() {
+            copy_tree(&path, &dest)?;
+        } else if ft.is_file() {
+            fs::copy(&path, &dest).map_err(|e| format!("copy {}: {e}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn stage_dir_in(trash: &Path, target: &Path) -> Result<StagedDir, String> {
+    let id = new_id();
+    let staged = StagedDir {
+        trash: trash.to_path_buf(),
+        id,
+        target: target.to_path_buf(),
+        committed: false,
+    };
+    copy_tree(target, &trash.join(&staged.id).join(PAYLOAD))?;
+    Ok(staged)
+}
+
+/// Copy `target` into the trash.  `None` (logged) when it doesn't exist or
+/// can't be copied — the operation then simply goes ahead without undo.
+pub(crate) fn stage_dir(target: &Path) -> Option<StagedDir> {
+    if !target.is_dir() {
+        return None;
+    }
+    stage_dir_in(&trash_dir(), target)
+        .inspect_err(|e| log_to_file(&format!("[undo] stage {} failed: {e}", target.display())))
+        .ok()
+}
+
+impl StagedDir {
+    pub(crate) fn commit(mut self, kind: UndoKind, workspace_id: &str, subject: &str) {
+        let now = now_epoch_secs();
+        let record = UndoRecord {
+            id: self.id.clone(),
+            kind,
+            workspace_id: workspace_id.to_string(),
+            subject: subject.to_string(),
+            created_at: now,
+            expires_at: now + UNDO_RETENTION_SECS,
+            target: self.target.to_string_lossy().to_string(),
+            target_sha256: None,
+        };
+        match register(&self.trash, record) {
+            Ok(()) => self.committed = true,
+            Err(e) => log_to_file(&format!("[undo] record {kind:?} failed: {e}")),
+        }
+    }
+}
+
+impl Drop for StagedDir {
+    fn drop(&mut self) {
+        if !self.committed {
+            remove_any(&self.trash.join(&self.id));
+        }
+    }
+}
+
+pub(crate) fn list_in(trash: &Path, now: u64) -> Vec<UndoRecord> {
+    let _guard = UNDO_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut records = prune(trash, now);
+    records.reverse();
+    records
+}
+
+fn restore_dir(payload: &Path, target: &Path) -> Result<(), String> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("create {}: {e}", parent.display()))?;
+    }
+    if fs::rename(payload, target).is_ok() {
+        return Ok(());
+    }
+    // 跨盘（工作区在别的卷上）时 rename 失败，退回复制
+    copy_tree(payload, target).inspect_err(|_| remove_any(target))
+}
+
+/// Put the payload of record `id` back and drop the record.
+pub(crate) fn undo_in(trash: &Path, id: &str, now: u64) -> Result<UndoRecord, String> {
+    let _guard = UNDO_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut records = prune(trash, now);
+    let pos = records
+        .iter()
+        .position(|r| r.id == id)
+        .ok_or_else(|| format!("NOT_FOUND|撤销记录不存在或已过期: {id}"))?;
+    let record = records[pos].clone();
+    let payload = trash.join(&record.id).join(PAYLOAD);
+    let target = PathBuf::from(&record.target);
+    match &record.target_sha256 {
+        Some(expected) => {
+            let current = fs::read(&target).unwrap_or_default();
+            if sha256_hex(&current) != *expected {
+                return Err(format!(
+                    "UNDO_CONFLICT|{} 在此之后又被修改过，未撤销",
+                    target.display()
+                ));
+            }
+            let previous = fs::read(&payload).map_err(|e| format!("read undo payload: {e}"))?;
+            atomic_write(&target, previous)
+                .map_err(|e| format!("restore {}: {e}", target.display()))?;
+        }
+        None => {
+            if target.exists() {
+                return Err(format!(
+                    "UNDO_CONFLICT|{} 已存在（可能已重新安装），未撤销",
+                    target.display()
+                ));
+            }
+            restore_dir(&payload, &target)?;
+        }
+    }
+    records.remove(pos);
+    write_index(trash, &records)?;
+    remove_any(&trash.join(&record.id));
+    log_to_file(&format!(
+        "[undo] restored {:?} {} ({})",
+        record.kind, record.subject, record.workspace_id
+    ));
+    Ok(record)
+}
+
+/// Undo records still inside the retention window, newest first.
+#[tauri::command]
+pub fn list_undoable_actions() -> Vec<UndoRecord> {
+    list_in(&trash_dir(), now_epoch_secs())
+}
+
+/// Restore what record `id` saved.  Returns the record that was undone.
+#[tauri::command]
+pub async fn undo_action(id: String) -> CmdResult<UndoRecord> {
+    let record =
+        spawn_blocking_result(move || undo_in(&trash_dir(), &id, now_epoch_secs())).await?;
+    if record.kind == UndoKind::SkillUninstall {
+        skills::clear_uninstalled(&record.workspace_id, &record.subject);
+        // 后端在跑就让它重新扫描，恢复的技能立即可用
+        skills::notify_backend_reload(&record.workspace_id, None).await;
+    }
+    Ok(record)
+}
@@ -0,0 +1,539 @@
+//! Localhost webhook relay for IM channels that need a public callback URL.
+//!
+//! WeCom (企业微信智能机器人回调模式) and QQ official bots in webhook mode
+//! push events to an HTTP endpoint the backend adapter serves on a local
+//! port (`WEWORK_CALLBACK_PORT` / `QQBOT_WEBHOOK_PORT`).  During setup the
+//! user usually has no public address, so `start_webhook_relay(channel)`:
+//!
+//! 1. binds a relay listener on `127.0.0.1:<random port>`;
+//! 2. optionally starts a tunnel to it (`cloudflared` quick tunnel or
+//!    `ngrok`) and picks the public URL from the tunnel's output;
+//! 3. forwards each incoming request to the adapter's local callback after
+//!    a structural check — right path, GET/POST only, body ≤ 1 MB, the
+//!    platform's signature parameters present and the timestamp within five
+//!    minutes.  The cryptographic signature check stays in the adapter,
+//!    which owns the secrets.
+//!
+//! Feishu and Slack are not relayed.  The Feishu adapter receives events over
+//! the lark WebSocket long connection and serves no local callback port to
+//! forward to, and there is no Slack adapter in the backend; both get
+//! `WEBHOOK_RELAY_UNSUPPORTED`.
+//!
+//! Status changes and per-request counters are emitted as `webhook-relay`
+//! events; `stop_webhook_relay` tears down the listener and the tunnel.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::BufRead as _;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::Notify;
+
+use crate::errors::CmdResult;
+use crate::{
+    apply_no_window, emit_if_ui_live, env_overlay, log_to_file, net, now_epoch_secs, observer,
+};
+
+const RELAY_MAX_HEADER_BYTES: usize = 16 * 1024;
+const RELAY_MAX_BODY_BYTES: usize = 1024 * 1024;
+const RELAY_MAX_CLOCK_SKEW_SECS: u64 = 5 * 60;
+const RELAY_FORWARD_TIMEOUT: Duration = Duration::from_secs(15);
+const TUNNEL_URL_WAIT: Duration = Duration::from_secs(30);
+
+/// Where a channel's adapter listens and which parameters its callbacks carry.
+struct RelayTarget {
+    port: u16,
+    path: String,
+    kind: &'static str,
+}
+
+fn read_workspace_env_value(workspace_id: &str, key: &str) -> Option<String> {
+    env_overlay::workspace_var(workspace_id, key).filter(|v| !v.is_empty())
+}
+
+fn resolve_target(channel: &str, workspace_id: &str) -> Result<RelayTarget, String> {
+    let env_port = |key: &str, default: u16| {
+        read_workspace_env_value(workspace_id, key)
+            .and_then(|v| v.parse::<u16>().ok())
+            .unwrap_or(default)
+    };
+    match channel {
+        "wework" => Ok(RelayTarget {
+            port: env_port("WEWORK_CALLBACK_PORT", 9880),
+            path: "/callback".into(),
+            kind: "wework",
+        }),
+        "qqbot" => Ok(RelayTarget {
+            port: env_port("QQBOT_WEBHOOK_PORT", 9890),
+            path: read_workspace_env_value(workspace_id, "QQBOT_WEBHOOK_PATH")
+                .unwrap_or_else(|| "/qqbot/callback".into()),
+            kind: "qqbot",
+        }),
+        other => Err(format!(
+            "WEBHOOK_RELAY_UNSUPPORTED|{other} 不需要或不支持回调中继（支持: wework, qqbot）"
+        )),
+    }
+}
+
+/// Structural check of an incoming callback before it is forwarded.
+/// `query` is the raw query string, `headers` are lower-cased names.
+pub(crate) fn verify_callback(
+    kind: &str,
+    method: &str,
+    query: &str,
+    headers: &HashMap<String, String>,
+    now_secs: u64,
+) -> Result<(), String> {
+    if method != "GET" && method != "POST" {
+        return Err(format!("method {method} not allowed"));
+    }
+    let fresh = |ts: Option<&str>| -> Result<(), String> {
+        let ts = ts
+            .and_then(|t| t.trim().parse::<u64>().ok())
+            .ok_or("missing timestamp")?;
+        if now_secs.abs_diff(ts) > RELAY_MAX_CLOCK_SKEW_SECS {
+            return Err(format!("stale timestamp {ts}"));
+        }
+        Ok(())
+    };
+    match kind {
+        "wework" => {
+            let params: HashMap<String, String> = query
+                .split('&')
+                .filter_map(|kv| kv.split_once('='))
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            for p in ["msg_signature", "timestamp", "nonce"] {
+                if params.get(p).is_none_or(|v| v.is_empty()) {
+                    return Err(format!("missing {p}"));
+                }
+            }
+            if method == "GET" && !params.contains_key("echostr") {
+                return Err("missing echostr".into());
+            }
+            fresh(params.get("timestamp").map(String::as_str))
+        }
+        "qqbot" => {
+            if method != "POST" {
+                return Err("qqbot callbacks are POST only".into());
+            }
+            if headers
+                .get("x-signature-ed25519")
+                .is_none_or(|v| v.is_empty())
+            {
+                return Err("missing X-Signature-Ed25519".into());
+            }
+            fresh(headers.get("x-signature-timestamp").map(String::as_str))
+        }
+        _ => Err(format!("unknown relay kind {kind}")),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookRelayStatus {
+    channel: String,
+    running: bool,
+    local_url: String,
+    /// Public base URL from the tunnel (None for `tunnel = "none"`).
+    public_url: Option<String>,
+    /// URL to paste into the IM platform console.
+    callback_url: Option<String>,
+    tunnel: String,
+    forwarded: u64,
+    rejected: u64,
+}
+
+struct RelayHandle {
+    status: WebhookRelayStatus,
+    shutdown: Arc<Notify>,
+    tunnel_child: Option<Child>,
+    forwarded: Arc<AtomicU64>,
+    rejected: Arc<AtomicU64>,
+}
+
+impl RelayHandle {
+    fn snapshot(&self) -> WebhookRelayStatus {
+        let mut s = self.status.clone();
+        s.forwarded = self.forwarded.load(Ordering::Relaxed);
+        s.rejected = self.rejected.load(Ordering::Relaxed);
+        s
+    }
+}
+
+static RELAYS: Lazy<Mutex<HashMap<String, RelayHandle>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+async fn respond(stream: &mut tokio::net::TcpStream, status: u16, reason: &str, body: &[u8]) {
+    let head = format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(body).await;
+}
+
+async fn handle_connection(
+    mut stream: tokio::net::TcpStream,
+    target: Arc<RelayTarget>,
+    forwarded: Arc<AtomicU64>,
+    rejected: Arc<AtomicU64>,
+) {
+    // 读取请求头
+    let mut buf = Vec::with_capacity(4096);
+    let head_end = loop {
+        let mut chunk = [0u8; 4096];
+        let n = match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => n,
+        };
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        if buf.len() > RELAY_MAX_HEADER_BYTES {
+            respond(&mut stream, 431, "Request Header Fields Too Large", b"").await;
+            return;
+        }
+    };
+    let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut parts = lines.next().unwrap_or_default().split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target_uri = parts.next().unwrap_or_default().to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+    let (path, query) = target_uri.split_once('?').unwrap_or((&target_uri, ""));
+
+    let reject = |reason: String| {
+        rejected.fetch_add(1, Ordering::Relaxed);
+        log_to_file(&format!(
+            "[webhook-relay] {} rejected {method} {path}: {reason}",
+            target.kind
+        ));
+    };
+    if path != target.path {
+        reject("unexpected path".into());
+        respond(&mut stream, 404, "Not Found", b"").await;
+        return;
+    }
+    if let Err(e) = verify_callback(target.kind, &method, query, &headers, now_epoch_secs()) {
+        reject(e);
+        respond(&mut stream, 403, "Forbidden", b"").await;
+        return;
+    }
+
+    // 读取请求体
+    let content_length = headers
+        .get("content-length")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    if content_length > RELAY_MAX_BODY_BYTES {
+        reject(format!("body too large ({content_length} bytes)"));
+        respond(&mut stream, 413, "Payload Too Large", b"").await;
+        return;
+    }
+    let mut body = buf[head_end + 4..].to_vec();
+    while body.len() < content_length {
+        let mut chunk = vec![0u8; (content_length - body.len()).min(64 * 1024)];
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => body.extend_from_slice(&chunk[..n]),
+        }
+    }
+    body.truncate(content_length);
+
+    let url = format!("http://127.0.0.1:{}{}", target.port, target_uri);
+    let method_parsed =
+        reqwest::Method::from_bytes(method.as_bytes()).unwrap_or(reqwest::Method::POST);
+    let mut req = net::local_http_client()
+        .request(method_parsed, &url)
+        .timeout(RELAY_FORWARD_TIMEOUT)
+        .body(body);
+    for (k, v) in &headers {
+        if matches!(k.as_str(), "content-type" | "user-agent")
+            || k.starts_with("x-signature-")
+            || k.starts_with("x-bot-")
+        {
+            req = req.header(k, v);
+        }
+    }
+    match req.send().await {
+        Ok(resp) => {
+            forwarded.fetch_add(1, Ordering::Relaxed);
+            let status = resp.status();
+            let bytes = resp.bytes().await.map(|b| b.to_vec()).unwrap_or_default();
+            respond(
+                &mut stream,
+                status.as_u16(),
+                status.canonical_reason().unwrap_or("OK"),
+                &bytes,
+            )
+            .await;
+        }
+        Err(e) => {
+            log_to_file(&format!(
+                "[webhook-relay] {} forward to {url} failed: {e}",
+                target.kind
+            ));
+            respond(&mut stream, 502, "Bad Gateway", b"").await;
+        }
+    }
+}
+
+/// Extract the public URL from one line of tunnel output.
+pub(crate) fn parse_tunnel_url(tunnel: &str, line: &str) -> Option<String> {
+    match tunnel {
+        "cloudflared" => {
+            let start = line.find("https://")?;
+            let url: String = line[start..]
+                .chars()
+                .take_while(|c| !c.is_whitespace() && *c != '|')
+                .collect();
+            url.ends_with(".trycloudflare.com").then_some(url)
+        }
+        "ngrok" => {
+            let v: serde_json::Value = serde_json::from_str(line).ok()?;
+            v.get("url")
+                .and_then(|u| u.as_str())
+                .filter(|u| u.starts_with("https://"))
+                .map(|u| u.to_string())
+        }
+        _ => None,
+    }
+}
+
+fn spawn_tunnel(
+    tunnel: &str,
+    binary: Option<&str>,
+    local_port: u16,
+) -> Result<(Child, String), String> {
+    let local = format!("http://127.0.0.1:{local_port}");
+    let mut cmd = match tunnel {
+        "cloudflared" => {
+            let mut c = Command::new(binary.unwrap_or("cloudflared"));
+            c.args(["tunnel", "--no-autoupdate", "--url", &local]);
+            c
+        }
+        "ngrok" => {
+            let mut c = Command::new(binary.unwrap_or("ngrok"));
+            c.args([
+                "http",
+                &local_port.to_string(),
+                "--log",
+                "stdout",
+                "--log-format",
+                "json",
+            ]);
+            c
+        }
+        other => return Err(format!("unknown tunnel provider: {other}")),
+    };
+    apply_no_window(&mut cmd);
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = cmd.spawn().map_err(|e| {
+        format!("WEBHOOK_TUNNEL_MISSING|无法启动 {tunnel}（请确认已安装并在 PATH 中）: {e}")
+    })?;
+
+    // cloudflared 把地址打在 stderr，ngrok 打在 stdout；两个都读
+    let (tx, rx) = std::sync::mpsc::channel::<String>();
+    let pipes: Vec<Box<dyn std::io::Read + Send>> = vec![
+        Box::new(child.stdout.take().ok_or("tunnel stdout missing")?),
+        Box::new(child.stderr.take().ok_or("tunnel stderr missing")?),
+    ];
+    for pipe in pipes {
+        let tx = tx.clone();
+        let tunnel = tunnel.to_string();
+        std::thread::spawn(move || {
+            for line in std::io::BufReader::new(pipe).lines().map_while(Result::ok) {
+                if let Some(url) = parse_tunnel_url(&tunnel, &line) {
+                    let _ = tx.send(url);
+                }
+            }
+        });
+    }
+    drop(tx);
+    match rx.recv_timeout(TUNNEL_URL_WAIT) {
+        Ok(url) => Ok((child, url)),
+        Err(_) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            Err(format!(
+                "WEBHOOK_TUNNEL_FAILED|{tunnel} 在 {}s 内未返回公网地址",
+                TUNNEL_URL_WAIT.as_secs()
+            ))
+        }
+    }
+}
+
+fn emit_status(app: &tauri::AppHandle, status: &WebhookRelayStatus) {
+    emit_if_ui_live(app, "webhook-relay", status.clone());
+}
+
+fn stop_relay_locked(
+    map: &mut HashMap<String, RelayHandle>,
+    channel: &str,
+) -> Option<WebhookRelayStatus> {
+    let mut handle = map.remove(channel)?;
+    handle.shutdown.notify_waiters();
+    if let Some(mut child) = handle.tunnel_child.take() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+    let mut status = handle.snapshot();
+    status.running = false;
+    log_to_file(&format!("[webhook-relay] {channel} stopped"));
+    Some(status)
+}
+
+/// 启动回调中继。`tunnel`: `none`（默认，仅本地）/ `cloudflared` / `ngrok`；
+/// `tunnel_binary` 可指定可执行文件路径。同一通道重复调用会先停掉旧的中继。
+#[tauri::command]
+pub async fn start_webhook_relay(
+    app: tauri::AppHandle,
+    channel: String,
+    workspace_id: String,
+    tunnel: Option<String>,
+    tunnel_binary: Option<String>,
+) -> CmdResult<WebhookRelayStatus> {
+    observer::ensure_writable(&workspace_id)?;
+    let target = Arc::new(resolve_target(&channel, &workspace_id)?);
+    let tunnel = tunnel.unwrap_or_else(|| "none".into());
+    if tunnel != "none" {
+        net::ensure_online("启动回调隧道")?;
+    }
+    if let Ok(mut map) = RELAYS.lock() {
+        stop_relay_locked(&mut map, &channel);
+    }
+
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+        .await
+        .map_err(|e| format!("bind relay listener failed: {e}"))?;
+    let local_port = listener
+        .local_addr()
+        .map_err(|e| format!("relay listener address: {e}"))?
+        .port();
+
+    let (tunnel_child, public_url) = if tunnel == "none" {
+        (None, None)
+    } else {
+        let t = tunnel.clone();
+        let bin = tunnel_binary.clone();
+        let (child, url) = tauri::async_runtime::spawn_blocking(move || {
+            spawn_tunnel(&t, bin.as_deref(), local_port)
+        })
+        .await
+        .map_err(|e| format!("tunnel task failed: {e}"))??;
+        (Some(child), Some(url))
+    };
+
+    let shutdown = Arc::new(Notify::new());
+    let forwarded = Arc::new(AtomicU64::new(0));
+    let rejected = Arc::new(AtomicU64::new(0));
+    let local_url = format!("http://127.0.0.1:{local_port}");
+    let status = WebhookRelayStatus {
+        channel: channel.clone(),
+        running: true,
+        callback_url: Some(format!(
+            "{}{}",
+            public_url.as_deref().unwrap_or(&local_url),
+            target.path
+        )),
+        local_url,
+        public_url,
+        tunnel: tunnel.clone(),
+        forwarded: 0,
+        rejected: 0,
+    };
+
+    {
+        let shutdown = shutdown.clone();
+        let forwarded = forwarded.clone();
+        let rejected = rejected.clone();
+        let target = target.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = shutdown.notified() => break,
+                    accepted = listener.accept() => {
+                        let Ok((stream, _)) = accepted else { continue };
+                        tauri::async_runtime::spawn(handle_connection(
+                            stream,
+                            target.clone(),
+                            forwarded.clone(),
+                            rejected.clone(),
+                        ));
+                    }
+                }
+            }
+        });
+    }
+
+    log_to_file(&format!(
+        "[webhook-relay] {channel} listening on 127.0.0.1:{local_port} -> 127.0.0.1:{}{} (tunnel={tunnel})",
+        target.port, target.path
+    ));
+    emit_status(&app, &status);
+    let mut map = RELAYS
+        .lock()
+        .map_err(|e| format!("relay lock failed: {e}"))?;
+    map.insert(
+        channel,
+        RelayHandle {
+            status: status.clone(),
+            shutdown,
+            tunnel_child,
+            forwarded,
+            rejected,
+        },
+    );
+    Ok(status)
+}
+
+/// 停止回调中继；`channel` 为空时停止全部。
+#[tauri::command]
+pub fn stop_webhook_relay(
+    app: tauri::AppHandle,
+    channel: Option<String>,
+) -> CmdResult<Vec<WebhookRelayStatus>> {
+    let mut map = RELAYS
+        .lock()
+        .map_err(|e| format!("relay lock failed: {e}"))?;
+    let channels: Vec<String> = match channel {
+        Some(c) => vec![c],
+        None => map.keys().cloned().collect(),
+    };
+    let stopped: Vec<WebhookRelayStatus> = channels
+        .iter()
+        .filter_map(|c| stop_relay_locked(&mut map, c))
+        .collect();
+    for s in &stopped {
+        emit_status(&app, s);
+    }
+    Ok(stopped)
+}
+
+#[tauri::command]
+pub fn get_webhook_relay_status() -> Vec<WebhookRelayStatus> {
+    RELAYS
+        .lock()
+        .map(|map| map.values().map(RelayHandle::snapshot).collect())
+        .unwrap_or_default()
+}
+
+/// 退出时清理隧道子进程，避免 cloudflared / ngrok 残留。
+pub(crate) fn stop_all_relays() {
+    if let Ok(mut map) = RELAYS.lock() {
+        let channels: Vec<String> = map.keys().cloned().collect();
+        for c in channels {
+            stop_relay_locked(&mut map, &c);
+        }
+    }
+}
@@ -0,0 +1,321 @@
+//! WSL (Windows Subsystem for Linux) awareness.
+//!
+//! Two situations used to misbehave silently:
+//!
+//! * Setup Center on Windows, backend wanted inside a WSL distro — a PID
+//!   from inside the distro means nothing to Windows, paths like
+//!   `C:\Users\..` don't exist there, and environment variables don't cross
+//!   the boundary unless listed in `WSLENV`.
+//! * Setup Center itself running inside WSL (WSLg) — Windows-side backends
+//!   are invisible to `kill -0` / `/proc`, so they look stopped.
+//!
+//! [`detect`] reports which side we're on and, on Windows, the installed
+//! distros.  A workspace can opt into "WSL workspace" mode
+//! (`set_wsl_workspace`): the service is then started as
+//! `wsl.exe -d <distro> --cd <translated workspace dir> -- <python> -m
+//! openakita.main serve`, path-valued variables are forwarded with `WSLENV`
+//! `/p` translation, the tracked PID is the `wsl.exe` launcher, and stop
+//! falls back to killing the port's listener inside the distro.  The
+//! config lives in `<workspace>/wsl.json`; only Windows honours it.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use std::sync::Mutex;
+
+use crate::errors::CmdResult;
+use crate::{atomic_write, log_to_file, observer, workspace_dir};
+
+const CONFIG_FILE: &str = "wsl.json";
+
+/// Variables the backend needs from the desktop side; `true` = path value,
+/// translated by WSL (`NAME/p`).
+const FORWARDED_ENV: &[(&str, bool)] = &[
+    ("PYTHONUTF8", false),
+    ("PYTHONIOENCODING", false),
+    ("PYTHONUNBUFFERED", false),
+    ("NO_COLOR", false),
+    ("OPENAKITA_DESKTOP_SESSION_TOKEN", false),
+    ("OPENAKITA_SPAWN_STARTED_AT_MS", false),
+    ("LLM_ENDPOINTS_CONFIG", true),
+    ("OPENAKITA_ROOT", true),
+];
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WslInfo {
+    /// This process runs inside a WSL distro.
+    pub inside_wsl: bool,
+    /// 1 or 2 when `inside_wsl`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wsl_version: Option<u8>,
+    /// Windows host with `wsl.exe` and at least one distro.
+    pub available: bool,
+    pub distros: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_distro: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WslWorkspaceConfig {
+    pub distro: String,
+    /// Python inside the distro that has `openakita` installed.
+    #[serde(default = "default_python")]
+    pub python: String,
+}
+
+fn default_python() -> String {
+    "python3".to_string()
+}
+
+static INFO: Lazy<Mutex<Option<WslInfo>>> = Lazy::new(|| Mutex::new(None));
+
+/// `C:\Users\a` → `/mnt/c/Users/a`; `\\wsl$\Ubuntu\home\a` (or
+/// `\\wsl.localhost\..`) → `/home/a`.  Other UNC paths have no WSL form.
+pub(crate) fn windows_to_wsl_path(path: &str) -> Option<String> {
+    let p = path.replace('\\', "/");
+    for prefix in ["//wsl$/", "//wsl.localhost/"] {
+        if p.len() > prefix.len() && p[..prefix.len()].eq_ignore_ascii_case(prefix) {
+            let rest = &p[prefix.len()..];
+            let inner = rest.find('/').map(|i| &rest[i..]).unwrap_or("/");
+            return Some(inner.to_string());
+        }
+    }
+    let bytes = p.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        let drive = (bytes[0] as char).to_ascii_lowercase();
+        let rest = p[2..].trim_end_matches('/');
+        return Some(format!("/mnt/{drive}{rest}"));
+    }
+    None
+}
+
+/// `/mnt/c/x` → `C:\x`; anything else → `\\wsl.localhost\<distro>\..`.
+pub(crate) fn wsl_to_windows_path(path: &str, distro: &str) -> String {
+    if let Some(rest) = path.strip_prefix("/mnt/") {
+        let (drive, tail) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        if drive.len() == 1 && drive.as_bytes()[0].is_ascii_alphabetic() {
+            return format!(
+                "{}:\\{}",
+                drive.to_ascii_uppercase(),
+                tail.trim_start_matches('/').replace('/', "\\")
+            );
+        }
+    }
+    format!("\\\\wsl.localhost\\{distro}{}", path.replace('/', "\\"))
+}
+
+/// `wsl.exe -l -q` output: UTF-16LE when written to a pipe (no BOM), one
+/// distro per line, default first.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) fn parse_distro_list(raw: &[u8]) -> Vec<String> {
+    let text = if raw.len() >= 2 && raw.iter().skip(1).step_by(2).all(|b| *b == 0) {
+        let units: Vec<u16> = raw
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        String::from_utf8_lossy(raw).to_string()
+    };
+    text.lines()
+        .map(|l| l.trim_matches(|c: char| c.is_whitespace() || c == '\u{feff}' || c == '\0'))
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// WSL version from `/proc/sys/kernel/osrelease` (`None` = not WSL).
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn wsl_version_from_osrelease(osrelease: &str) -> Option<u8> {
+    let lower = osrelease.to_lowercase();
+    if lower.contains("wsl2") || lower.contains("microsoft-standard") {
+        Some(2)
+    } else if lower.contains("microsoft") {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+fn run_detect() -> WslInfo {
+    let mut info = WslInfo::default();
+    #[cfg(target_os = "linux")]
+    {
+        let osrelease = std::fs::read_to_string("/proc/sys/kernel/osrelease").unwrap_or_default();
+        info.wsl_version = wsl_version_from_osrelease(&osrelease);
+        info.inside_wsl =
+            info.wsl_version.is_some() || Path::new("/proc/sys/fs/binfmt_misc/WSLInterop").exists();
+    }
+    #[cfg(windows)]
+    {
+        let mut cmd = Command::new("wsl.exe");
+        cmd.args(["--list", "--quiet"]);
+        crate::apply_no_window(&mut cmd);
+        if let Ok(out) = cmd.output() {
+            if out.status.success() {
+                info.distros = parse_distro_list(&out.stdout);
+            }
+        }
+        info.default_distro = info.distros.first().cloned();
+        info.available = !info.distros.is_empty();
+    }
+    log_to_file(&format!("[wsl] detected: {info:?}"));
+    info
+}
+
+/// WSL environment (cached; `refresh` re-probes, e.g. after installing a
+/// distro).
+pub(crate) fn detect(refresh: bool) -> WslInfo {
+    let mut guard = INFO.lock().unwrap_or_else(|e| e.into_inner());
+    if refresh || guard.is_none() {
+        *guard = Some(run_detect());
+    }
+    guard.clone().unwrap_or_default()
+}
+
+fn read_config(workspace_id: &str) -> Option<WslWorkspaceConfig> {
+    let path = workspace_dir(workspace_id).join(CONFIG_FILE);
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// The workspace's WSL config when it should be honoured (Windows only).
+pub(crate) fn active_config(workspace_id: &str) -> Option<WslWorkspaceConfig> {
+    if cfg!(windows) {
+        read_config(workspace_id)
+    } else {
+        None
+    }
+}
+
+/// `WSLENV` value forwarding [`FORWARDED_ENV`] on top of `existing`.
+pub(crate) fn wslenv_value(existing: Option<&str>) -> String {
+    let mut parts: Vec<String> = existing
+        .unwrap_or_default()
+        .split(':')
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect();
+    for (name, is_path) in FORWARDED_ENV {
+        let already = parts.iter().any(|p| p.split('/').next() == Some(*name));
+        if !already {
+            parts.push(if *is_path {
+                format!("{name}/p")
+            } else {
+                (*name).to_string()
+            });
+        }
+    }
+    parts.join(":")
+}
+
+/// Command that runs `openakita serve` for a workspace inside its distro.
+/// Environment set on the command afterwards reaches the backend only when
+/// listed in [`FORWARDED_ENV`].
+pub(crate) fn service_command(
+    config: &WslWorkspaceConfig,
+    ws_dir: &Path,
+) -> Result<Command, String> {
+    let linux_dir = windows_to_wsl_path(&ws_dir.to_string_lossy()).ok_or_else(|| {
+        format!(
+            "工作区目录 {} 无法映射到 WSL 路径 / workspace dir has no WSL path",
+            ws_dir.display()
+        )
+    })?;
+    let mut cmd = Command::new("wsl.exe");
+    cmd.args(["-d", &config.distro, "--cd", &linux_dir, "--"])
+        .args([config.python.as_str(), "-m", "openakita.main", "serve"]);
+    cmd.env(
+        "WSLENV",
+        wslenv_value(std::env::var("WSLENV").ok().as_deref()),
+    );
+    Ok(cmd)
+}
+
+/// Killing `wsl.exe` doesn't reliably take down the Linux process behind
+/// it; after a stop, kill whatever still listens on the workspace port
+/// inside the distro.
+pub(crate) fn stop_leftover(workspace_id: &str, port: u16) {
+    let Some(config) = active_config(workspace_id) else {
+        return;
+    };
+    let mut cmd = Command::new("wsl.exe");
+    cmd.args(["-d", &config.distro, "--", "fuser", "-k", "-TERM"])
+        .arg(format!("{port}/tcp"));
+    crate::apply_no_window(&mut cmd);
+    let result = cmd.output();
+    log_to_file(&format!(
+        "[wsl] stop leftover ws={workspace_id} distro={} port={port}: {:?}",
+        config.distro,
+        result.map(|o| o.status.code())
+    ));
+}
+
+#[tauri::command]
+pub fn get_wsl_info(refresh: Option<bool>) -> WslInfo {
+    detect(refresh.unwrap_or(false))
+}
+
+/// Translate a path across the boundary: Linux paths (`/...`) to their
+/// Windows form in `distro`, Windows paths to their WSL form.
+#[tauri::command]
+pub fn wsl_translate_path(path: String, distro: Option<String>) -> CmdResult<String> {
+    if path.starts_with('/') {
+        let distro = distro
+            .or_else(|| detect(false).default_distro)
+            .ok_or("INVALID_ARGUMENT|需要指定 WSL 发行版 / distro required")?;
+        return Ok(wsl_to_windows_path(&path, &distro));
+    }
+    windows_to_wsl_path(&path)
+        .ok_or_else(|| format!("INVALID_ARGUMENT|{path} 没有对应的 WSL 路径").into())
+}
+
+#[tauri::command]
+pub fn get_wsl_workspace(workspace_id: String) -> Option<WslWorkspaceConfig> {
+    read_config(&workspace_id)
+}
+
+/// Switch a workspace into (`Some`) or out of (`None`) WSL mode.  Takes
+/// effect on the next service start.
+#[tauri::command]
+pub fn set_wsl_workspace(
+    workspace_id: String,
+    config: Option<WslWorkspaceConfig>,
+) -> CmdResult<Option<WslWorkspaceConfig>> {
+    observer::ensure_writable(&workspace_id)?;
+    let path = workspace_dir(&workspace_id).join(CONFIG_FILE);
+    let Some(mut config) = config else {
+        if path.exists() {
+            std::fs::remove_file(&path).map_err(|e| format!("remove {CONFIG_FILE}: {e}"))?;
+        }
+        return Ok(None);
+    };
+    if !cfg!(windows) {
+        return Err(
+            "INVALID_ARGUMENT|WSL 工作区只能在 Windows 上使用 / WSL workspaces need Windows".into(),
+        );
+    }
+    let info = detect(true);
+    if !info.distros.iter().any(|d| d == &config.distro) {
+        return Err(format!(
+            "INVALID_ARGUMENT|WSL 发行版不存在: {} (已安装: {})",
+            config.distro,
+            info.distros.join(", ")
+        )
+        .into());
+    }
+    config.python = config.python.trim().to_string();
+    if config.python.is_empty() {
+        config.python = default_python();
+    }
+    let data = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    atomic_write(&path, data)?;
+    log_to_file(&format!(
+        "[wsl] workspace {workspace_id} now runs in {}",
+        config.distro
+    ));
+    Ok(Some(config))
+}
@@ -75,6 +75,8 @@ type ServiceStatus = AppServiceStatus & {
   heartbeatImReady?: boolean;
   heartbeatReady?: boolean;
   lastLinkDiagnostic?: LinkDiagnostic | null;
+  /** Only on start / stop results: id of the lifecycle operation that produced it. */
+  operationId?: number;
 };
 
 const externalRunningStatus = (pid: number | null = null): ServiceStatus => ({
@@ -763,6 +765,15 @@ function MainApp() {
   const [autoUpdateEnabled, setAutoUpdateEnabled] = useState<boolean | null>(null);
   // autoStartBackend 已合并到"开机自启"：--background 模式自动拉起后端，无需独立开关
   const [serviceStatus, setServiceStatus] = useState<ServiceStatus | null>(null);
+  // 最新一次 start / stop 操作的 id：快速连点时，先发起、后返回的旧操作结果不再覆盖界面
+  const latestServiceOpRef = useRef(0);
+  const applyServiceOpResult = useCallback((ss: ServiceStatus) => {
+    if (ss.operationId != null) {
+      if (ss.operationId < latestServiceOpRef.current) return;
+      latestServiceOpRef.current = ss.operationId;
+    }
+    setServiceStatus(ss);
+  }, []);
   // ── 后端启动阶段（独立于 serviceStatus）──
   // serviceStatus 只能表达 "running:true|false"，无法区分"未启动"和"正在启动中"。
   // 老 UI 在自动启动期间一旦 invoke is_backend_auto_starting 偶发返回 false 或失败，
@@ -1385,6 +1396,38 @@ function MainApp() {
     };
   }, [pipInstallPolling, pipInstallId]);
 
+  // start / stop 完成事件：记下最新的操作 id（其他窗口或托盘发起的操作也算）
+  useEffect(() => {
+    let unlisten: null | (() => void) = null;
+    (async () => {
+      unlisten = await listen<{ operationId: number; workspaceId: string; kind: string; ok: boolean }>(
+        "service-operation",
+        (ev) => {
+          const { operationId, workspaceId, kind, ok } = ev.payload;
+          latestServiceOpRef.current = Math.max(latestServiceOpRef.current, operationId);
+          logger.info("App", `service ${kind} #${operationId} for ${workspaceId} finished ok=${ok}`);
+        },
+      );
+    })();
+    return () => {
+      if (unlisten) unlisten();
+    };
+  }, []);
+
+  // 切换配置档后整个数据根目录都换了：重新加载工作区 / 状态
+  useEffect(() => {
+    let unlisten: null | (() => void) = null;
+    (async () => {
+      unlisten = await listen("profile-switched", () => {
+        refreshAll().catch((e) => logger.warn("App", "refresh after profile switch failed", String(e)));
+      });
+    })();
+    return () => {
+      if (unlisten) unlisten();
+    };
+  // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, []);
+
   // tray quit failed: service still running
   useEffect(() => {
     let unlisten: null | (() => void) = null;
@@ -1686,7 +1729,7 @@ function MainApp() {
             const ss = await invoke<ServiceStatus>(
               "openakita_service_start", { venvDir, workspaceId: opts.targetId },
             );
-            setServiceStatus(ss);
+            applyServiceOpResult(ss);
           } catch (e) {
             setRestartOverlay({ phase: "fail" });
             setTimeout(() => {
@@ -2215,7 +2258,7 @@ function MainApp() {
           const ss = await invoke<ServiceStatus>(
             "openakita_service_start", { venvDir, workspaceId: wsId },
           );
-          setServiceStatus(ss);
+          applyServiceOpResult(ss);
         } catch (e) {
           setRestartOverlay({ phase: "fail" });
           setTimeout(() => {
@@ -2921,7 +2964,7 @@ function MainApp() {
         venvDir,
         workspaceId: effectiveWsId,
       });
-      setServiceStatus(ss);
+      applyServiceOpResult(ss);
       const ready = await waitForServiceReady("http://127.0.0.1:18900", LOCAL_SERVICE_READY_TIMEOUT_MS);
       const real = await invoke<ServiceStatus>("openakita_service_status", {
         workspaceId: effectiveWsId,
@@ -3078,7 +3121,7 @@ function MainApp() {
     // 2. PID-based kill as fallback (handles locally started services)
     try {
       const ss = await invoke<ServiceStatus>("openakita_service_stop", { workspaceId: id });
-      setServiceStatus(ss);
+      applyServiceOpResult(ss);
     } catch { /* PID file might not exist for externally started services */ }
     // 3. Quick verify — is the port freed?
     await new Promise((r) => setTimeout(r, 300));
@@ -3684,7 +3727,7 @@ function MainApp() {
             log(t("onboarding.progress.startingService"));
             setObBackendStartupPhase("starting", t("onboarding.backendStartup.starting"));
             const ss = await invoke<ServiceStatus>("openakita_service_start", { venvDir: effectiveVenv, workspaceId: activeWsId });
-            setServiceStatus(ss);
+            applyServiceOpResult(ss);
             log(t("onboarding.progress.serviceStarted"));
             updateTask("service-start", { status: "done" });
             logTask("启动后端服务", "done");
@@ -4051,6 +4094,11 @@ function MainApp() {
               )}
               {backendStartupNotice}
 
+              {info?.portable ? (
+                <p className="w-full max-w-[460px] mt-1 text-xs text-muted-foreground">
+                  {t("onboarding.welcome.portableRoot", { path: info.openakitaRootDir })}
+                </p>
+              ) : (
               <div className="w-full max-w-[460px] mt-1">
                 <Button
                   variant="ghost"
@@ -4164,6 +4212,7 @@ function MainApp() {
                   </Card>
                 )}
               </div>
+              )}
 
               <Button
                 size="lg"
@@ -4210,7 +4259,7 @@ function MainApp() {
                         }
                         setObBackendStartupPhase("starting", t("onboarding.backendStartup.starting"));
                         const ss = await invoke<ServiceStatus>("openakita_service_start", { venvDir: effectiveVenv, workspaceId: wsId });
-                        setServiceStatus(ss);
+                        applyServiceOpResult(ss);
                         setObBackendStartupPhase("waiting", t("onboarding.backendStartup.waiting"));
                         let earlyHttpReady = false;
                         const maxEarlyHttpWaitTicks = Math.ceil(ONBOARDING_HTTP_READY_TIMEOUT_MS / HTTP_READY_POLL_INTERVAL_MS);
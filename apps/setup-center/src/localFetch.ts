@@ -20,6 +20,8 @@
  * is performed.
  */
 
+import { toCommandError } from "./platform";
+
 const LOCAL_RE = /^https?:\/\/(127\.0\.0\.1|localhost)(:\d+)?(?:\/|$)/;
 const TEXTUAL_BODY_RE = /^(application\/json\b|text\/|application\/x-www-form-urlencoded\b)/i;
 
@@ -192,7 +194,9 @@ export function installLocalFetchOverride(): void {
         headers,
         body,
       },
-    );
+    ).catch((err) => {
+      throw toCommandError(err);
+    });
 
     // Abort handling has two distinct concerns:
     //
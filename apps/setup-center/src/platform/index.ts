@@ -30,10 +30,94 @@ export function getAssetUrl(filePath: string): string | null {
 // Core: invoke & listen
 // ---------------------------------------------------------------------------
 
+/**
+ * Error codes a Tauri command can reject with (mirror of `ErrorCode` in
+ * `src-tauri/src/errors.rs`). `UNKNOWN` = free-form message only.
+ */
+export type CommandErrorCode =
+  | "UNKNOWN"
+  | "PANIC"
+  | "INVALID_ARGUMENT"
+  | "NOT_FOUND"
+  | "ALREADY_EXISTS"
+  | "PATH_ESCAPE"
+  | "FILE_TOO_LARGE"
+  | "BINARY_FILE"
+  | "URL_REJECTED"
+  | "BRIDGE_FAILED"
+  | "BRIDGE_TIMEOUT"
+  | "BRIDGE_CANCELLED"
+  | "BRIDGE_PROTOCOL"
+  | "REQUEST_CANCELLED"
+  | "OFFLINE_MODE"
+  | "GITHUB_RATE_LIMITED"
+  | "RUNTIME_INSTALL_TIMEOUT"
+  | "RUNTIME_PERMISSION_DENIED"
+  | "RUNTIME_WHEEL_HASH_MISMATCH"
+  | "SKILL_MANIFEST_INVALID"
+  | "SKILL_CONFIG_INVALID"
+  | "SKILL_UNVERIFIED"
+  | "SKILL_CHECKSUM_MISMATCH"
+  | "SKILL_NO_HISTORY"
+  | "OAUTH_NOT_CONFIGURED"
+  | "OAUTH_UNSUPPORTED"
+  | "OAUTH_DENIED"
+  | "OAUTH_STATE_MISMATCH"
+  | "OAUTH_TIMEOUT"
+  | "WEBHOOK_RELAY_UNSUPPORTED"
+  | "WEBHOOK_TUNNEL_MISSING"
+  | "WEBHOOK_TUNNEL_FAILED"
+  | "ELEVATION_DECLINED"
+  | "ELEVATION_FAILED"
+  | "UNDO_CONFLICT"
+  | "INSUFFICIENT_DISK_SPACE"
+  | "MODEL_CHECKSUM_MISMATCH"
+  | "OLLAMA_NOT_RUNNING"
+  | "BACKEND_RUNNING"
+  | "BACKEND_NOT_RUNNING"
+  | "BACKEND_INCOMPATIBLE"
+  | "BACKEND_UNAUTHORIZED"
+  | "BACKEND_TIMEOUT"
+  | "OBSERVER_READ_ONLY";
+
+/**
+ * Rejection value of `invoke()`. `String(err)` / `${err}` still give the
+ * plain message, so existing `String(e)` call sites keep working; new code
+ * can branch on `err.code` / `err.retryable`.
+ */
+export class CommandError extends Error {
+  code: CommandErrorCode;
+  details?: unknown;
+  retryable: boolean;
+
+  constructor(code: CommandErrorCode, message: string, details?: unknown, retryable = false) {
+    super(message);
+    this.name = "CommandError";
+    this.code = code;
+    this.details = details;
+    this.retryable = retryable;
+  }
+
+  toString(): string {
+    return this.message;
+  }
+}
+
+/** Normalize whatever a command rejected with into a `CommandError`. */
+export function toCommandError(err: unknown): CommandError {
+  if (err instanceof CommandError) return err;
+  if (err && typeof err === "object" && "code" in err && "message" in err) {
+    const p = err as { code: CommandErrorCode; message: string; details?: unknown; retryable?: boolean };
+    return new CommandError(p.code, String(p.message), p.details, !!p.retryable);
+  }
+  return new CommandError("UNKNOWN", err instanceof Error ? err.message : String(err));
+}
+
 /**
  * Drop-in replacement for `@tauri-apps/api/core` `invoke`.
  * In web mode this always throws — callers must guard with `IS_TAURI` or
  * use higher-level helpers that provide web fallbacks.
+ * Rejects with a `CommandError`.
  */
 export async function invoke<T>(
   cmd: string,
@@ -42,7 +126,64 @@ export async function invoke<T>(
   if (!IS_TAURI)
     throw new Error(`Tauri invoke("${cmd}") is not available in web mode`);
   const { invoke: tauriInvoke } = await import("@tauri-apps/api/core");
-  return tauriInvoke<T>(cmd, args);
+  try {
+    return await tauriInvoke<T>(cmd, args);
+  } catch (err) {
+    throw toCommandError(err);
+  }
+}
+
+let _bridgeCallCounter = 0;
+
+/**
+ * `invoke` for bridge commands that accept a `callId` (list-models,
+ * list-skills, list-providers).  Aborting `signal` kills the Python side
+ * via `cancel_bridge_call`; the promise then rejects with
+ * `BRIDGE_CANCELLED`.
+ */
+export async function invokeBridge<T>(
+  cmd: string,
+  args: Record<string, unknown>,
+  signal?: AbortSignal,
+): Promise<T> {
+  _bridgeCallCounter += 1;
+  const callId = `${cmd}-${Date.now()}-${_bridgeCallCounter}`;
+  const cancel = () => {
+    invoke("cancel_bridge_call", { callId }).catch(() => {});
+  };
+  if (signal?.aborted) cancel();
+  signal?.addEventListener("abort", cancel, { once: true });
+  try {
+    return await invoke<T>(cmd, { ...args, callId });
+  } finally {
+    signal?.removeEventListener("abort", cancel);
+  }
+}
+
+export type BackendCallParams = {
+  path?: Record<string, string | number>;
+  query?: Record<string, unknown>;
+  body?: unknown;
+  timeoutMs?: number;
+  token?: string;
+};
+
+/**
+ * Call an operation of the workspace's running backend by its OpenAPI
+ * `operationId` (see `get_backend_api_info`), without knowing its port or
+ * route.  Resolves with the response JSON.
+ */
+export async function callBackend<T>(
+  workspaceId: string,
+  operationId: string,
+  params?: BackendCallParams,
+): Promise<T> {
+  const res = await invoke<{ status: number; data: T }>("call_backend", {
+    workspaceId,
+    operationId,
+    params,
+  });
+  return res.data;
 }
 
 /**
@@ -142,6 +283,16 @@ export async function showInFolder(path: string): Promise<void> {
   await tauriInvoke("show_item_in_folder", { path });
 }
 
+/**
+ * Reveal a path in the OS file manager (files selected, directories opened);
+ * missing paths fall back to the nearest existing parent. No-op on web.
+ */
+export async function revealPath(path: string): Promise<{ opened: string; fellBack: boolean } | null> {
+  if (!IS_TAURI) return null;
+  const { invoke: tauriInvoke } = await import("@tauri-apps/api/core");
+  return tauriInvoke<{ opened: string; fellBack: boolean }>("reveal_path", { path });
+}
+
 /** Open a file with the OS default application. No-op on web. */
 export async function openFileWithDefault(path: string): Promise<void> {
   if (!IS_TAURI) return;
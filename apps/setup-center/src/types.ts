@@ -5,6 +5,8 @@ export type PlatformInfo = {
   arch: string;
   homeDir: string;
   openakitaRootDir: string;
+  /** portable.flag next to the executable: data lives beside the binary */
+  portable?: boolean;
 };
 
 export type WorkspaceSummary = {
@@ -9,6 +9,13 @@ export async function copyToClipboard(text: string | null | undefined): Promise<
   if (s.length === 0) return false;
 
   if (IS_TAURI) {
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      await invoke("copy_to_clipboard", { text: s });
+      return true;
+    } catch {
+      // 旧版桌面端没有该命令 — 回退到插件
+    }
     try {
       const { writeText } = await import("@tauri-apps/plugin-clipboard-manager");
       await writeText(s);
@@ -9,9 +9,12 @@
 import { Fragment, useEffect, useRef, useState } from "react";
 import { useTranslation } from "react-i18next";
 import { IconFolder, IconFile, IconClipboard, IconLightbulb, IconCheck } from "../icons";
-import { invoke, IS_TAURI, saveFileDialog } from "../platform";
+import { invoke, IS_TAURI, openFileDialog, revealPath, saveFileDialog } from "../platform";
+import { getLanguagePref, setLanguage } from "../i18n";
+import { getThemePref, setThemePref, type Theme } from "../theme";
 import { safeFetch } from "../providers";
 import { joinPath, envGet, envSet } from "../utils";
+import { copyToClipboard } from "../utils/clipboard";
 import { notifySuccess, notifyError, notifyLoading, dismissLoading } from "../utils/notify";
 import { FieldText, FieldBool, FieldSelect } from "../components/EnvFields";
 import { Section } from "../components/Section";
@@ -105,6 +108,12 @@ export function AdvancedView(props: AdvancedViewProps) {
   const [migrateBusy, setMigrateBusy] = useState(false);
   const [migrateCurrentRoot, setMigrateCurrentRoot] = useState("");
   const [migrateCustomRoot, setMigrateCustomRoot] = useState<string | null>(null);
+  // 配置档：各自独立的数据根目录（工作 / 个人），见 profiles.rs
+  const [profiles, setProfiles] = useState<Array<{ name: string; path: string; active: boolean; exists: boolean }>>([]);
+  const [profileTarget, setProfileTarget] = useState("");
+  const [newProfileName, setNewProfileName] = useState("");
+  const [newProfilePath, setNewProfilePath] = useState("");
+  const [profileBusy, setProfileBusy] = useState(false);
 
   // ── Auto-load on mount ──
   const loadedRef = useRef(false);
@@ -146,10 +155,52 @@ export function AdvancedView(props: AdvancedViewProps) {
           setMigrateCustomRoot(rootInfo.customRoot);
         })
         .catch(() => {});
+      loadProfiles();
     }
   // eslint-disable-next-line react-hooks/exhaustive-deps
   }, []);
 
+  function loadProfiles() {
+    invoke<typeof profiles>("list_profiles")
+      .then((list) => {
+        setProfiles(list);
+        setProfileTarget(list.find((p) => p.active)?.name ?? "");
+      })
+      .catch(() => {});
+  }
+
+  async function createProfile() {
+    setProfileBusy(true);
+    try {
+      await invoke("create_profile", { name: newProfileName.trim(), path: newProfilePath.trim() });
+      setNewProfileName("");
+      setNewProfilePath("");
+      loadProfiles();
+    } catch (e) {
+      notifyError(String(e));
+    } finally {
+      setProfileBusy(false);
+    }
+  }
+
+  async function switchProfile() {
+    setProfileBusy(true);
+    const toastId = notifyLoading(t("adv.profileSwitching"));
+    try {
+      const res = await invoke<{ profile: { name: string; path: string }; keptExternal: number[] }>(
+        "switch_profile", { name: profileTarget },
+      );
+      setMigrateCurrentRoot(res.profile.path);
+      notifySuccess(t("adv.profileSwitched", { name: res.profile.name }));
+      loadProfiles();
+    } catch (e) {
+      notifyError(String(e));
+    } finally {
+      dismissLoading(toastId);
+      setProfileBusy(false);
+    }
+  }
+
   // ── Async actions ──
 
   async function fetchSystemInfo() {
@@ -185,18 +236,43 @@ export function AdvancedView(props: AdvancedViewProps) {
   async function opsOpenFolder(p: string) {
     if (!p) return;
     try {
-      await invoke("show_item_in_folder", { path: p });
-    } catch {
-      try {
-        await invoke("open_file_with_default", { path: p });
-      } catch {
-        if (opsWsPath && opsWsPath !== p) {
-          try { await invoke("open_file_with_default", { path: opsWsPath }); } catch (e) { notifyError(String(e)); }
-        }
-      }
+      await revealPath(p);
+    } catch (e) {
+      notifyError(String(e));
     }
   }
 
+  async function exportAppSettings() {
+    const chosen = await saveFileDialog({
+      defaultPath: info?.homeDir ? joinPath(info.homeDir, "openakita-settings.json") : "openakita-settings.json",
+      filters: [{ name: "JSON", extensions: ["json"] }],
+    });
+    if (!chosen) return;
+    try {
+      const ui = { language: getLanguagePref(), theme: getThemePref() };
+      const dest = await invoke<string>("export_app_settings", { path: chosen, ui });
+      notifySuccess(t("adv.appSettingsExported", { path: dest }));
+    } catch (e) { notifyError(String(e)); }
+  }
+
+  async function importAppSettings() {
+    const chosen = await openFileDialog({ filters: [{ name: "JSON", extensions: ["json"] }] });
+    if (!chosen) return;
+    try {
+      const r = await invoke<{ applied: string[]; ui?: { language?: "auto" | "zh" | "en"; theme?: Theme } }>(
+        "import_app_settings", { path: chosen },
+      );
+      if (r.ui?.language) setLanguage(r.ui.language);
+      if (r.ui?.theme) setThemePref(r.ui.theme);
+      notifySuccess(t("adv.appSettingsImported", { count: r.applied.length }));
+    } catch (e) { notifyError(String(e)); }
+  }
+
+  async function opsCopyPath(p: string) {
+    if (!p) return;
+    if (await copyToClipboard(p)) notifySuccess(t("adv.opsPathCopied"));
+  }
+
   async function opsHandleBundleExport() {
     if (!currentWorkspaceId) return;
     let _b: string | number | undefined;
@@ -224,7 +300,7 @@ export function AdvancedView(props: AdvancedViewProps) {
         destPath: chosen,
       });
       notifySuccess(t("adv.opsLogExportSuccess", { path: dest }));
-      await invoke("show_item_in_folder", { path: dest });
+      await revealPath(dest);
     } catch (e) { notifyError(String(e)); } finally { if (_b !== undefined) dismissLoading(_b); }
   }
 
@@ -889,6 +965,56 @@ export function AdvancedView(props: AdvancedViewProps) {
             </div>
           </Section>
         )}
+
+        {IS_TAURI && profiles.length > 0 && (
+          <Section title={t("adv.profilesTitle")} subtitle={t("adv.profilesHint")} className="mt-2">
+            <div className="space-y-3">
+              <div className="flex gap-1.5 items-center">
+                <Select value={profileTarget} onValueChange={setProfileTarget}>
+                  <SelectTrigger size="sm" className="flex-1"><SelectValue /></SelectTrigger>
+                  <SelectContent>
+                    {profiles.map((p) => (
+                      <SelectItem key={p.name} value={p.name}>
+                        {p.name}{p.active ? ` (${t("adv.profileActive")})` : ""} — {p.path}
+                      </SelectItem>
+                    ))}
+                  </SelectContent>
+                </Select>
+                <Button
+                  size="sm"
+                  onClick={switchProfile}
+                  disabled={profileBusy || !profileTarget || !!profiles.find((p) => p.name === profileTarget)?.active}
+                >
+                  {t("adv.profileSwitch")}
+                </Button>
+              </div>
+              <div className="flex gap-1.5 items-center">
+                <Input
+                  value={newProfileName}
+                  onChange={(e) => setNewProfileName(e.target.value)}
+                  placeholder={t("adv.profileNamePlaceholder")}
+                  className="w-40"
+                  disabled={profileBusy}
+                />
+                <Input
+                  value={newProfilePath}
+                  onChange={(e) => setNewProfilePath(e.target.value)}
+                  placeholder={t("adv.profilePathPlaceholder")}
+                  className="flex-1"
+                  disabled={profileBusy}
+                />
+                <Button
+                  variant="outline"
+                  size="sm"
+                  onClick={createProfile}
+                  disabled={profileBusy || !newProfileName.trim() || !newProfilePath.trim()}
+                >
+                  {t("adv.profileCreate")}
+                </Button>
+              </div>
+            </div>
+          </Section>
+        )}
       </div>
 
       {/* ── Card 5: 外部扩展模块 ── */}
@@ -957,11 +1083,12 @@ export function AdvancedView(props: AdvancedViewProps) {
 
         {IS_TAURI && (
           <Section title={t("adv.opsPaths")} className="mt-2">
-            <div className="grid grid-cols-[auto_1fr_auto] gap-x-3 gap-y-1.5 items-center text-sm">
+            <div className="grid grid-cols-[auto_1fr_auto_auto] gap-x-3 gap-y-1.5 items-center text-sm">
               {opsPathRows.map((row) => (
                 <Fragment key={row.label}>
                   <span className="font-medium whitespace-nowrap">{row.label}</span>
                   <span className="break-all text-muted-foreground text-xs font-mono">{row.path || "—"}</span>
+                  <Button variant="outline" size="xs" onClick={() => opsCopyPath(row.path)} disabled={!row.path} title={t("adv.opsCopyPath")}><IconClipboard size={12} /></Button>
                   <Button variant="outline" size="xs" onClick={() => opsOpenFolder(row.path)} disabled={!row.path}>{t("adv.opsOpenFolder")}</Button>
                 </Fragment>
               ))}
@@ -969,6 +1096,15 @@ export function AdvancedView(props: AdvancedViewProps) {
           </Section>
         )}
 
+        {IS_TAURI && (
+          <Section title={t("adv.appSettingsTitle")} subtitle={t("adv.appSettingsDesc")} className="mt-2">
+            <div className="flex gap-2">
+              <Button variant="outline" size="sm" onClick={exportAppSettings}>{t("adv.appSettingsExport")}</Button>
+              <Button variant="outline" size="sm" onClick={importAppSettings}>{t("adv.appSettingsImport")}</Button>
+            </div>
+          </Section>
+        )}
+
         {IS_TAURI && (
           <Section title={t("adv.factoryResetTitle")} subtitle={t("adv.factoryResetSubtitle")} className="mt-2">
             <p className="text-xs text-muted-foreground mb-2">{t("adv.factoryResetDesc")}</p>
@@ -1,6 +1,6 @@
 import { Fragment, useMemo, useEffect, useRef, useState } from "react";
 import { useTranslation } from "react-i18next";
-import { invoke, IS_WEB, logger, openExternalUrl } from "../platform";
+import { invoke, invokeBridge, IS_WEB, logger, openExternalUrl } from "../platform";
 import {
   isLocalProvider, localProviderPlaceholderKey, friendlyFetchError,
   fetchModelsDirectly, safeFetch,
@@ -194,6 +194,13 @@ export function LLMView(props: LLMViewProps) {
   const propsRef = useRef(props);
   propsRef.current = props;
 
+  // 进行中的 bridge 模型查询；离开页面时一并中止，不让 Python 进程空跑
+  const bridgeCallsRef = useRef(new Set<AbortController>());
+  useEffect(() => {
+    const calls = bridgeCallsRef.current;
+    return () => calls.forEach((c) => c.abort());
+  }, []);
+
   // ── Utility constants & helpers ──
 
   const PROVIDER_APPLY_URLS: Record<string, string> = {
@@ -411,17 +418,26 @@ export function LLMView(props: LLMViewProps) {
         }
       }
     }
+    const abort = new AbortController();
+    bridgeCallsRef.current.add(abort);
     try {
-      const raw = await invoke<string>("openakita_list_models", {
-        venvDir,
-        apiType: params.apiType,
-        baseUrl: params.baseUrl,
-        providerSlug: params.providerSlug,
-        apiKey: params.apiKey,
-      });
+      const raw = await invokeBridge<string>(
+        "openakita_list_models",
+        {
+          venvDir,
+          apiType: params.apiType,
+          baseUrl: params.baseUrl,
+          providerSlug: params.providerSlug,
+          apiKey: params.apiKey,
+        },
+        abort.signal,
+      );
       return JSON.parse(raw) as ListedModel[];
     } catch (e) {
+      if (abort.signal.aborted) throw e;
       logger.warn("LLMView", "openakita_list_models via Python bridge failed, using direct fetch", { error: String(e) });
+    } finally {
+      bridgeCallsRef.current.delete(abort);
     }
     return fetchModelsDirectly(params);
   }
@@ -7,7 +7,7 @@ import {
 } from "../icons";
 import { safeFetch } from "../providers";
 import { encodePathSegment } from "../platform/apiUrl";
-import { IS_WEB, onWsEvent } from "../platform";
+import { invoke, IS_TAURI, IS_WEB, onWsEvent } from "../platform";
 import { ConfirmDialog } from "../components/ConfirmDialog";
 import { AgentIcon } from "../components/AgentIcon";
 import { cn } from "@/lib/utils";
@@ -364,6 +364,7 @@ export function SchedulerView({ serviceRunning, apiBaseUrl = "" }: { serviceRunn
   const [searchQuery, setSearchQuery] = useState("");
   const [confirmDialog, setConfirmDialog] = useState<{ message: string; onConfirm: () => void } | null>(null);
   const [expandedHistory, setExpandedHistory] = useState<Record<string, TaskExecution[]>>({});
+  const [timeIssues, setTimeIssues] = useState<{ code: string; severity: string; message: string }[]>([]);
   const expandedHistoryRef = React.useRef(expandedHistory);
   expandedHistoryRef.current = expandedHistory;
 
@@ -418,6 +419,16 @@ export function SchedulerView({ serviceRunning, apiBaseUrl = "" }: { serviceRunn
 
   useEffect(() => { fetchTasks(); fetchChannels(); fetchAgentProfiles(); }, [fetchTasks, fetchChannels, fetchAgentProfiles]);
 
+  // 时钟偏差 / 时区不一致会让任务在"奇怪的时间"触发，打开页面时查一次
+  useEffect(() => {
+    if (!IS_TAURI) return;
+    let cancelled = false;
+    invoke<{ issues: { code: string; severity: string; message: string }[] }>("get_time_diagnostics", {})
+      .then((d) => { if (!cancelled) setTimeIssues(d.issues.filter((i) => i.severity !== "info")); })
+      .catch(() => {});
+    return () => { cancelled = true; };
+  }, []);
+
   useEffect(() => {
     if (!serviceRunning) return;
     const interval = setInterval(() => fetchTasks(false), IS_WEB ? 60_000 : 10_000);
@@ -1064,6 +1075,18 @@ export function SchedulerView({ serviceRunning, apiBaseUrl = "" }: { serviceRunn
         </CardHeader>
       </Card>
 
+      {timeIssues.length > 0 && (
+        <Card className="gap-0 border-amber-500/40 bg-amber-500/10 py-0 shadow-sm">
+          <CardContent className="flex items-start gap-3 px-5 py-3">
+            <AlertTriangle size={16} className="mt-0.5 shrink-0 text-amber-600" />
+            <div className="space-y-1 text-xs text-amber-800 dark:text-amber-300">
+              <div className="text-sm font-semibold">{t("scheduler.timeIssuesTitle")}</div>
+              {timeIssues.map((i) => <div key={i.code}>{i.message}</div>)}
+            </div>
+          </CardContent>
+        </Card>
+      )}
+
       {/* Header: Tabs + Search + Actions */}
       <Card className="gap-0 border-border/80 py-0 shadow-sm">
         <CardContent className="p-4">
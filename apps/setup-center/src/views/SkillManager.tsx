@@ -1391,7 +1391,7 @@ export function SkillManager({
   //     （此前 `data = await res.json()` 不查 res.ok，500 错误体会被当成空列表）。
   //   - 完全没有可用数据源时（既无服务也非 Tauri 本地模式）才视为真·空。
   // 并发语义：用 skillsRequestId 做时序守卫，只有最新一次请求允许写状态。
-  const loadSkills = useCallback(async (): Promise<boolean> => {
+  const loadSkills = useCallback(async (opts?: { forceRefresh?: boolean }): Promise<boolean> => {
     const seq = ++skillsRequestId.current;
     const isLatest = () => seq === skillsRequestId.current;
     setLoading(true);
@@ -1429,7 +1429,11 @@ export function SkillManager({
       if (!data && IS_TAURI && dataMode !== "remote" && venvDir && currentWorkspaceId) {
         attempted = true;
         try {
-          const raw = await invoke<string>("openakita_list_skills", { venvDir, workspaceId: currentWorkspaceId });
+          const raw = await invoke<string>("openakita_list_skills", {
+            venvDir,
+            workspaceId: currentWorkspaceId,
+            forceRefresh: opts?.forceRefresh ?? false,
+          });
           const parsed = JSON.parse(raw);
           if (parsed && Array.isArray(parsed.skills)) {
             data = parsed;
@@ -2023,7 +2027,7 @@ export function SkillManager({
       }
 
       if (!installed && IS_TAURI && currentWorkspaceId) {
-        await invoke<string>("openakita_install_skill", {
+        await invoke("openakita_install_skill", {
           venvDir,
           workspaceId: currentWorkspaceId,
           url: folderPath,
@@ -2135,6 +2139,7 @@ export function SkillManager({
     const key = skill.skillId;
     setUninstallingSet(prev => new Set(prev).add(key));
     setError(null);
+    let undoId: string | undefined;
     try {
       if (serviceRunning && apiBaseUrl != null) {
         const res = await safeFetch(`${apiBaseUrl}/api/skills/uninstall`, {
@@ -2151,6 +2156,10 @@ export function SkillManager({
           workspaceId: currentWorkspaceId,
           skillName: key,
         });
+        undoId = (await invoke<{ id: string; kind: string; workspaceId: string; subject: string }[]>(
+          "list_undoable_actions",
+        ).catch(() => []))
+          .find(a => a.kind === "skillUninstall" && a.workspaceId === currentWorkspaceId && a.subject === key)?.id;
       } else {
         throw new Error(t("skills.envNotReady") || "环境未就绪");
       }
@@ -2161,7 +2170,21 @@ export function SkillManager({
         if (sid === key || s.url === skill.sourceUrl) return { ...s, installed: false };
         return s;
       }));
-      toast.success(t("skills.uninstallSuccess", { name: displayName }));
+      const undoAction = undoId;
+      toast.success(t("skills.uninstallSuccess", { name: displayName }), undoAction ? {
+        action: {
+          label: t("skills.uninstallUndo"),
+          onClick: async () => {
+            try {
+              await invoke("undo_action", { id: undoAction });
+              toast.success(t("skills.uninstallUndone", { name: displayName }));
+              await loadSkills();
+            } catch (e) {
+              toast.error(friendlyError(e, t, "uninstall"));
+            }
+          },
+        },
+      } : undefined);
       await loadSkills();
     } catch (e) {
       const msg = friendlyError(e, t, "uninstall");
@@ -2229,8 +2252,11 @@ export function SkillManager({
       } else {
         if (IS_TAURI) {
           try {
-            const raw = await invoke<string>("http_get_json", { url });
-            data = JSON.parse(raw);
+            const res = await invoke<{ status: number; body: string }>("http_get_json", {
+              url,
+              options: { timeoutMs: 15000, retries: 1 },
+            });
+            if (res.status >= 200 && res.status < 300) data = JSON.parse(res.body);
           } catch { /* Tauri invoke 失败，继续 fallback */ }
         }
 
@@ -2312,7 +2338,7 @@ export function SkillManager({
 
       // 方式2：服务未运行 → Tauri invoke（本地模式）
       if (!installed && IS_TAURI && dataMode !== "remote" && currentWorkspaceId) {
-        await invoke<string>("openakita_install_skill", {
+        await invoke("openakita_install_skill", {
           venvDir,
           workspaceId: currentWorkspaceId,
           url: skill.url,
@@ -2383,7 +2409,7 @@ export function SkillManager({
       }
 
       if (!installed && IS_TAURI && dataMode !== "remote" && currentWorkspaceId) {
-        await invoke<string>("openakita_install_skill", {
+        await invoke("openakita_install_skill", {
           venvDir,
           workspaceId: currentWorkspaceId,
           url,
@@ -2476,7 +2502,8 @@ export function SkillManager({
                 if (data.error) { setError(friendlyError(data.error, t, "reload")); return; }
                 skippedCount = Number(data.skipped_count || 0);
               }
-              const ok = await loadSkills();
+              // 手动刷新绕过 bridge 结果缓存
+              const ok = await loadSkills({ forceRefresh: true });
               if (ok) {
                 if (skippedCount > 0) {
                   toast.warning(t("skills.refreshedPartial", { count: skippedCount }));
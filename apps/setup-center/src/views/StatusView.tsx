@@ -1,9 +1,10 @@
 import { useEffect, useState } from "react";
 import { useTranslation } from "react-i18next";
-import { invoke, IS_TAURI, logger } from "../platform";
+import { invoke, IS_TAURI, logger, toCommandError } from "../platform";
 import { safeFetch } from "../providers";
 import { envGet } from "../utils";
 import { notifyLoading, notifyError, notifySuccess, dismissLoading } from "../utils/notify";
+import { toast } from "sonner";
 import { copyToClipboard } from "../utils/clipboard";
 import {
   DotGreen, DotGray, DotYellow,
@@ -126,6 +127,19 @@ export function StatusView(props: StatusViewProps) {
     runtimeRoot: string;
     manifestPath: string;
   } | null>(null);
+  // 启动自检（目录写权限 / 受控文件夹访问）发现的问题，见 get_startup_issues
+  const [startupIssues, setStartupIssues] = useState<{
+    code: string;
+    severity: string;
+    path?: string;
+    message: string;
+    hint: string;
+  }[]>([]);
+  // Linux 上没有托盘（GNOME 未装 AppIndicator 扩展）时，关闭窗口会直接退出
+  const [trayUnavailable, setTrayUnavailable] = useState<string | null>(null);
+  // WSL：在 WSL 里运行本程序时看不到 Windows 侧后端；Windows 上可把工作区切到 WSL 发行版里跑
+  const [wslInfo, setWslInfo] = useState<{ insideWsl: boolean; available: boolean; distros: string[] } | null>(null);
+  const [wslDistro, setWslDistro] = useState<string>("");
   const [logLevelFilter, setLogLevelFilter] = useState<Set<string>>(new Set(["INFO", "WARN", "ERROR", "DEBUG"]));
   const [logAtBottom, setLogAtBottom] = useState(true);
   // Local guard for the "Start backend" button. The parent App.tsx exposes a
@@ -181,6 +195,34 @@ export function StatusView(props: StatusViewProps) {
     };
   }, [serviceStatus?.running, backendBootPhase]);
 
+  useEffect(() => {
+    if (!IS_TAURI) return;
+    let cancelled = false;
+    invoke<typeof startupIssues>("get_startup_issues")
+      .then((issues) => { if (!cancelled) setStartupIssues(issues); })
+      .catch((e) => logger.warn("get_startup_issues failed", String(e)));
+    invoke<{ available: boolean; detail: string }>("get_tray_capability")
+      .then((cap) => { if (!cancelled && !cap.available) setTrayUnavailable(cap.detail); })
+      .catch((e) => logger.warn("get_tray_capability failed", String(e)));
+    invoke<NonNullable<typeof wslInfo>>("get_wsl_info")
+      .then((info) => { if (!cancelled) setWslInfo(info); })
+      .catch((e) => logger.warn("get_wsl_info failed", String(e)));
+    return () => {
+      cancelled = true;
+    };
+  }, []);
+
+  useEffect(() => {
+    if (!IS_TAURI || !wslInfo?.available || !effectiveWsId) return;
+    let cancelled = false;
+    invoke<{ distro: string } | null>("get_wsl_workspace", { workspaceId: effectiveWsId })
+      .then((cfg) => { if (!cancelled) setWslDistro(cfg?.distro ?? ""); })
+      .catch((e) => logger.warn("get_wsl_workspace failed", String(e)));
+    return () => {
+      cancelled = true;
+    };
+  }, [wslInfo?.available, effectiveWsId]);
+
   useEffect(() => {
     if (!serviceStatus?.running) {
       setMemorySubsystem(null);
@@ -319,6 +361,72 @@ export function StatusView(props: StatusViewProps) {
           t={t}
         />
       )}
+      {/* Banner: 启动自检发现的目录写权限问题——不等到启动服务时报 "write pid file" */}
+      {IS_TAURI && startupIssues.length > 0 && (
+        <Card className="gap-0 border-rose-500/40 bg-rose-500/10 py-0 shadow-sm">
+          <CardContent className="px-5 py-4">
+            <div className="mb-2 text-sm font-semibold text-rose-700 dark:text-rose-400">
+              {t("status.startupIssuesTitle")}
+            </div>
+            {startupIssues.map((issue) => (
+              <div key={`${issue.code}:${issue.path ?? ""}`} className="mb-2 text-xs text-rose-700/80 dark:text-rose-400/80">
+                <div>{issue.message}</div>
+                <div className="text-[11px] opacity-80">{issue.hint}</div>
+              </div>
+            ))}
+            <Button
+              size="sm"
+              variant="outline"
+              onClick={async () => {
+                try {
+                  setStartupIssues(await invoke<typeof startupIssues>("get_startup_issues", { refresh: true }));
+                } catch (e) {
+                  notifyError(String(e));
+                }
+              }}
+            >
+              <RotateCcw size={14} className="mr-1" />
+              {t("status.startupIssuesRecheck")}
+            </Button>
+          </CardContent>
+        </Card>
+      )}
+      {IS_TAURI && trayUnavailable && (
+        <div className="statusPanelAlert" title={trayUnavailable}>
+          <IconAlertCircle size={13} />
+          <span>{t("status.trayUnavailable")}</span>
+        </div>
+      )}
+      {IS_TAURI && wslInfo?.insideWsl && (
+        <div className="statusPanelAlert">
+          <IconAlertCircle size={13} />
+          <span>{t("status.insideWsl")}</span>
+        </div>
+      )}
+      {IS_TAURI && wslInfo?.available && effectiveWsId && (
+        <div className="statusPanelAlert" title={t("status.wslWorkspaceHint")}>
+          <span>{t("status.wslWorkspace")}</span>
+          <select
+            value={wslDistro}
+            disabled={!!serviceStatus?.running}
+            onChange={async (e) => {
+              const distro = e.target.value;
+              try {
+                const cfg = await invoke<{ distro: string } | null>("set_wsl_workspace", {
+                  workspaceId: effectiveWsId,
+                  config: distro ? { distro, python: "python3" } : null,
+                });
+                setWslDistro(cfg?.distro ?? "");
+              } catch (err) {
+                notifyError(String(err));
+              }
+            }}
+          >
+            <option value="">{t("status.wslNative")}</option>
+            {wslInfo.distros.map((d) => <option key={d} value={d}>{d}</option>)}
+          </select>
+        </div>
+      )}
       {/* Banner: RUNTIME_PERMISSION_DENIED — 企业 AD / 杀软"勒索软件防护"拦截
           runtime 目录创建时，给用户一条可操作的指引。在"未启动" banner 前
           展示，因为这是导致"未启动"的根因，应优先看到。
@@ -366,6 +474,26 @@ export function StatusView(props: StatusViewProps) {
               <FolderOpen size={14} className="mr-1" />
               {t("status.runtimePermissionDeniedOpen")}
             </Button>
+            {/* 目录被管理员/安装器占有时，提权把所有权交还当前用户；
+                run_elevated 会先弹原生确认框说明要执行的命令 */}
+            <Button
+              size="sm"
+              variant="outline"
+              onClick={async () => {
+                try {
+                  await invoke("run_elevated", {
+                    operation: { kind: "repairPermissions", path: runtimeLastError.runtimeRoot },
+                  });
+                  notifySuccess(t("status.runtimePermissionRepaired"));
+                } catch (e) {
+                  const err = toCommandError(e);
+                  if (err.code !== "ELEVATION_DECLINED") notifyError(err.message);
+                }
+              }}
+            >
+              <ShieldAlert size={14} className="mr-1" />
+              {t("status.runtimePermissionRepair")}
+            </Button>
           </CardContent>
         </Card>
       )}
@@ -501,9 +629,31 @@ export function StatusView(props: StatusViewProps) {
             <Button size="sm" variant="destructive" style={{ marginLeft: "auto" }} onClick={async () => {
               const _b = notifyLoading(t("statusExtra.stoppingAll"));
               try {
-                const stopped = await invoke<number[]>("openakita_stop_all_processes");
+                type StopAllResult = { stopped: number[]; skippedExternal: { workspaceId: string; pid: number }[] };
+                const res = await invoke<StopAllResult>("openakita_stop_all_processes");
                 setDetectedProcesses([]);
-                notifySuccess(t("statusExtra.stoppedCount", { count: stopped.length }));
+                notifySuccess(t("statusExtra.stoppedCount", { count: res.stopped.length }));
+                if (res.skippedExternal.length > 0) {
+                  // CLI 手动启动的后端默认保留；用户再点一次才一并停止
+                  toast.warning(t("statusExtra.externalKept", {
+                    pids: res.skippedExternal.map(p => `PID ${p.pid} (${p.workspaceId})`).join(", "),
+                  }), {
+                    duration: 15000,
+                    action: {
+                      label: t("statusExtra.stopExternalToo"),
+                      onClick: async () => {
+                        try {
+                          const more = await invoke<StopAllResult>("openakita_stop_all_processes", {
+                            includeExternal: true,
+                            confirmExternal: true,
+                          });
+                          notifySuccess(t("statusExtra.stoppedCount", { count: more.stopped.length }));
+                          await refreshStatus();
+                        } catch (e) { notifyError(String(e)); }
+                      },
+                    },
+                  });
+                }
                 await refreshStatus();
               } catch (e) { notifyError(String(e)); } finally { dismissLoading(_b); }
             }} disabled={!!busy}><Square size={12} className="mr-1" />{t("statusExtra.stopAll")}</Button>
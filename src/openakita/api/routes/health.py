@@ -809,6 +809,40 @@ async def orchestrator_state(request: Request):
     }
 
 
+def _format_thread_stacks() -> tuple[int, str]:
+    """Current stack of every Python thread, innermost call last."""
+    import sys
+    import threading
+    import traceback
+
+    threads = {t.ident: t for t in threading.enumerate()}
+    frames = sys._current_frames()
+    blocks = [
+        f"pid={os.getpid()} time={time.strftime('%Y-%m-%dT%H:%M:%S')} threads={len(frames)}"
+    ]
+    for ident, frame in frames.items():
+        t = threads.get(ident)
+        name = t.name if t else "?"
+        daemon = ", daemon" if t is not None and t.daemon else ""
+        stack = "".join(traceback.format_stack(frame))
+        blocks.append(f"--- thread {name} (ident={ident}{daemon}) ---\n{stack}")
+    return len(frames), "\n".join(blocks)
+
+
+@router.post("/api/debug/thread-dump")
+def thread_dump():
+    """Diagnostic: stacks of all threads.
+
+    The desktop asks for this when the backend answers health checks but
+    has written no log output for a long time.  Deliberately not logged:
+    a log line would look like the backend had woken up.  A plain ``def``
+    so it runs on the threadpool and still answers while the event loop is
+    busy with a long coroutine.
+    """
+    count, dump = _format_thread_stacks()
+    return {"threads": count, "dump": dump}
+
+
 @router.get("/api/diagnostics")
 async def diagnostics():
     """Self-check: the backend reports its own runtime health.
@@ -1817,6 +1817,83 @@ def create_app(
     return app
 
 
+def _uds_local_app(app: Any) -> Any:
+    """Mark UDS connections as loopback so local-only routes keep working.
+
+    Unix socket peers have no ``client`` address; the socket lives in a
+    0700 directory owned by the current user, so it is at least as trusted
+    as a 127.0.0.1 connection.
+    """
+
+    async def _wrapped(scope: dict, receive: Any, send: Any) -> None:
+        if scope.get("type") in ("http", "websocket") and not scope.get("client"):
+            scope = dict(scope)
+            scope["client"] = ("127.0.0.1", 0)
+        await app(scope, receive, send)
+
+    return _wrapped
+
+
+def _build_uds_server(app: Any, uvicorn_kwargs: dict[str, Any]) -> Any:
+    """Build the optional Unix-socket uvicorn server from ``OPENAKITA_API_UDS``."""
+    uds_path = os.environ.get("OPENAKITA_API_UDS", "").strip()
+    if not uds_path or not hasattr(socket, "AF_UNIX") or os.name == "nt":
+        return None
+
+    import uvicorn
+
+    kwargs = {k: v for k, v in uvicorn_kwargs.items() if k not in ("host", "port")}
+    kwargs["app"] = _uds_local_app(app)
+    kwargs["uds"] = uds_path
+    # lifespan 由 TCP server 负责，这里只做请求转发
+    kwargs["lifespan"] = "off"
+    try:
+        Path(uds_path).parent.mkdir(parents=True, exist_ok=True)
+        return uvicorn.Server(uvicorn.Config(**kwargs))
+    except Exception as exc:
+        logger.warning(f"UDS control channel disabled ({uds_path}): {exc}")
+        return None
+
+
+async def _serve_with_uds(server: Any, uds_server: Any) -> None:
+    """Run the TCP server and, if configured, the UDS server on the same loop.
+
+    The UDS listener is best-effort: if it fails the TCP server keeps running
+    and Setup Center falls back to TCP. It is stopped when the TCP server exits.
+    """
+    if uds_server is None:
+        await server.serve()
+        return
+
+    async def _run_uds() -> None:
+        try:
+            await uds_server.serve()
+        except Exception as exc:
+            logger.warning(f"UDS control channel stopped: {exc}")
+
+    uds_task = asyncio.create_task(_run_uds())
+
+    async def _restrict_perms() -> None:
+        # uvicorn 新建 socket 时会 chmod 0666，收紧为仅当前用户可访问
+        for _ in range(50):
+            if uds_server.started:
+                with contextlib.suppress(OSError):
+                    os.chmod(uds_server.config.uds, 0o600)
+                return
+            await asyncio.sleep(0.1)
+
+    perms_task = asyncio.create_task(_restrict_perms())
+    try:
+        await server.serve()
+    finally:
+        uds_server.should_exit = True
+        perms_task.cancel()
+        with contextlib.suppress(Exception):
+            await asyncio.wait_for(uds_task, timeout=5.0)
+        with contextlib.suppress(OSError):
+            os.unlink(uds_server.config.uds)
+
+
 async def _wait_for_uvicorn_started(
     server: Any,
     api_thread: Any,
@@ -1927,6 +2004,11 @@ async def start_api_server(
     config = uvicorn.Config(**uvicorn_kwargs)
     server = uvicorn.Server(config)
 
+    # Setup Center 通过 OPENAKITA_API_UDS 传入 Unix socket 路径时，额外监听一个
+    # UDS 控制通道（health / shutdown / 代理调用走这里，不受代理和端口占用影响）。
+    # TCP 监听保持不变，Web UI 和外部客户端仍然走 host:port。
+    uds_server = _build_uds_server(app, uvicorn_kwargs)
+
     # ── Launch uvicorn in a background thread ────────────────────────
     api_loop_holder: list[asyncio.AbstractEventLoop] = []
     thread_ready = threading.Event()
@@ -1938,7 +2020,7 @@ async def start_api_server(
             asyncio.set_event_loop(loop)
             api_loop_holder.append(loop)
             thread_ready.set()
-            loop.run_until_complete(server.serve())
+            loop.run_until_complete(_serve_with_uds(server, uds_server))
         except Exception as exc:
             thread_error.append(exc)
         finally:
@@ -21,7 +21,7 @@ from pathlib import Path
 
 from openakita.agent.errors import UserCancelledError
 
-from .config import get_default_config_path, load_endpoints_config
+from .config import get_default_config_path, load_endpoints_config, safe_mode
 from .normalize import normalize_messages_for_api
 from .providers.anthropic import AnthropicProvider
 from .providers.base import LLMProvider
@@ -221,6 +221,14 @@ class ModelInfo:
     note: str = ""  # 备注
 
 
+def _runtime_endpoints(endpoints: list[EndpointConfig]) -> list[EndpointConfig]:
+    """安全模式只用优先级最高的一个端点（配置文件本身不动）。"""
+    if safe_mode() and len(endpoints) > 1:
+        logger.info("Safe mode: using only endpoint '%s'", endpoints[0].name)
+        return endpoints[:1]
+    return endpoints
+
+
 class LLMClient:
     """统一 LLM 客户端"""
 
@@ -340,6 +348,7 @@ class LLMClient:
         elif config_path or get_default_config_path().exists():
             self._config_path = config_path or get_default_config_path()
             self._endpoints, _, _, self._settings = load_endpoints_config(self._config_path)
+            self._endpoints = _runtime_endpoints(self._endpoints)
 
         # 创建 Provider 实例
         self._init_providers()
@@ -366,7 +375,7 @@ class LLMClient:
             return False
         try:
             new_endpoints, _, _, new_settings = load_endpoints_config(self._config_path)
-            self._endpoints = new_endpoints
+            self._endpoints = _runtime_endpoints(new_endpoints)
             self._settings = new_settings
             self._providers.clear()
             self._init_providers()
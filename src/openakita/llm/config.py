@@ -8,6 +8,7 @@ import json
 import locale
 import logging
 import os
+import re
 from pathlib import Path
 
 from dotenv import load_dotenv
@@ -97,7 +98,50 @@ def read_workspace_env_values(config_path: Path | None = None) -> dict[str, str]
     return _parse_env_content(_read_text_robust(env_path))
 
 
+_ENV_PROFILE_RE = re.compile(r"^[A-Za-z0-9_-]{1,32}$")
+
+
+def _env_layers(env_path: Path) -> list[Path]:
+    """``.env`` followed by its overlays, in the order they are applied.
+
+    The desktop passes the workspace's env profile in ``OPENAKITA_ENV_PROFILE``:
+    ``.env`` ← ``.env.<profile>`` ← ``.env.local``, later files winning.
+    """
+    layers = [env_path]
+    if env_path.name != ".env":
+        return layers
+    profile = os.environ.get("OPENAKITA_ENV_PROFILE", "").strip()
+    if profile and _ENV_PROFILE_RE.match(profile) and profile.lower() != "local":
+        layers.append(env_path.with_name(f".env.{profile}"))
+    layers.append(env_path.with_name(".env.local"))
+    return layers
+
+
+def safe_mode() -> bool:
+    """Started by the desktop's "safe start" (``OPENAKITA_SAFE_MODE=1``).
+
+    A minimal backend for repairing a config that crash-loops: no MCP, no
+    external skills, only the first endpoint, debug logging.
+    """
+    return os.environ.get("OPENAKITA_SAFE_MODE", "").strip() == "1"
+
+
 def _safe_load_dotenv(env_path: Path) -> None:
+    """Load a .env file and its overlays (see ``_env_layers``)."""
+    for layer in _env_layers(env_path):
+        if layer == env_path or layer.exists():
+            _load_dotenv_file(layer)
+    # 桌面端滚动重启让新实例先在备用端口预热，.env 里的 API_PORT 不能把它改回去
+    pinned_port = os.environ.get("OPENAKITA_API_PORT_PIN", "").strip()
+    if pinned_port:
+        os.environ["API_PORT"] = pinned_port
+    # 安全模式同理：.env 里的 MCP / 日志设置不能把它盖回去
+    if safe_mode():
+        os.environ["MCP_ENABLED"] = "false"
+        os.environ["LOG_LEVEL"] = "DEBUG"
+
+
+def _load_dotenv_file(env_path: Path) -> None:
     """Load a .env file with BOM handling, encoding fallback, and override.
 
     - Strips UTF-8 BOM before loading (Windows Notepad compatibility).
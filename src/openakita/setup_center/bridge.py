@@ -6,6 +6,7 @@ Setup Center Bridge
 - `python -m openakita.setup_center.bridge list-providers`
 - `python -m openakita.setup_center.bridge list-models --api-type ... --base-url ... [--provider-slug ...]`
 - `python -m openakita.setup_center.bridge list-skills --workspace-dir ...`
+- `python -m openakita.setup_center.bridge --server`（常驻模式，见 `serve()`）
 
 输出均为 JSON（stdout），错误输出到 stderr 并以非 0 退出码返回。
 """
@@ -21,7 +22,9 @@ import json
 import os
 import re
 import sys
+import threading
 import time
+import traceback
 import zipfile
 from dataclasses import asdict, is_dataclass
 from pathlib import Path
@@ -43,6 +46,55 @@ def _json_print(obj: Any) -> None:
     sys.stdout.write("\n")
 
 
+_STDIN_SECRETS: dict[str, Any] | None = None
+
+# --server 模式下每个请求在自己的线程里执行：stdout / stderr / secrets 都按线程隔离
+_REQUEST_LOCAL = threading.local()
+
+
+def _stdin_secrets() -> dict[str, Any]:
+    """读取 Setup Center 经 stdin 传入的密钥（JSON 对象，只读一次）。
+
+    密钥不再放在命令行参数或环境变量里（其他本地进程可通过 ps /
+    /proc/<pid>/environ 看到）。仅当 ``OPENAKITA_BRIDGE_SECRETS_STDIN=1``
+    时才读 stdin，否则返回空字典。
+    """
+    server_secrets = getattr(_REQUEST_LOCAL, "secrets", None)
+    if server_secrets is not None:
+        return server_secrets
+    global _STDIN_SECRETS
+    if _STDIN_SECRETS is None:
+        _STDIN_SECRETS = {}
+        if os.environ.get("OPENAKITA_BRIDGE_SECRETS_STDIN") == "1":
+            raw = sys.stdin.read()
+            data = json.loads(raw) if raw.strip() else {}
+            if not isinstance(data, dict):
+                raise ValueError("stdin secrets must be a JSON object")
+            _STDIN_SECRETS = data
+    return _STDIN_SECRETS
+
+
+def _warn(message: str) -> None:
+    """非致命提示：写 stderr，信封模式下同时进入应答的 ``warnings``。"""
+    sys.stderr.write(message + "\n")
+    collected = getattr(_REQUEST_LOCAL, "warnings", None)
+    if collected is not None:
+        collected.append(message)
+
+
+# install-skill --stream 时置为 True：各阶段输出 ``@@skill-install <json>`` 进度行
+_SKILL_INSTALL_STREAM = False
+
+
+def _skill_progress(stage: str, **info: Any) -> None:
+    if not _SKILL_INSTALL_STREAM:
+        return
+    sys.stdout.write(
+        "@@skill-install " + json.dumps({"stage": stage, **info}, ensure_ascii=False) + "\n"
+    )
+    sys.stdout.flush()
+
+
 class SkillInstallError(RuntimeError):
     """Structured install failure for Setup Center API responses."""
 
@@ -536,8 +588,23 @@ async def list_models(
     raise ValueError(f"不支持的 api-type: {api_type}")
 
 
-async def health_check_endpoint(workspace_dir: str, endpoint_name: str | None) -> None:
-    """检测 LLM 端点连通性，同时更新业务状态（cooldown/mark_healthy）"""
+async def health_check_endpoint(
+    workspace_dir: str,
+    endpoint_name: str | None,
+    *,
+    parallel: int = 1,
+    timeout: float = 0.0,
+    stream: bool = False,
+) -> None:
+    """检测 LLM 端点连通性，同时更新业务状态（cooldown/mark_healthy）
+
+    Args:
+        parallel: 最大并发检测数（>=1）
+        timeout: 单个端点超时秒数，0 表示不限
+        stream: 先输出 ``@@endpoint-health-plan {"total": n}`` 供 Setup Center 按端点数
+            计算整体时限，再每完成一个端点输出一行 ``@@endpoint-health <json>`` 进度，
+            供逐条刷新；最后一行仍是完整结果数组
+    """
     import time
 
     from openakita.llm.client import LLMClient
@@ -562,23 +629,30 @@ async def health_check_endpoint(workspace_dir: str, endpoint_name: str | None) -
 
     client = LLMClient(config_path=config_path)
 
-    results = []
     targets = list(client._providers.items())
     if endpoint_name:
         targets = [(n, p) for n, p in targets if n == endpoint_name]
         if not targets:
             raise ValueError(f"未找到端点: {endpoint_name}")
 
-    for name, provider in targets:
-        t0 = time.time()
-        try:
-            await provider.health_check()
-            latency = round((time.time() - t0) * 1000)
-            results.append(
-                {
+    if stream:
+        sys.stdout.write("@@endpoint-health-plan " + json.dumps({"total": len(targets)}) + "\n")
+        sys.stdout.flush()
+
+    sem = asyncio.Semaphore(max(1, parallel))
+
+    async def _check(name: str, provider: Any) -> dict:
+        async with sem:
+            t0 = time.time()
+            try:
+                if timeout > 0:
+                    await asyncio.wait_for(provider.health_check(), timeout=timeout)
+                else:
+                    await provider.health_check()
+                result = {
                     "name": name,
                     "status": "healthy",
-                    "latency_ms": latency,
+                    "latency_ms": round((time.time() - t0) * 1000),
                     "error": None,
                     "error_category": None,
                     "consecutive_failures": 0,
@@ -586,22 +660,25 @@ async def health_check_endpoint(workspace_dir: str, endpoint_name: str | None) -
                     "is_extended_cooldown": False,
                     "last_checked_at": time.strftime("%Y-%m-%dT%H:%M:%S"),
                 }
-            )
-        except Exception as e:
-            latency = round((time.time() - t0) * 1000)
-            results.append(
-                {
+            except Exception as e:
+                timed_out = isinstance(e, asyncio.TimeoutError)
+                result = {
                     "name": name,
                     "status": "unhealthy" if provider.consecutive_cooldowns >= 3 else "degraded",
-                    "latency_ms": latency,
-                    "error": str(e)[:500],
-                    "error_category": provider.error_category,
+                    "latency_ms": round((time.time() - t0) * 1000),
+                    "error": (f"健康检查超时（{timeout:g}s）" if timed_out else str(e)[:500]),
+                    "error_category": "timeout" if timed_out else provider.error_category,
                     "consecutive_failures": provider.consecutive_cooldowns,
                     "cooldown_remaining": round(provider.cooldown_remaining),
                     "is_extended_cooldown": provider.is_extended_cooldown,
                     "last_checked_at": time.strftime("%Y-%m-%dT%H:%M:%S"),
                 }
-            )
+        if stream:
+            sys.stdout.write("@@endpoint-health " + json.dumps(result, ensure_ascii=False) + "\n")
+            sys.stdout.flush()
+        return result
+
+    results = list(await asyncio.gather(*(_check(n, p) for n, p in targets)))
 
     _json_print(results)
 
@@ -1094,6 +1171,17 @@ _GITHUB_ZIP_MIRRORS: list[str] = [
 ]
 
 
+def _github_zip_mirror_templates() -> list[str]:
+    """ZIP 下载模板；Setup Center 配置了 GitHub 代理时优先尝试该代理。"""
+    proxy = os.environ.get("OPENAKITA_GITHUB_PROXY", "").strip()
+    if not proxy:
+        return list(_GITHUB_ZIP_MIRRORS)
+    if not proxy.endswith("/"):
+        proxy += "/"
+    custom = proxy + _GITHUB_ZIP_MIRRORS[0]
+    return [custom] + [tpl for tpl in _GITHUB_ZIP_MIRRORS if tpl != custom]
+
+
 def _try_platform_skill_download(skill_id: str, dest_dir: Path) -> bool:
     """Try downloading a cached skill ZIP from the OpenAkita platform.
 
@@ -1158,7 +1246,7 @@ def _download_github_zip(repo_owner: str, repo_name: str, dest_dir: Path) -> Non
     for branch in ("main", "master"):
         if data is not None:
             break
-        for tpl in _GITHUB_ZIP_MIRRORS:
+        for tpl in _github_zip_mirror_templates():
             url = tpl.format(owner=repo_owner, repo=repo_name, branch=branch)
             try:
                 req = urllib.request.Request(url, headers={"User-Agent": "OpenAkita"})
@@ -1405,9 +1493,7 @@ def _cleanup_broken_skill_dir(d: Path) -> None:
                     "请关闭相关程序后重试，或手动删除该目录。"
                 ),
             ) from quarantine_err
-        sys.stderr.write(
-            f"[install_skill] 残留目录无法直接删除，已隔离到 {quarantined}: {remove_err}\n"
-        )
+        _warn(f"[install_skill] 残留目录无法直接删除，已隔离到 {quarantined}: {remove_err}")
 
 
 def _ensure_target_available(target: Path, url: str) -> None:
@@ -1432,6 +1518,8 @@ def _copy_skill_tree(source_dir: Path, target: Path) -> None:
     """Copy a validated skill tree to the final directory without Git metadata."""
     import shutil
 
+    _skill_progress("copy", target=str(target))
+
     shutil.copytree(
         str(source_dir),
         str(target),
@@ -1454,8 +1542,12 @@ def _install_repo_tree_to_target(
     tmp_dir = tmp_parent / "repo"
     try:
         if _has_git():
-            _git_clone(["git", "clone", "--depth", "1", repo_url, str(tmp_dir)])
+            _skill_progress("download", method="git", repo=repo_url)
+            auth = _registry_auth_headers(repo_url).get("Authorization")
+            extra = ["-c", f"http.extraHeader=Authorization: {auth}"] if auth else []
+            _git_clone(["git", *extra, "clone", "--depth", "1", repo_url, str(tmp_dir)])
         elif zip_downloader is not None:
+            _skill_progress("download", method="zip", repo=repo_url)
             zip_downloader(tmp_dir)
         else:
             raise FileNotFoundError("未找到 git 命令。请安装 Git (https://git-scm.com) 后重试")
@@ -1554,9 +1646,15 @@ def install_skill(workspace_dir: str, url: str, *, category: str | None = None)
         category: 可选大类。命中且通过校验时，安装到 ``skills/<category>/<skill_id>/``；
             否则维持旧行为安装到 ``skills/<skill_id>/``（顶层平铺）。
     """
-    url = _extract_skill_signal(url)
+    # 已存在的本地目录原样使用：路径里的空格会被命令前缀清洗误拆
+    raw_path = (url or "").strip()
+    if not (raw_path and Path(raw_path).expanduser().is_dir()):
+        url = _extract_skill_signal(url)
+    else:
+        url = raw_path
     if not url:
         raise ValueError("请输入有效的技能地址，如 owner/repo 或 Git URL")
+    _skill_progress("resolve", source=url)
 
     root_skills_dir = _resolve_skills_dir(workspace_dir)
     root_skills_dir.mkdir(parents=True, exist_ok=True)
@@ -1572,9 +1670,9 @@ def install_skill(workspace_dir: str, url: str, *, category: str | None = None)
                 skills_dir = root_skills_dir / category
                 skills_dir.mkdir(parents=True, exist_ok=True)
             else:
-                sys.stderr.write(f"[install_skill] 分类名 {category!r} 非法，已忽略并安装到顶层\n")
+                _warn(f"[install_skill] 分类名 {category!r} 非法，已忽略并安装到顶层")
         except Exception as ce:
-            sys.stderr.write(f"[install_skill] 分类校验异常 {category!r}: {ce}，已安装到顶层\n")
+            _warn(f"[install_skill] 分类校验异常 {category!r}: {ce}，已安装到顶层")
             skills_dir = root_skills_dir
 
     if url.startswith("github:"):
@@ -1659,6 +1757,7 @@ def install_skill(workspace_dir: str, url: str, *, category: str | None = None)
 
         # Strategy 1: Try platform cache first
         platform_skill_id = f"{owner}-{repo}-{skill_name}".lower().replace("/", "-")
+        _skill_progress("download", method="platform-cache", target=str(target))
         if _try_platform_skill_download(platform_skill_id, target):
             try:
                 origin_file = target / ".openakita-source"
@@ -1676,8 +1775,10 @@ def install_skill(workspace_dir: str, url: str, *, category: str | None = None)
         try:
             if _has_git():
                 repo_url = f"https://github.com/{repo_part}.git"
+                _skill_progress("download", method="git", repo=repo_url)
                 _git_clone(["git", "clone", "--depth", "1", repo_url, str(tmp_dir)])
             else:
+                _skill_progress("download", method="zip", repo=f"{owner}/{repo}")
                 _download_github_zip(owner, repo, tmp_dir)
 
             # 支持 skillId 为子路径（如 "skills/web-search"）
@@ -1760,10 +1861,138 @@ def uninstall_skill(workspace_dir: str, skill_name: str) -> None:
     _json_print({"status": "ok", "removed": skill_name})
 
 
+_REGISTRY_KEYRING_SERVICE = "openakita-skill-registry"
+_REGISTRY_ARTIFACT_MAX_BYTES = 512 * 1024 * 1024
+
+
+def _configured_registries() -> list[dict]:
+    """Setup Center 传入的私有技能注册表（``OPENAKITA_SKILL_REGISTRIES``，JSON 列表）。"""
+    raw = os.environ.get("OPENAKITA_SKILL_REGISTRIES", "").strip()
+    if not raw:
+        return []
+    try:
+        data = json.loads(raw)
+    except ValueError:
+        return []
+    if not isinstance(data, list):
+        return []
+    return [
+        reg
+        for reg in data
+        if isinstance(reg, dict) and isinstance(reg.get("url"), str) and reg["url"].strip()
+    ]
+
+
+def _registry_token(token_ref: str) -> str | None:
+    """从系统钥匙串读取注册表令牌，不可用时返回 None。"""
+    try:
+        import keyring
+
+        return keyring.get_password(_REGISTRY_KEYRING_SERVICE, token_ref) or None
+    except Exception as e:
+        _warn(f"[registry] keyring lookup for {token_ref} failed: {e}")
+        return None
+
+
+def _url_origin(url: str) -> tuple[str, str]:
+    from urllib.parse import urlsplit
+
+    parts = urlsplit(url.strip())
+    return parts.scheme.lower(), parts.netloc.lower()
+
+
+def _registry_auth_headers(url: str) -> dict[str, str]:
+    """url 与某个带令牌的私有注册表同源时，返回该注册表的 Authorization 头。"""
+    origin = _url_origin(url)
+    for reg in _configured_registries():
+        token_ref = reg.get("tokenRef")
+        if not token_ref or _url_origin(reg["url"]) != origin:
+            continue
+        token = _registry_token(token_ref)
+        if token:
+            return {"Authorization": f"Bearer {token}"}
+    return {}
+
+
+def set_registry_token(token_ref: str) -> None:
+    """写入 / 删除注册表令牌（系统钥匙串）。
+
+    令牌经 stdin 传入（``_stdin_secrets()["registry_token"]``），为空时删除。
+    """
+    import keyring
+    from keyring.errors import PasswordDeleteError
+
+    token = str(_stdin_secrets().get("registry_token") or "").strip()
+    if token:
+        keyring.set_password(_REGISTRY_KEYRING_SERVICE, token_ref, token)
+    else:
+        with contextlib.suppress(PasswordDeleteError):
+            keyring.delete_password(_REGISTRY_KEYRING_SERVICE, token_ref)
+    _json_print({"status": "ok", "tokenRef": token_ref, "stored": bool(token)})
+
+
+def download_registry_artifact(url: str, dest: str) -> None:
+    """带注册表令牌下载技能包（私有注册表的 archive_url）。"""
+    import urllib.request
+
+    headers = {"User-Agent": "OpenAkita-SetupCenter", **_registry_auth_headers(url)}
+    dest_path = Path(dest)
+    dest_path.parent.mkdir(parents=True, exist_ok=True)
+    total = 0
+    req = urllib.request.Request(url, headers=headers)
+    with urllib.request.urlopen(req, timeout=30) as resp, open(dest_path, "wb") as f:
+        while chunk := resp.read(64 * 1024):
+            total += len(chunk)
+            if total > _REGISTRY_ARTIFACT_MAX_BYTES:
+                raise ValueError("技能包超过 512 MB，已拒绝")
+            f.write(chunk)
+    _json_print({"status": "ok", "bytes": total})
+
+
+def _fetch_registry_marketplace(registry_url: str) -> list[dict] | None:
+    """从 Setup Center 配置的技能注册表拉取列表，失败返回 None。"""
+    import urllib.request
+
+    headers = {"User-Agent": "OpenAkita-SetupCenter", **_registry_auth_headers(registry_url)}
+    try:
+        req = urllib.request.Request(registry_url, headers=headers)
+        with urllib.request.urlopen(req, timeout=15) as resp:
+            data = json.loads(resp.read().decode("utf-8"))
+    except Exception as e:
+        _warn(f"[list_marketplace] registry {registry_url} failed: {e}")
+        return None
+    if isinstance(data, dict):
+        data = data.get("skills")
+    if not isinstance(data, list):
+        return None
+    return [item for item in data if isinstance(item, dict) and item.get("name")]
+
+
 def list_marketplace() -> None:
-    """列出市场可用技能（从注册表或 GitHub）"""
-    # TODO: 从真实的注册表 API 获取
-    # 暂返回硬编码的示例列表
+    """列出市场可用技能：镜像注册表与私有注册表的合并结果。
+
+    每项带 ``origin``（来源注册表 URL，内置示例为 ``builtin``）。
+    """
+    sources: list[str] = []
+    registry_url = os.environ.get("OPENAKITA_SKILL_REGISTRY_URL", "").strip()
+    if registry_url:
+        sources.append(registry_url)
+    for reg in _configured_registries():
+        url = reg["url"].strip()
+        if url not in sources:
+            sources.append(url)
+    merged: list[dict] = []
+    fetched = False
+    for url in sources:
+        skills = _fetch_registry_marketplace(url)
+        if skills is None:
+            continue
+        fetched = True
+        merged.extend({**item, "origin": url} for item in skills)
+    if fetched:
+        _json_print(merged)
+        return
+    # 未配置注册表或全部拉取失败：返回内置示例列表
     marketplace = [
         {
             "name": "web-search",
@@ -1798,7 +2027,7 @@ def list_marketplace() -> None:
             "tags": ["图片", "生成"],
         },
     ]
-    _json_print(marketplace)
+    _json_print([{**item, "origin": "builtin"} for item in marketplace])
 
 
 def get_skill_config(workspace_dir: str, skill_name: str) -> None:
@@ -1821,8 +2050,385 @@ def get_skill_config(workspace_dir: str, skill_name: str) -> None:
     )
 
 
+def set_skill_config(workspace_dir: str, skill_name: str) -> None:
+    """写入技能配置到 ``data/skill_configs.json``（与 POST /api/skills/config 同一文件）。
+
+    值由 Setup Center 校验后经 stdin 传入（``_stdin_secrets()["values"]``，
+    可能含密钥，不走命令行参数或环境变量）。
+    """
+    from openakita.utils.atomic_io import atomic_json_write, read_json_safe
+
+    values = _stdin_secrets().get("values", {})
+    if not isinstance(values, dict):
+        raise ValueError("技能配置必须是 JSON 对象")
+
+    config_file = Path(workspace_dir).expanduser().resolve() / "data" / "skill_configs.json"
+    existing = read_json_safe(config_file) or {}
+    if not isinstance(existing, dict):
+        existing = {}
+    existing[skill_name] = values
+    atomic_json_write(config_file, existing)
+    _json_print({"status": "ok", "skill": skill_name, "config": values})
+
+
+def register_skill(workspace_dir: str, skill_dir: str) -> None:
+    """校验新建技能目录并登记启用（Setup Center 技能脚手架使用）。
+
+    ``data/skills.json`` 存在 ``external_allowlist`` 时把技能加入其中，
+    否则外部技能默认全部启用，无需改动。
+    """
+    from openakita.skills.parser import SkillParser
+    from openakita.utils.atomic_io import atomic_json_write, read_json_safe
+
+    parsed = SkillParser().parse_directory(Path(skill_dir).expanduser().resolve())
+    name = parsed.metadata.name
+
+    cfg_path = Path(workspace_dir).expanduser().resolve() / "data" / "skills.json"
+    cfg = read_json_safe(cfg_path) if cfg_path.exists() else None
+    if isinstance(cfg, dict) and isinstance(cfg.get("external_allowlist"), list):
+        allow = [str(x) for x in cfg["external_allowlist"]]
+        if name not in allow:
+            cfg["external_allowlist"] = allow + [name]
+            atomic_json_write(cfg_path, cfg)
+    _json_print({"status": "ok", "skill_id": name, "name": name})
+
+
+# memory-db --stream 时置为 True：长操作输出 ``@@memory-db <json>`` 进度行
+_MEMORY_DB_STREAM = False
+
+
+def _memory_db_progress(stage: str, **info: Any) -> None:
+    if not _MEMORY_DB_STREAM:
+        return
+    sys.stdout.write(
+        "@@memory-db " + json.dumps({"stage": stage, **info}, ensure_ascii=False) + "\n"
+    )
+    sys.stdout.flush()
+
+
+def _sqlite_file_size(path: Path) -> int:
+    return sum(
+        p.stat().st_size
+        for p in (path, Path(f"{path}-wal"), Path(f"{path}-shm"))
+        if p.exists()
+    )
+
+
+def _memory_db_stats(db: Path) -> dict[str, Any]:
+    import sqlite3
+
+    conn = sqlite3.connect(f"{db.resolve().as_uri()}?mode=ro", uri=True)
+    try:
+        page_size = conn.execute("PRAGMA page_size").fetchone()[0]
+        page_count = conn.execute("PRAGMA page_count").fetchone()[0]
+        freelist = conn.execute("PRAGMA freelist_count").fetchone()[0]
+        names = [
+            r[0]
+            for r in conn.execute(
+                "SELECT name FROM sqlite_master WHERE type = 'table' "
+                "AND name NOT LIKE 'sqlite_%' ORDER BY name"
+            )
+        ]
+        tables = []
+        for name in names:
+            try:
+                quoted = '"' + name.replace('"', '""') + '"'
+                count = conn.execute(f"SELECT COUNT(*) FROM {quoted}").fetchone()[0]
+                tables.append({"name": name, "rows": count})
+            except sqlite3.DatabaseError:
+                # FTS / vec0 等虚拟表缺少扩展时无法计数
+                tables.append({"name": name, "rows": None})
+    finally:
+        conn.close()
+    return {
+        "path": str(db),
+        "size_bytes": db.stat().st_size,
+        "wal_bytes": _sqlite_file_size(db) - db.stat().st_size,
+        "page_size": page_size,
+        "page_count": page_count,
+        "free_pages": freelist,
+        "fragmentation": round(freelist / page_count, 4) if page_count else 0.0,
+        "reclaimable_bytes": freelist * page_size,
+        "tables": tables,
+    }
+
+
+def memory_db(action: str, db_path: str, dest: str = "") -> None:
+    """记忆库维护：stats / integrity-check / backup / vacuum。
+
+    Setup Center 只在后端停止时调用 vacuum；其余操作以只读方式打开数据库。
+    """
+    import sqlite3
+
+    db = Path(db_path).expanduser()
+    if not db.is_file():
+        raise FileNotFoundError(f"数据库不存在: {db}")
+
+    if action == "stats":
+        _json_print(_memory_db_stats(db))
+        return
+
+    if action == "integrity-check":
+        conn = sqlite3.connect(f"{db.resolve().as_uri()}?mode=ro", uri=True)
+        try:
+            rows = [r[0] for r in conn.execute("PRAGMA integrity_check(100)")]
+        finally:
+            conn.close()
+        problems = [r for r in rows if r != "ok"]
+        _json_print({"path": str(db), "ok": not problems, "problems": problems})
+        return
+
+    if action == "backup":
+        target = Path(dest).expanduser()
+        target.parent.mkdir(parents=True, exist_ok=True)
+        tmp = target.with_name(target.name + ".tmp")
+        tmp.unlink(missing_ok=True)
+        src = sqlite3.connect(f"{db.resolve().as_uri()}?mode=ro", uri=True)
+        out = sqlite3.connect(str(tmp))
+        try:
+
+            def _progress(_status: int, remaining: int, total: int) -> None:
+                done = total - remaining
+                percent = int(done * 100 / total) if total else 100
+                _memory_db_progress("backup", done=done, total=total, percent=percent)
+
+            src.backup(out, pages=256, progress=_progress)
+        finally:
+            out.close()
+            src.close()
+        os.replace(tmp, target)
+        _json_print({"path": str(target), "size_bytes": target.stat().st_size})
+        return
+
+    if action == "vacuum":
+        before = _sqlite_file_size(db)
+        tmp = db.with_name(db.name + ".vacuum.tmp")
+        tmp.unlink(missing_ok=True)
+        conn = sqlite3.connect(str(db))
+        try:
+            # 先把 WAL 合并进主库，VACUUM INTO 的结果才是完整的
+            conn.execute("PRAGMA wal_checkpoint(TRUNCATE)")
+            journal_mode = conn.execute("PRAGMA journal_mode").fetchone()[0]
+            source_bytes = max(db.stat().st_size, 1)
+            last = [-1]
+
+            def _tick() -> int:
+                size = tmp.stat().st_size if tmp.exists() else 0
+                percent = min(99, int(size * 100 / source_bytes))
+                if percent != last[0]:
+                    last[0] = percent
+                    _memory_db_progress("vacuum", done=size, total=source_bytes, percent=percent)
+                return 0
+
+            conn.set_progress_handler(_tick, 20000)
+            conn.execute("VACUUM INTO ?", (str(tmp),))
+            conn.set_progress_handler(None, 0)
+        finally:
+            conn.close()
+        check = sqlite3.connect(str(tmp))
+        try:
+            ok = check.execute("PRAGMA quick_check").fetchone()[0] == "ok"
+            # VACUUM INTO 的产物不带 WAL 标记，按原库恢复
+            if ok and journal_mode == "wal":
+                check.execute("PRAGMA journal_mode=WAL")
+        finally:
+            check.close()
+        if not ok:
+            tmp.unlink(missing_ok=True)
+            raise RuntimeError("VACUUM 结果未通过 quick_check，原数据库未改动")
+        for suffix in ("-wal", "-shm"):
+            Path(f"{db}{suffix}").unlink(missing_ok=True)
+        os.replace(tmp, db)
+        _memory_db_progress("vacuum", done=source_bytes, total=source_bytes, percent=100)
+        after = _sqlite_file_size(db)
+        _json_print({"path": str(db), "before_bytes": before, "after_bytes": after})
+        return
+
+    raise ValueError(f"未知操作: {action}")
+
+
+def load_yaml(path: str) -> None:
+    """把 YAML 文件转成 JSON 输出（Setup Center 读取技能清单等 YAML 配置）。"""
+    import yaml
+
+    data = yaml.safe_load(Path(path).expanduser().read_text(encoding="utf-8"))
+    _json_print(data if data is not None else {})
+
+
+SERVER_PROTOCOL = 1
+
+
+class _ThreadStream:
+    """按线程分流的 stdout / stderr：请求线程写入自己的缓冲，其余写到原始流。"""
+
+    def __init__(self, name: str, fallback: Any):
+        self._name = name
+        self._fallback = fallback
+
+    def _target(self) -> Any:
+        return getattr(_REQUEST_LOCAL, self._name, None) or self._fallback
+
+    def write(self, text: str) -> int:
+        return self._target().write(text)
+
+    def flush(self) -> None:
+        self._target().flush()
+
+    def __getattr__(self, item: str) -> Any:
+        return getattr(self._fallback, item)
+
+
+ENVELOPE_VERSION = 1
+
+
+def _envelope(
+    stdout: str, error: dict[str, Any] | None, warnings: list[str]
+) -> dict[str, Any]:
+    """把一次命令的 stdout 包成 ``{ok, data, error, warnings}``。
+
+    ``data`` 取最后一行合法 JSON；其余非空行（第三方库随手 print 的内容）
+    不再混进结果，而是记到 ``warnings`` 里。
+    """
+    data: Any = None
+    found = False
+    noise: list[str] = []
+    for line in reversed(stdout.splitlines()):
+        text = line.strip()
+        if not text:
+            continue
+        if not found:
+            try:
+                data = json.loads(text)
+                found = True
+                continue
+            except ValueError:
+                pass
+        noise.insert(0, text)
+    if error is None and not found:
+        error = {"code": "no_output", "message": "bridge 命令没有输出 JSON"}
+    return {
+        "bridgeEnvelope": ENVELOPE_VERSION,
+        "ok": error is None,
+        "data": data if error is None else None,
+        "error": error,
+        "warnings": [*warnings, *(f"stdout: {n}" for n in noise)],
+    }
+
+
+def _run_request(req: dict[str, Any]) -> dict[str, Any]:
+    """执行一条 ``{"id", "args", "secrets"?, "envelope"?}`` 请求，返回应答对象。
+
+    ``envelope`` 为真时 ``stdout`` 是一行 :func:`_envelope` 信封，命令失败也
+    照常应答（``ok`` 为真，失败信息在信封的 ``error`` 里）。
+    """
+    import io
+
+    out, err = io.StringIO(), io.StringIO()
+    # serve() 已装好按线程分流的流；单次调用时临时装上
+    real_stdout, real_stderr = sys.stdout, sys.stderr
+    if not isinstance(real_stdout, _ThreadStream):
+        sys.stdout = _ThreadStream("stdout", real_stdout)
+        sys.stderr = _ThreadStream("stderr", real_stderr)
+    _REQUEST_LOCAL.stdout, _REQUEST_LOCAL.stderr = out, err
+    _REQUEST_LOCAL.warnings = []
+    secrets = req.get("secrets")
+    _REQUEST_LOCAL.secrets = secrets if isinstance(secrets, dict) else {}
+    code = 0
+    error: dict[str, Any] | None = None
+    try:
+        main([str(a) for a in req.get("args") or []])
+    except SystemExit as e:
+        code = e.code if isinstance(e.code, int) else (0 if e.code is None else 1)
+        if e.code is not None and not isinstance(e.code, int):
+            err.write(f"{e.code}\n")
+    except BaseException as e:  # noqa: BLE001 - 与单次调用一样把异常交给调用方
+        traceback.print_exc(file=err)
+        err.write(f"{e}\n")
+        code = 1
+        err_code = getattr(e, "code", None)
+        error = {"code": err_code if isinstance(err_code, str) else None, "message": str(e)}
+    finally:
+        warnings = _REQUEST_LOCAL.warnings
+        _REQUEST_LOCAL.stdout = _REQUEST_LOCAL.stderr = None
+        _REQUEST_LOCAL.secrets = _REQUEST_LOCAL.warnings = None
+        sys.stdout, sys.stderr = real_stdout, real_stderr
+    reply = {
+        "id": req.get("id"),
+        "ok": code == 0,
+        "exitCode": code,
+        "stdout": out.getvalue(),
+        "stderr": err.getvalue(),
+    }
+    if req.get("envelope"):
+        if code and error is None:
+            error = {"code": None, "message": f"exit code {code}"}
+        if error is not None:
+            error.update(exitCode=code, stderr=err.getvalue())
+        env = _envelope(out.getvalue(), error, warnings)
+        reply.update(ok=True, exitCode=0, stdout=json.dumps(env, ensure_ascii=False))
+    return reply
+
+
+def _main_enveloped(argv: list[str]) -> int:
+    """单次调用的信封模式（``OPENAKITA_BRIDGE_ENVELOPE=1``）：stdout 只有一行信封。"""
+    reply = _run_request({"args": argv, "secrets": _stdin_secrets(), "envelope": True})
+    sys.stderr.write(reply["stderr"])
+    sys.stdout.write(reply["stdout"] + "\n")
+    return 0
+
+
+def serve(stdin: Any = None, stdout: Any = None) -> None:
+    """常驻模式：stdin 每行一个 JSON 请求，stdout 每行一个 JSON 应答。
+
+    Setup Center 每个 venv 只起一个 ``--server`` 进程，避免每次调用都付出
+    Python 启动 + import 的开销。请求 ``{"id": 1, "args": ["list-skills", ...],
+    "secrets": {...}}`` 与命令行参数一一对应；应答带回同一个 ``id``，
+    多个请求在各自线程里并发执行，应答顺序不保证。首行输出
+    ``{"ready": true, "protocol": 1}``；stdin 关闭即退出。
+    """
+    stdin = stdin or sys.stdin
+    proto = stdout or sys.stdout
+    write_lock = threading.Lock()
+    real_stdout, real_stderr = sys.stdout, sys.stderr
+    sys.stdout = _ThreadStream("stdout", sys.stdout)
+    sys.stderr = _ThreadStream("stderr", sys.stderr)
+
+    def reply(obj: dict[str, Any]) -> None:
+        with write_lock:
+            proto.write(json.dumps(obj, ensure_ascii=False) + "\n")
+            proto.flush()
+
+    def handle(req: dict[str, Any]) -> None:
+        reply(_run_request(req))
+
+    reply({"ready": True, "protocol": SERVER_PROTOCOL, "pid": os.getpid()})
+    workers: list[threading.Thread] = []
+    try:
+        for line in stdin:
+            line = line.strip()
+            if not line:
+                continue
+            try:
+                req = json.loads(line)
+                if not isinstance(req, dict):
+                    raise ValueError("request must be a JSON object")
+            except ValueError as e:
+                reply({"id": None, "ok": False, "exitCode": 2, "stdout": "", "stderr": str(e)})
+                continue
+            t = threading.Thread(target=handle, args=(req,), daemon=True)
+            t.start()
+            workers = [w for w in workers if w.is_alive()] + [t]
+        for t in workers:
+            t.join()
+    finally:
+        sys.stdout, sys.stderr = real_stdout, real_stderr
+
+
 def main(argv: list[str] | None = None) -> None:
     argv = list(sys.argv[1:] if argv is None else argv)
+    if argv[:1] == ["--server"]:
+        serve()
+        return
 
     p = argparse.ArgumentParser(prog="openakita.setup_center.bridge")
     sub = p.add_subparsers(dest="cmd", required=True)
@@ -1842,6 +2448,9 @@ def main(argv: list[str] | None = None) -> None:
     ph = sub.add_parser("health-check-endpoint", help="检测 LLM 端点健康度（JSON）")
     ph.add_argument("--workspace-dir", required=True, help="工作区目录")
     ph.add_argument("--endpoint-name", default="", help="可选：仅检测指定端点（为空=全部）")
+    ph.add_argument("--parallel", type=int, default=1, help="最大并发检测数")
+    ph.add_argument("--timeout", type=float, default=0.0, help="单个端点超时秒数（0=不限）")
+    ph.add_argument("--stream", action="store_true", help="逐条输出 @@endpoint-health 进度行")
 
     pi = sub.add_parser("health-check-im", help="检测 IM 通道连通性（JSON）")
     pi.add_argument("--workspace-dir", required=True, help="工作区目录")
@@ -1853,6 +2462,7 @@ def main(argv: list[str] | None = None) -> None:
     p_inst = sub.add_parser("install-skill", help="安装技能（从 URL/路径）")
     p_inst.add_argument("--workspace-dir", required=True, help="工作区目录")
     p_inst.add_argument("--url", required=True, help="技能来源 URL 或路径")
+    p_inst.add_argument("--stream", action="store_true", help="逐阶段输出 @@skill-install 进度行")
 
     p_uninst = sub.add_parser("uninstall-skill", help="卸载技能")
     p_uninst.add_argument("--workspace-dir", required=True, help="工作区目录")
@@ -1864,6 +2474,32 @@ def main(argv: list[str] | None = None) -> None:
     p_cfg.add_argument("--workspace-dir", required=True, help="工作区目录")
     p_cfg.add_argument("--skill-name", required=True, help="技能名称")
 
+    p_scfg = sub.add_parser("set-skill-config", help="写入技能配置（值经 stdin 传入，JSON）")
+    p_scfg.add_argument("--workspace-dir", required=True, help="工作区目录")
+    p_scfg.add_argument("--skill-name", required=True, help="技能名称")
+
+    p_rtok = sub.add_parser("set-registry-token", help="写入注册表令牌（经 stdin 传入）")
+    p_rtok.add_argument("--token-ref", required=True, help="令牌引用名")
+
+    p_rdl = sub.add_parser("download-registry-artifact", help="带令牌下载注册表技能包（JSON）")
+    p_rdl.add_argument("--url", required=True, help="技能包 URL")
+    p_rdl.add_argument("--dest", required=True, help="保存路径")
+
+    p_reg = sub.add_parser("register-skill", help="校验并登记新建的技能目录（JSON）")
+    p_reg.add_argument("--workspace-dir", required=True, help="工作区目录")
+    p_reg.add_argument("--skill-dir", required=True, help="技能目录")
+
+    p_mdb = sub.add_parser("memory-db", help="记忆库维护：统计/完整性检查/备份/VACUUM（JSON）")
+    p_mdb.add_argument(
+        "--action", required=True, choices=["stats", "integrity-check", "backup", "vacuum"]
+    )
+    p_mdb.add_argument("--db", required=True, help="SQLite 数据库路径")
+    p_mdb.add_argument("--dest", default="", help="backup 的目标文件")
+    p_mdb.add_argument("--stream", action="store_true", help="逐步输出 @@memory-db 进度行")
+
+    p_yaml = sub.add_parser("load-yaml", help="读取 YAML 文件并输出 JSON")
+    p_yaml.add_argument("--path", required=True, help="YAML 文件路径")
+
     p_fos = sub.add_parser("feishu-onboard-start", help="启动飞书 Device Flow 扫码建应用（JSON）")
     p_fos.add_argument("--domain", default="feishu", help="feishu | lark")
 
@@ -1873,7 +2509,9 @@ def main(argv: list[str] | None = None) -> None:
 
     p_fv = sub.add_parser("feishu-validate", help="验证飞书凭证有效性（JSON）")
     p_fv.add_argument("--app-id", required=True, help="飞书 App ID")
-    p_fv.add_argument("--app-secret", required=True, help="飞书 App Secret")
+    p_fv.add_argument(
+        "--app-secret", default="", help="飞书 App Secret（省略时从 stdin 密钥读取）"
+    )
     p_fv.add_argument("--domain", default="feishu", help="feishu | lark")
 
     sub.add_parser("wecom-onboard-start", help="生成企微扫码配置二维码（JSON）")
@@ -1893,7 +2531,9 @@ def main(argv: list[str] | None = None) -> None:
 
     p_qv = sub.add_parser("qqbot-validate", help="验证 QQ 机器人凭证有效性（JSON）")
     p_qv.add_argument("--app-id", required=True, help="QQ 机器人 App ID")
-    p_qv.add_argument("--app-secret", required=True, help="QQ 机器人 App Secret")
+    p_qv.add_argument(
+        "--app-secret", default="", help="QQ 机器人 App Secret（省略时从 stdin 密钥读取）"
+    )
 
     sub.add_parser("wechat-onboard-start", help="获取微信登录二维码（JSON）")
 
@@ -1907,7 +2547,7 @@ def main(argv: list[str] | None = None) -> None:
         return
 
     if args.cmd == "list-models":
-        api_key = os.environ.get("SETUPCENTER_API_KEY", "")
+        api_key = str(_stdin_secrets().get("api_key") or "")
         asyncio.run(
             list_models(
                 api_type=args.api_type,
@@ -1927,6 +2567,9 @@ def main(argv: list[str] | None = None) -> None:
             health_check_endpoint(
                 workspace_dir=args.workspace_dir,
                 endpoint_name=(args.endpoint_name.strip() or None),
+                parallel=args.parallel,
+                timeout=args.timeout,
+                stream=args.stream,
             )
         )
         return
@@ -1945,6 +2588,8 @@ def main(argv: list[str] | None = None) -> None:
         return
 
     if args.cmd == "install-skill":
+        global _SKILL_INSTALL_STREAM
+        _SKILL_INSTALL_STREAM = args.stream
         install_skill(workspace_dir=args.workspace_dir, url=args.url)
         return
 
@@ -1960,6 +2605,32 @@ def main(argv: list[str] | None = None) -> None:
         get_skill_config(workspace_dir=args.workspace_dir, skill_name=args.skill_name)
         return
 
+    if args.cmd == "set-skill-config":
+        set_skill_config(workspace_dir=args.workspace_dir, skill_name=args.skill_name)
+        return
+
+    if args.cmd == "set-registry-token":
+        set_registry_token(token_ref=args.token_ref)
+        return
+
+    if args.cmd == "download-registry-artifact":
+        download_registry_artifact(url=args.url, dest=args.dest)
+        return
+
+    if args.cmd == "memory-db":
+        global _MEMORY_DB_STREAM
+        _MEMORY_DB_STREAM = args.stream
+        memory_db(action=args.action, db_path=args.db, dest=args.dest)
+        return
+
+    if args.cmd == "load-yaml":
+        load_yaml(path=args.path)
+        return
+
+    if args.cmd == "register-skill":
+        register_skill(workspace_dir=args.workspace_dir, skill_dir=args.skill_dir)
+        return
+
     if args.cmd == "feishu-onboard-start":
         asyncio.run(feishu_onboard_start(domain=args.domain))
         return
@@ -1972,7 +2643,7 @@ def main(argv: list[str] | None = None) -> None:
         asyncio.run(
             feishu_validate(
                 app_id=args.app_id,
-                app_secret=args.app_secret,
+                app_secret=args.app_secret or str(_stdin_secrets().get("app_secret") or ""),
                 domain=args.domain,
             )
         )
@@ -2006,7 +2677,7 @@ def main(argv: list[str] | None = None) -> None:
         asyncio.run(
             qqbot_validate(
                 app_id=args.app_id,
-                app_secret=args.app_secret,
+                app_secret=args.app_secret or str(_stdin_secrets().get("app_secret") or ""),
             )
         )
         return
@@ -2029,6 +2700,9 @@ if __name__ == "__main__":
         ensure_ssl_certs()
         inject_module_paths()
 
+    if os.environ.get("OPENAKITA_BRIDGE_ENVELOPE") == "1" and sys.argv[1:2] != ["--server"]:
+        sys.exit(_main_enveloped(sys.argv[1:]))
+
     try:
         main()
     except Exception as e:
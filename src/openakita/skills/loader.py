@@ -778,7 +778,13 @@ class SkillLoader:
         - 被 agent_referenced_skills 引用 → 保留但标记 disabled=True
           （子 Agent INCLUSIVE 模式可显式启用）
         - 否则 → 从注册表和 loader 中移除
+
+        桌面端安全模式下按 set() 处理：外部技能都不启用，skills.json 不动。
         """
+        from ..llm.config import safe_mode
+
+        if safe_mode():
+            external_allowlist = set()
         if external_allowlist is None:
             for name in self._loaded_skills:
                 self.registry.set_disabled(name, False)
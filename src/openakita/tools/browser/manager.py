@@ -253,11 +253,18 @@ def _find_bundled_browser_executable() -> str | None:
     2. {base}/playwright-browsers/chromium-*/chrome-win/         — Playwright (Windows)
     3. {base}/playwright-browsers/chromium-*/chrome-mac[-arm64]/ — Playwright (macOS)
     4. {base}/playwright-browsers/chromium-*/chrome-linux/       — Playwright (Linux)
+
+    桌面端安装的便携版 Chromium 通过 OPENAKITA_BROWSER_EXECUTABLE 传入，优先使用。
     """
     import sys
 
     from openakita.runtime_env import IS_FROZEN
 
+    configured = os.environ.get("OPENAKITA_BROWSER_EXECUTABLE", "").strip()
+    if configured and Path(configured).is_file():
+        logger.info(f"[Browser] Using desktop-managed browser executable: {configured}")
+        return configured
+
     if not IS_FROZEN:
         return None
 
@@ -303,3 +303,50 @@ class TestRuntimeConfigConstraints:
         assert "✅" in result
         assert monitor.timeout_seconds == 0
         assert monitor.hard_timeout_seconds == 0
+
+
+class TestSafeLoadDotenv:
+    def test_api_port_pin_wins_over_env_file(self, tmp_path, monkeypatch):
+        from openakita.llm.config import _safe_load_dotenv
+
+        env_file = tmp_path / ".env"
+        env_file.write_text("API_PORT=18900\n", encoding="utf-8")
+        monkeypatch.setenv("API_PORT", "18901")
+        monkeypatch.setenv("OPENAKITA_API_PORT_PIN", "18901")
+        _safe_load_dotenv(env_file)
+        assert os.environ["API_PORT"] == "18901"
+
+        monkeypatch.delenv("OPENAKITA_API_PORT_PIN")
+        _safe_load_dotenv(env_file)
+        assert os.environ["API_PORT"] == "18900"
+
+    def test_env_overlays_apply_in_order(self, tmp_path, monkeypatch):
+        from openakita.llm.config import _safe_load_dotenv
+
+        (tmp_path / ".env").write_text("A=base\nB=base\nC=base\n", encoding="utf-8")
+        (tmp_path / ".env.dev").write_text("B=dev\nC=dev\n", encoding="utf-8")
+        (tmp_path / ".env.local").write_text("C=local\n", encoding="utf-8")
+        monkeypatch.setenv("OPENAKITA_ENV_PROFILE", "dev")
+        _safe_load_dotenv(tmp_path / ".env")
+        assert (os.environ["A"], os.environ["B"], os.environ["C"]) == ("base", "dev", "local")
+
+        monkeypatch.delenv("OPENAKITA_ENV_PROFILE")
+        _safe_load_dotenv(tmp_path / ".env")
+        assert (os.environ["B"], os.environ["C"]) == ("base", "local")
+        for key in ("A", "B", "C"):
+            monkeypatch.delenv(key)
+
+    def test_safe_mode_wins_over_env_file(self, tmp_path, monkeypatch):
+        from openakita.llm.config import _safe_load_dotenv
+
+        env_file = tmp_path / ".env"
+        env_file.write_text("MCP_ENABLED=true\nLOG_LEVEL=INFO\n", encoding="utf-8")
+        monkeypatch.setenv("MCP_ENABLED", "false")
+        monkeypatch.setenv("LOG_LEVEL", "DEBUG")
+        monkeypatch.setenv("OPENAKITA_SAFE_MODE", "1")
+        _safe_load_dotenv(env_file)
+        assert (os.environ["MCP_ENABLED"], os.environ["LOG_LEVEL"]) == ("false", "DEBUG")
+
+        monkeypatch.delenv("OPENAKITA_SAFE_MODE")
+        _safe_load_dotenv(env_file)
+        assert (os.environ["MCP_ENABLED"], os.environ["LOG_LEVEL"]) == ("true", "INFO")
@@ -0,0 +1,33 @@
+"""POST /api/debug/thread-dump — stacks for the desktop's silent-backend watchdog."""
+
+from __future__ import annotations
+
+import threading
+
+from fastapi import FastAPI
+from fastapi.testclient import TestClient
+
+from openakita.api.routes import health as health_module
+
+
+def test_thread_dump_lists_every_thread_with_its_stack():
+    release = threading.Event()
+
+    def parked_worker():
+        release.wait(10)
+
+    worker = threading.Thread(target=parked_worker, name="parked-worker", daemon=True)
+    worker.start()
+    try:
+        app = FastAPI()
+        app.include_router(health_module.router)
+        resp = TestClient(app).post("/api/debug/thread-dump")
+    finally:
+        release.set()
+        worker.join(5)
+
+    assert resp.status_code == 200
+    body = resp.json()
+    assert body["threads"] >= 2
+    assert "--- thread parked-worker" in body["dump"]
+    assert "parked_worker" in body["dump"]
@@ -96,3 +96,35 @@ def test_shorthand_install_rechecks_target_after_failed_platform_cache(
     target = tmp_path / "skills" / "demo"
     assert (target / "SKILL.md").exists()
     assert not (target / ".git").exists()
+
+
+def test_list_marketplace_merges_private_registries_with_origin(
+    monkeypatch: pytest.MonkeyPatch, capsys: pytest.CaptureFixture[str]
+):
+    import json
+
+    from openakita.setup_center import bridge
+
+    monkeypatch.setenv("OPENAKITA_SKILL_REGISTRY_URL", "https://mirror.example.com/skills.json")
+    monkeypatch.setenv(
+        "OPENAKITA_SKILL_REGISTRIES",
+        json.dumps([{"url": "https://corp.example.com/index.json", "tokenRef": "corp"}]),
+    )
+    monkeypatch.setattr(bridge, "_registry_token", lambda ref: "s3cret" if ref == "corp" else None)
+    seen: dict[str, dict] = {}
+
+    def fake_fetch(url: str):
+        seen[url] = bridge._registry_auth_headers(url)
+        return [{"name": "pdf" if "corp" in url else "web-search"}]
+
+    monkeypatch.setattr(bridge, "_fetch_registry_marketplace", fake_fetch)
+
+    bridge.list_marketplace()
+
+    items = json.loads(capsys.readouterr().out)
+    assert [(i["name"], i["origin"]) for i in items] == [
+        ("web-search", "https://mirror.example.com/skills.json"),
+        ("pdf", "https://corp.example.com/index.json"),
+    ]
+    assert seen["https://corp.example.com/index.json"] == {"Authorization": "Bearer s3cret"}
+    assert seen["https://mirror.example.com/skills.json"] == {}
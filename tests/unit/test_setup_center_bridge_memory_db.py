@@ -0,0 +1,81 @@
+from __future__ import annotations
+
+import json
+import sqlite3
+from pathlib import Path
+
+import pytest
+
+
+@pytest.fixture
+def bridge(monkeypatch: pytest.MonkeyPatch):
+    from openakita.setup_center import bridge
+
+    monkeypatch.setattr(bridge, "_MEMORY_DB_STREAM", True)
+    return bridge
+
+
+@pytest.fixture
+def memory_db(tmp_path: Path) -> Path:
+    db = tmp_path / "openakita.db"
+    conn = sqlite3.connect(db)
+    conn.execute("PRAGMA journal_mode=WAL")
+    conn.execute("CREATE TABLE memories (content TEXT)")
+    conn.executemany("INSERT INTO memories VALUES (?)", [("x" * 2000,)] * 400)
+    conn.commit()
+    conn.execute("DELETE FROM memories WHERE rowid > 100")
+    conn.commit()
+    conn.close()
+    return db
+
+
+def _run(bridge, capsys, *args: str) -> tuple[list[dict], dict]:
+    bridge.memory_db(*args)
+    lines = capsys.readouterr().out.strip().splitlines()
+    progress = [
+        json.loads(line.split(" ", 1)[1]) for line in lines if line.startswith("@@memory-db ")
+    ]
+    return progress, json.loads(lines[-1])
+
+
+def test_memory_db_stats_and_integrity(bridge, memory_db: Path, capsys):
+    _, stats = _run(bridge, capsys, "stats", str(memory_db))
+    assert stats["tables"] == [{"name": "memories", "rows": 100}]
+    assert stats["page_count"] > 0
+    assert stats["reclaimable_bytes"] == stats["free_pages"] * stats["page_size"]
+
+    _, report = _run(bridge, capsys, "integrity-check", str(memory_db))
+    assert report == {"path": str(memory_db), "ok": True, "problems": []}
+
+
+def test_memory_db_backup_reports_progress(bridge, memory_db: Path, tmp_path: Path, capsys):
+    dest = tmp_path / "backups" / "copy.db"
+    progress, result = _run(bridge, capsys, "backup", str(memory_db), str(dest))
+
+    assert result["path"] == str(dest)
+    assert progress and progress[-1]["percent"] == 100
+    conn = sqlite3.connect(dest)
+    assert conn.execute("SELECT COUNT(*) FROM memories").fetchone()[0] == 100
+    conn.close()
+
+
+def test_memory_db_vacuum_shrinks_and_keeps_wal(bridge, memory_db: Path, capsys):
+    progress, result = _run(bridge, capsys, "vacuum", str(memory_db))
+
+    assert result["after_bytes"] < result["before_bytes"]
+    assert progress[-1] == {
+        "stage": "vacuum",
+        "done": progress[-1]["total"],
+        "total": progress[-1]["total"],
+        "percent": 100,
+    }
+    conn = sqlite3.connect(memory_db)
+    assert conn.execute("PRAGMA journal_mode").fetchone()[0] == "wal"
+    assert conn.execute("SELECT COUNT(*) FROM memories").fetchone()[0] == 100
+    conn.close()
+    assert not memory_db.with_name(memory_db.name + ".vacuum.tmp").exists()
+
+
+def test_memory_db_missing_file(bridge, tmp_path: Path):
+    with pytest.raises(FileNotFoundError):
+        bridge.memory_db("stats", str(tmp_path / "nope.db"))
@@ -0,0 +1,39 @@
+from __future__ import annotations
+
+import io
+import json
+import sys
+
+import pytest
+
+
+@pytest.fixture
+def bridge(monkeypatch: pytest.MonkeyPatch):
+    from openakita.setup_center import bridge
+
+    monkeypatch.setattr(bridge, "_STDIN_SECRETS", None)
+    return bridge
+
+
+def test_stdin_secrets_are_read_once_when_flag_set(bridge, monkeypatch: pytest.MonkeyPatch):
+    monkeypatch.setenv("OPENAKITA_BRIDGE_SECRETS_STDIN", "1")
+    monkeypatch.setattr(sys, "stdin", io.StringIO(json.dumps({"api_key": "sk-test"})))
+
+    assert bridge._stdin_secrets() == {"api_key": "sk-test"}
+    # 第二次不再读 stdin（已到 EOF）
+    assert bridge._stdin_secrets()["api_key"] == "sk-test"
+
+
+def test_stdin_secrets_ignored_without_flag(bridge, monkeypatch: pytest.MonkeyPatch):
+    monkeypatch.delenv("OPENAKITA_BRIDGE_SECRETS_STDIN", raising=False)
+    monkeypatch.setattr(sys, "stdin", io.StringIO('{"api_key": "sk-test"}'))
+
+    assert bridge._stdin_secrets() == {}
+
+
+def test_stdin_secrets_reject_non_object(bridge, monkeypatch: pytest.MonkeyPatch):
+    monkeypatch.setenv("OPENAKITA_BRIDGE_SECRETS_STDIN", "1")
+    monkeypatch.setattr(sys, "stdin", io.StringIO('["sk-test"]'))
+
+    with pytest.raises(ValueError):
+        bridge._stdin_secrets()
@@ -0,0 +1,104 @@
+from __future__ import annotations
+
+import io
+import json
+
+import pytest
+
+
+@pytest.fixture
+def bridge(monkeypatch: pytest.MonkeyPatch):
+    from openakita.setup_center import bridge
+
+    monkeypatch.setattr(bridge, "_STDIN_SECRETS", None)
+    return bridge
+
+
+def _serve(bridge, requests: list) -> list[dict]:
+    lines = "".join((r if isinstance(r, str) else json.dumps(r)) + "\n" for r in requests)
+    out = io.StringIO()
+    bridge.serve(stdin=io.StringIO(lines), stdout=out)
+    return [json.loads(line) for line in out.getvalue().splitlines()]
+
+
+def test_server_answers_each_request_by_id(bridge, monkeypatch: pytest.MonkeyPatch):
+    monkeypatch.setattr(bridge, "load_yaml", lambda path: bridge._json_print({"path": path}))
+
+    replies = _serve(
+        bridge,
+        [
+            {"id": 1, "args": ["load-yaml", "--path", "a.yaml"]},
+            {"id": 2, "args": ["load-yaml", "--path", "b.yaml"]},
+        ],
+    )
+
+    assert replies[0]["ready"] is True
+    assert replies[0]["protocol"] == bridge.SERVER_PROTOCOL
+    by_id = {r["id"]: r for r in replies[1:]}
+    assert by_id[1]["ok"] is True
+    assert json.loads(by_id[1]["stdout"]) == {"path": "a.yaml"}
+    assert json.loads(by_id[2]["stdout"]) == {"path": "b.yaml"}
+
+
+def test_server_reports_failures_without_exiting(bridge, monkeypatch: pytest.MonkeyPatch):
+    def boom(path: str) -> None:
+        raise ValueError(f"bad yaml: {path}")
+
+    monkeypatch.setattr(bridge, "load_yaml", boom)
+
+    replies = _serve(
+        bridge,
+        [
+            "not json",
+            {"id": 7, "args": ["load-yaml", "--path", "x.yaml"]},
+            {"id": 8, "args": ["no-such-command"]},
+        ],
+    )
+
+    by_id = {r["id"]: r for r in replies[1:]}
+    assert by_id[None]["exitCode"] == 2
+    assert by_id[7]["ok"] is False
+    assert "bad yaml: x.yaml" in by_id[7]["stderr"]
+    assert by_id[8]["exitCode"] == 2
+
+
+def test_server_secrets_are_per_request(bridge, monkeypatch: pytest.MonkeyPatch):
+    monkeypatch.setattr(
+        bridge, "load_yaml", lambda path: bridge._json_print(bridge._stdin_secrets())
+    )
+
+    replies = _serve(
+        bridge,
+        [
+            {"id": 1, "args": ["load-yaml", "--path", "p"], "secrets": {"api_key": "sk-1"}},
+            {"id": 2, "args": ["load-yaml", "--path", "p"]},
+        ],
+    )
+
+    by_id = {r["id"]: r for r in replies[1:]}
+    assert json.loads(by_id[1]["stdout"]) == {"api_key": "sk-1"}
+    assert json.loads(by_id[2]["stdout"]) == {}
+    assert bridge._STDIN_SECRETS is None
+
+
+def test_envelope_separates_result_from_stray_output(bridge, monkeypatch: pytest.MonkeyPatch):
+    def noisy(path: str) -> None:
+        print("Loading plugins...")
+        bridge._warn("registry unreachable")
+        bridge._json_print({"path": path})
+
+    monkeypatch.setattr(bridge, "load_yaml", noisy)
+
+    ok = bridge._run_request({"id": 1, "args": ["load-yaml", "--path", "a"], "envelope": True})
+    env = json.loads(ok["stdout"])
+    assert env["bridgeEnvelope"] == bridge.ENVELOPE_VERSION
+    assert env["ok"] is True
+    assert env["data"] == {"path": "a"}
+    assert env["warnings"] == ["registry unreachable", "stdout: Loading plugins..."]
+
+    failed = bridge._run_request({"id": 2, "args": ["no-such-command"], "envelope": True})
+    env = json.loads(failed["stdout"])
+    assert failed["ok"] is True
+    assert env["ok"] is False
+    assert env["error"]["exitCode"] == 2
+    assert "usage:" in env["error"]["stderr"]